@@ -0,0 +1,418 @@
+use crate::commands::ReplayCommand;
+use crate::commands::ir::read_program;
+use crate::error::{CliError, Result};
+use crate::utils::{file_ops, process};
+use clap::Subcommand;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+pub struct CorpusCommand;
+
+impl CorpusCommand {
+    pub fn execute(command: &CorpusCommands) -> Result<()> {
+        match command {
+            CorpusCommands::Minimize {
+                corpus,
+                output,
+                scenario,
+                bitcoind,
+                node_args,
+            } => minimize_corpus(corpus, output, scenario, bitcoind, node_args.as_deref()),
+            CorpusCommands::Merge {
+                corpora,
+                output,
+                coverage,
+                scenario,
+                bitcoind,
+                node_args,
+            } => {
+                let coverage = if *coverage {
+                    let (Some(scenario), Some(bitcoind)) = (scenario, bitcoind) else {
+                        return Err(CliError::InvalidInput(
+                            "--coverage requires --scenario and --bitcoind".to_string(),
+                        ));
+                    };
+                    Some((scenario.as_path(), bitcoind.as_path(), node_args.as_deref()))
+                } else {
+                    None
+                };
+                merge_corpora(corpora, output, coverage)
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum CorpusCommands {
+    /// Reduce a corpus to a minimal subset with the same coverage
+    Minimize {
+        #[arg(long, help = "Path to the input corpus directory")]
+        corpus: PathBuf,
+        #[arg(long, help = "Path to the output directory for the minimized corpus")]
+        output: PathBuf,
+        #[arg(
+            long,
+            help = "Path to the fuzzamoto scenario binary (built without nyx) that should be run with coverage measurer"
+        )]
+        scenario: PathBuf,
+        #[arg(long, help = "Path to the coverage instrumented bitcoind binary")]
+        bitcoind: PathBuf,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+    },
+    /// Merge IR corpora into one, dropping programs that are semantically the same
+    Merge {
+        #[arg(
+            long = "corpus",
+            required = true,
+            help = "Path to an input corpus directory (can be passed multiple times)"
+        )]
+        corpora: Vec<PathBuf>,
+        #[arg(long, help = "Path to the output directory for the merged corpus")]
+        output: PathBuf,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Only keep programs that add coverage (requires --scenario and --bitcoind)"
+        )]
+        coverage: bool,
+        #[arg(
+            long,
+            help = "Path to the IR scenario binary (built without nyx and with compile_in_vm)"
+        )]
+        scenario: Option<PathBuf>,
+        #[arg(long, help = "Path to the coverage instrumented bitcoind binary")]
+        bitcoind: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+    },
+}
+
+/// A non-zero coverage counter, identified by its function and index
+pub type Feature = (String, usize);
+
+/// Run every testcase in `corpus` with coverage measurement and copy the smallest subset that
+/// covers all counters hit by the corpus into `output` (greedy set cover).
+pub fn minimize_corpus(
+    corpus: &Path,
+    output: &Path,
+    scenario: &Path,
+    bitcoind: &Path,
+    node_args: Option<&str>,
+) -> Result<()> {
+    file_ops::ensure_file_exists(scenario)?;
+    file_ops::ensure_file_exists(bitcoind)?;
+
+    let mut testcases = file_ops::read_dir_files(corpus)?;
+    if testcases.is_empty() {
+        return Err(CliError::InvalidInput("Empty corpus directory".to_string()));
+    }
+    testcases.sort();
+
+    let work_dir = std::env::temp_dir().join(format!("fuzzamoto-cmin-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&work_dir);
+    file_ops::create_dir_all(&work_dir)?;
+
+    let mut coverage = Vec::with_capacity(testcases.len());
+    for (i, testcase) in testcases.iter().enumerate() {
+        log::info!(
+            "[{}/{}] Measuring coverage of {}",
+            i + 1,
+            testcases.len(),
+            testcase.display()
+        );
+        let features = measure_coverage(&[testcase], scenario, bitcoind, node_args, &work_dir)
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to measure coverage of {}: {e}", testcase.display());
+                HashSet::new()
+            });
+        coverage.push(features);
+    }
+    let _ = std::fs::remove_dir_all(&work_dir);
+
+    let sizes: Vec<u64> = testcases
+        .iter()
+        .map(|testcase| std::fs::metadata(testcase).map_or(u64::MAX, |m| m.len()))
+        .collect();
+    let selected = greedy_set_cover(&coverage, &sizes);
+
+    file_ops::create_dir_all(output)?;
+    for index in &selected {
+        file_ops::copy_file_to_dir(&testcases[*index], output)?;
+    }
+
+    let total: HashSet<&Feature> = coverage.iter().flatten().collect();
+    log::info!(
+        "Kept {} of {} testcases covering {} counters in {}",
+        selected.len(),
+        testcases.len(),
+        total.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Merge the IR programs of all `corpora` into `output`, canonicalized (without nops) and named
+/// after their semantic hash, such that each program is only kept once.
+///
+/// With `coverage` (scenario, bitcoind and node arguments), programs are measured from the
+/// smallest to the largest and only kept if they hit coverage counters none of the kept programs
+/// hit.
+pub fn merge_corpora(
+    corpora: &[PathBuf],
+    output: &Path,
+    coverage: Option<(&Path, &Path, Option<&str>)>,
+) -> Result<()> {
+    if let Some((scenario, bitcoind, _)) = coverage {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+    }
+
+    let mut seen = HashSet::new();
+    let mut programs = Vec::new();
+    let mut total = 0;
+    for corpus in corpora {
+        let mut files = file_ops::read_dir_files(corpus)?;
+        files.sort();
+        for file in files {
+            total += 1;
+            let mut program = match read_program(&file) {
+                Ok(program) => program,
+                Err(e) => {
+                    log::warn!("Skipping {}: {e}", file.display());
+                    continue;
+                }
+            };
+            let hash = program.semantic_hash();
+            if !seen.insert(hash) {
+                log::debug!("Dropping duplicate {}", file.display());
+                continue;
+            }
+            if program.is_statically_valid() {
+                program.remove_nops();
+            }
+            programs.push((hash, program));
+        }
+    }
+    log::info!(
+        "Found {} unique programs in {total} testcases",
+        programs.len()
+    );
+
+    if let Some((scenario, bitcoind, node_args)) = coverage {
+        programs.sort_by_key(|(_, program)| program.instructions.len());
+
+        let work_dir = std::env::temp_dir().join(format!("fuzzamoto-merge-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&work_dir);
+        file_ops::create_dir_all(&work_dir)?;
+        let candidate = work_dir.join("candidate.ir");
+
+        let mut covered: HashSet<Feature> = HashSet::new();
+        let mut novel = Vec::new();
+        for (i, (hash, program)) in programs.into_iter().enumerate() {
+            log::info!("[{}] Measuring coverage of {hash:016x}", i + 1);
+            std::fs::write(&candidate, postcard::to_allocvec(&program)?)?;
+            match measure_coverage(&[&candidate], scenario, bitcoind, node_args, &work_dir) {
+                Ok(features) => {
+                    let before = covered.len();
+                    covered.extend(features);
+                    if covered.len() > before {
+                        novel.push((hash, program));
+                    }
+                }
+                Err(e) => log::warn!("Failed to measure coverage of {hash:016x}: {e}"),
+            }
+        }
+        let _ = std::fs::remove_dir_all(&work_dir);
+        programs = novel;
+    }
+
+    file_ops::create_dir_all(output)?;
+    for (hash, program) in &programs {
+        std::fs::write(
+            output.join(format!("{hash:016x}.ir")),
+            postcard::to_allocvec(program)?,
+        )?;
+    }
+    log::info!(
+        "Merged {} programs into {}",
+        programs.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Run `testcases` and return the coverage counters they hit (in the scenario and its targets).
+pub fn measure_coverage<P: AsRef<Path>>(
+    testcases: &[P],
+    scenario: &Path,
+    bitcoind: &Path,
+    node_args: Option<&str>,
+    work_dir: &Path,
+) -> Result<HashSet<Feature>> {
+    let run_dir = work_dir.join("run");
+    let _ = std::fs::remove_dir_all(&run_dir);
+    file_ops::create_dir_all(&run_dir)?;
+
+    // Failing testcases are measured as well, the profiles are written on exit either way
+    for testcase in testcases {
+        let _ = ReplayCommand::command(scenario, bitcoind, testcase.as_ref(), node_args)
+            .env("LLVM_PROFILE_FILE", run_dir.join("coverage.profraw.%p"))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+    }
+
+    let profraws: Vec<String> = file_ops::read_dir_files(&run_dir)?
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    if profraws.is_empty() {
+        return Err(CliError::ProcessError("No profraw files found".to_string()));
+    }
+
+    let profdata = work_dir.join("coverage.profdata");
+    let profdata = profdata.to_string_lossy().into_owned();
+    let mut merge_args = vec!["merge", "-sparse"];
+    merge_args.extend(profraws.iter().map(String::as_str));
+    merge_args.extend(["-o", profdata.as_str()]);
+    let profdata_cmd = process::get_llvm_command("llvm-profdata");
+    process::run_command_with_output(&profdata_cmd, &merge_args, None)?;
+
+    let show_args = ["show", "-all-functions", "-counts", profdata.as_str()];
+    let output = process::run_command_with_output(&profdata_cmd, &show_args, None)?;
+    Ok(parse_counters(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse the non-zero counters from the output of `llvm-profdata show -all-functions -counts`,
+/// which lists each function as:
+///
+/// ```text
+///   _Z3foov:
+///     Hash: 0x...
+///     Counters: 3
+///     Function count: 1
+///     Block counts: [1, 0]
+/// ```
+fn parse_counters(output: &str) -> HashSet<Feature> {
+    let mut features = HashSet::new();
+    let mut function: Option<&str> = None;
+    for line in output.lines() {
+        if let Some(name) = line
+            .strip_prefix("  ")
+            .filter(|rest| !rest.starts_with(' '))
+            .and_then(|rest| rest.strip_suffix(':'))
+        {
+            function = Some(name);
+            continue;
+        }
+        let Some(function) = function else {
+            continue;
+        };
+        let line = line.trim();
+        if let Some(count) = line.strip_prefix("Function count: ") {
+            if count.parse::<u64>().is_ok_and(|count| count > 0) {
+                features.insert((function.to_string(), 0));
+            }
+        } else if let Some(counts) = line.strip_prefix("Block counts: ") {
+            let counts = counts.trim_start_matches('[').trim_end_matches(']');
+            for (index, count) in counts.split(", ").enumerate() {
+                if count.parse::<u64>().is_ok_and(|count| count > 0) {
+                    features.insert((function.to_string(), index + 1));
+                }
+            }
+        }
+    }
+    features
+}
+
+/// Greedily pick the testcase covering the most uncovered features (the smallest one on ties)
+/// until all features are covered, returning the indices of the picked testcases.
+fn greedy_set_cover(coverage: &[HashSet<Feature>], sizes: &[u64]) -> Vec<usize> {
+    let mut uncovered: HashSet<&Feature> = coverage.iter().flatten().collect();
+    let mut selected = Vec::new();
+    while !uncovered.is_empty() {
+        let Some((index, gain)) = coverage
+            .iter()
+            .enumerate()
+            .map(|(index, features)| {
+                let gain = features.iter().filter(|f| uncovered.contains(f)).count();
+                (index, gain)
+            })
+            .max_by(|(a, gain_a), (b, gain_b)| {
+                gain_a.cmp(gain_b).then_with(|| sizes[*b].cmp(&sizes[*a]))
+            })
+        else {
+            break;
+        };
+        if gain == 0 {
+            break;
+        }
+        for feature in &coverage[index] {
+            uncovered.remove(feature);
+        }
+        selected.push(index);
+    }
+    selected.sort_unstable();
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(function: &str, index: usize) -> Feature {
+        (function.to_string(), index)
+    }
+
+    #[test]
+    fn parse_counters_keeps_covered_counters() {
+        let output = "\
+Counters:
+  _ZN4node3runEv:
+    Hash: 0x0000000000000001
+    Counters: 3
+    Function count: 5
+    Block counts: [2, 0]
+  _ZN4node4stopEv:
+    Hash: 0x0000000000000002
+    Counters: 2
+    Function count: 0
+    Block counts: [0]
+Instrumentation level: Front-end
+Functions shown: 2
+";
+        let features = parse_counters(output);
+        assert_eq!(
+            features,
+            HashSet::from([feature("_ZN4node3runEv", 0), feature("_ZN4node3runEv", 1)])
+        );
+    }
+
+    #[test]
+    fn greedy_set_cover_selects_minimal_cover() {
+        let coverage = [
+            HashSet::from([feature("a", 0), feature("b", 0)]),
+            HashSet::from([feature("b", 0)]),
+            HashSet::from([feature("c", 0)]),
+        ];
+        assert_eq!(greedy_set_cover(&coverage, &[1, 1, 1]), vec![0, 2]);
+    }
+
+    #[test]
+    fn greedy_set_cover_prefers_smaller_entries() {
+        let coverage = [
+            HashSet::from([feature("a", 0)]),
+            HashSet::from([feature("a", 0)]),
+            HashSet::new(),
+        ];
+        assert_eq!(greedy_set_cover(&coverage, &[10, 5, 1]), vec![1]);
+    }
+}
@@ -1,10 +1,53 @@
 use crate::error::{CliError, Result};
 use crate::utils::{file_ops, process};
+use clap::{Subcommand, ValueEnum};
+use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
 pub struct CoverageCommand;
 
+#[derive(Subcommand)]
+pub enum CoverageCommands {
+    /// Report the functions and coverage counters covered by one corpus but not the other
+    Diff {
+        #[arg(long, help = "Path to the baseline corpus directory")]
+        baseline: PathBuf,
+        #[arg(long, help = "Path to the candidate corpus directory")]
+        candidate: PathBuf,
+        #[arg(
+            long,
+            help = "Path to the fuzzamoto scenario binary (built without nyx) that should be run with coverage measurer"
+        )]
+        scenario: PathBuf,
+        #[arg(
+            id = "coverage_bitcoind",
+            long = "bitcoind",
+            help = "Path to the coverage instrumented bitcoind binary"
+        )]
+        bitcoind: PathBuf,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+        #[arg(long, help = "Path to write the report to (printed if not set)")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// Browsable report with annotated sources (`coverage-report/`)
+    Html,
+    /// LCOV tracefile (`coverage.lcov`), as used by Bitcoin Core's coverage tooling
+    Lcov,
+    /// Cobertura XML (`coverage.xml`), as ingested by most CI dashboards
+    Cobertura,
+}
+
 impl CoverageCommand {
+    #[expect(clippy::too_many_arguments)]
     pub fn execute(
         output: &Path,
         corpus: &Path,
@@ -12,6 +55,8 @@ impl CoverageCommand {
         scenario: &Path,
         profraws: Option<Vec<PathBuf>>,
         run_only: bool,
+        ignore_filename_regex: Option<&str>,
+        format: CoverageFormat,
     ) -> Result<()> {
         file_ops::ensure_file_exists(bitcoind)?;
         file_ops::ensure_file_exists(scenario)?;
@@ -43,7 +88,15 @@ impl CoverageCommand {
             Self::merge_profraws(output, &profraws_dir)?
         };
 
-        Self::generate_report(output, bitcoind, &profdata)?;
+        match format {
+            CoverageFormat::Html => {
+                Self::generate_report(output, bitcoind, &profdata, ignore_filename_regex)?;
+            }
+            CoverageFormat::Lcov | CoverageFormat::Cobertura => {
+                Self::export(output, bitcoind, &profdata, ignore_filename_regex, format)?;
+            }
+        }
+        Self::generate_summary(output, bitcoind, &profdata, ignore_filename_regex)?;
         Ok(())
     }
 
@@ -66,23 +119,33 @@ impl CoverageCommand {
         Ok(())
     }
 
-    fn generate_report(output: &Path, bitcoind: &Path, coverage_profdata: &Path) -> Result<()> {
+    fn generate_report(
+        output: &Path,
+        bitcoind: &Path,
+        coverage_profdata: &Path,
+        ignore_filename_regex: Option<&str>,
+    ) -> Result<()> {
         // Generate HTML report
         let coverage_report_dir = output.join("coverage-report");
         let coverage_report_str = coverage_report_dir.to_str().unwrap();
         let instr_profile_arg = format!("-instr-profile={}", coverage_profdata.to_str().unwrap());
         let output_dir_arg = format!("-output-dir={coverage_report_str}");
+        let ignore_arg =
+            ignore_filename_regex.map(|regex| format!("-ignore-filename-regex={regex}"));
 
-        let show_args = vec![
+        let mut show_args = vec![
             "show",
             bitcoind.to_str().unwrap(),
             &instr_profile_arg,
             "-format=html",
             "-show-directory-coverage",
             "-show-branches=count",
+            "-show-line-counts-or-regions",
+            "-project-title=fuzzamoto",
             &output_dir_arg,
             "-Xdemangler=c++filt",
         ];
+        show_args.extend(ignore_arg.as_deref());
 
         let show_cmd = process::get_llvm_command("llvm-cov");
         process::run_command_with_status(&show_cmd, &show_args, None)?;
@@ -95,6 +158,74 @@ impl CoverageCommand {
         Ok(())
     }
 
+    /// Export the coverage as an LCOV tracefile (`coverage.lcov`) or, converted from it, as
+    /// Cobertura XML (`coverage.xml`).
+    fn export(
+        output: &Path,
+        bitcoind: &Path,
+        coverage_profdata: &Path,
+        ignore_filename_regex: Option<&str>,
+        format: CoverageFormat,
+    ) -> Result<()> {
+        let instr_profile_arg = format!("-instr-profile={}", coverage_profdata.to_str().unwrap());
+        let ignore_arg =
+            ignore_filename_regex.map(|regex| format!("-ignore-filename-regex={regex}"));
+
+        let mut export_args = vec![
+            "export",
+            bitcoind.to_str().unwrap(),
+            &instr_profile_arg,
+            "-format=lcov",
+        ];
+        export_args.extend(ignore_arg.as_deref());
+
+        let export_cmd = process::get_llvm_command("llvm-cov");
+        let export = process::run_command_with_output(&export_cmd, &export_args, None)?;
+        let lcov = String::from_utf8_lossy(&export.stdout);
+
+        let path = if format == CoverageFormat::Cobertura {
+            let path = output.join("coverage.xml");
+            std::fs::write(&path, lcov_to_cobertura(&lcov))?;
+            path
+        } else {
+            let path = output.join("coverage.lcov");
+            std::fs::write(&path, lcov.as_bytes())?;
+            path
+        };
+        log::info!("Coverage exported to: {}", path.display());
+
+        Ok(())
+    }
+
+    /// Write the per-file coverage summary of `llvm-cov report` to `coverage-summary.txt` and log
+    /// the totals.
+    fn generate_summary(
+        output: &Path,
+        bitcoind: &Path,
+        coverage_profdata: &Path,
+        ignore_filename_regex: Option<&str>,
+    ) -> Result<()> {
+        let instr_profile_arg = format!("-instr-profile={}", coverage_profdata.to_str().unwrap());
+        let ignore_arg =
+            ignore_filename_regex.map(|regex| format!("-ignore-filename-regex={regex}"));
+
+        let mut report_args = vec!["report", bitcoind.to_str().unwrap(), &instr_profile_arg];
+        report_args.extend(ignore_arg.as_deref());
+
+        let report_cmd = process::get_llvm_command("llvm-cov");
+        let report = process::run_command_with_output(&report_cmd, &report_args, None)?;
+        let summary = String::from_utf8_lossy(&report.stdout);
+
+        let summary_path = output.join("coverage-summary.txt");
+        std::fs::write(&summary_path, summary.as_bytes())?;
+        if let Some(total) = summary.lines().find(|line| line.starts_with("TOTAL")) {
+            log::info!("{total}");
+        }
+        log::info!("Coverage summary written to: {}", summary_path.display());
+
+        Ok(())
+    }
+
     fn merge_profraws(output: &Path, profraws: &Vec<&Path>) -> Result<PathBuf> {
         if profraws.is_empty() {
             return Err(CliError::InvalidInput(
@@ -136,3 +267,224 @@ impl CoverageCommand {
         Ok(merged)
     }
 }
+
+/// Line and branch coverage of a single source file
+#[derive(Default)]
+struct FileCoverage {
+    /// Hit count per line
+    lines: BTreeMap<u64, u64>,
+    /// Taken and total branches per line
+    branches: BTreeMap<u64, (u64, u64)>,
+}
+
+impl FileCoverage {
+    fn lines_covered(&self) -> u64 {
+        self.lines.values().filter(|hits| **hits > 0).count() as u64
+    }
+
+    fn branches(&self) -> (u64, u64) {
+        self.branches
+            .values()
+            .fold((0, 0), |(taken, total), (t, n)| (taken + t, total + n))
+    }
+}
+
+fn rate(covered: u64, valid: u64) -> String {
+    if valid == 0 {
+        "1.0000".to_string()
+    } else {
+        #[expect(clippy::cast_precision_loss)]
+        let rate = covered as f64 / valid as f64;
+        format!("{rate:.4}")
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Parse the `SF`, `DA` and `BRDA` records of an LCOV tracefile, grouped by source file.
+fn parse_lcov(lcov: &str) -> BTreeMap<String, FileCoverage> {
+    let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in lcov.lines() {
+        if let Some(file) = line.strip_prefix("SF:") {
+            current = Some(file.to_string());
+            continue;
+        }
+        if line == "end_of_record" {
+            current = None;
+            continue;
+        }
+        let Some(file) = current.as_ref() else {
+            continue;
+        };
+        let coverage = files.entry(file.clone()).or_default();
+        if let Some(record) = line.strip_prefix("DA:") {
+            let mut fields = record.split(',');
+            if let (Some(Ok(line)), Some(Ok(hits))) = (
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+            ) {
+                *coverage.lines.entry(line).or_default() += hits;
+            }
+        } else if let Some(record) = line.strip_prefix("BRDA:") {
+            // `BRDA:<line>,<block>,<branch>,<taken>` where taken is `-` if the line never ran
+            let fields: Vec<&str> = record.split(',').collect();
+            if let [line, _, _, taken] = fields.as_slice()
+                && let Ok(line) = line.parse::<u64>()
+            {
+                let entry = coverage.branches.entry(line).or_default();
+                entry.0 += u64::from(taken.parse::<u64>().is_ok_and(|taken| taken > 0));
+                entry.1 += 1;
+            }
+        }
+    }
+    files
+}
+
+/// Convert an LCOV tracefile to Cobertura XML, with one package per source directory and one
+/// class per source file.
+fn lcov_to_cobertura(lcov: &str) -> String {
+    let files = parse_lcov(lcov);
+
+    let mut packages: BTreeMap<String, Vec<(&String, &FileCoverage)>> = BTreeMap::new();
+    for (file, coverage) in &files {
+        let package = Path::new(file)
+            .parent()
+            .map_or_else(String::new, |dir| dir.to_string_lossy().into_owned());
+        packages.entry(package).or_default().push((file, coverage));
+    }
+
+    let lines_valid: u64 = files.values().map(|c| c.lines.len() as u64).sum();
+    let lines_covered: u64 = files.values().map(FileCoverage::lines_covered).sum();
+    let (branches_covered, branches_valid) = files
+        .values()
+        .map(FileCoverage::branches)
+        .fold((0, 0), |(taken, total), (t, n)| (taken + t, total + n));
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" ?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<coverage line-rate="{}" branch-rate="{}" lines-covered="{lines_covered}" lines-valid="{lines_valid}" branches-covered="{branches_covered}" branches-valid="{branches_valid}" complexity="0" version="fuzzamoto" timestamp="{timestamp}">"#,
+        rate(lines_covered, lines_valid),
+        rate(branches_covered, branches_valid),
+    );
+    let _ = writeln!(xml, "  <sources><source>/</source></sources>");
+    let _ = writeln!(xml, "  <packages>");
+    for (package, files) in &packages {
+        let lines_valid: u64 = files.iter().map(|(_, c)| c.lines.len() as u64).sum();
+        let lines_covered: u64 = files.iter().map(|(_, c)| c.lines_covered()).sum();
+        let (branches_covered, branches_valid) = files
+            .iter()
+            .map(|(_, c)| c.branches())
+            .fold((0, 0), |(taken, total), (t, n)| (taken + t, total + n));
+        let _ = writeln!(
+            xml,
+            r#"    <package name="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+            escape_xml(package.trim_start_matches('/')),
+            rate(lines_covered, lines_valid),
+            rate(branches_covered, branches_valid),
+        );
+        let _ = writeln!(xml, "      <classes>");
+        for (file, coverage) in files {
+            let (branches_covered, branches_valid) = coverage.branches();
+            let name = Path::new(file.as_str())
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let _ = writeln!(
+                xml,
+                r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+                escape_xml(&name),
+                escape_xml(file.trim_start_matches('/')),
+                rate(coverage.lines_covered(), coverage.lines.len() as u64),
+                rate(branches_covered, branches_valid),
+            );
+            let _ = writeln!(xml, "          <methods/>");
+            let _ = writeln!(xml, "          <lines>");
+            for (line, hits) in &coverage.lines {
+                match coverage.branches.get(line) {
+                    Some((taken, total)) if *total > 0 => {
+                        let _ = writeln!(
+                            xml,
+                            r#"            <line number="{line}" hits="{hits}" branch="true" condition-coverage="{}% ({taken}/{total})"/>"#,
+                            taken * 100 / total,
+                        );
+                    }
+                    _ => {
+                        let _ = writeln!(
+                            xml,
+                            r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+                        );
+                    }
+                }
+            }
+            let _ = writeln!(xml, "          </lines>");
+            let _ = writeln!(xml, "        </class>");
+        }
+        let _ = writeln!(xml, "      </classes>");
+        let _ = writeln!(xml, "    </package>");
+    }
+    let _ = writeln!(xml, "  </packages>");
+    let _ = writeln!(xml, "</coverage>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "\
+TN:
+SF:/src/net.cpp
+DA:10,3
+DA:11,0
+DA:10,2
+BRDA:10,0,0,1
+BRDA:10,0,1,-
+BRDA:11,0,0,0
+end_of_record
+SF:/src/validation.cpp
+DA:5,1
+end_of_record
+DA:99,1
+";
+
+    #[test]
+    fn parse_lcov_sums_records_per_file() {
+        let files = parse_lcov(LCOV);
+        assert_eq!(files.len(), 2);
+
+        let net = &files["/src/net.cpp"];
+        assert_eq!(net.lines, BTreeMap::from([(10, 5), (11, 0)]));
+        assert_eq!(net.branches, BTreeMap::from([(10, (1, 2)), (11, (0, 1))]));
+        assert_eq!(net.lines_covered(), 1);
+        assert_eq!(net.branches(), (1, 3));
+
+        let validation = &files["/src/validation.cpp"];
+        assert_eq!(validation.lines, BTreeMap::from([(5, 1)]));
+        assert!(validation.branches.is_empty());
+    }
+
+    #[test]
+    fn lcov_to_cobertura_reports_rates() {
+        let xml = lcov_to_cobertura(LCOV);
+        assert!(xml.contains(
+            r#"line-rate="0.6667" branch-rate="0.3333" lines-covered="2" lines-valid="3" branches-covered="1" branches-valid="3""#
+        ));
+        assert!(xml.contains(r#"<package name="src" line-rate="0.6667" branch-rate="0.3333""#));
+        assert!(xml.contains(r#"<class name="net.cpp" filename="src/net.cpp""#));
+        assert!(xml.contains(
+            r#"<line number="10" hits="5" branch="true" condition-coverage="50% (1/2)"/>"#
+        ));
+        assert!(xml.contains(r#"<line number="5" hits="1" branch="false"/>"#));
+        assert!(!xml.contains(r#"number="99""#));
+    }
+}
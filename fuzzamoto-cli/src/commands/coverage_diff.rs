@@ -0,0 +1,133 @@
+use crate::commands::corpus::{Feature, measure_coverage};
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+pub struct CoverageDiffCommand;
+
+impl CoverageDiffCommand {
+    /// Measure the coverage of the `baseline` and `candidate` corpora and report the functions
+    /// and coverage counters (edges) only one of them reaches. The report is written to `output`
+    /// or printed if no output is given.
+    pub fn execute(
+        baseline: &Path,
+        candidate: &Path,
+        scenario: &Path,
+        bitcoind: &Path,
+        node_args: Option<&str>,
+        output: Option<&Path>,
+    ) -> Result<()> {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+
+        let work_dir =
+            std::env::temp_dir().join(format!("fuzzamoto-coverage-diff-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&work_dir);
+        file_ops::create_dir_all(&work_dir)?;
+
+        let baseline_features = corpus_coverage(baseline, scenario, bitcoind, node_args, &work_dir);
+        let candidate_features =
+            corpus_coverage(candidate, scenario, bitcoind, node_args, &work_dir);
+        let _ = std::fs::remove_dir_all(&work_dir);
+        let (baseline_features, candidate_features) = (baseline_features?, candidate_features?);
+
+        let report = diff_report(
+            (baseline, &baseline_features),
+            (candidate, &candidate_features),
+        );
+        if let Some(output) = output {
+            std::fs::write(output, &report)?;
+            log::info!("Coverage diff written to: {}", output.display());
+        } else {
+            print!("{report}");
+        }
+        Ok(())
+    }
+}
+
+/// Run all testcases of `corpus` and return the coverage counters hit by any of them.
+fn corpus_coverage(
+    corpus: &Path,
+    scenario: &Path,
+    bitcoind: &Path,
+    node_args: Option<&str>,
+    work_dir: &Path,
+) -> Result<HashSet<Feature>> {
+    let mut testcases = file_ops::read_dir_files(corpus)?;
+    if testcases.is_empty() {
+        return Err(CliError::InvalidInput(format!(
+            "Empty corpus directory: {}",
+            corpus.display()
+        )));
+    }
+    testcases.sort();
+
+    log::info!(
+        "Measuring coverage of {} testcases in {}",
+        testcases.len(),
+        corpus.display()
+    );
+    measure_coverage(&testcases, scenario, bitcoind, node_args, work_dir)
+}
+
+/// Counters covered by `a` but not by `b`, grouped by function. The flag is set if `b` does not
+/// cover the function at all.
+fn only_in<'a>(a: &'a HashSet<Feature>, b: &HashSet<Feature>) -> BTreeMap<&'a str, (usize, bool)> {
+    let b_functions: HashSet<&str> = b.iter().map(|(function, _)| function.as_str()).collect();
+    let mut functions = BTreeMap::new();
+    for feature in a.difference(b) {
+        let function = feature.0.as_str();
+        functions
+            .entry(function)
+            .or_insert((0, !b_functions.contains(function)))
+            .0 += 1;
+    }
+    functions
+}
+
+fn diff_report(
+    (baseline, baseline_features): (&Path, &HashSet<Feature>),
+    (candidate, candidate_features): (&Path, &HashSet<Feature>),
+) -> String {
+    let functions = |features: &HashSet<Feature>| {
+        features
+            .iter()
+            .map(|(function, _)| function.as_str())
+            .collect::<HashSet<_>>()
+            .len()
+    };
+
+    let mut report = String::new();
+    for (name, path, features) in [
+        ("Baseline", baseline, baseline_features),
+        ("Candidate", candidate, candidate_features),
+    ] {
+        let _ = writeln!(
+            report,
+            "{name}: {} ({} functions, {} counters)",
+            path.display(),
+            functions(features),
+            features.len()
+        );
+    }
+
+    for (name, a, b) in [
+        ("candidate", candidate_features, baseline_features),
+        ("baseline", baseline_features, candidate_features),
+    ] {
+        let only = only_in(a, b);
+        let new_functions = only.values().filter(|(_, new)| *new).count();
+        let counters: usize = only.values().map(|(counters, _)| counters).sum();
+        let _ = writeln!(
+            report,
+            "\nOnly covered by the {name}: {new_functions} functions, {counters} counters"
+        );
+        for (function, (counters, new)) in &only {
+            let marker = if *new { " (new function)" } else { "" };
+            let _ = writeln!(report, "  {function}: {counters} counters{marker}");
+        }
+    }
+    report
+}
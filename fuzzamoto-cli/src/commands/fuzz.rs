@@ -0,0 +1,264 @@
+use crate::commands::init::Sharedirs;
+use crate::commands::ir::seed_corpus;
+use crate::commands::{InitCommand, VerifySharedirCommand};
+use crate::config::Config;
+use crate::error::{CliError, Result};
+use crate::utils::{file_ops, process};
+use clap::{Args, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fuzzer {
+    /// `fuzzamoto-libafl`, built from this workspace
+    Libafl,
+    /// AFL++'s `afl-fuzz` in nyx mode (not for the IR scenario)
+    Afl,
+}
+
+#[derive(Args)]
+pub struct FuzzArgs {
+    #[arg(
+        long,
+        help = "Name of the scenario to fuzz (e.g. \"ir\" for scenario-ir)"
+    )]
+    scenario: String,
+    #[arg(long, help = "Path to the instrumented bitcoind binary")]
+    bitcoind: PathBuf,
+    #[arg(long, help = "Path to the nyx installation")]
+    nyx_dir: PathBuf,
+    #[arg(
+        long,
+        default_value = "libnyx_crash_handler.so",
+        help = "Path to the crash handler (built if it does not exist)"
+    )]
+    crash_handler: PathBuf,
+    #[arg(long, value_enum, default_value_t = Fuzzer::Libafl, help = "Fuzzer to run")]
+    fuzzer: Fuzzer,
+    #[arg(
+        long,
+        help = "Path to the nyx share directory, reused if it exists (defaults to fuzzamoto_scenario-<scenario> in the configured sharedir root or /tmp)"
+    )]
+    sharedir: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value = "/tmp/in",
+        help = "Path to the input corpus directory"
+    )]
+    input: PathBuf,
+    #[arg(
+        long,
+        default_value = "/tmp/out",
+        help = "Path to the output directory"
+    )]
+    output: PathBuf,
+    #[arg(
+        long,
+        help = "Program context file to seed an empty input corpus with (fuzzamoto-libafl only, see `ir seed`)"
+    )]
+    context: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = 256,
+        help = "Number of programs to seed an empty input corpus with"
+    )]
+    seeds: usize,
+    #[arg(
+        long,
+        default_value = "all",
+        help = "Cpu cores to fuzz on (fuzzamoto-libafl only)"
+    )]
+    cores: String,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Don't build the scenario and fuzzer, use the binaries in target/release"
+    )]
+    no_build: bool,
+    #[arg(
+        long,
+        help = "Path to the file with the RPC commands that should be copied into the share directory"
+    )]
+    rpc_path: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+    )]
+    node_args: Option<String>,
+    #[arg(
+        long,
+        help = "Named snapshot point of the scenario setup to fuzz from (e.g. \"post-handshake\")"
+    )]
+    snapshot: Option<String>,
+    #[arg(last = true, help = "Extra arguments passed to the fuzzer")]
+    fuzzer_args: Vec<String>,
+}
+
+pub struct FuzzCommand;
+
+impl FuzzCommand {
+    /// Build the scenario (and fuzzer), create the share directory (or reuse an existing one),
+    /// seed the corpus and launch the fuzzer, i.e. all steps of the usage docs in one go.
+    pub fn execute(args: &FuzzArgs, config: &Config) -> Result<()> {
+        if args.fuzzer == Fuzzer::Afl && args.scenario == "ir" {
+            return Err(CliError::InvalidInput(
+                "The IR scenario can only be fuzzed with fuzzamoto-libafl".to_string(),
+            ));
+        }
+        file_ops::ensure_file_exists(&args.bitcoind)?;
+
+        let release_dir = Path::new("target/release");
+        let scenario = release_dir.join(format!("scenario-{}", args.scenario));
+        let sharedir = match &args.sharedir {
+            Some(sharedir) => config.sharedir(sharedir),
+            None => config
+                .sharedir_root
+                .as_deref()
+                .unwrap_or(Path::new("/tmp"))
+                .join(format!("fuzzamoto_scenario-{}", args.scenario)),
+        };
+
+        if !args.no_build {
+            build(args)?;
+        }
+
+        if sharedir.exists() {
+            log::info!("Reusing share directory {}", sharedir.display());
+            VerifySharedirCommand::execute(&sharedir, None, None, Duration::ZERO)?;
+        } else {
+            InitCommand::execute(
+                Sharedirs::Single(&sharedir),
+                &args.crash_handler,
+                &args.bitcoind,
+                None,
+                std::slice::from_ref(&scenario),
+                &args.nyx_dir,
+                args.rpc_path.as_ref(),
+                args.node_args.as_deref(),
+                args.snapshot.as_deref(),
+                args.fuzzer_args
+                    .iter()
+                    .any(|arg| arg == "--characterization-feedback"),
+                None,
+            )?;
+        }
+
+        seed(args)?;
+
+        let (fuzzer, mut fuzzer_args) = match args.fuzzer {
+            Fuzzer::Libafl => (
+                config
+                    .fuzzer
+                    .clone()
+                    .unwrap_or_else(|| release_dir.join("fuzzamoto-libafl"))
+                    .display()
+                    .to_string(),
+                vec![
+                    "--input".to_string(),
+                    args.input.display().to_string(),
+                    "--output".to_string(),
+                    args.output.display().to_string(),
+                    "--share".to_string(),
+                    sharedir.display().to_string(),
+                    "--cores".to_string(),
+                    args.cores.clone(),
+                ],
+            ),
+            Fuzzer::Afl => (
+                "afl-fuzz".to_string(),
+                vec![
+                    "-X".to_string(),
+                    "-i".to_string(),
+                    args.input.display().to_string(),
+                    "-o".to_string(),
+                    args.output.display().to_string(),
+                ],
+            ),
+        };
+        fuzzer_args.extend(args.fuzzer_args.iter().cloned());
+        if args.fuzzer == Fuzzer::Afl {
+            fuzzer_args.extend(["--".to_string(), sharedir.display().to_string()]);
+        }
+
+        log::info!("Running {fuzzer} {}", fuzzer_args.join(" "));
+        let fuzzer_args: Vec<&str> = fuzzer_args.iter().map(String::as_str).collect();
+        process::run_command_with_status(&fuzzer, &fuzzer_args, None)
+    }
+}
+
+/// Build the scenario (and `fuzzamoto-libafl`) for nyx, as well as the crash handler if it
+/// doesn't exist yet.
+fn build(args: &FuzzArgs) -> Result<()> {
+    let mut cargo_args = vec![
+        "build".to_string(),
+        "--release".to_string(),
+        "--package".to_string(),
+        "fuzzamoto-scenarios".to_string(),
+        "--bin".to_string(),
+        format!("scenario-{}", args.scenario),
+    ];
+    let mut features = "fuzzamoto/fuzz,fuzzamoto-scenarios/fuzz".to_string();
+    if args.fuzzer == Fuzzer::Libafl {
+        cargo_args.extend(["--package".to_string(), "fuzzamoto-libafl".to_string()]);
+        features.push_str(",fuzzamoto-libafl/fuzz");
+    }
+    cargo_args.extend(["--features".to_string(), features]);
+
+    log::info!("Building scenario-{}", args.scenario);
+    let status = Command::new("cargo")
+        .args(&cargo_args)
+        .env("BITCOIND_PATH", &args.bitcoind)
+        .status()?;
+    if !status.success() {
+        return Err(CliError::ProcessError(format!(
+            "Failed to build scenario-{} (exit code: {})",
+            args.scenario,
+            status.code().unwrap_or(-1)
+        )));
+    }
+
+    if !args.crash_handler.exists() {
+        log::info!("Building crash handler {}", args.crash_handler.display());
+        let compiler = std::env::var("CC").unwrap_or_else(|_| "clang".to_string());
+        process::run_command_with_status(
+            &compiler,
+            &[
+                "-fPIC",
+                "-DENABLE_NYX",
+                "-D_GNU_SOURCE",
+                "-DNO_PT_NYX",
+                "./fuzzamoto-nyx-sys/src/nyx-crash-handler.c",
+                "-ldl",
+                "-I.",
+                "-shared",
+                "-o",
+                args.crash_handler.to_str().unwrap(),
+            ],
+            None,
+        )?;
+    }
+    Ok(())
+}
+
+/// Seed an empty input corpus, with the IR generators for `fuzzamoto-libafl` (if a program context
+/// is given) and with a dummy input for AFL++ (which can't start from an empty corpus).
+fn seed(args: &FuzzArgs) -> Result<()> {
+    file_ops::create_dir_all(&args.input)?;
+    if !file_ops::read_dir_files(&args.input)?.is_empty() {
+        return Ok(());
+    }
+
+    match (args.fuzzer, &args.context) {
+        (Fuzzer::Libafl, Some(context)) => seed_corpus(context, &args.input, args.seeds, 8, None),
+        // fuzzamoto-libafl starts from an empty program on its own
+        (Fuzzer::Libafl, None) => Ok(()),
+        (Fuzzer::Afl, _) => {
+            log::warn!(
+                "Empty input corpus, starting from a dummy seed. Pass a seed corpus with --input for better results"
+            );
+            std::fs::write(args.input.join("seed"), b"AAA")?;
+            Ok(())
+        }
+    }
+}
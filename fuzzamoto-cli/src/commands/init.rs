@@ -1,11 +1,147 @@
 use crate::error::{CliError, Result};
 use crate::utils::{file_ops, nyx, process};
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 pub struct InitCommand;
 
+/// Where `init` creates the share directories
+#[derive(Debug, Clone, Copy)]
+pub enum Sharedirs<'a> {
+    /// A single share directory for a single scenario
+    Single(&'a Path),
+    /// One share directory per scenario inside of the root (`<root>/fuzzamoto_<scenario name>`)
+    Root(&'a Path),
+}
+
 impl InitCommand {
+    /// Create the nyx share directories (see `Sharedirs`) from the given binaries and their shared
+    /// library dependencies.
+    ///
+    /// With a root, scenarios that aren't executable files (such as cargo's `.d` files matched by a
+    /// `scenario-*` glob) are skipped and files that are the same in all share directories
+    /// (packer binaries, bitcoind, libraries) are hard linked instead of copied.
+    ///
+    /// With `rootfs` (a directory or a tarball, e.g. from `docker export` or a CI artifact), the
+    /// binaries and their dependencies are taken from it instead of the host, so a sharedir for a
+    /// target built in a container can be created without Docker.
+    #[expect(clippy::too_many_arguments)]
     pub fn execute(
+        sharedirs: Sharedirs,
+        crash_handler: &Path,
+        bitcoind: &Path,
+        secondary_bitcoind: Option<&PathBuf>,
+        scenarios: &[PathBuf],
+        nyx_dir: &Path,
+        rpc_path: Option<&PathBuf>,
+        node_args: Option<&str>,
+        snapshot: Option<&str>,
+        characterize: bool,
+        rootfs: Option<&Path>,
+    ) -> Result<()> {
+        let sharedirs: Vec<(PathBuf, &Path)> = match (sharedirs, scenarios) {
+            (_, []) => {
+                return Err(CliError::InvalidInput("No scenario provided".to_string()));
+            }
+            (Sharedirs::Single(sharedir), [scenario]) => {
+                vec![(sharedir.to_path_buf(), scenario.as_path())]
+            }
+            (Sharedirs::Single(_), _) => {
+                return Err(CliError::InvalidInput(
+                    "Multiple scenarios need a share directory root (--sharedir-root)".to_string(),
+                ));
+            }
+            (Sharedirs::Root(root), scenarios) => scenarios
+                .iter()
+                .filter(|scenario| {
+                    // Paths that don't exist on the host may still exist in the rootfs
+                    let skip = std::fs::metadata(scenario)
+                        .is_ok_and(|m| !m.is_file() || m.permissions().mode() & 0o111 == 0);
+                    if skip {
+                        log::warn!("Skipping {}: not an executable", scenario.display());
+                    }
+                    !skip
+                })
+                .map(|scenario| {
+                    let name = scenario.file_name().unwrap_or_default().to_string_lossy();
+                    (root.join(format!("fuzzamoto_{name}")), scenario.as_path())
+                })
+                .collect(),
+        };
+        if sharedirs.is_empty() {
+            return Err(CliError::InvalidInput(
+                "None of the scenarios is an executable".to_string(),
+            ));
+        }
+
+        nyx::compile_packer_binaries(nyx_dir)?;
+
+        // Tarballs are extracted to a temporary directory first
+        let extracted = match rootfs {
+            Some(rootfs) if !rootfs.is_dir() => Some(Self::extract_rootfs(rootfs)?),
+            _ => None,
+        };
+
+        let result = sharedirs.iter().try_for_each(|(sharedir, scenario)| {
+            Self::create_sharedir(
+                sharedir,
+                crash_handler,
+                bitcoind,
+                secondary_bitcoind,
+                scenario,
+                nyx_dir,
+                rpc_path,
+                node_args,
+                snapshot,
+                characterize,
+                extracted.as_deref().or(rootfs),
+            )
+        });
+
+        if let Some(extracted) = &extracted {
+            let _ = std::fs::remove_dir_all(extracted);
+        }
+        result?;
+
+        if sharedirs.len() > 1 {
+            let sharedirs: Vec<&Path> = sharedirs.iter().map(|(dir, _)| dir.as_path()).collect();
+            link_identical_files(&sharedirs)?;
+        }
+        Ok(())
+    }
+
+    fn extract_rootfs(tarball: &Path) -> Result<PathBuf> {
+        file_ops::ensure_file_exists(tarball)?;
+
+        let extracted =
+            std::env::temp_dir().join(format!("fuzzamoto-rootfs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&extracted);
+        file_ops::create_dir_all(&extracted)?;
+
+        log::info!(
+            "Extracting {} to {}",
+            tarball.display(),
+            extracted.display()
+        );
+        if let Err(e) = process::run_command_with_status(
+            "tar",
+            &[
+                "-xf",
+                tarball.to_str().unwrap(),
+                "-C",
+                extracted.to_str().unwrap(),
+            ],
+            None,
+        ) {
+            let _ = std::fs::remove_dir_all(&extracted);
+            return Err(e);
+        }
+        Ok(extracted)
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    fn create_sharedir(
         sharedir: &Path,
         crash_handler: &Path,
         bitcoind: &Path,
@@ -13,7 +149,43 @@ impl InitCommand {
         scenario: &Path,
         nyx_dir: &Path,
         rpc_path: Option<&PathBuf>,
+        node_args: Option<&str>,
+        snapshot: Option<&str>,
+        characterize: bool,
+        rootfs: Option<&Path>,
     ) -> Result<()> {
+        // Paths are looked up inside the rootfs (relative to its root, even if they are absolute)
+        // and fall back to the host, e.g. for a crash handler built outside of the rootfs
+        let resolve = |path: &Path| match rootfs {
+            Some(root) if !path.starts_with(root) => {
+                let inside = root.join(path.strip_prefix("/").unwrap_or(path));
+                if inside.exists() {
+                    inside
+                } else {
+                    path.to_path_buf()
+                }
+            }
+            _ => path.to_path_buf(),
+        };
+        let crash_handler = &resolve(crash_handler);
+        let bitcoind = &resolve(bitcoind);
+        let secondary_bitcoind = secondary_bitcoind.map(|p| resolve(p.as_path()));
+        let secondary_bitcoind = secondary_bitcoind.as_ref();
+        let scenario = &resolve(scenario);
+        let rpc_path = rpc_path.map(|p| resolve(p.as_path()));
+        let rpc_path = rpc_path.as_ref();
+
+        if node_args.is_some_and(|args| args.contains('\'')) {
+            return Err(CliError::InvalidInput(
+                "Node arguments must not contain single quotes".to_string(),
+            ));
+        }
+        if snapshot.is_some_and(|name| name.contains('\'')) {
+            return Err(CliError::InvalidInput(
+                "Snapshot point must not contain single quotes".to_string(),
+            ));
+        }
+
         file_ops::ensure_sharedir_not_exists(sharedir)?;
         file_ops::create_dir_all(sharedir)?;
 
@@ -51,8 +223,12 @@ impl InitCommand {
             binary_names.push(binary_name.to_string());
 
             // Get and copy dependencies using lddtree
-            let output =
-                process::run_command_with_output("lddtree", &[binary.to_str().unwrap()], None)?;
+            let mut lddtree_args = Vec::new();
+            if let Some(root) = rootfs {
+                lddtree_args.extend(["-R", root.to_str().unwrap()]);
+            }
+            lddtree_args.push(binary.to_str().unwrap());
+            let output = process::run_command_with_output("lddtree", &lddtree_args, None)?;
 
             // Parse lddtree output and copy dependencies
             let deps = String::from_utf8_lossy(&output.stdout)
@@ -62,7 +238,7 @@ impl InitCommand {
                     let parts: Vec<&str> = line.split("=>").collect();
                     if parts.len() == 2 {
                         let name = parts[0].trim();
-                        let path = parts[1].trim();
+                        let path = resolve(Path::new(parts[1].trim()));
 
                         // Copy the dependency
                         if let Err(e) = std::fs::copy(path, sharedir.join(name)) {
@@ -96,7 +272,6 @@ impl InitCommand {
 
         log::info!("Created share directory: {}", sharedir.display());
 
-        nyx::compile_packer_binaries(nyx_dir)?;
         nyx::copy_packer_binaries(nyx_dir, sharedir)?;
         nyx::generate_nyx_config(nyx_dir, sharedir)?;
 
@@ -125,8 +300,40 @@ impl InitCommand {
             scenario_name,
             secondary_name,
             rpc_name,
+            node_args,
+            snapshot,
+            characterize,
         )?;
 
         Ok(())
     }
 }
+
+/// Replace files in `sharedirs` with hard links to the first file of the same name and content in
+/// an earlier share directory.
+fn link_identical_files(sharedirs: &[&Path]) -> Result<()> {
+    let mut originals: HashMap<std::ffi::OsString, PathBuf> = HashMap::new();
+    let mut linked = 0u64;
+    for sharedir in sharedirs {
+        for file in file_ops::read_dir_files(sharedir)? {
+            let name = file.file_name().unwrap().to_os_string();
+            let Some(original) = originals.get(&name) else {
+                originals.insert(name, file);
+                continue;
+            };
+            if std::fs::read(original)? != std::fs::read(&file)? {
+                continue;
+            }
+            let size = std::fs::metadata(&file)?.len();
+            std::fs::remove_file(&file)?;
+            std::fs::hard_link(original, &file)?;
+            linked += size;
+        }
+    }
+    log::info!(
+        "Hard linked identical files of {} share directories, saving {} MiB",
+        sharedirs.len(),
+        linked / (1024 * 1024)
+    );
+    Ok(())
+}
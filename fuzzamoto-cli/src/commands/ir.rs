@@ -1,14 +1,19 @@
 use clap::{Subcommand, ValueEnum};
+use std::collections::HashSet;
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
 use fuzzamoto_ir::compiler::Compiler;
+use fuzzamoto_ir::lift;
+use fuzzamoto_ir::nopping::NoppingMinimizer;
 use fuzzamoto_ir::{
     AddTxToBlockGenerator, AddrRelayGenerator, AddrRelayV2Generator, AdvanceTimeGenerator,
     BlockGenerator, BloomFilterAddGenerator, BloomFilterClearGenerator, BloomFilterLoadGenerator,
-    CompactFilterQueryGenerator, FullProgramContext, Generator, GetAddrGenerator, GetDataGenerator,
-    HeaderGenerator, InstructionContext, InventoryGenerator, LargeTxGenerator, LongChainGenerator,
-    OneParentOneChildGenerator, Program, ProgramBuilder, SendBlockGenerator, SendMessageGenerator,
-    SingleTxGenerator, TxoGenerator, WitnessGenerator,
+    CompactFilterQueryGenerator, DribbleMessageGenerator, FullProgramContext, Generator,
+    GetAddrGenerator, GetDataGenerator, HeaderGenerator, InstructionContext, InventoryGenerator,
+    LargeTxGenerator, LongChainGenerator, Minimizer, OneParentOneChildGenerator, Operation,
+    Program, ProgramBuilder, ProgramContext, RawFrameGenerator, RestartNodeGenerator,
+    SendBlockGenerator, SendMessageGenerator, SingleTxGenerator, TxoGenerator, WitnessGenerator,
 };
 
 use rand::Rng;
@@ -16,6 +21,7 @@ use rand::rngs::ThreadRng;
 use rand::seq::SliceRandom;
 
 use crate::error::{CliError, Result};
+use crate::utils::file_ops;
 
 pub struct IrCommand;
 
@@ -29,6 +35,13 @@ impl IrCommand {
                 context,
                 generators,
             } => generate_ir(output, *iterations, *programs, context, generators.as_ref()),
+            IRCommands::Seed {
+                context,
+                output,
+                count,
+                iterations,
+                generators,
+            } => seed_corpus(context, output, *count, *iterations, generators.as_ref()),
             IRCommands::Compile { input, output } => compile_ir(input, output),
             IRCommands::Print { input, json } => print_ir(input, *json),
             IRCommands::Convert {
@@ -38,6 +51,18 @@ impl IrCommand {
                 output,
             } => convert_ir(from, to, input, output),
             IRCommands::Analyze { input } => analyze_ir(input),
+            IRCommands::Migrate {
+                input,
+                output,
+                report,
+            } => migrate_corpus(input, output, report.as_deref()),
+            IRCommands::Simplify { corpus, dry_run } => simplify_corpus(corpus, *dry_run),
+            IRCommands::Lift {
+                from,
+                context,
+                input,
+                output,
+            } => lift_ir(*from, context, input, output),
         }
     }
 }
@@ -65,6 +90,32 @@ pub enum IRCommands {
         )]
         generators: Option<Vec<String>>,
     },
+    /// Generate a diverse initial corpus with all IR generators
+    Seed {
+        #[arg(
+            long,
+            alias = "scenario",
+            help = "Path to the program context file dumped by the scenario"
+        )]
+        context: PathBuf,
+        #[arg(long, help = "Path to the output corpus directory")]
+        output: PathBuf,
+        #[arg(long, help = "Number of distinct IR programs to generate")]
+        count: usize,
+        #[arg(
+            long,
+            default_value_t = 8,
+            help = "Max number of generators to run for each program"
+        )]
+        iterations: usize,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            num_args = 1..,
+            help = "Optional comma-separated list of generator names (defaults to all)"
+        )]
+        generators: Option<Vec<String>>,
+    },
     /// Compile fuzzamoto IR
     Compile {
         #[arg(long, help = "Path to the input file/directory for the generated IR")]
@@ -73,7 +124,7 @@ pub enum IRCommands {
         output: PathBuf,
     },
 
-    /// Convert fuzzamoto corpora
+    /// Convert fuzzamoto IR programs or whole corpora between formats
     Convert {
         #[arg(long, help = "Format of the input IR", value_enum, default_value_t = CorpusFormat::Postcard)]
         from: CorpusFormat,
@@ -99,12 +150,76 @@ pub enum IRCommands {
         #[arg(help = "Path to the input IR directory to analyze")]
         input: PathBuf,
     },
+
+    /// Migrate an IR corpus to the current IR schema, dropping programs that can't be upgraded
+    Migrate {
+        #[arg(
+            long,
+            help = "Path to the input corpus directory (postcard programs, or json with a .json extension)"
+        )]
+        input: PathBuf,
+        #[arg(long, help = "Path to the output directory for the migrated corpus")]
+        output: PathBuf,
+        #[arg(
+            long,
+            help = "Path to write the migration report to (printed if not given)"
+        )]
+        report: Option<PathBuf>,
+    },
+
+    /// Simplify the programs of an IR corpus in place, keeping only behavior-preserving changes
+    Simplify {
+        #[arg(long, help = "Path to the corpus directory to simplify")]
+        corpus: PathBuf,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Only report how much the programs can be simplified, don't rewrite them"
+        )]
+        dry_run: bool,
+    },
+
+    /// Lift testcases of a byte based scenario into equivalent IR programs
+    Lift {
+        #[arg(long, help = "Scenario the testcases were written for", value_enum, default_value_t = LiftFormat::Generic)]
+        from: LiftFormat,
+        #[arg(
+            long,
+            help = "Path to the program context file dumped by the IR scenario"
+        )]
+        context: PathBuf,
+        #[arg(long, help = "Path to the input testcase file/directory")]
+        input: PathBuf,
+        #[arg(long, help = "Path to the output file/directory for the lifted IR")]
+        output: PathBuf,
+    },
 }
 
 #[derive(ValueEnum, Debug, Clone)]
 pub enum CorpusFormat {
     Json,
     Postcard, // Default corpus format (https://github.com/jamesmunns/postcard)
+    /// Human readable IR as printed by `ir print` (output only)
+    Text,
+    /// Compiled program as run by scenarios built without `compile_in_vm` (output only)
+    Compiled,
+}
+
+impl CorpusFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            CorpusFormat::Json => "json",
+            CorpusFormat::Postcard => "ir",
+            CorpusFormat::Text => "txt",
+            CorpusFormat::Compiled => "prog",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum LiftFormat {
+    /// Testcases of the generic scenario (`scenario-generic`)
+    Generic,
 }
 
 pub fn generate_ir(
@@ -139,62 +254,155 @@ pub fn generate_ir(
     }
 
     for _ in 0..programs {
-        let mut used_generators = Vec::new();
-        let mut program = Program::unchecked_new(context.context.clone(), vec![]);
-
-        let mut insertion_index = 0;
-        for _i in 0..rng.gen_range(1..iterations) {
-            let mut builder = ProgramBuilder::new(program.context.clone());
-            if !program.instructions.is_empty() {
-                let instrs = &program.instructions[..insertion_index];
-                builder.append_all(instrs.iter().cloned()).unwrap();
-            }
+        let steps = rng.gen_range(1..iterations);
+        let (program, used_generators) =
+            generate_program(&context, &generators, None, steps, &mut rng);
 
-            let variable_threshold = builder.variable_count();
+        let file_name = output.join(format!("{:8x}.ir", rng.r#gen::<u64>()));
+        let bytes = postcard::to_allocvec(&program)?;
+        std::fs::write(&file_name, &bytes)?;
 
-            let generator = generators.choose(&mut rng).unwrap();
-            if generator.generate(&mut builder, &mut rng, None).is_err() {
-                continue;
-            }
+        log::info!(
+            "Generated IR: {} ({:?})",
+            file_name.display(),
+            used_generators.join("-")
+        );
+    }
 
-            used_generators.push(generator.name().to_string());
+    Ok(())
+}
 
-            let second_half = Program::unchecked_new(
-                builder.context().clone(),
-                program.instructions[insertion_index..].to_vec(),
-            );
+/// Build a program by running `steps` randomly picked generators (starting with `first`, if
+/// given), each inserting its instructions at a random position of the program built so far.
+/// Returns the program and the names of the generators that succeeded.
+fn generate_program(
+    context: &FullProgramContext,
+    generators: &[Box<dyn Generator<ThreadRng>>],
+    first: Option<&dyn Generator<ThreadRng>>,
+    steps: usize,
+    rng: &mut ThreadRng,
+) -> (Program, Vec<String>) {
+    let mut used_generators = Vec::new();
+    let mut program = Program::unchecked_new(context.context.clone(), vec![]);
+
+    let mut insertion_index = 0;
+    for step in 0..steps {
+        let mut builder = ProgramBuilder::new(program.context.clone());
+        if !program.instructions.is_empty() {
+            let instrs = &program.instructions[..insertion_index];
+            builder.append_all(instrs.iter().cloned()).unwrap();
+        }
+
+        let variable_threshold = builder.variable_count();
 
-            builder
-                .append_program(
-                    second_half,
-                    variable_threshold,
-                    builder.variable_count() - variable_threshold,
-                )
-                .unwrap();
-
-            program = builder.finalize().unwrap();
-            insertion_index = program
-                .get_random_instruction_index(&mut rng, &InstructionContext::Global)
-                .unwrap()
-                .max(1);
+        let generator = match first {
+            Some(first) if step == 0 => first,
+            _ => generators.choose(rng).unwrap().as_ref(),
+        };
+        if generator.generate(&mut builder, rng, None).is_err() {
+            continue;
         }
 
-        let file_name = output.join(format!("{:8x}.ir", rng.r#gen::<u64>()));
-        let bytes = postcard::to_allocvec(&program)?;
-        std::fs::write(&file_name, &bytes)?;
+        used_generators.push(generator.name().to_string());
 
-        log::info!(
+        let second_half = Program::unchecked_new(
+            builder.context().clone(),
+            program.instructions[insertion_index..].to_vec(),
+        );
+
+        builder
+            .append_program(
+                second_half,
+                variable_threshold,
+                builder.variable_count() - variable_threshold,
+            )
+            .unwrap();
+
+        program = builder.finalize().unwrap();
+        insertion_index = program
+            .get_random_instruction_index(rng, &InstructionContext::Global)
+            .unwrap()
+            .max(1);
+    }
+
+    (program, used_generators)
+}
+
+/// Generate an initial corpus of `count` distinct programs into `output`. Each generator starts
+/// an equal share of the programs (followed by up to `iterations - 1` random ones), so that every
+/// generator is represented. Programs that are not statically valid or semantically the same as
+/// an earlier one are dropped.
+pub fn seed_corpus(
+    context: &Path,
+    output: &Path,
+    count: usize,
+    iterations: usize,
+    generator_names: Option<&Vec<String>>,
+) -> Result<()> {
+    let context = std::fs::read(context)?;
+    let context: FullProgramContext = postcard::from_bytes(&context)?;
+
+    let mut generators = all_generators(&context);
+    if let Some(names) = generator_names {
+        let requested: Vec<_> = names.iter().map(|s| s.to_lowercase()).collect();
+        generators.retain(|g| requested.contains(&g.name().to_lowercase()));
+    }
+    if generators.is_empty() {
+        return Err(CliError::InvalidInput(
+            "No generators matched the names provided".to_string(),
+        ));
+    }
+    if iterations == 0 {
+        return Err(CliError::InvalidInput(
+            "Iterations must be greater than 0".to_string(),
+        ));
+    }
+
+    std::fs::create_dir_all(output)?;
+
+    let mut rng = rand::thread_rng();
+    let mut seen = HashSet::new();
+    let mut attempts = 0;
+    // Give up eventually, e.g. if the generators can only produce a few distinct programs
+    let max_attempts = count.saturating_mul(10);
+    while seen.len() < count && attempts < max_attempts {
+        let first = generators[attempts % generators.len()].as_ref();
+        attempts += 1;
+
+        let steps = rng.gen_range(1..=iterations);
+        let (mut program, used_generators) =
+            generate_program(&context, &generators, Some(first), steps, &mut rng);
+        if program.instructions.is_empty() || !program.is_statically_valid() {
+            continue;
+        }
+        program.remove_nops();
+        let hash = program.semantic_hash();
+        if !seen.insert(hash) {
+            continue;
+        }
+
+        let file_name = output.join(format!("{hash:016x}.ir"));
+        std::fs::write(&file_name, postcard::to_allocvec(&program)?)?;
+        log::debug!(
             "Generated IR: {} ({:?})",
             file_name.display(),
             used_generators.join("-")
         );
     }
 
+    log::info!(
+        "Generated {} programs in {attempts} attempts into {}",
+        seen.len(),
+        output.display()
+    );
+    if seen.len() < count {
+        log::warn!("Only {} of {count} programs are distinct", seen.len());
+    }
     Ok(())
 }
 
 fn all_generators(context: &FullProgramContext) -> Vec<Box<dyn Generator<ThreadRng>>> {
-    vec![
+    let mut generators: Vec<Box<dyn Generator<ThreadRng>>> = vec![
         Box::new(AdvanceTimeGenerator::default()),
         Box::new(HeaderGenerator::new(context.headers.clone())),
         Box::new(BlockGenerator::default()),
@@ -209,21 +417,42 @@ fn all_generators(context: &FullProgramContext) -> Vec<Box<dyn Generator<ThreadR
         Box::new(SendMessageGenerator::default()),
         Box::new(WitnessGenerator::new()),
         Box::new(SingleTxGenerator),
-        Box::new(OneParentOneChildGenerator),
         Box::new(LongChainGenerator),
         Box::new(LargeTxGenerator),
         Box::new(TxoGenerator::new(context.txos.clone())),
         Box::new(AddrRelayGenerator::default()),
         Box::new(AddrRelayV2Generator::default()),
         Box::new(GetAddrGenerator),
-    ]
+        Box::new(RawFrameGenerator::new(context.magic)),
+        Box::new(DribbleMessageGenerator),
+        Box::new(RestartNodeGenerator),
+    ];
+    // 1P1C packages are only accepted by targets that support package relay
+    if context.capabilities.package_relay {
+        generators.push(Box::new(OneParentOneChildGenerator));
+    }
+    generators
+}
+
+/// Read an IR program from disk, decoding it as JSON if the file has a `.json` extension and as
+/// postcard otherwise.
+pub fn read_program(path: &Path) -> Result<Program> {
+    let bytes = std::fs::read(path)?;
+    decode_program(path, &bytes)
+}
+
+fn decode_program(path: &Path, bytes: &[u8]) -> Result<Program> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        Ok(Program::from_json(bytes)?)
+    } else {
+        Ok(postcard::from_bytes(bytes)?)
+    }
 }
 
 fn compile_ir_file(input: &PathBuf, output: &PathBuf) -> Result<()> {
     assert!(input.is_file());
 
-    let bytes = std::fs::read(input)?;
-    let program: Program = postcard::from_bytes(&bytes)?;
+    let program = read_program(input)?;
 
     let mut compiler = Compiler::new();
     let compiled = compiler.compile(&program).unwrap();
@@ -266,11 +495,10 @@ pub fn compile_ir(input: &PathBuf, output: &PathBuf) -> Result<()> {
 }
 
 pub fn print_ir(input: &PathBuf, json: bool) -> Result<()> {
-    let bytes = std::fs::read(input)?;
-    let program: Program = postcard::from_bytes(&bytes)?;
+    let program = read_program(input)?;
 
     if json {
-        println!("{}", serde_json::to_string(&program)?);
+        println!("{}", program.to_json()?);
     } else {
         println!("{program}");
     }
@@ -283,19 +511,15 @@ fn convert_ir_dir(
     input: &Path,
     output: &Path,
 ) -> Result<()> {
+    std::fs::create_dir_all(output)?;
+
+    let mut converted = 0;
+    let mut failed = 0;
     for entry in input.read_dir()? {
         let path = entry?.path();
         if path.is_file() && !path.file_name().unwrap().to_str().unwrap().starts_with('.') {
             let mut new_path = output.join(path.file_name().unwrap().to_str().unwrap());
-
-            match *to {
-                CorpusFormat::Postcard => {
-                    new_path.set_extension("ir");
-                }
-                CorpusFormat::Json => {
-                    new_path.set_extension("json");
-                }
-            }
+            new_path.set_extension(to.extension());
 
             if let Err(e) = convert_ir_file(from, to, &path, &new_path) {
                 log::warn!(
@@ -303,10 +527,14 @@ fn convert_ir_dir(
                     path.display(),
                     new_path.display()
                 );
+                failed += 1;
+            } else {
+                converted += 1;
             }
         }
     }
 
+    log::info!("Converted {converted} programs ({failed} failed)");
     Ok(())
 }
 
@@ -319,12 +547,24 @@ fn convert_ir_file(
     let bytes = std::fs::read(input)?;
     let program: Program = match *from {
         CorpusFormat::Postcard => postcard::from_bytes(&bytes)?,
-        CorpusFormat::Json => serde_json::from_slice(&bytes)?,
+        CorpusFormat::Json => Program::from_json(&bytes)?,
+        CorpusFormat::Text | CorpusFormat::Compiled => {
+            return Err(CliError::InvalidInput(format!(
+                "Can't convert from {from:?}, it is an output only format"
+            )));
+        }
     };
 
     let bytes = match *to {
         CorpusFormat::Postcard => postcard::to_allocvec(&program)?,
-        CorpusFormat::Json => serde_json::to_vec(&program)?,
+        CorpusFormat::Json => program.to_json()?.into_bytes(),
+        CorpusFormat::Text => program.to_string().into_bytes(),
+        CorpusFormat::Compiled => {
+            let compiled = Compiler::new()
+                .compile(&program)
+                .map_err(|e| CliError::ProcessError(format!("Failed to compile: {e}")))?;
+            postcard::to_allocvec(&compiled)?
+        }
     };
     std::fs::write(output, &bytes)?;
 
@@ -339,7 +579,7 @@ pub fn convert_ir(
 ) -> Result<()> {
     if input.is_file() {
         convert_ir_file(from, to, input, output)?;
-    } else if input.is_dir() && output.is_dir() {
+    } else if input.is_dir() && !output.is_file() {
         convert_ir_dir(from, to, input, output)?;
     } else {
         return Err(CliError::InvalidInput(
@@ -350,6 +590,272 @@ pub fn convert_ir(
     Ok(())
 }
 
+/// Re-encode every program in the corpus `input` with the current IR schema and write it (as
+/// postcard) to `output`, keeping the file names.
+///
+/// Postcard is not self-describing, so programs serialized with an older `Operation`/`Program`
+/// layout either fail to decode or decode with bytes left over, and are dropped. Programs exported
+/// to JSON (`ir convert --to json`) by the older version are decoded by variant and field names
+/// instead, which survives added or reordered operations. Decoded programs are re-validated and
+/// compiled, and the dropped ones are listed in the report.
+pub fn migrate_corpus(input: &Path, output: &Path, report: Option<&Path>) -> Result<()> {
+    let mut files = file_ops::read_dir_files(input)?;
+    files.sort();
+    file_ops::create_dir_all(output)?;
+
+    let mut migrated = 0;
+    let mut dropped = Vec::new();
+    for file in &files {
+        match migrate_program(file) {
+            Ok(program) => {
+                let name = output.join(file.file_name().unwrap()).with_extension("ir");
+                std::fs::write(&name, postcard::to_allocvec(&program)?)?;
+                log::debug!("Migrated {} to {}", file.display(), name.display());
+                migrated += 1;
+            }
+            Err(reason) => dropped.push((file, reason)),
+        }
+    }
+
+    let mut summary = format!(
+        "Migrated {migrated} of {} programs from {} to {}\n",
+        files.len(),
+        input.display(),
+        output.display()
+    );
+    if !dropped.is_empty() {
+        let _ = writeln!(summary, "\nDropped {} programs:", dropped.len());
+        for (file, reason) in &dropped {
+            let _ = writeln!(summary, "  {}: {reason}", file.display());
+        }
+    }
+    if let Some(report) = report {
+        std::fs::write(report, &summary)?;
+        log::info!("Migration report written to: {}", report.display());
+    } else {
+        print!("{summary}");
+    }
+    Ok(())
+}
+
+/// Decode a program with the current IR schema and check that it is still valid.
+fn migrate_program(path: &Path) -> std::result::Result<Program, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read: {e}"))?;
+    let program = if path.extension().is_some_and(|ext| ext == "json") {
+        Program::from_json(&bytes).map_err(|e| format!("Failed to decode json: {e}"))?
+    } else {
+        let (program, rest) = postcard::take_from_bytes::<Program>(&bytes)
+            .map_err(|e| format!("Failed to decode postcard: {e}"))?;
+        if !rest.is_empty() {
+            return Err(format!(
+                "Failed to decode postcard: {} bytes left over (older schema?)",
+                rest.len()
+            ));
+        }
+        program
+    };
+
+    if !program.is_statically_valid() {
+        return Err("Not statically valid".to_string());
+    }
+    Compiler::new()
+        .compile(&program)
+        .map_err(|e| format!("Failed to compile: {e}"))?;
+    Ok(program)
+}
+
+/// Simplify every program in `corpus` in place (see `simplify_program`), keeping the file format.
+/// With `dry_run`, the simplifications are only reported.
+pub fn simplify_corpus(corpus: &Path, dry_run: bool) -> Result<()> {
+    let mut files = file_ops::read_dir_files(corpus)?;
+    files.sort();
+
+    let mut simplified = 0;
+    let (mut instructions_before, mut instructions_after) = (0, 0);
+    for file in &files {
+        let program = match read_program(file) {
+            Ok(program) => program,
+            Err(e) => {
+                log::warn!("Skipping {}: {e}", file.display());
+                continue;
+            }
+        };
+        let Some(simpler) = simplify_program(&program) else {
+            log::warn!("Skipping {}: invalid or does not compile", file.display());
+            continue;
+        };
+        instructions_before += program.instructions.len();
+        instructions_after += simpler.instructions.len();
+        if simpler.instructions.len() == program.instructions.len() {
+            continue;
+        }
+
+        log::debug!(
+            "Simplified {} from {} to {} instructions",
+            file.display(),
+            program.instructions.len(),
+            simpler.instructions.len()
+        );
+        simplified += 1;
+        if !dry_run {
+            let bytes = if file.extension().is_some_and(|ext| ext == "json") {
+                simpler.to_json()?.into_bytes()
+            } else {
+                postcard::to_allocvec(&simpler)?
+            };
+            std::fs::write(file, bytes)?;
+        }
+    }
+
+    log::info!(
+        "{} {simplified} of {} programs ({instructions_before} to {instructions_after} instructions)",
+        if dry_run {
+            "Could simplify"
+        } else {
+            "Simplified"
+        },
+        files.len()
+    );
+    Ok(())
+}
+
+/// Simplify `program` by deduplicating identical loads, removing instructions that don't
+/// contribute to its actions and removing nops. Every change is only kept if the program still
+/// compiles to the exact same actions, i.e. it behaves the same when run against the target.
+///
+/// Returns `None` if `program` is not valid or does not compile.
+fn simplify_program(program: &Program) -> Option<Program> {
+    let actions = compiled_actions(program)?;
+    let preserves_actions =
+        |candidate: &Program| compiled_actions(candidate).is_some_and(|a| a == actions);
+
+    // Index of the first variable defined by each instruction (nopping keeps the indices)
+    let mut variables = Vec::with_capacity(program.instructions.len());
+    let mut variable_count = 0;
+    for instr in &program.instructions {
+        variables.push(variable_count);
+        variable_count += instr.operation.num_outputs() + instr.operation.num_inner_outputs();
+    }
+
+    // Use the first of several identical loads in place of the later ones
+    let mut current = program.clone();
+    let mut loads: Vec<(&Operation, usize)> = Vec::new();
+    for (index, instr) in program.instructions.iter().enumerate() {
+        if !instr.inputs.is_empty()
+            || instr.operation.num_outputs() != 1
+            || instr.operation.num_inner_outputs() != 0
+            || matches!(instr.operation, Operation::Nop { .. })
+        {
+            continue;
+        }
+        let variable = variables[index];
+        let Some(first) = loads
+            .iter()
+            .find(|(operation, _)| **operation == instr.operation)
+            .map(|(_, first)| *first)
+        else {
+            loads.push((&instr.operation, variable));
+            continue;
+        };
+
+        let mut candidate = current.clone();
+        candidate.instructions[index].nop();
+        for later in &mut candidate.instructions[index + 1..] {
+            for input in &mut later.inputs {
+                if *input == variable {
+                    *input = first;
+                }
+            }
+        }
+        if preserves_actions(&candidate) {
+            current = candidate;
+        }
+    }
+
+    // Dead code elimination: nop every instruction that can go without changing the actions
+    let mut minimizer = NoppingMinimizer::new(current.clone());
+    while let Some(candidate) = minimizer.next() {
+        if preserves_actions(&candidate) {
+            current = candidate;
+            minimizer.success();
+        } else {
+            minimizer.failure();
+        }
+    }
+
+    current.remove_nops();
+    Some(current)
+}
+
+/// The actions `program` compiles to, serialized (as they can't be compared directly). `None` if
+/// the program is not valid or does not compile.
+fn compiled_actions(program: &Program) -> Option<Vec<u8>> {
+    if !program.is_statically_valid() {
+        return None;
+    }
+    let compiled = Compiler::new().compile(program).ok()?;
+    postcard::to_allocvec(&compiled.actions).ok()
+}
+
+/// Lift the testcase(s) `input` of the byte based scenario `from` into equivalent IR programs in
+/// `output`, for the program context `context`. When lifting a directory, testcases that can't be
+/// lifted are skipped and the programs are named after their semantic hash (dropping duplicates).
+pub fn lift_ir(from: LiftFormat, context: &Path, input: &Path, output: &Path) -> Result<()> {
+    let context = std::fs::read(context)?;
+    let context: FullProgramContext = postcard::from_bytes(&context)?;
+
+    if input.is_file() {
+        let program =
+            lift_testcase(from, &context.context, &std::fs::read(input)?).map_err(|e| {
+                CliError::InvalidInput(format!("Failed to lift {}: {e}", input.display()))
+            })?;
+        std::fs::write(output, postcard::to_allocvec(&program)?)?;
+        return Ok(());
+    }
+
+    let mut files = file_ops::read_dir_files(input)?;
+    files.sort();
+    file_ops::create_dir_all(output)?;
+
+    let mut seen = HashSet::new();
+    let mut failed = 0;
+    for file in &files {
+        match lift_testcase(from, &context.context, &std::fs::read(file)?) {
+            Ok(program) => {
+                let hash = program.semantic_hash();
+                if seen.insert(hash) {
+                    std::fs::write(
+                        output.join(format!("{hash:016x}.ir")),
+                        postcard::to_allocvec(&program)?,
+                    )?;
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to lift {}: {e}", file.display());
+                failed += 1;
+            }
+        }
+    }
+
+    log::info!(
+        "Lifted {} programs from {} testcases ({failed} failed) into {}",
+        seen.len(),
+        files.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn lift_testcase(
+    from: LiftFormat,
+    context: &ProgramContext,
+    bytes: &[u8],
+) -> std::result::Result<Program, String> {
+    match from {
+        LiftFormat::Generic => lift::lift_generic(context, bytes),
+    }
+}
+
 struct Point {
     ir_size: usize,
     compiled_size: usize,
@@ -446,7 +952,7 @@ pub fn analyze_ir(input: &Path) -> Result<()> {
         if path.is_file() && !path.file_name().unwrap().to_str().unwrap().starts_with('.') {
             // Read and parse the IR file
             let bytes = std::fs::read(&path)?;
-            if let Ok(program) = postcard::from_bytes::<fuzzamoto_ir::Program>(&bytes) {
+            if let Ok(program) = decode_program(&path, &bytes) {
                 // Count instructions
                 let instr_count = program.instructions.len();
                 let bucket = instr_count / INSTRUCTIONS_BUCKET_SIZE;
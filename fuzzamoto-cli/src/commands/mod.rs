@@ -1,8 +1,28 @@
+pub mod corpus;
 pub mod coverage;
 pub mod coverage_batch;
+pub mod coverage_diff;
+pub mod fuzz;
 pub mod init;
 pub mod ir;
+pub mod monitor;
+pub mod remote_daemon;
+pub mod replay;
+pub mod replay_trace;
+pub mod run_corpus;
+pub mod tmin;
+pub mod verify_sharedir;
 
+pub use corpus::CorpusCommand;
 pub use coverage::CoverageCommand;
+pub use coverage_diff::CoverageDiffCommand;
+pub use fuzz::FuzzCommand;
 pub use init::InitCommand;
 pub use ir::IrCommand;
+pub use monitor::MonitorCommand;
+pub use remote_daemon::RemoteDaemonCommand;
+pub use replay::ReplayCommand;
+pub use replay_trace::ReplayTraceCommand;
+pub use run_corpus::RunCorpusCommand;
+pub use tmin::TminCommand;
+pub use verify_sharedir::VerifySharedirCommand;
@@ -0,0 +1,197 @@
+use crate::error::{CliError, Result};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub struct MonitorCommand;
+
+impl MonitorCommand {
+    /// Show a live dashboard of the `fuzzamoto-libafl` campaign writing to `output`, refreshed
+    /// every `interval`. With `once`, the dashboard is printed a single time.
+    ///
+    /// Corpus sizes and crashes are read from the per-core output directories (`cpu_NNN`), the
+    /// executions and coverage from the bench stats (`bench/bench-cpu_NNN.csv`, written by
+    /// fuzzers built with the `bench` feature).
+    pub fn execute(output: &Path, interval: Duration, once: bool) -> Result<()> {
+        if !output.is_dir() {
+            return Err(CliError::FileNotFound(output.display().to_string()));
+        }
+
+        loop {
+            let dashboard = render(output, &collect(output)?);
+            if once {
+                print!("{dashboard}");
+                return Ok(());
+            }
+            // Clear the screen and move the cursor to the top left before redrawing
+            print!("\x1b[2J\x1b[H{dashboard}");
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+/// Stats of a single fuzzer client
+struct CoreStats {
+    core: String,
+    corpus: usize,
+    crashes: usize,
+    /// File name and modification time of the newest crash
+    last_crash: Option<(String, SystemTime)>,
+    /// Last row of the bench stats, if any
+    bench: Option<BenchRow>,
+}
+
+struct BenchRow {
+    elapsed_secs: f64,
+    execs: u64,
+    execs_per_sec: f64,
+    coverage_pct: f64,
+}
+
+fn collect(output: &Path) -> Result<Vec<CoreStats>> {
+    let mut cores: Vec<PathBuf> = std::fs::read_dir(output)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("cpu_"))
+        })
+        .collect();
+    cores.sort();
+
+    Ok(cores
+        .iter()
+        .map(|dir| {
+            let core = dir.file_name().unwrap().to_string_lossy().into_owned();
+            let crashes = testcases(&dir.join("crashes"));
+            let last_crash = crashes
+                .iter()
+                .filter_map(|path| {
+                    let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+                    let name = path.file_name()?.to_string_lossy().into_owned();
+                    Some((name, modified))
+                })
+                .max_by_key(|(_, modified)| *modified);
+            let bench = read_bench_row(&output.join("bench").join(format!("bench-{core}.csv")));
+            CoreStats {
+                corpus: testcases(&dir.join("queue")).len(),
+                crashes: crashes.len(),
+                last_crash,
+                bench,
+                core,
+            }
+        })
+        .collect())
+}
+
+/// Testcases in a libafl corpus directory, skipping its metadata and lock files (hidden) and the
+/// crash contexts stored next to crashes.
+fn testcases(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| !name.starts_with('.') && !name.ends_with(".context.json"))
+        })
+        .collect()
+}
+
+/// Parse the last row of a bench stats CSV file, looking up the columns by their header.
+fn read_bench_row(path: &Path) -> Option<BenchRow> {
+    let csv = std::fs::read_to_string(path).ok()?;
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines.next()?.split(',').collect();
+    let row: Vec<&str> = lines.next_back()?.split(',').collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| *column == name)
+            .and_then(|index| row.get(index))
+    };
+
+    Some(BenchRow {
+        elapsed_secs: column("elapsed_s")?.parse().ok()?,
+        execs: column("execs")?.parse().ok()?,
+        execs_per_sec: column("execs_per_sec")?.parse().ok()?,
+        coverage_pct: column("coverage_pct")?.parse().ok()?,
+    })
+}
+
+fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |age| age.as_secs());
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        _ => format!("{}h{:02}m ago", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn render(output: &Path, cores: &[CoreStats]) -> String {
+    let mut dashboard = String::new();
+    let _ = writeln!(dashboard, "fuzzamoto monitor: {}\n", output.display());
+
+    if cores.is_empty() {
+        let _ = writeln!(dashboard, "No fuzzer clients found (yet)");
+        return dashboard;
+    }
+
+    let _ = writeln!(
+        dashboard,
+        "{:<8} {:>10} {:>12} {:>10} {:>8} {:>8} {:>8}  last crash",
+        "core", "uptime", "execs", "execs/s", "cov", "corpus", "crashes"
+    );
+    for stats in cores {
+        let (uptime, execs, execs_per_sec, coverage) = match &stats.bench {
+            Some(bench) => (
+                format!("{:.0}s", bench.elapsed_secs),
+                bench.execs.to_string(),
+                format!("{:.1}", bench.execs_per_sec),
+                format!("{:.3}%", bench.coverage_pct),
+            ),
+            None => ("-".into(), "-".into(), "-".into(), "-".into()),
+        };
+        let last_crash = stats.last_crash.as_ref().map_or_else(
+            || "-".to_string(),
+            |(name, time)| format!("{name} ({})", format_age(*time)),
+        );
+        let _ = writeln!(
+            dashboard,
+            "{:<8} {uptime:>10} {execs:>12} {execs_per_sec:>10} {coverage:>8} {:>8} {:>8}  {last_crash}",
+            stats.core, stats.corpus, stats.crashes
+        );
+    }
+
+    let benches: Vec<&BenchRow> = cores.iter().filter_map(|s| s.bench.as_ref()).collect();
+    let execs: u64 = benches.iter().map(|bench| bench.execs).sum();
+    let execs_per_sec: f64 = benches.iter().map(|bench| bench.execs_per_sec).sum();
+    let coverage = benches
+        .iter()
+        .map(|bench| bench.coverage_pct)
+        .fold(0.0, f64::max);
+    let _ = writeln!(
+        dashboard,
+        "{:<8} {:>10} {execs:>12} {execs_per_sec:>10.1} {:>8} {:>8} {:>8}",
+        "total",
+        "",
+        format!("{coverage:.3}%"),
+        cores.iter().map(|s| s.corpus).sum::<usize>(),
+        cores.iter().map(|s| s.crashes).sum::<usize>(),
+    );
+    if benches.is_empty() {
+        let _ = writeln!(
+            dashboard,
+            "\nNo bench stats found, build fuzzamoto-libafl with the bench feature to see executions and coverage"
+        );
+    }
+    dashboard
+}
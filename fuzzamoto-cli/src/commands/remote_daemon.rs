@@ -0,0 +1,34 @@
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use fuzzamoto::remote::RemoteDaemon;
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+
+pub struct RemoteDaemonCommand;
+
+impl RemoteDaemonCommand {
+    /// Serve test cases from remote fuzzers on `listen` (see `fuzzamoto::remote`).
+    pub fn execute(
+        scenario: &Path,
+        bitcoind: &Path,
+        listen: &str,
+        map_size: usize,
+        max_timeout_ms: u64,
+    ) -> Result<()> {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+
+        let listener = TcpListener::bind(listen)?;
+        log::info!("Listening for fuzzers on {}", listener.local_addr()?);
+
+        RemoteDaemon {
+            scenario: scenario.to_path_buf(),
+            bitcoind: bitcoind.to_path_buf(),
+            map_size,
+            max_timeout: Duration::from_millis(max_timeout_ms),
+        }
+        .serve(&listener)
+        .map_err(CliError::ProcessError)
+    }
+}
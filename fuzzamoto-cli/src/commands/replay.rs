@@ -0,0 +1,91 @@
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use fuzzamoto::runners::{Outcome, Verdict};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+
+pub struct ReplayCommand;
+
+impl ReplayCommand {
+    /// Run `input` once against `bitcoind` with a scenario built without nyx, and print the
+    /// `Verdict` reported by its `ReplayRunner`, optionally recording a trace of the run to
+    /// `record_trace`.
+    pub fn execute(
+        scenario: &Path,
+        bitcoind: &Path,
+        input: &Path,
+        node_args: Option<&str>,
+        record_trace: Option<&Path>,
+    ) -> Result<()> {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+        file_ops::ensure_file_exists(input)?;
+
+        let mut cmd = Self::command(scenario, bitcoind, input, node_args);
+        cmd.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        // Full logging, unless the caller asked for something else
+        if std::env::var("RUST_LOG").is_err() {
+            cmd.env("RUST_LOG", "debug");
+        }
+        if let Some(record_trace) = record_trace {
+            cmd.env("FUZZAMOTO_RECORD_TRACE", record_trace);
+        }
+
+        log::info!("Replaying {} with {}", input.display(), scenario.display());
+        let verdict = Self::run(&mut cmd)?;
+
+        println!("{}", verdict.to_json().map_err(CliError::ProcessError)?);
+
+        match verdict.outcome {
+            Outcome::Pass | Outcome::Skip => Ok(()),
+            Outcome::Fail => Err(CliError::ProcessError(format!(
+                "Test case failed: {}",
+                verdict.message.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Command running `input` once against `bitcoind` with `scenario` (see `run`).
+    pub fn command(
+        scenario: &Path,
+        bitcoind: &Path,
+        input: &Path,
+        node_args: Option<&str>,
+    ) -> Command {
+        let mut cmd = Command::new(scenario);
+        cmd.arg(bitcoind).env("FUZZAMOTO_INPUT", input);
+        if let Some(node_args) = node_args {
+            cmd.env("FUZZAMOTO_NODE_ARGS", node_args);
+        }
+        cmd
+    }
+
+    /// Run a scenario `cmd` to completion and return the `Verdict` reported by its runner.
+    pub fn run(cmd: &mut Command) -> Result<Verdict> {
+        let (status, verdicts) = Self::run_verdicts(cmd)?;
+        verdicts.into_iter().next_back().ok_or_else(|| {
+            CliError::ProcessError(format!(
+                "Scenario did not report a verdict (exit code: {}), it likely failed to initialize",
+                status.code().unwrap_or(-1)
+            ))
+        })
+    }
+
+    /// Run a scenario `cmd` to completion and return its exit status and the `Verdict`s reported
+    /// by its runner, one per test case that ran (in order).
+    pub fn run_verdicts(cmd: &mut Command) -> Result<(ExitStatus, Vec<Verdict>)> {
+        let verdict_path =
+            std::env::temp_dir().join(format!("fuzzamoto-verdict-{}.json", std::process::id()));
+        // Left over from an earlier run with the same pid
+        let _ = std::fs::remove_file(&verdict_path);
+
+        let status = cmd.env("FUZZAMOTO_VERDICT", &verdict_path).status()?;
+
+        let verdicts = match std::fs::read_to_string(&verdict_path) {
+            Ok(verdicts) => Verdict::from_lines(&verdicts).map_err(CliError::ProcessError)?,
+            Err(_) => Vec::new(),
+        };
+        let _ = std::fs::remove_file(&verdict_path);
+        Ok((status, verdicts))
+    }
+}
@@ -0,0 +1,30 @@
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use fuzzamoto::targets::TargetNode;
+use fuzzamoto::trace::Trace;
+use std::path::Path;
+
+pub struct ReplayTraceCommand;
+
+impl ReplayTraceCommand {
+    /// Replay the `Trace` recorded by a scenario (see `fuzzamoto::trace`) against `bitcoind`, which
+    /// may be a wrapper script running the node under a debugger (e.g. `rr record`).
+    pub fn execute(bitcoind: &Path, trace: &Path) -> Result<()> {
+        file_ops::ensure_file_exists(bitcoind)?;
+        file_ops::ensure_file_exists(trace)?;
+
+        let trace =
+            Trace::from_json(&std::fs::read_to_string(trace)?).map_err(CliError::InvalidInput)?;
+        log::info!("Replaying {} trace events", trace.events().len());
+
+        let target = trace
+            .replay(&bitcoind.to_string_lossy())
+            .map_err(CliError::ProcessError)?;
+        target
+            .is_alive()
+            .map_err(|e| CliError::ProcessError(format!("Node did not survive the trace: {e}")))?;
+
+        log::info!("Node is still alive after replaying the trace");
+        Ok(())
+    }
+}
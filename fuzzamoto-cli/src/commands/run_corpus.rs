@@ -0,0 +1,209 @@
+use crate::commands::ReplayCommand;
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use fuzzamoto::runners::{Outcome, Verdict};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+
+pub struct RunCorpusCommand;
+
+/// Counts of the outcomes, failing oracles and failing assertions of the testcases
+#[derive(Default)]
+struct Counts {
+    outcomes: BTreeMap<String, usize>,
+    oracles: BTreeMap<String, usize>,
+    assertions: BTreeMap<String, usize>,
+}
+
+impl RunCorpusCommand {
+    /// Run every testcase in `corpus` once against `bitcoind` with a scenario built without nyx
+    /// and write a JSON report of their verdicts and run times to `output`.
+    ///
+    /// All testcases run in one scenario process (see `run_batch`), with the target's state reset
+    /// in between testcases from a snapshot of the node's data directory taken after the scenario
+    /// setup (see `FUZZAMOTO_DATADIR_SNAPSHOT`), such that the setup only runs once. If the
+    /// scenario exits early (e.g. a testcase crashed the scenario itself), the remaining
+    /// testcases run in a new process.
+    pub fn execute(
+        scenario: &Path,
+        bitcoind: &Path,
+        corpus: &Path,
+        output: &Path,
+        node_args: Option<&str>,
+    ) -> Result<()> {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+
+        let mut testcases = file_ops::read_dir_files(corpus)?;
+        if testcases.is_empty() {
+            return Err(CliError::InvalidInput("Empty corpus directory".to_string()));
+        }
+        testcases.sort();
+
+        let tmp = std::env::temp_dir();
+        let snapshot = tmp.join(format!("fuzzamoto-corpus-snapshot-{}", std::process::id()));
+        let inputs = tmp.join(format!("fuzzamoto-corpus-inputs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&snapshot);
+
+        let mut results = Vec::with_capacity(testcases.len());
+        let mut counts = Counts::default();
+        let mut next = 0;
+        while next < testcases.len() {
+            log::info!(
+                "Running testcases {}-{} of {}",
+                next + 1,
+                testcases.len(),
+                testcases.len()
+            );
+            let batch = Self::run_batch(
+                scenario,
+                bitcoind,
+                &testcases[next..],
+                node_args,
+                &snapshot,
+                &inputs,
+            );
+            let (status, verdicts) = match batch {
+                Ok(batch) => batch,
+                Err(e) => {
+                    let _ = std::fs::remove_dir_all(&snapshot);
+                    return Err(e);
+                }
+            };
+
+            if verdicts.is_empty() {
+                // The snapshot is created once the setup is done, without it the setup failed
+                // and no testcase will run
+                if !snapshot.exists() {
+                    return Err(CliError::ProcessError(format!(
+                        "Scenario failed to initialize (exit code: {})",
+                        status.code().unwrap_or(-1)
+                    )));
+                }
+                let testcase = &testcases[next];
+                let error = format!(
+                    "Scenario exited without a verdict (exit code: {})",
+                    status.code().unwrap_or(-1)
+                );
+                log::warn!("Failed to run {}: {error}", testcase.display());
+                *counts.outcomes.entry("Error".to_string()).or_default() += 1;
+                results.push(serde_json::json!({
+                    "input": testcase,
+                    "error": error,
+                }));
+                next += 1;
+                continue;
+            }
+
+            for verdict in verdicts {
+                results.push(counts.record(&testcases[next], verdict));
+                next += 1;
+            }
+        }
+        let _ = std::fs::remove_dir_all(&snapshot);
+
+        let Counts {
+            outcomes,
+            oracles,
+            assertions,
+        } = counts;
+        let report = serde_json::json!({
+            "outcomes": outcomes,
+            "oracles": oracles,
+            "assertions": assertions,
+            "testcases": results,
+        });
+        std::fs::write(output, serde_json::to_string_pretty(&report)?)?;
+        log::info!(
+            "Outcomes: {outcomes:?}, oracle failures: {oracles:?}, assertion failures: {assertions:?}"
+        );
+        log::info!("Report written to {}", output.display());
+
+        match outcomes.get(&format!("{:?}", Outcome::Fail)) {
+            Some(failed) => Err(CliError::ProcessError(format!(
+                "{failed} testcase(s) failed"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Run `testcases` in order in a single scenario process, returning the verdicts of the
+    /// testcases that ran before the scenario exited.
+    ///
+    /// The testcases are linked into the directory `inputs` (named by their index, as the
+    /// `LocalRunner` runs a directory's inputs in order of their names) and the scenario keeps
+    /// going past failing testcases (see `FUZZAMOTO_KEEP_GOING`).
+    fn run_batch(
+        scenario: &Path,
+        bitcoind: &Path,
+        testcases: &[PathBuf],
+        node_args: Option<&str>,
+        snapshot: &Path,
+        inputs: &Path,
+    ) -> Result<(ExitStatus, Vec<Verdict>)> {
+        let _ = std::fs::remove_dir_all(inputs);
+        std::fs::create_dir_all(inputs)?;
+        for (i, testcase) in testcases.iter().enumerate() {
+            std::os::unix::fs::symlink(
+                std::path::absolute(testcase)?,
+                inputs.join(format!("{i:06}")),
+            )?;
+        }
+
+        let mut cmd = ReplayCommand::command(scenario, bitcoind, inputs, node_args);
+        cmd.env("FUZZAMOTO_DATADIR_SNAPSHOT", snapshot)
+            .env("FUZZAMOTO_KEEP_GOING", "true")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let batch = ReplayCommand::run_verdicts(&mut cmd);
+        let _ = std::fs::remove_dir_all(inputs);
+        batch
+    }
+
+    /// Name of the oracle that failed, from a failure message like
+    /// `Test case failed: CRASH: NAME; details`
+    pub fn oracle_name(message: &str) -> Option<&str> {
+        let (_, crash) = message.split_once("CRASH: ")?;
+        let (name, _) = crash.split_once(';')?;
+        Some(name)
+    }
+
+    /// Names of the assertions that failed, from a failure message of the assertion oracle like
+    /// `Test case failed: CRASH: ASSERTION; Assertions failed: name: details; name: details`
+    fn assertion_names(message: &str) -> Vec<&str> {
+        let Some((_, failures)) = message.split_once("Assertions failed: ") else {
+            return Vec::new();
+        };
+        failures
+            .split("; ")
+            .filter_map(|failure| failure.split_once(": ").map(|(name, _)| name))
+            .collect()
+    }
+}
+
+impl Counts {
+    /// Count the `verdict` of `testcase` and return its entry in the report
+    fn record(&mut self, testcase: &Path, verdict: Verdict) -> serde_json::Value {
+        *self
+            .outcomes
+            .entry(format!("{:?}", verdict.outcome))
+            .or_default() += 1;
+        let message = verdict.message.as_deref().unwrap_or_default();
+        let oracle = RunCorpusCommand::oracle_name(message);
+        if let Some(oracle) = oracle {
+            *self.oracles.entry(oracle.to_string()).or_default() += 1;
+            if oracle == "ASSERTION" {
+                for assertion in RunCorpusCommand::assertion_names(message) {
+                    *self.assertions.entry(assertion.to_string()).or_default() += 1;
+                }
+            }
+        }
+        serde_json::json!({
+            "input": testcase,
+            "duration_ms": verdict.metrics.as_ref().map(|metrics| metrics.duration_micros / 1000),
+            "oracle": oracle,
+            "verdict": verdict,
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use crate::commands::ir::read_program;
+use crate::commands::{ReplayCommand, RunCorpusCommand};
+use crate::error::{CliError, Result};
+use crate::utils::file_ops;
+use fuzzamoto::runners::Outcome;
+use fuzzamoto_ir::compiler::Compiler;
+use fuzzamoto_ir::{
+    Minimizer, Program, cutting::CuttingMinimizer, instr_block::InstrBlockMinimizer,
+    nopping::NoppingMinimizer,
+};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+pub struct TminCommand;
+
+impl TminCommand {
+    /// Minimize the crashing IR program `input` with the IR minimization passes, keeping every
+    /// reduction that still fails with the same oracle when run against `bitcoind` with a scenario
+    /// built without nyx. The smallest program is written to `output`, its disassembly next to it
+    /// (`<output>.txt`).
+    ///
+    /// Programs are passed to the scenario as is (for scenarios built with `compile_in_vm`), or
+    /// compiled first if `compiled` is set.
+    pub fn execute(
+        scenario: &Path,
+        bitcoind: &Path,
+        input: &Path,
+        output: &Path,
+        node_args: Option<&str>,
+        compiled: bool,
+    ) -> Result<()> {
+        file_ops::ensure_file_exists(scenario)?;
+        file_ops::ensure_file_exists(bitcoind)?;
+        file_ops::ensure_file_exists(input)?;
+
+        let mut program = read_program(input)?;
+        let mut tmin = Tmin {
+            scenario,
+            bitcoind,
+            node_args,
+            compiled,
+            candidate: std::env::temp_dir().join(format!("fuzzamoto-tmin-{}", std::process::id())),
+            oracle: None,
+            executions: 0,
+        };
+
+        // Reductions have to fail the same way as the original program
+        let verdict = tmin.run(&program)?;
+        if verdict.outcome != Outcome::Fail {
+            let _ = std::fs::remove_file(&tmin.candidate);
+            return Err(CliError::InvalidInput(format!(
+                "{} does not fail (outcome: {:?})",
+                input.display(),
+                verdict.outcome
+            )));
+        }
+        tmin.oracle = verdict
+            .message
+            .as_deref()
+            .and_then(RunCorpusCommand::oracle_name)
+            .map(str::to_string);
+        log::info!(
+            "Minimizing {} ({} instructions, oracle: {:?})",
+            input.display(),
+            program.instructions.len(),
+            tmin.oracle
+        );
+
+        // Passes enable each other (e.g. nopping an instruction can make a block removable), so
+        // run all of them until none of them makes progress
+        loop {
+            let before = program.instructions.len();
+            program = tmin.minimize::<CuttingMinimizer>(program)?;
+            program = tmin.minimize::<InstrBlockMinimizer>(program)?;
+            program = tmin.minimize::<NoppingMinimizer>(program)?;
+            if program.instructions.len() >= before {
+                break;
+            }
+        }
+        let _ = std::fs::remove_file(&tmin.candidate);
+
+        let bytes = if output.extension().is_some_and(|ext| ext == "json") {
+            program.to_json()?.into_bytes()
+        } else {
+            postcard::to_allocvec(&program)?
+        };
+        std::fs::write(output, bytes)?;
+        let disassembly = PathBuf::from(format!("{}.txt", output.display()));
+        std::fs::write(&disassembly, program.to_string())?;
+
+        log::info!(
+            "Minimized to {} instructions in {} executions, written to {} ({})",
+            program.instructions.len(),
+            tmin.executions,
+            output.display(),
+            disassembly.display()
+        );
+        Ok(())
+    }
+}
+
+struct Tmin<'a> {
+    scenario: &'a Path,
+    bitcoind: &'a Path,
+    node_args: Option<&'a str>,
+    compiled: bool,
+    /// Path the candidate programs are written to
+    candidate: PathBuf,
+    /// Oracle the original program failed with
+    oracle: Option<String>,
+    executions: usize,
+}
+
+impl Tmin<'_> {
+    /// Run a single minimization pass over `program`, returning the smallest program that still
+    /// reproduces.
+    fn minimize<M: Minimizer>(&mut self, program: Program) -> Result<Program> {
+        let mut minimizer = M::new(program.clone());
+        let mut current = program;
+        while let Some(candidate) = minimizer.next() {
+            if candidate.is_statically_valid() && self.reproduces(&candidate)? {
+                current = candidate;
+                minimizer.success();
+            } else {
+                minimizer.failure();
+            }
+        }
+        current.remove_nops();
+
+        log::info!(
+            "{} reduced program to {} instructions",
+            std::any::type_name::<M>(),
+            current.instructions.len()
+        );
+        Ok(current)
+    }
+
+    fn reproduces(&mut self, program: &Program) -> Result<bool> {
+        match self.run(program) {
+            Ok(verdict) => Ok(verdict.outcome == Outcome::Fail
+                && verdict
+                    .message
+                    .as_deref()
+                    .and_then(RunCorpusCommand::oracle_name)
+                    == self.oracle.as_deref()),
+            Err(CliError::ProcessError(e)) => {
+                log::debug!("Candidate did not run: {e}");
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn run(&mut self, program: &Program) -> Result<fuzzamoto::runners::Verdict> {
+        let bytes = if self.compiled {
+            let compiled = Compiler::new()
+                .compile(program)
+                .map_err(|e| CliError::ProcessError(format!("Failed to compile: {e}")))?;
+            postcard::to_allocvec(&compiled)?
+        } else {
+            postcard::to_allocvec(program)?
+        };
+        std::fs::write(&self.candidate, bytes)?;
+
+        self.executions += 1;
+        let mut cmd = ReplayCommand::command(
+            self.scenario,
+            self.bitcoind,
+            &self.candidate,
+            self.node_args,
+        );
+        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+        ReplayCommand::run(&mut cmd)
+    }
+}
@@ -0,0 +1,205 @@
+use crate::error::{CliError, Result};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Packer binaries the fuzz script fetches from the share directory, besides its dependencies
+const PACKER_BINARIES: [&str; 3] = ["hget", "hcat_no_pt", "habort_no_pt"];
+
+/// Entries of `config.ron` that point to files on the host
+const CONFIG_PATHS: [&str; 4] = [
+    "include_default_config_path",
+    "qemu_binary",
+    "kernel",
+    "ramfs",
+];
+
+pub struct VerifySharedirCommand;
+
+impl VerifySharedirCommand {
+    /// Check that `sharedir` (as created by `init`) contains everything needed to boot the
+    /// scenario and that its nyx config is sane.
+    ///
+    /// With `fuzzer` (a `fuzzamoto-libafl` binary) and `input`, the VM is also booted once to run
+    /// `input`, which fails if the scenario does not start within `timeout`.
+    pub fn execute(
+        sharedir: &Path,
+        fuzzer: Option<&Path>,
+        input: Option<&Path>,
+        timeout: Duration,
+    ) -> Result<()> {
+        if !sharedir.is_dir() {
+            return Err(CliError::FileNotFound(sharedir.display().to_string()));
+        }
+
+        let mut problems = Vec::new();
+        check_script(sharedir, &mut problems);
+        check_config(sharedir, &mut problems);
+        for problem in &problems {
+            log::error!("{problem}");
+        }
+        if !problems.is_empty() {
+            return Err(CliError::InvalidInput(format!(
+                "Found {} problems in {}",
+                problems.len(),
+                sharedir.display()
+            )));
+        }
+        log::info!("All artifacts of {} are present", sharedir.display());
+
+        if let (Some(fuzzer), Some(input)) = (fuzzer, input) {
+            boot(sharedir, fuzzer, input, timeout)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that all files fetched by `fuzz_no_pt.sh` exist and that the scenario, the target and the
+/// crash handler it runs are ELF binaries.
+fn check_script(sharedir: &Path, problems: &mut Vec<String>) {
+    let script = match std::fs::read_to_string(sharedir.join("fuzz_no_pt.sh")) {
+        Ok(script) => script,
+        Err(e) => {
+            problems.push(format!("Failed to read fuzz_no_pt.sh: {e}"));
+            return;
+        }
+    };
+
+    let fetched = script
+        .lines()
+        .filter_map(|line| line.strip_prefix("./hget "))
+        .filter_map(|args| args.split_whitespace().next());
+    for file in PACKER_BINARIES.into_iter().chain(fetched) {
+        if !sharedir.join(file).is_file() {
+            problems.push(format!("Missing {file}"));
+        }
+    }
+
+    // The scenario is run with its output redirected to the log
+    let scenario = script
+        .lines()
+        .find(|line| line.ends_with("> log.txt 2>&1"))
+        .and_then(|line| {
+            line.split_whitespace()
+                .find_map(|word| word.strip_prefix("./"))
+        });
+    let crash_handler = script.lines().find_map(|line| {
+        line.split_whitespace()
+            .find_map(|word| word.strip_prefix("LD_PRELOAD=./"))
+    });
+    for (what, binary) in [
+        ("scenario", scenario),
+        ("bitcoind", Some("bitcoind")),
+        ("crash handler", crash_handler),
+    ] {
+        let Some(binary) = binary else {
+            problems.push(format!("fuzz_no_pt.sh does not run a {what}"));
+            continue;
+        };
+        if !is_elf(&sharedir.join(binary)) {
+            problems.push(format!(
+                "The {what} ({binary}) is missing or not an ELF binary"
+            ));
+        }
+    }
+}
+
+/// Check that `config.ron` exists, that the host files it points to exist and that the VM has
+/// memory.
+fn check_config(sharedir: &Path, problems: &mut Vec<String>) {
+    let config = match std::fs::read_to_string(sharedir.join("config.ron")) {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(format!("Failed to read config.ron: {e}"));
+            return;
+        }
+    };
+
+    for line in config.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_end_matches(',').trim();
+        if CONFIG_PATHS.contains(&key) {
+            let path = value.trim_matches('"');
+            if !Path::new(path).exists() {
+                problems.push(format!("config.ron: {key} does not exist ({path})"));
+            }
+        } else if key == "mem_limit" && !value.parse::<u64>().is_ok_and(|mb| mb > 0) {
+            problems.push(format!("config.ron: invalid mem_limit ({value})"));
+        }
+    }
+}
+
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path).is_ok_and(|mut file| file.read_exact(&mut magic).is_ok())
+        && magic == *b"\x7fELF"
+}
+
+/// Boot the VM once by rerunning `input` with `fuzzer`.
+fn boot(sharedir: &Path, fuzzer: &Path, input: &Path, timeout: Duration) -> Result<()> {
+    let work_dir = std::env::temp_dir().join(format!("fuzzamoto-verify-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&work_dir);
+    std::fs::create_dir_all(work_dir.join("in"))?;
+
+    log::info!("Booting {} with {}", sharedir.display(), input.display());
+    let mut child = Command::new(fuzzer)
+        .arg("--input")
+        .arg(work_dir.join("in"))
+        .arg("--output")
+        .arg(work_dir.join("out"))
+        .arg("--share")
+        .arg(sharedir)
+        .arg("-r")
+        .arg(input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Read stdout concurrently, the fuzzer blocks once the pipe is full
+    let mut stdout_pipe = child.stdout.take();
+    let stdout_reader = std::thread::spawn(move || {
+        let mut stdout = String::new();
+        if let Some(pipe) = &mut stdout_pipe {
+            let _ = pipe.read_to_string(&mut stdout);
+        }
+        stdout
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let _ = std::fs::remove_dir_all(&work_dir);
+
+    let Some(status) = status else {
+        return Err(CliError::ProcessError(format!(
+            "VM did not finish within {}s",
+            timeout.as_secs()
+        )));
+    };
+    match stdout
+        .lines()
+        .find(|line| line.starts_with("Rerun finished"))
+    {
+        Some(line) if status.success() => {
+            log::info!("{line}");
+            Ok(())
+        }
+        _ => Err(CliError::ProcessError(format!(
+            "Failed to boot the VM (exit code: {:?})",
+            status.code()
+        ))),
+    }
+}
@@ -0,0 +1,121 @@
+use crate::error::{CliError, Result};
+use clap::{Arg, Command};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Config file used if no `--config` is given (and it exists)
+const DEFAULT_CONFIG_PATH: &str = "fuzzamoto.toml";
+
+/// Paths shared by many subcommands, read from `fuzzamoto.toml`. Each entry is the default for the
+/// subcommand arguments of the same name, so they don't have to be passed on every invocation.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Path to the nyx instrumented bitcoind binary (`--bitcoind`, except for the coverage
+    /// commands)
+    pub bitcoind: Option<PathBuf>,
+    /// Path to the coverage instrumented bitcoind binary (`--bitcoind` of the coverage commands)
+    pub coverage_bitcoind: Option<PathBuf>,
+    /// Path to the nyx installation (`--nyx-dir`)
+    pub nyx_dir: Option<PathBuf>,
+    /// Path to the crash handler (`--crash-handler`)
+    pub crash_handler: Option<PathBuf>,
+    /// Path to the `fuzzamoto-libafl` binary (`--fuzzer`, and the binary `fuzz` runs)
+    pub fuzzer: Option<PathBuf>,
+    /// Directory relative share directory paths (`--sharedir`) are resolved in
+    pub sharedir_root: Option<PathBuf>,
+}
+
+impl Config {
+    /// The `--config` argument, which is handled before the other arguments are parsed (see
+    /// `path_from_args`).
+    #[must_use]
+    pub fn arg() -> Arg {
+        Arg::new("config")
+            .long("config")
+            .global(true)
+            .value_name("PATH")
+            .help(format!(
+                "Path to the config file with default paths (defaults to {DEFAULT_CONFIG_PATH} if it exists)"
+            ))
+    }
+
+    /// Find the `--config` argument in the command line, as it provides the defaults of the other
+    /// arguments it has to be known before parsing them.
+    #[must_use]
+    pub fn path_from_args() -> Option<PathBuf> {
+        let mut args = std::env::args_os().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                return args.next().map(PathBuf::from);
+            }
+            if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+                return Some(PathBuf::from(path));
+            }
+        }
+        None
+    }
+
+    /// Load the config from `path`, or from `fuzzamoto.toml` in the working directory if no path
+    /// is given. Without either, all entries are unset.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => Path::new(DEFAULT_CONFIG_PATH),
+            None => return Ok(Self::default()),
+        };
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            CliError::InvalidInput(format!("Failed to read {}: {e}", path.display()))
+        })?;
+        let config = toml::from_str(&contents).map_err(|e| {
+            CliError::InvalidInput(format!("Failed to parse {}: {e}", path.display()))
+        })?;
+        log::debug!("Loaded config from {}: {config:?}", path.display());
+        Ok(config)
+    }
+
+    /// Use the configured paths as the defaults of the arguments with the same name in `command`
+    /// and all of its subcommands.
+    #[must_use]
+    pub fn apply_defaults(&self, mut command: Command) -> Command {
+        let defaults = [
+            ("bitcoind", &self.bitcoind),
+            ("coverage_bitcoind", &self.coverage_bitcoind),
+            ("nyx_dir", &self.nyx_dir),
+            ("crash_handler", &self.crash_handler),
+            ("fuzzer", &self.fuzzer),
+        ];
+        for (id, path) in defaults {
+            let Some(path) = path else {
+                continue;
+            };
+            // Skip arguments that share the name but don't take a path (e.g. `fuzz --fuzzer afl`)
+            if command
+                .get_arguments()
+                .any(|arg| arg.get_id() == id && arg.get_possible_values().is_empty())
+            {
+                let path = path.display().to_string();
+                command = command.mut_arg(id, |arg| arg.default_value(path).required(false));
+            }
+        }
+
+        let subcommands: Vec<String> = command
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect();
+        for name in subcommands {
+            command = command.mut_subcommand(name, |subcommand| self.apply_defaults(subcommand));
+        }
+        command
+    }
+
+    /// Resolve a relative share directory path in `sharedir_root` (if configured).
+    #[must_use]
+    pub fn sharedir(&self, sharedir: &Path) -> PathBuf {
+        match &self.sharedir_root {
+            Some(root) if sharedir.is_relative() => root.join(sharedir),
+            _ => sharedir.to_path_buf(),
+        }
+    }
+}
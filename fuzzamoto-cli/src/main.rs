@@ -1,13 +1,22 @@
 mod commands;
+mod config;
 mod error;
 mod utils;
 
-use clap::{Parser, Subcommand};
-use commands::{CoverageCommand, InitCommand, IrCommand, ir};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use commands::{
+    CorpusCommand, CoverageCommand, CoverageDiffCommand, FuzzCommand, InitCommand, IrCommand,
+    MonitorCommand, RemoteDaemonCommand, ReplayCommand, ReplayTraceCommand, RunCorpusCommand,
+    TminCommand, VerifySharedirCommand, corpus, fuzz, ir,
+};
+use config::Config;
 use error::Result;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::commands::coverage::{self, CoverageFormat};
 use crate::commands::coverage_batch::CoverageBatchCommand;
+use crate::commands::init::Sharedirs;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -20,8 +29,18 @@ struct Cli {
 enum Commands {
     /// Initialize a new fuzzamoto fuzzing campaign with Nyx
     Init {
-        #[arg(long, help = "Path to the nyx share directory that should be created")]
-        sharedir: PathBuf,
+        #[arg(
+            long,
+            required_unless_present = "sharedir_root",
+            conflicts_with = "sharedir_root",
+            help = "Path to the nyx share directory that should be created (for a single scenario)"
+        )]
+        sharedir: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Directory to create one share directory per scenario in (fuzzamoto_<scenario name>)"
+        )]
+        sharedir_root: Option<PathBuf>,
         #[arg(
             long,
             help = "Path to the crash handler that should be copied into the share directory"
@@ -39,9 +58,11 @@ enum Commands {
         secondary_bitcoind: Option<PathBuf>,
         #[arg(
             long,
-            help = "Path to the fuzzamoto scenario binary that should be copied into the share directory"
+            num_args = 1..,
+            required = true,
+            help = "Path to the fuzzamoto scenario binary that should be copied into the share directory (multiple scenarios need --sharedir-root)"
         )]
-        scenario: PathBuf,
+        scenario: Vec<PathBuf>,
 
         #[arg(long, help = "Path to the nyx installation")]
         nyx_dir: PathBuf,
@@ -51,24 +72,62 @@ enum Commands {
             help = "Path to the file with the RPC commands that should be copied into the share directory"
         )]
         rpc_path: Option<PathBuf>,
+
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+
+        #[arg(
+            long,
+            help = "Named snapshot point of the scenario setup to fuzz from (e.g. \"post-handshake\")"
+        )]
+        snapshot: Option<String>,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Report the target's state after each test case (needed by fuzzamoto-libafl's --characterization-feedback)"
+        )]
+        characterize: bool,
+
+        #[arg(
+            long,
+            help = "Root filesystem (directory or tarball) to take the binaries and their dependencies from, instead of the host"
+        )]
+        rootfs: Option<PathBuf>,
     },
 
+    /// Build, initialize, seed and launch a fuzzing campaign in one go
+    Fuzz(fuzz::FuzzArgs),
+
     /// Create a html coverage report for a given corpus
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Coverage {
-        #[arg(long, help = "Path to the output directory for the coverage report")]
-        output: PathBuf,
-        #[arg(long, help = "Path to the input corpus directory")]
-        corpus: PathBuf,
+        #[command(subcommand)]
+        command: Option<coverage::CoverageCommands>,
         #[arg(
             long,
-            help = "Path to the bitcoind binary that should be copied into the share directory"
+            required = true,
+            help = "Path to the output directory for the coverage report"
         )]
-        bitcoind: PathBuf,
+        output: Option<PathBuf>,
+        #[arg(long, required = true, help = "Path to the input corpus directory")]
+        corpus: Option<PathBuf>,
+        #[arg(
+            id = "coverage_bitcoind",
+            long = "bitcoind",
+            required = true,
+            help = "Path to the coverage instrumented bitcoind binary"
+        )]
+        bitcoind: Option<PathBuf>,
         #[arg(
             long,
+            required = true,
             help = "Path to the fuzzamoto scenario binary that should be run with coverage measurer"
         )]
-        scenario: PathBuf,
+        scenario: Option<PathBuf>,
         #[arg(
             long,
             value_name = "PROFRAWS",
@@ -82,6 +141,13 @@ enum Commands {
             help = "Only execute the corpus testcases and write .profraw files; skip merging profraws and HTML report generation"
         )]
         run_only: bool,
+        #[arg(
+            long,
+            help = "Skip source files matching this regex in the report (e.g. \"depends/|test/\")"
+        )]
+        ignore_filename_regex: Option<String>,
+        #[arg(long, help = "Format of the coverage report", value_enum, default_value_t = CoverageFormat::Html)]
+        format: CoverageFormat,
     },
 
     /// Create a html coverage report for a given corpus, runs using multiple docker instances
@@ -109,11 +175,163 @@ enum Commands {
         scenario: String,
     },
 
+    /// Run a testcase once outside of Nyx and print its verdict
+    Replay {
+        #[arg(
+            long,
+            help = "Path to the fuzzamoto scenario binary (built without the nyx feature)"
+        )]
+        scenario: PathBuf,
+        #[arg(long, help = "Path to the bitcoind binary")]
+        bitcoind: PathBuf,
+        #[arg(long, help = "Path to the testcase file")]
+        input: PathBuf,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+        #[arg(
+            long,
+            help = "Record everything the scenario does to its target into this trace file"
+        )]
+        record_trace: Option<PathBuf>,
+    },
+
+    /// Replay a trace recorded with `replay --record-trace` against a fresh node
+    ReplayTrace {
+        #[arg(
+            long,
+            help = "Path to the bitcoind binary (or a wrapper script running it under a debugger)"
+        )]
+        bitcoind: PathBuf,
+        #[arg(long, help = "Path to the trace file")]
+        trace: PathBuf,
+    },
+
+    /// Run every testcase of a corpus once outside of Nyx and write a JSON report of the results
+    RunCorpus {
+        #[arg(
+            long,
+            help = "Path to the fuzzamoto scenario binary (built without the nyx feature)"
+        )]
+        scenario: PathBuf,
+        #[arg(long, help = "Path to the bitcoind binary")]
+        bitcoind: PathBuf,
+        #[arg(long, help = "Path to the input corpus directory")]
+        corpus: PathBuf,
+        #[arg(long, help = "Path the JSON report is written to")]
+        output: PathBuf,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+    },
+
+    /// Minimize a crashing IR testcase while it keeps failing with the same oracle
+    Tmin {
+        #[arg(
+            long,
+            help = "Path to the IR scenario binary (built without the nyx feature)"
+        )]
+        scenario: PathBuf,
+        #[arg(long, help = "Path to the bitcoind binary")]
+        bitcoind: PathBuf,
+        #[arg(long, help = "Path to the crashing IR program")]
+        input: PathBuf,
+        #[arg(
+            long,
+            help = "Path the minimized IR program is written to (JSON if it ends in .json)"
+        )]
+        output: PathBuf,
+        #[arg(
+            long,
+            help = "Extra arguments passed to bitcoind (e.g. \"-acceptnonstdtxn=1 -mempoolfullrbf=0\")"
+        )]
+        node_args: Option<String>,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Compile programs before running them, for scenarios built without compile_in_vm"
+        )]
+        compiled: bool,
+    },
+
+    /// Show a live dashboard of a running fuzzamoto-libafl campaign
+    Monitor {
+        #[arg(long, help = "Path to the output directory of the campaign")]
+        output: PathBuf,
+        #[arg(long, help = "Refresh interval in seconds", default_value_t = 5)]
+        interval: u64,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Print the dashboard once instead of refreshing it"
+        )]
+        once: bool,
+    },
+
+    /// Check a nyx share directory for missing artifacts and invalid config values
+    VerifySharedir {
+        #[arg(help = "Path to the nyx share directory")]
+        sharedir: PathBuf,
+        #[arg(
+            long,
+            requires = "input",
+            help = "Path to the fuzzamoto-libafl binary, to boot the VM once with --input"
+        )]
+        fuzzer: Option<PathBuf>,
+        #[arg(
+            long,
+            requires = "fuzzer",
+            help = "Path to an IR testcase to run when booting the VM"
+        )]
+        input: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Timeout for booting the VM in seconds",
+            default_value_t = 300
+        )]
+        timeout: u64,
+    },
+
+    /// Run testcases sent by remote fuzzers (e.g. inside a VM or container without Nyx)
+    RemoteDaemon {
+        #[arg(
+            long,
+            help = "Path to the fuzzamoto scenario binary (built without the nyx feature)"
+        )]
+        scenario: PathBuf,
+        #[arg(long, help = "Path to the (AFL++ instrumented) bitcoind binary")]
+        bitcoind: PathBuf,
+        #[arg(
+            long,
+            help = "Address to listen on (anyone able to connect can run testcases)",
+            default_value = "127.0.0.1:1338"
+        )]
+        listen: String,
+        #[arg(long, help = "Size of the coverage map", default_value_t = 65536)]
+        map_size: usize,
+        #[arg(
+            long,
+            help = "Maximum timeout per testcase in milli-seconds (fuzzers send their own timeout)",
+            default_value_t = 10000
+        )]
+        max_timeout: u64,
+    },
+
     /// Fuzzamoto intermediate representation (IR) commands
     IR {
         #[command(subcommand)]
         command: ir::IRCommands,
     },
+
+    /// Corpus management commands
+    Corpus {
+        #[command(subcommand)]
+        command: corpus::CorpusCommands,
+    },
 }
 
 fn main() -> Result<()> {
@@ -122,33 +340,79 @@ fn main() -> Result<()> {
         .filter_level(log::LevelFilter::Info)
         .init();
 
-    let cli = Cli::parse();
+    let config = Config::load(Config::path_from_args().as_deref())?;
+    let matches = config
+        .apply_defaults(Cli::command().arg(Config::arg()))
+        .get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     match &cli.command {
         Commands::Init {
             sharedir,
+            sharedir_root,
             crash_handler,
             bitcoind,
             secondary_bitcoind,
             scenario,
             nyx_dir,
             rpc_path,
-        } => InitCommand::execute(
-            sharedir,
-            crash_handler,
-            bitcoind,
-            secondary_bitcoind.as_ref(),
+            node_args,
+            snapshot,
+            characterize,
+            rootfs,
+        } => {
+            let sharedir = sharedir
+                .as_deref()
+                .map(|sharedir| config.sharedir(sharedir));
+            let sharedirs = match (&sharedir, sharedir_root) {
+                (Some(sharedir), _) => Sharedirs::Single(sharedir),
+                (None, Some(root)) => Sharedirs::Root(root),
+                (None, None) => unreachable!("clap requires --sharedir or --sharedir-root"),
+            };
+            InitCommand::execute(
+                sharedirs,
+                crash_handler,
+                bitcoind,
+                secondary_bitcoind.as_ref(),
+                scenario,
+                nyx_dir,
+                rpc_path.as_ref(),
+                node_args.as_deref(),
+                snapshot.as_deref(),
+                *characterize,
+                rootfs.as_deref(),
+            )
+        }
+        Commands::Fuzz(args) => FuzzCommand::execute(args, &config),
+        Commands::Coverage {
+            command:
+                Some(coverage::CoverageCommands::Diff {
+                    baseline,
+                    candidate,
+                    scenario,
+                    bitcoind,
+                    node_args,
+                    output,
+                }),
+            ..
+        } => CoverageDiffCommand::execute(
+            baseline,
+            candidate,
             scenario,
-            nyx_dir,
-            rpc_path.as_ref(),
+            bitcoind,
+            node_args.as_deref(),
+            output.as_deref(),
         ),
         Commands::Coverage {
-            output,
-            corpus,
-            bitcoind,
-            scenario,
+            command: None,
+            output: Some(output),
+            corpus: Some(corpus),
+            bitcoind: Some(bitcoind),
+            scenario: Some(scenario),
             profraws,
             run_only,
+            ignore_filename_regex,
+            format,
         } => CoverageCommand::execute(
             output,
             corpus,
@@ -156,6 +420,8 @@ fn main() -> Result<()> {
             scenario,
             profraws.clone(),
             *run_only,
+            ignore_filename_regex.as_deref(),
+            *format,
         ),
         Commands::CoverageBatch {
             output,
@@ -164,6 +430,69 @@ fn main() -> Result<()> {
             cpu,
             scenario,
         } => CoverageBatchCommand::execute(output, corpus, docker_image, *cpu, scenario),
+        Commands::Coverage { .. } => {
+            unreachable!("clap requires the report arguments without a subcommand")
+        }
+        Commands::Replay {
+            scenario,
+            bitcoind,
+            input,
+            node_args,
+            record_trace,
+        } => ReplayCommand::execute(
+            scenario,
+            bitcoind,
+            input,
+            node_args.as_deref(),
+            record_trace.as_deref(),
+        ),
+        Commands::ReplayTrace { bitcoind, trace } => ReplayTraceCommand::execute(bitcoind, trace),
+        Commands::RunCorpus {
+            scenario,
+            bitcoind,
+            corpus,
+            output,
+            node_args,
+        } => RunCorpusCommand::execute(scenario, bitcoind, corpus, output, node_args.as_deref()),
+        Commands::Tmin {
+            scenario,
+            bitcoind,
+            input,
+            output,
+            node_args,
+            compiled,
+        } => TminCommand::execute(
+            scenario,
+            bitcoind,
+            input,
+            output,
+            node_args.as_deref(),
+            *compiled,
+        ),
+        Commands::Monitor {
+            output,
+            interval,
+            once,
+        } => MonitorCommand::execute(output, Duration::from_secs(*interval), *once),
+        Commands::VerifySharedir {
+            sharedir,
+            fuzzer,
+            input,
+            timeout,
+        } => VerifySharedirCommand::execute(
+            &config.sharedir(sharedir),
+            fuzzer.as_deref(),
+            input.as_deref(),
+            Duration::from_secs(*timeout),
+        ),
+        Commands::RemoteDaemon {
+            scenario,
+            bitcoind,
+            listen,
+            map_size,
+            max_timeout,
+        } => RemoteDaemonCommand::execute(scenario, bitcoind, listen, *map_size, *max_timeout),
         Commands::IR { command } => IrCommand::execute(command),
+        Commands::Corpus { command } => CorpusCommand::execute(command),
     }
 }
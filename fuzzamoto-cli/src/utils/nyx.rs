@@ -43,6 +43,7 @@ pub fn generate_nyx_config(nyx_path: &Path, sharedir: &Path) -> Result<()> {
     Ok(())
 }
 
+#[expect(clippy::too_many_arguments)]
 pub fn create_nyx_script(
     sharedir: &Path,
     all_deps: &[String],
@@ -51,6 +52,9 @@ pub fn create_nyx_script(
     scenario_name: &str,
     secondary_bitcoind: Option<&str>,
     rpc_path: Option<&str>,
+    node_args: Option<&str>,
+    snapshot: Option<&str>,
+    characterize: bool,
 ) -> Result<()> {
     let mut script = vec![
         "chmod +x hget".to_string(),
@@ -109,6 +113,22 @@ pub fn create_nyx_script(
     script.push(format!("echo \"{proxy_script}\" >> ./bitcoind_proxy"));
     script.push("chmod +x ./bitcoind_proxy".to_string());
 
+    // Extra bitcoind arguments (picked up by the scenario, see `ScenarioArgs::node_args`)
+    if let Some(node_args) = node_args {
+        script.push(format!("export FUZZAMOTO_NODE_ARGS='{node_args}'"));
+    }
+
+    // Snapshot point to fuzz from (picked up by the scenario, see `FUZZAMOTO_SNAPSHOT`)
+    if let Some(snapshot) = snapshot {
+        script.push(format!("export FUZZAMOTO_SNAPSHOT='{snapshot}'"));
+    }
+
+    // Report the target's state after each test case (picked up by the scenario, see
+    // `FUZZAMOTO_CHARACTERIZE`)
+    if characterize {
+        script.push("export FUZZAMOTO_CHARACTERIZE=true".to_string());
+    }
+
     // Run the scenario
     script.push(format!(
         "RUST_LOG=debug LD_LIBRARY_PATH=/tmp LD_BIND_NOW=1 ./{} ./bitcoind_proxy {} ./{} > log.txt 2>&1",
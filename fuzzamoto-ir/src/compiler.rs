@@ -63,8 +63,14 @@ pub enum CompiledAction {
     },
     /// Send a message on one of the connections
     SendRawMessage(usize, String, Vec<u8>),
+    /// Send raw bytes on one of the connections, bypassing message framing
+    SendRawFrame(usize, Vec<u8>),
+    /// Send a message on one of the connections in chunks of the given size
+    SendDribbleMessage(usize, String, Vec<u8>, usize),
     /// Set mock time for all nodes in the test
     SetTime(u64),
+    /// Restart one of the nodes
+    RestartNode(usize),
     Probe,
 }
 
@@ -459,7 +465,16 @@ impl Compiler {
                     self.handle_new_connection_operations(instruction)?;
                 }
 
+                Operation::RestartNode => {
+                    let node_var = self.get_input::<usize>(&instruction.inputs, 0)?;
+                    self.output
+                        .actions
+                        .push(CompiledAction::RestartNode(*node_var));
+                }
+
                 Operation::SendRawMessage
+                | Operation::SendRawFrame
+                | Operation::SendDribbleMessage
                 | Operation::SendTxNoWit
                 | Operation::SendTx
                 | Operation::SendGetData
@@ -1403,6 +1418,28 @@ impl Compiler {
                     bytes_var.clone(),
                 );
             }
+            Operation::SendRawFrame => {
+                let connection_var = self.get_input::<usize>(&instruction.inputs, 0)?;
+                let bytes_var = self.get_input::<Vec<u8>>(&instruction.inputs, 1)?;
+
+                self.output.actions.push(CompiledAction::SendRawFrame(
+                    *connection_var,
+                    bytes_var.clone(),
+                ));
+            }
+            Operation::SendDribbleMessage => {
+                let connection_var = self.get_input::<usize>(&instruction.inputs, 0)?;
+                let message_type_var = self.get_input::<[char; 12]>(&instruction.inputs, 1)?;
+                let bytes_var = self.get_input::<Vec<u8>>(&instruction.inputs, 2)?;
+                let chunk_size_var = self.get_input::<usize>(&instruction.inputs, 3)?;
+
+                self.output.actions.push(CompiledAction::SendDribbleMessage(
+                    *connection_var,
+                    message_type_var.iter().collect::<String>(),
+                    bytes_var.clone(),
+                    *chunk_size_var,
+                ));
+            }
             Operation::SendTxNoWit | Operation::SendTx => {
                 let connection_var = self.get_input::<usize>(&instruction.inputs, 0)?;
                 let tx_var = self.get_input::<Tx>(&instruction.inputs, 1)?;
@@ -2297,6 +2334,116 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compile_send_raw_frame_emits_raw_frame() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+
+        let mut builder = ProgramBuilder::new(context.clone());
+        let conn_var = builder.force_append_expect_output(vec![], &Operation::LoadConnection(0));
+        let bytes_var = builder
+            .force_append_expect_output(vec![], &Operation::LoadBytes(vec![0xfa, 0xbf, 0xb5]));
+        builder.force_append(
+            vec![conn_var.index, bytes_var.index],
+            &Operation::SendRawFrame,
+        );
+
+        let program = builder.finalize().unwrap();
+
+        let mut compiler = Compiler::new();
+        let compiled = compiler
+            .compile(&program)
+            .expect("failed to compile program");
+
+        assert_eq!(compiled.actions.len(), 1);
+        match &compiled.actions[0] {
+            CompiledAction::SendRawFrame(conn, bytes) => {
+                assert_eq!(*conn, 0);
+                assert_eq!(bytes, &[0xfa, 0xbf, 0xb5]);
+            }
+            other => panic!("unexpected action {other:?}",),
+        }
+    }
+
+    #[test]
+    fn compile_send_dribble_message_emits_dribble_message() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+
+        let mut builder = ProgramBuilder::new(context.clone());
+        let conn_var = builder.force_append_expect_output(vec![], &Operation::LoadConnection(0));
+        let msg_type_var = builder.force_append_expect_output(
+            vec![],
+            &Operation::LoadMsgType([
+                'p', 'i', 'n', 'g', '\0', '\0', '\0', '\0', '\0', '\0', '\0', '\0',
+            ]),
+        );
+        let bytes_var = builder.force_append_expect_output(
+            vec![],
+            &Operation::LoadBytes(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+        );
+        let chunk_size_var = builder.force_append_expect_output(vec![], &Operation::LoadSize(3));
+        builder.force_append(
+            vec![
+                conn_var.index,
+                msg_type_var.index,
+                bytes_var.index,
+                chunk_size_var.index,
+            ],
+            &Operation::SendDribbleMessage,
+        );
+
+        let program = builder.finalize().unwrap();
+
+        let mut compiler = Compiler::new();
+        let compiled = compiler
+            .compile(&program)
+            .expect("failed to compile program");
+
+        assert_eq!(compiled.actions.len(), 1);
+        match &compiled.actions[0] {
+            CompiledAction::SendDribbleMessage(conn, command, payload, chunk_size) => {
+                assert_eq!(*conn, 0);
+                assert_eq!(command, "ping\0\0\0\0\0\0\0\0");
+                assert_eq!(payload, &[1, 2, 3, 4, 5, 6, 7, 8]);
+                assert_eq!(*chunk_size, 3);
+            }
+            other => panic!("unexpected action {other:?}",),
+        }
+    }
+
+    #[test]
+    fn compile_restart_node_emits_restart() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+
+        let mut builder = ProgramBuilder::new(context.clone());
+        let node_var = builder.force_append_expect_output(vec![], &Operation::LoadNode(0));
+        builder.force_append(vec![node_var.index], &Operation::RestartNode);
+
+        let program = builder.finalize().unwrap();
+
+        let mut compiler = Compiler::new();
+        let compiled = compiler
+            .compile(&program)
+            .expect("failed to compile program");
+
+        assert_eq!(compiled.actions.len(), 1);
+        match &compiled.actions[0] {
+            CompiledAction::RestartNode(node) => assert_eq!(*node, 0),
+            other => panic!("unexpected action {other:?}",),
+        }
+    }
+
     #[test]
     fn compile_send_addr_emits_addr_message() {
         let context = ProgramContext {
@@ -2,6 +2,7 @@ use crate::{
     Instruction, Operation, PerTestcaseMetadata, Variable,
     generators::{Generator, GeneratorResult, ProgramBuilder},
 };
+use fuzzamoto::targets::TargetCapabilities;
 use rand::{Rng, RngCore};
 
 #[derive(Debug, Clone, Copy)]
@@ -24,9 +25,14 @@ impl ConnectionType {
 /// Can be configured to:
 /// - Perform handshake or not
 /// - Create inbound or outbound connections
+/// - Only negotiate the optional features supported by the target (see `with_capabilities`)
 pub struct AddConnectionGenerator {
     handshake: bool,
     connection_type: ConnectionType,
+    /// Whether handshakes may negotiate Erlay (`sendtxrcncl`)
+    erlay: bool,
+    /// Whether handshakes may negotiate addrv2 (`sendaddrv2`)
+    addrv2: bool,
 }
 
 impl AddConnectionGenerator {
@@ -35,6 +41,8 @@ impl AddConnectionGenerator {
         Self {
             handshake: true,
             connection_type: ConnectionType::Outbound,
+            erlay: true,
+            addrv2: true,
         }
     }
 
@@ -43,6 +51,8 @@ impl AddConnectionGenerator {
         Self {
             handshake: true,
             connection_type: ConnectionType::Inbound,
+            erlay: true,
+            addrv2: true,
         }
     }
 
@@ -51,6 +61,8 @@ impl AddConnectionGenerator {
         Self {
             handshake: false,
             connection_type: ConnectionType::Outbound,
+            erlay: true,
+            addrv2: true,
         }
     }
 
@@ -59,8 +71,18 @@ impl AddConnectionGenerator {
         Self {
             handshake: false,
             connection_type: ConnectionType::Inbound,
+            erlay: true,
+            addrv2: true,
         }
     }
+
+    /// Restrict the features negotiated in handshakes to those supported by the target.
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: &TargetCapabilities) -> Self {
+        self.erlay = capabilities.erlay;
+        self.addrv2 = capabilities.addrv2;
+        self
+    }
 }
 
 impl<R: RngCore> Generator<R> for AddConnectionGenerator {
@@ -118,8 +140,8 @@ impl<R: RngCore> Generator<R> for AddConnectionGenerator {
                             relay: rng.gen_bool(0.5),
                             starting_height: rng.gen_range(0..400),
                             wtxidrelay: rng.gen_bool(0.5),
-                            addrv2: rng.gen_bool(0.5),
-                            erlay: rng.gen_bool(0.5),
+                            addrv2: self.addrv2 && rng.gen_bool(0.5),
+                            erlay: self.erlay && rng.gen_bool(0.5),
                         },
                     })
                     .expect("Inserting LoadHandshakeOpts should always succeed")
@@ -1,15 +1,49 @@
 use crate::{
-    Operation, PerTestcaseMetadata, Variable,
+    Header, Operation, PerTestcaseMetadata, Variable,
     generators::{Generator, ProgramBuilder},
 };
 use rand::{Rng, RngCore, seq::SliceRandom};
 
 use super::{GeneratorError, GeneratorResult};
 
+/// Maximum number of filters served per `getcfilters` request (see BIP 157)
+const MAX_GETCFILTERS_SIZE: u32 = 1000;
+/// Maximum number of filter hashes served per `getcfheaders` request (see BIP 157)
+const MAX_GETCFHEADERS_SIZE: u32 = 2000;
+
 /// `CompactFilterQueryGenerator` generates a new `SendGetCFilters`, `SendGetCFHeaders` or
 /// `SendGetCFCheckpt` instruction into a global context.
+///
+/// The stop hash is either taken from a header variable of the program or loaded from the
+/// context's headers. In the latter case the start height is chosen relative to the stop height,
+/// covering consistent ranges as well as inconsistent ones (start after stop, ranges exceeding
+/// the serving limits).
 #[derive(Debug, Default)]
-pub struct CompactFilterQueryGenerator;
+pub struct CompactFilterQueryGenerator {
+    headers: Vec<Header>,
+}
+
+impl CompactFilterQueryGenerator {
+    #[must_use]
+    pub fn new(headers: Vec<Header>) -> Self {
+        Self { headers }
+    }
+
+    fn start_height<R: RngCore>(rng: &mut R, stop_height: Option<u32>) -> u32 {
+        let Some(stop) = stop_height else {
+            return rng.gen_range(0..200); // TODO: Find a better way to generate block heights
+        };
+
+        match rng.gen_range(0..6) {
+            0 => rng.gen_range(0..=stop),
+            1 => stop,
+            2 => stop.saturating_add(rng.gen_range(1..=10)),
+            3 => stop.saturating_sub(MAX_GETCFILTERS_SIZE - rng.gen_range(0..=1)),
+            4 => stop.saturating_sub(MAX_GETCFHEADERS_SIZE - rng.gen_range(0..=1)),
+            _ => rng.r#gen(),
+        }
+    }
+}
 
 impl<R: RngCore> Generator<R> for CompactFilterQueryGenerator {
     fn generate(
@@ -18,14 +52,39 @@ impl<R: RngCore> Generator<R> for CompactFilterQueryGenerator {
         rng: &mut R,
         _meta: Option<&PerTestcaseMetadata>,
     ) -> GeneratorResult {
-        let Some(header_var) = builder.get_random_variable(rng, &Variable::Header) else {
+        let existing_header = builder.get_random_variable(rng, &Variable::Header);
+        let context_header = self
+            .headers
+            .choose(rng)
+            .filter(|_| existing_header.is_none() || rng.gen_bool(0.5));
+
+        let (header_var, stop_height) = if let Some(header) = context_header {
+            let header_var = builder.force_append_expect_output(
+                vec![],
+                &Operation::LoadHeader {
+                    prev: header.prev,
+                    merkle_root: header.merkle_root,
+                    nonce: header.nonce,
+                    bits: header.bits,
+                    time: header.time,
+                    version: header.version,
+                    height: header.height,
+                },
+            );
+            (header_var, Some(header.height))
+        } else if let Some(header_var) = existing_header {
+            (header_var, None)
+        } else {
             return Err(GeneratorError::MissingVariables);
         };
+
         let connection_var = builder.get_or_create_random_connection(rng);
         let compact_filter_type_var =
             builder.force_append_expect_output(vec![], &Operation::LoadCompactFilterType(0));
-        let block_height_var = builder
-            .force_append_expect_output(vec![], &Operation::LoadBlockHeight(rng.gen_range(0..200))); // TODO: Find a better way to generate block heights
+        let block_height_var = builder.force_append_expect_output(
+            vec![],
+            &Operation::LoadBlockHeight(Self::start_height(rng, stop_height)),
+        );
 
         let op = [
             Operation::SendGetCFilters,
@@ -0,0 +1,64 @@
+use rand::{Rng, RngCore, seq::SliceRandom};
+
+use crate::{
+    Operation, PerTestcaseMetadata,
+    generators::{Generator, GeneratorError, GeneratorResult, ProgramBuilder},
+};
+
+const MSG_TYPES: &[&str] = &[
+    "version", "verack", "ping", "inv", "getdata", "headers", "block", "tx", "addr", "pong",
+];
+
+/// Upper bound on the chunk size, larger chunks are unlikely to split headers or payloads in
+/// interesting ways
+const MAX_CHUNK_SIZE: usize = 32;
+
+/// `DribbleMessageGenerator` generates programs that send a message on a random connection in
+/// small chunks (`SendDribbleMessage`), to exercise the target's message reassembly.
+#[derive(Default)]
+pub struct DribbleMessageGenerator;
+
+impl<R: RngCore> Generator<R> for DribbleMessageGenerator {
+    fn generate(
+        &self,
+        builder: &mut ProgramBuilder,
+        rng: &mut R,
+        _meta: Option<&PerTestcaseMetadata>,
+    ) -> GeneratorResult {
+        if builder.context().num_connections == 0 {
+            return Err(GeneratorError::InvalidContext(builder.context().clone()));
+        }
+
+        let mut msg_type = ['\0'; 12];
+        for (i, c) in MSG_TYPES.choose(rng).unwrap().chars().enumerate() {
+            msg_type[i] = c;
+        }
+
+        let mut payload = vec![0; rng.gen_range(0..256)];
+        rng.fill_bytes(&mut payload);
+
+        let conn_var = builder.get_or_create_random_connection(rng);
+        let msg_type_var =
+            builder.force_append_expect_output(vec![], &Operation::LoadMsgType(msg_type));
+        let bytes_var = builder.force_append_expect_output(vec![], &Operation::LoadBytes(payload));
+        let chunk_size_var = builder.force_append_expect_output(
+            vec![],
+            &Operation::LoadSize(rng.gen_range(1..=MAX_CHUNK_SIZE)),
+        );
+        builder.force_append(
+            vec![
+                conn_var.index,
+                msg_type_var.index,
+                bytes_var.index,
+                chunk_size_var.index,
+            ],
+            &Operation::SendDribbleMessage,
+        );
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "DribbleMessageGenerator"
+    }
+}
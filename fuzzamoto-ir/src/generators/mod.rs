@@ -6,8 +6,11 @@ pub mod block_txn;
 pub mod bloom_filter;
 pub mod compact_block;
 pub mod compact_filters;
+pub mod dribble;
 pub mod getaddr;
 pub mod getdata;
+pub mod raw_frame;
+pub mod restart;
 pub mod send_raw_message;
 pub mod tx;
 pub mod txo;
@@ -21,8 +24,11 @@ pub use block_txn::*;
 pub use bloom_filter::*;
 pub use compact_block::*;
 pub use compact_filters::*;
+pub use dribble::*;
 pub use getaddr::*;
 pub use getdata::*;
+pub use raw_frame::*;
+pub use restart::*;
 pub use send_raw_message::*;
 pub use tx::*;
 pub use txo::*;
@@ -0,0 +1,88 @@
+use bitcoin::p2p::Magic;
+use fuzzamoto::connections::encode_p2p_message;
+use rand::{Rng, RngCore, seq::SliceRandom};
+
+use crate::{
+    Operation, PerTestcaseMetadata,
+    generators::{Generator, GeneratorError, GeneratorResult, ProgramBuilder},
+};
+
+const MAGIC_RANGE: std::ops::Range<usize> = 0..4;
+const LENGTH_RANGE: std::ops::Range<usize> = 16..20;
+const CHECKSUM_RANGE: std::ops::Range<usize> = 20..24;
+
+const MSG_TYPES: &[&str] = &[
+    "version", "verack", "ping", "pong", "inv", "getdata", "headers", "block", "tx", "addr",
+];
+
+/// `RawFrameGenerator` generates programs that send a malformed v1 p2p frame on a random
+/// connection. The frame starts out as a valid message, which is then corrupted by e.g. a wrong
+/// length field, a bad checksum, bad magic or truncation.
+pub struct RawFrameGenerator {
+    /// Network magic of the target's chain (see `FullProgramContext::magic`)
+    magic: Magic,
+}
+
+impl RawFrameGenerator {
+    #[must_use]
+    pub fn new(magic: [u8; 4]) -> Self {
+        Self {
+            magic: Magic::from_bytes(magic),
+        }
+    }
+
+    fn corrupt_frame<R: RngCore>(frame: &mut Vec<u8>, rng: &mut R) {
+        match rng.gen_range(0..4) {
+            0 => {
+                let len: u32 = rng.r#gen();
+                frame[LENGTH_RANGE].copy_from_slice(&len.to_le_bytes());
+            }
+            1 => {
+                let idx = rng.gen_range(CHECKSUM_RANGE);
+                frame[idx] ^= rng.gen_range(1..=u8::MAX);
+            }
+            2 => {
+                let idx = rng.gen_range(MAGIC_RANGE);
+                frame[idx] ^= rng.gen_range(1..=u8::MAX);
+            }
+            _ => {
+                // Truncate somewhere in the header or payload
+                frame.truncate(rng.gen_range(0..frame.len()));
+            }
+        }
+    }
+}
+
+impl<R: RngCore> Generator<R> for RawFrameGenerator {
+    fn generate(
+        &self,
+        builder: &mut ProgramBuilder,
+        rng: &mut R,
+        _meta: Option<&PerTestcaseMetadata>,
+    ) -> GeneratorResult {
+        if builder.context().num_connections == 0 {
+            return Err(GeneratorError::InvalidContext(builder.context().clone()));
+        }
+
+        let mut payload = vec![0; rng.gen_range(0..64)];
+        rng.fill_bytes(&mut payload);
+        let msg_type = (*MSG_TYPES.choose(rng).unwrap()).to_string();
+
+        let mut frame = encode_p2p_message(self.magic, &(msg_type, payload))
+            .expect("Encoding a known message type should always succeed");
+        Self::corrupt_frame(&mut frame, rng);
+
+        let conn_var = builder.get_or_create_random_connection(rng);
+        let bytes_var = builder.force_append_expect_output(vec![], &Operation::LoadBytes(frame));
+        builder.force_append(
+            vec![conn_var.index, bytes_var.index],
+            &Operation::SendRawFrame,
+        );
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "RawFrameGenerator"
+    }
+}
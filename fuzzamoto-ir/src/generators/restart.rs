@@ -0,0 +1,43 @@
+use rand::{Rng, RngCore};
+
+use crate::{
+    Operation, PerTestcaseMetadata, Variable,
+    generators::{Generator, GeneratorError, GeneratorResult, ProgramBuilder},
+};
+
+/// `RestartNodeGenerator` generates programs that restart one of the nodes, such that the code
+/// persisting and loading state across restarts (e.g. `mempool.dat`, `peers.dat`, `anchors.dat`
+/// and startup re-validation) is exercised with the state built up by the rest of the program.
+///
+/// All connections to the node are closed by the restart, so only later `AddConnection`
+/// instructions are able to talk to the node again.
+#[derive(Default)]
+pub struct RestartNodeGenerator;
+
+impl<R: RngCore> Generator<R> for RestartNodeGenerator {
+    fn generate(
+        &self,
+        builder: &mut ProgramBuilder,
+        rng: &mut R,
+        _meta: Option<&PerTestcaseMetadata>,
+    ) -> GeneratorResult {
+        let node_var = if let Some(v) = builder.get_random_variable(rng, &Variable::Node) {
+            v
+        } else {
+            if builder.context().num_nodes == 0 {
+                return Err(GeneratorError::InvalidContext(builder.context().clone()));
+            }
+
+            let node = rng.gen_range(0..builder.context().num_nodes);
+            builder.force_append_expect_output(vec![], &Operation::LoadNode(node))
+        };
+
+        builder.force_append(vec![node_var.index], &Operation::RestartNode);
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "RestartNodeGenerator"
+    }
+}
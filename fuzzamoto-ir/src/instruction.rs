@@ -87,9 +87,12 @@ impl Instruction {
             | Operation::LoadBlockHeight(_)
             | Operation::LoadCompactFilterType(_)
             | Operation::SendRawMessage
+            | Operation::SendRawFrame
+            | Operation::SendDribbleMessage
             | Operation::AdvanceTime
             | Operation::LoadTime(_)
             | Operation::SetTime
+            | Operation::RestartNode
             | Operation::AddConnection
             | Operation::AddConnectionWithHandshake { .. }
             | Operation::LoadHandshakeOpts { .. }
@@ -4,6 +4,7 @@ pub mod compiler;
 pub mod errors;
 pub mod generators;
 pub mod instruction;
+pub mod lift;
 pub mod metadata;
 pub mod minimizers;
 pub mod mutators;
@@ -20,11 +21,16 @@ pub use minimizers::*;
 pub use mutators::*;
 pub use operation::*;
 
+pub use fuzzamoto::scenarios::generic::SetupOptions;
 pub use fuzzamoto::taproot::*;
 use rand::{RngCore, seq::IteratorRandom};
 pub use variable::*;
 
-use std::{collections::HashMap, fmt, hash::Hash};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+};
 
 /// Program represent a sequence of operations to perform on target nodes.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Hash)]
@@ -33,6 +39,18 @@ pub struct Program {
     pub context: ProgramContext,
 }
 
+/// Summary of the shape of a program (see `Program::stats`)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramStats {
+    /// Number of instructions, excluding nops
+    pub instructions: usize,
+    /// Deepest block nesting (e.g. 2 for a `BeginBuildTxInputs` block inside a `BeginBuildTx`
+    /// block)
+    pub max_depth: usize,
+    /// Number of instructions per operation kind (see `Operation::kind`), excluding nops
+    pub operations: BTreeMap<String, usize>,
+}
+
 /// `ProgramContext` provides a summary of the context in which a program is executed, describing
 /// the snapshot state of the VM.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Hash)]
@@ -62,6 +80,12 @@ pub struct FullProgramContext {
     pub txos: Vec<Txo>,
     /// List of headers present in the snapshotted state
     pub headers: Vec<Header>,
+    /// Version and optional P2P features supported by the target
+    pub capabilities: fuzzamoto::targets::TargetCapabilities,
+    /// Options the scenario setup (connections, blocks, negotiated features) was created with
+    pub setup: SetupOptions,
+    /// Network magic of the chain the target is running on (see `fuzzamoto::chain::ChainParams`)
+    pub magic: [u8; 4],
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -207,6 +231,21 @@ impl Program {
         debug_assert!(self.is_statically_valid());
     }
 
+    /// Hash of the program without its nops, such that programs that only differ in their nops
+    /// (e.g. a program and a partially minimized version of it) hash the same. The hash is stable
+    /// across runs and builds (murmur3 of the postcard encoding), such that it can be compared
+    /// between corpora.
+    #[must_use]
+    pub fn semantic_hash(&self) -> u64 {
+        let mut program = self.clone();
+        if program.is_statically_valid() {
+            program.remove_nops();
+        }
+        let bytes = postcard::to_allocvec(&program).expect("programs are always serializable");
+        (u64::from(murmurs::murmur3_x86_32(&bytes, 0)) << 32)
+            | u64::from(murmurs::murmur3_x86_32(&bytes, 0x9E37_79B9))
+    }
+
     pub fn get_random_instruction_index<R: RngCore>(
         &self,
         rng: &mut R,
@@ -251,6 +290,43 @@ impl Program {
 
         contexts.into_iter().filter(|i| *i >= from).choose(rng)
     }
+
+    /// Count the program's instructions per operation kind and measure its block nesting.
+    #[must_use]
+    pub fn stats(&self) -> ProgramStats {
+        let mut stats = ProgramStats::default();
+        let mut depth = 0usize;
+        for instr in &self.instructions {
+            if matches!(instr.operation, Operation::Nop { .. }) {
+                continue;
+            }
+            if instr.operation.is_block_end() {
+                depth = depth.saturating_sub(1);
+            }
+            if instr.operation.is_block_begin() {
+                depth += 1;
+                stats.max_depth = stats.max_depth.max(depth);
+            }
+            stats.instructions += 1;
+            *stats
+                .operations
+                .entry(instr.operation.kind().to_string())
+                .or_default() += 1;
+        }
+        stats
+    }
+
+    /// Serialize the program to JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize a program from JSON
+    #[cfg(feature = "json")]
+    pub fn from_json(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
 }
 
 impl fmt::Display for Program {
@@ -374,3 +450,96 @@ impl Ord for RecentBlock {
         self.height.cmp(&other.height)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip_preserves_program() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+
+        let mut builder = ProgramBuilder::new(context);
+        let conn_var = builder.force_append_expect_output(vec![], &Operation::LoadConnection(0));
+        builder.force_append(vec![conn_var.index], &Operation::SendGetAddr);
+        let program = builder.finalize().unwrap();
+
+        let json = program.to_json().unwrap();
+        let decoded = Program::from_json(json.as_bytes()).unwrap();
+
+        assert_eq!(
+            postcard::to_allocvec(&program).unwrap(),
+            postcard::to_allocvec(&decoded).unwrap()
+        );
+    }
+
+    #[test]
+    fn semantic_hash_ignores_nops() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+
+        let mut builder = ProgramBuilder::new(context);
+        let conn_var = builder.force_append_expect_output(vec![], &Operation::LoadConnection(0));
+        builder.force_append(vec![conn_var.index], &Operation::SendGetAddr);
+        let program = builder.finalize().unwrap();
+
+        let mut with_nop = program.clone();
+        with_nop.instructions.insert(
+            0,
+            Instruction {
+                inputs: vec![],
+                operation: Operation::Nop {
+                    outputs: 0,
+                    inner_outputs: 0,
+                },
+            },
+        );
+        assert_eq!(program.semantic_hash(), with_nop.semantic_hash());
+
+        let mut different = program.clone();
+        different.instructions.push(program.instructions[1].clone());
+        assert_ne!(program.semantic_hash(), different.semantic_hash());
+    }
+
+    #[test]
+    fn test_stats() {
+        let context = ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        };
+        let instructions = [
+            Operation::LoadBytes(vec![0; 1024]),
+            Operation::BeginBuildTx,
+            Operation::BeginBuildTxInputs,
+            Operation::Nop {
+                outputs: 1,
+                inner_outputs: 0,
+            },
+            Operation::EndBuildTxInputs,
+            Operation::EndBuildTx,
+            Operation::LoadBytes(vec![1]),
+        ]
+        .into_iter()
+        .map(|operation| Instruction {
+            inputs: vec![],
+            operation,
+        })
+        .collect();
+        let stats = Program::unchecked_new(context, instructions).stats();
+
+        assert_eq!(stats.instructions, 6);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.operations.get("LoadBytes"), Some(&2));
+        assert_eq!(stats.operations.get("BeginBuildTxInputs"), Some(&1));
+        assert!(!stats.operations.contains_key("Nop"));
+    }
+}
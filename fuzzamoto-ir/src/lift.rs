@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use fuzzamoto::scenarios::ScenarioInput;
+use fuzzamoto::scenarios::generic::{Action as GenericAction, TestCase as GenericTestCase};
+
+use crate::{Operation, Program, ProgramBuilder, ProgramContext};
+
+/// Translate a generic scenario testcase (`bytes`) into IR, mirroring `GenericScenario::run`:
+/// messages are sent on the connection with the same index, `SetMocktime` sets the time and
+/// `AdvanceTime` advances the scenario's clock (which starts at the program's timestamp). `Connect`
+/// actions are ignored by the scenario and dropped.
+pub fn lift_generic(context: &ProgramContext, bytes: &[u8]) -> Result<Program, String> {
+    let testcase = GenericTestCase::decode(bytes)?;
+    let mut builder = ProgramBuilder::new(context.clone());
+    let mut time = None;
+    for action in &testcase.actions {
+        match action {
+            GenericAction::Connect { .. } => {}
+            GenericAction::Message {
+                from,
+                command,
+                data,
+            } => {
+                if context.num_connections == 0 {
+                    return Err("The program context has no connections".to_string());
+                }
+                // Connections are picked modulo the number of connections in both scenarios
+                let connection = builder.force_append_expect_output(
+                    vec![],
+                    &Operation::LoadConnection(usize::from(*from) % context.num_connections),
+                );
+                let mut msg_type = ['\0'; 12];
+                for (c, b) in msg_type.iter_mut().zip(command.to_string().bytes()) {
+                    *c = char::from(b);
+                }
+                let msg_type =
+                    builder.force_append_expect_output(vec![], &Operation::LoadMsgType(msg_type));
+                let bytes =
+                    builder.force_append_expect_output(vec![], &Operation::LoadBytes(data.clone()));
+                builder.force_append(
+                    vec![connection.index, msg_type.index, bytes.index],
+                    &Operation::SendRawMessage,
+                );
+            }
+            GenericAction::SetMocktime { time: mocktime } => {
+                let mocktime =
+                    builder.force_append_expect_output(vec![], &Operation::LoadTime(*mocktime));
+                builder.force_append(vec![mocktime.index], &Operation::SetTime);
+            }
+            GenericAction::AdvanceTime { seconds } => {
+                let current = time.unwrap_or_else(|| {
+                    builder
+                        .force_append_expect_output(vec![], &Operation::LoadTime(context.timestamp))
+                        .index
+                });
+                let duration = builder.force_append_expect_output(
+                    vec![],
+                    &Operation::LoadDuration(Duration::from_secs(u64::from(*seconds))),
+                );
+                let advanced = builder
+                    .force_append_expect_output(
+                        vec![current, duration.index],
+                        &Operation::AdvanceTime,
+                    )
+                    .index;
+                builder.force_append(vec![advanced], &Operation::SetTime);
+                time = Some(advanced);
+            }
+        }
+    }
+
+    builder
+        .finalize()
+        .map_err(|e| format!("Failed to build program: {e:?}"))
+}
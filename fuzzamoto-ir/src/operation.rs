@@ -208,6 +208,15 @@ pub enum Operation {
     // TODO: SendGetBlockTxn
     // TODO: SendGetBlocks
     // TODO: SendGetHeaders
+
+    // New operations are appended at the end, programs are serialized with the variant index
+    /// Send raw bytes on a connection, bypassing message framing (e.g. to send malformed frames)
+    SendRawFrame,
+    /// Send a message given a connection, message type, bytes and a chunk size, writing the
+    /// encoded frame in chunks of the given size
+    SendDribbleMessage,
+    /// Gracefully shut down a node and restart it on the same data directory
+    RestartNode,
 }
 
 impl fmt::Display for Operation {
@@ -253,9 +262,12 @@ impl fmt::Display for Operation {
                 write!(f, "LoadCompactFilterType({filter_type})")
             }
             Operation::SendRawMessage => write!(f, "SendRawMessage"),
+            Operation::SendRawFrame => write!(f, "SendRawFrame"),
+            Operation::SendDribbleMessage => write!(f, "SendDribbleMessage"),
             Operation::AdvanceTime => write!(f, "AdvanceTime"),
             Operation::LoadTime(time) => write!(f, "LoadTime({time})"),
             Operation::SetTime => write!(f, "SetTime"),
+            Operation::RestartNode => write!(f, "RestartNode"),
             Operation::AddConnection => write!(f, "AddConnection"),
             Operation::AddConnectionWithHandshake { send_compact } => {
                 write!(
@@ -453,6 +465,126 @@ fn hex_witness_stack(witness: &[Vec<u8>]) -> String {
 }
 
 impl Operation {
+    /// Name of the operation's variant without its parameters (e.g. `"LoadBytes"`)
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Operation::Nop { .. } => "Nop",
+            Operation::LoadBytes(_) => "LoadBytes",
+            Operation::LoadMsgType(_) => "LoadMsgType",
+            Operation::LoadNode(_) => "LoadNode",
+            Operation::LoadConnection(_) => "LoadConnection",
+            Operation::LoadConnectionType(_) => "LoadConnectionType",
+            Operation::LoadDuration(_) => "LoadDuration",
+            Operation::LoadAddr(_) => "LoadAddr",
+            Operation::LoadTime(_) => "LoadTime",
+            Operation::LoadAmount(_) => "LoadAmount",
+            Operation::LoadSize(_) => "LoadSize",
+            Operation::LoadTxVersion(_) => "LoadTxVersion",
+            Operation::LoadBlockVersion(_) => "LoadBlockVersion",
+            Operation::LoadLockTime(_) => "LoadLockTime",
+            Operation::LoadSequence(_) => "LoadSequence",
+            Operation::LoadBlockHeight(_) => "LoadBlockHeight",
+            Operation::LoadCompactFilterType(_) => "LoadCompactFilterType",
+            Operation::LoadPrivateKey(_) => "LoadPrivateKey",
+            Operation::LoadSigHashFlags(_) => "LoadSigHashFlags",
+            Operation::LoadNonce(_) => "LoadNonce",
+            Operation::LoadTxo { .. } => "LoadTxo",
+            Operation::LoadTaprootAnnex { .. } => "LoadTaprootAnnex",
+            Operation::LoadHeader { .. } => "LoadHeader",
+            Operation::LoadFilterLoad { .. } => "LoadFilterLoad",
+            Operation::LoadFilterAdd { .. } => "LoadFilterAdd",
+            Operation::LoadHandshakeOpts { .. } => "LoadHandshakeOpts",
+            Operation::BeginBuildBlockTxn => "BeginBuildBlockTxn",
+            Operation::AddTxToBlockTxn => "AddTxToBlockTxn",
+            Operation::EndBuildBlockTxn => "EndBuildBlockTxn",
+            Operation::SendRawMessage => "SendRawMessage",
+            Operation::AdvanceTime => "AdvanceTime",
+            Operation::SetTime => "SetTime",
+            Operation::AddConnection => "AddConnection",
+            Operation::AddConnectionWithHandshake { .. } => "AddConnectionWithHandshake",
+            Operation::BuildRawScripts => "BuildRawScripts",
+            Operation::BuildPayToWitnessScriptHash => "BuildPayToWitnessScriptHash",
+            Operation::BuildPayToPubKey => "BuildPayToPubKey",
+            Operation::BuildPayToPubKeyHash => "BuildPayToPubKeyHash",
+            Operation::BuildPayToWitnessPubKeyHash => "BuildPayToWitnessPubKeyHash",
+            Operation::BuildPayToScriptHash => "BuildPayToScriptHash",
+            Operation::BuildOpReturnScripts => "BuildOpReturnScripts",
+            Operation::BuildPayToAnchor => "BuildPayToAnchor",
+            Operation::BuildPayToTaproot => "BuildPayToTaproot",
+            Operation::BuildCompactBlock => "BuildCompactBlock",
+            Operation::BeginBuildFilterLoad => "BeginBuildFilterLoad",
+            Operation::AddTxToFilter => "AddTxToFilter",
+            Operation::AddTxoToFilter => "AddTxoToFilter",
+            Operation::EndBuildFilterLoad => "EndBuildFilterLoad",
+            Operation::BuildFilterAddFromTx => "BuildFilterAddFromTx",
+            Operation::BuildFilterAddFromTxo => "BuildFilterAddFromTxo",
+            Operation::BeginWitnessStack => "BeginWitnessStack",
+            Operation::EndWitnessStack => "EndWitnessStack",
+            Operation::AddWitness => "AddWitness",
+            Operation::BeginBuildTx => "BeginBuildTx",
+            Operation::EndBuildTx => "EndBuildTx",
+            Operation::BeginBuildTxInputs => "BeginBuildTxInputs",
+            Operation::EndBuildTxInputs => "EndBuildTxInputs",
+            Operation::BeginBuildTxOutputs => "BeginBuildTxOutputs",
+            Operation::EndBuildTxOutputs => "EndBuildTxOutputs",
+            Operation::AddTxOutput => "AddTxOutput",
+            Operation::AddTxInput => "AddTxInput",
+            Operation::TakeTxo => "TakeTxo",
+            Operation::TakeCoinbaseTxo => "TakeCoinbaseTxo",
+            Operation::BeginBuildCoinbaseTx => "BeginBuildCoinbaseTx",
+            Operation::EndBuildCoinbaseTx => "EndBuildCoinbaseTx",
+            Operation::BuildCoinbaseTxInput => "BuildCoinbaseTxInput",
+            Operation::BeginBuildCoinbaseTxOutputs => "BeginBuildCoinbaseTxOutputs",
+            Operation::EndBuildCoinbaseTxOutputs => "EndBuildCoinbaseTxOutputs",
+            Operation::AddCoinbaseTxOutput => "AddCoinbaseTxOutput",
+            Operation::BeginBlockTransactions => "BeginBlockTransactions",
+            Operation::EndBlockTransactions => "EndBlockTransactions",
+            Operation::BuildBlock => "BuildBlock",
+            Operation::AddTx => "AddTx",
+            Operation::BeginBuildInventory => "BeginBuildInventory",
+            Operation::EndBuildInventory => "EndBuildInventory",
+            Operation::AddCompactBlockInv => "AddCompactBlockInv",
+            Operation::AddTxidInv => "AddTxidInv",
+            Operation::AddTxidWithWitnessInv => "AddTxidWithWitnessInv",
+            Operation::AddWtxidInv => "AddWtxidInv",
+            Operation::AddBlockInv => "AddBlockInv",
+            Operation::AddBlockWithWitnessInv => "AddBlockWithWitnessInv",
+            Operation::AddFilteredBlockInv => "AddFilteredBlockInv",
+            Operation::BeginBuildAddrList => "BeginBuildAddrList",
+            Operation::EndBuildAddrList => "EndBuildAddrList",
+            Operation::AddAddr => "AddAddr",
+            Operation::BeginBuildAddrListV2 => "BeginBuildAddrListV2",
+            Operation::EndBuildAddrListV2 => "EndBuildAddrListV2",
+            Operation::AddAddrV2 => "AddAddrV2",
+            Operation::Probe => "Probe",
+            Operation::SendGetData => "SendGetData",
+            Operation::SendInv => "SendInv",
+            Operation::SendGetAddr => "SendGetAddr",
+            Operation::SendAddr => "SendAddr",
+            Operation::SendAddrV2 => "SendAddrV2",
+            Operation::SendTx => "SendTx",
+            Operation::SendTxNoWit => "SendTxNoWit",
+            Operation::SendHeader => "SendHeader",
+            Operation::SendBlock => "SendBlock",
+            Operation::SendBlockNoWit => "SendBlockNoWit",
+            Operation::SendGetCFilters => "SendGetCFilters",
+            Operation::SendGetCFHeaders => "SendGetCFHeaders",
+            Operation::SendGetCFCheckpt => "SendGetCFCheckpt",
+            Operation::SendFilterLoad => "SendFilterLoad",
+            Operation::SendFilterAdd => "SendFilterAdd",
+            Operation::SendFilterClear => "SendFilterClear",
+            Operation::SendCompactBlock => "SendCompactBlock",
+            Operation::SendBlockTxn => "SendBlockTxn",
+            Operation::TaprootScriptsUseAnnex => "TaprootScriptsUseAnnex",
+            Operation::TaprootTxoUseAnnex => "TaprootTxoUseAnnex",
+            Operation::BuildTaprootTree { .. } => "BuildTaprootTree",
+            Operation::SendRawFrame => "SendRawFrame",
+            Operation::SendDribbleMessage => "SendDribbleMessage",
+            Operation::RestartNode => "RestartNode",
+        }
+    }
+
     #[must_use]
     pub fn mutates_nth_input(&self, index: usize) -> bool {
         matches!(self,
@@ -498,10 +630,13 @@ impl Operation {
             | Operation::LoadBlockHeight(_)
             | Operation::LoadCompactFilterType(_)
             | Operation::SendRawMessage
+            | Operation::SendRawFrame
+            | Operation::SendDribbleMessage
             | Operation::AdvanceTime
             | Operation::LoadTime(_)
             | Operation::LoadSize(_)
             | Operation::SetTime
+            | Operation::RestartNode
             | Operation::AddConnection
             | Operation::AddConnectionWithHandshake { .. }
             | Operation::LoadHandshakeOpts { .. }
@@ -657,10 +792,13 @@ impl Operation {
             | Operation::LoadBlockHeight(_)
             | Operation::LoadCompactFilterType(_)
             | Operation::SendRawMessage
+            | Operation::SendRawFrame
+            | Operation::SendDribbleMessage
             | Operation::AdvanceTime
             | Operation::LoadTime(_)
             | Operation::LoadSize(_)
             | Operation::SetTime
+            | Operation::RestartNode
             | Operation::AddConnection
             | Operation::AddConnectionWithHandshake { .. }
             | Operation::LoadHandshakeOpts { .. }
@@ -802,9 +940,12 @@ impl Operation {
             Operation::LoadBlockHeight(_) => vec![Variable::BlockHeight],
             Operation::LoadCompactFilterType(_) => vec![Variable::CompactFilterType],
             Operation::SendRawMessage => vec![],
+            Operation::SendRawFrame => vec![],
+            Operation::SendDribbleMessage => vec![],
             Operation::AdvanceTime => vec![Variable::Time],
             Operation::LoadTime(_) => vec![Variable::Time],
             Operation::SetTime => vec![],
+            Operation::RestartNode => vec![],
             Operation::AddConnection => vec![Variable::Connection],
             Operation::AddConnectionWithHandshake { .. } => vec![Variable::Connection],
             Operation::LoadHandshakeOpts { .. } => vec![Variable::HandshakeParams],
@@ -926,8 +1067,16 @@ impl Operation {
             Operation::SendRawMessage => {
                 vec![Variable::Connection, Variable::MsgType, Variable::Bytes]
             }
+            Operation::SendRawFrame => vec![Variable::Connection, Variable::Bytes],
+            Operation::SendDribbleMessage => vec![
+                Variable::Connection,
+                Variable::MsgType,
+                Variable::Bytes,
+                Variable::Size,
+            ],
             Operation::AdvanceTime => vec![Variable::Time, Variable::Duration],
             Operation::SetTime => vec![Variable::Time],
+            Operation::RestartNode => vec![Variable::Node],
             Operation::AddConnection => vec![Variable::Node, Variable::ConnectionType],
             Operation::AddConnectionWithHandshake { .. } => vec![
                 Variable::Node,
@@ -1130,9 +1279,12 @@ impl Operation {
             | Operation::LoadBlockHeight(_)
             | Operation::LoadCompactFilterType(_)
             | Operation::SendRawMessage
+            | Operation::SendRawFrame
+            | Operation::SendDribbleMessage
             | Operation::AdvanceTime
             | Operation::LoadTime(_)
             | Operation::SetTime
+            | Operation::RestartNode
             | Operation::AddConnection
             | Operation::AddConnectionWithHandshake { .. }
             | Operation::LoadHandshakeOpts { .. }
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use fuzzamoto::remote::RemoteClient;
+use libafl::{
+    Error,
+    executors::{Executor, ExitKind, HasObservers, HasTimeout, SetTimeout},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, StdOutObserver},
+    state::HasExecutions,
+};
+use libafl_bolts::{
+    AsSlice,
+    tuples::{Handle, RefIndexable},
+};
+
+/// Executor running testcases on a remote runner daemon (`fuzzamoto-cli remote-daemon`, see
+/// `fuzzamoto::remote`) instead of in Nyx.
+///
+/// The coverage reported by the daemon is written to the coverage map at `map` (observed by the
+/// trace observer) and the scenario's stdout is handed to the stdout observer, such that the
+/// feedbacks work the same as with Nyx.
+pub struct RemoteExecutor<OT> {
+    client: RemoteClient,
+    observers: OT,
+    stdout: Handle<StdOutObserver>,
+    map: *mut u8,
+    map_size: usize,
+    timeout: Duration,
+}
+
+impl<OT> RemoteExecutor<OT> {
+    pub fn new(
+        client: RemoteClient,
+        observers: OT,
+        stdout: Handle<StdOutObserver>,
+        map: *mut u8,
+        map_size: usize,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            client,
+            observers,
+            stdout,
+            map,
+            map_size,
+            timeout,
+        }
+    }
+}
+
+impl<OT> HasObservers for RemoteExecutor<OT> {
+    type Observers = OT;
+
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
+
+impl<EM, I, OT, S, Z> Executor<EM, I, S, Z> for RemoteExecutor<OT>
+where
+    I: HasTargetBytes,
+    OT: ObserversTuple<I, S>,
+    S: HasExecutions,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut S,
+        _mgr: &mut EM,
+        input: &I,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        let response = self
+            .client
+            .run(input.target_bytes().as_slice(), self.timeout)
+            .map_err(Error::unknown)?;
+
+        // The map is only accessed by the trace observer in between executions
+        let map = unsafe { std::slice::from_raw_parts_mut(self.map, self.map_size) };
+        for (index, hits) in response.coverage {
+            if let Some(entry) = map.get_mut(index as usize) {
+                *entry = hits;
+            }
+        }
+        self.observers_mut()[&self.stdout].output = Some(response.stdout);
+
+        Ok(if response.timed_out {
+            ExitKind::Timeout
+        } else if response.exit_code == Some(0) {
+            ExitKind::Ok
+        } else {
+            ExitKind::Crash
+        })
+    }
+}
+
+impl<OT> HasTimeout for RemoteExecutor<OT> {
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl<OT> SetTimeout for RemoteExecutor<OT> {
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+/// Executor running testcases either in Nyx or on a remote runner daemon, such that the rest of
+/// the fuzzer is the same for both.
+pub enum TargetExecutor<N, R> {
+    Nyx(N),
+    Remote(R),
+}
+
+impl<N, R> HasObservers for TargetExecutor<N, R>
+where
+    N: HasObservers,
+    R: HasObservers<Observers = N::Observers>,
+{
+    type Observers = N::Observers;
+
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        match self {
+            Self::Nyx(executor) => executor.observers(),
+            Self::Remote(executor) => executor.observers(),
+        }
+    }
+
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        match self {
+            Self::Nyx(executor) => executor.observers_mut(),
+            Self::Remote(executor) => executor.observers_mut(),
+        }
+    }
+}
+
+impl<EM, I, S, Z, N, R> Executor<EM, I, S, Z> for TargetExecutor<N, R>
+where
+    N: Executor<EM, I, S, Z>,
+    R: Executor<EM, I, S, Z>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &I,
+    ) -> Result<ExitKind, Error> {
+        match self {
+            Self::Nyx(executor) => executor.run_target(fuzzer, state, mgr, input),
+            Self::Remote(executor) => executor.run_target(fuzzer, state, mgr, input),
+        }
+    }
+}
+
+impl<N: HasTimeout, R: HasTimeout> HasTimeout for TargetExecutor<N, R> {
+    fn timeout(&self) -> Duration {
+        match self {
+            Self::Nyx(executor) => executor.timeout(),
+            Self::Remote(executor) => executor.timeout(),
+        }
+    }
+}
+
+impl<N: SetTimeout, R: SetTimeout> SetTimeout for TargetExecutor<N, R> {
+    fn set_timeout(&mut self, timeout: Duration) {
+        match self {
+            Self::Nyx(executor) => executor.set_timeout(timeout),
+            Self::Remote(executor) => executor.set_timeout(timeout),
+        }
+    }
+}
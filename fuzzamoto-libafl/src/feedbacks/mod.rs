@@ -1,5 +1,10 @@
+use fuzzamoto::{
+    runners::ExecutionMetrics,
+    scenarios::{characterization::Characterization, crash_context::CrashContext},
+};
 use regex::bytes::Regex;
-use std::{borrow::Cow, cell::RefCell, fmt::Debug, rc::Rc};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, cell::RefCell, collections::HashSet, fmt::Debug, rc::Rc};
 
 use core::marker::PhantomData;
 use libafl::{
@@ -308,6 +313,225 @@ where
             }
         }
 
+        // Store the crash context reported by the runner (if any) alongside the crashing input
+        if let Some(output) = &stdout_observer.output
+            && let Some(context) = CrashContext::parse_output(output)
+            && let Some(file_path) = testcase.file_path()
+        {
+            let context_path = file_path.with_extension("context.json");
+            let json = context.to_json().map_err(Error::illegal_state)?;
+            std::fs::write(&context_path, json)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A Feedback that considers inputs interesting if they leave the target in a state that hasn't
+/// been observed before, as characterized by the scenario (see
+/// `fuzzamoto::scenarios::characterization`).
+pub struct CharacterizationFeedback {
+    handle: Handle<StdOutObserver>,
+    seen: HashSet<Characterization>,
+    /// Characterizations of the last execution that haven't been observed before
+    novel: Vec<Characterization>,
+}
+
+impl CharacterizationFeedback {
+    pub fn new(handle: Handle<StdOutObserver>) -> Self {
+        Self {
+            handle,
+            seen: HashSet::new(),
+            novel: Vec::new(),
+        }
+    }
+}
+
+impl Named for CharacterizationFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CharacterizationFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for CharacterizationFeedback {}
+
+impl<EM, OT, S> Feedback<EM, IrInput, OT, S> for CharacterizationFeedback
+where
+    OT: ObserversTuple<IrInput, S>,
+    S: HasCorpus<IrInput> + HasMetadata + HasExecutions,
+    EM: EventFirer<IrInput, S>,
+{
+    #[inline]
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &IrInput,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        self.novel.clear();
+        if !matches!(exit_kind, ExitKind::Ok) {
+            return Ok(false);
+        }
+
+        let stdout_observer = observers
+            .get(&self.handle)
+            .ok_or_else(|| Error::illegal_state("StdOutObserver is missing"))?;
+        if let Some(output) = &stdout_observer.output {
+            self.novel = Characterization::parse_output(output)
+                .into_iter()
+                .filter(|characterization| !self.seen.contains(characterization))
+                .collect();
+        }
+
+        Ok(!self.novel.is_empty())
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        _observers: &OT,
+        _testcase: &mut Testcase<IrInput>,
+    ) -> Result<(), Error> {
+        // Only remember the states of inputs that end up in the corpus
+        self.seen.extend(self.novel.drain(..));
+
+        manager.fire(
+            state,
+            EventWithStats::with_current_time(
+                Event::UpdateUserStats {
+                    name: Cow::from("characterizations"),
+                    value: UserStats::new(
+                        UserStatsValue::Number(self.seen.len() as u64),
+                        AggregatorOps::Max,
+                    ),
+                    phantom: PhantomData,
+                },
+                *state.executions(),
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Totals of the execution metrics reported by the runner (see
+/// `fuzzamoto::runners::ExecutionMetrics`), kept in the state by `ExecutionMetricsFeedback`.
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutionMetricsStats {
+    pub executions: u64,
+    pub totals: ExecutionMetrics,
+}
+
+libafl_bolts::impl_serdeany!(ExecutionMetricsStats);
+
+impl ExecutionMetricsStats {
+    pub fn add(&mut self, metrics: &ExecutionMetrics) {
+        self.executions += 1;
+        self.totals.duration_micros += metrics.duration_micros;
+        self.totals.bytes_sent += metrics.bytes_sent;
+        self.totals.messages_sent += metrics.messages_sent;
+        self.totals.messages_received += metrics.messages_received;
+    }
+
+    /// Average metrics per execution
+    pub fn average(&self) -> ExecutionMetrics {
+        let executions = self.executions.max(1);
+        ExecutionMetrics {
+            duration_micros: self.totals.duration_micros / executions,
+            bytes_sent: self.totals.bytes_sent / executions,
+            messages_sent: self.totals.messages_sent / executions,
+            messages_received: self.totals.messages_received / executions,
+        }
+    }
+}
+
+/// A Feedback that never considers inputs interesting, but collects the execution metrics
+/// reported by the runner into `ExecutionMetricsStats` (e.g. for `BenchStatsStage`) and reports
+/// their averages as user stats.
+pub struct ExecutionMetricsFeedback {
+    handle: Handle<StdOutObserver>,
+}
+
+impl ExecutionMetricsFeedback {
+    pub fn new(handle: Handle<StdOutObserver>) -> Self {
+        Self { handle }
+    }
+}
+
+impl Named for ExecutionMetricsFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ExecutionMetricsFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for ExecutionMetricsFeedback {}
+
+impl<EM, OT, S> Feedback<EM, IrInput, OT, S> for ExecutionMetricsFeedback
+where
+    OT: ObserversTuple<IrInput, S>,
+    S: HasCorpus<IrInput> + HasMetadata + HasExecutions,
+    EM: EventFirer<IrInput, S>,
+{
+    #[inline]
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &IrInput,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let stdout_observer = observers
+            .get(&self.handle)
+            .ok_or_else(|| Error::illegal_state("StdOutObserver is missing"))?;
+        if let Some(metrics) = stdout_observer
+            .output
+            .as_deref()
+            .and_then(ExecutionMetrics::parse_output)
+        {
+            state
+                .metadata_or_insert_with(ExecutionMetricsStats::default)
+                .add(&metrics);
+        }
+
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        _observers: &OT,
+        _testcase: &mut Testcase<IrInput>,
+    ) -> Result<(), Error> {
+        // Only report on corpus additions, firing events on every execution would be too costly
+        let Ok(stats) = state.metadata::<ExecutionMetricsStats>() else {
+            return Ok(());
+        };
+        let average = stats.average();
+        for (name, value) in [
+            ("avg_exec_us", average.duration_micros),
+            ("avg_msgs_sent", average.messages_sent),
+            ("avg_msgs_received", average.messages_received),
+        ] {
+            manager.fire(
+                state,
+                EventWithStats::with_current_time(
+                    Event::UpdateUserStats {
+                        name: Cow::from(name),
+                        value: UserStats::new(UserStatsValue::Number(value), AggregatorOps::Avg),
+                        phantom: PhantomData,
+                    },
+                    *state.executions(),
+                ),
+            )?;
+        }
+
         Ok(())
     }
 }
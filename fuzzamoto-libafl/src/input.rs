@@ -1,10 +1,23 @@
 use std::{fs::File, hash::Hash, io::Read, path::PathBuf};
 
+#[cfg(not(feature = "compile_in_vm"))]
+use std::{
+    hash::{DefaultHasher, Hasher},
+    path::Path,
+    sync::OnceLock,
+};
+
 use fuzzamoto_ir::Program;
+#[cfg(not(feature = "compile_in_vm"))]
+use fuzzamoto_ir::compiler::CompilerError;
 
 use libafl::inputs::{HasTargetBytes, Input};
 use libafl_bolts::{HasLen, ownedref::OwnedSlice};
 
+/// Directory that programs failing to compile are written to (if set)
+#[cfg(not(feature = "compile_in_vm"))]
+static COMPILE_ERRORS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Hash)]
 pub struct IrInput {
     ir: Program,
@@ -29,12 +42,65 @@ impl IrInput {
         let mut file = File::open(path).unwrap();
         let mut bytes = vec![];
         file.read_to_end(&mut bytes).unwrap();
-        let program = postcard::from_bytes(&bytes).unwrap();
+        let program = if path.extension().is_some_and(|ext| ext == "json") {
+            Program::from_json(&bytes).unwrap()
+        } else {
+            postcard::from_bytes(&bytes).unwrap()
+        };
 
         Self { ir: program }
     }
 }
 
+#[cfg(not(feature = "compile_in_vm"))]
+impl IrInput {
+    /// Set the directory that programs failing to compile are written to. Only the first call has
+    /// an effect.
+    pub fn set_compile_errors_dir(dir: PathBuf) {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::warn!("Failed to create compile errors dir {}: {e}", dir.display());
+            return;
+        }
+        let _ = COMPILE_ERRORS_DIR.set(dir);
+    }
+
+    /// Compile the IR program and serialize the result.
+    pub fn compile(&self) -> Result<Vec<u8>, CompilerError> {
+        let mut compiler = fuzzamoto_ir::compiler::Compiler::new();
+        let compiled_input = compiler.compile(self.ir())?;
+
+        Ok(postcard::to_allocvec(&compiled_input).expect("serialization should never fail"))
+    }
+
+    /// Write the program to the compile errors dir, so that compiler bugs can be reproduced.
+    fn dump_compile_error(&self, error: &CompilerError) {
+        let Some(dir) = COMPILE_ERRORS_DIR.get() else {
+            return;
+        };
+
+        let mut hasher = DefaultHasher::new();
+        self.ir().hash(&mut hasher);
+        let path = dir.join(format!("{:016x}.ir", hasher.finish()));
+        if path.exists() {
+            return;
+        }
+
+        if let Err(e) = Self::write_program(&path, self.ir()) {
+            log::warn!("Failed to write {}: {e}", path.display());
+            return;
+        }
+        log::warn!(
+            "Wrote program that failed to compile ({error}) to {}",
+            path.display()
+        );
+    }
+
+    fn write_program(path: &Path, program: &Program) -> std::io::Result<()> {
+        let bytes = postcard::to_allocvec(program).map_err(std::io::Error::other)?;
+        std::fs::write(path, bytes)
+    }
+}
+
 impl HasLen for IrInput {
     fn len(&self) -> usize {
         self.ir().instructions.len()
@@ -45,14 +111,15 @@ impl HasTargetBytes for IrInput {
     fn target_bytes(&self) -> OwnedSlice<'_, u8> {
         #[cfg(not(feature = "compile_in_vm"))]
         {
-            let mut compiler = fuzzamoto_ir::compiler::Compiler::new();
-
-            let compiled_input = compiler
-                .compile(self.ir())
-                .expect("Compilation should never fail");
-
-            let mut bytes =
-                postcard::to_allocvec(&compiled_input).expect("serialization should never fail");
+            // Programs that fail to compile result in an empty input, which the scenario skips.
+            let mut bytes = match self.compile() {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    log::warn!("Failed to compile input: {e}");
+                    self.dump_compile_error(&e);
+                    Vec::new()
+                }
+            };
             log::trace!("Compiled input size: {}", bytes.len());
             if bytes.len() > 8 * 1024 * 1024 {
                 bytes = Vec::new();
@@ -73,3 +140,70 @@ impl HasTargetBytes for IrInput {
         }
     }
 }
+
+#[cfg(all(test, not(feature = "compile_in_vm")))]
+mod tests {
+    use super::*;
+    use fuzzamoto_ir::{Instruction, Operation, ProgramBuilder, ProgramContext};
+    use libafl_bolts::AsSlice;
+
+    fn context() -> ProgramContext {
+        ProgramContext {
+            num_nodes: 1,
+            num_connections: 1,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn valid_program_compiles() {
+        let mut builder = ProgramBuilder::new(context());
+        let conn_var = builder.force_append_expect_output(vec![], &Operation::LoadConnection(0));
+        builder.force_append(vec![conn_var.index], &Operation::SendGetAddr);
+        let input = IrInput::new(builder.finalize().unwrap());
+
+        let bytes = input.compile().expect("failed to compile program");
+        assert!(!bytes.is_empty());
+        assert_eq!(input.target_bytes().as_slice(), bytes.as_slice());
+    }
+
+    #[test]
+    fn invalid_program_is_skipped_and_dumped() {
+        let dir =
+            std::env::temp_dir().join(format!("fuzzamoto-compile-errors-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        IrInput::set_compile_errors_dir(dir.clone());
+
+        // `SendGetAddr` without its connection input
+        let program = Program::unchecked_new(
+            context(),
+            vec![Instruction {
+                inputs: vec![],
+                operation: Operation::SendGetAddr,
+            }],
+        );
+        let input = IrInput::new(program);
+        assert!(matches!(
+            input.compile(),
+            Err(CompilerError::IncorrectNumberOfInputs)
+        ));
+
+        // The input is empty instead of panicking, and the program is written to the compile
+        // errors dir (once)
+        assert!(input.target_bytes().as_slice().is_empty());
+        assert!(input.target_bytes().as_slice().is_empty());
+        let dumped: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(dumped.len(), 1);
+        let program: Program = postcard::from_bytes(&std::fs::read(&dumped[0]).unwrap()).unwrap();
+        assert_eq!(program.instructions.len(), 1);
+        assert!(matches!(
+            program.instructions[0].operation,
+            Operation::SendGetAddr
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
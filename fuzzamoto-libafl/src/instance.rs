@@ -4,15 +4,16 @@ use fuzzamoto_ir::{
     AddConnectionGenerator, AddTxToBlockGenerator, AddrRelayGenerator, AddrRelayV2Generator,
     AdvanceTimeGenerator, BlockGenerator, BlockTxnGenerator, BloomFilterAddGenerator,
     BloomFilterClearGenerator, BloomFilterLoadGenerator, CombineMutator, CompactBlockGenerator,
-    CompactFilterQueryGenerator, GetAddrGenerator, GetDataGenerator, HeaderGenerator, InputMutator,
-    InventoryGenerator, LargeTxGenerator, LongChainGenerator, OneParentOneChildGenerator,
-    OperationMutator, Program, ReorgBlockGenerator, SendBlockGenerator, SendMessageGenerator,
-    SingleTxGenerator, TipBlockGenerator, TxoGenerator, WitnessGenerator,
-    cutting::CuttingMinimizer, instr_block::InstrBlockMinimizer, nopping::NoppingMinimizer,
+    CompactFilterQueryGenerator, DribbleMessageGenerator, GetAddrGenerator, GetDataGenerator,
+    HeaderGenerator, InputMutator, InventoryGenerator, LargeTxGenerator, LongChainGenerator,
+    OneParentOneChildGenerator, OperationMutator, Program, RawFrameGenerator, ReorgBlockGenerator,
+    RestartNodeGenerator, SendBlockGenerator, SendMessageGenerator, SingleTxGenerator,
+    TipBlockGenerator, TxoGenerator, WitnessGenerator, cutting::CuttingMinimizer,
+    instr_block::InstrBlockMinimizer, nopping::NoppingMinimizer,
 };
 
 use libafl::{
-    Error, NopFuzzer,
+    Error, HasMetadata, NopFuzzer,
     corpus::{CachedOnDiskCorpus, Corpus, CorpusId, OnDiskCorpus, Testcase},
     events::{
         ClientDescription, EventFirer, EventReceiver, EventRestarter, NopEventManager,
@@ -25,7 +26,7 @@ use libafl::{
     mutators::{ComposedByMutations, TuneableScheduledMutator},
     observers::{CanTrack, HitcountsMapObserver, StdMapObserver, StdOutObserver, TimeObserver},
     schedulers::{
-        IndexesLenTimeMinimizerScheduler, QueueScheduler, StdWeightedScheduler,
+        IndexesLenTimeMinimizerScheduler, QueueScheduler, WeightedScheduler,
         powersched::PowerSchedule,
     },
     stages::{ClosureStage, IfStage, StagesTuple, TuneableMutationalStage, WhileStage},
@@ -41,17 +42,22 @@ use libafl_bolts::{
 
 use std::collections::BTreeMap;
 
+use fuzzamoto::remote::RemoteClient;
 use libafl_nyx::{executor::NyxExecutor, helper::NyxHelper, settings::NyxSettings};
 use rand::{SeedableRng, rngs::SmallRng};
 use typed_builder::TypedBuilder;
 
 use crate::{
-    feedbacks::{CaptureTimeoutFeedback, CrashCauseFeedback},
+    executors::{RemoteExecutor, TargetExecutor},
+    feedbacks::{
+        CaptureTimeoutFeedback, CharacterizationFeedback, CrashCauseFeedback,
+        ExecutionMetricsFeedback,
+    },
     input::IrInput,
     mutators::{IrGenerator, IrMutator, IrSpliceMutator, LibAflByteMutator},
-    options::FuzzerOptions,
-    schedulers::SupportedSchedulers,
-    stages::{IrMinimizerStage, ProbingStage, StabilityCheckStage, VerifyTimeoutsStage},
+    options::{FuzzerOptions, SchedulerPolicy},
+    schedulers::{IrScheduler, IrSchedulerMetadata, IrTestcaseScore, SupportedSchedulers},
+    stages::{IrMinimizerStage, ProbingStage, StabilityCheckStage, TrimStage, VerifyTimeoutsStage},
 };
 
 #[cfg(feature = "bench")]
@@ -130,10 +136,34 @@ where
             ))
             .build();
 
-        let helper = NyxHelper::new(self.options.shared_dir(), settings)?;
+        // Testcases run on a remote runner daemon if one is configured, otherwise in Nyx
+        let remote = self
+            .options
+            .remote
+            .as_ref()
+            .map(|addr| RemoteClient::connect(addr.as_str()).map_err(Error::unknown))
+            .transpose()?;
+        let helper = if remote.is_some() {
+            None
+        } else {
+            Some(NyxHelper::new(self.options.shared_dir(), settings)?)
+        };
+        let (map, map_size) = match &helper {
+            Some(helper) => (helper.bitmap_buffer, helper.bitmap_size),
+            None => {
+                let map = Box::leak(vec![0u8; self.options.remote_map_size].into_boxed_slice());
+                (map.as_mut_ptr(), map.len())
+            }
+        };
+
+        #[cfg(not(feature = "compile_in_vm"))]
+        IrInput::set_compile_errors_dir(
+            self.options
+                .compile_errors_dir(self.client_description.core_id()),
+        );
 
         let trace_observer = HitcountsMapObserver::new(unsafe {
-            StdMapObserver::from_mut_ptr("trace", helper.bitmap_buffer, helper.bitmap_size)
+            StdMapObserver::from_mut_ptr("trace", map, map_size)
         })
         .track_indices()
         .track_novelties();
@@ -152,7 +182,7 @@ where
             u32::try_from(self.client_description.core_id().0)
                 .expect("core_id should fit into u32"),
             map_feedback_name.clone(),
-            helper.bitmap_size,
+            map_size,
             Duration::from_secs(self.options.bench_snapshot_secs()),
             self.options.bench_dir().join(format!(
                 "bench-cpu_{:03}.csv",
@@ -177,6 +207,13 @@ where
             ),
             // Time feedback
             TimeFeedback::new(&time_observer),
+            // Target state feedback
+            feedback_and_fast!(
+                ConstFeedback::new(self.options.characterization_feedback),
+                CharacterizationFeedback::new(stdout_observer_handle.clone())
+            ),
+            // Execution metrics reported by the runner (never interesting)
+            ExecutionMetricsFeedback::new(stdout_observer_handle.clone()),
         );
 
         let enable_capture_timeouts = Rc::new(RefCell::new(true));
@@ -236,16 +273,22 @@ where
             // Avoid scheduler metatdata dependency
             SupportedSchedulers::Queue(QueueScheduler::new(), PhantomData)
         } else {
+            if self.options.scheduler == SchedulerPolicy::Ir
+                && !state.has_metadata::<IrSchedulerMetadata>()
+            {
+                // Enables the IR weights of `IrTestcaseScore`
+                state.add_metadata(IrSchedulerMetadata::default());
+            }
             // A minimization+queue policy to get testcasess from the corpus
             SupportedSchedulers::LenTimeMinimizer(
-                IndexesLenTimeMinimizerScheduler::new(
+                IrScheduler::new(IndexesLenTimeMinimizerScheduler::new(
                     &trace_observer,
-                    StdWeightedScheduler::with_schedule(
+                    WeightedScheduler::<_, IrTestcaseScore, _>::with_schedule(
                         &mut state,
                         &trace_observer,
                         Some(PowerSchedule::explore()),
                     ),
-                ),
+                )),
                 PhantomData,
             )
         };
@@ -257,11 +300,28 @@ where
         // A fuzzer with feedbacks and a corpus scheduler
         let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
 
+        let mut executor = match remote {
+            Some(client) => TargetExecutor::Remote(RemoteExecutor::new(
+                client,
+                observers,
+                stdout_observer_handle.clone(),
+                map,
+                map_size,
+                timeout,
+            )),
+            None => TargetExecutor::Nyx(
+                NyxExecutor::builder()
+                    .stdout(stdout_observer_handle.clone())
+                    .build(
+                        helper.expect("Nyx is used without a remote daemon"),
+                        observers,
+                    ),
+            ),
+        };
+
         if let Some(rerun_input) = &self.options.rerun_input {
             let input = IrInput::unparse(rerun_input);
 
-            let mut executor = NyxExecutor::builder().build(helper, observers);
-
             let exit_kind = executor
                 .run_target(
                     &mut NopFuzzer::new(),
@@ -275,11 +335,11 @@ where
             process::exit(0);
         }
 
-        let mut executor = NyxExecutor::builder()
-            .stdout(stdout_observer_handle.clone())
-            .build(helper, observers);
-
-        let ir_context_dump = self.options.work_dir().join("dump/ir.context");
+        let ir_context_dump = self
+            .options
+            .remote_context
+            .clone()
+            .unwrap_or_else(|| self.options.work_dir().join("dump/ir.context"));
         let bytes = std::fs::read(ir_context_dump).expect("Could not read ir context file");
         let full_program_context: fuzzamoto_ir::FullProgramContext =
             postcard::from_bytes(&bytes).expect("could not deser ir context");
@@ -343,8 +403,13 @@ where
             (50.0, IrGenerator::new(SingleTxGenerator, rng.clone())),
             (50.0, IrGenerator::new(LongChainGenerator, rng.clone())),
             (50.0, IrGenerator::new(LargeTxGenerator, rng.clone())),
+            // 1P1C packages are only accepted by targets that support package relay
             (
-                50.0,
+                if full_program_context.capabilities.package_relay {
+                    50.0
+                } else {
+                    0.0
+                },
                 IrGenerator::new(OneParentOneChildGenerator, rng.clone())
             ),
             (
@@ -372,7 +437,10 @@ where
             (50.0, IrGenerator::new(AddTxToBlockGenerator, rng.clone())),
             (
                 10.0,
-                IrGenerator::new(CompactFilterQueryGenerator, rng.clone())
+                IrGenerator::new(
+                    CompactFilterQueryGenerator::new(full_program_context.headers.clone()),
+                    rng.clone()
+                )
             ),
             (
                 20.0,
@@ -392,15 +460,32 @@ where
                 IrGenerator::new(AddrRelayV2Generator::default(), rng.clone())
             ),
             (10.0, IrGenerator::new(GetAddrGenerator, rng.clone())),
+            (
+                5.0,
+                IrGenerator::new(
+                    RawFrameGenerator::new(full_program_context.magic),
+                    rng.clone()
+                )
+            ),
+            (5.0, IrGenerator::new(DribbleMessageGenerator, rng.clone())),
+            (1.0, IrGenerator::new(RestartNodeGenerator, rng.clone())),
             (200.0, IrGenerator::new(CompactBlockGenerator, rng.clone())),
             (200.0, IrGenerator::new(BlockTxnGenerator, rng.clone())),
             (
                 20.0,
-                IrGenerator::new(AddConnectionGenerator::handshake_outbound(), rng.clone())
+                IrGenerator::new(
+                    AddConnectionGenerator::handshake_outbound()
+                        .with_capabilities(&full_program_context.capabilities),
+                    rng.clone()
+                )
             ),
             (
                 50.0,
-                IrGenerator::new(AddConnectionGenerator::handshake_inbound(), rng.clone())
+                IrGenerator::new(
+                    AddConnectionGenerator::handshake_inbound()
+                        .with_capabilities(&full_program_context.capabilities),
+                    rng.clone()
+                )
             ),
             (
                 20.0,
@@ -440,6 +525,20 @@ where
         // Counter holding the number of successful minimizations in the last round
         let continue_minimizing = RefCell::new(1u64);
 
+        let trim = TrimStage::new(trace_handle.clone(), self.options.trim_interval, 200);
+        let sync_stage = IfStage::new(
+            |_, _, _, _| Ok(self.options.sync_dir.is_some()),
+            tuple_list!(SyncStage::new(
+                self.options.sync_dir.clone().unwrap_or_default(),
+                format!(
+                    "fuzzamoto-libafl-cpu_{:03}",
+                    self.client_description.core_id().0
+                ),
+                full_program_context.context.clone(),
+                self.options.sync_lift.clone(),
+                Duration::from_secs(self.options.sync_interval),
+            )),
+        );
         let probing = ProbingStage::new(&stdout_observer_handle);
         let stability = StabilityCheckStage::new(&map_observer_handle, &map_feedback_name, 8);
         let mut stages = tuple_list!(
@@ -481,9 +580,11 @@ where
                 |_, _, _, _| Ok(self.options.minimize_input.is_none()),
                 tuple_list!(
                     stability,
+                    trim,
                     probing,
                     TuneableMutationalStage::new(&mut state, mutator),
                     timeout_verify_stage,
+                    sync_stage,
                     bench_stats_stage,
                 )
             ),
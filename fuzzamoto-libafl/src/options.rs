@@ -16,6 +16,17 @@ pub enum Profile {
     Connections,
 }
 
+/// Policies that define how corpus entries are scheduled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SchedulerPolicy {
+    /// LibAFL's weighted power schedule
+    #[default]
+    Default,
+    /// Power schedule additionally weighted by the IR programs, favoring programs that use
+    /// operations rare in the corpus and programs that are short but deeply nested
+    Ir,
+}
+
 #[readonly::make]
 #[derive(Parser, Debug)]
 #[clap(author, about, long_about = None)]
@@ -80,6 +91,13 @@ pub struct FuzzerOptions {
     )]
     pub prune_disabled: bool,
 
+    #[arg(
+        long,
+        help = "Treat new target states reported by the scenario (e.g. tip, mempool size) as interesting (the share directory has to be created with `fuzzamoto-cli init --characterize`)",
+        default_value_t = false
+    )]
+    pub characterization_feedback: bool,
+
     #[arg(
         long,
         help = "Pushover token",
@@ -165,6 +183,60 @@ pub struct FuzzerOptions {
         help = "Profile that defines which generators are enabled"
     )]
     pub profile: Profile,
+
+    #[arg(
+        long,
+        default_value = "default",
+        help = "Policy that defines how corpus entries are scheduled"
+    )]
+    pub scheduler: SchedulerPolicy,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Trim corpus entries with the IR minimizers every N-th time they are scheduled, keeping reductions that preserve their coverage (0 disables trimming)"
+    )]
+    pub trim_interval: usize,
+
+    #[arg(
+        long,
+        help = "AFL++ sync directory (-o of afl-fuzz) to import testcases from and export the corpus to"
+    )]
+    pub sync_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma-separated list of sync directory instances running the generic scenario, whose testcases are lifted into IR"
+    )]
+    pub sync_lift: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Interval in seconds between syncs with the sync directory",
+        default_value_t = 60
+    )]
+    pub sync_interval: u64,
+
+    #[arg(
+        long,
+        requires = "remote_context",
+        help = "Address of a remote runner daemon (fuzzamoto-cli remote-daemon) to run testcases on instead of Nyx"
+    )]
+    pub remote: Option<String>,
+
+    #[arg(
+        long,
+        help = "IR context dumped by the daemon's scenario (DUMP_CONTEXT), used instead of the one dumped in Nyx"
+    )]
+    pub remote_context: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Size of the coverage map of the remote runner daemon (--map-size)",
+        default_value_t = 65536
+    )]
+    pub remote_map_size: usize,
 }
 
 fn unix_time() -> u64 {
@@ -221,6 +293,12 @@ impl FuzzerOptions {
         dir
     }
 
+    pub fn compile_errors_dir(&self, core_id: CoreId) -> PathBuf {
+        let mut dir = self.output_dir(core_id).clone();
+        dir.push("compile_errors");
+        dir
+    }
+
     /// Returns the weight for a mutator/generator, or 0.0 if it's disabled
     pub fn mutator_weight<R: RngCore>(&self, name: &str, weight: f32, rng: &mut R) -> f32 {
         let base_weight = match &self.mutators {
@@ -240,6 +318,8 @@ impl FuzzerOptions {
                             "AddConnectionGenerator:in:handshake",
                             "AddConnectionGenerator:out",
                             "AddConnectionGenerator:in",
+                            // Restarts are slow
+                            "RestartNodeGenerator",
                         ];
                         if DISABLED.contains(&name) {
                             0.0
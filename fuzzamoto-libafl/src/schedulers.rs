@@ -1,12 +1,24 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
+use fuzzamoto_ir::ProgramStats;
 use libafl::{
     Error, HasMetadata,
     corpus::{Corpus, CorpusId, HasTestcase, SchedulerTestcaseMetadata, Testcase},
-    schedulers::{HasQueueCycles, RemovableScheduler, Scheduler},
+    schedulers::{
+        HasQueueCycles, RemovableScheduler, Scheduler,
+        testcase_score::{CorpusWeightTestcaseScore, TestcaseScore},
+    },
     state::HasCorpus,
 };
-use libafl_bolts::tuples::MatchName;
+use libafl_bolts::{impl_serdeany, tuples::MatchName};
+use serde::{Deserialize, Serialize};
+
+use crate::input::IrInput;
+
+/// Operations used by at most this share of the corpus are considered rare
+const RARE_OPERATION_SHARE: f64 = 0.1;
+/// Number of instructions that count as one level of block nesting when weighing program shapes
+const INSTRUCTIONS_PER_LEVEL: f64 = 32.0;
 
 pub enum SupportedSchedulers<Q, M> {
     Queue(Q, PhantomData<M>),
@@ -110,3 +122,197 @@ where
         }
     }
 }
+
+/// Corpus wide IR statistics, only present in the state if the IR scheduling policy is enabled
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IrSchedulerMetadata {
+    /// Number of programs in the corpus
+    programs: u64,
+    /// Number of programs in the corpus using an operation (by `Operation::kind`)
+    operations: HashMap<String, u64>,
+}
+impl_serdeany!(IrSchedulerMetadata);
+
+impl IrSchedulerMetadata {
+    fn add(&mut self, stats: &ProgramStats) {
+        self.programs += 1;
+        for kind in stats.operations.keys() {
+            *self.operations.entry(kind.clone()).or_default() += 1;
+        }
+    }
+
+    fn remove(&mut self, stats: &ProgramStats) {
+        self.programs = self.programs.saturating_sub(1);
+        for kind in stats.operations.keys() {
+            if let Some(count) = self.operations.get_mut(kind) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Replace the statistics of a program that changed (e.g. because it was trimmed)
+    pub fn replace(&mut self, previous: &ProgramStats, stats: &ProgramStats) {
+        self.remove(previous);
+        self.add(stats);
+    }
+
+    /// Number of operation kinds used by `stats` that are rare in the corpus
+    #[expect(clippy::cast_precision_loss)]
+    fn rare_operations(&self, stats: &ProgramStats) -> usize {
+        stats
+            .operations
+            .keys()
+            .filter(|kind| {
+                let count = self.operations.get(*kind).copied().unwrap_or(0);
+                count as f64 <= self.programs as f64 * RARE_OPERATION_SHARE
+            })
+            .count()
+    }
+
+    /// Scheduling weight of a program: programs using rare operations and programs with deep
+    /// block nesting relative to their length are favored.
+    #[expect(clippy::cast_precision_loss)]
+    fn weight(&self, stats: &ProgramStats) -> f64 {
+        let rarity = 1.0 + 0.5 * self.rare_operations(stats).min(6) as f64;
+        let shape = (1.0 + stats.max_depth as f64)
+            / (1.0 + stats.instructions as f64 / INSTRUCTIONS_PER_LEVEL);
+        rarity * shape.clamp(0.25, 4.0)
+    }
+}
+
+/// Statistics of a corpus entry's program, as used by `IrTestcaseScore`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrTestcaseMetadata(pub ProgramStats);
+impl_serdeany!(IrTestcaseMetadata);
+
+/// Testcase score of the weighted scheduler, scaling LibAFL's corpus weight with the
+/// `IrSchedulerMetadata` weight of the testcase's program (if the IR policy is enabled).
+#[derive(Debug, Clone)]
+pub struct IrTestcaseScore;
+
+impl<S> TestcaseScore<IrInput, S> for IrTestcaseScore
+where
+    S: HasCorpus<IrInput> + HasMetadata,
+{
+    fn compute(state: &S, entry: &mut Testcase<IrInput>) -> Result<f64, Error> {
+        let score = CorpusWeightTestcaseScore::compute(state, entry)?;
+        let (Ok(corpus), Ok(program)) = (
+            state.metadata::<IrSchedulerMetadata>(),
+            entry.metadata::<IrTestcaseMetadata>(),
+        ) else {
+            return Ok(score);
+        };
+        Ok(score * corpus.weight(&program.0))
+    }
+}
+
+/// Scheduler wrapper maintaining the `IrSchedulerMetadata` and `IrTestcaseMetadata` used by
+/// `IrTestcaseScore`. Does nothing beyond forwarding to the inner scheduler if the state has no
+/// `IrSchedulerMetadata`.
+pub struct IrScheduler<CS> {
+    inner: CS,
+}
+
+impl<CS> IrScheduler<CS> {
+    pub fn new(inner: CS) -> Self {
+        Self { inner }
+    }
+}
+
+/// Compute the statistics of the program of corpus entry `id` and attach them to the entry
+fn update_testcase_stats<S>(state: &mut S, id: CorpusId) -> Result<ProgramStats, Error>
+where
+    S: HasCorpus<IrInput>,
+{
+    let mut testcase = state.corpus().get(id)?.borrow_mut();
+    let stats = testcase.load_input(state.corpus())?.ir().stats();
+    testcase.add_metadata(IrTestcaseMetadata(stats.clone()));
+    Ok(stats)
+}
+
+impl<CS, S> RemovableScheduler<IrInput, S> for IrScheduler<CS>
+where
+    CS: RemovableScheduler<IrInput, S>,
+    S: HasCorpus<IrInput> + HasMetadata,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        testcase: &Option<Testcase<IrInput>>,
+    ) -> Result<(), Error> {
+        if let (Ok(corpus), Some(Ok(program))) = (
+            state.metadata_mut::<IrSchedulerMetadata>(),
+            testcase
+                .as_ref()
+                .map(|testcase| testcase.metadata::<IrTestcaseMetadata>()),
+        ) {
+            corpus.remove(&program.0);
+        }
+        self.inner.on_remove(state, id, testcase)
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        prev: &Testcase<IrInput>,
+    ) -> Result<(), Error> {
+        if state.has_metadata::<IrSchedulerMetadata>() {
+            let stats = update_testcase_stats(state, id)?;
+            let corpus = state.metadata_mut::<IrSchedulerMetadata>()?;
+            match prev.metadata::<IrTestcaseMetadata>() {
+                Ok(previous) => corpus.replace(&previous.0, &stats),
+                Err(_) => corpus.add(&stats),
+            }
+        }
+        self.inner.on_replace(state, id, prev)
+    }
+}
+
+impl<CS, S> Scheduler<IrInput, S> for IrScheduler<CS>
+where
+    CS: Scheduler<IrInput, S>,
+    S: HasCorpus<IrInput> + HasMetadata,
+{
+    fn on_add(&mut self, state: &mut S, id: CorpusId) -> Result<(), Error> {
+        if state.has_metadata::<IrSchedulerMetadata>() {
+            let stats = update_testcase_stats(state, id)?;
+            state.metadata_mut::<IrSchedulerMetadata>()?.add(&stats);
+        }
+        self.inner.on_add(state, id)
+    }
+
+    fn next(&mut self, state: &mut S) -> Result<CorpusId, Error> {
+        self.inner.next(state)
+    }
+
+    fn on_evaluation<OTB>(
+        &mut self,
+        state: &mut S,
+        input: &IrInput,
+        observers: &OTB,
+    ) -> Result<(), Error>
+    where
+        OTB: MatchName,
+    {
+        self.inner.on_evaluation(state, input, observers)
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut S,
+        next_id: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.set_current_scheduled(state, next_id)
+    }
+}
+
+impl<CS> HasQueueCycles for IrScheduler<CS>
+where
+    CS: HasQueueCycles,
+{
+    fn queue_cycles(&self) -> u64 {
+        self.inner.queue_cycles()
+    }
+}
@@ -6,7 +6,7 @@ use std::{
 };
 
 use libafl::{
-    Evaluator, ExecutesInput, HasNamedMetadata,
+    Evaluator, ExecutesInput, HasMetadata, HasNamedMetadata,
     corpus::Corpus,
     events::EventFirer,
     executors::{Executor, HasObservers},
@@ -16,7 +16,7 @@ use libafl::{
     state::{HasCorpus, HasExecutions, HasSolutions},
 };
 
-use crate::input::IrInput;
+use crate::{feedbacks::ExecutionMetricsStats, input::IrInput};
 
 /// Stage for collecting fuzzer stats useful for benchmarking.
 ///
@@ -72,7 +72,7 @@ impl<S> Restartable<S> for BenchStatsStage {
 
 impl<E, EM, S, Z, OT> Stage<E, EM, S, Z> for BenchStatsStage
 where
-    S: HasCorpus<IrInput> + HasExecutions + HasSolutions<IrInput> + HasNamedMetadata,
+    S: HasCorpus<IrInput> + HasExecutions + HasSolutions<IrInput> + HasNamedMetadata + HasMetadata,
     E: Executor<EM, IrInput, S, Z> + HasObservers<Observers = OT>,
     EM: EventFirer<IrInput, S>,
     Z: Evaluator<E, EM, IrInput, S> + ExecutesInput<E, EM, IrInput, S>,
@@ -118,6 +118,12 @@ where
         let corpus_size = state.corpus().count();
         let crashes = state.solutions().count();
 
+        // Averages over all executions so far, as reported by the runner
+        let metrics = state
+            .metadata::<ExecutionMetricsStats>()
+            .map(ExecutionMetricsStats::average)
+            .unwrap_or_default();
+
         let Some(parent) = self.stats_file_path.parent() else {
             log::warn!(
                 "bench_stats: cpu={} missing parent dir, skipping write",
@@ -149,7 +155,7 @@ where
         if !self.csv_header_written {
             if writeln!(
                 &stats_file,
-                "elapsed_s,execs,execs_per_sec,coverage_pct,corpus_size,crashes"
+                "elapsed_s,execs,execs_per_sec,coverage_pct,corpus_size,crashes,avg_exec_us,avg_bytes_sent,avg_msgs_sent,avg_msgs_received"
             )
             .is_err()
             {
@@ -174,8 +180,17 @@ where
 
         if writeln!(
             &stats_file,
-            "{:.3},{},{:.2},{:.4},{},{}",
-            elapsed, total_execs, execs_per_sec, coverage_pct, corpus_size, crashes
+            "{:.3},{},{:.2},{:.4},{},{},{},{},{},{}",
+            elapsed,
+            total_execs,
+            execs_per_sec,
+            coverage_pct,
+            corpus_size,
+            crashes,
+            metrics.duration_micros,
+            metrics.bytes_sent,
+            metrics.messages_sent,
+            metrics.messages_received
         )
         .is_err()
         {
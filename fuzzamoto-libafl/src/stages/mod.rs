@@ -9,6 +9,12 @@ pub use probe::*;
 pub mod stability_check;
 pub use stability_check::*;
 
+pub mod sync;
+pub use sync::*;
+
+pub mod trim;
+pub use trim::*;
+
 pub mod verify_timeouts;
 
 pub use verify_timeouts::*;
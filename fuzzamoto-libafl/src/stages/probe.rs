@@ -1,4 +1,6 @@
 use crate::input::IrInput;
+use fuzzamoto::runners::METRICS_PREFIX;
+use fuzzamoto::scenarios::characterization::CHARACTERIZATION_PREFIX;
 use fuzzamoto_ir::{Instruction, Operation};
 use fuzzamoto_ir::{ProbeResult, ProbeResults};
 use libafl::ExecutesInput;
@@ -164,7 +166,10 @@ where
             let chunks: Vec<Vec<u8>> = buffer.split(|b| *b == b'\n').map(<[u8]>::to_vec).collect();
 
             for chunk in chunks {
-                if chunk.is_empty() {
+                if chunk.is_empty()
+                    || chunk.starts_with(CHARACTERIZATION_PREFIX.as_bytes())
+                    || chunk.starts_with(METRICS_PREFIX.as_bytes())
+                {
                     continue;
                 }
 
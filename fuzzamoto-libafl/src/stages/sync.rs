@@ -0,0 +1,197 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use fuzzamoto_ir::{Program, ProgramContext, lift::lift_generic};
+use libafl::{
+    Error, Evaluator, HasMetadata,
+    corpus::{Corpus, CorpusId},
+    inputs::Input,
+    stages::{Restartable, Stage},
+    state::HasCorpus,
+};
+use libafl_bolts::impl_serdeany;
+use serde::{Deserialize, Serialize};
+
+use crate::input::IrInput;
+
+/// Files imported from and corpus entries exported to the sync directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncMetadata {
+    imported: HashSet<PathBuf>,
+    exported: HashSet<CorpusId>,
+}
+impl_serdeany!(SyncMetadata);
+
+/// Stage that periodically syncs with co-running AFL++ instances through an AFL++ sync directory
+/// (`-o` of `afl-fuzz`):
+///
+/// - New testcases in the other instances' `<sync_dir>/<instance>/queue` directories are imported.
+///   Testcases that are IR programs for our program context are evaluated as they are, testcases
+///   of the instances in `lift` (which run the generic scenario) are lifted into IR first and
+///   anything else is skipped. Other `fuzzamoto-libafl` instances are skipped, LibAFL already
+///   shares their corpus.
+/// - Our corpus is exported to `<sync_dir>/<name>/queue/id:NNNNNN` (postcard encoded IR programs),
+///   where AFL++ instances (and other `fuzzamoto-libafl` instances) pick it up.
+pub struct SyncStage {
+    sync_dir: PathBuf,
+    name: String,
+    context: ProgramContext,
+    lift: Vec<String>,
+    interval: Duration,
+    last_sync: Option<Instant>,
+}
+
+impl SyncStage {
+    pub fn new(
+        sync_dir: PathBuf,
+        name: String,
+        context: ProgramContext,
+        lift: Vec<String>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            sync_dir,
+            name,
+            context,
+            lift,
+            interval,
+            last_sync: None,
+        }
+    }
+
+    fn queue_dir(&self) -> PathBuf {
+        self.sync_dir.join(&self.name).join("queue")
+    }
+
+    /// Testcases in the queues of the other instances, with whether they should be lifted
+    fn foreign_testcases(&self) -> Vec<(PathBuf, bool)> {
+        let Ok(instances) = std::fs::read_dir(&self.sync_dir) else {
+            return Vec::new();
+        };
+        let mut testcases: Vec<(PathBuf, bool)> = instances
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|instance| instance.is_dir())
+            .filter_map(|instance| {
+                let name = instance.file_name()?.to_string_lossy().into_owned();
+                if name.starts_with('.') || name.starts_with("fuzzamoto-libafl-") {
+                    return None;
+                }
+                let lift = self.lift.contains(&name);
+                Some((std::fs::read_dir(instance.join("queue")).ok()?, lift))
+            })
+            .flat_map(|(queue, lift)| {
+                queue.filter_map(move |entry| entry.ok().map(|entry| (entry.path(), lift)))
+            })
+            .filter(|(path, _)| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        testcases.sort();
+        testcases
+    }
+
+    /// Convert a foreign testcase into an IR program for our program context
+    fn convert(&self, path: &Path, lift: bool) -> Result<IrInput, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        if lift {
+            return lift_generic(&self.context, &bytes).map(IrInput::new);
+        }
+        match postcard::take_from_bytes::<Program>(&bytes) {
+            Ok((program, []))
+                if program.context == self.context && program.is_statically_valid() =>
+            {
+                Ok(IrInput::new(program))
+            }
+            _ => Err("Not an IR program for this program context".to_string()),
+        }
+    }
+}
+
+impl<S> Restartable<S> for SyncStage {
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<E, EM, S, Z> Stage<E, EM, S, Z> for SyncStage
+where
+    S: HasCorpus<IrInput> + HasMetadata,
+    Z: Evaluator<E, EM, IrInput, S>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        if self
+            .last_sync
+            .is_some_and(|last_sync| last_sync.elapsed() < self.interval)
+        {
+            return Ok(());
+        }
+        self.last_sync = Some(Instant::now());
+
+        let new_testcases: Vec<(PathBuf, bool)> = {
+            let sync = state.metadata_or_insert_with(SyncMetadata::default);
+            self.foreign_testcases()
+                .into_iter()
+                .filter(|(path, _)| sync.imported.insert(path.clone()))
+                .collect()
+        };
+
+        let (mut imported, mut interesting) = (0, 0);
+        for (path, lift) in new_testcases {
+            let input = match self.convert(&path, lift) {
+                Ok(input) => input,
+                Err(e) => {
+                    log::debug!("Skipping {}: {e}", path.display());
+                    continue;
+                }
+            };
+            imported += 1;
+            match fuzzer.evaluate_input(state, executor, manager, &input) {
+                Ok((_, Some(_))) => interesting += 1,
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to evaluate {}: {e}", path.display()),
+            }
+        }
+
+        let queue_dir = self.queue_dir();
+        std::fs::create_dir_all(&queue_dir)?;
+        let ids: Vec<CorpusId> = {
+            let sync = state.metadata::<SyncMetadata>()?;
+            state
+                .corpus()
+                .ids()
+                .filter(|id| !sync.exported.contains(id))
+                .collect()
+        };
+        for id in ids {
+            let input = state.corpus().cloned_input_for_id(id)?;
+            let sync = state.metadata_mut::<SyncMetadata>()?;
+            input.to_file(queue_dir.join(format!("id:{:06}", sync.exported.len())))?;
+            sync.exported.insert(id);
+        }
+
+        if imported > 0 {
+            log::info!(
+                "Synced {imported} testcases from {} ({interesting} added to the corpus)",
+                self.sync_dir.display()
+            );
+        }
+        Ok(())
+    }
+}
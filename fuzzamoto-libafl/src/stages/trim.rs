@@ -0,0 +1,240 @@
+use std::marker::PhantomData;
+
+use fuzzamoto_ir::{
+    Minimizer, cutting::CuttingMinimizer, instr_block::InstrBlockMinimizer,
+    nopping::NoppingMinimizer,
+};
+use libafl::{
+    Evaluator, ExecutesInput, HasMetadata,
+    events::EventFirer,
+    executors::{Executor, HasObservers},
+    inputs::Input,
+    observers::{CanTrack, MapObserver, ObserversTuple},
+    stages::{Restartable, Stage},
+    state::{HasCorpus, HasCurrentTestcase},
+};
+use libafl_bolts::{impl_serdeany, tuples::Handle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::IrInput,
+    schedulers::{IrSchedulerMetadata, IrTestcaseMetadata},
+};
+
+/// Maximum number of rounds of all minimizers per trim
+const MAX_ROUNDS: usize = 4;
+
+/// Trim state of a corpus entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimMetadata {
+    /// Whether the last trim failed to reduce the entry, in which case it is not trimmed again
+    exhausted: bool,
+}
+impl_serdeany!(TrimMetadata);
+
+/// Stage that periodically trims the scheduled corpus entry with the IR minimizers, keeping the
+/// trimmed program if it still covers every map entry the original program covered.
+///
+/// New entries are already minimized by `IrMinimizerStage` (preserving their novelties), this
+/// stage revisits entries every `interval`-th time they are scheduled to keep the average program
+/// length (and thus the cost of each execution) down over long campaigns.
+pub struct TrimStage<T, O> {
+    trace_handle: Handle<T>,
+    interval: usize,
+    max_consecutive_failures: usize,
+    _phantom: PhantomData<O>,
+}
+
+impl<T, O> TrimStage<T, O>
+where
+    O: MapObserver,
+    T: AsRef<O> + CanTrack,
+{
+    /// Create a trim stage, an `interval` of 0 disables trimming.
+    pub fn new(trace_handle: Handle<T>, interval: usize, max_consecutive_failures: usize) -> Self {
+        Self {
+            trace_handle,
+            interval,
+            max_consecutive_failures,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, O, S> Restartable<S> for TrimStage<T, O> {
+    fn should_restart(&mut self, _state: &mut S) -> Result<bool, libafl::Error> {
+        Ok(true)
+    }
+
+    fn clear_progress(&mut self, _state: &mut S) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+impl<T, O> TrimStage<T, O>
+where
+    O: MapObserver,
+    T: CanTrack + AsRef<O>,
+{
+    /// Run the minimizer `M` on `input`, returning the smallest program that still covers all of
+    /// `covered` (or `None` if no reduction was found).
+    fn minimize<M, E, EM, S, Z, OT>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        input: &IrInput,
+        covered: &[usize],
+    ) -> Option<IrInput>
+    where
+        M: Minimizer,
+        E: Executor<EM, IrInput, S, Z> + HasObservers<Observers = OT>,
+        Z: ExecutesInput<E, EM, IrInput, S>,
+        OT: ObserversTuple<IrInput, S>,
+    {
+        let mut reduced = None;
+        let mut consecutive_failures = 0;
+        let mut minimizer = M::new(input.ir().clone());
+        while let Some(prog) = minimizer.next() {
+            if consecutive_failures > self.max_consecutive_failures {
+                break;
+            }
+
+            if !prog.is_statically_valid() {
+                minimizer.failure();
+                consecutive_failures += 1;
+                continue;
+            }
+
+            let attempt = IrInput::new(prog);
+            if fuzzer
+                .execute_input(state, executor, manager, &attempt)
+                .is_ok()
+                && executor.observers()[&self.trace_handle]
+                    .as_ref()
+                    .how_many_set(covered)
+                    == covered.len()
+            {
+                reduced = Some(attempt);
+                minimizer.success();
+                consecutive_failures = 0;
+            } else {
+                minimizer.failure();
+                consecutive_failures += 1;
+            }
+        }
+        reduced
+    }
+}
+
+impl<E, EM, S, Z, OT, T, O> Stage<E, EM, S, Z> for TrimStage<T, O>
+where
+    S: HasCorpus<IrInput> + HasCurrentTestcase<IrInput> + HasMetadata,
+    E: Executor<EM, IrInput, S, Z> + HasObservers<Observers = OT>,
+    EM: EventFirer<IrInput, S>,
+    Z: Evaluator<E, EM, IrInput, S> + ExecutesInput<E, EM, IrInput, S>,
+    OT: ObserversTuple<IrInput, S>,
+    O: MapObserver,
+    T: CanTrack + AsRef<O>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), libafl::Error> {
+        if self.interval == 0 {
+            return Ok(());
+        }
+        {
+            let testcase = state.current_testcase()?;
+            let scheduled = testcase.scheduled_count();
+            if scheduled == 0
+                || !scheduled.is_multiple_of(self.interval)
+                || testcase
+                    .metadata::<TrimMetadata>()
+                    .is_ok_and(|trim| trim.exhausted)
+            {
+                return Ok(());
+            }
+        }
+
+        let original = state.current_input_cloned()?;
+        if fuzzer
+            .execute_input(state, executor, manager, &original)
+            .is_err()
+        {
+            return Ok(());
+        }
+        let covered: Vec<usize> = {
+            let map = executor.observers()[&self.trace_handle].as_ref();
+            let initial = map.initial();
+            (0..map.usable_count())
+                .filter(|index| map.get(*index) != initial)
+                .collect()
+        };
+
+        let original_len = original.ir().instructions.len();
+        let mut current = original;
+        for _ in 0..MAX_ROUNDS {
+            let before = current.ir().instructions.len();
+            if let Some(reduced) = self.minimize::<CuttingMinimizer, _, _, _, _, _>(
+                fuzzer, executor, state, manager, &current, &covered,
+            ) {
+                current = reduced;
+            }
+            if let Some(reduced) = self.minimize::<InstrBlockMinimizer, _, _, _, _, _>(
+                fuzzer, executor, state, manager, &current, &covered,
+            ) {
+                current = reduced;
+            }
+            if let Some(reduced) = self.minimize::<NoppingMinimizer, _, _, _, _, _>(
+                fuzzer, executor, state, manager, &current, &covered,
+            ) {
+                current = reduced;
+            }
+            current.ir_mut().remove_nops();
+            if current.ir().instructions.len() >= before {
+                break;
+            }
+        }
+
+        let trimmed_len = current.ir().instructions.len();
+        let exhausted = trimmed_len >= original_len;
+        if exhausted {
+            state
+                .current_testcase_mut()?
+                .add_metadata(TrimMetadata { exhausted });
+            return Ok(());
+        }
+
+        log::info!("Trimmed ir from {original_len} to {trimmed_len} instructions");
+        let stats = current.ir().stats();
+        let previous_stats = {
+            let mut testcase = state.current_testcase_mut()?;
+            testcase.add_metadata(TrimMetadata { exhausted });
+            testcase.set_input(current);
+            if let Some(filepath) = testcase.file_path().clone() {
+                let _ = testcase.input().as_ref().unwrap().to_file(filepath);
+            }
+            let previous = testcase
+                .metadata::<IrTestcaseMetadata>()
+                .ok()
+                .map(|program| program.0.clone());
+            if previous.is_some() {
+                testcase.add_metadata(IrTestcaseMetadata(stats.clone()));
+            }
+            previous
+        };
+        // Keep the operation frequencies of the IR scheduling policy in sync with the corpus
+        if let (Some(previous_stats), Ok(corpus)) =
+            (previous_stats, state.metadata_mut::<IrSchedulerMetadata>())
+        {
+            corpus.replace(&previous_stats, &stats);
+        }
+
+        Ok(())
+    }
+}
@@ -13,6 +13,9 @@
 /// - -DENABLE_NYX: Use nyx hypercalls to let nyx know that a crash has occured.
 /// - -DASAN_LOG_PATH=<path>: Path to the ASan log file.
 /// - -DCUSTOM_BACKTRACE: Enable custom backtrace.
+/// - -DCRASH_CONTEXT_PATH=<path>: Path of the crash context published by the
+///   scenario (see `fuzzamoto::scenarios::crash_context`), which is appended to
+///   the crash log if present.
 ///
 /// Example instructions for compiling the crash handler for use with an ASan
 /// compiled target:
@@ -35,6 +38,9 @@
 #endif
 
 #define ASAN_LOG_PATH "/tmp/asan.log"
+#ifndef CRASH_CONTEXT_PATH
+#define CRASH_CONTEXT_PATH "/tmp/fuzzamoto_crash_context.json"
+#endif
 #define MAX_CUSTOM_BACKTRACE_SIZE 50
 
 static char *log = NULL;
@@ -82,6 +88,28 @@ void append_asan_log() {
   append_log(buffer);
 }
 
+// Fetch the crash context published by the scenario (if any) and append it to
+// the global log
+void append_crash_context() {
+  FILE *file = fopen(CRASH_CONTEXT_PATH, "r");
+  if (file == NULL) {
+    return;
+  }
+
+  static char buffer[0x100000];
+  size_t bytes_read = fread(buffer, 1, sizeof(buffer) - 1, file);
+  fclose(file);
+
+  if (bytes_read == 0) {
+    return;
+  }
+
+  buffer[bytes_read] = '\0';
+  append_log("\n====== CRASH CONTEXT ======\n");
+  append_log(buffer);
+  append_log("\n");
+}
+
 extern void _exit(int);
 
 #ifdef ENABLE_NYX
@@ -113,6 +141,7 @@ extern void _exit(int);
 
 void panic_with_backtrace(const char *extra_msg) {
   append_asan_log();
+  append_crash_context();
 
 #ifdef CUSTOM_BACKTRACE
   char custom_backtrace[0x10000];
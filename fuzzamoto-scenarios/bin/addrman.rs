@@ -0,0 +1,262 @@
+use fuzzamoto::{
+    connections::{ConnectionPool, ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, ResourceUsage, Target, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    consensus::encode,
+    p2p::{
+        ServiceFlags,
+        address::{AddrV2, AddrV2Message, Address},
+        message::NetworkMessage,
+    },
+};
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Number of inbound peers connected during setup and after restarts
+const NUM_PEERS: usize = 8;
+
+#[derive(Arbitrary)]
+enum Network {
+    Ipv4([u8; 4]),
+    Ipv6([u8; 16]),
+    TorV3([u8; 32]),
+    I2p([u8; 32]),
+    Cjdns([u8; 16]),
+    Unknown { id: u8, payload: Vec<u8> },
+}
+
+#[derive(Arbitrary)]
+struct AddrEntry {
+    /// Age of the address in minutes relative to the target's time (negative values are in the
+    /// future)
+    age: i16,
+    services: u64,
+    network: Network,
+    port: u16,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new inbound connection, optionally announcing `addrv2` support
+    Connect { addrv2: bool },
+    /// Send an `addr` message (entries that can't be represented in `addr` are skipped)
+    Addr { from: u8, entries: Vec<AddrEntry> },
+    /// Send an `addrv2` message
+    AddrV2 { from: u8, entries: Vec<AddrEntry> },
+    /// Send a `getaddr` message
+    GetAddr { from: u8 },
+    /// Advance the mocktime of the target node (e.g. to refill the addr rate limiting buckets or
+    /// to age addresses)
+    AdvanceTime { seconds: u32 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+    /// Restart the node after all actions, such that the addrman state is written to and loaded
+    /// from `peers.dat`
+    restart: bool,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        TestCase::arbitrary(&mut unstructured).map_err(|e| e.to_string())
+    }
+}
+
+/// `AddrmanScenario` tests the address manager (addrman) of Bitcoin Core.
+///
+/// The target starts with an empty `peers.dat` and addrman consistency checks enabled
+/// (`-checkaddrman=1`). Testcases gossip addresses from many inbound peers through `addr`,
+/// `addrv2` and `getaddr` messages and optionally restart the node afterwards, which exercises the
+/// (de)serialization of `peers.dat` for whatever addrman state was created.
+struct AddrmanScenario<TX: Transport> {
+    target: BitcoinCoreTarget,
+    connections: ConnectionPool<TX>,
+    time: u64,
+    handshake_opts: HandshakeOpts,
+}
+
+impl<TX: Transport> AddrmanScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn connect(&mut self, addrv2: bool) -> Result<(), String> {
+        let mut connection = self.target.connect(ConnectionType::Inbound)?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.time).map_err(|_| "Invalid time".to_string())?,
+            addrv2,
+            ..self.handshake_opts.clone()
+        })?;
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    fn connect_peers(&mut self) -> Result<(), String> {
+        for i in 0..NUM_PEERS {
+            self.connect(i % 2 == 0)?;
+        }
+        Ok(())
+    }
+
+    fn entry_time(&self, age: i16) -> u32 {
+        let time = i64::try_from(self.time).unwrap_or_default() - i64::from(age) * 60;
+        u32::try_from(time).unwrap_or_default()
+    }
+
+    fn addr_v2(entry: &AddrEntry) -> AddrV2 {
+        match &entry.network {
+            Network::Ipv4(octets) => AddrV2::Ipv4(Ipv4Addr::from(*octets)),
+            Network::Ipv6(octets) => AddrV2::Ipv6(Ipv6Addr::from(*octets)),
+            Network::TorV3(bytes) => AddrV2::TorV3(*bytes),
+            Network::I2p(bytes) => AddrV2::I2p(*bytes),
+            Network::Cjdns(octets) => AddrV2::Cjdns(Ipv6Addr::from(*octets)),
+            Network::Unknown { id, payload } => AddrV2::Unknown(*id, payload.clone()),
+        }
+    }
+
+    /// Entry as sent in `addr` messages, which only support IPv4 and IPv6
+    fn addr_v1(entry: &AddrEntry) -> Option<Address> {
+        let socket = match &entry.network {
+            Network::Ipv4(octets) => {
+                SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(*octets), entry.port))
+            }
+            Network::Ipv6(octets) => {
+                SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(*octets), entry.port, 0, 0))
+            }
+            _ => return None,
+        };
+        Some(Address::new(&socket, ServiceFlags::from(entry.services)))
+    }
+
+    fn send(&mut self, from: u8, message: &NetworkMessage) {
+        let command = message.cmd().to_string();
+        let _ = self
+            .connections
+            .send(usize::from(from), &(command, encode::serialize(message)));
+    }
+
+    /// Restart the target and reconnect the peers (connections don't survive restarts)
+    fn restart(&mut self) -> Result<(), String> {
+        self.target.restart()?;
+        self.connections = ConnectionPool::new();
+        self.connect_peers()?;
+        // Read back (some of) the addresses loaded from peers.dat
+        self.send(0, &NetworkMessage::GetAddr);
+        self.connections.ping_all();
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for AddrmanScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_path_with_args(
+            args,
+            &args.chain_params()?,
+            &["-checkaddrman=1"],
+        )?;
+
+        // Addresses are aged relative to the target's time, so start out at the current time
+        // rather than the genesis time
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get the current time: {e}"))?
+            .as_secs();
+
+        let mut scenario = Self {
+            target,
+            connections: ConnectionPool::new(),
+            time,
+            handshake_opts: args.handshake_opts(),
+        };
+        scenario.target.set_mocktime(time)?;
+        scenario.connect_peers()?;
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 256;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::Connect { addrv2 } => {
+                    if self.connections.len() < test_utils::MAX_PEERS {
+                        let _ = self.connect(addrv2);
+                    }
+                }
+                Action::Addr { from, entries } => {
+                    let addresses = entries
+                        .iter()
+                        .filter_map(|entry| {
+                            Some((self.entry_time(entry.age), Self::addr_v1(entry)?))
+                        })
+                        .collect();
+                    self.send(from, &NetworkMessage::Addr(addresses));
+                }
+                Action::AddrV2 { from, entries } => {
+                    let addresses = entries
+                        .iter()
+                        .map(|entry| AddrV2Message {
+                            time: self.entry_time(entry.age),
+                            services: ServiceFlags::from(entry.services),
+                            addr: Self::addr_v2(entry),
+                            port: entry.port,
+                        })
+                        .collect();
+                    self.send(from, &NetworkMessage::AddrV2(addresses));
+                }
+                Action::GetAddr { from } => self.send(from, &NetworkMessage::GetAddr),
+                Action::AdvanceTime { seconds } => {
+                    self.time += u64::from(seconds);
+                    let _ = self.target.set_mocktime(self.time);
+                }
+            }
+        }
+
+        self.connections.ping_all();
+
+        if testcase.restart
+            && let Err(e) = self.restart()
+        {
+            return ScenarioResult::Fail(format!("Failed to restart target: {e}"));
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(AddrmanScenario::<ScenarioTransport>, TestCase);
@@ -0,0 +1,408 @@
+use fuzzamoto::{
+    connections::{Connection, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, HasTipInfo, ResourceUsage, Target, TargetNode},
+    test_utils::{self, mining},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, BlockHash, MerkleBlock, OutPoint, Transaction, Txid,
+    consensus::encode,
+    hashes::Hash,
+    p2p::{
+        message::NetworkMessage,
+        message_blockdata::Inventory,
+        message_bloom::{BloomFlags, FilterAdd, FilterLoad},
+    },
+};
+use std::collections::HashSet;
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Inventory type for requesting `merkleblock`s (see BIP 37)
+const MSG_FILTERED_BLOCK: u32 = 3;
+/// Maximum size of filters loaded by testcases (see BIP 37)
+const MAX_BLOOM_FILTER_SIZE: u16 = 36_000;
+/// Maximum number of hash functions of filters loaded by testcases (see BIP 37)
+const MAX_HASH_FUNCS: u8 = 50;
+
+#[derive(Arbitrary)]
+enum Element {
+    /// Txid of a known transaction (coinbases and transactions created by the testcase)
+    Txid(u8),
+    /// Outpoint of a known transaction
+    Outpoint {
+        tx: u8,
+        vout: u8,
+    },
+    /// Witness script hash of the `OP_TRUE` outputs, as pushed by their script pubkey
+    WitnessScriptHash,
+    Raw(Vec<u8>),
+}
+
+#[derive(Arbitrary, Clone, Copy)]
+enum Flags {
+    None,
+    All,
+    PubkeyOnly,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Load a filter containing `elements` (built with a zero tweak)
+    FilterLoad {
+        peer: u8,
+        elements: Vec<Element>,
+        size: u16,
+        hash_funcs: u8,
+        flags: Flags,
+    },
+    FilterAdd {
+        peer: u8,
+        element: Element,
+    },
+    FilterClear {
+        peer: u8,
+    },
+    /// Create a transaction splitting one of the `OP_TRUE` outputs, optionally sending it to the
+    /// target
+    CreateTx {
+        from: u8,
+        utxo: u8,
+        outputs: u8,
+        send: bool,
+    },
+    /// Mine a block on the tip confirming the selected transactions
+    Mine {
+        txs: Vec<u8>,
+    },
+    /// Request a `merkleblock` for a known block (most recent first)
+    GetFilteredBlock {
+        peer: u8,
+        block: u8,
+    },
+    /// Request the (filtered) mempool contents
+    Mempool {
+        peer: u8,
+    },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `BloomScenario` tests serving BIP 37 bloom filter peers (`-peerbloomfilters`).
+///
+/// Testcases load, extend and clear filters built from txids, outpoints and scripts of known
+/// transactions on the connections of the generic setup, create and mine transactions and request
+/// `merkleblock`s for the setup chain and the mined blocks, as well as filtered `mempool`
+/// contents. Each `merkleblock` served by the target has to contain a partial merkle tree that
+/// commits to the block's merkle root and only matches transactions of that block.
+struct BloomScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Hashes of the blocks of the active chain (setup chain and mined blocks), in order
+    blocks: Vec<BlockHash>,
+    /// `OP_TRUE` outputs spendable by `CreateTx`
+    utxos: Vec<(OutPoint, Amount)>,
+    /// Txids of all known transactions
+    txids: Vec<Txid>,
+    /// Transactions created by the testcase
+    txs: Vec<Transaction>,
+}
+
+impl<TX: Transport> BloomScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn element(&self, element: &Element) -> Vec<u8> {
+        let txid = |tx: u8| self.txids[usize::from(tx) % self.txids.len()];
+        match element {
+            Element::Txid(tx) => txid(*tx).to_byte_array().to_vec(),
+            Element::Outpoint { tx, vout } => {
+                encode::serialize(&OutPoint::new(txid(*tx), u32::from(*vout % 4)))
+            }
+            Element::WitnessScriptHash => test_utils::op_true_script()
+                .wscript_hash()
+                .to_byte_array()
+                .to_vec(),
+            Element::Raw(bytes) => bytes.clone(),
+        }
+    }
+
+    fn send(&mut self, peer: u8, message: &NetworkMessage) -> Result<(), String> {
+        let command = message.cmd().to_string();
+        self.inner
+            .connections
+            .send(usize::from(peer), &(command, encode::serialize(message)))
+    }
+
+    fn filter_load(
+        &mut self,
+        peer: u8,
+        elements: &[Element],
+        size: u16,
+        hash_funcs: u8,
+        flags: Flags,
+    ) -> Result<(), String> {
+        // Allow exceeding the limits by one to hit the size checks
+        let mut filter = vec![0u8; usize::from(size % (MAX_BLOOM_FILTER_SIZE + 2))];
+        let hash_funcs = u32::from(hash_funcs % (MAX_HASH_FUNCS + 2));
+        for element in elements {
+            fuzzamoto_ir::filter_insert(&mut filter, hash_funcs, &self.element(element));
+        }
+
+        let flags = match flags {
+            Flags::None => BloomFlags::None,
+            Flags::All => BloomFlags::All,
+            Flags::PubkeyOnly => BloomFlags::PubkeyOnly,
+        };
+        self.send(
+            peer,
+            &NetworkMessage::FilterLoad(FilterLoad {
+                filter,
+                hash_funcs,
+                tweak: 0,
+                flags,
+            }),
+        )
+    }
+
+    fn create_tx(&mut self, from: u8, utxo: u8, outputs: u8, send: bool) -> Result<(), String> {
+        let utxo = self.utxos[usize::from(utxo) % self.utxos.len()];
+        let tx = test_utils::create_fanout_tx(utxo, usize::from(outputs % 4) + 1)?;
+        if send {
+            self.send(from, &NetworkMessage::Tx(tx.clone()))?;
+        }
+
+        let txid = tx.compute_txid();
+        for (vout, output) in (0u32..).zip(&tx.output) {
+            self.utxos.push((OutPoint::new(txid, vout), output.value));
+        }
+        self.txids.push(txid);
+        self.txs.push(tx);
+        Ok(())
+    }
+
+    fn mine(&mut self, txs: &[u8]) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        let confirm = txs
+            .iter()
+            .filter_map(|index| self.txs.get(usize::from(*index) % self.txs.len().max(1)))
+            .filter(|tx| seen.insert(tx.compute_txid()))
+            .cloned();
+
+        let (prev_hash, height) = self
+            .inner
+            .target
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip".to_string())?;
+        let height = u32::try_from(height).map_err(|e| e.to_string())?;
+        self.inner.time += 1;
+        let time = u32::try_from(self.inner.time).map_err(|e| e.to_string())?;
+        let mut block = mining::mine_block(prev_hash, height + 1, time);
+        block.txdata.extend(confirm);
+        mining::fixup_commitments(&mut block);
+        mining::fixup_proof_of_work(&mut block);
+
+        self.inner.target.set_mocktime(self.inner.time)?;
+        self.inner
+            .connections
+            .send(0, &("block".to_string(), encode::serialize(&block)))?;
+        self.inner.connections.ping_all();
+
+        let hash = block.block_hash();
+        if self.inner.target.get_tip_info().map(|(tip, _)| tip) != Some(hash) {
+            return Err(format!("Target did not accept block {hash}"));
+        }
+        self.blocks.push(hash);
+        self.txids.push(block.txdata[0].compute_txid());
+        self.inner.block_tree.insert(hash, (block, height + 1));
+        Ok(())
+    }
+
+    /// Request a `merkleblock` for a known block, returning the ones served by the target
+    fn get_filtered_block(&mut self, peer: u8, block: u8) -> Result<Vec<MerkleBlock>, String> {
+        let hash = self.blocks[self.blocks.len() - 1 - usize::from(block) % self.blocks.len()];
+        let getdata = NetworkMessage::GetData(vec![Inventory::Unknown {
+            inv_type: MSG_FILTERED_BLOCK,
+            hash: hash.to_byte_array(),
+        }]);
+
+        let captured =
+            self.inner
+                .connections
+                .with_connection(usize::from(peer), |_, connection| {
+                    connection.take_captured();
+                    connection.send(&("getdata".to_string(), encode::serialize(&getdata)))?;
+                    connection.ping()?;
+                    Ok(connection.take_captured())
+                })?;
+
+        Ok(captured
+            .iter()
+            .filter(|(command, _)| command == "merkleblock")
+            .filter_map(|(_, payload)| encode::deserialize(payload).ok())
+            .collect())
+    }
+
+    /// Check that a served `merkleblock` commits to the merkle root of a known block and only
+    /// matches transactions of that block
+    fn check_merkle_block(&self, merkle_block: &MerkleBlock) -> Result<(), String> {
+        let hash = merkle_block.header.block_hash();
+        let Some((block, _)) = self.inner.block_tree.get(&hash) else {
+            return Err(format!("merkleblock for unknown block {hash}"));
+        };
+
+        let mut matches = Vec::new();
+        let mut indexes = Vec::new();
+        merkle_block
+            .extract_matches(&mut matches, &mut indexes)
+            .map_err(|e| format!("Invalid partial merkle tree for block {hash}: {e:?}"))?;
+
+        for (txid, index) in matches.iter().zip(&indexes) {
+            let tx = usize::try_from(*index)
+                .ok()
+                .and_then(|index| block.txdata.get(index));
+            if tx.map(Transaction::compute_txid) != Some(*txid) {
+                return Err(format!(
+                    "merkleblock for block {hash} matches {txid} at index {index} outside the block"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for BloomScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let mut inner = GenericScenario::from_target(target, &args.handshake_opts())?;
+        // Responses are collected from the messages captured during ping/pong roundtrips
+        inner
+            .connections
+            .iter_alive_mut()
+            .for_each(Connection::enable_capture);
+
+        let mut chain: Vec<(u32, BlockHash, &Transaction)> = inner
+            .block_tree
+            .iter()
+            .map(|(hash, (block, height))| (*height, *hash, &block.txdata[0]))
+            .collect();
+        chain.sort_by_key(|(height, _, _)| *height);
+        let tip_height = chain.last().map_or(0, |(height, _, _)| *height);
+
+        let utxos = chain
+            .iter()
+            .filter(|(height, _, _)| *height + test_utils::COINBASE_MATURITY <= tip_height)
+            .map(|(_, _, coinbase)| {
+                (
+                    OutPoint::new(coinbase.compute_txid(), 0),
+                    coinbase.output[0].value,
+                )
+            })
+            .collect();
+        let txids = chain
+            .iter()
+            .map(|(_, _, coinbase)| coinbase.compute_txid())
+            .collect();
+        let blocks = chain.iter().map(|(_, hash, _)| *hash).collect();
+
+        Ok(Self {
+            inner,
+            blocks,
+            utxos,
+            txids,
+            txs: Vec::new(),
+        })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 64;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::FilterLoad {
+                    peer,
+                    elements,
+                    size,
+                    hash_funcs,
+                    flags,
+                } => {
+                    let _ = self.filter_load(peer, &elements, size, hash_funcs, flags);
+                }
+                Action::FilterAdd { peer, element } => {
+                    let data = self.element(&element);
+                    let _ = self.send(peer, &NetworkMessage::FilterAdd(FilterAdd { data }));
+                }
+                Action::FilterClear { peer } => {
+                    let _ = self.send(peer, &NetworkMessage::FilterClear);
+                }
+                Action::CreateTx {
+                    from,
+                    utxo,
+                    outputs,
+                    send,
+                } => {
+                    let _ = self.create_tx(from, utxo, outputs, send);
+                }
+                Action::Mine { txs } => {
+                    let _ = self.mine(&txs);
+                }
+                Action::GetFilteredBlock { peer, block } => {
+                    let Ok(merkle_blocks) = self.get_filtered_block(peer, block) else {
+                        continue;
+                    };
+                    for merkle_block in &merkle_blocks {
+                        if let Err(e) = self.check_merkle_block(merkle_block) {
+                            return ScenarioResult::Fail(e);
+                        }
+                    }
+                }
+                Action::Mempool { peer } => {
+                    let _ = self.send(peer, &NetworkMessage::MemPool);
+                }
+            }
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(BloomScenario::<ScenarioTransport>, TestCase);
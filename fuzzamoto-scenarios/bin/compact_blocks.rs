@@ -1,8 +1,10 @@
 use fuzzamoto::{
-    connections::Transport,
+    connections::{TrafficMetrics, Transport},
     fuzzamoto_main,
-    scenarios::{Scenario, ScenarioInput, ScenarioResult, generic::GenericScenario},
-    targets::{BitcoinCoreTarget, Target},
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, Target},
     test_utils,
 };
 
@@ -198,13 +200,15 @@ impl<TX: Transport, T: Target<TX>> CompactBlocksScenario<TX, T> {
         });
 
         let from = *from;
-        let _ = self.inner.connections[from]
-            .send(&("cmpctblock".to_string(), encode::serialize(&cmpctblock)));
+        let _ = self.inner.connections.send(
+            from,
+            &("cmpctblock".to_string(), encode::serialize(&cmpctblock)),
+        );
     }
 }
 
 impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScenario<TX, T> {
-    fn new(args: &[String]) -> Result<Self, String> {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
         let inner = GenericScenario::new(args)?;
 
         Ok(Self {
@@ -246,8 +250,10 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
                         .map(|b| (b.0, b.1.block_hash()))
                     {
                         let inv = NetworkMessage::Inv(vec![Inventory::Block(block_hash)]);
-                        let _ = self.inner.connections[from]
-                            .send(&("inv".to_string(), encode::serialize(&inv)));
+                        let _ = self
+                            .inner
+                            .connections
+                            .send(from, &("inv".to_string(), encode::serialize(&inv)));
                     }
                 }
 
@@ -256,8 +262,10 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
                         self.get_block(block as usize).map(|b| (b.0, b.1.header))
                     {
                         let headers = NetworkMessage::Headers(vec![header]);
-                        let _ = self.inner.connections[from]
-                            .send(&("headers".to_string(), encode::serialize(&headers)));
+                        let _ = self
+                            .inner
+                            .connections
+                            .send(from, &("headers".to_string(), encode::serialize(&headers)));
                     }
                 }
 
@@ -272,8 +280,10 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
                     if let Some((from, block)) = self.get_block(block as usize) {
                         let from = *from;
                         let block = block.clone();
-                        let _ = self.inner.connections[from]
-                            .send(&("block".to_string(), encode::serialize(&block)));
+                        let _ = self
+                            .inner
+                            .connections
+                            .send(from, &("block".to_string(), encode::serialize(&block)));
                     }
                 }
 
@@ -282,8 +292,10 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
                         let from = *from;
                         let block = block.clone();
                         let tx = tx as usize % block.txdata.len();
-                        let _ = self.inner.connections[from]
-                            .send(&("tx".to_string(), encode::serialize(&block.txdata[tx])));
+                        let _ = self.inner.connections.send(
+                            from,
+                            &("tx".to_string(), encode::serialize(&block.txdata[tx])),
+                        );
                     }
                 }
 
@@ -305,8 +317,10 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
                         });
                         let from = *from;
 
-                        let _ = self.inner.connections[from]
-                            .send(&("blocktxn".to_string(), encode::serialize(&blocktxn)));
+                        let _ = self.inner.connections.send(
+                            from,
+                            &("blocktxn".to_string(), encode::serialize(&blocktxn)),
+                        );
                     }
                 }
                 Action::AdvanceTime { seconds } => {
@@ -316,9 +330,7 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
             }
         }
 
-        for connection in &mut self.inner.connections {
-            let _ = connection.ping();
-        }
+        self.inner.connections.ping_all();
 
         if let Err(e) = self.inner.target.is_alive() {
             return ScenarioResult::Fail(format!("Target is not alive: {e}"));
@@ -326,6 +338,14 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for CompactBlocksScena
 
         ScenarioResult::Ok
     }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        self.inner.traffic_metrics()
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.resource_usage()
+    }
 }
 
 fuzzamoto_main!(
@@ -0,0 +1,24 @@
+mod filter_index;
+mod ir_scenario;
+
+use filter_index::FilterIndex;
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput},
+    targets::BitcoinCoreTarget,
+};
+use ir_scenario::{IrScenario, TestCase};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// `CompactFiltersScenario` tests serving BIP 157/158 compact block filters.
+///
+/// IR programs are run like in `IrScenario`, on a target with a synced filter index and with the
+/// headers of the entire chain exposed to them (see `FilterIndex`).
+type CompactFiltersScenario = IrScenario<ScenarioTransport, BitcoinCoreTarget, FilterIndex>;
+
+fuzzamoto_main!(CompactFiltersScenario, TestCase);
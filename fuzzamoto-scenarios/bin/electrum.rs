@@ -0,0 +1,323 @@
+use fuzzamoto::{
+    fuzzamoto_main,
+    oracles::{ElectrsSyncOracle, Oracle, OracleResult},
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{ElectrsTarget, ElectrumConnection, RpcTarget, TargetNode},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Address,
+    hashes::{Hash, sha256},
+};
+use std::{str::FromStr, time::Duration};
+
+const ADDRESS_BCRT1_P2WSH_OP_TRUE: &str =
+    "bcrt1qft5p2uhsdcdc3l2ua4ap5qqfg4pjaqlp250x7us7a8qqhrxrxfsqseac85";
+
+#[derive(Arbitrary)]
+enum Request<'a> {
+    ServerVersion,
+    ServerFeatures,
+    Ping,
+    HeadersSubscribe,
+    BlockHeader {
+        height: u32,
+        cp_height: u32,
+    },
+    BlockHeaders {
+        start_height: u32,
+        count: u32,
+    },
+    EstimateFee {
+        blocks: u16,
+    },
+    RelayFee,
+    FeeHistogram,
+    GetBalance {
+        script: u8,
+    },
+    GetHistory {
+        script: u8,
+    },
+    ListUnspent {
+        script: u8,
+    },
+    ScriptSubscribe {
+        script: u8,
+    },
+    ScriptUnsubscribe {
+        script: u8,
+    },
+    GetTransaction {
+        txid: u16,
+        verbose: bool,
+    },
+    GetMerkle {
+        txid: u16,
+        height: u32,
+    },
+    IdFromPos {
+        height: u32,
+        pos: u16,
+        merkle: bool,
+    },
+    Broadcast {
+        tx: &'a [u8],
+    },
+    /// Raw bytes sent on the connection (e.g. malformed JSON or batched requests)
+    Raw {
+        bytes: &'a [u8],
+    },
+}
+
+#[derive(Arbitrary)]
+enum Action<'a> {
+    Request(Request<'a>),
+    /// Mine blocks to the OP_TRUE address
+    Mine {
+        blocks: u8,
+    },
+    /// Invalidate one of the recent blocks, reorging the chain for electrs
+    Invalidate {
+        depth: u8,
+    },
+}
+
+#[derive(Arbitrary)]
+struct TestCase<'a> {
+    actions: Vec<Action<'a>>,
+}
+
+impl<'a> ScenarioInput<'a> for TestCase<'a> {
+    fn decode(bytes: &'a [u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `ElectrumScenario` tests the Electrum protocol of electrs, indexing a Bitcoin Core node (see
+/// `ElectrsTarget`).
+///
+/// The snapshot state contains a short chain mined to a known script, such that the fuzzer can
+/// select existing transactions and script hashes by index. Testcases send Electrum requests and
+/// mine or reorg blocks, after which electrs has to catch up with the node's tip.
+struct ElectrumScenario {
+    target: ElectrsTarget,
+    connection: ElectrumConnection,
+    script_hashes: Vec<String>,
+    txids: Vec<String>,
+    /// Height of the chain mined during setup, which is never invalidated
+    setup_height: u64,
+}
+
+/// Electrum script hash of `script_pubkey` (reversed sha256, hex encoded)
+fn script_hash(script_pubkey: &[u8]) -> String {
+    let mut hash = sha256::Hash::hash(script_pubkey).to_byte_array();
+    hash.reverse();
+    hex::encode(hash)
+}
+
+impl ElectrumScenario {
+    fn pick(values: &[String], index: impl Into<usize>) -> &str {
+        if values.is_empty() {
+            return "";
+        }
+        &values[index.into() % values.len()]
+    }
+
+    fn request(&mut self, request: Request<'_>) -> Result<(), String> {
+        let script = |index: u8| Self::pick(&self.script_hashes, index).to_string();
+        let txid = |index: u16| Self::pick(&self.txids, index).to_string();
+
+        let (method, params): (&str, Vec<serde_json::Value>) = match request {
+            Request::ServerVersion => ("server.version", vec!["fuzzamoto".into(), "1.4".into()]),
+            Request::ServerFeatures => ("server.features", vec![]),
+            Request::Ping => ("server.ping", vec![]),
+            Request::HeadersSubscribe => ("blockchain.headers.subscribe", vec![]),
+            Request::BlockHeader { height, cp_height } => (
+                "blockchain.block.header",
+                vec![height.into(), cp_height.into()],
+            ),
+            Request::BlockHeaders {
+                start_height,
+                count,
+            } => (
+                "blockchain.block.headers",
+                vec![start_height.into(), count.into()],
+            ),
+            Request::EstimateFee { blocks } => ("blockchain.estimatefee", vec![blocks.into()]),
+            Request::RelayFee => ("blockchain.relayfee", vec![]),
+            Request::FeeHistogram => ("mempool.get_fee_histogram", vec![]),
+            Request::GetBalance { script: index } => (
+                "blockchain.scripthash.get_balance",
+                vec![script(index).into()],
+            ),
+            Request::GetHistory { script: index } => (
+                "blockchain.scripthash.get_history",
+                vec![script(index).into()],
+            ),
+            Request::ListUnspent { script: index } => (
+                "blockchain.scripthash.listunspent",
+                vec![script(index).into()],
+            ),
+            Request::ScriptSubscribe { script: index } => (
+                "blockchain.scripthash.subscribe",
+                vec![script(index).into()],
+            ),
+            Request::ScriptUnsubscribe { script: index } => (
+                "blockchain.scripthash.unsubscribe",
+                vec![script(index).into()],
+            ),
+            Request::GetTransaction {
+                txid: index,
+                verbose,
+            } => (
+                "blockchain.transaction.get",
+                vec![txid(index).into(), verbose.into()],
+            ),
+            Request::GetMerkle {
+                txid: index,
+                height,
+            } => (
+                "blockchain.transaction.get_merkle",
+                vec![txid(index).into(), height.into()],
+            ),
+            Request::IdFromPos {
+                height,
+                pos,
+                merkle,
+            } => (
+                "blockchain.transaction.id_from_pos",
+                vec![height.into(), pos.into(), merkle.into()],
+            ),
+            Request::Broadcast { tx } => (
+                "blockchain.transaction.broadcast",
+                vec![hex::encode(tx).into()],
+            ),
+            Request::Raw { bytes } => {
+                let mut line = bytes.to_vec();
+                line.push(b'\n');
+                return self.connection.send_raw(&line);
+            }
+        };
+
+        self.connection.request(method, &params).map(|_| ())
+    }
+
+    fn mine(&self, blocks: u8) -> Result<(), String> {
+        self.target
+            .call_rpc(
+                "generatetoaddress",
+                &[blocks.into(), ADDRESS_BCRT1_P2WSH_OP_TRUE.into()],
+            )
+            .map(|_| ())
+    }
+
+    fn invalidate(&self, depth: u8) -> Result<(), String> {
+        let height = self.target.call_rpc("getblockcount", &[])?;
+        let height = height
+            .as_u64()
+            .ok_or_else(|| "Failed to parse block count".to_string())?;
+        if height.saturating_sub(u64::from(depth)) <= self.setup_height {
+            return Ok(());
+        }
+
+        let hash = self
+            .target
+            .call_rpc("getblockhash", &[(height - u64::from(depth)).into()])?;
+        self.target.call_rpc("invalidateblock", &[hash]).map(|_| ())
+    }
+}
+
+impl<'a> Scenario<'a, TestCase<'a>> for ElectrumScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        const BLOCKS: u64 = 110;
+        const SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+
+        // Blocks are mined to a regtest address
+        args.chain_params()?.require_regtest()?;
+        let target = ElectrsTarget::from_scenario_args(args)?;
+
+        let block_hashes: Vec<String> = serde_json::from_value(target.call_rpc(
+            "generatetoaddress",
+            &[BLOCKS.into(), ADDRESS_BCRT1_P2WSH_OP_TRUE.into()],
+        )?)
+        .map_err(|e| format!("Failed to parse generated block hashes: {e}"))?;
+
+        let mut txids = Vec::new();
+        for hash in &block_hashes {
+            let block = target.call_rpc("getblock", &[hash.as_str().into(), 1.into()])?;
+            let block_txids: Vec<String> = serde_json::from_value(block["tx"].clone())
+                .map_err(|e| format!("Failed to parse txids of block {hash}: {e}"))?;
+            txids.extend(block_txids);
+        }
+
+        let address = Address::from_str(ADDRESS_BCRT1_P2WSH_OP_TRUE)
+            .map_err(|e| format!("Failed to parse address: {e}"))?
+            .assume_checked();
+        let script_hashes = vec![
+            script_hash(address.script_pubkey().as_bytes()),
+            // Script without any history
+            script_hash(&[0x51]),
+        ];
+
+        target.wait_for_sync(SYNC_TIMEOUT)?;
+        let connection = target.connect_electrum()?;
+
+        log::info!(
+            "electrs indexed {} blocks with {} transactions",
+            block_hashes.len(),
+            txids.len()
+        );
+
+        Ok(Self {
+            target,
+            connection,
+            script_hashes,
+            txids,
+            setup_height: BLOCKS,
+        })
+    }
+
+    fn run(&mut self, input: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 64;
+        if input.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in input.actions {
+            match action {
+                Action::Request(request) => {
+                    if let Err(e) = self.request(request) {
+                        log::debug!("Electrum request failed: {e}");
+                        // electrs might have closed the connection (e.g. after malformed requests)
+                        if let Ok(connection) = self.target.connect_electrum() {
+                            self.connection = connection;
+                        }
+                    }
+                }
+                Action::Mine { blocks } => {
+                    let _ = self.mine(blocks % 8);
+                }
+                Action::Invalidate { depth } => {
+                    let _ = self.invalidate(depth % 8);
+                }
+            }
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        let sync_oracle = ElectrsSyncOracle::new(Duration::from_secs(30));
+        if let OracleResult::Fail(e) = sync_oracle.evaluate(&mut self.target) {
+            return ScenarioResult::Fail(format!("CRASH: ELECTRS; {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+fuzzamoto_main!(ElectrumScenario, TestCase);
@@ -0,0 +1,404 @@
+use fuzzamoto::{
+    connections::{HandshakeOpts, TrafficMetrics, Transport},
+    erlay::{ShortIdHasher, Sketch},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, Target, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, OutPoint, Transaction,
+    consensus::encode,
+    p2p::{message::NetworkMessage, message_blockdata::Inventory},
+};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Salt announced in `sendtxrcncl` on all connections
+const ERLAY_SALT: u64 = 0x6572_6c61_7973_616c;
+
+#[derive(Arbitrary)]
+enum SketchCorruption {
+    /// Flip a single bit of the serialized sketch
+    FlipBit { bit: u16 },
+    /// Truncate the serialized sketch (possibly to a length that isn't a multiple of 4)
+    Truncate { len: u16 },
+    /// Append bytes to the serialized sketch
+    Extend { bytes: Vec<u8> },
+    /// Add elements that aren't in the reconciliation set, possibly exceeding the capacity
+    AddElements { elements: Vec<u32> },
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Construct a new transaction spending one of the available outputs
+    CreateTx { funding: u16, num_outputs: u8 },
+    /// Send a `tx` message for a previously constructed transaction
+    SendTx { from: u8, tx: u16 },
+    /// Announce a previously constructed transaction through a regular `inv` (wtxid)
+    AnnounceTx { from: u8, tx: u16 },
+    /// Add a previously constructed transaction to the reconciliation set of a connection
+    AddToSet { from: u8, tx: u16 },
+    /// Run a full reconciliation round as the initiator: request a sketch with `reqrecon`, decode
+    /// the difference to our set, ask for the missing transactions with `reconcildiff` and
+    /// announce the ones the target is missing
+    Reconcile { from: u8, q: u16 },
+    /// Send a sketch of the reconciliation set (e.g. in response to the target's `reqrecon`)
+    SendSketch {
+        from: u8,
+        capacity: u8,
+        corruption: Option<SketchCorruption>,
+    },
+    /// Send a `reconcildiff` asking for previously constructed transactions
+    SendReconcilDiff {
+        from: u8,
+        success: bool,
+        ask: Vec<u16>,
+    },
+    /// Advance the mocktime of the target node (e.g. to trigger its reconciliation timers)
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `ErlayScenario` tests transaction reconciliation (erlay, BIP 330) in Bitcoin Core's
+/// txreconciliation module.
+///
+/// The target is started with `-txreconciliation` and the connections of the generic scenario
+/// setup negotiate reconciliation with `sendtxrcncl` (see `GenericScenario`). Testcases construct
+/// transactions and interleave regular `inv`-based relay with reconciliation rounds, i.e. each
+/// testcase represents a series of different types of actions:
+///
+/// 1. Construct a new transaction
+/// 2. Send or announce a transaction through `tx`/`inv`
+/// 3. Add a transaction to the reconciliation set of a connection
+/// 4. Run a full reconciliation round with the target
+/// 5. Send valid or corrupted `sketch` and `reconcildiff` messages
+/// 6. Advance the mocktime of the target node
+struct ErlayScenario<TX: Transport, T: Target<TX>> {
+    inner: GenericScenario<TX, T>,
+
+    /// Short id hashers per connection, derived from the salts exchanged during the handshake
+    hashers: Vec<ShortIdHasher>,
+    /// Reconciliation sets per connection (indices into `txs`)
+    sets: Vec<Vec<usize>>,
+    /// Outputs that haven't been spent by any of the constructed transactions
+    utxos: Vec<(OutPoint, Amount)>,
+    txs: Vec<Transaction>,
+}
+
+impl<TX: Transport, T: Target<TX>> ErlayScenario<TX, T> {
+    fn get_tx(&self, index: u16) -> Option<usize> {
+        if self.txs.is_empty() {
+            return None;
+        }
+        Some(usize::from(index) % self.txs.len())
+    }
+
+    fn create_tx(&mut self, funding: u16, num_outputs: u8) {
+        if self.utxos.is_empty() {
+            return;
+        }
+        let funding = self
+            .utxos
+            .swap_remove(usize::from(funding) % self.utxos.len());
+        let Ok(tx) = test_utils::create_fanout_tx(funding, usize::from(num_outputs % 8) + 1) else {
+            self.utxos.push(funding);
+            return;
+        };
+
+        let txid = tx.compute_txid();
+        self.utxos.extend(
+            (0u32..)
+                .zip(&tx.output)
+                .map(|(vout, output)| (OutPoint::new(txid, vout), output.value)),
+        );
+        self.txs.push(tx);
+    }
+
+    fn send(&mut self, from: u8, message: &NetworkMessage) {
+        let command = message.cmd().to_string();
+        let _ = self
+            .inner
+            .connections
+            .send(usize::from(from), &(command, encode::serialize(message)));
+    }
+
+    /// Short ids of the reconciliation set of connection `index`
+    fn short_ids(&self, index: usize) -> Vec<u32> {
+        self.sets[index]
+            .iter()
+            .map(|tx| self.hashers[index].short_id(&self.txs[*tx].compute_wtxid()))
+            .collect()
+    }
+
+    fn reconcile(&mut self, from: u8, q: u16) -> Result<(), String> {
+        let Some(index) = self.inner.connections.resolve(usize::from(from)) else {
+            return Ok(());
+        };
+        let local = self.short_ids(index);
+        let set_size = u16::try_from(local.len()).unwrap_or(u16::MAX);
+
+        let difference = self
+            .inner
+            .connections
+            .with_connection(index, |_, connection| {
+                connection.send_reqrecon(set_size, q)?;
+                // Don't block on `receive_sketch`, the target might not respond at all. Two ping
+                // roundtrips give it a chance to process the request and send its sketch.
+                connection.ping()?;
+                connection.ping()?;
+                let Some((_, payload)) = connection
+                    .take_captured()
+                    .into_iter()
+                    .rev()
+                    .find(|(command, _)| command == "sketch")
+                else {
+                    return Ok(None);
+                };
+                let bytes: Vec<u8> = encode::deserialize(&payload)
+                    .map_err(|e| format!("Failed to decode sketch message: {e}"))?;
+                let mut sketch = Sketch::deserialize(&bytes)?;
+
+                let mut local_sketch = Sketch::new(sketch.capacity());
+                for short_id in &local {
+                    local_sketch.add(*short_id);
+                }
+                sketch.merge(&local_sketch);
+
+                let Ok(difference) = sketch.decode() else {
+                    connection.send_reconcildiff(false, &[])?;
+                    return Ok(None);
+                };
+                let ask: Vec<u32> = difference
+                    .iter()
+                    .filter(|short_id| !local.contains(short_id))
+                    .copied()
+                    .collect();
+                connection.send_reconcildiff(true, &ask)?;
+                Ok(Some(difference))
+            })?;
+
+        let set = std::mem::take(&mut self.sets[index]);
+        let Some(difference) = difference else {
+            return Ok(());
+        };
+
+        // Announce the transactions the target is missing
+        let missing: Vec<Inventory> = set
+            .iter()
+            .zip(&local)
+            .filter(|(_, short_id)| difference.contains(short_id))
+            .map(|(tx, _)| Inventory::WTx(self.txs[*tx].compute_wtxid()))
+            .collect();
+        if !missing.is_empty() {
+            let inv = NetworkMessage::Inv(missing);
+            self.inner
+                .connections
+                .send(index, &("inv".to_string(), encode::serialize(&inv)))?;
+        }
+        Ok(())
+    }
+
+    fn send_sketch(&mut self, from: u8, capacity: u8, corruption: Option<SketchCorruption>) {
+        let Some(index) = self.inner.connections.resolve(usize::from(from)) else {
+            return;
+        };
+
+        let mut sketch = Sketch::new(usize::from(capacity));
+        for short_id in self.short_ids(index) {
+            sketch.add(short_id);
+        }
+        if let Some(SketchCorruption::AddElements { elements }) = &corruption {
+            for element in elements.iter().filter(|element| **element != 0) {
+                sketch.add(*element);
+            }
+        }
+
+        let mut bytes = sketch.serialize();
+        match corruption {
+            Some(SketchCorruption::FlipBit { bit }) if !bytes.is_empty() => {
+                let bit = usize::from(bit) % (bytes.len() * 8);
+                bytes[bit / 8] ^= 1 << (bit % 8);
+            }
+            Some(SketchCorruption::Truncate { len }) => bytes.truncate(usize::from(len)),
+            Some(SketchCorruption::Extend { bytes: extra }) => bytes.extend(extra),
+            _ => {}
+        }
+
+        let _ = self
+            .inner
+            .connections
+            .send(index, &("sketch".to_string(), encode::serialize(&bytes)));
+    }
+
+    fn send_reconcildiff(&mut self, from: u8, success: bool, ask: &[u16]) {
+        let Some(index) = self.inner.connections.resolve(usize::from(from)) else {
+            return;
+        };
+        let ask: Vec<u32> = ask
+            .iter()
+            .filter_map(|tx| self.get_tx(*tx))
+            .map(|tx| self.hashers[index].short_id(&self.txs[tx].compute_wtxid()))
+            .collect();
+
+        let _ = self
+            .inner
+            .connections
+            .with_connection(index, |_, connection| {
+                connection.send_reconcildiff(success, &ask)
+            });
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for ErlayScenario<TX, BitcoinCoreTarget> {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_path_with_args(
+            args,
+            &args.chain_params()?,
+            &["-txreconciliation"],
+        )?;
+        if !target.capabilities().erlay {
+            return Err("Target does not support transaction reconciliation".to_string());
+        }
+
+        let mut inner = GenericScenario::from_target(
+            target,
+            &HandshakeOpts {
+                erlay_salt: ERLAY_SALT,
+                ..args.handshake_opts()
+            },
+        )?;
+
+        let mut hashers = Vec::new();
+        let mut negotiated = 0;
+        for index in 0..inner.connections.len() {
+            let hasher = inner.connections.with_connection(index, |_, connection| {
+                // Sketches are collected from the messages captured during ping/pong roundtrips
+                connection.enable_capture();
+                Ok(connection.erlay_short_id_hasher(ERLAY_SALT))
+            })?;
+            negotiated += usize::from(hasher.is_some());
+            // Connections that didn't negotiate reconciliation still get to send (invalid)
+            // reconciliation messages
+            hashers.push(hasher.unwrap_or_else(|| ShortIdHasher::new(ERLAY_SALT, 0)));
+        }
+        log::info!("{negotiated} connections negotiated transaction reconciliation");
+
+        // Mature coinbase outputs fund the constructed transactions
+        let utxos = inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height < 100)
+            .map(|(block, _)| {
+                let coinbase = &block.txdata[0];
+                (
+                    OutPoint::new(coinbase.compute_txid(), 0),
+                    coinbase.output[0].value,
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            sets: vec![Vec::new(); inner.connections.len()],
+            hashers,
+            utxos,
+            txs: Vec::new(),
+            inner,
+        })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 256;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::CreateTx {
+                    funding,
+                    num_outputs,
+                } => self.create_tx(funding, num_outputs),
+                Action::SendTx { from, tx } => {
+                    if let Some(tx) = self.get_tx(tx) {
+                        let tx = NetworkMessage::Tx(self.txs[tx].clone());
+                        self.send(from, &tx);
+                    }
+                }
+                Action::AnnounceTx { from, tx } => {
+                    if let Some(tx) = self.get_tx(tx) {
+                        let wtxid = self.txs[tx].compute_wtxid();
+                        self.send(from, &NetworkMessage::Inv(vec![Inventory::WTx(wtxid)]));
+                    }
+                }
+                Action::AddToSet { from, tx } => {
+                    if let (Some(index), Some(tx)) = (
+                        self.inner.connections.resolve(usize::from(from)),
+                        self.get_tx(tx),
+                    ) && !self.sets[index].contains(&tx)
+                    {
+                        self.sets[index].push(tx);
+                    }
+                }
+                Action::Reconcile { from, q } => {
+                    if let Err(e) = self.reconcile(from, q) {
+                        log::debug!("Reconciliation round failed: {e}");
+                    }
+                }
+                Action::SendSketch {
+                    from,
+                    capacity,
+                    corruption,
+                } => self.send_sketch(from, capacity, corruption),
+                Action::SendReconcilDiff { from, success, ask } => {
+                    self.send_reconcildiff(from, success, &ask);
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    let _ = self.inner.target.set_mocktime(self.inner.time);
+                }
+            }
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        self.inner.traffic_metrics()
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.resource_usage()
+    }
+}
+
+fuzzamoto_main!(
+    ErlayScenario::<ScenarioTransport, BitcoinCoreTarget>,
+    TestCase
+);
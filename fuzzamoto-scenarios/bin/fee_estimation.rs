@@ -0,0 +1,255 @@
+use fuzzamoto::{
+    connections::{TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult,
+        args::ScenarioArgs,
+        generic::{FUNDED_COINBASES, GenericScenario, OUTPUTS_PER_COINBASE},
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, RpcTarget, Target},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{Amount, OutPoint, Transaction, consensus::encode, p2p::message::NetworkMessage};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Maximum number of transactions sent in one action
+const MAX_TXS: usize = 32;
+/// Confirmation targets queried after each block (as in Core's `feature_fee_estimation.py`)
+const MAX_CONF_TARGET: u64 = 25;
+/// Tolerance for rounding in the feerates returned by `estimatesmartfee` (BTC/kvB)
+const FEERATE_DELTA: f64 = 1.0e-6;
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Send transactions paying the given feerates (sat/vB) to the mempool
+    SendTxs { from: u8, feerates: Vec<u16> },
+    /// Mine a block confirming the `count` highest feerate transactions sent by the testcase
+    /// (the others remain in the mempool) and check the fee estimates
+    MineBlock { count: u8 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// Transaction sent to the mempool by the testcase, but not yet confirmed
+struct PendingTx {
+    tx: Transaction,
+    /// Feerate in sat/vB
+    feerate: u64,
+}
+
+/// Create a transaction spending `utxo` into a single output, paying `feerate` (sat/vB)
+fn create_tx(utxo: (OutPoint, Amount), feerate: u64) -> Option<Transaction> {
+    // The fan-out transaction pays 1 sat/vB
+    let mut tx = test_utils::create_fanout_tx(utxo, 1).ok()?;
+    let vsize = u64::try_from(tx.vsize()).ok()?;
+    let extra_fee = Amount::from_sat(feerate.saturating_sub(1).checked_mul(vsize)?);
+    tx.output[0].value = tx.output[0]
+        .value
+        .checked_sub(extra_fee)
+        .filter(|value| value.to_sat() > 1000)?;
+    Some(tx)
+}
+
+/// `FeeEstimationScenario` tests the fee estimation subsystem of Bitcoin Core end to end.
+///
+/// During setup, a block splitting mature coinbase outputs into many confirmed outputs is mined.
+/// Testcases send transactions with fuzzer-chosen feerate distributions to the mempool and mine
+/// blocks confirming (a subset of) them, i.e. each testcase represents a series of different types
+/// of actions:
+///
+/// 1. Send transactions paying the given feerates
+/// 2. Mine a block confirming the highest feerate transactions
+/// 3. Advance the mocktime of the target node
+///
+/// After each block, `estimatesmartfee` is queried for all confirmation targets up to 25. The
+/// scenario fails if an estimate is outside the range of the feerates seen by the target, below the
+/// minimum relay or mempool feerate, increases with the confirmation target, or is for more
+/// blocks than requested (the same invariants as in Core's `feature_fee_estimation.py`).
+struct FeeEstimationScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Confirmed outputs available to the testcase
+    utxos: Vec<(OutPoint, Amount)>,
+}
+
+impl<TX: Transport> FeeEstimationScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn send_txs(
+        &mut self,
+        from: u8,
+        feerates: &[u16],
+        pending: &mut Vec<PendingTx>,
+        fees_seen: &mut Vec<u64>,
+    ) -> Result<(), String> {
+        for feerate in feerates.iter().take(MAX_TXS) {
+            let Some(utxo) = self.utxos.pop() else {
+                break;
+            };
+            let feerate = u64::from(*feerate).max(1);
+            let Some(tx) = create_tx(utxo, feerate) else {
+                continue;
+            };
+
+            let message = NetworkMessage::Tx(tx.clone());
+            self.inner.connections.send(
+                usize::from(from),
+                &("tx".to_string(), encode::serialize(&message)),
+            )?;
+            pending.push(PendingTx { tx, feerate });
+            fees_seen.push(feerate);
+        }
+        self.inner.connections.ping_all();
+        Ok(())
+    }
+
+    fn mine_block(&mut self, count: u8, pending: &mut Vec<PendingTx>) -> Result<(), String> {
+        pending.sort_by_key(|pending| std::cmp::Reverse(pending.feerate));
+        let count = usize::from(count).min(pending.len());
+        let confirmed: Vec<Transaction> = pending.drain(..count).map(|p| p.tx).collect();
+
+        let outputs: Vec<(OutPoint, Amount)> = confirmed
+            .iter()
+            .map(|tx| (OutPoint::new(tx.compute_txid(), 0), tx.output[0].value))
+            .collect();
+        self.inner.confirm(confirmed)?;
+        // Outputs of confirmed transactions can be spent again
+        self.utxos.extend(outputs);
+        Ok(())
+    }
+
+    /// Check the invariants of `estimatesmartfee` for all confirmation targets up to
+    /// `MAX_CONF_TARGET`, given the feerates (sat/vB) of all transactions sent so far.
+    fn check_estimates(&self, fees_seen: &[u64]) -> Result<(), String> {
+        let (Some(min_seen), Some(max_seen)) = (fees_seen.iter().min(), fees_seen.iter().max())
+        else {
+            return Ok(());
+        };
+        // sat/vB -> BTC/kvB
+        #[expect(clippy::cast_precision_loss)]
+        let (min_seen, max_seen) = (*min_seen as f64 * 1e-5, *max_seen as f64 * 1e-5);
+
+        let target = &self.inner.target;
+        let mempool_info = target.call_rpc("getmempoolinfo", &[])?;
+        let min_fee = mempool_info["mempoolminfee"]
+            .as_f64()
+            .unwrap_or_default()
+            .max(mempool_info["minrelaytxfee"].as_f64().unwrap_or_default());
+
+        let mut last_feerate = max_seen;
+        for conf_target in 1..=MAX_CONF_TARGET {
+            let estimate = target.call_rpc("estimatesmartfee", &[conf_target.into()])?;
+            let Some(feerate) = estimate["feerate"].as_f64() else {
+                // Not enough data for an estimate
+                continue;
+            };
+
+            if feerate <= 0.0 || feerate + FEERATE_DELTA < min_fee {
+                return Err(format!(
+                    "Estimated feerate {feerate} below minimum feerate {min_fee} (target \
+                     {conf_target})"
+                ));
+            }
+            if feerate + FEERATE_DELTA < min_seen || feerate - FEERATE_DELTA > max_seen.max(min_fee)
+            {
+                return Err(format!(
+                    "Estimated feerate {feerate} outside the range of seen feerates \
+                     [{min_seen}, {max_seen}] (target {conf_target})"
+                ));
+            }
+            if feerate - FEERATE_DELTA > last_feerate {
+                return Err(format!(
+                    "Estimated feerate {feerate} for target {conf_target} is larger than \
+                     {last_feerate} for a lower target"
+                ));
+            }
+            last_feerate = feerate;
+
+            let blocks = estimate["blocks"].as_u64().unwrap_or_default();
+            if blocks > conf_target.max(2) {
+                return Err(format!(
+                    "Estimate for target {conf_target} is for {blocks} blocks"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for FeeEstimationScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let mut inner = GenericScenario::new(args)?;
+        let utxos = inner.fund(FUNDED_COINBASES, OUTPUTS_PER_COINBASE)?;
+        Ok(Self { inner, utxos })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        let mut pending = Vec::new();
+        let mut fees_seen = Vec::new();
+        for action in testcase.actions {
+            match action {
+                Action::SendTxs { from, feerates } => {
+                    let _ = self.send_txs(from, &feerates, &mut pending, &mut fees_seen);
+                }
+                Action::MineBlock { count } => {
+                    if self.mine_block(count, &mut pending).is_err() {
+                        continue;
+                    }
+                    if let Err(e) = self.check_estimates(&fees_seen) {
+                        return ScenarioResult::Fail(e);
+                    }
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    let _ = self.inner.target.set_mocktime(self.inner.time);
+                }
+            }
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(FeeEstimationScenario::<ScenarioTransport>, TestCase);
@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use fuzzamoto::{
+    connections::Transport,
+    scenarios::generic::GenericScenario,
+    targets::{BitcoinCoreTarget, RpcTarget, Target},
+};
+
+use crate::ir_scenario::{IrContext, IrExtension};
+
+const FILTER_INDEX: &str = "basic block filter index";
+const FILTER_INDEX_SYNC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `FilterIndex` starts the target with `-blockfilterindex=1 -peerblockfilters=1`, waits for the
+/// filter index to catch up with the chain and exposes the headers of the entire chain to
+/// programs, such that `getcfilters`, `getcfheaders` and `getcfcheckpt` requests can cover many
+/// different stop hashes and heights.
+#[derive(Default)]
+pub struct FilterIndex;
+
+/// Wait until the target's filter index has indexed the chain up to `height`.
+fn wait_for_filter_index(target: &BitcoinCoreTarget, height: u32) -> Result<(), String> {
+    let start = Instant::now();
+    loop {
+        let info = target.call_rpc("getindexinfo", &[FILTER_INDEX.into()])?;
+        let index = &info[FILTER_INDEX];
+        if index["synced"].as_bool() == Some(true)
+            && index["best_block_height"].as_u64() >= Some(u64::from(height))
+        {
+            return Ok(());
+        }
+
+        if start.elapsed() > FILTER_INDEX_SYNC_TIMEOUT {
+            return Err(format!(
+                "Filter index did not sync to height {height}: {info}"
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+impl<TX: Transport> IrExtension<TX, BitcoinCoreTarget> for FilterIndex
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn node_args(&self) -> Vec<String> {
+        vec![
+            "-blockfilterindex=1".to_string(),
+            "-peerblockfilters=1".to_string(),
+        ]
+    }
+
+    fn extend(
+        &self,
+        inner: &mut GenericScenario<TX, BitcoinCoreTarget>,
+        context: &mut IrContext,
+    ) -> Result<(), String> {
+        let height = inner
+            .block_tree
+            .values()
+            .map(|(_, height)| *height)
+            .max()
+            .unwrap_or_default();
+        wait_for_filter_index(&inner.target, height)?;
+        log::info!("Filter index synced to height {height}");
+
+        context.min_header_height = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use bitcoin::hashes::Hash;
+use fuzzamoto::{
+    connections::Transport,
+    scenarios::generic::GenericScenario,
+    targets::{HasBlockChainInterface, Target},
+};
+
+use crate::ir_scenario::{IrContext, IrExtension, OP_TRUE_SCRIPT_PUBKEY};
+
+/// `Funding` confirms a block on top of the setup chain that splits mature coinbase outputs into
+/// many smaller outputs, which are exposed to programs ahead of the remaining coinbase outputs.
+///
+/// This way programs can build many independent transactions and packages without first having to
+/// create their own funding transactions.
+pub struct Funding {
+    /// Number of mature coinbase outputs that are split up
+    pub coinbases: usize,
+    /// Number of outputs created per split coinbase output
+    pub outputs_per_coinbase: usize,
+}
+
+impl Default for Funding {
+    fn default() -> Self {
+        Self {
+            coinbases: 20,
+            outputs_per_coinbase: 25,
+        }
+    }
+}
+
+impl<TX, T> IrExtension<TX, T> for Funding
+where
+    TX: Transport,
+    T: Target<TX> + HasBlockChainInterface,
+{
+    fn extend(
+        &self,
+        inner: &mut GenericScenario<TX, T>,
+        context: &mut IrContext,
+    ) -> Result<(), String> {
+        let outputs = inner.fund(self.coinbases, self.outputs_per_coinbase)?;
+
+        // The splitting transactions are confirmed in the new tip, drop the coinbase outputs they
+        // spend from the context
+        let (tip_hash, _) = inner.tip()?;
+        let spent: HashSet<([u8; 32], u32)> = inner.block_tree[&tip_hash]
+            .0
+            .txdata
+            .iter()
+            .flat_map(|tx| &tx.input)
+            .map(|input| {
+                let outpoint = input.previous_output;
+                (outpoint.txid.to_byte_array(), outpoint.vout)
+            })
+            .collect();
+        context.txos.retain(|txo| !spent.contains(&txo.outpoint));
+
+        let mut txos: Vec<fuzzamoto_ir::Txo> = outputs
+            .into_iter()
+            .map(|(outpoint, value)| fuzzamoto_ir::Txo {
+                outpoint: (outpoint.txid.to_byte_array(), outpoint.vout),
+                value: value.to_sat(),
+                script_pubkey: OP_TRUE_SCRIPT_PUBKEY.to_vec(),
+                spending_script_sig: vec![],
+                spending_witness: vec![vec![0x51]],
+            })
+            .collect();
+        txos.append(&mut context.txos);
+        context.txos = txos;
+        Ok(())
+    }
+}
@@ -4,16 +4,24 @@ use fuzzamoto::{
         Scenario, ScenarioInput, ScenarioResult,
         generic::{GenericScenario, TestCase},
     },
-    targets::BitcoinCoreTarget,
 };
 
 // Transport type alias based on feature flag
-#[cfg(not(feature = "v2transport"))]
+#[cfg(not(any(feature = "v2transport", feature = "chaos")))]
 type ScenarioTransport = fuzzamoto::connections::V1Transport;
 #[cfg(feature = "v2transport")]
 type ScenarioTransport = fuzzamoto::connections::V2Transport;
+#[cfg(all(feature = "chaos", not(feature = "v2transport")))]
+type ScenarioTransport =
+    fuzzamoto::connections::ChaosTransport<fuzzamoto::connections::V1Transport>;
+
+// Target type alias based on feature flag
+#[cfg(not(feature = "docker"))]
+type ScenarioTarget = fuzzamoto::targets::BitcoinCoreTarget;
+#[cfg(feature = "docker")]
+type ScenarioTarget = fuzzamoto::targets::DockerTarget;
 
 fuzzamoto_main!(
-    GenericScenario::<ScenarioTransport, BitcoinCoreTarget>,
+    GenericScenario::<ScenarioTransport, ScenarioTarget>,
     TestCase
 );
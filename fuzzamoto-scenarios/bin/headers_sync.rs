@@ -0,0 +1,335 @@
+use fuzzamoto::{
+    connections::{ConnectionPool, ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    oracles::{DisconnectOracle, Oracle, OracleResult},
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, ResourceUsage, Target, TargetNode},
+    test_utils::{self, mining},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    BlockHash, CompactTarget, TxMerkleNode, block,
+    consensus::encode,
+    hashes::Hash,
+    p2p::{message::NetworkMessage, message_blockdata::GetHeadersMessage},
+};
+use std::time::Duration;
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Length of the valid header chain created during setup
+const CHAIN_LENGTH: u32 = 12_000;
+/// Minimum chain work of the target, reached after 10000 regtest headers (2 work each), such that
+/// shorter chains are subject to headers presync
+const MINIMUM_CHAIN_WORK: &str = "-minimumchainwork=0x4e20";
+/// Maximum length of forks created by testcases
+const MAX_FORK_LENGTH: u16 = 2500;
+/// Time after which a connection that doesn't answer a ping is considered stalled
+const PONG_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Arbitrary, Clone, Copy)]
+enum Corruption {
+    /// Leave out a header, such that the sequence isn't continuous
+    Unconnecting { index: u16 },
+    /// Set the difficulty of a header to one its hash doesn't satisfy
+    BadProofOfWork { index: u16 },
+    /// Move the timestamp of a header before the median time past
+    TimeTooOld { index: u16 },
+    /// Set the version of a header (e.g. to an outdated one)
+    Version { index: u16, version: i32 },
+    /// Append a duplicate of a header
+    Duplicate { index: u16 },
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new connection
+    Connect { outbound: bool },
+    /// Create a fork of the valid chain at `base` (or of a previously created fork)
+    Fork { chain: u8, base: u16, length: u16 },
+    /// Send a `headers` message containing up to `count` headers of a chain, starting at `start`
+    SendHeaders {
+        from: u8,
+        chain: u8,
+        start: u16,
+        count: u16,
+        corruption: Option<Corruption>,
+    },
+    /// Request headers from the target with a locator pointing into a chain
+    GetHeaders { from: u8, chain: u8, height: u16 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `HeadersSyncScenario` tests headers synchronization, including the low-work headers presync
+/// (and the commitments verified while redownloading) and the related DoS protections.
+///
+/// The target starts at genesis with a minimum chain work that is only reached by long header
+/// chains. The setup mines a valid chain exceeding the minimum chain work, from which testcases
+/// create forks and send (possibly corrupted) chunks of headers from various peers, i.e. each
+/// testcase represents a series of different types of actions:
+///
+/// 1. Open a new connection
+/// 2. Create a fork of one of the known chains
+/// 3. Send `headers` of one of the known chains (valid, low-work, forked, unconnecting or
+///    otherwise corrupted)
+/// 4. Send `getheaders` for one of the known chains
+/// 5. Advance the mocktime of the target node
+///
+/// Only peers that sent corrupted or oversized `headers` may be disconnected, unless the mocktime
+/// was advanced (e.g. due to headers sync timeouts), and no peer may stall (see
+/// `DisconnectOracle`).
+struct HeadersSyncScenario<TX: Transport> {
+    target: BitcoinCoreTarget,
+    connections: ConnectionPool<TX>,
+    time: u64,
+    genesis: block::Header,
+    /// The valid chain (first) and forks created by testcases, each excluding its base
+    chains: Vec<Vec<block::Header>>,
+    handshake_opts: HandshakeOpts,
+}
+
+/// Mine `length` headers on top of `base`, distinguished from other chains by `chain_id`
+fn mine_chain(base: &block::Header, length: u32, chain_id: u32) -> Vec<block::Header> {
+    let mut headers: Vec<block::Header> = Vec::new();
+    for i in 0..length {
+        let prev = headers.last().unwrap_or(base);
+        let (prev_hash, time) = (prev.block_hash(), prev.time + 1);
+        let merkle_root = TxMerkleNode::hash(&[chain_id.to_le_bytes(), i.to_le_bytes()].concat());
+        headers.push(mining::mine_header(prev_hash, time, merkle_root));
+    }
+    headers
+}
+
+impl<TX: Transport> HeadersSyncScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn connect(&mut self, outbound: bool) -> Result<(), String> {
+        let connection_type = if outbound {
+            ConnectionType::Outbound
+        } else {
+            ConnectionType::Inbound
+        };
+        let mut connection = self.target.connect(connection_type)?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.time).map_err(|_| "Invalid time".to_string())?,
+            ..self.handshake_opts.clone()
+        })?;
+        connection.set_pong_timeout(Some(PONG_TIMEOUT));
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    fn chain(&self, chain: u8) -> &[block::Header] {
+        &self.chains[usize::from(chain) % self.chains.len()]
+    }
+
+    fn fork(&mut self, chain: u8, base: u16, length: u16) {
+        let parent = self.chain(chain);
+        let base = if parent.is_empty() {
+            self.genesis
+        } else {
+            parent[usize::from(base) % parent.len()]
+        };
+        let chain_id = u32::try_from(self.chains.len()).unwrap_or(u32::MAX);
+        let fork = mine_chain(&base, u32::from(length % MAX_FORK_LENGTH) + 1, chain_id);
+        self.chains.push(fork);
+    }
+
+    fn corrupt(headers: &mut Vec<block::Header>, corruption: Corruption) {
+        if headers.is_empty() {
+            return;
+        }
+        let len = headers.len();
+        let pick = |index: u16| usize::from(index) % len;
+        match corruption {
+            Corruption::Unconnecting { index } => {
+                headers.remove(pick(index));
+            }
+            Corruption::BadProofOfWork { index } => {
+                let header = &mut headers[pick(index)];
+                header.bits = CompactTarget::from_consensus(0x1d00_ffff);
+            }
+            Corruption::TimeTooOld { index } => {
+                let header = &mut headers[pick(index)];
+                header.time = header.time.saturating_sub(3600);
+                mining::fixup_header_proof_of_work(header);
+            }
+            Corruption::Version { index, version } => {
+                let header = &mut headers[pick(index)];
+                header.version = block::Version::from_consensus(version);
+                mining::fixup_header_proof_of_work(header);
+            }
+            Corruption::Duplicate { index } => {
+                let header = headers[pick(index)];
+                headers.push(header);
+            }
+        }
+    }
+
+    fn send_headers(
+        &mut self,
+        from: u8,
+        chain: u8,
+        start: u16,
+        count: u16,
+        corruption: Option<Corruption>,
+    ) {
+        let chain = self.chain(chain);
+        if chain.is_empty() {
+            return;
+        }
+        let start = usize::from(start) % chain.len();
+        // Allow exceeding the limit by one to hit the oversized message check
+        let end = chain
+            .len()
+            .min(start + usize::from(count) % (test_utils::MAX_HEADERS_RESULTS + 2));
+        let mut headers = chain[start..end].to_vec();
+        if let Some(corruption) = corruption {
+            Self::corrupt(&mut headers, corruption);
+        }
+        if corruption.is_some() || headers.len() > test_utils::MAX_HEADERS_RESULTS {
+            self.connections.expect_disconnect(usize::from(from));
+        }
+
+        let headers = NetworkMessage::Headers(headers);
+        let _ = self.connections.send(
+            usize::from(from),
+            &("headers".to_string(), encode::serialize(&headers)),
+        );
+    }
+
+    fn get_headers(&mut self, from: u8, chain: u8, height: u16) {
+        let chain = self.chain(chain);
+        let tip = if chain.is_empty() {
+            self.genesis.block_hash()
+        } else {
+            chain[usize::from(height) % chain.len()].block_hash()
+        };
+        let getheaders = NetworkMessage::GetHeaders(GetHeadersMessage {
+            version: 70016,
+            locator_hashes: vec![tip, self.genesis.block_hash()],
+            stop_hash: BlockHash::all_zeros(),
+        });
+        let _ = self.connections.send(
+            usize::from(from),
+            &("getheaders".to_string(), encode::serialize(&getheaders)),
+        );
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for HeadersSyncScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        // The headers are mined at regtest difficulty
+        let chain_params = args.chain_params()?;
+        chain_params.require_regtest()?;
+        let target =
+            BitcoinCoreTarget::from_path_with_args(args, &chain_params, &[MINIMUM_CHAIN_WORK])?;
+
+        let genesis = chain_params.genesis.header;
+        let main_chain = mine_chain(&genesis, CHAIN_LENGTH, 0);
+        // Headers more than two hours in the future are rejected
+        let time = u64::from(main_chain.last().map_or(genesis.time, |header| header.time));
+
+        let mut scenario = Self {
+            target,
+            connections: ConnectionPool::new(),
+            time,
+            genesis,
+            chains: vec![main_chain],
+            handshake_opts: args.handshake_opts(),
+        };
+        scenario.target.set_mocktime(time)?;
+        for outbound in [true, true, true, true, false, false, false, false] {
+            scenario.connect(outbound)?;
+        }
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::Connect { outbound } => {
+                    if self.connections.len() < test_utils::MAX_PEERS {
+                        let _ = self.connect(outbound);
+                    }
+                }
+                Action::Fork {
+                    chain,
+                    base,
+                    length,
+                } => self.fork(chain, base, length),
+                Action::SendHeaders {
+                    from,
+                    chain,
+                    start,
+                    count,
+                    corruption,
+                } => self.send_headers(from, chain, start, count, corruption),
+                Action::GetHeaders {
+                    from,
+                    chain,
+                    height,
+                } => self.get_headers(from, chain, height),
+                Action::AdvanceTime { seconds } => {
+                    self.time += u64::from(seconds);
+                    let _ = self.target.set_mocktime(self.time);
+                    // Timeouts may legitimately disconnect any peer
+                    for index in 0..self.connections.len() {
+                        self.connections.expect_disconnect(index);
+                    }
+                }
+            }
+        }
+
+        self.connections.ping_all();
+
+        if let OracleResult::Fail(e) = DisconnectOracle::default().evaluate(&mut self.connections) {
+            return ScenarioResult::Fail(e);
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(HeadersSyncScenario::<ScenarioTransport>, TestCase);
@@ -1,15 +1,23 @@
 use fuzzamoto::{
     fuzzamoto_main,
-    scenarios::{Scenario, ScenarioInput, ScenarioResult},
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
     targets::{BitcoinCoreTarget, TargetNode},
 };
 
 use arbitrary::{Arbitrary, Unstructured};
+use base64::prelude::*;
 use std::fmt;
+use std::fs;
 use std::io::Write;
 
 use std::net::TcpStream;
 
+/// RPC methods the cookie user may call. Authenticated requests of testcases reach the RPC
+/// dispatcher, so methods altering the node (e.g. `stop`) are excluded. The wallet methods are
+/// needed to start the node.
+const RPC_WHITELIST: &str = "-rpcwhitelist=__cookie__:echo,help,uptime,getnetworkinfo,\
+getblockchaininfo,getblockcount,getbestblockhash,getmempoolinfo,createwallet,loadwallet";
+
 #[derive(Arbitrary, Clone, Copy)]
 enum Method {
     Get,
@@ -99,12 +107,44 @@ fn nibble_to_hex(n: u8) -> char {
     }
 }
 
+/// Value of the `Authorization` header
+#[derive(Arbitrary, Clone, Copy)]
+enum Authorization<'a> {
+    /// No `Authorization` header
+    Missing,
+    /// `Basic` authorization with the credentials from the target's cookie file
+    Cookie,
+    /// `Basic` authorization as the cookie user with an arbitrary password
+    CookieUser { password: &'a [u8] },
+    /// `Basic` authorization with arbitrary (base64 encoded) credentials
+    Basic { credentials: &'a [u8] },
+    /// Arbitrary header value (e.g. other schemes or invalid base64)
+    Raw { value: &'a [u8] },
+}
+
+/// How the body of a request is framed
+#[derive(Arbitrary, Clone, Copy)]
+enum BodyEncoding {
+    /// `Content-Length` header
+    ContentLength,
+    /// `Transfer-Encoding: chunked` with chunks of `chunk_size` bytes, the size of the first chunk
+    /// misreported by `size_delta`
+    Chunked { chunk_size: u16, size_delta: i8 },
+    /// Both a `Content-Length` header and chunked transfer encoding
+    Both { chunk_size: u16 },
+}
+
 struct HttpMessage<'a> {
     is_chaos: bool,
     method: Method,
     path: Path<'a>,
     body: &'a [u8],
     chaos_data: &'a [u8],
+    authorization: Authorization<'a>,
+    encoding: BodyEncoding,
+    keep_alive: bool,
+    /// Only send the first `truncate` bytes of the request
+    truncate: Option<u16>,
 }
 
 impl<'a> Arbitrary<'a> for HttpMessage<'a> {
@@ -117,11 +157,15 @@ impl<'a> Arbitrary<'a> for HttpMessage<'a> {
             path: Arbitrary::arbitrary(u)?,
             body: Arbitrary::arbitrary(u)?,
             chaos_data: Arbitrary::arbitrary(u)?,
+            authorization: Arbitrary::arbitrary(u)?,
+            encoding: Arbitrary::arbitrary(u)?,
+            keep_alive: Arbitrary::arbitrary(u)?,
+            truncate: Arbitrary::arbitrary(u)?,
         })
     }
 }
 
-fn build_request(msg: &HttpMessage<'_>) -> Vec<u8> {
+fn build_request(msg: &HttpMessage<'_>, cookie: &str) -> Vec<u8> {
     if msg.is_chaos {
         const MAX_CHAOS: usize = 8 * 1024;
         if msg.chaos_data.len() > MAX_CHAOS {
@@ -130,16 +174,60 @@ fn build_request(msg: &HttpMessage<'_>) -> Vec<u8> {
             msg.chaos_data.to_vec()
         }
     } else {
-        build_wellformed_request(msg.method, &msg.path, msg.body)
+        let mut req = build_wellformed_request(msg, cookie);
+        if let Some(len) = msg.truncate {
+            req.truncate(usize::from(len));
+        }
+        req
     }
 }
 
-fn build_wellformed_request(method: Method, path: &Path<'_>, body: &[u8]) -> Vec<u8> {
+fn write_authorization(req: &mut Vec<u8>, authorization: Authorization<'_>, cookie: &str) {
+    let credentials = match authorization {
+        Authorization::Missing => return,
+        Authorization::Cookie => cookie.as_bytes().to_vec(),
+        Authorization::CookieUser { password } => {
+            let mut credentials = b"__cookie__:".to_vec();
+            credentials.extend_from_slice(password);
+            credentials
+        }
+        Authorization::Basic { credentials } => credentials.to_vec(),
+        Authorization::Raw { value } => {
+            // Keep the header on a single line
+            req.extend_from_slice(b"Authorization: ");
+            req.extend(value.iter().filter(|b| !matches!(b, b'\r' | b'\n')));
+            req.extend_from_slice(b"\r\n");
+            return;
+        }
+    };
+    let _ = write!(
+        req,
+        "Authorization: Basic {}\r\n",
+        BASE64_STANDARD.encode(credentials)
+    );
+}
+
+fn write_chunked(req: &mut Vec<u8>, body: &[u8], chunk_size: u16, size_delta: i8) {
+    let chunk_size = usize::from(chunk_size).max(1);
+    for (i, chunk) in body.chunks(chunk_size).enumerate() {
+        let size = if i == 0 {
+            chunk.len().saturating_add_signed(isize::from(size_delta))
+        } else {
+            chunk.len()
+        };
+        let _ = write!(req, "{size:x}\r\n");
+        req.extend_from_slice(chunk);
+        req.extend_from_slice(b"\r\n");
+    }
+    req.extend_from_slice(b"0\r\n\r\n");
+}
+
+fn build_wellformed_request(msg: &HttpMessage<'_>, cookie: &str) -> Vec<u8> {
     const MAX_BODY: usize = 8 * 1024;
-    let body = if body.len() > MAX_BODY {
-        &body[..MAX_BODY]
+    let body = if msg.body.len() > MAX_BODY {
+        &msg.body[..MAX_BODY]
     } else {
-        body
+        msg.body
     };
 
     let mut req = Vec::with_capacity(256 + body.len());
@@ -148,20 +236,41 @@ fn build_wellformed_request(method: Method, path: &Path<'_>, body: &[u8]) -> Vec
         req,
         "{} {} HTTP/1.1\r\n\
          Host: localhost\r\n\
-         Connection: keep-alive\r\n",
-        method.as_str(),
-        path,
+         Connection: {}\r\n",
+        msg.method.as_str(),
+        msg.path,
+        if msg.keep_alive {
+            "keep-alive"
+        } else {
+            "close"
+        },
     );
+    write_authorization(&mut req, msg.authorization, cookie);
 
-    if method.can_have_body() {
-        let _ = write!(
-            req,
-            "Content-Type: application/json\r\n\
-             Content-Length: {}\r\n\
-             \r\n",
-            body.len()
-        );
-        req.extend_from_slice(body);
+    if msg.method.can_have_body() {
+        req.extend_from_slice(b"Content-Type: application/json\r\n");
+        match msg.encoding {
+            BodyEncoding::ContentLength => {
+                let _ = write!(req, "Content-Length: {}\r\n\r\n", body.len());
+                req.extend_from_slice(body);
+            }
+            BodyEncoding::Chunked {
+                chunk_size,
+                size_delta,
+            } => {
+                req.extend_from_slice(b"Transfer-Encoding: chunked\r\n\r\n");
+                write_chunked(&mut req, body, chunk_size, size_delta);
+            }
+            BodyEncoding::Both { chunk_size } => {
+                let _ = write!(
+                    req,
+                    "Content-Length: {}\r\n\
+                     Transfer-Encoding: chunked\r\n\r\n",
+                    body.len()
+                );
+                write_chunked(&mut req, body, chunk_size, 0);
+            }
+        }
     } else {
         req.extend_from_slice(b"\r\n");
     }
@@ -176,6 +285,11 @@ enum Action<'a> {
         connection_id: u8,
         message: HttpMessage<'a>,
     },
+    /// Send several requests with a single write, without waiting for the responses
+    SendPipelined {
+        connection_id: u8,
+        messages: Vec<HttpMessage<'a>>,
+    },
     Disconnect {
         connection_id: u8,
     },
@@ -197,19 +311,30 @@ impl<'a> ScenarioInput<'a> for TestCase<'a> {
 /// `HttpServerScenario` is a scenario that tests the HTTP server of Bitcoin Core.
 ///
 /// Testcases simulate the processing of a series of actions by the HTTP server of Bitcoin Core.
-/// Each testcase represents a series of three types of actions:
+/// Requests control their authorization (valid cookie credentials, wrong passwords, arbitrary
+/// credentials), the framing of their body (`Content-Length`, chunked or both), keep-alive and
+/// truncation. Each testcase represents a series of four types of actions:
 ///
 /// 1. Connect to the HTTP server
 /// 2. Send a message to the HTTP server from a specific connection
-/// 3. Disconnect one of the existing connections
+/// 3. Send several pipelined messages from a specific connection
+/// 4. Disconnect one of the existing connections
 struct HttpServerScenario {
     target: BitcoinCoreTarget,
+    /// Credentials (`__cookie__:<password>`) from the target's cookie file
+    cookie: String,
 }
 
 impl<'a> Scenario<'a, TestCase<'a>> for HttpServerScenario {
-    fn new(args: &[String]) -> Result<Self, String> {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target =
+            BitcoinCoreTarget::from_path_with_args(args, &args.chain_params()?, &[RPC_WHITELIST])?;
+        let cookie = fs::read_to_string(&target.node.params.cookie_file)
+            .map_err(|e| format!("Failed to read RPC cookie: {e}"))?;
+
         Ok(Self {
-            target: BitcoinCoreTarget::from_path(&args[1])?,
+            target,
+            cookie: cookie.trim().to_string(),
         })
     }
 
@@ -239,7 +364,26 @@ impl<'a> Scenario<'a, TestCase<'a>> for HttpServerScenario {
                     }
                     let index = connection_id as usize % connections.len();
                     let connection = connections.get_mut(index).unwrap();
-                    let req = build_request(&message);
+                    let req = build_request(&message, &self.cookie);
+                    let _ = connection.write_all(&req);
+                    let _ = connection.flush();
+                }
+                Action::SendPipelined {
+                    connection_id,
+                    messages,
+                } => {
+                    // Limit the number of requests
+                    const MAX_PIPELINED: usize = 16;
+                    if connections.is_empty() {
+                        continue;
+                    }
+                    let index = connection_id as usize % connections.len();
+                    let connection = connections.get_mut(index).unwrap();
+                    let req: Vec<u8> = messages
+                        .iter()
+                        .take(MAX_PIPELINED)
+                        .flat_map(|message| build_request(message, &self.cookie))
+                        .collect();
                     let _ = connection.write_all(&req);
                     let _ = connection.flush();
                 }
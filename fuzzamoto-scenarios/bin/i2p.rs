@@ -0,0 +1,200 @@
+use fuzzamoto::{
+    connections::{
+        ConnectionPool, ConnectionType, HandshakeOpts, I2pDestination, I2pSamTransport,
+        TrafficMetrics,
+    },
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, ResourceUsage, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    consensus::encode,
+    p2p::{
+        ServiceFlags,
+        address::{AddrV2, AddrV2Message},
+        message::NetworkMessage,
+    },
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of inbound I2P peers connected during setup
+const NUM_PEERS: u8 = 4;
+
+#[derive(Arbitrary)]
+enum Destination {
+    /// One of the destinations derived with `I2pDestination::from_seed`, which the fuzzer can
+    /// accept connections for
+    Known(u8),
+    /// An arbitrary destination hash
+    Raw([u8; 32]),
+}
+
+#[derive(Arbitrary)]
+struct I2pEntry {
+    /// Age of the address in minutes relative to the target's time (negative values are in the
+    /// future)
+    age: i16,
+    services: u64,
+    destination: Destination,
+    /// Port of the address (I2P addresses are only valid with port 0)
+    port: u16,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new I2P connection from (inbound) or to (outbound) the destination derived from
+    /// `peer`
+    Connect { outbound: bool, peer: u8 },
+    /// Send an `addrv2` message with I2P addresses
+    AddrV2 { from: u8, entries: Vec<I2pEntry> },
+    /// Send a `getaddr` message
+    GetAddr { from: u8 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u32 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        TestCase::arbitrary(&mut unstructured).map_err(|e| e.to_string())
+    }
+}
+
+/// `I2pScenario` tests Bitcoin Core's I2P connection and address handling.
+///
+/// The target is started with the fuzzer as its I2P SAM bridge (see
+/// `BitcoinCoreTarget::from_path_with_i2p_sam`) and a couple of inbound I2P connections are made
+/// during setup. Testcases open further inbound and outbound I2P connections and gossip I2P
+/// addresses (of destinations the bridge can or can't resolve), i.e. each testcase represents a
+/// series of different types of actions:
+///
+/// 1. Open a new inbound or outbound I2P connection
+/// 2. Send I2P addresses or request addresses
+/// 3. Advance the mocktime of the target node
+struct I2pScenario {
+    target: BitcoinCoreTarget,
+    connections: ConnectionPool<I2pSamTransport>,
+    time: u64,
+    handshake_opts: HandshakeOpts,
+}
+
+impl I2pScenario {
+    fn connect(&mut self, connection_type: ConnectionType, peer: u8) -> Result<(), String> {
+        let destination = I2pDestination::from_seed(u64::from(peer));
+        let mut connection = self.target.connect_i2p(connection_type, &destination)?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.time).map_err(|_| "Invalid time".to_string())?,
+            ..self.handshake_opts.clone()
+        })?;
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    fn entry_time(&self, age: i16) -> u32 {
+        let time = i64::try_from(self.time).unwrap_or_default() - i64::from(age) * 60;
+        u32::try_from(time).unwrap_or_default()
+    }
+
+    fn send(&mut self, from: u8, message: &NetworkMessage) {
+        let command = message.cmd().to_string();
+        let _ = self
+            .connections
+            .send(usize::from(from), &(command, encode::serialize(message)));
+    }
+}
+
+impl Scenario<'_, TestCase> for I2pScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_path_with_i2p_sam(args, &args.chain_params()?, &[])?;
+
+        // Addresses are aged relative to the target's time, so start out at the current time
+        // rather than the genesis time
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get the current time: {e}"))?
+            .as_secs();
+
+        let mut scenario = Self {
+            target,
+            connections: ConnectionPool::new(),
+            time,
+            handshake_opts: args.handshake_opts(),
+        };
+        scenario.target.set_mocktime(time)?;
+        for peer in 0..NUM_PEERS {
+            scenario.connect(ConnectionType::Inbound, peer)?;
+        }
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::Connect { outbound, peer } => {
+                    if self.connections.len() < test_utils::MAX_PEERS {
+                        let connection_type = if outbound {
+                            ConnectionType::Outbound
+                        } else {
+                            ConnectionType::Inbound
+                        };
+                        let _ = self.connect(connection_type, peer);
+                    }
+                }
+                Action::AddrV2 { from, entries } => {
+                    let addresses = entries
+                        .iter()
+                        .map(|entry| AddrV2Message {
+                            time: self.entry_time(entry.age),
+                            services: ServiceFlags::from(entry.services),
+                            addr: AddrV2::I2p(match &entry.destination {
+                                Destination::Known(seed) => {
+                                    I2pDestination::from_seed(u64::from(*seed)).hash()
+                                }
+                                Destination::Raw(hash) => *hash,
+                            }),
+                            port: entry.port,
+                        })
+                        .collect();
+                    self.send(from, &NetworkMessage::AddrV2(addresses));
+                }
+                Action::GetAddr { from } => self.send(from, &NetworkMessage::GetAddr),
+                Action::AdvanceTime { seconds } => {
+                    self.time += u64::from(seconds);
+                    let _ = self.target.set_mocktime(self.time);
+                }
+            }
+        }
+
+        self.connections.ping_all();
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(I2pScenario, TestCase);
@@ -1,8 +1,10 @@
 use fuzzamoto::{
     connections::Transport,
     fuzzamoto_main,
-    scenarios::{Scenario, ScenarioInput, ScenarioResult, generic::GenericScenario},
-    targets::{BitcoinCoreTarget, TargetNode},
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, RpcTarget, TargetNode},
 };
 
 use std::io::Write;
@@ -37,7 +39,7 @@ impl<'a, TX: Transport> Scenario<'a, MempoolDotDatBytes<'a>> for ImportMempoolSc
 where
     BitcoinCoreTarget: fuzzamoto::targets::Target<TX>,
 {
-    fn new(args: &[String]) -> Result<Self, String> {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
         let inner = GenericScenario::<TX, BitcoinCoreTarget>::new(args)?;
         // Creates it on node's workdir but could be in any other place.
         let mempool_path = inner.target.node.workdir();
@@ -53,11 +55,10 @@ where
             let _ = mempool_file.write_all(input.0);
             let _ = mempool_file.flush();
 
-            let _ =
-                self.inner.target.node.client.call::<serde_json::Value>(
-                    "importmempool",
-                    &[self.mempool_path.to_str().into()],
-                );
+            let _ = self
+                .inner
+                .target
+                .call_rpc("importmempool", &[self.mempool_path.to_str().into()]);
         }
 
         if let Err(e) = self.inner.target.is_alive() {
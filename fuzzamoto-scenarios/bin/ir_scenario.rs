@@ -0,0 +1,911 @@
+#[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+use std::time::{Duration, Instant};
+
+use std::marker::PhantomData;
+
+use bitcoin::{
+    bip152::BlockTransactionsRequest,
+    consensus::{Decodable, encode},
+    hashes::Hash,
+    p2p::{Magic, message::NetworkMessage, message_compact_blocks::SendCmpct},
+};
+use fuzzamoto::{
+    connections::{HandshakeOpts, TrafficMetrics, Transport},
+    oracles::{CrashOracle, Oracle, OracleResult},
+    runners::snapshot_point,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult,
+        args::ScenarioArgs,
+        characterization::{Characterization, TipCharacterization},
+        crash_context::CrashContext,
+        generic::{GenericScenario, SetupOptions},
+    },
+    targets::{
+        ConnectableTarget, GenerateToAddress, HasBlockChainInterface, ResourceUsage, Target,
+        TargetCapabilities,
+    },
+};
+
+#[cfg(feature = "nyx")]
+use fuzzamoto_nyx_sys::*;
+use io::Cursor;
+#[cfg(feature = "nyx")]
+use std::ffi::CString;
+
+#[cfg(feature = "oracle_inflation")]
+use fuzzamoto::oracles::InflationOracle;
+
+#[cfg(feature = "oracle_blocktemplate")]
+use fuzzamoto::oracles::BlockTemplateOracle;
+
+#[cfg(feature = "oracle_netsplit")]
+use fuzzamoto::oracles::{NetSplitContext, NetSplitOracle};
+
+#[cfg(feature = "oracle_consensus")]
+use fuzzamoto::oracles::{ConsensusContext, ConsensusOracle};
+
+#[cfg(feature = "oracle_resources")]
+use fuzzamoto::oracles::ResourceOracle;
+
+#[cfg(feature = "oracle_log")]
+use fuzzamoto::oracles::LogOracle;
+
+#[cfg(feature = "oracle_zmq")]
+use fuzzamoto::oracles::ZmqOracle;
+
+#[cfg(feature = "oracle_latency")]
+use fuzzamoto::oracles::LatencyOracle;
+
+#[cfg(feature = "oracle_assertions")]
+use fuzzamoto::oracles::AssertionOracle;
+
+use fuzzamoto_ir::{
+    ProbeResult, ProbeResults, Program, ProgramContext, RecentBlock,
+    compiler::{CompiledAction, CompiledMetadata, CompiledProgram, Compiler},
+};
+
+const COINBASE_MATURITY_HEIGHT_LIMIT: u32 = 100;
+/// Lower bound of the latency oracle's baseline, such that setups that only sync quickly don't
+/// make it flaky
+#[cfg(feature = "oracle_latency")]
+const MIN_LATENCY_BASELINE: std::time::Duration = std::time::Duration::from_millis(10);
+/// Default multiple of the baseline latency a message batch may take (see `LatencyOracle`)
+#[cfg(feature = "oracle_latency")]
+const DEFAULT_LATENCY_FACTOR: u32 = 100;
+pub const LATE_BLOCK_HEIGHT_LIMIT: u32 = 190;
+const COINBASE_VALUE: u64 = 25 * 100_000_000;
+// OP_TRUE script pubkey: 0x0 0x20 sha256(OP_TRUE)
+pub const OP_TRUE_SCRIPT_PUBKEY: [u8; 34] = [
+    0u8, 32, 74, 232, 21, 114, 240, 110, 27, 136, 253, 92, 237, 122, 26, 0, 9, 69, 67, 46, 131,
+    225, 85, 30, 111, 114, 30, 233, 192, 11, 140, 195, 50, 96,
+];
+
+/// `IrScenario` is a scenario with the same context as `GenericScenario` but it operates on
+/// `fuzzamoto_ir::CompiledProgram`s as input.
+///
+/// The setup of `GenericScenario` is extended by `E` (see `IrExtension`), such that scenarios with
+/// additional snapshot state are just an `IrScenario` with a different extension.
+pub struct IrScenario<TX: Transport, T: Target<TX> + ConnectableTarget, E = ()> {
+    inner: GenericScenario<TX, T>,
+    /// Options of the connections made by the scenario (see `ScenarioArgs::handshake_opts`), the
+    /// base for the connections opened by IR programs
+    handshake_opts: HandshakeOpts,
+    recording_received_messages: bool,
+    probe_results: ProbeResults,
+    #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+    second: T,
+    #[cfg(feature = "oracle_resources")]
+    resource_oracle: Option<ResourceOracle>,
+    #[cfg(feature = "oracle_log")]
+    log_oracle: LogOracle,
+    #[cfg(feature = "oracle_latency")]
+    latency_oracle: LatencyOracle,
+    #[cfg(feature = "oracle_assertions")]
+    assertion_oracle: AssertionOracle,
+    /// Index of the IR instruction of the action that was executed last
+    instruction_index: Option<usize>,
+    futurest: u64,
+    extension: PhantomData<E>,
+}
+
+/// Latency factor of the latency oracle, configurable with `FUZZAMOTO_LATENCY_FACTOR`
+#[cfg(feature = "oracle_latency")]
+fn latency_factor() -> Result<u32, String> {
+    std::env::var("FUZZAMOTO_LATENCY_FACTOR").map_or(Ok(DEFAULT_LATENCY_FACTOR), |factor| {
+        factor
+            .parse()
+            .map_err(|_| format!("Failed to parse FUZZAMOTO_LATENCY_FACTOR: {factor:?}"))
+    })
+}
+
+#[cfg(feature = "nyx")]
+pub fn nyx_print(bytes: &[u8]) {
+    if let Ok(message) = CString::new(bytes) {
+        unsafe {
+            nyx_println(message.as_ptr(), bytes.len());
+        }
+    }
+}
+
+pub struct TestCase {
+    program: CompiledProgram,
+}
+
+fn probe_result_mapper(
+    action_index: usize,
+    metadata: &CompiledMetadata,
+) -> impl Fn((usize, String, Vec<u8>)) -> ProbeResult {
+    move |(conn, s, mut bytes): (usize, String, Vec<u8>)| match s.as_str() {
+        "getblocktxn" => {
+            let Ok(request) = BlockTransactionsRequest::consensus_decode_from_finite_reader(
+                &mut Cursor::new(&mut bytes),
+            ) else {
+                return ProbeResult::Failure {
+                    command: s.clone(),
+                    reason: "getblocktxn: Fail to call consensus_decode_from_finite_reader"
+                        .to_string(),
+                };
+            };
+
+            let Some((_, block_var, tx_vars)) = metadata.block_variables(&request.block_hash)
+            else {
+                return ProbeResult::Failure {
+                    command: s.clone(),
+                    reason: "getblocktxn: block hash is not registered in the metadata".to_string(),
+                };
+            };
+
+            let Some(conn_var) = metadata.connection_map().get(&conn) else {
+                return ProbeResult::Failure {
+                    command: s.clone(),
+                    reason: "getblocktxn: couldn't find matching connection var".to_string(),
+                };
+            };
+
+            let get_block_txn = fuzzamoto_ir::GetBlockTxn {
+                connection_index: *conn_var,
+                triggering_instruction_index: metadata.instruction_indices()[action_index],
+                block_variable: block_var,
+                tx_indices_variables: tx_vars.to_vec(),
+            };
+
+            ProbeResult::GetBlockTxn { get_block_txn }
+        }
+        _ => unreachable!(
+            "Unexpected command; The filter must ensure only supported commands reach this point"
+        ),
+    }
+}
+
+impl<'a> ScenarioInput<'a> for TestCase {
+    fn decode(bytes: &'a [u8]) -> Result<Self, String> {
+        let program = if cfg!(feature = "compile_in_vm") {
+            let program: Program = postcard::from_bytes(bytes).map_err(|e| e.to_string())?;
+            let mut compiler = Compiler::new();
+            compiler.compile(&program).map_err(|e| e.to_string())?
+        } else {
+            postcard::from_bytes(bytes).map_err(|e| e.to_string())?
+        };
+        Ok(Self { program })
+    }
+}
+
+impl<TX, T, E> IrScenario<TX, T, E>
+where
+    TX: Transport,
+    T: Target<TX> + ConnectableTarget + HasBlockChainInterface + GenerateToAddress,
+{
+    /// Build the IR program context
+    fn build_program_context(inner: &GenericScenario<TX, T>) -> ProgramContext {
+        ProgramContext {
+            num_nodes: 1,
+            num_connections: inner.connections.len(),
+            timestamp: inner.time,
+        }
+    }
+
+    /// Extract coinbase outputs from mature blocks (height < 100) for use in IR programs
+    pub fn build_txos(inner: &GenericScenario<TX, T>) -> Vec<fuzzamoto_ir::Txo> {
+        let mut txos = Vec::new();
+        for (block, _height) in inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height < COINBASE_MATURITY_HEIGHT_LIMIT)
+        {
+            let coinbase = block.coinbase().unwrap();
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(
+                coinbase
+                    .compute_txid()
+                    .as_raw_hash()
+                    .as_byte_array()
+                    .as_slice(),
+            );
+
+            txos.push(fuzzamoto_ir::Txo {
+                outpoint: (hash, 0u32),
+                value: COINBASE_VALUE,
+                script_pubkey: OP_TRUE_SCRIPT_PUBKEY.to_vec(),
+                spending_script_sig: vec![],
+                spending_witness: vec![vec![0x51]],
+            });
+        }
+        txos
+    }
+
+    /// Extract block headers above `min_height` (usually `LATE_BLOCK_HEIGHT_LIMIT`) for use in IR
+    /// programs
+    pub fn build_headers(
+        inner: &GenericScenario<TX, T>,
+        min_height: u32,
+    ) -> Vec<fuzzamoto_ir::Header> {
+        inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height > min_height)
+            .map(|(block, height)| fuzzamoto_ir::Header {
+                prev: *block.header.prev_blockhash.as_byte_array(),
+                merkle_root: *block.header.merkle_root.as_byte_array(),
+                nonce: block.header.nonce,
+                bits: block.header.bits.to_consensus(),
+                time: block.header.time,
+                version: block.header.version.to_consensus(),
+                height: *height,
+            })
+            .collect()
+    }
+
+    /// Dump the full program context either to Nyx host or to a file
+    fn dump_context(
+        context: ProgramContext,
+        txos: Vec<fuzzamoto_ir::Txo>,
+        headers: Vec<fuzzamoto_ir::Header>,
+        capabilities: TargetCapabilities,
+        setup: SetupOptions,
+        magic: Magic,
+    ) -> Result<(), String> {
+        let full_context = postcard::to_allocvec(&fuzzamoto_ir::FullProgramContext {
+            context,
+            txos,
+            headers,
+            capabilities,
+            setup,
+            magic: magic.to_bytes(),
+        })
+        .map_err(|e| e.to_string())?;
+
+        #[cfg(feature = "nyx")]
+        {
+            const CONTEXT_FILE_NAME: &str = "ir.context";
+            unsafe {
+                nyx_dump_file_to_host(
+                    CONTEXT_FILE_NAME.as_ptr() as *const i8,
+                    CONTEXT_FILE_NAME.len(),
+                    full_context.as_ptr(),
+                    full_context.len(),
+                );
+            }
+        }
+
+        #[cfg(not(feature = "nyx"))]
+        if let Ok(context_file) = std::env::var("DUMP_CONTEXT") {
+            std::fs::write(context_file, &full_context).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+    fn create_and_sync_second_target(args: &ScenarioArgs, primary: &T) -> Result<T, String> {
+        // The second target defaults to the primary target's executable and runs with the same
+        // chain and node arguments
+        let second_args = ScenarioArgs {
+            target: args.extra.first().unwrap_or(&args.target).clone(),
+            ..args.clone()
+        };
+        let mut second = T::from_scenario_args(&second_args)?;
+        second.connect_to(primary)?;
+        Self::sync_nodes(primary, &mut second)?;
+        Ok(second)
+    }
+
+    #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+    fn sync_nodes(primary: &T, reference: &mut T) -> Result<(), String> {
+        const SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let start = Instant::now();
+        let mut synced = false;
+
+        while start.elapsed() < SYNC_TIMEOUT {
+            let primary_tip = primary.get_tip_info();
+            let reference_tip = reference.get_tip_info();
+
+            if primary_tip.is_some() && primary_tip == reference_tip {
+                log::info!("Nodes synced successfully!");
+                synced = true;
+                break;
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        if !synced {
+            return Err("nodes failed to sync".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn process_actions(&mut self, mut program: CompiledProgram) {
+        let message_filter = |(s, _): &(String, Vec<u8>)| ["getblocktxn"].contains(&s.as_str());
+        let mut non_probe_action_count = 0;
+        // Consecutive messages to the same connection are sent as one batch (i.e. with a single
+        // write), unless every message needs to be followed by a ping.
+        let mut batch: Option<(usize, Vec<(String, Vec<u8>)>)> = None;
+        for action in program.actions.drain(..) {
+            let instruction_index = program
+                .metadata
+                .instruction_indices()
+                .get(non_probe_action_count)
+                .copied();
+            let action = match action {
+                CompiledAction::SendRawMessage(from, command, message)
+                    if !cfg!(feature = "force_send_and_ping")
+                        && !self.inner.connections.is_empty() =>
+                {
+                    if let Some((to, messages)) = &mut batch
+                        && *to == from
+                    {
+                        messages.push((command, message));
+                    } else {
+                        self.send_batch(batch.take());
+                        batch = Some((from, vec![(command, message)]));
+                    }
+                    self.instruction_index = instruction_index;
+                    non_probe_action_count += 1;
+                    continue;
+                }
+                action => {
+                    self.send_batch(batch.take());
+                    if !matches!(action, CompiledAction::Probe) {
+                        self.instruction_index = instruction_index;
+                        self.publish_crash_context();
+                    }
+                    action
+                }
+            };
+
+            match action {
+                CompiledAction::Connect(_node, connection_type) => {
+                    let conn_type = match connection_type.as_str() {
+                        "inbound" => fuzzamoto::connections::ConnectionType::Inbound,
+                        "outbound" => fuzzamoto::connections::ConnectionType::Outbound,
+                        _ => continue,
+                    };
+
+                    if let Ok(connection) = self.inner.target.connect(conn_type) {
+                        self.inner.connections.push(connection);
+                    }
+                    non_probe_action_count += 1;
+                }
+                CompiledAction::ConnectAndHandshake {
+                    node: _,
+                    connection_type,
+                    relay,
+                    starting_height,
+                    wtxidrelay,
+                    addrv2,
+                    erlay,
+                    time,
+                    send_compact,
+                } => {
+                    let conn_type = match connection_type.as_str() {
+                        "inbound" => fuzzamoto::connections::ConnectionType::Inbound,
+                        "outbound" => fuzzamoto::connections::ConnectionType::Outbound,
+                        _ => continue,
+                    };
+
+                    #[allow(clippy::cast_possible_wrap)]
+                    let handshake_opts = HandshakeOpts {
+                        time: time as i64,
+                        relay,
+                        starting_height,
+                        wtxidrelay,
+                        addrv2,
+                        erlay,
+                        ..self.handshake_opts.clone()
+                    };
+
+                    if let Ok(mut connection) = self.inner.target.connect(conn_type)
+                        && connection.version_handshake(handshake_opts).is_ok()
+                    {
+                        if let Some(send_compact) = send_compact {
+                            let sendcmpct = NetworkMessage::SendCmpct(SendCmpct {
+                                version: 2,
+                                send_compact,
+                            });
+                            let _ = connection
+                                .send(&("sendcmpct".to_string(), encode::serialize(&sendcmpct)));
+                        }
+
+                        self.inner.connections.push(connection);
+                    }
+                    non_probe_action_count += 1;
+                }
+                CompiledAction::SendRawMessage(from, command, message) => {
+                    if self.inner.connections.is_empty() {
+                        return;
+                    }
+
+                    if cfg!(feature = "force_send_and_ping") {
+                        let recording = self.recording_received_messages;
+                        if let Ok((dst, received)) =
+                            self.inner
+                                .connections
+                                .with_connection(from, |dst, connection| {
+                                    connection
+                                        .send_and_recv(&(command, message), recording)
+                                        .map(|received| (dst, received))
+                                })
+                        {
+                            self.probe_results.extend(
+                                received
+                                    .into_iter()
+                                    .filter(message_filter)
+                                    .map(|(s, v)| (dst, s, v))
+                                    .map(probe_result_mapper(
+                                        non_probe_action_count,
+                                        &program.metadata,
+                                    )),
+                            );
+                        }
+                    } else {
+                        let _ = self.inner.connections.send(from, &(command, message));
+                    }
+                    non_probe_action_count += 1;
+                }
+                CompiledAction::SendRawFrame(from, frame) => {
+                    if self.inner.connections.is_empty() {
+                        return;
+                    }
+
+                    let _ = self
+                        .inner
+                        .connections
+                        .with_connection(from, |_, connection| connection.send_raw(&frame));
+                    non_probe_action_count += 1;
+                }
+                CompiledAction::SendDribbleMessage(from, command, message, chunk_size) => {
+                    if self.inner.connections.is_empty() {
+                        return;
+                    }
+
+                    // Ping the other connections in between chunks, to make sure the target
+                    // processed each chunk before the next one arrives
+                    let _ = self.inner.connections.with_connection_and_others(
+                        from,
+                        |connection, others| {
+                            connection.send_dribble(&(command, message), chunk_size, || {
+                                for other in others.iter_mut() {
+                                    other.ping()?;
+                                }
+                                Ok(())
+                            })
+                        },
+                    );
+                    non_probe_action_count += 1;
+                }
+                CompiledAction::Probe => {
+                    log::info!("Enable recording for connection");
+                    self.recording_received_messages = true;
+                }
+                CompiledAction::SetTime(time) => {
+                    let _ = self.inner.target.set_mocktime(time);
+                    #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+                    let _ = self.second.set_mocktime(time);
+                    non_probe_action_count += 1;
+
+                    self.futurest = std::cmp::max(self.futurest, time);
+                }
+                CompiledAction::RestartNode(node) => {
+                    // The program context only exposes the node under test (node 0)
+                    if node == 0 {
+                        // Connections to the node are closed by the restart, a failed restart
+                        // is caught by the liveness check at the end of the test case
+                        if let Err(e) = self.inner.target.restart() {
+                            log::warn!("Failed to restart node: {e}");
+                        }
+                    } else {
+                        log::warn!("Failed to restart node {node}: unknown node");
+                    }
+                    non_probe_action_count += 1;
+                }
+            }
+        }
+        self.send_batch(batch);
+    }
+
+    fn send_batch(&mut self, batch: Option<(usize, Vec<(String, Vec<u8>)>)>) {
+        if let Some((from, messages)) = batch {
+            self.publish_crash_context();
+            let _ = self
+                .inner
+                .connections
+                .with_connection(from, |_, connection| connection.send_batch(&messages));
+        }
+    }
+
+    /// Publish the crash context before executing an action, such that crashes of the target
+    /// caught by the crash handler carry it as well. Only enabled with the `crash_context` feature,
+    /// as it costs a file write per action.
+    ///
+    /// The published context has no characterizations, which would cost RPCs to the target per
+    /// action. They are only captured for crashes reported through `crash_context`.
+    fn publish_crash_context(&self) {
+        if !cfg!(feature = "crash_context") {
+            return;
+        }
+        let context = CrashContext {
+            recent_sent: self.inner.connections.recent_sent(),
+            instruction_index: self.instruction_index,
+            characterizations: Vec::new(),
+        };
+        if let Err(e) = context.publish() {
+            log::warn!("{e}");
+        }
+    }
+
+    fn print_received(&mut self) {
+        #[cfg(feature = "nyx")]
+        if !self.probe_results.is_empty()
+            && let Ok(bytes) = postcard::to_allocvec(&self.probe_results)
+        {
+            use base64::prelude::{BASE64_STANDARD, Engine};
+            nyx_print(BASE64_STANDARD.encode(&bytes).as_bytes());
+        }
+        self.probe_results.clear();
+    }
+
+    fn ping_connections(&mut self) {
+        self.inner.connections.ping_all();
+    }
+
+    fn evaluate_oracles(&mut self) -> ScenarioResult {
+        let crash_oracle = CrashOracle::<TX>::default();
+        if let OracleResult::Fail(e) = crash_oracle.evaluate(&mut self.inner.target) {
+            return ScenarioResult::Fail(format!("CRASH: CRASH; {e}",));
+        }
+
+        #[cfg(feature = "oracle_blocktemplate")]
+        {
+            let template_oracle = BlockTemplateOracle::<TX>::default();
+            if let OracleResult::Fail(e) = template_oracle.evaluate(&mut self.inner.target) {
+                return ScenarioResult::Fail(format!("CRASH: BLOCKTEMPLATE; {e}"));
+            }
+        }
+
+        #[cfg(feature = "oracle_inflation")]
+        {
+            let inflation_oracle = InflationOracle::<TX>::default();
+            if let OracleResult::Fail(e) = inflation_oracle.evaluate(&mut self.inner.target) {
+                return ScenarioResult::Fail(format!("CRASH: INFLATION; {e}"));
+            }
+        }
+
+        #[cfg(feature = "oracle_netsplit")]
+        {
+            let net_split_oracle = NetSplitOracle::<TX, TX>::default();
+            if let OracleResult::Fail(e) = net_split_oracle.evaluate(&mut NetSplitContext {
+                primary: &self.inner.target,
+                reference: &self.second,
+            }) {
+                return ScenarioResult::Fail(format!("CRASH: NETSPLIT; {e}"));
+            }
+        }
+
+        #[cfg(feature = "oracle_consensus")]
+        {
+            let consensus_oracle = ConsensusOracle::<TX, TX>::default();
+            if let OracleResult::Fail(e) = consensus_oracle.evaluate(&mut ConsensusContext {
+                primary: &mut self.inner.target,
+                reference: &mut self.second,
+                // Poll every 10 milliseconds and timeout after 60 seconds. This way hang detection
+                // will fĺag consensus bugs as hangs.
+                consensus_timeout: Duration::from_secs(60),
+                poll_interval: Duration::from_millis(10),
+                futurest: self.futurest,
+            }) {
+                return ScenarioResult::Fail(format!("CRASH: CONSENSUS; {e}"));
+            }
+        }
+
+        #[cfg(feature = "oracle_resources")]
+        if let Some(resource_oracle) = &self.resource_oracle
+            && let OracleResult::Fail(e) = resource_oracle.evaluate(&mut self.inner.target)
+        {
+            return ScenarioResult::Fail(format!("CRASH: RESOURCES; {e}"));
+        }
+
+        #[cfg(feature = "oracle_log")]
+        if let OracleResult::Fail(e) = self.log_oracle.evaluate(&mut self.inner.target) {
+            return ScenarioResult::Fail(format!("CRASH: LOG; {e}"));
+        }
+
+        #[cfg(feature = "oracle_zmq")]
+        if let OracleResult::Fail(e) = ZmqOracle.evaluate(&mut self.inner.target) {
+            return ScenarioResult::Fail(format!("CRASH: ZMQ; {e}"));
+        }
+
+        #[cfg(feature = "oracle_latency")]
+        {
+            let result = self.latency_oracle.evaluate(&mut self.inner.connections);
+            // Only batches of the next test case count towards its latency
+            self.inner.connections.reset_max_latency();
+            if let OracleResult::Fail(e) = result {
+                return ScenarioResult::Fail(format!("CRASH: LATENCY; {e}"));
+            }
+        }
+
+        #[cfg(feature = "oracle_assertions")]
+        if let OracleResult::Fail(e) = self.assertion_oracle.evaluate(&mut self.inner.target) {
+            return ScenarioResult::Fail(format!("CRASH: ASSERTION; {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+const NUM_RECENT_BLOCKS: u64 = 10;
+
+pub fn probe_recent_block_hashes<T: HasBlockChainInterface>(
+    target: &T,
+    meta: &CompiledMetadata,
+) -> Option<ProbeResult> {
+    // get current height
+    let mut hashes = Vec::new();
+    let (mut hash, height) = target.get_tip_info()?;
+    for back in 0..NUM_RECENT_BLOCKS {
+        let new_height = height - back;
+        hashes.push((new_height, hash));
+        let block = target.get_block(hash)?;
+        hash = block.header.prev_blockhash;
+    }
+
+    let mut result = Vec::new();
+    for (height, hash) in &hashes {
+        if let Some((header, _, _)) = meta.block_variables(hash)
+            && let Some(inst) = meta.variable_indices().get(header)
+        {
+            result.push(RecentBlock {
+                height: *height,
+                defining_block: (header, *inst),
+            });
+        }
+    }
+    Some(ProbeResult::RecentBlockes { result })
+}
+
+/// State exposed to IR programs through the `FullProgramContext`, on top of the `ProgramContext`
+/// derived from the `GenericScenario` setup
+pub struct IrContext {
+    /// Transaction outputs programs can spend
+    pub txos: Vec<fuzzamoto_ir::Txo>,
+    /// Headers of the blocks above this height are exposed to programs
+    pub min_header_height: u32,
+}
+
+/// `IrExtension` extends the setup of an `IrScenario` with additional snapshot state (e.g. funded
+/// outputs or a synced index), which is exposed to programs through the `IrContext`.
+///
+/// Extensions compose as tuples, e.g. `(Funding, FilterIndex)` applies both setups one after the
+/// other. This way a single binary can combine the state of scenarios that would otherwise each
+/// require their own binary, letting programs interleave their message families.
+pub trait IrExtension<TX: Transport, T: Target<TX>> {
+    /// Node arguments required by the extension, taking precedence over the default ones
+    fn node_args(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Extend the state set up by `inner` and the context exposed to programs
+    fn extend(
+        &self,
+        inner: &mut GenericScenario<TX, T>,
+        context: &mut IrContext,
+    ) -> Result<(), String>;
+}
+
+impl<TX: Transport, T: Target<TX>> IrExtension<TX, T> for () {
+    fn extend(&self, _: &mut GenericScenario<TX, T>, _: &mut IrContext) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<TX, T, A, B> IrExtension<TX, T> for (A, B)
+where
+    TX: Transport,
+    T: Target<TX>,
+    A: IrExtension<TX, T>,
+    B: IrExtension<TX, T>,
+{
+    fn node_args(&self) -> Vec<String> {
+        let mut node_args = self.0.node_args();
+        node_args.extend(self.1.node_args());
+        node_args
+    }
+
+    fn extend(
+        &self,
+        inner: &mut GenericScenario<TX, T>,
+        context: &mut IrContext,
+    ) -> Result<(), String> {
+        self.0.extend(inner, context)?;
+        self.1.extend(inner, context)
+    }
+}
+
+impl<TX, T, E> IrScenario<TX, T, E>
+where
+    TX: Transport,
+    T: Target<TX> + ConnectableTarget + HasBlockChainInterface + GenerateToAddress,
+{
+    /// Create the scenario from an already set up `GenericScenario`, exposing `txos` and `headers`
+    /// to IR programs (e.g. the results of `build_txos` and `build_headers`, or outputs funded by
+    /// the caller).
+    pub fn from_inner(
+        inner: GenericScenario<TX, T>,
+        args: &ScenarioArgs,
+        txos: Vec<fuzzamoto_ir::Txo>,
+        headers: Vec<fuzzamoto_ir::Header>,
+    ) -> Result<Self, String> {
+        let context = Self::build_program_context(&inner);
+        log::info!("IR context: {context:?}");
+
+        Self::dump_context(
+            context,
+            txos,
+            headers,
+            inner.target.capabilities(),
+            inner.setup,
+            inner.target.chain_params().magic,
+        )?;
+
+        #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+        let second = Self::create_and_sync_second_target(args, &inner.target)?;
+
+        let genesis_time = inner.target.chain_params().genesis.header.time;
+
+        // Resource usage after the setup is the baseline for the resource oracle
+        #[cfg(feature = "oracle_resources")]
+        let resource_oracle = inner.target.resource_usage().map(|baseline| {
+            ResourceOracle::new(
+                baseline,
+                ResourceUsage {
+                    rss_bytes: 512 * 1024 * 1024,
+                    open_fds: 256,
+                    threads: 32,
+                    disk_bytes: 256 * 1024 * 1024,
+                },
+            )
+        });
+
+        // Only errors logged by test cases are of interest to the log oracle
+        #[cfg(feature = "oracle_log")]
+        let inner = {
+            let mut inner = inner;
+            inner.target.log_events()?;
+            inner
+        };
+
+        // The slowest message batch of the setup is the baseline for the latency oracle
+        #[cfg(feature = "oracle_latency")]
+        let (inner, latency_oracle) = {
+            let mut inner = inner;
+            inner.connections.ping_all();
+            let baseline = inner.connections.max_latency().max(MIN_LATENCY_BASELINE);
+            inner.connections.reset_max_latency();
+            (inner, LatencyOracle::new(baseline, latency_factor()?))
+        };
+
+        Ok(Self {
+            inner,
+            handshake_opts: args.handshake_opts(),
+            recording_received_messages: false,
+            probe_results: Vec::new(),
+            #[cfg(any(feature = "oracle_netsplit", feature = "oracle_consensus"))]
+            second,
+            #[cfg(feature = "oracle_resources")]
+            resource_oracle,
+            #[cfg(feature = "oracle_log")]
+            log_oracle: LogOracle::default(),
+            #[cfg(feature = "oracle_latency")]
+            latency_oracle,
+            #[cfg(feature = "oracle_assertions")]
+            assertion_oracle: AssertionOracle::from_node_args(&args.node_args),
+            instruction_index: None,
+            futurest: u64::from(genesis_time),
+            extension: PhantomData,
+        })
+    }
+
+    /// Create the scenario with the setup of `GenericScenario` extended by `extension`.
+    pub fn with_extension(args: &ScenarioArgs, extension: &E) -> Result<Self, String>
+    where
+        E: IrExtension<TX, T>,
+    {
+        let mut scenario_args = args.clone();
+        // Node arguments passed by the user take precedence over the ones of the extension
+        let mut node_args = extension.node_args();
+        node_args.append(&mut scenario_args.node_args);
+        scenario_args.node_args = node_args;
+        // The ZMQ oracle needs the target's notifications
+        #[cfg(feature = "oracle_zmq")]
+        {
+            scenario_args.zmq = true;
+        }
+
+        let mut inner = GenericScenario::from_scenario_args(&scenario_args)?;
+        let mut context = IrContext {
+            txos: Self::build_txos(&inner),
+            min_header_height: LATE_BLOCK_HEIGHT_LIMIT,
+        };
+        // Test cases run from the synced chain without the extension's state if the setup stops
+        // here
+        if !snapshot_point("post-ibd") {
+            extension.extend(&mut inner, &mut context)?;
+        }
+
+        let headers = Self::build_headers(&inner, context.min_header_height);
+        Self::from_inner(inner, args, context.txos, headers)
+    }
+}
+
+impl<TX, T, E> Scenario<'_, TestCase> for IrScenario<TX, T, E>
+where
+    TX: Transport,
+    T: Target<TX> + ConnectableTarget + HasBlockChainInterface + GenerateToAddress,
+    E: IrExtension<TX, T> + Default,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        Self::with_extension(args, &E::default())
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        let metadata = testcase.program.metadata.clone();
+        self.process_actions(testcase.program);
+        self.ping_connections();
+
+        if self.recording_received_messages
+            && let Some(ret) = probe_recent_block_hashes(&self.inner.target, &metadata)
+        {
+            self.probe_results.push(ret);
+        }
+
+        self.print_received();
+        self.evaluate_oracles()
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        self.inner.traffic_metrics()
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.resource_usage()
+    }
+
+    fn characterizations(&self) -> Vec<Characterization> {
+        let mut characterizations = self.inner.characterizations();
+        if let Ok(tip) = TipCharacterization::from_target(&self.inner.target) {
+            characterizations.push(Characterization::Tip(tip));
+        }
+        characterizations
+    }
+
+    fn crash_context(&self) -> Option<CrashContext> {
+        Some(CrashContext {
+            recent_sent: self.inner.connections.recent_sent(),
+            instruction_index: self.instruction_index,
+            characterizations: self.characterizations(),
+        })
+    }
+}
@@ -0,0 +1,305 @@
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, TargetNode},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use base64::prelude::*;
+use serde_json::{Map, Number, Value};
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+/// Maximum nesting depth of generated JSON values
+const MAX_DEPTH: usize = 4;
+/// Maximum size of raw request bodies
+const MAX_RAW_BODY: usize = 8 * 1024;
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Arbitrary)]
+enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// Hex string, as used for hashes and serialized objects
+    Hex(Vec<u8>),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn to_value(&self, depth: usize) -> Value {
+        match self {
+            Json::Null => Value::Null,
+            Json::Bool(b) => Value::Bool(*b),
+            Json::Int(n) => Value::from(*n),
+            // Non-finite floats can't be represented in JSON
+            Json::Float(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+            Json::String(s) => Value::String(s.clone()),
+            Json::Hex(bytes) => Value::String(hex::encode(bytes)),
+            Json::Array(_) | Json::Object(_) if depth >= MAX_DEPTH => Value::Null,
+            Json::Array(items) => {
+                Value::Array(items.iter().map(|item| item.to_value(depth + 1)).collect())
+            }
+            Json::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_value(depth + 1)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Arbitrary)]
+enum Params {
+    /// Positional parameters (`"params": [...]`)
+    Positional(Vec<Json>),
+    /// Named parameters (`"params": {...}`), including the `args` key for extra positional ones
+    Named(Vec<(String, Json)>),
+    /// Any other value, which the server has to reject
+    Other(Json),
+    /// No `params` member
+    Missing,
+}
+
+#[derive(Arbitrary)]
+enum Version {
+    /// No `jsonrpc` member (JSON-RPC 1.0 style)
+    Missing,
+    V1,
+    V2,
+    Other(Json),
+}
+
+#[derive(Arbitrary)]
+enum Id {
+    Number(i64),
+    String(String),
+    Null,
+    /// No `id` member, which turns JSON-RPC 2.0 requests into notifications
+    Missing,
+}
+
+#[derive(Arbitrary)]
+struct Call {
+    method: u16,
+    params: Params,
+    version: Version,
+    id: Id,
+}
+
+#[derive(Arbitrary)]
+enum Request {
+    /// A single JSON-RPC request object
+    Single(Call),
+    /// A batch of requests (JSON array)
+    Batch(Vec<Call>),
+    /// A raw request body, e.g. malformed JSON
+    Raw(Vec<u8>),
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    requests: Vec<Request>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let requests = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { requests })
+    }
+}
+
+/// `JsonRpcScenario` tests Bitcoin Core's JSON-RPC server on the level of raw HTTP requests.
+///
+/// Unlike `RpcScenario`, which executes well-formed calls through an RPC client, each testcase is
+/// a series of JSON-RPC requests built from scratch and sent over the cookie-authenticated HTTP
+/// interface: single requests and batches with positional, named or invalid parameters, different
+/// `jsonrpc` versions and ids, as well as raw (possibly malformed) request bodies. This covers the
+/// RPC server's request handling, parameter parsing and the univalue (de)serialization. Methods are
+/// selected by index from the list of RPC names passed as the second argument (the same format as
+/// for `RpcScenario`).
+///
+/// Responses of the server to well-formed JSON requests have to be valid JSON.
+struct JsonRpcScenario {
+    target: BitcoinCoreTarget,
+    authorization: String,
+    available_rpcs: Vec<String>,
+}
+
+impl JsonRpcScenario {
+    fn call_to_value(&self, call: &Call) -> Value {
+        let mut request = Map::new();
+        match &call.version {
+            Version::Missing => {}
+            Version::V1 => {
+                request.insert("jsonrpc".to_string(), "1.0".into());
+            }
+            Version::V2 => {
+                request.insert("jsonrpc".to_string(), "2.0".into());
+            }
+            Version::Other(version) => {
+                request.insert("jsonrpc".to_string(), version.to_value(0));
+            }
+        }
+
+        let method = &self.available_rpcs[usize::from(call.method) % self.available_rpcs.len()];
+        request.insert("method".to_string(), method.as_str().into());
+
+        let params = match &call.params {
+            Params::Positional(params) => Some(Value::Array(
+                params.iter().map(|param| param.to_value(1)).collect(),
+            )),
+            Params::Named(params) => Some(Value::Object(
+                params
+                    .iter()
+                    .map(|(name, param)| (name.clone(), param.to_value(1)))
+                    .collect(),
+            )),
+            Params::Other(params) => Some(params.to_value(0)),
+            Params::Missing => None,
+        };
+        if let Some(params) = params {
+            request.insert("params".to_string(), params);
+        }
+
+        let id = match &call.id {
+            Id::Number(id) => Some(Value::from(*id)),
+            Id::String(id) => Some(Value::String(id.clone())),
+            Id::Null => Some(Value::Null),
+            Id::Missing => None,
+        };
+        if let Some(id) = id {
+            request.insert("id".to_string(), id);
+        }
+
+        Value::Object(request)
+    }
+
+    /// Send `body` to the RPC server, returning the response status and body.
+    fn post(&self, body: &[u8]) -> Result<(u16, Vec<u8>), String> {
+        let mut stream = TcpStream::connect(self.target.node.params.rpc_socket)
+            .map_err(|e| format!("Failed to connect to RPC server: {e}"))?;
+        stream
+            .set_read_timeout(Some(RPC_TIMEOUT))
+            .map_err(|e| format!("Failed to set RPC read timeout: {e}"))?;
+
+        let mut request = format!(
+            "POST / HTTP/1.1\r\n\
+             Host: localhost\r\n\
+             Connection: close\r\n\
+             Authorization: Basic {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n",
+            self.authorization,
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(body);
+        stream
+            .write_all(&request)
+            .map_err(|e| format!("Failed to send RPC request: {e}"))?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|e| format!("Failed to read RPC response: {e}"))?;
+
+        let header_len = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| "Malformed HTTP response".to_string())?;
+        let header = String::from_utf8_lossy(&response[..header_len]);
+        let status = header
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| format!("Malformed HTTP status line: {header}"))?;
+
+        Ok((status, response[header_len + 4..].to_vec()))
+    }
+}
+
+impl Scenario<'_, TestCase> for JsonRpcScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let rpcs_path = args
+            .extra
+            .first()
+            .ok_or("Missing the path to the list of RPCs")?;
+        let rpcs =
+            fs::read_to_string(rpcs_path).map_err(|e| format!("Failed to parse file: {e}"))?;
+
+        // Note that any change in the file may invalidate existing seeds
+        let available_rpcs: Vec<String> = rpcs
+            .lines()
+            .map(str::trim)
+            .filter(|rpc| !rpc.is_empty())
+            .map(str::to_string)
+            .collect();
+        if available_rpcs.is_empty() {
+            return Err("File with the RPC commands is empty".to_string());
+        }
+
+        let cookie = fs::read_to_string(&target.node.params.cookie_file)
+            .map_err(|e| format!("Failed to read RPC cookie: {e}"))?;
+        let authorization = BASE64_STANDARD.encode(cookie.trim());
+
+        Ok(Self {
+            target,
+            authorization,
+            available_rpcs,
+        })
+    }
+
+    fn run(&mut self, input: TestCase) -> ScenarioResult {
+        const MAX_REQUESTS: usize = 64;
+        if input.requests.len() > MAX_REQUESTS {
+            return ScenarioResult::Ok;
+        }
+
+        for request in input.requests {
+            let body = match &request {
+                Request::Single(call) => self.call_to_value(call).to_string().into_bytes(),
+                Request::Batch(calls) => {
+                    Value::Array(calls.iter().map(|call| self.call_to_value(call)).collect())
+                        .to_string()
+                        .into_bytes()
+                }
+                Request::Raw(body) => body[..body.len().min(MAX_RAW_BODY)].to_vec(),
+            };
+
+            let Ok((status, response)) = self.post(&body) else {
+                log::info!("\t-> Error");
+                continue;
+            };
+            log::info!("\t-> {status} {}", String::from_utf8_lossy(&response));
+
+            if !matches!(request, Request::Raw(_))
+                && !response.is_empty()
+                && serde_json::from_slice::<Value>(&response).is_err()
+            {
+                return ScenarioResult::Fail(format!(
+                    "Invalid JSON response (status {status}): {}",
+                    String::from_utf8_lossy(&response)
+                ));
+            }
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+fuzzamoto_main!(JsonRpcScenario, TestCase);
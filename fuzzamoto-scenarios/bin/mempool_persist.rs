@@ -0,0 +1,328 @@
+use fuzzamoto::{
+    connections::{TrafficMetrics, Transport},
+    fuzzamoto_main,
+    oracles::{Oracle, OracleResult, UtxoSetContext, UtxoSetOracle},
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, HasTxOutSetInfo, ResourceUsage, RpcTarget, Target, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, FeeRate, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    absolute, consensus::encode, p2p::message::NetworkMessage, transaction,
+};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+const MEMPOOL_LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Build a transaction spending the selected outputs (coinbases and outputs of previously
+    /// built transactions, so it may conflict with or descend from other transactions) and send it
+    /// to the target
+    SendTx {
+        from: u8,
+        inputs: Vec<u8>,
+        outputs: u8,
+        /// Feerate in sat/vB
+        fee_rate: u8,
+        version: u8,
+        rbf: bool,
+    },
+    /// Submit a transaction through `sendrawtransaction`, which adds it to the unbroadcast set
+    SubmitLocal { tx: u8 },
+    /// Apply a fee delta to a transaction (`prioritisetransaction`)
+    Prioritise { tx: u8, delta: i32 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u32 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `MempoolPersistScenario` tests the persistence of the mempool across restarts (`mempool.dat`).
+///
+/// Testcases populate the mempool with transaction chains, conflicts, TRUC transactions,
+/// prioritised and unbroadcast transactions, after which the target is restarted. The target has
+/// to come back healthy, finish loading `mempool.dat` and must not end up with transactions that
+/// weren't in its mempool before the restart.
+///
+/// Connections don't survive the restart, so this scenario relies on the snapshot being restored
+/// after each testcase.
+struct MempoolPersistScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Spendable `OP_TRUE` outputs, starting with the mature coinbase outputs
+    utxos: Vec<(OutPoint, Amount)>,
+    /// Transactions built by the testcase
+    txs: Vec<Transaction>,
+}
+
+impl<TX: Transport> MempoolPersistScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn build_tx(
+        &mut self,
+        inputs: &[u8],
+        outputs: u8,
+        fee_rate: u8,
+        version: u8,
+        rbf: bool,
+    ) -> Result<Transaction, String> {
+        let mut seen = HashSet::new();
+        let inputs: Vec<(OutPoint, Amount)> = inputs
+            .iter()
+            .map(|input| self.utxos[usize::from(*input) % self.utxos.len()])
+            .filter(|(outpoint, _)| seen.insert(*outpoint))
+            .collect();
+        if inputs.is_empty() {
+            return Err("No inputs".to_string());
+        }
+
+        let mut witness = Witness::new();
+        witness.push(test_utils::op_true_script().as_bytes());
+        let sequence = if rbf {
+            Sequence::ENABLE_RBF_NO_LOCKTIME
+        } else {
+            Sequence::MAX
+        };
+        let spk = ScriptBuf::new_p2wsh(&test_utils::op_true_script().wscript_hash());
+        let mut tx = Transaction {
+            version: transaction::Version(i32::from(version % 3) + 1),
+            lock_time: absolute::LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|(outpoint, _)| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: ScriptBuf::new(),
+                    sequence,
+                    witness: witness.clone(),
+                })
+                .collect(),
+            output: vec![
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: spk,
+                };
+                usize::from(outputs % 8) + 1
+            ],
+        };
+
+        let fee = FeeRate::from_sat_per_vb(u64::from(fee_rate))
+            .and_then(|fee_rate| fee_rate.fee_wu(tx.weight()))
+            .ok_or_else(|| "Invalid fee rate".to_string())?;
+        let num_outputs = u64::try_from(tx.output.len()).map_err(|e| e.to_string())?;
+        let output_value = inputs
+            .iter()
+            .map(|(_, value)| *value)
+            .sum::<Amount>()
+            .checked_sub(fee)
+            .and_then(|value| value.checked_div(num_outputs))
+            .filter(|value| *value > Amount::ZERO)
+            .ok_or_else(|| "Insufficient funds".to_string())?;
+
+        for output in &mut tx.output {
+            output.value = output_value;
+        }
+
+        let txid = tx.compute_txid();
+        for vout in 0..u32::try_from(tx.output.len()).map_err(|e| e.to_string())? {
+            self.utxos.push((OutPoint::new(txid, vout), output_value));
+        }
+        self.txs.push(tx.clone());
+        Ok(tx)
+    }
+
+    fn tx(&self, tx: u8) -> Option<&Transaction> {
+        self.txs.get(usize::from(tx) % self.txs.len().max(1))
+    }
+
+    fn mempool(&self) -> Result<HashSet<Txid>, String> {
+        Ok(self
+            .inner
+            .target
+            .mempool_contents()?
+            .into_iter()
+            .map(|tx| tx.txid)
+            .collect())
+    }
+
+    /// Wait until the target has finished loading `mempool.dat`
+    fn wait_for_mempool_load(&self) -> Result<(), String> {
+        let start = Instant::now();
+        loop {
+            let info = self.inner.target.call_rpc("getmempoolinfo", &[])?;
+            if info["loaded"].as_bool() == Some(true) {
+                return Ok(());
+            }
+
+            if start.elapsed() > MEMPOOL_LOAD_TIMEOUT {
+                return Err(format!("Mempool was not loaded: {info}"));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Restart the target and check the mempool loaded from `mempool.dat` and the UTXO set
+    /// against the ones before the restart
+    fn restart(&mut self) -> Result<(), String> {
+        let before = self.mempool()?;
+        let utxo_set = self.inner.target.tx_out_set_muhash()?;
+        self.inner.target.restart()?;
+        self.wait_for_mempool_load()?;
+
+        let oracle = UtxoSetOracle::<TX>::default();
+        if let OracleResult::Fail(e) = oracle.evaluate(&mut UtxoSetContext {
+            target: &self.inner.target,
+            recorded: &[utxo_set],
+        }) {
+            return Err(e);
+        }
+
+        let after = self.mempool()?;
+
+        log::info!(
+            "Loaded {} of {} mempool transactions after restart",
+            after.len(),
+            before.len()
+        );
+        if let Some(txid) = after.difference(&before).next() {
+            return Err(format!(
+                "Transaction {txid} appeared in the mempool after restart"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for MempoolPersistScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let inner = GenericScenario::from_target(target, &args.handshake_opts())?;
+
+        let tip_height = inner
+            .block_tree
+            .values()
+            .map(|(_, height)| *height)
+            .max()
+            .unwrap_or_default();
+        let mut coinbases: Vec<(u32, (OutPoint, Amount))> = inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height + test_utils::COINBASE_MATURITY <= tip_height)
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (
+                    *height,
+                    (
+                        OutPoint::new(coinbase.compute_txid(), 0),
+                        coinbase.output[0].value,
+                    ),
+                )
+            })
+            .collect();
+        coinbases.sort_by_key(|(height, _)| *height);
+
+        Ok(Self {
+            inner,
+            utxos: coinbases.into_iter().map(|(_, utxo)| utxo).collect(),
+            txs: Vec::new(),
+        })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::SendTx {
+                    from,
+                    inputs,
+                    outputs,
+                    fee_rate,
+                    version,
+                    rbf,
+                } => {
+                    if let Ok(tx) = self.build_tx(&inputs, outputs, fee_rate, version, rbf) {
+                        let _ = self.inner.connections.send(
+                            usize::from(from),
+                            &("tx".to_string(), encode::serialize(&NetworkMessage::Tx(tx))),
+                        );
+                    }
+                }
+                Action::SubmitLocal { tx } => {
+                    if let Some(tx) = self.tx(tx) {
+                        let hex = encode::serialize_hex(tx);
+                        let _ = self
+                            .inner
+                            .target
+                            .call_rpc("sendrawtransaction", &[hex.into()]);
+                    }
+                }
+                Action::Prioritise { tx, delta } => {
+                    if let Some(tx) = self.tx(tx) {
+                        let txid = tx.compute_txid().to_string();
+                        let _ = self.inner.target.call_rpc(
+                            "prioritisetransaction",
+                            &[txid.into(), serde_json::Value::Null, delta.into()],
+                        );
+                    }
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    let _ = self.inner.target.set_mocktime(self.inner.time);
+                }
+            }
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.restart() {
+            return ScenarioResult::Fail(format!("Failed to restart target: {e}"));
+        }
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(MempoolPersistScenario::<ScenarioTransport>, TestCase);
@@ -0,0 +1,269 @@
+use fuzzamoto::{
+    connections::{ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, Target, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, BlockHash, CompactTarget, OutPoint, Transaction,
+    consensus::encode,
+    p2p::{ServiceFlags, address::Address, message::NetworkMessage, message_blockdata::Inventory},
+};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Permissions of the whitebind listeners, one connection per listener is opened during setup
+/// (in addition to the unprivileged connections of the generic setup)
+const PERMISSIONS: &[&str] = &[
+    "forcerelay",
+    "noban",
+    "mempool",
+    "relay",
+    "download",
+    "addr",
+    "bloomfilter",
+    "noban,forcerelay,mempool",
+    "all",
+];
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new inbound connection to the whitebind listener `class` (or to the regular P2P
+    /// port if `class` is out of range)
+    Connect { class: u8 },
+    /// Create a transaction splitting one of the mature coinbase outputs and send it
+    SendTx { from: u8, utxo: u8, outputs: u8 },
+    /// Send a previously created transaction again (e.g. from a `forcerelay` peer)
+    ResendTx { from: u8, tx: u8 },
+    /// Request a previously created transaction
+    GetData { from: u8, tx: u8 },
+    /// Request the mempool contents (BIP 35)
+    Mempool { from: u8 },
+    /// Send a `headers` message that doesn't satisfy its proof of work, which is punished for
+    /// peers without `noban`
+    InvalidHeaders { from: u8 },
+    /// Send an `addr` message with `count` addresses, which is rate limited for peers without
+    /// `addr`
+    Addr { from: u8, count: u16, seed: u32 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `NetPermissionsScenario` tests permission dependent relay and ban logic.
+///
+/// The target is started with a `-whitebind` listener per permission class (`forcerelay`,
+/// `noban`, `mempool`, ...). On top of the unprivileged connections of the generic setup, one
+/// connection is made to each of the listeners. Testcases relay and request transactions, request
+/// the mempool, send addresses and misbehave from connections of different classes, i.e. each
+/// testcase represents a series of different types of actions:
+///
+/// 1. Open a new connection with one of the permission classes
+/// 2. Send a new or previously sent transaction
+/// 3. Request a transaction or the mempool contents
+/// 4. Send invalid headers
+/// 5. Send (many) addresses
+/// 6. Advance the mocktime of the target node
+struct NetPermissionsScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    tip: (BlockHash, u32),
+    /// Mature coinbase outputs
+    utxos: Vec<(OutPoint, Amount)>,
+    /// Transactions created by the testcase
+    txs: Vec<Transaction>,
+    handshake_opts: HandshakeOpts,
+}
+
+impl<TX: Transport> NetPermissionsScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn connect(&mut self, class: Option<usize>) -> Result<(), String> {
+        let target = &mut self.inner.target;
+        target.select_whitebind(class)?;
+        let connection = target.connect(ConnectionType::Inbound);
+        target.select_whitebind(None)?;
+
+        let mut connection = connection?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.inner.time).map_err(|_| "Invalid time".to_string())?,
+            starting_height: i32::try_from(self.tip.1).unwrap_or_default(),
+            ..self.handshake_opts.clone()
+        })?;
+        self.inner.connections.push(connection);
+        Ok(())
+    }
+
+    fn send(&mut self, from: u8, message: &NetworkMessage) -> Result<(), String> {
+        let command = message.cmd().to_string();
+        self.inner
+            .connections
+            .send(usize::from(from), &(command, encode::serialize(message)))
+    }
+
+    fn tx(&self, tx: u8) -> Option<&Transaction> {
+        self.txs.get(usize::from(tx) % self.txs.len().max(1))
+    }
+
+    fn send_tx(&mut self, from: u8, utxo: u8, outputs: u8) -> Result<(), String> {
+        let utxo = self.utxos[usize::from(utxo) % self.utxos.len()];
+        let tx = test_utils::create_fanout_tx(utxo, usize::from(outputs % 8) + 1)?;
+        self.send(from, &NetworkMessage::Tx(tx.clone()))?;
+        self.txs.push(tx);
+        Ok(())
+    }
+
+    fn invalid_headers(&mut self, from: u8) -> Result<(), String> {
+        let (prev_hash, height) = self.tip;
+        let time = u32::try_from(self.inner.time).map_err(|e| e.to_string())? + 1;
+        let mut block = test_utils::mining::mine_block(prev_hash, height + 1, time);
+        block.header.bits = CompactTarget::from_consensus(0x1d00_ffff);
+        self.send(from, &NetworkMessage::Headers(vec![block.header]))
+    }
+
+    fn addr(&mut self, from: u8, count: u16, seed: u32) -> Result<(), String> {
+        let time = u32::try_from(self.inner.time).map_err(|e| e.to_string())?;
+        let addresses = (0..u32::from(count % 1001))
+            .map(|i| {
+                let ip = Ipv4Addr::from(seed.wrapping_add(i.wrapping_mul(0x0101_0101)));
+                let socket = SocketAddr::V4(SocketAddrV4::new(ip, 8333));
+                (time, Address::new(&socket, ServiceFlags::NETWORK))
+            })
+            .collect();
+        self.send(from, &NetworkMessage::Addr(addresses))
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for NetPermissionsScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_path_with_whitebinds(
+            args,
+            &args.chain_params()?,
+            PERMISSIONS,
+            &[],
+        )?;
+        let handshake_opts = args.handshake_opts();
+        let inner = GenericScenario::from_target(target, &handshake_opts)?;
+
+        let tip = inner
+            .block_tree
+            .iter()
+            .max_by_key(|(_, (_, height))| *height)
+            .map(|(hash, (_, height))| (*hash, *height))
+            .ok_or_else(|| "Block tree is empty".to_string())?;
+        let mut utxos: Vec<(u32, (OutPoint, Amount))> = inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height + test_utils::COINBASE_MATURITY <= tip.1)
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (
+                    *height,
+                    (
+                        OutPoint::new(coinbase.compute_txid(), 0),
+                        coinbase.output[0].value,
+                    ),
+                )
+            })
+            .collect();
+        utxos.sort_by_key(|(height, _)| *height);
+
+        let mut scenario = Self {
+            inner,
+            tip,
+            utxos: utxos.into_iter().map(|(_, utxo)| utxo).collect(),
+            txs: Vec::new(),
+            handshake_opts,
+        };
+        for class in 0..PERMISSIONS.len() {
+            scenario.connect(Some(class))?;
+        }
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            let _ = match action {
+                Action::Connect { class } => {
+                    if self.inner.connections.len() >= test_utils::MAX_PEERS {
+                        continue;
+                    }
+                    let class = usize::from(class);
+                    self.connect((class < PERMISSIONS.len()).then_some(class))
+                }
+                Action::SendTx {
+                    from,
+                    utxo,
+                    outputs,
+                } => self.send_tx(from, utxo, outputs),
+                Action::ResendTx { from, tx } => match self.tx(tx).cloned() {
+                    Some(tx) => self.send(from, &NetworkMessage::Tx(tx)),
+                    None => Ok(()),
+                },
+                Action::GetData { from, tx } => match self.tx(tx) {
+                    Some(tx) => {
+                        let inv = Inventory::WTx(tx.compute_wtxid());
+                        self.send(from, &NetworkMessage::GetData(vec![inv]))
+                    }
+                    None => Ok(()),
+                },
+                Action::Mempool { from } => self.send(from, &NetworkMessage::MemPool),
+                Action::InvalidHeaders { from } => self.invalid_headers(from),
+                Action::Addr { from, count, seed } => self.addr(from, count, seed),
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    self.inner.target.set_mocktime(self.inner.time)
+                }
+            };
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(NetPermissionsScenario::<ScenarioTransport>, TestCase);
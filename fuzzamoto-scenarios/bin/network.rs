@@ -0,0 +1,87 @@
+use fuzzamoto::{
+    connections::{TrafficMetrics, Transport},
+    fuzzamoto_main,
+    oracles::{Oracle, OracleResult, PropagationContext, PropagationOracle},
+    scenarios::{
+        Scenario, ScenarioResult,
+        args::ScenarioArgs,
+        characterization::Characterization,
+        crash_context::CrashContext,
+        generic::{GenericScenario, TestCase},
+    },
+    targets::{NodeNetworkTarget, ResourceUsage, Target, TargetNode},
+};
+
+use bitcoin::Txid;
+use std::{collections::HashSet, time::Duration};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// `NetworkScenario` is the generic p2p scenario against a network of nodes (see
+/// `NodeNetworkTarget::from_env`), with the scenario's connections spread over all nodes.
+///
+/// After each test case, blocks and transactions delivered to one node have to propagate to all
+/// nodes connected to it (see `PropagationOracle`), which detects relay stalls and announcement
+/// suppression bugs.
+struct NetworkScenario<TX: Transport> {
+    inner: GenericScenario<TX, NodeNetworkTarget>,
+    /// Transactions that were in a mempool before the current test case
+    known_txids: HashSet<Txid>,
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for NetworkScenario<TX>
+where
+    NodeNetworkTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let inner = GenericScenario::new(args)?;
+        let mut known_txids = HashSet::new();
+        for node in inner.target.nodes() {
+            known_txids.extend(node.mempool_contents()?.into_iter().map(|tx| tx.txid));
+        }
+
+        Ok(Self { inner, known_txids })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        if let ScenarioResult::Fail(e) = self.inner.run(testcase) {
+            return ScenarioResult::Fail(e);
+        }
+
+        let oracle = PropagationOracle;
+        if let OracleResult::Fail(e) = oracle.evaluate(&mut PropagationContext {
+            network: &mut self.inner.target,
+            time: &mut self.inner.time,
+            known_txids: &mut self.known_txids,
+            time_step: 60,
+            max_rounds: 10,
+            poll_interval: Duration::from_millis(50),
+        }) {
+            return ScenarioResult::Fail(e);
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        self.inner.traffic_metrics()
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.resource_usage()
+    }
+
+    fn characterizations(&self) -> Vec<Characterization> {
+        self.inner.characterizations()
+    }
+
+    fn crash_context(&self) -> Option<CrashContext> {
+        self.inner.crash_context()
+    }
+}
+
+fuzzamoto_main!(NetworkScenario::<ScenarioTransport>, TestCase);
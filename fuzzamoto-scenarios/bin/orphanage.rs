@@ -0,0 +1,306 @@
+use fuzzamoto::{
+    connections::{Connection, ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult,
+        args::ScenarioArgs,
+        generic::{FUNDED_COINBASES, GenericScenario, OUTPUTS_PER_COINBASE},
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, Target, TargetNode},
+    test_utils,
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, OutPoint, Transaction,
+    consensus::encode,
+    p2p::{message::NetworkMessage, message_blockdata::Inventory},
+};
+use std::collections::HashSet;
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Number of peers connected during setup
+const INITIAL_PEERS: usize = 4;
+/// Maximum number of request/response rounds when serving a peer's `getdata` requests
+const MAX_SERVE_ROUNDS: usize = 16;
+
+#[derive(Arbitrary)]
+enum Input {
+    /// One of the confirmed outputs created during setup
+    Confirmed(u16),
+    /// One of the outputs of the transactions constructed by the testcase
+    Unconfirmed(u16),
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Construct a new transaction spending the given outputs (without sending it). Spending
+    /// unconfirmed outputs creates parent/child relations, which turn into orphans if children
+    /// are delivered first.
+    CreateTx { inputs: Vec<Input>, num_outputs: u8 },
+    /// Send a `tx` message for a previously constructed transaction
+    SendTx { peer: u8, tx: u16 },
+    /// Announce a previously constructed transaction through `inv` (wtxid)
+    AnnounceTx { peer: u8, tx: u16 },
+    /// Answer the peer's pending `getdata` requests (e.g. for the parents of orphans), either with
+    /// the requested transactions or with `notfound`
+    Serve { peer: u8, notfound: bool },
+    /// Open a new connection
+    Connect,
+    /// Close one of the connections, which erases its orphans from the orphanage
+    Disconnect { peer: u8 },
+    /// Advance the mocktime of the target node (e.g. to expire orphans)
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `OrphanageScenario` tests orphan transaction handling (`TxOrphanage`) of Bitcoin Core.
+///
+/// During setup, a block splitting mature coinbase outputs into many confirmed outputs is mined,
+/// such that testcases can construct large numbers of independent transaction families. Actions
+/// focus exclusively on the creation of orphans, the order in which their parents are delivered
+/// and peer disconnects, i.e. each testcase represents a series of different types of actions:
+///
+/// 1. Construct a transaction spending confirmed or unconfirmed outputs
+/// 2. Send or announce a transaction from one of the peers
+/// 3. Answer (or refuse) the target's requests for the parents of orphans
+/// 4. Connect new peers or disconnect existing ones
+/// 5. Advance the mocktime of the target node
+struct OrphanageScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Connections that orphans are sent from, separate from the ones of the generic setup such
+    /// that they can be disconnected
+    peers: Vec<Connection<TX>>,
+    /// Confirmed outputs created during setup
+    confirmed: Vec<(OutPoint, Amount)>,
+    /// Outputs of the transactions constructed by the testcase
+    unconfirmed: Vec<(OutPoint, Amount)>,
+    txs: Vec<Transaction>,
+    handshake_opts: HandshakeOpts,
+}
+
+impl<TX: Transport> OrphanageScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn connect(&mut self) -> Result<(), String> {
+        let mut connection = self.inner.target.connect(ConnectionType::Inbound)?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.inner.time).map_err(|_| "Invalid time".to_string())?,
+            ..self.handshake_opts.clone()
+        })?;
+        // Requests for the parents of orphans are collected from the captured messages
+        connection.enable_capture();
+        self.peers.push(connection);
+        Ok(())
+    }
+
+    fn peer(&mut self, peer: u8) -> Option<&mut Connection<TX>> {
+        let len = self.peers.len();
+        self.peers.get_mut(usize::from(peer) % len.max(1))
+    }
+
+    fn get_tx(&self, tx: u16) -> Option<&Transaction> {
+        self.txs.get(usize::from(tx) % self.txs.len().max(1))
+    }
+
+    fn create_tx(&mut self, inputs: &[Input], num_outputs: u8) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        let inputs: Vec<(OutPoint, Amount)> = inputs
+            .iter()
+            .filter_map(|input| match input {
+                Input::Confirmed(index) => self
+                    .confirmed
+                    .get(usize::from(*index) % self.confirmed.len().max(1)),
+                Input::Unconfirmed(index) => self
+                    .unconfirmed
+                    .get(usize::from(*index) % self.unconfirmed.len().max(1)),
+            })
+            .copied()
+            .filter(|(outpoint, _)| seen.insert(*outpoint))
+            .collect();
+
+        let tx = match inputs.as_slice() {
+            [] => return Err("No inputs".to_string()),
+            [input] => test_utils::create_fanout_tx(*input, usize::from(num_outputs % 8) + 1)?,
+            inputs => test_utils::create_consolidation_tx(inputs)?,
+        };
+
+        let txid = tx.compute_txid();
+        self.unconfirmed.extend(
+            (0u32..)
+                .zip(&tx.output)
+                .map(|(vout, output)| (OutPoint::new(txid, vout), output.value)),
+        );
+        self.txs.push(tx);
+        Ok(())
+    }
+
+    fn send(&mut self, peer: u8, message: &NetworkMessage) -> Result<(), String> {
+        let command = message.cmd().to_string();
+        let connection = self
+            .peer(peer)
+            .ok_or_else(|| "No connected peers".to_string())?;
+        connection.send(&(command, encode::serialize(message)))
+    }
+
+    /// Answer the `getdata` requests of the target on `peer` until it stops requesting
+    /// transactions
+    fn serve(&mut self, peer: u8, notfound: bool) -> Result<(), String> {
+        for _ in 0..MAX_SERVE_ROUNDS {
+            let connection = self
+                .peer(peer)
+                .ok_or_else(|| "No connected peers".to_string())?;
+            connection.ping()?;
+            let requests = connection.take_captured();
+
+            let mut found = Vec::new();
+            let mut missing = Vec::new();
+            for (command, payload) in requests {
+                if command != "getdata" {
+                    continue;
+                }
+                let Ok(inventory) = encode::deserialize::<Vec<Inventory>>(&payload) else {
+                    continue;
+                };
+                for inv in inventory {
+                    let tx = self.txs.iter().find(|tx| match inv {
+                        Inventory::Transaction(txid) | Inventory::WitnessTransaction(txid) => {
+                            tx.compute_txid() == txid
+                        }
+                        Inventory::WTx(wtxid) => tx.compute_wtxid() == wtxid,
+                        _ => false,
+                    });
+                    match tx {
+                        Some(tx) if !notfound => found.push(NetworkMessage::Tx(tx.clone())),
+                        _ => missing.push(inv),
+                    }
+                }
+            }
+
+            if found.is_empty() && missing.is_empty() {
+                break;
+            }
+            for message in &found {
+                self.send(peer, message)?;
+            }
+            if !missing.is_empty() {
+                self.send(peer, &NetworkMessage::NotFound(missing))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for OrphanageScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let handshake_opts = args.handshake_opts();
+        let mut inner = GenericScenario::from_target(target, &handshake_opts)?;
+        let confirmed = inner.fund(FUNDED_COINBASES, OUTPUTS_PER_COINBASE)?;
+
+        let mut scenario = Self {
+            inner,
+            peers: Vec::new(),
+            confirmed,
+            unconfirmed: Vec::new(),
+            txs: Vec::new(),
+            handshake_opts,
+        };
+        for _ in 0..INITIAL_PEERS {
+            scenario.connect()?;
+        }
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 256;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            let _ = match action {
+                Action::CreateTx {
+                    inputs,
+                    num_outputs,
+                } => self.create_tx(&inputs, num_outputs),
+                Action::SendTx { peer, tx } => match self.get_tx(tx).cloned() {
+                    Some(tx) => self.send(peer, &NetworkMessage::Tx(tx)),
+                    None => Ok(()),
+                },
+                Action::AnnounceTx { peer, tx } => match self.get_tx(tx) {
+                    Some(tx) => {
+                        let inv = Inventory::WTx(tx.compute_wtxid());
+                        self.send(peer, &NetworkMessage::Inv(vec![inv]))
+                    }
+                    None => Ok(()),
+                },
+                Action::Serve { peer, notfound } => self.serve(peer, notfound),
+                Action::Connect => {
+                    if self.peers.len() >= test_utils::MAX_PEERS {
+                        continue;
+                    }
+                    self.connect()
+                }
+                Action::Disconnect { peer } => {
+                    if !self.peers.is_empty() {
+                        let index = usize::from(peer) % self.peers.len();
+                        drop(self.peers.swap_remove(index));
+                    }
+                    Ok(())
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    self.inner.target.set_mocktime(self.inner.time)
+                }
+            };
+        }
+
+        for peer in &mut self.peers {
+            let _ = peer.ping();
+        }
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        let mut metrics = self.inner.connections.metrics();
+        for peer in &self.peers {
+            metrics.merge(peer.metrics());
+        }
+        Some(metrics)
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(OrphanageScenario::<ScenarioTransport>, TestCase);
@@ -0,0 +1,244 @@
+use fuzzamoto::{
+    connections::{ConnectionPool, ConnectionType, HandshakeOpts, V1Transport},
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, RpcTarget, Target, TargetNode},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    BlockHash,
+    consensus::encode,
+    hashes::Hash,
+    p2p::{
+        ServiceFlags,
+        message::NetworkMessage,
+        message_blockdata::{GetHeadersMessage, Inventory},
+        message_filter::GetCFilters,
+    },
+};
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const ADDRESS_BCRT1_P2WSH_OP_TRUE: &str =
+    "bcrt1qft5p2uhsdcdc3l2ua4ap5qqfg4pjaqlp250x7us7a8qqhrxrxfsqseac85";
+
+/// Number of blocks mined during setup, enough to fill a couple of (`-fastprune`) block files
+/// beyond the 288 blocks that are never pruned.
+const NUM_BLOCKS: u64 = 1000;
+const MIN_BLOCKS_TO_KEEP: u64 = 288;
+
+#[derive(Arbitrary, Clone, Copy)]
+enum BlockRequest {
+    Block,
+    WitnessBlock,
+    CompactBlock,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new inbound connection, optionally advertising `NODE_NETWORK_LIMITED` only
+    Connect { limited: bool },
+    /// Request one of the blocks with `getdata`
+    GetData {
+        from: u8,
+        block: u16,
+        request: BlockRequest,
+    },
+    /// Send `getheaders` with a locator made of the given blocks
+    GetHeaders {
+        from: u8,
+        locator: Vec<u16>,
+        stop: Option<u16>,
+    },
+    /// Request basic block filters from `start_height` up to the `stop` block
+    GetCFilters {
+        from: u8,
+        start_height: u16,
+        stop: u16,
+    },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `PrunedScenario` tests a pruned node serving blocks to its peers.
+///
+/// The scenario setup mines enough blocks to prune the oldest block files and connects a couple
+/// of peers. Testcases request blocks (pruned or not), headers and block filters, exercising the
+/// `NODE_NETWORK_LIMITED` serving limits and the handling of requests for pruned blocks.
+struct PrunedScenario {
+    target: BitcoinCoreTarget,
+    connections: ConnectionPool<V1Transport>,
+    /// Hashes of all blocks, indexed by height
+    block_hashes: Vec<BlockHash>,
+    handshake_opts: HandshakeOpts,
+}
+
+impl PrunedScenario {
+    fn block(&self, index: u16) -> BlockHash {
+        self.block_hashes[usize::from(index) % self.block_hashes.len()]
+    }
+
+    fn handshake_opts(&self, limited: bool) -> Result<HandshakeOpts, String> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get the current time: {e}"))?
+            .as_secs();
+
+        let services = if limited {
+            ServiceFlags::NETWORK_LIMITED | ServiceFlags::WITNESS
+        } else {
+            ServiceFlags::NETWORK | ServiceFlags::WITNESS
+        };
+
+        Ok(HandshakeOpts {
+            time: i64::try_from(time).map_err(|_| "Invalid time".to_string())?,
+            services,
+            ..self.handshake_opts.clone()
+        })
+    }
+
+    fn connect(&mut self, limited: bool) -> Result<(), String> {
+        let mut connection = self.target.connect(ConnectionType::Inbound)?;
+        connection.version_handshake(self.handshake_opts(limited)?)?;
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    fn send(&mut self, from: u8, message: &NetworkMessage) {
+        let command = message.cmd().to_string();
+        let _ = self
+            .connections
+            .send(usize::from(from), &(command, encode::serialize(message)));
+    }
+}
+
+impl Scenario<'_, TestCase> for PrunedScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        const BATCH_SIZE: u64 = 100;
+
+        // Blocks are mined to a regtest address and block files are only kept small on regtest
+        let chain_params = args.chain_params()?;
+        chain_params.require_regtest()?;
+        let target = BitcoinCoreTarget::from_path_pruned(args, &chain_params, &[])?;
+
+        let genesis = target.call_rpc("getblockhash", &[0.into()])?;
+        let mut hashes: Vec<String> = vec![
+            serde_json::from_value(genesis)
+                .map_err(|e| format!("Failed to parse genesis hash: {e}"))?,
+        ];
+        for _ in 0..NUM_BLOCKS / BATCH_SIZE {
+            let batch = target.call_rpc(
+                "generatetoaddress",
+                &[BATCH_SIZE.into(), ADDRESS_BCRT1_P2WSH_OP_TRUE.into()],
+            )?;
+            let batch: Vec<String> = serde_json::from_value(batch)
+                .map_err(|e| format!("Failed to parse generated block hashes: {e}"))?;
+            hashes.extend(batch);
+        }
+        let block_hashes = hashes
+            .iter()
+            .map(|hash| BlockHash::from_str(hash).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pruned = target.prune_blockchain(NUM_BLOCKS - MIN_BLOCKS_TO_KEEP)?;
+        let Some(pruned) = pruned else {
+            return Err("No blocks were pruned".to_string());
+        };
+        log::info!("Pruned blocks up to height {pruned}");
+
+        let mut scenario = Self {
+            target,
+            connections: ConnectionPool::new(),
+            block_hashes,
+            handshake_opts: args.handshake_opts(),
+        };
+        for limited in [false, false, true, true] {
+            scenario.connect(limited)?;
+        }
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, input: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 256;
+        if input.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in input.actions {
+            match action {
+                Action::Connect { limited } => {
+                    let _ = self.connect(limited);
+                }
+                Action::GetData {
+                    from,
+                    block,
+                    request,
+                } => {
+                    let hash = self.block(block);
+                    let inventory = match request {
+                        BlockRequest::Block => Inventory::Block(hash),
+                        BlockRequest::WitnessBlock => Inventory::WitnessBlock(hash),
+                        BlockRequest::CompactBlock => Inventory::CompactBlock(hash),
+                    };
+                    self.send(from, &NetworkMessage::GetData(vec![inventory]));
+                }
+                Action::GetHeaders {
+                    from,
+                    locator,
+                    stop,
+                } => {
+                    let locator_hashes = locator.iter().map(|block| self.block(*block)).collect();
+                    let stop_hash = stop.map_or(BlockHash::all_zeros(), |block| self.block(block));
+                    self.send(
+                        from,
+                        &NetworkMessage::GetHeaders(GetHeadersMessage {
+                            version: 70016,
+                            locator_hashes,
+                            stop_hash,
+                        }),
+                    );
+                }
+                Action::GetCFilters {
+                    from,
+                    start_height,
+                    stop,
+                } => {
+                    let stop_hash = self.block(stop);
+                    self.send(
+                        from,
+                        &NetworkMessage::GetCFilters(GetCFilters {
+                            filter_type: 0,
+                            start_height: u32::from(start_height),
+                            stop_hash,
+                        }),
+                    );
+                }
+            }
+        }
+
+        self.connections.ping_all();
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+fuzzamoto_main!(PrunedScenario, TestCase);
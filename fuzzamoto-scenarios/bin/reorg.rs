@@ -0,0 +1,491 @@
+use fuzzamoto::{
+    connections::{Connection, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    oracles::{
+        ChainTipContext, ChainTipOracle, Oracle, OracleResult, UtxoSetContext, UtxoSetOracle,
+    },
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{
+        BitcoinCoreTarget, HasTipInfo, HasTxOutSetInfo, ResourceUsage, Target, TargetNode,
+        bitcoin_core::TxOutSetMuHash,
+    },
+    test_utils::{self, mining},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Amount, Block, BlockHash, OutPoint, Transaction,
+    consensus::encode,
+    p2p::{
+        message::NetworkMessage,
+        message_blockdata::{GetHeadersMessage, Inventory},
+    },
+};
+use std::collections::{HashMap, HashSet};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Forks never branch off below this height, which bounds the depth of reorgs of the setup chain
+const MIN_FORK_HEIGHT: u32 = 100;
+/// Maximum number of blocks mined per `Extend` action
+const MAX_EXTEND: u8 = 16;
+/// Maximum number of ping roundtrips spent serving requests after an announcement
+const MAX_SERVE_ROUNDS: usize = 128;
+
+#[derive(Arbitrary, Clone, Copy)]
+enum Announcement {
+    /// Send the headers leading up to the tip, after which the target requests the blocks
+    Headers,
+    /// Send an `inv` for the tip, after which the target requests the headers (and blocks)
+    Inv,
+    /// Send the tip block unrequested
+    Block,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Move a peer's fork tip to the block `depth` blocks below the tip of another fork
+    Fork { peer: u8, chain: u8, depth: u8 },
+    /// Mine blocks on top of a peer's fork tip, confirming the selected transactions in the first
+    /// one
+    Extend { peer: u8, blocks: u8, txs: Vec<u8> },
+    /// Announce a peer's fork tip and serve the resulting requests
+    Announce { peer: u8, via: Announcement },
+    /// Create a transaction spending one of the coinbase outputs and send it to the target
+    SendTx { from: u8, utxo: u8, outputs: u8 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `ReorgScenario` tests chain selection and reorgs with competing forks announced by multiple
+/// peers.
+///
+/// Every connection of the generic setup maintains its own fork, initially pointing at the tip of
+/// the setup chain (fork blocks are added to the generic scenario's block tree). Testcases move
+/// forks onto (ancestors of) other forks, extend them with blocks that may confirm transactions
+/// previously sent to the target's mempool and announce them through `headers`, `inv` or
+/// unrequested `block` messages. Requests for headers and blocks are served
+/// from the announcing peer, such that the target reorgs to whichever chain has the most work and
+/// has to move transactions between its mempool and the active chain. Each testcase represents a
+/// series of different types of actions:
+///
+/// 1. Move a peer's fork onto another fork (or the setup chain)
+/// 2. Extend a peer's fork
+/// 3. Announce a peer's fork tip
+/// 4. Send a transaction spending a coinbase output
+/// 5. Advance the mocktime of the target node
+///
+/// After each announcement, the target's UTXO set has to match the one recorded when it was
+/// last on the same tip (see `UtxoSetOracle`), and its tip has to be a valid block that is not
+/// lower than any tip it was on before (see `ChainTipOracle`). Fork blocks are valid if their
+/// parent is and they only confirm transactions spending mature coinbase outputs that are unspent
+/// on their fork.
+struct ReorgScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Fork tip per connection
+    tips: Vec<BlockHash>,
+    /// Coinbase outputs mature at the tip of the setup chain (deep reorgs make spends of some of
+    /// them immature again)
+    utxos: Vec<(OutPoint, Amount)>,
+    /// Transactions created by the current testcase
+    txs: Vec<Transaction>,
+    /// UTXO sets of the tips the target was on
+    utxo_sets: Vec<TxOutSetMuHash>,
+    /// Heights of the coinbase outputs of the setup chain
+    coinbase_heights: HashMap<OutPoint, u32>,
+    /// Valid blocks of the block tree
+    valid: HashSet<BlockHash>,
+    /// Height of the best tip the target was on
+    best_height: u64,
+}
+
+impl<TX: Transport> ReorgScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn peer(&self, peer: u8) -> usize {
+        usize::from(peer) % self.tips.len()
+    }
+
+    /// Blocks leading up to (and including) `tip`, oldest first, limited to the last `max` blocks
+    fn path(&self, tip: BlockHash, max: usize) -> Vec<&Block> {
+        let mut path = Vec::new();
+        let mut hash = tip;
+        while path.len() < max
+            && let Some((block, _)) = self.inner.block_tree.get(&hash)
+        {
+            path.push(block);
+            hash = block.header.prev_blockhash;
+        }
+        path.reverse();
+        path
+    }
+
+    fn fork(&mut self, peer: u8, chain: u8, depth: u8) {
+        let mut hash = self.tips[usize::from(chain) % self.tips.len()];
+        for _ in 0..depth {
+            let Some((block, height)) = self.inner.block_tree.get(&hash) else {
+                break;
+            };
+            if *height <= MIN_FORK_HEIGHT {
+                break;
+            }
+            hash = block.header.prev_blockhash;
+        }
+        let peer = self.peer(peer);
+        self.tips[peer] = hash;
+    }
+
+    fn extend(&mut self, peer: u8, blocks: u8, txs: &[u8]) -> Result<(), String> {
+        let peer = self.peer(peer);
+
+        let mut seen = HashSet::new();
+        let mut confirm: Vec<Transaction> = txs
+            .iter()
+            .filter_map(|index| {
+                self.txs
+                    .get(usize::from(*index) % self.txs.len().max(1))
+                    .cloned()
+            })
+            .filter(|tx| seen.insert(tx.compute_txid()))
+            .collect();
+
+        for i in 0..blocks % MAX_EXTEND + 1 {
+            let prev_hash = self.tips[peer];
+            let (prev_time, height) = self
+                .inner
+                .block_tree
+                .get(&prev_hash)
+                .map(|(block, height)| (block.header.time, *height))
+                .ok_or_else(|| "Unknown fork tip".to_string())?;
+
+            // Offset the time of the first block by the peer index, such that forks of different
+            // peers branching off the same block don't end up identical
+            let offset = if i == 0 { peer } else { 0 };
+            let time = prev_time + 1 + u32::try_from(offset).unwrap_or_default();
+            let valid = self.valid.contains(&prev_hash)
+                && self.confirmable(prev_hash, height + 1, &confirm);
+            let mut block = mining::mine_block(prev_hash, height + 1, time);
+            block.txdata.append(&mut confirm);
+            mining::fixup_commitments(&mut block);
+            mining::fixup_proof_of_work(&mut block);
+
+            let hash = block.block_hash();
+            if valid {
+                self.valid.insert(hash);
+            }
+            self.inner.block_tree.insert(hash, (block, height + 1));
+            self.tips[peer] = hash;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `txs` can be confirmed in a block at `height` on top of `prev_hash`, i.e. whether
+    /// they only spend mature coinbase outputs that are unspent on that fork
+    fn confirmable(&self, prev_hash: BlockHash, height: u32, txs: &[Transaction]) -> bool {
+        let mut spent: HashSet<OutPoint> = self
+            .path(prev_hash, usize::MAX)
+            .iter()
+            .flat_map(|block| block.txdata.iter().skip(1))
+            .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+            .collect();
+
+        txs.iter().flat_map(|tx| &tx.input).all(|input| {
+            self.coinbase_heights
+                .get(&input.previous_output)
+                .is_some_and(|coinbase_height| {
+                    coinbase_height + test_utils::COINBASE_MATURITY <= height
+                })
+                && spent.insert(input.previous_output)
+        })
+    }
+
+    fn send(&mut self, peer: usize, message: &NetworkMessage) -> Result<(), String> {
+        let command = message.cmd().to_string();
+        self.inner
+            .connections
+            .send(peer, &(command, encode::serialize(message)))
+    }
+
+    fn announce(&mut self, peer: u8, via: Announcement) -> Result<(), String> {
+        let peer = self.peer(peer);
+        let tip = self.tips[peer];
+        let message = match via {
+            Announcement::Headers => NetworkMessage::Headers(
+                self.path(tip, test_utils::MAX_HEADERS_RESULTS)
+                    .iter()
+                    .map(|block| block.header)
+                    .collect(),
+            ),
+            Announcement::Inv => NetworkMessage::Inv(vec![Inventory::Block(tip)]),
+            Announcement::Block => {
+                let (block, _) = self
+                    .inner
+                    .block_tree
+                    .get(&tip)
+                    .ok_or_else(|| "Unknown fork tip".to_string())?;
+                NetworkMessage::Block(block.clone())
+            }
+        };
+        self.send(peer, &message)?;
+        self.serve(peer)
+    }
+
+    /// Answer the `getheaders` and `getdata` requests of the target on `peer`'s connection
+    /// (with headers along the peer's fork and any known block) until no more requests arrive.
+    fn serve(&mut self, peer: usize) -> Result<(), String> {
+        for _ in 0..MAX_SERVE_ROUNDS {
+            let requests = self
+                .inner
+                .connections
+                .with_connection(peer, |_, connection| {
+                    connection.ping()?;
+                    Ok(connection.take_captured())
+                })?;
+
+            let mut responses = Vec::new();
+            for (command, payload) in requests {
+                match command.as_str() {
+                    "getheaders" => {
+                        let Ok(getheaders) = encode::deserialize::<GetHeadersMessage>(&payload)
+                        else {
+                            continue;
+                        };
+                        responses.push(self.headers_response(peer, &getheaders));
+                    }
+                    "getdata" => {
+                        let Ok(inventory) = encode::deserialize::<Vec<Inventory>>(&payload) else {
+                            continue;
+                        };
+                        responses.extend(inventory.iter().filter_map(|inv| {
+                            match inv {
+                                Inventory::Block(hash) | Inventory::WitnessBlock(hash) => self
+                                    .inner
+                                    .block_tree
+                                    .get(hash)
+                                    .map(|(block, _)| NetworkMessage::Block(block.clone())),
+                                _ => None,
+                            }
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+
+            if responses.is_empty() {
+                break;
+            }
+            for response in &responses {
+                self.send(peer, response)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Headers along `peer`'s fork following the first locator hash found on it
+    fn headers_response(&self, peer: usize, getheaders: &GetHeadersMessage) -> NetworkMessage {
+        let tip = self.tips[peer];
+        let path = self.path(tip, usize::MAX);
+        let start = getheaders
+            .locator_hashes
+            .iter()
+            .find_map(|hash| {
+                path.iter()
+                    .position(|block| block.block_hash() == *hash)
+                    .map(|position| position + 1)
+            })
+            .unwrap_or_default();
+        NetworkMessage::Headers(
+            path[start..]
+                .iter()
+                .take(test_utils::MAX_HEADERS_RESULTS)
+                .map(|block| block.header)
+                .collect(),
+        )
+    }
+
+    /// Check the target's UTXO set against the one recorded at the same tip, or record it if the
+    /// target wasn't on this tip before
+    fn check_utxo_set(&mut self) -> Result<(), String> {
+        let oracle = UtxoSetOracle::<TX>::default();
+        if let OracleResult::Fail(e) = oracle.evaluate(&mut UtxoSetContext {
+            target: &self.inner.target,
+            recorded: &self.utxo_sets,
+        }) {
+            return Err(e);
+        }
+
+        let info = self.inner.target.tx_out_set_muhash()?;
+        if !self
+            .utxo_sets
+            .iter()
+            .any(|recorded| recorded.best_block == info.best_block)
+        {
+            self.utxo_sets.push(info);
+        }
+        Ok(())
+    }
+
+    /// Check that the target's tip is valid and not lower than the best tip it was on before
+    fn check_tip(&mut self) -> Result<(), String> {
+        let oracle = ChainTipOracle::<TX>::default();
+        if let OracleResult::Fail(e) = oracle.evaluate(&mut ChainTipContext {
+            target: &self.inner.target,
+            valid_blocks: &self.valid,
+            min_height: self.best_height,
+        }) {
+            return Err(e);
+        }
+
+        if let Some((_, height)) = self.inner.target.get_tip_info() {
+            self.best_height = self.best_height.max(height);
+        }
+        Ok(())
+    }
+
+    fn send_tx(&mut self, from: u8, utxo: u8, outputs: u8) -> Result<(), String> {
+        let utxo = self.utxos[usize::from(utxo) % self.utxos.len()];
+        let tx = test_utils::create_fanout_tx(utxo, usize::from(outputs % 8) + 1)?;
+        self.send(usize::from(from), &NetworkMessage::Tx(tx.clone()))?;
+        self.txs.push(tx);
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for ReorgScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let mut inner = GenericScenario::from_target(target, &args.handshake_opts())?;
+        // Responses are collected from the messages captured during ping/pong roundtrips
+        inner
+            .connections
+            .iter_alive_mut()
+            .for_each(Connection::enable_capture);
+
+        let (tip, tip_height) = inner
+            .block_tree
+            .iter()
+            .max_by_key(|(_, (_, height))| *height)
+            .map(|(hash, (_, height))| (*hash, *height))
+            .ok_or_else(|| "Block tree is empty".to_string())?;
+
+        let mut utxos: Vec<(u32, (OutPoint, Amount))> = inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height + test_utils::COINBASE_MATURITY <= tip_height)
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (
+                    *height,
+                    (
+                        OutPoint::new(coinbase.compute_txid(), 0),
+                        coinbase.output[0].value,
+                    ),
+                )
+            })
+            .collect();
+        utxos.sort_by_key(|(height, _)| *height);
+
+        let tips = vec![tip; inner.connections.len()];
+        let utxo_sets = vec![inner.target.tx_out_set_muhash()?];
+        let coinbase_heights = inner
+            .block_tree
+            .values()
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (OutPoint::new(coinbase.compute_txid(), 0), *height)
+            })
+            .collect();
+        let valid = inner.block_tree.keys().copied().collect();
+        log::info!(
+            "Created {} forks at height {tip_height} with {} spendable coinbase outputs",
+            tips.len(),
+            utxos.len()
+        );
+
+        Ok(Self {
+            inner,
+            tips,
+            utxos: utxos.into_iter().map(|(_, utxo)| utxo).collect(),
+            txs: Vec::new(),
+            utxo_sets,
+            coinbase_heights,
+            valid,
+            best_height: u64::from(tip_height),
+        })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 64;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::Fork { peer, chain, depth } => self.fork(peer, chain, depth),
+                Action::Extend { peer, blocks, txs } => {
+                    let _ = self.extend(peer, blocks, &txs);
+                }
+                Action::Announce { peer, via } => {
+                    let _ = self.announce(peer, via);
+                    if let Err(e) = self.check_utxo_set().and_then(|()| self.check_tip()) {
+                        return ScenarioResult::Fail(e);
+                    }
+                }
+                Action::SendTx {
+                    from,
+                    utxo,
+                    outputs,
+                } => {
+                    let _ = self.send_tx(from, utxo, outputs);
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    let _ = self.inner.target.set_mocktime(self.inner.time);
+                }
+            }
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(ReorgScenario::<ScenarioTransport>, TestCase);
@@ -0,0 +1,319 @@
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, RestFormat, RpcTarget, TargetNode},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use std::fmt::Write;
+
+const ADDRESS_BCRT1_P2WSH_OP_TRUE: &str =
+    "bcrt1qft5p2uhsdcdc3l2ua4ap5qqfg4pjaqlp250x7us7a8qqhrxrxfsqseac85";
+
+#[derive(Arbitrary, Clone, Copy)]
+enum Format {
+    Binary,
+    Hex,
+    Json,
+    /// Unsupported format extension
+    Unknown,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Binary => RestFormat::Binary.extension(),
+            Format::Hex => RestFormat::Hex.extension(),
+            Format::Json => RestFormat::Json.extension(),
+            Format::Unknown => "txt",
+        }
+    }
+}
+
+#[derive(Arbitrary)]
+enum FilterType {
+    Basic,
+    Unknown,
+}
+
+impl FilterType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FilterType::Basic => "basic",
+            FilterType::Unknown => "extended",
+        }
+    }
+}
+
+#[derive(Arbitrary)]
+enum Endpoint<'a> {
+    Tx {
+        txid: u16,
+        format: Format,
+    },
+    Block {
+        block: u16,
+        notxdetails: bool,
+        format: Format,
+    },
+    Headers {
+        block: u16,
+        count: u16,
+        format: Format,
+    },
+    BlockHashByHeight {
+        height: u32,
+        format: Format,
+    },
+    BlockFilter {
+        filter_type: FilterType,
+        block: u16,
+        format: Format,
+    },
+    BlockFilterHeaders {
+        filter_type: FilterType,
+        block: u16,
+        count: u16,
+        format: Format,
+    },
+    GetUtxos {
+        check_mempool: bool,
+        outpoints: Vec<(u16, u32)>,
+        format: Format,
+    },
+    ChainInfo,
+    MempoolInfo,
+    MempoolContents {
+        verbose: bool,
+        mempool_sequence: bool,
+    },
+    DeploymentInfo {
+        block: Option<u16>,
+    },
+    /// Raw path below `/rest/`
+    Raw {
+        path: &'a [u8],
+    },
+}
+
+#[derive(Arbitrary)]
+struct Request<'a> {
+    endpoint: Endpoint<'a>,
+    query: Option<&'a [u8]>,
+}
+
+#[derive(Arbitrary)]
+struct TestCase<'a> {
+    requests: Vec<Request<'a>>,
+}
+
+impl<'a> ScenarioInput<'a> for TestCase<'a> {
+    fn decode(bytes: &'a [u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let requests = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { requests })
+    }
+}
+
+/// Append `bytes` to `out`, percent-encoding everything that is not URL-like (in particular
+/// whitespace and control characters, which would break the request line).
+fn append_encoded(out: &mut String, bytes: &[u8]) {
+    const MAX_LEN: usize = 128;
+
+    for &b in bytes.iter().take(MAX_LEN) {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'~'
+            | b'/'
+            | b'&'
+            | b'='
+            | b'%'
+            | b'+'
+            | b':' => out.push(b as char),
+            _ => {
+                let _ = write!(out, "%{b:02X}");
+            }
+        }
+    }
+}
+
+/// `RestScenario` tests the REST interface of Bitcoin Core.
+///
+/// The snapshot state contains a short chain, such that the fuzzer can select existing blocks
+/// and transactions by index. Each testcase is a series of REST requests, built from one of
+/// the known endpoints (or a raw path) and an optional fuzzer-controlled query string.
+struct RestScenario {
+    target: BitcoinCoreTarget,
+    block_hashes: Vec<String>,
+    txids: Vec<String>,
+}
+
+impl RestScenario {
+    fn pick(values: &[String], index: u16) -> &str {
+        if values.is_empty() {
+            return "";
+        }
+        &values[index as usize % values.len()]
+    }
+
+    fn build_path(&self, request: &Request<'_>) -> String {
+        let block = |index: u16| Self::pick(&self.block_hashes, index);
+        let txid = |index: u16| Self::pick(&self.txids, index);
+
+        let mut path = match &request.endpoint {
+            Endpoint::Tx {
+                txid: index,
+                format,
+            } => {
+                format!("/rest/tx/{}.{}", txid(*index), format.extension())
+            }
+            Endpoint::Block {
+                block: index,
+                notxdetails,
+                format,
+            } => format!(
+                "/rest/block/{}{}.{}",
+                if *notxdetails { "notxdetails/" } else { "" },
+                block(*index),
+                format.extension()
+            ),
+            Endpoint::Headers {
+                block: index,
+                count,
+                format,
+            } => format!(
+                "/rest/headers/{}.{}?count={count}",
+                block(*index),
+                format.extension()
+            ),
+            Endpoint::BlockHashByHeight { height, format } => {
+                format!("/rest/blockhashbyheight/{height}.{}", format.extension())
+            }
+            Endpoint::BlockFilter {
+                filter_type,
+                block: index,
+                format,
+            } => format!(
+                "/rest/blockfilter/{}/{}.{}",
+                filter_type.as_str(),
+                block(*index),
+                format.extension()
+            ),
+            Endpoint::BlockFilterHeaders {
+                filter_type,
+                block: index,
+                count,
+                format,
+            } => format!(
+                "/rest/blockfilterheaders/{}/{}.{}?count={count}",
+                filter_type.as_str(),
+                block(*index),
+                format.extension()
+            ),
+            Endpoint::GetUtxos {
+                check_mempool,
+                outpoints,
+                format,
+            } => {
+                let mut path = "/rest/getutxos".to_string();
+                if *check_mempool {
+                    path.push_str("/checkmempool");
+                }
+                for (index, vout) in outpoints {
+                    let _ = write!(path, "/{}-{vout}", txid(*index));
+                }
+                let _ = write!(path, ".{}", format.extension());
+                path
+            }
+            Endpoint::ChainInfo => "/rest/chaininfo.json".to_string(),
+            Endpoint::MempoolInfo => "/rest/mempool/info.json".to_string(),
+            Endpoint::MempoolContents {
+                verbose,
+                mempool_sequence,
+            } => format!(
+                "/rest/mempool/contents.json?verbose={verbose}&mempool_sequence={mempool_sequence}"
+            ),
+            Endpoint::DeploymentInfo { block: None } => "/rest/deploymentinfo.json".to_string(),
+            Endpoint::DeploymentInfo { block: Some(index) } => {
+                format!("/rest/deploymentinfo/{}.json", block(*index))
+            }
+            Endpoint::Raw { path: raw } => {
+                let mut path = "/rest/".to_string();
+                append_encoded(&mut path, raw);
+                path
+            }
+        };
+
+        if let Some(query) = request.query {
+            path.push(if path.contains('?') { '&' } else { '?' });
+            append_encoded(&mut path, query);
+        }
+
+        path
+    }
+}
+
+impl<'a> Scenario<'a, TestCase<'a>> for RestScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        const BLOCKS: u64 = 110;
+
+        // Blocks are mined to a regtest address
+        args.chain_params()?.require_regtest()?;
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+
+        let block_hashes: Vec<String> = serde_json::from_value(target.call_rpc(
+            "generatetoaddress",
+            &[BLOCKS.into(), ADDRESS_BCRT1_P2WSH_OP_TRUE.into()],
+        )?)
+        .map_err(|e| format!("Failed to parse generated block hashes: {e}"))?;
+
+        let mut txids = Vec::new();
+        for hash in &block_hashes {
+            let block = target.call_rpc("getblock", &[hash.as_str().into(), 1.into()])?;
+            let block_txids: Vec<String> = serde_json::from_value(block["tx"].clone())
+                .map_err(|e| format!("Failed to parse txids of block {hash}: {e}"))?;
+            txids.extend(block_txids);
+        }
+
+        log::info!(
+            "Generated {} blocks with {} transactions",
+            block_hashes.len(),
+            txids.len()
+        );
+
+        Ok(Self {
+            target,
+            block_hashes,
+            txids,
+        })
+    }
+
+    fn run(&mut self, input: TestCase) -> ScenarioResult {
+        // Each request is sent over a new connection; limit them
+        const MAX_REQUESTS: usize = 64;
+        if input.requests.len() > MAX_REQUESTS {
+            return ScenarioResult::Ok;
+        }
+
+        for request in &input.requests {
+            let path = self.build_path(request);
+            match self.target.rest_request(&path) {
+                Ok(response) => log::debug!("GET {path} -> {}", response.status),
+                Err(e) => log::debug!("GET {path} failed: {e}"),
+            }
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+fuzzamoto_main!(RestScenario, TestCase);
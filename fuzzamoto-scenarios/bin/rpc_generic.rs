@@ -1,7 +1,7 @@
 use fuzzamoto::{
     fuzzamoto_main,
-    scenarios::{Scenario, ScenarioInput, ScenarioResult},
-    targets::{BitcoinCoreTarget, TargetNode},
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, RpcTarget, TargetNode},
 };
 
 use arbitrary::{Arbitrary, Unstructured};
@@ -173,10 +173,14 @@ struct RpcScenario {
 }
 
 impl Scenario<'_, TestCase> for RpcScenario {
-    fn new(args: &[String]) -> Result<Self, String> {
-        let target = BitcoinCoreTarget::from_path(&args[1])?;
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = BitcoinCoreTarget::from_scenario_args(args)?;
+        let rpcs_path = args
+            .extra
+            .first()
+            .ok_or("Missing the path to the list of RPCs")?;
         let rpcs =
-            fs::read_to_string(&args[2]).map_err(|e| format!("Failed to parse file: {e}"))?;
+            fs::read_to_string(rpcs_path).map_err(|e| format!("Failed to parse file: {e}"))?;
 
         // Note that any change in the file may invalidate existing seeds
         let mut available_rpcs: Vec<String> = vec![];
@@ -188,14 +192,11 @@ impl Scenario<'_, TestCase> for RpcScenario {
         }
 
         // Remove the default wallet, so the test may create it
-        let _ = target
-            .node
-            .client
-            .call::<serde_json::Value>("unloadwallet", &["default".into()]);
+        let _ = target.call_rpc("unloadwallet", &["default".into()]);
         let wallet_path = target
             .node
             .workdir()
-            .join("regtest")
+            .join(args.chain_params()?.chain)
             .join("wallets")
             .join("default");
         let _ = std::fs::remove_dir_all(&wallet_path);
@@ -225,12 +226,7 @@ impl Scenario<'_, TestCase> for RpcScenario {
 
             log::info!("{rpc_name} {params:?}");
 
-            let Ok(result) = self
-                .target
-                .node
-                .client
-                .call::<serde_json::Value>(rpc_name, &params)
-            else {
+            let Ok(result) = self.target.call_rpc(rpc_name, &params) else {
                 log::info!("\t-> Error");
                 continue;
             };
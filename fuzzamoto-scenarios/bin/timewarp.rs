@@ -0,0 +1,463 @@
+use fuzzamoto::{
+    chain::ChainParams,
+    connections::{ConnectionPool, ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult,
+        args::ScenarioArgs,
+        characterization::{Characterization, TipCharacterization},
+    },
+    targets::{BitcoinCoreTarget, ResourceUsage, RpcTarget, Target, TargetNode},
+    test_utils::{self, mining},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    CompactTarget, Target as PowTarget, TxMerkleNode, block,
+    consensus::encode,
+    hashes::{Hash, sha256d},
+    p2p::{Magic, message::NetworkMessage},
+};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Number of blocks between difficulty adjustments
+const INTERVAL: u32 = 2016;
+/// Target time between blocks
+const POW_TARGET_SPACING: u32 = 600;
+/// Target time of a difficulty period
+const POW_TARGET_TIMESPAN: u64 = 14 * 24 * 60 * 60;
+/// Height of the header chain created during setup, a few blocks short of the first retarget
+const SETUP_HEIGHT: u32 = INTERVAL - 16;
+/// Maximum time headers may be ahead of the target's (mock)time
+const MAX_FUTURE_BLOCK_TIME: i64 = 2 * 60 * 60;
+
+/// Timestamp of a mined header
+#[derive(Arbitrary, Clone, Copy)]
+enum Time {
+    /// Seconds relative to the parent's timestamp
+    Parent(i32),
+    /// Seconds relative to the median time past of the parent
+    MedianTimePast(i16),
+    /// Seconds relative to the maximum future block time (mocktime plus two hours)
+    Future(i16),
+    /// Seconds relative to the timestamp of the first block of the parent's difficulty period,
+    /// as used in timewarp attacks
+    PeriodStart(i32),
+}
+
+/// Difficulty (`nBits`) of a mined header
+#[derive(Arbitrary, Clone, Copy)]
+enum Bits {
+    /// The difficulty required by the consensus rules
+    Valid,
+    /// The minimum difficulty of the chain
+    PowLimit,
+    /// The required difficulty with the compact encoding off by `delta`
+    Adjacent(i8),
+    /// Arbitrary compact encoding
+    Raw(u32),
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new connection
+    Connect { outbound: bool },
+    /// Mine a header on top of the block `reorg` blocks below the tip, dropping the headers above
+    /// it from the scenario's chain
+    MineHeader { reorg: u8, time: Time, bits: Bits },
+    /// Mine valid headers `spacing` seconds apart, up to the last block before the next retarget
+    MineToBoundary { spacing: u16 },
+    /// Send the last `count` headers of the chain
+    SendHeaders { from: u8, count: u16 },
+    /// Advance the mocktime of the target node
+    AdvanceTime { seconds: u16 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// Chain the target runs on
+struct Chain {
+    params: ChainParams,
+    /// Node argument configuring the chain
+    node_arg: &'static str,
+    /// Whether blocks more than 20 minutes after their parent may use the minimum difficulty
+    allow_min_difficulty_blocks: bool,
+    /// Whether the timewarp mitigation (BIP94) is enforced
+    enforce_bip94: bool,
+}
+
+impl Chain {
+    /// A signet with the challenge `OP_TRUE`, such that blocks don't need to be signed
+    fn signet() -> Self {
+        // The message start of a signet is derived from its challenge
+        let hash = sha256d::Hash::hash(&[0x01, 0x51]);
+        let mut magic = [0u8; 4];
+        magic.copy_from_slice(&hash.as_byte_array()[..4]);
+
+        let signet = ChainParams::signet();
+        Self {
+            params: ChainParams::custom(
+                "signet",
+                Magic::from_bytes(magic),
+                signet.genesis,
+                signet.default_port,
+            ),
+            node_arg: "-signetchallenge=51",
+            allow_min_difficulty_blocks: false,
+            enforce_bip94: false,
+        }
+    }
+
+    fn testnet4() -> Self {
+        Self {
+            params: ChainParams::testnet4(),
+            node_arg: "-minimumchainwork=0x00",
+            allow_min_difficulty_blocks: true,
+            enforce_bip94: true,
+        }
+    }
+
+    fn pow_limit(&self) -> CompactTarget {
+        self.params.genesis.header.bits
+    }
+
+    /// The difficulty required for a header at `time` on top of `chain` (see Bitcoin Core's
+    /// `GetNextWorkRequired`)
+    fn next_bits(&self, chain: &[block::Header], time: u32) -> CompactTarget {
+        let pow_limit = self.pow_limit();
+        let Some(last) = chain.last() else {
+            return pow_limit;
+        };
+        let height = chain.len();
+        let interval = INTERVAL as usize;
+
+        if height % interval != 0 {
+            if !self.allow_min_difficulty_blocks {
+                return last.bits;
+            }
+            if time > last.time.saturating_add(2 * POW_TARGET_SPACING) {
+                return pow_limit;
+            }
+            // Difficulty of the last block not mined under the minimum difficulty exception
+            return chain
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(index, header)| index % interval == 0 || header.bits != pow_limit)
+                .map_or(pow_limit, |(_, header)| header.bits);
+        }
+
+        let first = &chain[height - interval];
+        let timespan = i64::from(last.time) - i64::from(first.time);
+        let bits = if self.enforce_bip94 {
+            first.bits
+        } else {
+            last.bits
+        };
+        retarget(bits, u64::try_from(timespan).unwrap_or(0), pow_limit)
+    }
+}
+
+/// Scale the target of `bits` by `timespan` relative to the target timespan, capped at
+/// `pow_limit` (see Bitcoin Core's `CalculateNextWorkRequired`)
+#[expect(clippy::cast_possible_truncation)]
+fn retarget(bits: CompactTarget, timespan: u64, pow_limit: CompactTarget) -> CompactTarget {
+    let timespan = timespan.clamp(POW_TARGET_TIMESPAN / 4, POW_TARGET_TIMESPAN * 4);
+
+    // Multiply the little endian 64 bit limbs of the target with `timespan`
+    let bytes = PowTarget::from_compact(bits).to_le_bytes();
+    let mut limbs = [0u64; 5];
+    let mut carry = 0u128;
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        let mut le_bytes = [0u8; 8];
+        le_bytes.copy_from_slice(chunk);
+        let product = u128::from(u64::from_le_bytes(le_bytes)) * u128::from(timespan) + carry;
+        *limb = product as u64;
+        carry = product >> 64;
+    }
+    limbs[4] = carry as u64;
+
+    // Divide by the target timespan, starting with the most significant limb
+    let mut remainder = 0u128;
+    for limb in limbs.iter_mut().rev() {
+        let dividend = (remainder << 64) | u128::from(*limb);
+        *limb = (dividend / u128::from(POW_TARGET_TIMESPAN)) as u64;
+        remainder = dividend % u128::from(POW_TARGET_TIMESPAN);
+    }
+    if limbs[4] != 0 {
+        return pow_limit;
+    }
+
+    let mut bytes = [0u8; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(&limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    let target = PowTarget::from_le_bytes(bytes);
+    if target > PowTarget::from_compact(pow_limit) {
+        pow_limit
+    } else {
+        target.to_compact_lossy()
+    }
+}
+
+/// Median of the timestamps of the last 11 headers of `chain`
+fn median_time_past(chain: &[block::Header]) -> u32 {
+    let mut times: Vec<u32> = chain
+        .iter()
+        .rev()
+        .take(11)
+        .map(|header| header.time)
+        .collect();
+    times.sort_unstable();
+    times.get(times.len() / 2).copied().unwrap_or_default()
+}
+
+/// `TimewarpScenario` tests difficulty adjustment, the timewarp mitigation (BIP94) and the
+/// timestamp rules of block headers.
+///
+/// The target runs a chain with real difficulty adjustment: a signet with the challenge `OP_TRUE`
+/// (the default), or testnet4 (with `--chain testnet4`), which additionally enforces BIP94 and
+/// allows minimum difficulty blocks. The setup sends a valid header chain ending a few blocks
+/// before the first retarget boundary. Testcases mine headers with fuzzer-chosen timestamps and
+/// difficulties across retarget boundaries, i.e. each testcase represents a series of different
+/// types of actions:
+///
+/// 1. Open a new connection
+/// 2. Mine a header (or a fork) with a timestamp relative to its parent, the median time past,
+///    the maximum future block time or the start of the difficulty period, and with valid or
+///    invalid difficulty
+/// 3. Mine valid headers up to the next retarget boundary
+/// 4. Send the last headers of the chain
+/// 5. Advance the mocktime of the target node
+///
+/// Note that the headers are mined with the reduced proof of work of fuzzing builds (see
+/// `reduced_pow`), the actual difficulty of the chain is out of reach otherwise.
+struct TimewarpScenario<TX: Transport> {
+    target: BitcoinCoreTarget,
+    connections: ConnectionPool<TX>,
+    chain: Chain,
+    /// Header chain known to the scenario, starting with the genesis block
+    headers: Vec<block::Header>,
+    time: u64,
+    /// Number of headers mined, distinguishing the merkle roots of headers
+    mined: u32,
+    handshake_opts: HandshakeOpts,
+}
+
+impl<TX: Transport> TimewarpScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn connect(&mut self, outbound: bool) -> Result<(), String> {
+        let connection_type = if outbound {
+            ConnectionType::Outbound
+        } else {
+            ConnectionType::Inbound
+        };
+        let mut connection = self.target.connect(connection_type)?;
+        connection.version_handshake(HandshakeOpts {
+            time: i64::try_from(self.time).map_err(|_| "Invalid time".to_string())?,
+            ..self.handshake_opts.clone()
+        })?;
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    fn mine_header(&mut self, time: u32, bits: Option<CompactTarget>) {
+        let bits = bits.unwrap_or_else(|| self.chain.next_bits(&self.headers, time));
+        let prev_hash = self.headers.last().map(block::Header::block_hash).unwrap();
+        let mut header = mining::mine_header(
+            prev_hash,
+            time,
+            TxMerkleNode::hash(&self.mined.to_le_bytes()),
+        );
+        header.bits = bits;
+        mining::fixup_header_proof_of_work(&mut header);
+        self.headers.push(header);
+        self.mined += 1;
+    }
+
+    fn header_time(&self, time: Time) -> u32 {
+        let parent = self.headers.last().map_or(0, |header| header.time);
+        let time = match time {
+            Time::Parent(delta) => i64::from(parent) + i64::from(delta),
+            Time::MedianTimePast(delta) => {
+                i64::from(median_time_past(&self.headers)) + i64::from(delta)
+            }
+            Time::Future(delta) => {
+                i64::try_from(self.time).unwrap_or(i64::MAX)
+                    + MAX_FUTURE_BLOCK_TIME
+                    + i64::from(delta)
+            }
+            Time::PeriodStart(delta) => {
+                let start = (self.headers.len() - 1) / INTERVAL as usize * INTERVAL as usize;
+                i64::from(self.headers[start].time) + i64::from(delta)
+            }
+        };
+        u32::try_from(time.clamp(0, i64::from(u32::MAX))).unwrap_or_default()
+    }
+
+    fn bits(&self, time: u32, bits: Bits) -> CompactTarget {
+        match bits {
+            Bits::Valid => self.chain.next_bits(&self.headers, time),
+            Bits::PowLimit => self.chain.pow_limit(),
+            Bits::Adjacent(delta) => {
+                let valid = self.chain.next_bits(&self.headers, time).to_consensus();
+                CompactTarget::from_consensus(valid.wrapping_add_signed(i32::from(delta)))
+            }
+            Bits::Raw(bits) => CompactTarget::from_consensus(bits),
+        }
+    }
+
+    fn mine(&mut self, reorg: u8, time: Time, bits: Bits) {
+        let height = self.headers.len() - 1;
+        self.headers
+            .truncate(height - usize::from(reorg).min(height) + 1);
+
+        let time = self.header_time(time);
+        let bits = self.bits(time, bits);
+        self.mine_header(time, Some(bits));
+    }
+
+    fn mine_to_boundary(&mut self, spacing: u16) -> Result<(), String> {
+        let spacing = u32::from(spacing).max(1);
+        loop {
+            let time = self.headers.last().map_or(0, |header| header.time) + spacing;
+            self.mine_header(time, None);
+            if self.headers.len() % INTERVAL as usize == 0 {
+                break;
+            }
+        }
+
+        // Keep the mined headers within the maximum future block time
+        let time = u64::from(self.headers.last().map_or(0, |header| header.time));
+        if time > self.time {
+            self.time = time;
+            self.target.set_mocktime(self.time)?;
+        }
+        Ok(())
+    }
+
+    fn send_headers(&mut self, from: u8, count: u16) -> Result<(), String> {
+        let count = usize::from(count).clamp(1, test_utils::MAX_HEADERS_RESULTS);
+        let start = self.headers.len().saturating_sub(count).max(1);
+        let headers = NetworkMessage::Headers(self.headers[start..].to_vec());
+        self.connections.send(
+            usize::from(from),
+            &("headers".to_string(), encode::serialize(&headers)),
+        )
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for TimewarpScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        let chain = match args.chain.as_str() {
+            "testnet4" => Chain::testnet4(),
+            _ => Chain::signet(),
+        };
+        let target =
+            BitcoinCoreTarget::from_path_with_args(args, &chain.params, &[chain.node_arg])?;
+
+        let genesis = chain.params.genesis.header;
+        let mut scenario = Self {
+            target,
+            connections: ConnectionPool::new(),
+            chain,
+            headers: vec![genesis],
+            time: u64::from(genesis.time),
+            mined: 0,
+            handshake_opts: args.handshake_opts(),
+        };
+        for _ in 0..SETUP_HEIGHT {
+            let time = scenario.headers.last().unwrap().time + POW_TARGET_SPACING;
+            scenario.mine_header(time, None);
+        }
+        scenario.time = u64::from(scenario.headers.last().unwrap().time);
+        scenario.target.set_mocktime(scenario.time)?;
+
+        for outbound in [true, true, false, false] {
+            scenario.connect(outbound)?;
+        }
+        scenario.send_headers(0, u16::MAX)?;
+        scenario.connections.ping_all();
+
+        let info = scenario.target.call_rpc("getblockchaininfo", &[])?;
+        if info["headers"].as_u64() != Some(u64::from(SETUP_HEIGHT)) {
+            return Err(format!("Target did not accept the setup headers: {info}"));
+        }
+        log::info!("Setup headers accepted up to height {SETUP_HEIGHT}");
+
+        Ok(scenario)
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 128;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            match action {
+                Action::Connect { outbound } => {
+                    if self.connections.len() < test_utils::MAX_PEERS {
+                        let _ = self.connect(outbound);
+                    }
+                }
+                Action::MineHeader { reorg, time, bits } => self.mine(reorg, time, bits),
+                Action::MineToBoundary { spacing } => {
+                    let _ = self.mine_to_boundary(spacing);
+                }
+                Action::SendHeaders { from, count } => {
+                    let _ = self.send_headers(from, count);
+                }
+                Action::AdvanceTime { seconds } => {
+                    self.time += u64::from(seconds);
+                    let _ = self.target.set_mocktime(self.time);
+                }
+            }
+        }
+
+        self.connections.ping_all();
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.target.resource_usage()
+    }
+
+    fn characterizations(&self) -> Vec<Characterization> {
+        TipCharacterization::from_target(&self.target)
+            .map(|tip| vec![Characterization::Tip(tip)])
+            .unwrap_or_default()
+    }
+}
+
+fuzzamoto_main!(TimewarpScenario::<ScenarioTransport>, TestCase);
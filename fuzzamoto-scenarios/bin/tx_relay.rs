@@ -0,0 +1,32 @@
+mod funding;
+mod ir_scenario;
+
+use funding::Funding;
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput},
+};
+use ir_scenario::{IrScenario, TestCase};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+// Target type alias based on feature flag
+#[cfg(not(feature = "docker"))]
+type ScenarioTarget = fuzzamoto::targets::BitcoinCoreTarget;
+#[cfg(feature = "docker")]
+type ScenarioTarget = fuzzamoto::targets::DockerTarget;
+
+/// `TxRelayScenario` is the harness for fuzzing transaction relay and mempool policy.
+///
+/// It runs IR programs like `IrScenario`, but the snapshot state additionally contains a confirmed
+/// block that splits mature coinbase outputs into many smaller outputs (see `Funding`). This way
+/// programs can build many independent transactions and packages (and send them through
+/// `inv`/`getdata` sequences across all connections) without first having to create their own
+/// funding transactions.
+type TxRelayScenario = IrScenario<ScenarioTransport, ScenarioTarget, Funding>;
+
+fuzzamoto_main!(TxRelayScenario, TestCase);
@@ -0,0 +1,30 @@
+mod filter_index;
+mod funding;
+mod ir_scenario;
+
+use filter_index::FilterIndex;
+use funding::Funding;
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput},
+    targets::BitcoinCoreTarget,
+};
+use ir_scenario::{IrScenario, TestCase};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// `TxRelayCompactFiltersScenario` composes the setups of `TxRelayScenario` and
+/// `CompactFiltersScenario`.
+///
+/// The snapshot state contains the funded outputs of the tx relay setup (see `Funding`) and a
+/// synced filter index including the funding block (see `FilterIndex`), such that programs can
+/// interleave transaction relay with the blocks and filters serving them, instead of each message
+/// family being covered by a separate binary.
+type TxRelayCompactFiltersScenario =
+    IrScenario<ScenarioTransport, BitcoinCoreTarget, (Funding, FilterIndex)>;
+
+fuzzamoto_main!(TxRelayCompactFiltersScenario, TestCase);
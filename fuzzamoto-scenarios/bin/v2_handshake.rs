@@ -0,0 +1,257 @@
+use fuzzamoto::{
+    fuzzamoto_main,
+    scenarios::{Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs},
+    targets::{BitcoinCoreTarget, TargetNode},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    hashes::{Hash, HashEngine, hmac, sha256},
+    p2p::Magic,
+    secp256k1::{
+        Secp256k1, SecretKey,
+        ellswift::{ElligatorSwift, ElligatorSwiftParty},
+    },
+};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+/// Maximum garbage length allowed by BIP324 plus the length of the garbage terminator
+const MAX_GARBAGE: usize = 4095 + 16;
+/// Maximum number of raw bytes sent in one action
+const MAX_RAW: usize = 8 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Arbitrary)]
+enum Key {
+    /// ElligatorSwift encoding of a valid key derived from `secret`, which allows computing the
+    /// garbage terminator later on
+    Valid { secret: [u8; 32], aux: [u8; 32] },
+    /// Arbitrary 64 bytes
+    Raw([u8; 64]),
+    /// (Prefix of) a v1 `version` message header, which makes the target fall back to v1
+    V1Prefix { len: u8 },
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Open a new connection to the P2P port of the target
+    Connect,
+    /// Send the initiator's key
+    SendKey { connection: u8, key: Key },
+    /// Send garbage (as sent between the key and the garbage terminator)
+    SendGarbage { connection: u8, garbage: Vec<u8> },
+    /// Send the initiator's garbage terminator (optionally with a flipped bit), which requires
+    /// a valid key to have been sent on the connection
+    SendTerminator {
+        connection: u8,
+        flip_bit: Option<u8>,
+    },
+    /// Send arbitrary bytes
+    SendRaw { connection: u8, bytes: Vec<u8> },
+    /// Close one of the connections
+    Disconnect { connection: u8 },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// Handshake state of one connection from the initiator's (our) side
+struct Handshake {
+    stream: TcpStream,
+    /// Secret and encoding of the valid key we sent, if any
+    key: Option<(SecretKey, ElligatorSwift)>,
+    /// Key received from the target
+    their_key: Option<ElligatorSwift>,
+}
+
+impl Handshake {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.stream
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to send: {e}"))
+    }
+
+    /// Compute the initiator's garbage terminator of the session (see BIP324)
+    fn garbage_terminator(&mut self) -> Result<[u8; 16], String> {
+        let (secret, key) = self
+            .key
+            .ok_or_else(|| "No valid key was sent".to_string())?;
+
+        let their_key = match self.their_key {
+            Some(their_key) => their_key,
+            None => {
+                let mut bytes = [0u8; 64];
+                self.stream
+                    .read_exact(&mut bytes)
+                    .map_err(|e| format!("Failed to read responder key: {e}"))?;
+                let their_key = ElligatorSwift::from_array(bytes);
+                self.their_key = Some(their_key);
+                their_key
+            }
+        };
+
+        let shared_secret =
+            ElligatorSwift::shared_secret(key, their_key, secret, ElligatorSwiftParty::A, None);
+
+        // HKDF-SHA256 with a single block of output (RFC 5869)
+        let mut salt = b"bitcoin_v2_shared_secret".to_vec();
+        salt.extend_from_slice(&Magic::REGTEST.to_bytes());
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&salt);
+        engine.input(shared_secret.as_secret_bytes());
+        let prk = hmac::Hmac::from_engine(engine);
+
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(prk.as_byte_array());
+        engine.input(b"garbage_terminators");
+        engine.input(&[1]);
+        let terminators = hmac::Hmac::<sha256::Hash>::from_engine(engine).to_byte_array();
+
+        let mut terminator = [0u8; 16];
+        terminator.copy_from_slice(&terminators[..16]);
+        Ok(terminator)
+    }
+}
+
+fn select(handshakes: &mut [Handshake], connection: u8) -> Option<&mut Handshake> {
+    let len = handshakes.len();
+    handshakes.get_mut(usize::from(connection) % len.max(1))
+}
+
+/// `V2HandshakeScenario` tests the BIP324 (v2 transport) handshake of Bitcoin Core.
+///
+/// Unlike the other scenarios, which only use connections after the handshake completed, this
+/// scenario controls the raw bytes of the key exchange and garbage phases on connections to a
+/// `-v2transport=1` node, targeting the handshake state machine before any valid session exists.
+/// Each testcase represents a series of different types of actions:
+///
+/// 1. Open a new connection
+/// 2. Send a valid or arbitrary key, or a v1 message header prefix
+/// 3. Send garbage
+/// 4. Send the (possibly corrupted) garbage terminator
+/// 5. Send arbitrary bytes
+/// 6. Close one of the connections
+struct V2HandshakeScenario {
+    target: BitcoinCoreTarget,
+}
+
+impl Scenario<'_, TestCase> for V2HandshakeScenario {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        Ok(Self {
+            target: BitcoinCoreTarget::from_path_with_args(
+                args,
+                &args.chain_params()?,
+                &["-v2transport=1"],
+            )?,
+        })
+    }
+
+    fn run(&mut self, input: TestCase) -> ScenarioResult {
+        // Network actions are slow; limit them
+        const MAX_ACTIONS: usize = 128;
+        if input.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        let Some(p2p_socket) = self.target.node.params.p2p_socket else {
+            return ScenarioResult::Fail("P2P socket address not available".to_string());
+        };
+
+        let secp = Secp256k1::new();
+        let mut handshakes: Vec<Handshake> = Vec::with_capacity(MAX_ACTIONS);
+        for action in input.actions {
+            let _ = match action {
+                Action::Connect => {
+                    let Ok(stream) = TcpStream::connect(p2p_socket) else {
+                        return ScenarioResult::Fail("Failed to connect to the target".to_string());
+                    };
+                    let _ = stream.set_nodelay(true);
+                    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                    handshakes.push(Handshake {
+                        stream,
+                        key: None,
+                        their_key: None,
+                    });
+                    Ok(())
+                }
+                Action::SendKey { connection, key } => {
+                    let Some(handshake) = select(&mut handshakes, connection) else {
+                        continue;
+                    };
+                    match key {
+                        Key::Valid { secret, aux } => {
+                            let secret = SecretKey::from_slice(&secret)
+                                .unwrap_or_else(|_| SecretKey::from_slice(&[1; 32]).unwrap());
+                            let key = ElligatorSwift::from_seckey(&secp, secret, Some(aux));
+                            handshake.key = Some((secret, key));
+                            handshake.send(&key.to_array())
+                        }
+                        Key::Raw(bytes) => handshake.send(&bytes),
+                        Key::V1Prefix { len } => {
+                            let mut header = Magic::REGTEST.to_bytes().to_vec();
+                            header.extend_from_slice(b"version\0\0\0\0\0");
+                            header.truncate(usize::from(len) % (header.len() + 1));
+                            handshake.send(&header)
+                        }
+                    }
+                }
+                Action::SendGarbage {
+                    connection,
+                    garbage,
+                } => {
+                    let Some(handshake) = select(&mut handshakes, connection) else {
+                        continue;
+                    };
+                    handshake.send(&garbage[..garbage.len().min(MAX_GARBAGE)])
+                }
+                Action::SendTerminator {
+                    connection,
+                    flip_bit,
+                } => {
+                    let Some(handshake) = select(&mut handshakes, connection) else {
+                        continue;
+                    };
+                    handshake.garbage_terminator().and_then(|mut terminator| {
+                        if let Some(bit) = flip_bit {
+                            terminator[usize::from(bit / 8) % 16] ^= 1 << (bit % 8);
+                        }
+                        handshake.send(&terminator)
+                    })
+                }
+                Action::SendRaw { connection, bytes } => {
+                    let Some(handshake) = select(&mut handshakes, connection) else {
+                        continue;
+                    };
+                    handshake.send(&bytes[..bytes.len().min(MAX_RAW)])
+                }
+                Action::Disconnect { connection } => {
+                    if !handshakes.is_empty() {
+                        let index = usize::from(connection) % handshakes.len();
+                        let _ = handshakes.swap_remove(index);
+                    }
+                    Ok(())
+                }
+            };
+        }
+
+        if let Err(e) = self.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+}
+
+fuzzamoto_main!(V2HandshakeScenario, TestCase);
@@ -0,0 +1,465 @@
+use fuzzamoto::{
+    connections::{TrafficMetrics, Transport},
+    fuzzamoto_main,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
+    targets::{BitcoinCoreTarget, HasTipInfo, ResourceUsage, RpcTarget, Target, TargetNode},
+    test_utils::{self, mining},
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use bitcoin::{
+    Address, Amount, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness, absolute, consensus::encode, p2p::message::NetworkMessage, transaction,
+};
+use serde_json::{Value, json};
+use std::{collections::HashSet, str::FromStr};
+
+// Transport type alias based on feature flag
+#[cfg(not(feature = "v2transport"))]
+type ScenarioTransport = fuzzamoto::connections::V1Transport;
+#[cfg(feature = "v2transport")]
+type ScenarioTransport = fuzzamoto::connections::V2Transport;
+
+/// Address types of the wallet addresses created during setup
+const ADDRESS_TYPES: &[&str] = &["legacy", "p2sh-segwit", "bech32", "bech32m"];
+const ADDRESSES_PER_TYPE: usize = 2;
+/// Unit of the amounts chosen by testcases
+const AMOUNT_UNIT: u64 = 10_000;
+/// Maximum depth of the blocks invalidated by testcases
+const MAX_INVALIDATE_DEPTH: u8 = 10;
+
+#[derive(Arbitrary)]
+struct Payment {
+    address: u8,
+    /// Amount in units of `AMOUNT_UNIT` satoshis
+    amount: u16,
+}
+
+#[derive(Arbitrary)]
+enum Action {
+    /// Build a transaction spending one of the known `OP_TRUE` outputs, paying to the selected
+    /// addresses (the remainder minus `fee` goes to an `OP_TRUE` change output) and send it to the
+    /// target
+    Pay {
+        from: u8,
+        utxo: u8,
+        payments: Vec<Payment>,
+        fee: u16,
+        rbf: bool,
+    },
+    /// Mine a block on the target's tip confirming the selected transactions
+    Mine {
+        txs: Vec<u8>,
+    },
+    /// Invalidate the block `depth` blocks below the target's tip (`invalidateblock`)
+    Invalidate {
+        depth: u8,
+    },
+    /// Reconsider the most recently invalidated block (`reconsiderblock`)
+    Reconsider,
+    ListUnspent {
+        min_conf: u8,
+        include_unsafe: bool,
+    },
+    GetBalances,
+    SendToAddress {
+        address: u8,
+        amount: u16,
+        subtract_fee: bool,
+        replaceable: bool,
+        fee_rate: Option<u16>,
+    },
+    SendAll {
+        address: u8,
+        fee_rate: Option<u16>,
+        send_max: bool,
+    },
+    BumpFee {
+        tx: u8,
+        fee_rate: Option<u16>,
+    },
+    AbandonTransaction {
+        tx: u8,
+    },
+    /// Advance the mocktime of the target node
+    AdvanceTime {
+        seconds: u16,
+    },
+}
+
+#[derive(Arbitrary)]
+struct TestCase {
+    actions: Vec<Action>,
+}
+
+impl ScenarioInput<'_> for TestCase {
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut unstructured = Unstructured::new(bytes);
+        let actions = Vec::arbitrary(&mut unstructured).map_err(|e| e.to_string())?;
+        Ok(Self { actions })
+    }
+}
+
+/// `WalletScenario` tests the wallet's transaction processing against adversarial chain and
+/// mempool states.
+///
+/// The setup creates addresses of all types in the target's default wallet. Testcases build
+/// transactions paying to these addresses (spending `OP_TRUE` outputs controlled by the scenario,
+/// possibly conflicting with or replacing each other), confirm them in blocks, reorg them out
+/// again with `invalidateblock` and interleave this with wallet RPCs that spend the received
+/// outputs (`sendtoaddress`, `sendall`, `bumpfee`, `abandontransaction`, ...).
+struct WalletScenario<TX: Transport> {
+    inner: GenericScenario<TX, BitcoinCoreTarget>,
+    /// Wallet addresses followed by an external `OP_TRUE` address
+    addresses: Vec<(String, ScriptBuf)>,
+    /// `OP_TRUE` outputs spendable by `Pay`, starting with the mature coinbase outputs
+    utxos: Vec<(OutPoint, Amount)>,
+    /// Transactions built by `Pay`
+    txs: Vec<Transaction>,
+    /// Transactions created by the wallet
+    wallet_txs: Vec<Txid>,
+    invalidated: Vec<String>,
+}
+
+impl<TX: Transport> WalletScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn address(&self, address: u8) -> &(String, ScriptBuf) {
+        &self.addresses[usize::from(address) % self.addresses.len()]
+    }
+
+    fn pay(
+        &mut self,
+        from: u8,
+        utxo: u8,
+        payments: &[Payment],
+        fee: u16,
+        rbf: bool,
+    ) -> Result<(), String> {
+        let (outpoint, value) = self.utxos[usize::from(utxo) % self.utxos.len()];
+
+        let mut output: Vec<TxOut> = payments
+            .iter()
+            .map(|payment| TxOut {
+                value: Amount::from_sat(u64::from(payment.amount) * AMOUNT_UNIT),
+                script_pubkey: self.address(payment.address).1.clone(),
+            })
+            .collect();
+        let paid = output.iter().map(|output| output.value).sum::<Amount>()
+            + Amount::from_sat(u64::from(fee));
+        let change = value
+            .checked_sub(paid)
+            .ok_or_else(|| "Insufficient funds".to_string())?;
+        output.push(TxOut {
+            value: change,
+            script_pubkey: ScriptBuf::new_p2wsh(&test_utils::op_true_script().wscript_hash()),
+        });
+
+        let mut witness = Witness::new();
+        witness.push(test_utils::op_true_script().as_bytes());
+        let tx = Transaction {
+            version: transaction::Version(2),
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: if rbf {
+                    Sequence::ENABLE_RBF_NO_LOCKTIME
+                } else {
+                    Sequence::MAX
+                },
+                witness,
+            }],
+            output,
+        };
+
+        self.inner.connections.send(
+            usize::from(from),
+            &(
+                "tx".to_string(),
+                encode::serialize(&NetworkMessage::Tx(tx.clone())),
+            ),
+        )?;
+
+        let vout = u32::try_from(tx.output.len() - 1).map_err(|e| e.to_string())?;
+        self.utxos
+            .push((OutPoint::new(tx.compute_txid(), vout), change));
+        self.txs.push(tx);
+        Ok(())
+    }
+
+    fn mine(&mut self, txs: &[u8]) -> Result<(), String> {
+        let (tip, height) = self
+            .inner
+            .target
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip".to_string())?;
+        let height = u32::try_from(height).map_err(|e| e.to_string())?;
+
+        let mut seen = HashSet::new();
+        let confirm = txs
+            .iter()
+            .filter_map(|index| self.txs.get(usize::from(*index) % self.txs.len().max(1)))
+            .filter(|tx| seen.insert(tx.compute_txid()))
+            .cloned();
+
+        self.inner.time += 1;
+        let time = u32::try_from(self.inner.time).map_err(|e| e.to_string())?;
+        let mut block = mining::mine_block(tip, height + 1, time);
+        block.txdata.extend(confirm);
+        mining::fixup_commitments(&mut block);
+        mining::fixup_proof_of_work(&mut block);
+
+        self.inner.target.set_mocktime(self.inner.time)?;
+        self.inner
+            .connections
+            .send(0, &("block".to_string(), encode::serialize(&block)))?;
+        self.inner.connections.ping_all();
+        Ok(())
+    }
+
+    fn invalidate(&mut self, depth: u8) -> Result<(), String> {
+        let (_, height) = self
+            .inner
+            .target
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip".to_string())?;
+        let height = height
+            .saturating_sub(u64::from(depth % MAX_INVALIDATE_DEPTH))
+            .max(1);
+        let hash = self
+            .inner
+            .target
+            .call_rpc("getblockhash", &[height.into()])?;
+        self.inner
+            .target
+            .call_rpc("invalidateblock", &[hash.clone()])?;
+        if let Some(hash) = hash.as_str() {
+            self.invalidated.push(hash.to_string());
+        }
+        Ok(())
+    }
+
+    fn reconsider(&mut self) -> Result<(), String> {
+        let Some(hash) = self.invalidated.pop() else {
+            return Ok(());
+        };
+        self.inner
+            .target
+            .call_rpc("reconsiderblock", &[hash.into()])?;
+        Ok(())
+    }
+
+    fn wallet_tx(&self, tx: u8) -> Option<String> {
+        self.wallet_txs
+            .get(usize::from(tx) % self.wallet_txs.len().max(1))
+            .map(ToString::to_string)
+    }
+
+    /// Remember the wallet transaction created by an RPC (either returned as a txid or as an
+    /// object with a `txid` field)
+    fn add_wallet_tx(&mut self, result: &Value) {
+        let txid = result.as_str().or_else(|| result["txid"].as_str());
+        if let Some(txid) = txid.and_then(|txid| Txid::from_str(txid).ok()) {
+            self.wallet_txs.push(txid);
+        }
+    }
+
+    fn wallet_rpc(&mut self, action: Action) -> Result<(), String> {
+        let target = &self.inner.target;
+        let result = match action {
+            Action::ListUnspent {
+                min_conf,
+                include_unsafe,
+            } => target.call_rpc(
+                "listunspent",
+                &[
+                    min_conf.into(),
+                    9_999_999.into(),
+                    json!([]),
+                    include_unsafe.into(),
+                ],
+            )?,
+            Action::GetBalances => target.call_rpc("getbalances", &[])?,
+            Action::SendToAddress {
+                address,
+                amount,
+                subtract_fee,
+                replaceable,
+                fee_rate,
+            } => target.call_rpc(
+                "sendtoaddress",
+                &[
+                    self.address(address).0.clone().into(),
+                    Amount::from_sat(u64::from(amount) * AMOUNT_UNIT)
+                        .to_btc()
+                        .into(),
+                    "".into(),
+                    "".into(),
+                    subtract_fee.into(),
+                    replaceable.into(),
+                    Value::Null,
+                    "unset".into(),
+                    Value::Null,
+                    fee_rate.into(),
+                ],
+            )?,
+            Action::SendAll {
+                address,
+                fee_rate,
+                send_max,
+            } => target.call_rpc(
+                "sendall",
+                &[
+                    json!([self.address(address).0]),
+                    Value::Null,
+                    "unset".into(),
+                    fee_rate.into(),
+                    json!({ "send_max": send_max }),
+                ],
+            )?,
+            Action::BumpFee { tx, fee_rate } => {
+                let Some(txid) = self.wallet_tx(tx) else {
+                    return Ok(());
+                };
+                let options =
+                    fee_rate.map_or_else(|| json!({}), |rate| json!({ "fee_rate": rate }));
+                target.call_rpc("bumpfee", &[txid.into(), options])?
+            }
+            Action::AbandonTransaction { tx } => {
+                let Some(txid) = self.wallet_tx(tx) else {
+                    return Ok(());
+                };
+                target.call_rpc("abandontransaction", &[txid.into()])?
+            }
+            _ => return Ok(()),
+        };
+        log::info!("\t-> {result}");
+        self.add_wallet_tx(&result);
+        Ok(())
+    }
+}
+
+impl<TX: Transport> Scenario<'_, TestCase> for WalletScenario<TX>
+where
+    BitcoinCoreTarget: Target<TX>,
+{
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        // Regtest has no fee estimates, so let the wallet fall back to a fixed feerate
+        let target = BitcoinCoreTarget::from_path_with_args(
+            args,
+            &args.chain_params()?,
+            &["-fallbackfee=0.0001"],
+        )?;
+        let inner = GenericScenario::from_target(target, &args.handshake_opts())?;
+
+        let mut addresses = Vec::new();
+        for address_type in ADDRESS_TYPES {
+            for _ in 0..ADDRESSES_PER_TYPE {
+                let address = inner
+                    .target
+                    .call_rpc("getnewaddress", &["".into(), (*address_type).into()])?;
+                let address = address
+                    .as_str()
+                    .ok_or_else(|| format!("Unexpected getnewaddress result: {address}"))?;
+                let script_pubkey = Address::from_str(address)
+                    .map_err(|e| format!("Failed to parse wallet address: {e}"))?
+                    .assume_checked()
+                    .script_pubkey();
+                addresses.push((address.to_string(), script_pubkey));
+            }
+        }
+        let external = Address::p2wsh(&test_utils::op_true_script(), Network::Regtest);
+        addresses.push((external.to_string(), external.script_pubkey()));
+
+        let tip_height = inner
+            .block_tree
+            .values()
+            .map(|(_, height)| *height)
+            .max()
+            .unwrap_or_default();
+        let mut utxos: Vec<(u32, (OutPoint, Amount))> = inner
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height + test_utils::COINBASE_MATURITY <= tip_height)
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (
+                    *height,
+                    (
+                        OutPoint::new(coinbase.compute_txid(), 0),
+                        coinbase.output[0].value,
+                    ),
+                )
+            })
+            .collect();
+        utxos.sort_by_key(|(height, _)| *height);
+
+        log::info!(
+            "Created {} wallet addresses, {} spendable coinbase outputs",
+            addresses.len() - 1,
+            utxos.len()
+        );
+
+        Ok(Self {
+            inner,
+            addresses,
+            utxos: utxos.into_iter().map(|(_, utxo)| utxo).collect(),
+            txs: Vec::new(),
+            wallet_txs: Vec::new(),
+            invalidated: Vec::new(),
+        })
+    }
+
+    fn run(&mut self, testcase: TestCase) -> ScenarioResult {
+        const MAX_ACTIONS: usize = 64;
+        if testcase.actions.len() > MAX_ACTIONS {
+            return ScenarioResult::Ok;
+        }
+
+        for action in testcase.actions {
+            let _ = match action {
+                Action::Pay {
+                    from,
+                    utxo,
+                    payments,
+                    fee,
+                    rbf,
+                } => self.pay(from, utxo, &payments, fee, rbf),
+                Action::Mine { txs } => self.mine(&txs),
+                Action::Invalidate { depth } => self.invalidate(depth),
+                Action::Reconsider => self.reconsider(),
+                Action::AdvanceTime { seconds } => {
+                    self.inner.time += u64::from(seconds);
+                    self.inner.target.set_mocktime(self.inner.time)
+                }
+                action => self.wallet_rpc(action),
+            };
+        }
+
+        self.inner.connections.ping_all();
+
+        if let Err(e) = self.inner.target.is_alive() {
+            return ScenarioResult::Fail(format!("Target is not alive: {e}"));
+        }
+
+        if let Err(e) = self.inner.target.call_rpc("getbalances", &[]) {
+            return ScenarioResult::Fail(format!("Failed to get wallet balances: {e}"));
+        }
+
+        ScenarioResult::Ok
+    }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.inner.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.inner.target.resource_usage()
+    }
+}
+
+fuzzamoto_main!(WalletScenario::<ScenarioTransport>, TestCase);
@@ -1,7 +1,9 @@
 use fuzzamoto::{
     connections::Transport,
     fuzzamoto_main,
-    scenarios::{Scenario, ScenarioInput, ScenarioResult, generic::GenericScenario},
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult, args::ScenarioArgs, generic::GenericScenario,
+    },
     targets::{BitcoinCoreTarget, TargetNode},
 };
 
@@ -37,7 +39,7 @@ impl<'a, TX: Transport> Scenario<'a, WalletDotDatBytes<'a>> for WalletMigrationS
 where
     BitcoinCoreTarget: fuzzamoto::targets::Target<TX>,
 {
-    fn new(args: &[String]) -> Result<Self, String> {
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
         let inner = GenericScenario::<TX, BitcoinCoreTarget>::new(args)?;
 
         let _ = inner
@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+use bitcoin::Txid;
+
+use crate::targets::{HasGetRawMempoolEntries, HasTipInfo, MempoolTx, TargetNode};
+
+/// `NodeState` is the state of a target node extracted through RPC once at the end of a test
+/// case, which `AssertionScope`s are evaluated against.
+#[derive(Debug, Clone, Default)]
+pub struct NodeState {
+    pub tip_height: u64,
+    /// Whether the node went through a reorg (see `HasTipInfo::has_reorged`)
+    pub reorged: bool,
+    /// Transactions in the mempool by txid
+    pub mempool: BTreeMap<Txid, MempoolTx>,
+    /// In-mempool parents of each mempool transaction (`depends` of `getrawmempool`)
+    pub parents: BTreeMap<Txid, Vec<Txid>>,
+    /// In-mempool children of each mempool transaction (`spentby` of `getrawmempool`)
+    pub children: BTreeMap<Txid, Vec<Txid>>,
+}
+
+impl NodeState {
+    pub fn from_target<T>(target: &T) -> Result<Self, String>
+    where
+        T: TargetNode + HasTipInfo + HasGetRawMempoolEntries,
+    {
+        let (_, tip_height) = target
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip info".to_string())?;
+        let mempool = target
+            .mempool_contents()?
+            .into_iter()
+            .map(|tx| (tx.txid, tx))
+            .collect();
+
+        let mut parents = BTreeMap::new();
+        let mut children = BTreeMap::new();
+        for entry in target.get_mempool_entries()? {
+            parents.insert(*entry.txid(), entry.depends().to_vec());
+            children.insert(*entry.txid(), entry.spentby().to_vec());
+        }
+
+        Ok(Self {
+            tip_height,
+            reorged: target.has_reorged()?,
+            mempool,
+            parents,
+            children,
+        })
+    }
+
+    /// All in-mempool ancestors of `txid` (excluding `txid` itself, unless it is part of a cycle)
+    #[must_use]
+    pub fn ancestors(&self, txid: &Txid) -> BTreeSet<Txid> {
+        Self::reachable(&self.parents, txid)
+    }
+
+    /// All in-mempool descendants of `txid` (excluding `txid` itself, unless it is part of a
+    /// cycle)
+    #[must_use]
+    pub fn descendants(&self, txid: &Txid) -> BTreeSet<Txid> {
+        Self::reachable(&self.children, txid)
+    }
+
+    fn reachable(edges: &BTreeMap<Txid, Vec<Txid>>, txid: &Txid) -> BTreeSet<Txid> {
+        let mut reached = BTreeSet::new();
+        let mut stack = vec![*txid];
+        while let Some(current) = stack.pop() {
+            for next in edges.get(&current).into_iter().flatten() {
+                if reached.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Number and total virtual size of `txid` and `relatives` (transactions missing from the
+    /// mempool are ignored)
+    fn package(&self, txid: &Txid, relatives: &BTreeSet<Txid>) -> (usize, u64) {
+        std::iter::once(txid)
+            .chain(relatives)
+            .filter_map(|txid| self.mempool.get(txid))
+            .fold((0, 0), |(count, vsize), tx| (count + 1, vsize + tx.vsize))
+    }
+}
+
+/// `AssertionScope` is an invariant over the final `NodeState` of a test case.
+///
+/// Scenarios register the scopes that hold for their setup with an `AssertionOracle` (see
+/// `crate::oracles`), instead of checking each invariant by hand.
+#[derive(Debug, Clone)]
+pub enum AssertionScope {
+    /// Every mempool transaction has at most `count` in-mempool ancestors of at most `vsize`
+    /// virtual bytes, both including the transaction itself (`-limitancestorcount` and
+    /// `-limitancestorsize`). Not checked after reorgs, as transactions of disconnected blocks
+    /// are added back to the mempool regardless of the limits.
+    AncestorLimits { count: usize, vsize: u64 },
+    /// Every mempool transaction has at most `count` in-mempool descendants of at most `vsize`
+    /// virtual bytes, both including the transaction itself (`-limitdescendantcount` and
+    /// `-limitdescendantsize`). Not checked after reorgs, like `AncestorLimits`.
+    DescendantLimits { count: usize, vsize: u64 },
+    /// The mempool's parent and child relations only refer to mempool transactions, mirror each
+    /// other and contain no cycles
+    MempoolGraph,
+    /// The tip is at least at `height`
+    MinTipHeight(u64),
+    /// A scenario specific invariant
+    Custom {
+        name: &'static str,
+        check: fn(&NodeState) -> Result<(), String>,
+    },
+}
+
+impl AssertionScope {
+    /// Scopes that hold for a Bitcoin Core node started with `node_args`, with the mempool limits
+    /// taken from the arguments (Bitcoin Core's defaults if absent).
+    #[must_use]
+    pub fn defaults(node_args: &[String]) -> Vec<Self> {
+        // Sizes are configured in kvB
+        vec![
+            Self::AncestorLimits {
+                count: node_arg(node_args, "limitancestorcount").unwrap_or(25),
+                vsize: node_arg::<u64>(node_args, "limitancestorsize").unwrap_or(101) * 1000,
+            },
+            Self::DescendantLimits {
+                count: node_arg(node_args, "limitdescendantcount").unwrap_or(25),
+                vsize: node_arg::<u64>(node_args, "limitdescendantsize").unwrap_or(101) * 1000,
+            },
+            Self::MempoolGraph,
+        ]
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::AncestorLimits { .. } => "ancestor limits",
+            Self::DescendantLimits { .. } => "descendant limits",
+            Self::MempoolGraph => "mempool graph",
+            Self::MinTipHeight(_) => "min tip height",
+            Self::Custom { name, .. } => name,
+        }
+    }
+
+    pub fn evaluate(&self, state: &NodeState) -> Result<(), String> {
+        match self {
+            Self::AncestorLimits { .. } | Self::DescendantLimits { .. } if state.reorged => Ok(()),
+            Self::AncestorLimits { count, vsize } => {
+                for txid in state.mempool.keys() {
+                    let (actual_count, actual_vsize) = state.package(txid, &state.ancestors(txid));
+                    if actual_count > *count || actual_vsize > *vsize {
+                        return Err(format!(
+                            "{txid} has {actual_count} ancestors of {actual_vsize} vbytes (limits: {count}, {vsize})"
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            Self::DescendantLimits { count, vsize } => {
+                for txid in state.mempool.keys() {
+                    let (actual_count, actual_vsize) =
+                        state.package(txid, &state.descendants(txid));
+                    if actual_count > *count || actual_vsize > *vsize {
+                        return Err(format!(
+                            "{txid} has {actual_count} descendants of {actual_vsize} vbytes (limits: {count}, {vsize})"
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            Self::MempoolGraph => {
+                for (txid, parents) in &state.parents {
+                    for parent in parents {
+                        if !state.parents.contains_key(parent) {
+                            return Err(format!("{txid} depends on {parent} outside the mempool"));
+                        }
+                        if !state.children.get(parent).is_some_and(|c| c.contains(txid)) {
+                            return Err(format!(
+                                "{txid} depends on {parent}, which is not spent by it"
+                            ));
+                        }
+                    }
+                    if state.ancestors(txid).contains(txid) {
+                        return Err(format!("{txid} is its own ancestor"));
+                    }
+                }
+                for (txid, children) in &state.children {
+                    for child in children {
+                        if !state.parents.get(child).is_some_and(|p| p.contains(txid)) {
+                            return Err(format!(
+                                "{txid} is spent by {child}, which does not depend on it"
+                            ));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Self::MinTipHeight(height) => {
+                if state.tip_height < *height {
+                    return Err(format!("Tip height {} is below {height}", state.tip_height));
+                }
+                Ok(())
+            }
+            Self::Custom { check, .. } => check(state),
+        }
+    }
+}
+
+/// Value of the last `-<name>=<value>` argument in `node_args` (later arguments take precedence)
+fn node_arg<T: FromStr>(node_args: &[String], name: &str) -> Option<T> {
+    node_args.iter().rev().find_map(|arg| {
+        arg.strip_prefix('-')?
+            .strip_prefix(name)?
+            .strip_prefix('=')?
+            .parse()
+            .ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{Amount, Wtxid, hashes::Hash};
+
+    fn txid(n: u8) -> Txid {
+        Txid::from_byte_array([n; 32])
+    }
+
+    /// A mempool with the chain `1 <- 2 <- 3` of 100 vbyte transactions
+    fn chain_state() -> NodeState {
+        let mut state = NodeState::default();
+        for n in 1..=3 {
+            state.mempool.insert(
+                txid(n),
+                MempoolTx {
+                    txid: txid(n),
+                    wtxid: Wtxid::from_byte_array([n; 32]),
+                    vsize: 100,
+                    fee: Amount::from_sat(1000),
+                },
+            );
+            state.parents.insert(txid(n), Vec::new());
+            state.children.insert(txid(n), Vec::new());
+        }
+        for (parent, child) in [(1, 2), (2, 3)] {
+            state
+                .parents
+                .get_mut(&txid(child))
+                .unwrap()
+                .push(txid(parent));
+            state
+                .children
+                .get_mut(&txid(parent))
+                .unwrap()
+                .push(txid(child));
+        }
+        state
+    }
+
+    #[test]
+    fn evaluate_scopes() {
+        let state = chain_state();
+        assert_eq!(state.ancestors(&txid(3)), [txid(1), txid(2)].into());
+        assert_eq!(state.descendants(&txid(1)), [txid(2), txid(3)].into());
+
+        for scope in AssertionScope::defaults(&[]) {
+            assert!(scope.evaluate(&state).is_ok(), "{}", scope.name());
+        }
+        assert!(
+            AssertionScope::AncestorLimits {
+                count: 2,
+                vsize: 1000
+            }
+            .evaluate(&state)
+            .is_err()
+        );
+        assert!(
+            AssertionScope::DescendantLimits {
+                count: 3,
+                vsize: 299
+            }
+            .evaluate(&state)
+            .is_err()
+        );
+        assert!(AssertionScope::MinTipHeight(1).evaluate(&state).is_err());
+
+        let node_args = ["-limitancestorcount=2".to_string()];
+        let ancestor_limits = &AssertionScope::defaults(&node_args)[0];
+        assert!(ancestor_limits.evaluate(&state).is_err());
+        let mut reorged = state.clone();
+        reorged.reorged = true;
+        assert!(ancestor_limits.evaluate(&reorged).is_ok());
+
+        let mut broken = state.clone();
+        broken.children.get_mut(&txid(1)).unwrap().clear();
+        assert!(AssertionScope::MempoolGraph.evaluate(&broken).is_err());
+
+        let mut cyclic = state;
+        cyclic.parents.get_mut(&txid(1)).unwrap().push(txid(3));
+        cyclic.children.get_mut(&txid(3)).unwrap().push(txid(1));
+        assert!(AssertionScope::MempoolGraph.evaluate(&cyclic).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use bitcoin::{Block, Network, blockdata::constants::genesis_block, p2p::Magic};
+
+/// Parameters of the chain a target is running on.
+///
+/// Regtest is used by default, but scenarios can select other chains (e.g. signet or testnet4)
+/// or describe a custom chain, so that chain specific code paths in the target can be fuzzed.
+#[derive(Debug, Clone)]
+pub struct ChainParams {
+    /// Name of the chain, as passed to the target (e.g. `-chain=<name>` for Bitcoin Core)
+    pub chain: &'static str,
+    /// Network magic used in the v1 p2p message header
+    pub magic: Magic,
+    /// Genesis block of the chain
+    pub genesis: Block,
+    /// Default p2p port of the chain
+    pub default_port: u16,
+}
+
+impl ChainParams {
+    #[must_use]
+    pub fn regtest() -> Self {
+        Self::from_network(Network::Regtest)
+    }
+
+    #[must_use]
+    pub fn signet() -> Self {
+        Self::from_network(Network::Signet)
+    }
+
+    #[must_use]
+    pub fn testnet4() -> Self {
+        Self::from_network(Network::Testnet4)
+    }
+
+    /// Create the chain parameters for one of the networks known to rust-bitcoin.
+    #[must_use]
+    pub fn from_network(network: Network) -> Self {
+        let (chain, default_port) = match network {
+            Network::Bitcoin => ("main", 8333),
+            Network::Testnet => ("test", 18333),
+            Network::Testnet4 => ("testnet4", 48333),
+            Network::Signet => ("signet", 38333),
+            _ => ("regtest", 18444),
+        };
+
+        Self {
+            chain,
+            magic: network.magic(),
+            genesis: genesis_block(network),
+            default_port,
+        }
+    }
+
+    /// Create the chain parameters for a custom chain.
+    #[must_use]
+    pub fn custom(chain: &'static str, magic: Magic, genesis: Block, default_port: u16) -> Self {
+        Self {
+            chain,
+            magic,
+            genesis,
+            default_port,
+        }
+    }
+
+    /// Select the chain by name (`regtest`, `signet` or `testnet4`).
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "regtest" => Ok(Self::regtest()),
+            "signet" => Ok(Self::signet()),
+            "testnet4" => Ok(Self::testnet4()),
+            _ => Err(format!("Unknown chain: {name}")),
+        }
+    }
+
+    /// Select the chain through the `FUZZAMOTO_CHAIN` environment variable, defaulting to regtest
+    /// if it is not set.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("FUZZAMOTO_CHAIN") {
+            Ok(name) => Self::from_name(&name),
+            Err(_) => Ok(Self::regtest()),
+        }
+    }
+
+    /// Returns whether these are the regtest chain parameters.
+    #[must_use]
+    pub fn is_regtest(&self) -> bool {
+        self.magic == Network::Regtest.magic()
+    }
+
+    /// The network known to rust-bitcoin with the magic of this chain, `None` for custom chains.
+    #[must_use]
+    pub fn network(&self) -> Option<Network> {
+        Network::from_magic(self.magic)
+    }
+
+    /// Fail unless these are the regtest chain parameters, for setups that mine blocks (at regtest
+    /// difficulty or with the target's `generatetoaddress`, which both only work on regtest).
+    pub fn require_regtest(&self) -> Result<(), String> {
+        if self.is_regtest() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Mining during setup is only supported on regtest, not on {}",
+                self.chain
+            ))
+        }
+    }
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self::regtest()
+    }
+}
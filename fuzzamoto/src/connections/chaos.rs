@@ -0,0 +1,210 @@
+use std::io::ErrorKind;
+use std::net;
+use std::time::Duration;
+
+use bitcoin::p2p::Magic;
+
+use crate::chain::ChainParams;
+use crate::connections::{Transport, encode_p2p_message};
+
+/// Configuration of the faults injected by a `ChaosTransport`
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    /// Seed for all random decisions, such that a run can be reproduced
+    pub seed: u64,
+    /// Maximum size of a single write. Frames are split into segments of random size in
+    /// `1..=max_segment_size`.
+    pub max_segment_size: usize,
+    /// Maximum delay inserted before each write
+    pub max_delay: Duration,
+    /// Probability (0.0 to 1.0) that a sent message is held back and coalesced with the next
+    /// message(s) into a single write
+    pub coalesce_probability: f64,
+    /// Shuffle coalesced messages before writing them
+    pub reorder: bool,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            max_segment_size: 16,
+            max_delay: Duration::ZERO,
+            coalesce_probability: 0.0,
+            reorder: false,
+        }
+    }
+}
+
+/// `ChaosTransport` wraps another transport and messes with how sent messages end up on the
+/// wire: frames are split into arbitrarily sized segments, writes are delayed and messages are
+/// coalesced (and optionally reordered) into a single write. This exercises message reassembly
+/// and partial read handling in the target's net layer.
+///
+/// Sending relies on `Transport::send_raw`, so the inner transport has to support raw frames.
+/// Note that segments only reach the target as separate TCP segments if Nagle's algorithm is
+/// disabled on the socket (`TcpStream::set_nodelay`).
+pub struct ChaosTransport<T: Transport> {
+    inner: T,
+    magic: Magic,
+    config: ChaosConfig,
+    rng: u64,
+    /// Encoded frames held back for coalescing
+    pending: Vec<Vec<u8>>,
+}
+
+impl<T: Transport> ChaosTransport<T> {
+    #[must_use]
+    pub fn new(inner: T, chain_params: &ChainParams, config: ChaosConfig) -> Self {
+        Self {
+            inner,
+            magic: chain_params.magic,
+            rng: config.seed,
+            config,
+            pending: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Write all held back messages to the inner transport.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut frames = std::mem::take(&mut self.pending);
+        if self.config.reorder {
+            for i in (1..frames.len()).rev() {
+                let j = self.gen_range(i + 1);
+                frames.swap(i, j);
+            }
+        }
+
+        let bytes = frames.concat();
+        let mut remaining = &bytes[..];
+        while !remaining.is_empty() {
+            let segment_size = 1 + self.gen_range(self.config.max_segment_size.max(1));
+            let (segment, rest) = remaining.split_at(segment_size.min(remaining.len()));
+
+            if !self.config.max_delay.is_zero() {
+                let max_micros =
+                    u64::try_from(self.config.max_delay.as_micros()).unwrap_or(u64::MAX);
+                std::thread::sleep(Duration::from_micros(
+                    self.next_u64() % max_micros.saturating_add(1),
+                ));
+            }
+
+            self.inner.send_raw(segment)?;
+            remaining = rest;
+        }
+
+        Ok(())
+    }
+
+    /// splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Random number in `0..upper` (`upper` must be non-zero)
+    #[expect(clippy::cast_possible_truncation)]
+    fn gen_range(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    fn gen_bool(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+}
+
+impl<T: Transport> Transport for ChaosTransport<T> {
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        let frame = encode_p2p_message(self.magic, message)?;
+        self.send_raw(&frame)
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        // Held back messages might be required for the target to respond
+        self.flush()?;
+        self.inner.receive()
+    }
+
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        self.flush()?;
+        self.inner.try_receive()
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.inner.local_addr()
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.pending.push(bytes.to_vec());
+        if self.gen_bool(self.config.coalesce_probability) {
+            return Ok(());
+        }
+        self.flush()
+    }
+
+    fn encode_frame(&self, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        encode_p2p_message(self.magic, message)
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        self.inner.disconnect_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::V1Transport;
+
+    #[test]
+    fn reassembles_fragmented_messages() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.set_nodelay(true).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        let config = ChaosConfig {
+            seed: 42,
+            max_segment_size: 5,
+            coalesce_probability: 0.5,
+            ..Default::default()
+        };
+        let mut sender =
+            ChaosTransport::new(V1Transport::new(client), &ChainParams::regtest(), config);
+        let mut receiver = V1Transport::new(server);
+
+        let messages: Vec<_> = (0u8..10)
+            .map(|i| ("ping".to_string(), vec![i; 8]))
+            .collect();
+        for message in &messages {
+            sender.send(message).unwrap();
+        }
+        sender.flush().unwrap();
+
+        for message in &messages {
+            assert_eq!(&receiver.receive().unwrap(), message);
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{ErrorKind, Read, Write};
+use std::net;
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::{Hash, sha256};
+
+use crate::chain::ChainParams;
+use crate::connections::{Transport, V1Transport};
+
+const I2P_BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-~";
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Length of the public keys of a destination (encryption and signing key)
+const DESTINATION_KEYS_LEN: usize = 384;
+/// Key certificate (type 5, length 4) announcing Ed25519 signing (7) and ElGamal encryption (0)
+const KEY_CERTIFICATE: [u8; 7] = [5, 0, 4, 0, 7, 0, 0];
+/// Length of the private keys following the destination in a SAM private key
+const PRIVATE_KEYS_LEN: usize = 256 + 32;
+
+const MAX_SAM_LINE_LEN: usize = 64 * 1024;
+/// How long to wait for the target to issue the SAM requests we are waiting for
+const SAM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An I2P destination (the public part of an I2P identity)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct I2pDestination(Vec<u8>);
+
+impl I2pDestination {
+    /// Create a destination with key material derived from `seed`. The keys are not usable for
+    /// actual I2P traffic, but the destination is well-formed.
+    #[must_use]
+    pub fn from_seed(seed: u64) -> Self {
+        let mut bytes = Vec::with_capacity(DESTINATION_KEYS_LEN + KEY_CERTIFICATE.len());
+        let mut counter = 0u64;
+        while bytes.len() < DESTINATION_KEYS_LEN {
+            let mut preimage = seed.to_le_bytes().to_vec();
+            preimage.extend_from_slice(&counter.to_le_bytes());
+            bytes.extend_from_slice(sha256::Hash::hash(&preimage).as_byte_array());
+            counter += 1;
+        }
+        bytes.extend_from_slice(&KEY_CERTIFICATE);
+        Self(bytes)
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        decode_i2p_base64(encoded).map(Self)
+    }
+
+    #[must_use]
+    pub fn to_base64(&self) -> String {
+        encode_i2p_base64(&self.0)
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// SHA256 of the destination, as carried by addrv2 I2P addresses
+    #[must_use]
+    pub fn hash(&self) -> [u8; 32] {
+        sha256::Hash::hash(&self.0).to_byte_array()
+    }
+
+    /// The `<base32>.b32.i2p` address of the destination
+    #[must_use]
+    pub fn b32_address(&self) -> String {
+        format!("{}.b32.i2p", encode_bits(&self.hash(), BASE32_ALPHABET, 5))
+    }
+
+    /// SAM private key for this destination (with zeroed private keys)
+    fn private_key(&self) -> String {
+        let mut bytes = self.0.clone();
+        bytes.resize(bytes.len() + PRIVATE_KEYS_LEN, 0);
+        encode_i2p_base64(&bytes)
+    }
+}
+
+/// A `V1Transport` over an I2P stream handed out by a `SamBridge`.
+pub struct I2pSamTransport {
+    inner: V1Transport,
+    /// b32 address of the remote end, as seen by the target
+    peer: String,
+}
+
+impl I2pSamTransport {
+    /// The b32 address of the remote end (as seen by the target)
+    #[must_use]
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    #[must_use]
+    pub fn inner(&self) -> &V1Transport {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut V1Transport {
+        &mut self.inner
+    }
+}
+
+impl Transport for I2pSamTransport {
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        self.inner.send(message)
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        self.inner.receive()
+    }
+
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        self.inner.try_receive()
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.inner.local_addr()
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.inner.send_raw(bytes)
+    }
+
+    fn encode_frame(&self, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        self.inner.encode_frame(message)
+    }
+
+    fn send_batch(&mut self, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+        self.inner.send_batch(messages)
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        self.inner.disconnect_error()
+    }
+}
+
+/// What a socket opened by the target to the SAM bridge is used for
+enum SamSocket {
+    /// Control socket of a session (the session ends when it is closed)
+    Session,
+    /// The target waits for an incoming stream (`STREAM ACCEPT`)
+    Accept,
+    /// The target opened a stream to the given peer (`STREAM CONNECT`)
+    Connect(String),
+}
+
+/// A SAM request, e.g. `STREAM ACCEPT ID=session SILENT=false`
+struct SamRequest {
+    command: String,
+    options: HashMap<String, String>,
+}
+
+impl SamRequest {
+    fn parse(line: &str) -> Self {
+        let mut words = line.split_whitespace();
+        let command = words.by_ref().take(2).collect::<Vec<_>>().join(" ");
+        let options = words
+            .filter_map(|word| word.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Self { command, options }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+}
+
+/// Minimal I2P SAM v3.1 bridge for targets running with `-i2psam=<bridge address>`.
+///
+/// The bridge plays the I2P router: it creates sessions for the target, hands incoming streams
+/// to the target's pending `STREAM ACCEPT`s (`accept_inbound`) and accepts the streams the
+/// target opens with `STREAM CONNECT` (`accept_outbound`). This reaches the target's I2P session
+/// handling and I2P address code, which plain TCP connections never touch.
+pub struct SamBridge {
+    listener: net::TcpListener,
+    chain_params: ChainParams,
+    /// Control sockets of the sessions created by the target
+    sessions: Vec<net::TcpStream>,
+    pending_accepts: VecDeque<net::TcpStream>,
+    pending_connects: VecDeque<I2pSamTransport>,
+    /// Destinations resolvable with `NAMING LOOKUP`, by b32 address
+    destinations: HashMap<String, I2pDestination>,
+    /// Number of destinations generated for the target's sessions so far
+    generated: u64,
+}
+
+impl SamBridge {
+    pub fn bind(addr: net::SocketAddr, chain_params: &ChainParams) -> Result<Self, String> {
+        let listener =
+            net::TcpListener::bind(addr).map_err(|e| format!("Failed to bind SAM bridge: {e}"))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure SAM bridge: {e}"))?;
+
+        Ok(Self {
+            listener,
+            chain_params: chain_params.clone(),
+            sessions: Vec::new(),
+            pending_accepts: VecDeque::new(),
+            pending_connects: VecDeque::new(),
+            destinations: HashMap::new(),
+            generated: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.listener
+            .local_addr()
+            .map_err(|e| format!("Failed to get SAM bridge address: {e}"))
+    }
+
+    /// Make `destination` resolvable through `NAMING LOOKUP` (by its b32 address), which the
+    /// target does before connecting to an I2P address.
+    pub fn add_destination(&mut self, destination: I2pDestination) {
+        self.destinations
+            .insert(destination.b32_address(), destination);
+    }
+
+    /// Open an inbound I2P connection from `peer` to the target, by handing it to one of the
+    /// target's pending `STREAM ACCEPT`s.
+    pub fn accept_inbound(&mut self, peer: &I2pDestination) -> Result<I2pSamTransport, String> {
+        let mut socket = self.wait_for(|bridge| bridge.pending_accepts.pop_front())?;
+        // SAM 3.1 announces the peer's destination before the stream data
+        write_line(&mut socket, &peer.to_base64())?;
+
+        log::debug!(
+            "accepted inbound i2p connection from {}",
+            peer.b32_address()
+        );
+
+        Ok(I2pSamTransport {
+            inner: V1Transport::with_chain_params(socket, &self.chain_params),
+            peer: peer.b32_address(),
+        })
+    }
+
+    /// Wait for the target to open an outbound I2P connection (`STREAM CONNECT`).
+    pub fn accept_outbound(&mut self) -> Result<I2pSamTransport, String> {
+        self.wait_for(|bridge| bridge.pending_connects.pop_front())
+    }
+
+    fn wait_for<R>(&mut self, mut take: impl FnMut(&mut Self) -> Option<R>) -> Result<R, String> {
+        let start = Instant::now();
+        loop {
+            if let Some(result) = take(self) {
+                return Ok(result);
+            }
+            if start.elapsed() > SAM_TIMEOUT {
+                return Err("Timed out waiting for SAM requests from the target".to_string());
+            }
+            if !self.poll()? {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// Service the target's SAM requests, returning whether there were any.
+    fn poll(&mut self) -> Result<bool, String> {
+        let mut progress = false;
+
+        // Requests on established sessions (e.g. `NAMING LOOKUP`)
+        for mut socket in std::mem::take(&mut self.sessions) {
+            match readable(&socket) {
+                Ok(true) => {
+                    progress = true;
+                    self.handle_request(&mut socket)?;
+                    self.sessions.push(socket);
+                }
+                Ok(false) => self.sessions.push(socket),
+                Err(e) => log::debug!("i2p session ended: {e}"),
+            }
+        }
+
+        match self.listener.accept() {
+            Ok((socket, _)) => {
+                socket
+                    .set_nonblocking(false)
+                    .and_then(|()| socket.set_nodelay(true))
+                    .map_err(|e| format!("Failed to configure SAM socket: {e}"))?;
+                self.handle_socket(socket)?;
+                progress = true;
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(format!("Failed to accept SAM connection: {e}")),
+        }
+
+        Ok(progress)
+    }
+
+    /// Handle the requests on a new socket until it is clear what it is used for.
+    fn handle_socket(&mut self, mut socket: net::TcpStream) -> Result<(), String> {
+        loop {
+            match self.handle_request(&mut socket)? {
+                None => {}
+                Some(SamSocket::Session) => {
+                    self.sessions.push(socket);
+                    return Ok(());
+                }
+                Some(SamSocket::Accept) => {
+                    self.pending_accepts.push_back(socket);
+                    return Ok(());
+                }
+                Some(SamSocket::Connect(peer)) => {
+                    log::debug!("accepted outbound i2p connection to {peer}");
+                    self.pending_connects.push_back(I2pSamTransport {
+                        inner: V1Transport::with_chain_params(socket, &self.chain_params),
+                        peer,
+                    });
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn handle_request(&mut self, socket: &mut net::TcpStream) -> Result<Option<SamSocket>, String> {
+        let line = read_line(socket)?;
+        log::trace!("SAM request: {line}");
+
+        let request = SamRequest::parse(&line);
+        let (reply, role) = match request.command.as_str() {
+            "HELLO VERSION" => ("HELLO REPLY RESULT=OK VERSION=3.1".to_string(), None),
+            "DEST GENERATE" => {
+                let destination = self.generate_destination();
+                (
+                    format!(
+                        "DEST REPLY PUB={} PRIV={}",
+                        destination.to_base64(),
+                        destination.private_key()
+                    ),
+                    None,
+                )
+            }
+            "SESSION CREATE" => {
+                let private_key = match request.get("DESTINATION") {
+                    Some("TRANSIENT") | None => self.generate_destination().private_key(),
+                    Some(private_key) => private_key.to_string(),
+                };
+                (
+                    format!("SESSION STATUS RESULT=OK DESTINATION={private_key}"),
+                    Some(SamSocket::Session),
+                )
+            }
+            "NAMING LOOKUP" => {
+                let name = request.get("NAME").unwrap_or_default();
+                let reply = match self.destinations.get(name) {
+                    Some(destination) => format!(
+                        "NAMING REPLY RESULT=OK NAME={name} VALUE={}",
+                        destination.to_base64()
+                    ),
+                    None => format!("NAMING REPLY RESULT=KEY_NOT_FOUND NAME={name}"),
+                };
+                (reply, None)
+            }
+            "STREAM ACCEPT" => (
+                "STREAM STATUS RESULT=OK".to_string(),
+                Some(SamSocket::Accept),
+            ),
+            "STREAM CONNECT" => {
+                let destination = request.get("DESTINATION").unwrap_or_default();
+                let peer = I2pDestination::from_base64(destination)
+                    .map_or_else(|_| destination.to_string(), |d| d.b32_address());
+                (
+                    "STREAM STATUS RESULT=OK".to_string(),
+                    Some(SamSocket::Connect(peer)),
+                )
+            }
+            _ => return Err(format!("Unsupported SAM request: {line}")),
+        };
+
+        write_line(socket, &reply)?;
+        Ok(role)
+    }
+
+    fn generate_destination(&mut self) -> I2pDestination {
+        self.generated += 1;
+        I2pDestination::from_seed(u64::MAX - self.generated)
+    }
+}
+
+/// Check whether `socket` has data to read, failing if it was closed.
+fn readable(socket: &net::TcpStream) -> Result<bool, String> {
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to poll SAM socket: {e}"))?;
+    let result = socket.peek(&mut [0u8; 1]);
+    socket
+        .set_nonblocking(false)
+        .map_err(|e| format!("Failed to poll SAM socket: {e}"))?;
+
+    match result {
+        Ok(0) => Err("Connection closed by peer".to_string()),
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+        Err(e) => Err(format!("Failed to poll SAM socket: {e}")),
+    }
+}
+
+/// Read a newline terminated SAM line (byte by byte, to not consume any stream data following it)
+fn read_line(socket: &mut net::TcpStream) -> Result<String, String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        socket
+            .read_exact(&mut byte)
+            .map_err(|e| format!("Failed to read SAM line: {e}"))?;
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+        if line.len() > MAX_SAM_LINE_LEN {
+            return Err("SAM line too long".to_string());
+        }
+    }
+    Ok(String::from_utf8_lossy(&line)
+        .trim_end_matches('\r')
+        .to_string())
+}
+
+fn write_line(socket: &mut net::TcpStream, line: &str) -> Result<(), String> {
+    socket
+        .write_all(format!("{line}\n").as_bytes())
+        .map_err(|e| format!("Failed to write SAM line: {e}"))
+}
+
+/// Encode `bytes` with `bits_per_char` bits per character of `alphabet` (without padding)
+fn encode_bits(bytes: &[u8], alphabet: &[u8], bits_per_char: usize) -> String {
+    let mask = (1 << bits_per_char) - 1;
+    let mut encoded = String::with_capacity(bytes.len() * 8 / bits_per_char + 1);
+    let mut acc = 0usize;
+    let mut bits = 0;
+    for byte in bytes {
+        acc = ((acc << 8) | usize::from(*byte)) & 0xffff;
+        bits += 8;
+        while bits >= bits_per_char {
+            bits -= bits_per_char;
+            encoded.push(char::from(alphabet[(acc >> bits) & mask]));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(alphabet[(acc << (bits_per_char - bits)) & mask]));
+    }
+    encoded
+}
+
+/// Base64 with I2P's alphabet (`-` and `~` instead of `+` and `/`)
+fn encode_i2p_base64(bytes: &[u8]) -> String {
+    let mut encoded = encode_bits(bytes, I2P_BASE64_ALPHABET, 6);
+    while !encoded.len().is_multiple_of(4) {
+        encoded.push('=');
+    }
+    encoded
+}
+
+fn decode_i2p_base64(encoded: &str) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut acc = 0usize;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').bytes() {
+        let value = I2P_BASE64_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| format!("Invalid I2P base64 character: {:?}", char::from(c)))?;
+        acc = ((acc << 6) | value) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push(u8::try_from((acc >> bits) & 0xff).unwrap());
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridges_inbound_stream() {
+        let chain_params = ChainParams::regtest();
+        let mut bridge = SamBridge::bind(([127, 0, 0, 1], 0).into(), &chain_params).unwrap();
+        let bridge_addr = bridge.local_addr().unwrap();
+        let peer = I2pDestination::from_seed(1);
+        let ping = ("ping".to_string(), vec![7u8; 8]);
+
+        // Play the target: create a session and wait for an incoming stream
+        let target = std::thread::spawn(move || {
+            let request = |socket: &mut net::TcpStream, line: &str| {
+                write_line(socket, line).unwrap();
+                read_line(socket).unwrap()
+            };
+
+            let mut control = net::TcpStream::connect(bridge_addr).unwrap();
+            request(&mut control, "HELLO VERSION MIN=3.1 MAX=3.1");
+            let reply = request(
+                &mut control,
+                "SESSION CREATE STYLE=STREAM ID=test DESTINATION=TRANSIENT SIGNATURE_TYPE=7",
+            );
+            assert!(reply.starts_with("SESSION STATUS RESULT=OK DESTINATION="));
+
+            let mut stream = net::TcpStream::connect(bridge_addr).unwrap();
+            request(&mut stream, "HELLO VERSION MIN=3.1 MAX=3.1");
+            let reply = request(&mut stream, "STREAM ACCEPT ID=test SILENT=false");
+            assert_eq!(reply, "STREAM STATUS RESULT=OK");
+
+            let peer_destination = read_line(&mut stream).unwrap();
+            let mut transport = V1Transport::with_chain_params(stream, &ChainParams::regtest());
+            (peer_destination, transport.receive().unwrap(), control)
+        });
+
+        let mut transport = bridge.accept_inbound(&peer).unwrap();
+        assert_eq!(transport.peer(), peer.b32_address());
+        transport.send(&ping).unwrap();
+
+        let (peer_destination, received, _control) = target.join().unwrap();
+        assert_eq!(
+            I2pDestination::from_base64(&peer_destination).unwrap(),
+            peer
+        );
+        assert_eq!(received, ping);
+        assert_eq!(peer.b32_address().len(), 52 + ".b32.i2p".len());
+    }
+}
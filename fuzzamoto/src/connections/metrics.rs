@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+/// Number of messages and payload bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MessageCounters {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+impl MessageCounters {
+    fn add(&mut self, other: &MessageCounters) {
+        self.messages += other.messages;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Traffic sent and received on one or more connections, per message type.
+///
+/// Byte counts only include message payloads, except for raw frames which are counted in full.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TrafficMetrics {
+    pub sent: BTreeMap<String, MessageCounters>,
+    pub received: BTreeMap<String, MessageCounters>,
+    /// Raw frames sent with `send_raw`
+    pub sent_raw: MessageCounters,
+}
+
+impl TrafficMetrics {
+    pub fn record_sent(&mut self, command: &str, payload_len: usize) {
+        Self::record(&mut self.sent, command, payload_len);
+    }
+
+    pub fn record_received(&mut self, command: &str, payload_len: usize) {
+        Self::record(&mut self.received, command, payload_len);
+    }
+
+    pub fn record_sent_raw(&mut self, frame_len: usize) {
+        self.sent_raw.add(&MessageCounters {
+            messages: 1,
+            bytes: frame_len as u64,
+        });
+    }
+
+    fn record(counters: &mut BTreeMap<String, MessageCounters>, command: &str, len: usize) {
+        counters
+            .entry(command.to_string())
+            .or_default()
+            .add(&MessageCounters {
+                messages: 1,
+                bytes: len as u64,
+            });
+    }
+
+    /// Add the counters of `other` to these metrics.
+    pub fn merge(&mut self, other: &TrafficMetrics) {
+        for (command, counters) in &other.sent {
+            self.sent.entry(command.clone()).or_default().add(counters);
+        }
+        for (command, counters) in &other.received {
+            self.received
+                .entry(command.clone())
+                .or_default()
+                .add(counters);
+        }
+        self.sent_raw.add(&other.sent_raw);
+    }
+
+    /// Totals over all sent messages (including raw frames)
+    #[must_use]
+    pub fn total_sent(&self) -> MessageCounters {
+        let mut total = self.sent_raw;
+        self.sent.values().for_each(|counters| total.add(counters));
+        total
+    }
+
+    /// Totals over all received messages
+    #[must_use]
+    pub fn total_received(&self) -> MessageCounters {
+        let mut total = MessageCounters::default();
+        self.received
+            .values()
+            .for_each(|counters| total.add(counters));
+        total
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net;
+
+use bitcoin::p2p::Magic;
+
+use crate::chain::ChainParams;
+use crate::connections::{Transport, encode_p2p_message};
+
+type Responder = Box<dyn FnMut(&(String, Vec<u8>)) -> Vec<(String, Vec<u8>)>>;
+
+/// In-memory transport for testing `Connection`, handshake and scenario logic without a real
+/// node.
+///
+/// All sent messages (and raw frames) are recorded for inspection. Received messages are
+/// scripted, either by queueing them up front (`queue_response`) or by registering responders
+/// that produce replies for each sent message (`on_send`). Receiving fails once no scripted
+/// message is left, instead of blocking forever.
+pub struct MockTransport {
+    local_addr: net::SocketAddr,
+    magic: Magic,
+    sent: VecDeque<(String, Vec<u8>)>,
+    sent_raw: VecDeque<Vec<u8>>,
+    incoming: VecDeque<(String, Vec<u8>)>,
+    responders: Vec<Responder>,
+    /// Simulated I/O error after which sending and receiving fail (see `disconnect`)
+    disconnect_error: Option<ErrorKind>,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockTransport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            local_addr: net::SocketAddr::from(([127, 0, 0, 1], 0)),
+            magic: ChainParams::regtest().magic,
+            sent: VecDeque::new(),
+            sent_raw: VecDeque::new(),
+            incoming: VecDeque::new(),
+            responders: Vec::new(),
+            disconnect_error: None,
+        }
+    }
+
+    /// Create a mock transport that answers the version handshake (with a version and verack)
+    /// and replies to each ping with a matching pong.
+    #[must_use]
+    pub fn with_peer_behaviour() -> Self {
+        let mut transport = Self::new();
+        transport.on_send(|(command, payload)| match command.as_str() {
+            "version" => vec![
+                ("version".to_string(), payload.clone()),
+                ("verack".to_string(), vec![]),
+            ],
+            "ping" => vec![("pong".to_string(), payload.clone())],
+            _ => vec![],
+        });
+        transport
+    }
+
+    /// Queue a message to be received.
+    pub fn queue_response(&mut self, message: (String, Vec<u8>)) {
+        self.incoming.push_back(message);
+    }
+
+    /// Register a responder that is called for every sent message, the returned messages are
+    /// queued to be received.
+    pub fn on_send<F>(&mut self, responder: F)
+    where
+        F: FnMut(&(String, Vec<u8>)) -> Vec<(String, Vec<u8>)> + 'static,
+    {
+        self.responders.push(Box::new(responder));
+    }
+
+    /// Messages sent so far (oldest first)
+    #[must_use]
+    pub fn sent(&self) -> &VecDeque<(String, Vec<u8>)> {
+        &self.sent
+    }
+
+    /// Take all messages sent so far.
+    pub fn take_sent(&mut self) -> Vec<(String, Vec<u8>)> {
+        self.sent.drain(..).collect()
+    }
+
+    /// Raw frames sent so far (oldest first)
+    #[must_use]
+    pub fn sent_raw(&self) -> &VecDeque<Vec<u8>> {
+        &self.sent_raw
+    }
+
+    /// Simulate the connection being gone: sending and receiving fail from now on, with `kind`
+    /// as the transport's disconnect error (see `Transport::disconnect_error`).
+    pub fn disconnect(&mut self, kind: ErrorKind) {
+        self.disconnect_error = Some(kind);
+    }
+
+    fn check_connected(&self) -> Result<(), String> {
+        match self.disconnect_error {
+            Some(kind) => Err(format!("Mock connection disconnected: {kind}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of scripted messages that have not been received yet
+    #[must_use]
+    pub fn pending_responses(&self) -> usize {
+        self.incoming.len()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        self.check_connected()?;
+        for responder in &mut self.responders {
+            self.incoming.extend(responder(message));
+        }
+        self.sent.push_back(message.clone());
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        self.check_connected()?;
+        self.incoming
+            .pop_front()
+            .ok_or_else(|| "No scripted message left to receive".to_string())
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        Ok(self.local_addr)
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.check_connected()?;
+        self.sent_raw.push_back(bytes.to_vec());
+        Ok(())
+    }
+
+    fn encode_frame(&self, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        encode_p2p_message(self.magic, message)
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        self.disconnect_error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::{Connection, ConnectionType, HandshakeOpts};
+
+    #[test]
+    fn handshake_and_ping() {
+        let mut connection = Connection::new(
+            ConnectionType::Inbound,
+            MockTransport::with_peer_behaviour(),
+        );
+        connection
+            .version_handshake(HandshakeOpts::default())
+            .unwrap();
+        connection.ping().unwrap();
+
+        let sent: Vec<_> = connection
+            .transport()
+            .sent()
+            .iter()
+            .map(|(command, _)| command.as_str())
+            .collect();
+        assert_eq!(
+            sent,
+            ["version", "wtxidrelay", "sendaddrv2", "verack", "ping"]
+        );
+    }
+
+    #[test]
+    fn capture_after_handshake_is_opt_in() {
+        let mut connection = Connection::new(
+            ConnectionType::Inbound,
+            MockTransport::with_peer_behaviour(),
+        );
+        connection
+            .version_handshake(HandshakeOpts::default())
+            .unwrap();
+        let captured: Vec<_> = connection
+            .take_captured()
+            .into_iter()
+            .map(|(command, _)| command)
+            .collect();
+        assert_eq!(captured, ["version"]);
+
+        connection
+            .transport_mut()
+            .queue_response(("inv".to_string(), vec![0]));
+        connection.ping().unwrap();
+        assert!(connection.take_captured().is_empty());
+
+        connection.enable_capture();
+        connection
+            .transport_mut()
+            .queue_response(("inv".to_string(), vec![1]));
+        connection.ping().unwrap();
+        assert_eq!(
+            connection.take_captured(),
+            vec![("inv".to_string(), vec![1])]
+        );
+    }
+
+    #[test]
+    fn receive_until_captures_other_messages() {
+        let mut transport = MockTransport::new();
+        transport.queue_response(("inv".to_string(), vec![0]));
+        transport.queue_response(("addr".to_string(), vec![0]));
+        transport.queue_response(("headers".to_string(), vec![1]));
+
+        let mut connection = Connection::new(ConnectionType::Inbound, transport);
+        let received = connection.receive_until("headers", None).unwrap();
+        assert_eq!(received, ("headers".to_string(), vec![1]));
+        assert_eq!(
+            connection.take_captured(),
+            vec![("inv".to_string(), vec![0]), ("addr".to_string(), vec![0])]
+        );
+
+        // Nothing left to receive
+        assert!(connection.receive_until("headers", None).is_err());
+    }
+}
@@ -0,0 +1,1498 @@
+use bitcoin::consensus::encode::{Encodable, ReadExt};
+use bitcoin::p2p::{Magic, ServiceFlags, address::Address, message_network::VersionMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufReader, BufWriter, ErrorKind, IoSlice, Read, Write};
+use std::time::{Duration, Instant};
+
+use std::net;
+
+use crate::chain::ChainParams;
+use crate::trace::{self, TraceEvent};
+use crate::transcript::{Direction, Transcript, TranscriptEntry};
+
+mod chaos;
+mod i2p;
+mod metrics;
+mod mock;
+mod pool;
+pub use chaos::*;
+pub use i2p::*;
+pub use metrics::*;
+pub use mock::*;
+pub use pool::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionType {
+    Inbound,
+    Outbound,
+}
+
+/// Version of the p2p transport protocol spoken by a `Transport`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+pub trait Transport {
+    /// Version of the p2p transport protocol of this transport. The default is v1, which all
+    /// transports wrapping a `V1Transport` speak.
+    fn version(&self) -> TransportVersion {
+        TransportVersion::V1
+    }
+
+    /// Send a message to the target node
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String>;
+
+    /// Send multiple messages to the target node
+    ///
+    /// The default implementation sends the messages one by one, transports may override it to
+    /// write all messages at once.
+    fn send_batch(&mut self, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+        messages.iter().try_for_each(|message| self.send(message))
+    }
+
+    /// Receive a message from the target node
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String>;
+
+    /// Try to receive a message from the target node, returning `Ok(None)` if no complete message
+    /// is available (e.g. because the transport's read timeout expired).
+    ///
+    /// The default implementation blocks until a message is received.
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        self.receive().map(Some)
+    }
+
+    /// Get the local address of the transport
+    fn local_addr(&self) -> Result<net::SocketAddr, String>;
+
+    /// Send raw bytes to the target node, bypassing message framing. This allows sending
+    /// deliberately malformed frames (e.g. wrong length fields, bad checksums or truncated
+    /// headers), which can't be expressed with `send`. Use `encode_p2p_message` to create a
+    /// valid frame to start from.
+    ///
+    /// The default implementation fails, as not every transport can put arbitrary bytes on the
+    /// wire (e.g. v2 transports encrypt all frames).
+    fn send_raw(&mut self, _bytes: &[u8]) -> Result<(), String> {
+        Err("Transport does not support sending raw frames".to_string())
+    }
+
+    /// Encode a message into the frame that `send` would put on the wire, such that it can be
+    /// sent in pieces using `send_raw`.
+    ///
+    /// The default implementation fails, see `send_raw`.
+    fn encode_frame(&self, _message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        Err("Transport does not support encoding raw frames".to_string())
+    }
+
+    /// Kind of the last I/O error that indicates the connection is gone, if any (e.g.
+    /// `ErrorKind::UnexpectedEof` after the target closed it), from which disconnects are
+    /// classified (see `DisconnectReason::from_error_kind`).
+    ///
+    /// The default implementation reports none.
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        None
+    }
+}
+
+/// Whether `kind` indicates that the connection is gone (as opposed to e.g. a timeout)
+fn is_disconnect(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::UnexpectedEof
+            | ErrorKind::WriteZero
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+    )
+}
+
+/// Check the state of `socket` for a disconnect without consuming any data, for transports that
+/// don't get to see the I/O errors of their socket.
+fn socket_disconnect_error(socket: &net::TcpStream) -> Option<ErrorKind> {
+    socket.set_nonblocking(true).ok()?;
+    let result = socket.peek(&mut [0u8; 1]);
+    let _ = socket.set_nonblocking(false);
+    match result {
+        Ok(0) => Some(ErrorKind::UnexpectedEof),
+        Ok(_) => None,
+        Err(e) => Some(e.kind()).filter(|kind| is_disconnect(*kind)),
+    }
+}
+
+/// Encode a message as a v1 p2p frame (24 byte header followed by the payload).
+pub fn encode_p2p_message(magic: Magic, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+    if message.0.len() > 12 {
+        return Err(format!("Message type too long: {:?}", message.0));
+    }
+
+    let mut frame = Vec::with_capacity(V1_HEADER_LEN + message.1.len());
+    frame.extend_from_slice(&magic.to_bytes());
+
+    // Command (12 bytes, null-padded)
+    let mut command_bytes = [0u8; 12];
+    command_bytes[..message.0.len()].copy_from_slice(message.0.as_bytes());
+    frame.extend_from_slice(&command_bytes);
+
+    frame.extend_from_slice(
+        &u32::try_from(message.1.len())
+            .map_err(|_| "Failed to convert message len to u32")?
+            .to_le_bytes(),
+    );
+    frame.extend_from_slice(&checksum(&message.1));
+    frame.extend_from_slice(&message.1);
+
+    Ok(frame)
+}
+
+const V1_HEADER_LEN: usize = 24;
+
+/// Framing errors detected by a `V1Transport` in strict mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramingError {
+    /// The message started with unexpected network magic
+    BadMagic {
+        expected: [u8; 4],
+        received: [u8; 4],
+    },
+    /// The checksum in the header does not match the payload
+    BadChecksum {
+        command: String,
+        expected: [u8; 4],
+        received: [u8; 4],
+    },
+}
+
+impl std::fmt::Display for FramingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramingError::BadMagic { expected, received } => write!(
+                f,
+                "Framing error: bad magic (expected={expected:02x?} received={received:02x?})"
+            ),
+            FramingError::BadChecksum {
+                command,
+                expected,
+                received,
+            } => write!(
+                f,
+                "Framing error: bad checksum for {command:?} message (expected={expected:02x?} received={received:02x?})"
+            ),
+        }
+    }
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let mut hasher = bitcoin_hashes::sha256d::HashEngine::default();
+    hasher.write_all(payload).unwrap();
+    let hash = bitcoin_hashes::Sha256d::from_engine(hasher);
+
+    let mut checksum = [0u8; 4];
+    checksum.copy_from_slice(&hash.as_byte_array()[0..4]);
+    checksum
+}
+
+/// Header of a v1 p2p message
+pub(crate) struct V1Header {
+    pub magic: [u8; 4],
+    pub command: String,
+    pub payload_len: u32,
+    pub checksum: [u8; 4],
+}
+
+impl V1Header {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut cursor = std::io::Cursor::new(bytes);
+
+        let mut magic = [0u8; 4];
+        cursor
+            .read_exact(&mut magic)
+            .map_err(|e| format!("Failed to read magic: {e}"))?;
+
+        // Read command (12 bytes, null-padded)
+        let mut command = [0u8; 12];
+        cursor
+            .read_exact(&mut command)
+            .map_err(|e| format!("Failed to read command: {e}"))?;
+
+        // Convert command to string, trimming null bytes
+        let command = String::from_utf8_lossy(&command)
+            .trim_matches(char::from(0))
+            .to_string();
+
+        // Read payload length
+        let payload_len = cursor
+            .read_u32()
+            .map_err(|e| format!("Failed to read payload length: {e}"))?;
+
+        let mut checksum = [0u8; 4];
+        cursor
+            .read_exact(&mut checksum)
+            .map_err(|e| format!("Failed to read checksum: {e}"))?;
+
+        Ok(Self {
+            magic,
+            command,
+            payload_len,
+            checksum,
+        })
+    }
+}
+
+pub struct V1Transport {
+    pub socket: net::TcpStream,
+    /// Network magic used for sent messages (and expected for received messages in strict mode)
+    magic: Magic,
+    /// Bytes read from the socket that do not form a complete message yet
+    recv_buffer: Vec<u8>,
+    /// Whether to validate the magic and checksum of received messages
+    strict: bool,
+    /// The most recent framing error detected in strict mode
+    framing_error: Option<FramingError>,
+    /// The I/O error that showed the connection to be gone (see `Transport::disconnect_error`)
+    disconnect_error: Option<ErrorKind>,
+}
+
+impl V1Transport {
+    /// Create a new `V1Transport` for the regtest chain.
+    #[must_use]
+    pub fn new(socket: net::TcpStream) -> Self {
+        Self::with_chain_params(socket, &ChainParams::regtest())
+    }
+
+    /// Create a new `V1Transport` using the network magic of the given chain.
+    #[must_use]
+    pub fn with_chain_params(socket: net::TcpStream, chain_params: &ChainParams) -> Self {
+        Self {
+            socket,
+            magic: chain_params.magic,
+            recv_buffer: Vec::new(),
+            strict: false,
+            framing_error: None,
+            disconnect_error: None,
+        }
+    }
+
+    /// Enable or disable strict mode. In strict mode the magic and checksum of received messages
+    /// are validated and mismatches are reported as errors, otherwise they are ignored.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns the most recent framing error detected in strict mode, which allows callers to
+    /// distinguish corrupted target output from other receive failures.
+    #[must_use]
+    pub fn framing_error(&self) -> Option<&FramingError> {
+        self.framing_error.as_ref()
+    }
+
+    /// Configure the read and write timeouts of the underlying socket (`None` blocks forever).
+    ///
+    /// With a read timeout set, `receive` fails and `try_receive` returns `Ok(None)` if no
+    /// complete message arrives in time.
+    pub fn set_timeouts(
+        &self,
+        read: Option<Duration>,
+        write: Option<Duration>,
+    ) -> Result<(), String> {
+        self.socket
+            .set_read_timeout(read)
+            .map_err(|e| format!("Failed to set read timeout: {e}"))?;
+        self.socket
+            .set_write_timeout(write)
+            .map_err(|e| format!("Failed to set write timeout: {e}"))
+    }
+
+    /// Format an I/O error as `context: error`, remembering it if it indicates a disconnect.
+    fn io_error(&mut self, context: &str, error: &std::io::Error) -> String {
+        if is_disconnect(error.kind()) {
+            self.disconnect_error = Some(error.kind());
+        }
+        format!("{context}: {error}")
+    }
+
+    /// Read more bytes from the socket into the receive buffer. Returns `Ok(false)` if the read
+    /// timed out.
+    fn fill_buffer(&mut self) -> Result<bool, String> {
+        const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+        let buffered = self.recv_buffer.len();
+        self.recv_buffer.resize(buffered + READ_CHUNK_SIZE, 0);
+        let result = self.socket.read(&mut self.recv_buffer[buffered..]);
+        self.recv_buffer
+            .truncate(buffered + *result.as_ref().unwrap_or(&0));
+
+        match result {
+            Ok(0) => {
+                self.disconnect_error = Some(ErrorKind::UnexpectedEof);
+                Err("Connection closed by peer".to_string())
+            }
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(false),
+            Err(e) if e.kind() == ErrorKind::Interrupted => Ok(true),
+            Err(e) => Err(self.io_error("Failed to read from socket", &e)),
+        }
+    }
+
+    /// Take the next complete message out of the receive buffer, if there is one.
+    fn take_buffered_message(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        if self.recv_buffer.len() < V1_HEADER_LEN {
+            return Ok(None);
+        }
+
+        let V1Header {
+            magic,
+            command,
+            payload_len,
+            checksum: header_checksum,
+        } = V1Header::parse(&self.recv_buffer[..V1_HEADER_LEN])?;
+
+        let expected_magic = self.magic.to_bytes();
+        if self.strict && magic != expected_magic {
+            let error = FramingError::BadMagic {
+                expected: expected_magic,
+                received: magic,
+            };
+            let msg = error.to_string();
+            self.framing_error = Some(error);
+            return Err(msg);
+        }
+
+        let message_len = V1_HEADER_LEN + payload_len as usize;
+        if self.recv_buffer.len() < message_len {
+            return Ok(None);
+        }
+
+        let payload = self.recv_buffer[V1_HEADER_LEN..message_len].to_vec();
+        self.recv_buffer.drain(..message_len);
+
+        if self.strict {
+            let expected_checksum = checksum(&payload);
+            if header_checksum != expected_checksum {
+                let error = FramingError::BadChecksum {
+                    command,
+                    expected: expected_checksum,
+                    received: header_checksum,
+                };
+                let msg = error.to_string();
+                self.framing_error = Some(error);
+                return Err(msg);
+            }
+        }
+
+        log::debug!(
+            "received {:?} message (len={} on={:?})",
+            command,
+            payload_len,
+            self.socket.local_addr().unwrap(),
+        );
+
+        Ok(Some((command, payload)))
+    }
+}
+
+impl Transport for V1Transport {
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        log::debug!(
+            "send {:?} message (len={} from={:?})",
+            message.0,
+            message.1.len(),
+            self.socket.local_addr().unwrap(),
+        );
+
+        let frame = encode_p2p_message(self.magic, message)?;
+        self.socket
+            .write_all(&frame)
+            .map_err(|e| self.io_error("Failed to send message", &e))
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        log::debug!(
+            "send raw frame (len={} from={:?})",
+            bytes.len(),
+            self.socket.local_addr().unwrap(),
+        );
+
+        self.socket
+            .write_all(bytes)
+            .map_err(|e| self.io_error("Failed to send raw frame", &e))
+    }
+
+    fn encode_frame(&self, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        encode_p2p_message(self.magic, message)
+    }
+
+    fn send_batch(&mut self, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+        log::debug!(
+            "send batch of {} messages (from={:?})",
+            messages.len(),
+            self.socket.local_addr().unwrap(),
+        );
+
+        let frames = messages
+            .iter()
+            .map(|message| encode_p2p_message(self.magic, message))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Write all frames with as few (vectored) writes as possible
+        let mut slices: Vec<_> = frames.iter().map(|frame| IoSlice::new(frame)).collect();
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            match self.socket.write_vectored(slices) {
+                Ok(0) => {
+                    self.disconnect_error = Some(ErrorKind::WriteZero);
+                    return Err("Failed to send batch: connection closed".to_string());
+                }
+                Ok(n) => IoSlice::advance_slices(&mut slices, n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(self.io_error("Failed to send batch", &e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        loop {
+            if let Some(message) = self.take_buffered_message()? {
+                return Ok(message);
+            }
+
+            if !self.fill_buffer()? {
+                return Err("Timed out waiting for message".to_string());
+            }
+        }
+    }
+
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        if let Some(message) = self.take_buffered_message()? {
+            return Ok(Some(message));
+        }
+
+        self.fill_buffer()?;
+        self.take_buffered_message()
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.socket
+            .local_addr()
+            .map_err(|e| format!("Failed to get local address: {e}"))
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        self.disconnect_error
+    }
+}
+
+pub struct V2Transport {
+    socket: net::TcpStream,
+    proto: bip324::io::Protocol<BufReader<net::TcpStream>, BufWriter<net::TcpStream>>,
+}
+
+impl V2Transport {
+    /// Create a new `V2Transport` by performing the BIP-324 handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The TCP stream to use for the connection
+    /// * `role` - Whether we are the initiator or responder of the handshake
+    /// * `chain_params` - The chain of the target, whose magic is part of the handshake
+    pub fn new(
+        socket: net::TcpStream,
+        role: bip324::Role,
+        chain_params: &ChainParams,
+    ) -> Result<Self, String> {
+        let network = chain_params.network().ok_or_else(|| {
+            format!(
+                "BIP-324 is not supported on the custom chain {}",
+                chain_params.chain
+            )
+        })?;
+        let reader = BufReader::new(
+            socket
+                .try_clone()
+                .map_err(|e| format!("Failed to clone socket for reader: {e}"))?,
+        );
+        let writer = BufWriter::new(
+            socket
+                .try_clone()
+                .map_err(|e| format!("Failed to clone socket for writer: {e}"))?,
+        );
+
+        let proto = bip324::io::Protocol::new(
+            network, role, None, // no garbage
+            None, // no decoys
+            reader, writer,
+        )
+        .map_err(|e| format!("BIP-324 handshake failed: {e}"))?;
+
+        Ok(Self { socket, proto })
+    }
+
+    /// Convert BIP-324 short command ID to command string.
+    /// See: <https://github.com/bitcoin/bips/blob/master/bip-0324.mediawiki#user-content-v2_Bitcoin_P2P_message_structure>
+    fn short_command_id_to_string(id: u8) -> Option<String> {
+        let cmd = match id {
+            1 => "addr",
+            2 => "block",
+            3 => "blocktxn",
+            4 => "cmpctblock",
+            5 => "feefilter",
+            6 => "filteradd",
+            7 => "filterclear",
+            8 => "filterload",
+            9 => "getblocks",
+            10 => "getblocktxn",
+            11 => "getdata",
+            12 => "getheaders",
+            13 => "headers",
+            14 => "inv",
+            15 => "mempool",
+            16 => "merkleblock",
+            17 => "notfound",
+            18 => "ping",
+            19 => "pong",
+            20 => "sendcmpct",
+            21 => "tx",
+            22 => "getcfilters",
+            23 => "cfilter",
+            24 => "getcfheaders",
+            25 => "cfheaders",
+            26 => "getcfcheckpt",
+            27 => "cfcheckpt",
+            28 => "addrv2",
+            _ => return None,
+        };
+        Some(cmd.to_string())
+    }
+}
+
+impl Transport for V2Transport {
+    fn version(&self) -> TransportVersion {
+        TransportVersion::V2
+    }
+
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        log::debug!(
+            "send {:?} message (len={} from={:?})",
+            message.0,
+            message.1.len(),
+            self.socket.local_addr().unwrap(),
+        );
+        // Long format: 0x00 followed by 12-byte ASCII command, then payload
+        let mut command_bytes = [0u8; 13];
+        command_bytes[1..=message.0.len()].copy_from_slice(message.0.as_bytes());
+
+        let mut payload = Vec::with_capacity(13 + message.1.len());
+        payload.extend_from_slice(&command_bytes);
+        payload.extend_from_slice(&message.1);
+
+        self.proto
+            .write(&bip324::io::Payload::genuine(payload))
+            .map_err(|e| format!("bip324 write failed: {e}"))
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        loop {
+            let packet = self
+                .proto
+                .read()
+                .map_err(|e| format!("bip324 read failed: {e}"))?;
+
+            // Skip decoy packets
+            if packet.packet_type() == bip324::PacketType::Decoy {
+                continue;
+            }
+
+            let contents = packet.contents();
+            if contents.is_empty() {
+                return Err("Empty packet contents".to_string());
+            }
+
+            let (command, payload) = if contents[0] == 0x00 {
+                // Long format: 0x00 followed by 12-byte ASCII command, then payload
+                if contents.len() < 13 {
+                    return Err("Packet too short for long command format".to_string());
+                }
+                let cmd = String::from_utf8_lossy(&contents[1..13])
+                    .trim_matches(char::from(0))
+                    .to_string();
+                (cmd, contents[13..].to_vec())
+            } else {
+                // Short format: single byte command ID, then payload
+                // Convert to long command string
+                let cmd = V2Transport::short_command_id_to_string(contents[0])
+                    .ok_or_else(|| format!("Unknown short command ID: {}", contents[0]))?;
+                (cmd, contents[1..].to_vec())
+            };
+
+            log::debug!(
+                "received {:?} message (len={} on={:?})",
+                command,
+                payload.len(),
+                self.socket.local_addr().unwrap(),
+            );
+
+            return Ok((command, payload));
+        }
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.socket
+            .local_addr()
+            .map_err(|e| format!("local_addr: {e}"))
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        // The errors of the BIP-324 protocol don't carry the underlying I/O error
+        socket_disconnect_error(&self.socket)
+    }
+}
+
+/// Destination requested in a SOCKS5 `CONNECT` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Socks5Destination {
+    Ip(net::SocketAddr),
+    /// Domain name and port (e.g. onion or i2p addresses)
+    Domain(String, u16),
+}
+
+impl std::fmt::Display for Socks5Destination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Socks5Destination::Ip(addr) => write!(f, "{addr}"),
+            Socks5Destination::Domain(domain, port) => write!(f, "{domain}:{port}"),
+        }
+    }
+}
+
+const SOCKS5_VERSION: u8 = 0x05;
+const SOCKS5_AUTH_NONE: u8 = 0x00;
+const SOCKS5_AUTH_USERPASS: u8 = 0x02;
+const SOCKS5_AUTH_UNACCEPTABLE: u8 = 0xff;
+const SOCKS5_CMD_CONNECT: u8 = 0x01;
+const SOCKS5_ATYP_IPV4: u8 = 0x01;
+const SOCKS5_ATYP_DOMAIN: u8 = 0x03;
+const SOCKS5_ATYP_IPV6: u8 = 0x04;
+
+/// A `V1Transport` tunneled through a SOCKS5 proxy.
+///
+/// `ProxiedTransport::accept` plays the proxy for targets configured with `-proxy` (e.g. to
+/// exercise `-onlynet=onion`), while `ProxiedTransport::connect` connects to a target through an
+/// external SOCKS5 proxy.
+pub struct ProxiedTransport {
+    inner: V1Transport,
+    destination: Socks5Destination,
+    /// Username and password used to authenticate with the proxy (Tor uses these for stream
+    /// isolation)
+    credentials: Option<(String, String)>,
+}
+
+impl ProxiedTransport {
+    /// Act as the SOCKS5 proxy for a connection made by the target, by performing the server side
+    /// of the SOCKS5 handshake on `socket` and accepting the requested destination.
+    pub fn accept(mut socket: net::TcpStream, chain_params: &ChainParams) -> Result<Self, String> {
+        let map_err = |e: std::io::Error| format!("SOCKS5 handshake failed: {e}");
+
+        // Greeting: version, number of methods, methods
+        let mut greeting = [0u8; 2];
+        socket.read_exact(&mut greeting).map_err(map_err)?;
+        if greeting[0] != SOCKS5_VERSION {
+            return Err(format!("Unsupported SOCKS version: {}", greeting[0]));
+        }
+        let mut methods = vec![0u8; usize::from(greeting[1])];
+        socket.read_exact(&mut methods).map_err(map_err)?;
+
+        let method = if methods.contains(&SOCKS5_AUTH_USERPASS) {
+            SOCKS5_AUTH_USERPASS
+        } else if methods.contains(&SOCKS5_AUTH_NONE) {
+            SOCKS5_AUTH_NONE
+        } else {
+            let _ = socket.write_all(&[SOCKS5_VERSION, SOCKS5_AUTH_UNACCEPTABLE]);
+            return Err("No acceptable SOCKS5 auth method offered".to_string());
+        };
+        socket
+            .write_all(&[SOCKS5_VERSION, method])
+            .map_err(map_err)?;
+
+        // Username/password authentication (RFC 1929)
+        let credentials = if method == SOCKS5_AUTH_USERPASS {
+            let mut version = [0u8; 1];
+            socket.read_exact(&mut version).map_err(map_err)?;
+            let username = Self::read_length_prefixed(&mut socket)?;
+            let password = Self::read_length_prefixed(&mut socket)?;
+            socket.write_all(&[version[0], 0x00]).map_err(map_err)?;
+            Some((username, password))
+        } else {
+            None
+        };
+
+        // Request: version, command, reserved, address type
+        let mut request = [0u8; 4];
+        socket.read_exact(&mut request).map_err(map_err)?;
+        if request[1] != SOCKS5_CMD_CONNECT {
+            return Err(format!("Unsupported SOCKS5 command: {}", request[1]));
+        }
+        let destination = Self::read_destination(&mut socket, request[3])?;
+
+        // Reply with success and an unspecified bound address
+        socket
+            .write_all(&[
+                SOCKS5_VERSION,
+                0x00,
+                0x00,
+                SOCKS5_ATYP_IPV4,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ])
+            .map_err(map_err)?;
+
+        log::debug!("accepted proxied connection to {destination} (credentials={credentials:?})");
+
+        Ok(Self {
+            inner: V1Transport::with_chain_params(socket, chain_params),
+            destination,
+            credentials,
+        })
+    }
+
+    /// Connect to `destination` through the SOCKS5 proxy at `proxy`, optionally authenticating
+    /// with a username and password.
+    pub fn connect(
+        proxy: net::SocketAddr,
+        destination: Socks5Destination,
+        credentials: Option<(String, String)>,
+        chain_params: &ChainParams,
+    ) -> Result<Self, String> {
+        let mut socket = net::TcpStream::connect(proxy)
+            .map_err(|e| format!("Failed to connect to SOCKS5 proxy: {e}"))?;
+        let map_err = |e: std::io::Error| format!("SOCKS5 handshake failed: {e}");
+
+        let method = if credentials.is_some() {
+            SOCKS5_AUTH_USERPASS
+        } else {
+            SOCKS5_AUTH_NONE
+        };
+        socket
+            .write_all(&[SOCKS5_VERSION, 1, method])
+            .map_err(map_err)?;
+        let mut choice = [0u8; 2];
+        socket.read_exact(&mut choice).map_err(map_err)?;
+        if choice[1] != method {
+            return Err(format!("SOCKS5 proxy rejected auth method {method}"));
+        }
+
+        if let Some((username, password)) = &credentials {
+            let mut auth = vec![0x01];
+            Self::write_length_prefixed(&mut auth, username)?;
+            Self::write_length_prefixed(&mut auth, password)?;
+            socket.write_all(&auth).map_err(map_err)?;
+
+            let mut status = [0u8; 2];
+            socket.read_exact(&mut status).map_err(map_err)?;
+            if status[1] != 0x00 {
+                return Err("SOCKS5 proxy rejected credentials".to_string());
+            }
+        }
+
+        let mut request = vec![SOCKS5_VERSION, SOCKS5_CMD_CONNECT, 0x00];
+        match &destination {
+            Socks5Destination::Ip(net::SocketAddr::V4(addr)) => {
+                request.push(SOCKS5_ATYP_IPV4);
+                request.extend_from_slice(&addr.ip().octets());
+                request.extend_from_slice(&addr.port().to_be_bytes());
+            }
+            Socks5Destination::Ip(net::SocketAddr::V6(addr)) => {
+                request.push(SOCKS5_ATYP_IPV6);
+                request.extend_from_slice(&addr.ip().octets());
+                request.extend_from_slice(&addr.port().to_be_bytes());
+            }
+            Socks5Destination::Domain(domain, port) => {
+                request.push(SOCKS5_ATYP_DOMAIN);
+                Self::write_length_prefixed(&mut request, domain)?;
+                request.extend_from_slice(&port.to_be_bytes());
+            }
+        }
+        socket.write_all(&request).map_err(map_err)?;
+
+        let mut reply = [0u8; 4];
+        socket.read_exact(&mut reply).map_err(map_err)?;
+        if reply[1] != 0x00 {
+            return Err(format!("SOCKS5 connect failed with status {}", reply[1]));
+        }
+        // Skip the bound address
+        Self::read_destination(&mut socket, reply[3])?;
+
+        Ok(Self {
+            inner: V1Transport::with_chain_params(socket, chain_params),
+            destination,
+            credentials,
+        })
+    }
+
+    /// The destination requested through the proxy
+    #[must_use]
+    pub fn destination(&self) -> &Socks5Destination {
+        &self.destination
+    }
+
+    /// The credentials used to authenticate with the proxy (if any)
+    #[must_use]
+    pub fn credentials(&self) -> Option<&(String, String)> {
+        self.credentials.as_ref()
+    }
+
+    #[must_use]
+    pub fn inner(&self) -> &V1Transport {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut V1Transport {
+        &mut self.inner
+    }
+
+    fn read_length_prefixed(socket: &mut net::TcpStream) -> Result<String, String> {
+        let mut len = [0u8; 1];
+        socket
+            .read_exact(&mut len)
+            .map_err(|e| format!("SOCKS5 handshake failed: {e}"))?;
+        let mut bytes = vec![0u8; usize::from(len[0])];
+        socket
+            .read_exact(&mut bytes)
+            .map_err(|e| format!("SOCKS5 handshake failed: {e}"))?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    fn write_length_prefixed(buffer: &mut Vec<u8>, value: &str) -> Result<(), String> {
+        let len =
+            u8::try_from(value.len()).map_err(|_| format!("SOCKS5 field too long: {value}"))?;
+        buffer.push(len);
+        buffer.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn read_destination(
+        socket: &mut net::TcpStream,
+        address_type: u8,
+    ) -> Result<Socks5Destination, String> {
+        let map_err = |e: std::io::Error| format!("SOCKS5 handshake failed: {e}");
+        let read_port = |socket: &mut net::TcpStream| -> Result<u16, String> {
+            let mut port = [0u8; 2];
+            socket.read_exact(&mut port).map_err(map_err)?;
+            Ok(u16::from_be_bytes(port))
+        };
+
+        match address_type {
+            SOCKS5_ATYP_IPV4 => {
+                let mut ip = [0u8; 4];
+                socket.read_exact(&mut ip).map_err(map_err)?;
+                let port = read_port(socket)?;
+                Ok(Socks5Destination::Ip(net::SocketAddr::from((ip, port))))
+            }
+            SOCKS5_ATYP_IPV6 => {
+                let mut ip = [0u8; 16];
+                socket.read_exact(&mut ip).map_err(map_err)?;
+                let port = read_port(socket)?;
+                Ok(Socks5Destination::Ip(net::SocketAddr::from((ip, port))))
+            }
+            SOCKS5_ATYP_DOMAIN => {
+                let domain = Self::read_length_prefixed(socket)?;
+                let port = read_port(socket)?;
+                Ok(Socks5Destination::Domain(domain, port))
+            }
+            _ => Err(format!("Unsupported SOCKS5 address type: {address_type}")),
+        }
+    }
+}
+
+impl Transport for ProxiedTransport {
+    fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        self.inner.send(message)
+    }
+
+    fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        self.inner.receive()
+    }
+
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        self.inner.try_receive()
+    }
+
+    fn local_addr(&self) -> Result<net::SocketAddr, String> {
+        self.inner.local_addr()
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.inner.send_raw(bytes)
+    }
+
+    fn encode_frame(&self, message: &(String, Vec<u8>)) -> Result<Vec<u8>, String> {
+        self.inner.encode_frame(message)
+    }
+
+    fn disconnect_error(&self) -> Option<ErrorKind> {
+        self.inner.disconnect_error()
+    }
+
+    fn send_batch(&mut self, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+        self.inner.send_batch(messages)
+    }
+}
+
+/// Maximum number of messages kept in a connection's capture buffer
+pub const MAX_CAPTURED_MESSAGES: usize = 1024;
+/// Maximum number of sent messages a connection keeps for crash context (see `recent_sent`)
+pub const MAX_RECENT_SENT: usize = 8;
+
+pub struct Connection<T: Transport> {
+    connection_type: ConnectionType,
+    transport: T,
+    ping_counter: u64,
+    handshake_complete: bool,
+    pong_timeout: Option<Duration>,
+    /// Whether the target did not answer the last ping in time (see `set_pong_timeout`)
+    stalled: bool,
+    transcript: Option<Transcript>,
+    /// Messages received while waiting for a specific message (e.g. in `receive_until`)
+    captured: VecDeque<(String, Vec<u8>)>,
+    /// Whether messages are captured after the version handshake (see `enable_capture`)
+    capture: bool,
+    /// The most recent messages (and raw frames) sent on this connection, only recorded with the
+    /// `crash_context` feature
+    recent_sent: VecDeque<TranscriptEntry>,
+    /// Id of the connection in the recorded trace (see `crate::trace`), if recording
+    trace_id: Option<usize>,
+    metrics: TrafficMetrics,
+    /// Time of the first message sent since the last pong
+    unsynced_since: Option<Instant>,
+    /// Longest time from sending a batch of messages until the following pong
+    max_latency: Duration,
+}
+
+impl<T: Transport> Connection<T> {
+    /// Create a new connection to the target node from a socket.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection_type` - The type of connection to create (either inbound or outbound)
+    /// * `transport` - The transport to use for the connection
+    pub fn new(connection_type: ConnectionType, transport: T) -> Self {
+        log::debug!(
+            "new connection (type={:?} addr={:?})",
+            connection_type,
+            transport.local_addr().unwrap(),
+        );
+        let trace_id = trace::is_recording().then(|| {
+            let connection = trace::next_connection_id();
+            trace::record(TraceEvent::Connect {
+                connection,
+                connection_type: connection_type.clone(),
+                transport: transport.version(),
+            });
+            connection
+        });
+        Self {
+            connection_type,
+            transport,
+            ping_counter: 0,
+            handshake_complete: false,
+            pong_timeout: None,
+            stalled: false,
+            transcript: None,
+            captured: VecDeque::new(),
+            capture: false,
+            recent_sent: VecDeque::new(),
+            trace_id,
+            metrics: TrafficMetrics::default(),
+            unsynced_since: None,
+            max_latency: Duration::ZERO,
+        }
+    }
+
+    #[must_use]
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Consume the connection and return the underlying transport.
+    #[must_use]
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    /// Returns whether the version handshake has been completed on this connection.
+    pub fn is_handshake_complete(&self) -> bool {
+        self.handshake_complete
+    }
+
+    /// Start recording all messages sent and received on this connection.
+    pub fn enable_transcript(&mut self) -> Result<(), String> {
+        if self.transcript.is_none() {
+            self.transcript = Some(Transcript::new(self.transport.local_addr()?));
+        }
+        Ok(())
+    }
+
+    /// Returns the transcript recorded so far (if recording was enabled).
+    #[must_use]
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
+
+    /// Take the recorded transcript, which also stops the recording.
+    pub fn take_transcript(&mut self) -> Option<Transcript> {
+        self.transcript.take()
+    }
+
+    /// Traffic sent and received on this connection so far
+    #[must_use]
+    pub fn metrics(&self) -> &TrafficMetrics {
+        &self.metrics
+    }
+
+    /// Longest time the target took to process a batch of messages sent on this connection, i.e.
+    /// from sending the first message of the batch until receiving the pong that follows it
+    #[must_use]
+    pub fn max_latency(&self) -> Duration {
+        self.max_latency
+    }
+
+    /// Reset the latency returned by `max_latency`.
+    pub fn reset_max_latency(&mut self) {
+        self.max_latency = Duration::ZERO;
+    }
+
+    /// The last `MAX_RECENT_SENT` messages and raw frames sent on this connection (oldest first),
+    /// recorded regardless of whether a transcript is enabled. Only recorded with the
+    /// `crash_context` feature, empty otherwise.
+    #[must_use]
+    pub fn recent_sent(&self) -> &VecDeque<TranscriptEntry> {
+        &self.recent_sent
+    }
+
+    /// Record a message (or raw frame, with an empty `command`) in the transcript, the traffic
+    /// metrics and, if sent, the recently sent messages.
+    fn record(&mut self, direction: Direction, command: &str, payload: &[u8]) {
+        if direction != Direction::Received {
+            self.unsynced_since.get_or_insert_with(Instant::now);
+            self.record_recent(direction, command, payload);
+        }
+        match direction {
+            Direction::Sent => self.metrics.record_sent(command, payload.len()),
+            Direction::SentRaw => self.metrics.record_sent_raw(payload.len()),
+            Direction::Received => self.metrics.record_received(command, payload.len()),
+        }
+        if let Some(transcript) = &mut self.transcript {
+            transcript.record(direction, command, payload);
+        }
+    }
+
+    /// Messages that were received while waiting for another message (in `receive_until`, the
+    /// version handshake or ping/pong roundtrips) and not returned to the caller. Only the most
+    /// recent `MAX_CAPTURED_MESSAGES` messages are kept.
+    ///
+    /// Messages are always captured during the version handshake, afterwards only if capturing
+    /// was enabled (see `enable_capture`).
+    #[must_use]
+    pub fn captured(&self) -> &VecDeque<(String, Vec<u8>)> {
+        &self.captured
+    }
+
+    /// Take all captured messages, leaving the buffer empty.
+    pub fn take_captured(&mut self) -> Vec<(String, Vec<u8>)> {
+        self.captured.drain(..).collect()
+    }
+
+    /// Keep capturing messages after the version handshake (see `captured`).
+    pub fn enable_capture(&mut self) {
+        self.capture = true;
+    }
+
+    fn record_recent(&mut self, direction: Direction, command: &str, payload: &[u8]) {
+        // Copying the payload is only worth it if it's traced or kept for crash context
+        if self.trace_id.is_none() && !cfg!(feature = "crash_context") {
+            return;
+        }
+        let entry = TranscriptEntry::new(direction, command, payload);
+        #[cfg(feature = "crash_context")]
+        {
+            if self.recent_sent.len() >= MAX_RECENT_SENT {
+                self.recent_sent.pop_front();
+            }
+            self.recent_sent.push_back(entry.clone());
+        }
+        if let Some(connection) = self.trace_id {
+            trace::record(TraceEvent::Send { connection, entry });
+        }
+    }
+
+    fn capture(&mut self, message: (String, Vec<u8>)) {
+        if self.handshake_complete && !self.capture {
+            return;
+        }
+        if self.captured.len() >= MAX_CAPTURED_MESSAGES {
+            self.captured.pop_front();
+        }
+        self.captured.push_back(message);
+    }
+
+    /// Set how long to wait for a pong before giving up (`None` waits forever).
+    ///
+    /// The timeout is only checked whenever the transport's `try_receive` returns, so transports
+    /// should be configured with a read timeout for it to be effective.
+    pub fn set_pong_timeout(&mut self, timeout: Option<Duration>) {
+        self.pong_timeout = timeout;
+    }
+
+    /// Why this connection is considered dead, or `None` if it isn't (as far as we can tell).
+    #[must_use]
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        if self.stalled {
+            return Some(DisconnectReason::Stalled);
+        }
+        self.transport
+            .disconnect_error()
+            .and_then(DisconnectReason::from_error_kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HandshakeOpts {
+    pub time: i64,
+    pub relay: bool,
+    pub starting_height: i32,
+    pub wtxidrelay: bool,
+    pub addrv2: bool,
+    pub erlay: bool,
+    /// Salt announced in `sendtxrcncl` if `erlay` is set
+    pub erlay_salt: u64,
+    /// Protocol version advertised in the version message
+    pub version: u32,
+    /// Services advertised in the version message
+    pub services: ServiceFlags,
+    /// User agent advertised in the version message
+    pub user_agent: String,
+    /// Nonce of the version message (used by the target to detect connections to itself)
+    pub nonce: u64,
+}
+
+impl Default for HandshakeOpts {
+    fn default() -> Self {
+        Self {
+            time: 0,
+            relay: true,
+            starting_height: 0,
+            wtxidrelay: true,
+            addrv2: true,
+            erlay: false,
+            erlay_salt: 0,
+            version: 70016, // wtxidrelay version
+            services: ServiceFlags::NETWORK | ServiceFlags::WITNESS,
+            user_agent: String::from("fuzzamoto"),
+            nonce: 0xdead_beef,
+        }
+    }
+}
+
+impl<T: Transport> Connection<T> {
+    fn send_ping(&mut self, nonce: u64) -> Result<(), String> {
+        let ping_message = ("ping".to_string(), nonce.to_le_bytes().to_vec());
+        self.send(&ping_message)?;
+        Ok(())
+    }
+
+    fn try_receive(&mut self) -> Result<Option<(String, Vec<u8>)>, String> {
+        let received = self.transport.try_receive()?;
+        if let Some(message) = &received {
+            self.record(Direction::Received, &message.0, &message.1);
+        }
+        Ok(received)
+    }
+
+    pub(crate) fn wait_for_pong(
+        &mut self,
+        nonce: u64,
+        recording: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let deadline = self.pong_timeout.map(|timeout| Instant::now() + timeout);
+        let mut ret = Vec::new();
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.stalled = true;
+                return Err(format!("Timed out waiting for pong (nonce={nonce})"));
+            }
+
+            let Some(received) = self.try_receive()? else {
+                continue;
+            };
+            if received.0 == "pong" && received.1.len() == 8 && received.1 == nonce.to_le_bytes() {
+                self.stalled = false;
+                if let Some(since) = self.unsynced_since.take() {
+                    self.max_latency = self.max_latency.max(since.elapsed());
+                }
+                if let Some(connection) = self.trace_id {
+                    trace::record(TraceEvent::Sync { connection, nonce });
+                }
+                break;
+            }
+
+            // Pongs for other nonces are our own sync traffic
+            if received.0 == "pong" {
+                continue;
+            }
+            if recording {
+                ret.push(received);
+            } else {
+                self.capture(received);
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Wait for a message of type `command` (for at most `timeout`, or forever if `None`) and
+    /// return it. All other messages received in the meantime are captured (see `captured`).
+    ///
+    /// The timeout is only checked whenever the transport's `try_receive` returns, see
+    /// `set_pong_timeout`.
+    pub fn receive_until(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(String, Vec<u8>), String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(format!("Timed out waiting for {command:?} message"));
+            }
+
+            let Some(received) = self.try_receive()? else {
+                continue;
+            };
+            if received.0 == command {
+                return Ok(received);
+            }
+            self.capture(received);
+        }
+    }
+
+    pub fn send(&mut self, message: &(String, Vec<u8>)) -> Result<(), String> {
+        self.record(Direction::Sent, &message.0, &message.1);
+        self.transport.send(message)
+    }
+
+    /// Send multiple messages at once (see `Transport::send_batch`).
+    pub fn send_batch(&mut self, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+        for message in messages {
+            self.record(Direction::Sent, &message.0, &message.1);
+        }
+        self.transport.send_batch(messages)
+    }
+
+    pub fn receive(&mut self) -> Result<(String, Vec<u8>), String> {
+        let received = self.transport.receive()?;
+        self.record(Direction::Received, &received.0, &received.1);
+        Ok(received)
+    }
+
+    pub fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.record(Direction::SentRaw, "", bytes);
+        self.transport.send_raw(bytes)
+    }
+
+    /// Send a message in chunks of `chunk_size` bytes (the last chunk may be smaller), calling
+    /// `between_chunks` after each chunk but the last. Callers can e.g. ping other connections in
+    /// `between_chunks` to make sure the target has processed each chunk, which exercises header
+    /// and payload reassembly as well as the target's per-peer receive buffer limits.
+    pub fn send_dribble<F>(
+        &mut self,
+        message: &(String, Vec<u8>),
+        chunk_size: usize,
+        mut between_chunks: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut() -> Result<(), String>,
+    {
+        let frame = self.transport.encode_frame(message)?;
+        self.record(Direction::Sent, &message.0, &message.1);
+
+        let mut chunks = frame.chunks(chunk_size.max(1)).peekable();
+        while let Some(chunk) = chunks.next() {
+            self.transport.send_raw(chunk)?;
+            if chunks.peek().is_some() {
+                between_chunks()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ping(&mut self) -> Result<(), String> {
+        // Skip ping sync on connections that haven't completed the handshake
+        // to avoid hanging indefinitely
+        if !self.handshake_complete {
+            return Ok(());
+        }
+        self.ping_counter += 1;
+        self.send_ping(self.ping_counter)?;
+        self.wait_for_pong(self.ping_counter, false)?;
+        Ok(())
+    }
+
+    pub fn send_and_recv(
+        &mut self,
+        message: &(String, Vec<u8>),
+        recording: bool,
+    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+        self.send(message)?;
+
+        if !self.handshake_complete {
+            return Ok(vec![]);
+        }
+
+        // Sending two pings back-to-back, requires that the node calls `ProcessMessage` twice, and
+        // thus ensures `SendMessages` must have been called at least once
+        self.send_ping(0x0)?;
+        self.ping_counter += 1;
+        self.send_ping(self.ping_counter)?;
+        self.wait_for_pong(self.ping_counter, recording)
+    }
+
+    pub fn version_handshake(&mut self, opts: HandshakeOpts) -> Result<(), String> {
+        let socket_addr = self.transport.local_addr().unwrap();
+
+        let mut version_message = VersionMessage::new(
+            opts.services,
+            opts.time,
+            Address::new(&socket_addr, ServiceFlags::NONE),
+            Address::new(&socket_addr, ServiceFlags::NONE),
+            opts.nonce,
+            opts.user_agent,
+            opts.starting_height,
+        );
+
+        version_message.version = opts.version;
+        version_message.relay = opts.relay;
+
+        if self.connection_type == ConnectionType::Outbound {
+            self.receive_until("version", None)?;
+        }
+
+        // Convert version message to (String, Vec<u8>) format
+        let mut version_bytes = Vec::new();
+        version_message
+            .consensus_encode(&mut version_bytes)
+            .map_err(|e| format!("Failed to encode version message: {e}"))?;
+        self.send(&("version".to_string(), version_bytes))?;
+
+        // Send optional features if configured
+        if opts.wtxidrelay {
+            self.send(&("wtxidrelay".to_string(), vec![]))?;
+        }
+        if opts.addrv2 {
+            self.send(&("sendaddrv2".to_string(), vec![]))?;
+        }
+        if opts.erlay {
+            self.send_sendtxrcncl(crate::erlay::ERLAY_VERSION, opts.erlay_salt)?;
+        }
+
+        // Send verack
+        self.send(&("verack".to_string(), vec![]))?;
+
+        // Wait for verack
+        self.receive_until("verack", None)?;
+
+        self.handshake_complete = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport_pair() -> (V1Transport, V1Transport) {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (V1Transport::new(client), V1Transport::new(server))
+    }
+
+    #[test]
+    fn strict_mode_rejects_bad_checksum() {
+        let (mut sender, mut receiver) = transport_pair();
+        receiver.set_strict(true);
+
+        // Valid message followed by one with a corrupted checksum
+        sender.send(&("ping".to_string(), vec![0u8; 8])).unwrap();
+        let mut header = Vec::new();
+        header.extend_from_slice(&bitcoin::network::Network::Regtest.magic().to_bytes());
+        header.extend_from_slice(b"pong\0\0\0\0\0\0\0\0");
+        header.extend_from_slice(&8u32.to_le_bytes());
+        header.extend_from_slice(&[0xff; 4]);
+        header.extend_from_slice(&[0u8; 8]);
+        sender.socket.write_all(&header).unwrap();
+
+        assert_eq!(receiver.receive().unwrap().0, "ping");
+        assert!(receiver.receive().is_err());
+        assert!(matches!(
+            receiver.framing_error(),
+            Some(FramingError::BadChecksum { command, .. }) if command == "pong"
+        ));
+    }
+
+    #[test]
+    fn send_batch_writes_all_messages() {
+        let (mut sender, mut receiver) = transport_pair();
+
+        let messages: Vec<_> = (0u8..16)
+            .map(|i| ("ping".to_string(), vec![i; 8]))
+            .collect();
+        sender.send_batch(&messages).unwrap();
+
+        for message in &messages {
+            assert_eq!(&receiver.receive().unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn socks5_handshake() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            ProxiedTransport::accept(socket, &ChainParams::regtest()).unwrap()
+        });
+
+        let destination = Socks5Destination::Domain("fuzzamoto.onion".to_string(), 8333);
+        let credentials = Some(("user".to_string(), "pass".to_string()));
+        let mut client = ProxiedTransport::connect(
+            proxy,
+            destination.clone(),
+            credentials.clone(),
+            &ChainParams::regtest(),
+        )
+        .unwrap();
+
+        let mut server = server.join().unwrap();
+        assert_eq!(server.destination(), &destination);
+        assert_eq!(server.credentials(), credentials.as_ref());
+
+        client.send(&("ping".to_string(), vec![1u8; 8])).unwrap();
+        assert_eq!(
+            server.receive().unwrap(),
+            ("ping".to_string(), vec![1u8; 8])
+        );
+    }
+}
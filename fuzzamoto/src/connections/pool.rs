@@ -0,0 +1,314 @@
+use crate::connections::{Connection, TrafficMetrics, Transport};
+use crate::transcript::TranscriptEntry;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// Why a pooled connection is considered dead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The target closed the connection (e.g. after a protocol violation)
+    Closed,
+    /// Writing to the connection failed because the target already closed it
+    BrokenPipe,
+    /// The target reset the connection
+    Reset,
+    /// The target did not answer a ping in time (see `Connection::set_pong_timeout`)
+    Stalled,
+}
+
+impl DisconnectReason {
+    /// Classify the kind of a transport's I/O error (see `Transport::disconnect_error`),
+    /// returning `None` if it does not indicate that the connection is gone (e.g. a timeout).
+    #[must_use]
+    pub fn from_error_kind(kind: ErrorKind) -> Option<Self> {
+        match kind {
+            ErrorKind::UnexpectedEof | ErrorKind::WriteZero | ErrorKind::NotConnected => {
+                Some(Self::Closed)
+            }
+            ErrorKind::BrokenPipe => Some(Self::BrokenPipe),
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => Some(Self::Reset),
+            _ => None,
+        }
+    }
+}
+
+/// A connection of a `ConnectionPool` was disconnected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisconnectEvent {
+    /// Index of the connection in the pool
+    pub index: usize,
+    pub reason: DisconnectReason,
+    /// The error that revealed the disconnect
+    pub error: String,
+    /// Whether the disconnect was provoked deliberately (see `ConnectionPool::expect_disconnect`)
+    pub expected: bool,
+}
+
+/// `ConnectionPool` owns the connections of a scenario and tracks their liveness.
+///
+/// Connections are addressed by index, with indices wrapping around the pool size. Operations
+/// on a dead connection are transparently redirected to the next alive connection, and each
+/// detected disconnect is recorded as a `DisconnectEvent` for oracles to inspect.
+pub struct ConnectionPool<T: Transport> {
+    connections: Vec<Connection<T>>,
+    alive: Vec<bool>,
+    /// Connections that are expected to be disconnected
+    expected: Vec<bool>,
+    disconnects: Vec<DisconnectEvent>,
+}
+
+impl<T: Transport> Default for ConnectionPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Transport> ConnectionPool<T> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            connections: Vec::new(),
+            alive: Vec::new(),
+            expected: Vec::new(),
+            disconnects: Vec::new(),
+        }
+    }
+
+    /// Add a connection to the pool, returning its index.
+    pub fn push(&mut self, connection: Connection<T>) -> usize {
+        self.connections.push(connection);
+        self.alive.push(true);
+        self.expected.push(false);
+        self.connections.len() - 1
+    }
+
+    /// Number of connections in the pool (including dead ones)
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    #[must_use]
+    pub fn alive_count(&self) -> usize {
+        self.alive.iter().filter(|alive| **alive).count()
+    }
+
+    #[must_use]
+    pub fn is_alive(&self, index: usize) -> bool {
+        self.alive.get(index).copied().unwrap_or(false)
+    }
+
+    /// Resolve `index` (modulo the pool size) to the next alive connection.
+    #[must_use]
+    pub fn resolve(&self, index: usize) -> Option<usize> {
+        let len = self.connections.len();
+        if len == 0 {
+            return None;
+        }
+        (0..len)
+            .map(|offset| (index % len + offset) % len)
+            .find(|index| self.alive[*index])
+    }
+
+    /// Run `f` on the connection resolved from `index` (see `resolve`), passing it the actual
+    /// index. Errors that indicate a disconnect mark the connection as dead.
+    pub fn with_connection<R, F>(&mut self, index: usize, f: F) -> Result<R, String>
+    where
+        F: FnOnce(usize, &mut Connection<T>) -> Result<R, String>,
+    {
+        let index = self
+            .resolve(index)
+            .ok_or_else(|| "No alive connections".to_string())?;
+
+        let result = f(index, &mut self.connections[index]);
+        if let Err(error) = &result {
+            self.check_disconnect(index, error);
+        }
+        result
+    }
+
+    /// Run `f` on the connection resolved from `index`, additionally passing it all other alive
+    /// connections (e.g. to sync with the target through them).
+    pub fn with_connection_and_others<R, F>(&mut self, index: usize, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut Connection<T>, &mut [&mut Connection<T>]) -> Result<R, String>,
+    {
+        let index = self
+            .resolve(index)
+            .ok_or_else(|| "No alive connections".to_string())?;
+
+        let (before, rest) = self.connections.split_at_mut(index);
+        let (connection, after) = rest.split_first_mut().unwrap();
+        let mut others: Vec<_> = before
+            .iter_mut()
+            .zip(&self.alive[..index])
+            .chain(after.iter_mut().zip(&self.alive[index + 1..]))
+            .filter(|(_, alive)| **alive)
+            .map(|(connection, _)| connection)
+            .collect();
+
+        let result = f(connection, &mut others);
+        if let Err(error) = &result {
+            self.check_disconnect(index, error);
+        }
+        result
+    }
+
+    /// Send a message on the connection resolved from `index`.
+    pub fn send(&mut self, index: usize, message: &(String, Vec<u8>)) -> Result<(), String> {
+        self.with_connection(index, |_, connection| connection.send(message))
+    }
+
+    /// Mark the connection resolved from `index` as expected to be disconnected, e.g. after it
+    /// deliberately misbehaved. Its disconnect (if any) is recorded as expected.
+    pub fn expect_disconnect(&mut self, index: usize) {
+        if let Some(index) = self.resolve(index) {
+            self.expected[index] = true;
+        }
+    }
+
+    /// Ping all alive connections, marking the ones that turn out to be disconnected as dead.
+    pub fn ping_all(&mut self) {
+        for index in 0..self.connections.len() {
+            if !self.alive[index] {
+                continue;
+            }
+            if let Err(error) = self.connections[index].ping() {
+                self.check_disconnect(index, &error);
+            }
+        }
+    }
+
+    /// Iterate over all alive connections.
+    pub fn iter_alive_mut(&mut self) -> impl Iterator<Item = &mut Connection<T>> {
+        self.connections
+            .iter_mut()
+            .zip(&self.alive)
+            .filter(|(_, alive)| **alive)
+            .map(|(connection, _)| connection)
+    }
+
+    /// Traffic sent and received on all connections of the pool so far
+    #[must_use]
+    pub fn metrics(&self) -> TrafficMetrics {
+        let mut metrics = TrafficMetrics::default();
+        for connection in &self.connections {
+            metrics.merge(connection.metrics());
+        }
+        metrics
+    }
+
+    /// Longest time the target took to process a batch of messages on any of the connections
+    /// (see `Connection::max_latency`)
+    #[must_use]
+    pub fn max_latency(&self) -> Duration {
+        self.connections
+            .iter()
+            .map(Connection::max_latency)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Reset the latencies of all connections.
+    pub fn reset_max_latency(&mut self) {
+        self.connections
+            .iter_mut()
+            .for_each(Connection::reset_max_latency);
+    }
+
+    /// The most recently sent messages of each connection, indexed like the pool (see
+    /// `Connection::recent_sent`)
+    #[must_use]
+    pub fn recent_sent(&self) -> Vec<Vec<TranscriptEntry>> {
+        self.connections
+            .iter()
+            .map(|connection| connection.recent_sent().iter().cloned().collect())
+            .collect()
+    }
+
+    /// All disconnects detected so far
+    #[must_use]
+    pub fn disconnects(&self) -> &[DisconnectEvent] {
+        &self.disconnects
+    }
+
+    /// Take all disconnects detected so far.
+    pub fn take_disconnects(&mut self) -> Vec<DisconnectEvent> {
+        std::mem::take(&mut self.disconnects)
+    }
+
+    fn check_disconnect(&mut self, index: usize, error: &str) {
+        let Some(reason) = self.connections[index].disconnect_reason() else {
+            return;
+        };
+
+        log::debug!("connection {index} disconnected ({reason:?}): {error}");
+        self.alive[index] = false;
+        self.disconnects.push(DisconnectEvent {
+            index,
+            reason,
+            error: error.to_string(),
+            expected: self.expected[index],
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::{ConnectionType, MockTransport};
+
+    #[test]
+    fn skips_dead_connections() {
+        let mut pool = ConnectionPool::new();
+        for _ in 0..3 {
+            pool.push(Connection::new(
+                ConnectionType::Inbound,
+                MockTransport::new(),
+            ));
+        }
+
+        let result = pool.with_connection(1, |_, connection| {
+            connection
+                .transport_mut()
+                .disconnect(ErrorKind::UnexpectedEof);
+            connection.send(&("ping".to_string(), vec![0; 8]))
+        });
+        assert!(result.is_err());
+        assert!(!pool.is_alive(1));
+        assert_eq!(pool.alive_count(), 2);
+        assert_eq!(pool.disconnects()[0].reason, DisconnectReason::Closed);
+        assert!(!pool.disconnects()[0].expected);
+
+        // Index 1 is redirected to the next alive connection
+        assert_eq!(pool.resolve(1), Some(2));
+        assert_eq!(pool.with_connection(4, |index, _| Ok(index)), Ok(2));
+
+        // Errors that don't indicate a disconnect keep the connection alive, whatever they say
+        let _ = pool.with_connection(0, |_, _| Err::<(), _>("Broken pipe".to_string()));
+        assert!(pool.is_alive(0));
+
+        pool.expect_disconnect(0);
+        let _ = pool.with_connection(0, |_, connection| {
+            connection.transport_mut().disconnect(ErrorKind::BrokenPipe);
+            connection.receive()
+        });
+        assert_eq!(pool.disconnects()[1].reason, DisconnectReason::BrokenPipe);
+        assert!(pool.disconnects()[1].expected);
+
+        // Pings that aren't answered in time stall the connection
+        pool.with_connection(2, |_, connection| {
+            connection.set_pong_timeout(Some(Duration::ZERO));
+            Ok(())
+        })
+        .unwrap();
+        pool.ping_all();
+        assert!(!pool.is_alive(2));
+        assert_eq!(pool.disconnects()[2].reason, DisconnectReason::Stalled);
+    }
+}
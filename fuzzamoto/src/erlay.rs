@@ -0,0 +1,449 @@
+//! Helpers for transaction reconciliation (erlay, BIP 330).
+//!
+//! This includes the salt negotiation, short transaction ids and a minisketch compatible
+//! `Sketch` (a `PinSketch` over GF(2^32)), such that scenarios can construct valid (and, by
+//! tweaking the results, near-valid) reconciliation rounds.
+
+use bitcoin::{
+    Wtxid,
+    consensus::encode::{self, Decodable, Encodable, VarInt},
+    hashes::{Hash, HashEngine, sha256, siphash24},
+};
+
+use crate::connections::{Connection, Transport};
+
+/// Reconciliation protocol version sent in `sendtxrcncl`
+pub const ERLAY_VERSION: u32 = 1;
+
+const SALT_TAG: &[u8] = b"Tx Relay Salting";
+
+/// Modulus of GF(2^32): x^32 + x^7 + x^3 + x^2 + 1 (the x^32 term is implicit)
+const FIELD_MODULUS: u32 = 0x8d;
+const FIELD_BITS: usize = 32;
+
+/// Computes the short transaction ids used in sketches for a pair of peers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShortIdHasher {
+    k0: u64,
+    k1: u64,
+}
+
+impl ShortIdHasher {
+    /// Derive the SipHash keys from both peers' salts (as announced in `sendtxrcncl`).
+    #[must_use]
+    pub fn new(local_salt: u64, remote_salt: u64) -> Self {
+        let tag = sha256::Hash::hash(SALT_TAG);
+        let mut engine = sha256::Hash::engine();
+        engine.input(tag.as_byte_array());
+        engine.input(tag.as_byte_array());
+        engine.input(&local_salt.min(remote_salt).to_le_bytes());
+        engine.input(&local_salt.max(remote_salt).to_le_bytes());
+        let full_salt = sha256::Hash::from_engine(engine).to_byte_array();
+
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&full_salt[..8]);
+        k1.copy_from_slice(&full_salt[8..16]);
+        Self {
+            k0: u64::from_le_bytes(k0),
+            k1: u64::from_le_bytes(k1),
+        }
+    }
+
+    #[must_use]
+    pub fn short_id(&self, wtxid: &Wtxid) -> u32 {
+        let hash = siphash24::Hash::hash_with_keys(self.k0, self.k1, wtxid.as_byte_array());
+        u32::try_from(hash.as_u64() & 0xffff_ffff)
+            .unwrap()
+            .wrapping_add(1)
+    }
+}
+
+/// A set sketch over 32-bit elements, compatible with minisketch's serialization.
+///
+/// Adding an element twice removes it again, and merging two sketches yields a sketch of the
+/// symmetric difference of their sets, which can be decoded as long as it contains at most
+/// `capacity` elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sketch {
+    /// Odd power sums `s_1, s_3, ..., s_{2 * capacity - 1}`
+    syndromes: Vec<u32>,
+}
+
+impl Sketch {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            syndromes: vec![0; capacity],
+        }
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.syndromes.len()
+    }
+
+    /// Add (or remove, if already present) a non-zero element.
+    pub fn add(&mut self, element: u32) {
+        let square = gf_mul(element, element);
+        let mut power = element;
+        for syndrome in &mut self.syndromes {
+            *syndrome ^= power;
+            power = gf_mul(power, square);
+        }
+    }
+
+    /// Merge `other` into this sketch (up to the smaller of both capacities).
+    pub fn merge(&mut self, other: &Sketch) {
+        self.syndromes.truncate(other.capacity());
+        for (syndrome, other) in self.syndromes.iter_mut().zip(&other.syndromes) {
+            *syndrome ^= other;
+        }
+    }
+
+    #[must_use]
+    pub fn serialize(&self) -> Vec<u8> {
+        self.syndromes
+            .iter()
+            .flat_map(|syndrome| syndrome.to_le_bytes())
+            .collect()
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(format!("Invalid sketch length: {}", bytes.len()));
+        }
+        Ok(Self {
+            syndromes: bytes
+                .chunks_exact(4)
+                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        })
+    }
+
+    /// Recover the elements of the sketched set, failing if it holds more than `capacity`
+    /// elements.
+    pub fn decode(&self) -> Result<Vec<u32>, String> {
+        let capacity = self.capacity();
+
+        // Derive the even power sums: s_2k = s_k^2
+        let mut power_sums = vec![0u32; 2 * capacity];
+        for (i, syndrome) in self.syndromes.iter().enumerate() {
+            power_sums[2 * i] = *syndrome;
+        }
+        for k in 1..=capacity {
+            let s = power_sums[k - 1];
+            power_sums[2 * k - 1] = gf_mul(s, s);
+        }
+
+        let (connection, len) = berlekamp_massey(&power_sums);
+        if len > capacity || connection[len] == 0 {
+            return Err("Failed to decode sketch: capacity exceeded".to_string());
+        }
+
+        // The elements are the roots of the reversed connection polynomial
+        let locator: Poly = connection.iter().rev().copied().collect();
+        let roots = find_roots(&locator)
+            .ok_or_else(|| "Failed to decode sketch: capacity exceeded".to_string())?;
+        if roots.len() != len {
+            return Err("Failed to decode sketch: capacity exceeded".to_string());
+        }
+
+        Ok(roots)
+    }
+}
+
+/// Encode a `sendtxrcncl` message payload
+#[must_use]
+pub fn encode_sendtxrcncl(version: u32, salt: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(12);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&salt.to_le_bytes());
+    bytes
+}
+
+/// Decode a `sendtxrcncl` message payload into the version and salt
+pub fn decode_sendtxrcncl(payload: &[u8]) -> Result<(u32, u64), String> {
+    let mut cursor = payload;
+    let version = u32::consensus_decode(&mut cursor)
+        .map_err(|e| format!("Failed to decode sendtxrcncl version: {e}"))?;
+    let salt = u64::consensus_decode(&mut cursor)
+        .map_err(|e| format!("Failed to decode sendtxrcncl salt: {e}"))?;
+    Ok((version, salt))
+}
+
+impl<T: Transport> Connection<T> {
+    /// Announce support for reconciliation with the given salt (usually done during the version
+    /// handshake, see `HandshakeOpts::erlay`).
+    pub fn send_sendtxrcncl(&mut self, version: u32, salt: u64) -> Result<(), String> {
+        self.send(&("sendtxrcncl".to_string(), encode_sendtxrcncl(version, salt)))
+    }
+
+    /// The salt the target announced in its `sendtxrcncl` message, if it was captured (e.g.
+    /// during the version handshake).
+    #[must_use]
+    pub fn erlay_remote_salt(&self) -> Option<u64> {
+        self.captured()
+            .iter()
+            .rev()
+            .find(|(command, _)| command == "sendtxrcncl")
+            .and_then(|(_, payload)| decode_sendtxrcncl(payload).ok())
+            .map(|(_, salt)| salt)
+    }
+
+    /// The short id hasher for this connection, given our own salt.
+    #[must_use]
+    pub fn erlay_short_id_hasher(&self, local_salt: u64) -> Option<ShortIdHasher> {
+        self.erlay_remote_salt()
+            .map(|remote_salt| ShortIdHasher::new(local_salt, remote_salt))
+    }
+
+    /// Request a reconciliation round (`reqrecon`).
+    pub fn send_reqrecon(&mut self, set_size: u16, q: u16) -> Result<(), String> {
+        let mut payload = Vec::with_capacity(4);
+        payload.extend_from_slice(&set_size.to_le_bytes());
+        payload.extend_from_slice(&q.to_le_bytes());
+        self.send(&("reqrecon".to_string(), payload))
+    }
+
+    pub fn send_sketch(&mut self, sketch: &Sketch) -> Result<(), String> {
+        self.send(&("sketch".to_string(), encode::serialize(&sketch.serialize())))
+    }
+
+    /// Conclude a reconciliation round (`reconcildiff`), asking for the transactions with the
+    /// given short ids.
+    pub fn send_reconcildiff(
+        &mut self,
+        success: bool,
+        ask_short_ids: &[u32],
+    ) -> Result<(), String> {
+        let mut payload = Vec::new();
+        success
+            .consensus_encode(&mut payload)
+            .and_then(|_| VarInt(ask_short_ids.len() as u64).consensus_encode(&mut payload))
+            .map_err(|e| format!("Failed to encode reconcildiff: {e}"))?;
+        for short_id in ask_short_ids {
+            payload.extend_from_slice(&short_id.to_le_bytes());
+        }
+        self.send(&("reconcildiff".to_string(), payload))
+    }
+
+    /// Wait for the target's next `sketch` message and decode it.
+    pub fn receive_sketch(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Sketch, String> {
+        let (_, payload) = self.receive_until("sketch", timeout)?;
+        let bytes: Vec<u8> = encode::deserialize(&payload)
+            .map_err(|e| format!("Failed to decode sketch message: {e}"))?;
+        Sketch::deserialize(&bytes)
+    }
+}
+
+/// Polynomial over GF(2^32), coefficients ordered from lowest to highest degree
+type Poly = Vec<u32>;
+
+fn gf_mul(mut a: u32, mut b: u32) -> u32 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        b >>= 1;
+        let carry = a & 0x8000_0000 != 0;
+        a <<= 1;
+        if carry {
+            a ^= FIELD_MODULUS;
+        }
+    }
+    result
+}
+
+/// Inverse of a non-zero element: a^(2^32 - 2) = a^2 * a^4 * ... * a^(2^31)
+fn gf_inv(a: u32) -> u32 {
+    let mut result = 1;
+    let mut power = a;
+    for _ in 1..FIELD_BITS {
+        power = gf_mul(power, power);
+        result = gf_mul(result, power);
+    }
+    result
+}
+
+/// Find the shortest linear recurrence (connection polynomial and its length) generating `s`.
+fn berlekamp_massey(s: &[u32]) -> (Poly, usize) {
+    let mut connection: Poly = vec![1];
+    let mut previous: Poly = vec![1];
+    let mut len = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = 1;
+
+    for (n, s_n) in s.iter().enumerate() {
+        let mut discrepancy = *s_n;
+        for i in 1..=len.min(connection.len() - 1) {
+            discrepancy ^= gf_mul(connection[i], s[n - i]);
+        }
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        let coefficient = gf_mul(discrepancy, gf_inv(previous_discrepancy));
+        let mut next = connection.clone();
+        if next.len() < previous.len() + shift {
+            next.resize(previous.len() + shift, 0);
+        }
+        for (i, p) in previous.iter().enumerate() {
+            next[i + shift] ^= gf_mul(coefficient, *p);
+        }
+
+        if 2 * len <= n {
+            previous = connection;
+            len = n + 1 - len;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        connection = next;
+    }
+
+    connection.resize(len + 1, 0);
+    (connection, len)
+}
+
+fn trim(p: &mut Poly) {
+    while p.last() == Some(&0) {
+        p.pop();
+    }
+}
+
+fn poly_mul(a: &Poly, b: &Poly) -> Poly {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            result[i + j] ^= gf_mul(*x, *y);
+        }
+    }
+    trim(&mut result);
+    result
+}
+
+/// Divide `a` by the (trimmed, non-zero) polynomial `b`, returning quotient and remainder.
+fn poly_divmod(a: &Poly, b: &Poly) -> (Poly, Poly) {
+    let mut remainder = a.clone();
+    trim(&mut remainder);
+    if remainder.len() < b.len() {
+        return (Vec::new(), remainder);
+    }
+
+    let lead_inverse = gf_inv(b[b.len() - 1]);
+    let mut quotient = vec![0; remainder.len() - b.len() + 1];
+    for i in (0..quotient.len()).rev() {
+        let coefficient = gf_mul(remainder[i + b.len() - 1], lead_inverse);
+        quotient[i] = coefficient;
+        for (j, y) in b.iter().enumerate() {
+            remainder[i + j] ^= gf_mul(coefficient, *y);
+        }
+    }
+
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
+
+fn poly_gcd(a: &Poly, b: &Poly) -> Poly {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    trim(&mut a);
+    trim(&mut b);
+    while !b.is_empty() {
+        let (_, remainder) = poly_divmod(&a, &b);
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+/// Find all roots of `f`, if it splits into distinct linear factors.
+fn find_roots(f: &Poly) -> Option<Vec<u32>> {
+    let x: Poly = vec![0, 1];
+
+    // f splits into distinct linear factors iff it divides x^(2^32) - x
+    let x_mod_f = poly_divmod(&x, f).1;
+    let mut power = x_mod_f.clone();
+    for _ in 0..FIELD_BITS {
+        power = poly_divmod(&poly_mul(&power, &power), f).1;
+    }
+    if power != x_mod_f {
+        return None;
+    }
+
+    let mut roots = Vec::new();
+    split(f, 0, &mut roots).then_some(roots)
+}
+
+/// Split `f` (a product of distinct linear factors) using the trace maps Tr(beta * x) for the
+/// basis elements beta = 2^k (k >= `start`), pushing its roots to `roots`.
+fn split(f: &Poly, start: usize, roots: &mut Vec<u32>) -> bool {
+    match f.len() {
+        0 | 1 => return true,
+        2 => {
+            roots.push(gf_mul(f[0], gf_inv(f[1])));
+            return true;
+        }
+        _ => {}
+    }
+
+    for k in start..FIELD_BITS {
+        let mut term = poly_divmod(&vec![0, 1 << k], f).1;
+        let mut trace = term.clone();
+        for _ in 1..FIELD_BITS {
+            term = poly_divmod(&poly_mul(&term, &term), f).1;
+            trace.resize(trace.len().max(term.len()), 0);
+            for (t, c) in trace.iter_mut().zip(&term) {
+                *t ^= c;
+            }
+        }
+
+        let factor = poly_gcd(f, &trace);
+        if factor.len() > 1 && factor.len() < f.len() {
+            let (cofactor, _) = poly_divmod(f, &factor);
+            return split(&factor, k + 1, roots) && split(&cofactor, k + 1, roots);
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sketch_roundtrip() {
+        let mut alice = Sketch::new(8);
+        let mut bob = Sketch::new(8);
+        for element in [1, 2, 3, 1000, 0xdead_beef] {
+            alice.add(element);
+        }
+        for element in [2, 3, 1000, 42, 0xffff_ffff] {
+            bob.add(element);
+        }
+
+        let mut difference = Sketch::deserialize(&alice.serialize()).unwrap();
+        difference.merge(&bob);
+        let mut decoded = difference.decode().unwrap();
+        decoded.sort_unstable();
+        assert_eq!(decoded, vec![1, 42, 0xdead_beef, 0xffff_ffff]);
+
+        let mut too_small = Sketch::new(8);
+        for element in 1..=20 {
+            too_small.add(element);
+        }
+        assert!(too_small.decode().is_err());
+    }
+}
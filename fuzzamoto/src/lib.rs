@@ -1,10 +1,18 @@
+pub mod assertions;
+pub mod chain;
 pub mod connections;
 pub mod dictionaries;
+pub mod erlay;
 pub mod oracles;
+#[cfg(feature = "remote")]
+pub mod remote;
 pub mod runners;
 pub mod scenarios;
 pub mod taproot;
 pub mod targets;
+pub mod trace;
 pub mod test_utils;
+pub mod transcript;
+pub mod zmq;
 
 pub use taproot::*;
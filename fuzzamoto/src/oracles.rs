@@ -1,12 +1,19 @@
 use crate::{
-    connections::Transport,
+    assertions::{AssertionScope, NodeState},
+    connections::{ConnectionPool, DisconnectReason, Transport},
     targets::{
-        ConnectableTarget, GenerateToAddress, HasBlockTemplate, HasTipInfo, HasTxOutSetInfo,
-        Target, bitcoin_core::TxOutSetInfo,
+        BitcoinCoreTarget, ConnectableTarget, ElectrsTarget, GenerateToAddress, HasBlockTemplate,
+        HasGetRawMempoolEntries, HasTipInfo, HasTxOutSetInfo, LogLevel, NodeNetworkTarget,
+        ResourceUsage, RpcTarget, Target, TargetNode,
+        bitcoin_core::{TxOutSetInfo, TxOutSetMuHash},
     },
+    zmq,
 };
+use bitcoin::{BlockHash, Txid};
 use std::{
+    collections::HashSet,
     marker::PhantomData,
+    str::FromStr,
     time::{Duration, Instant},
 };
 
@@ -244,6 +251,105 @@ where
     }
 }
 
+/// `UtxoSetContext` is the context for the `UtxoSetOracle`
+pub struct UtxoSetContext<'a, T> {
+    pub target: &'a T,
+    /// UTXO sets recorded earlier, e.g. before a restart or a sequence of reorgs
+    pub recorded: &'a [TxOutSetMuHash],
+}
+
+/// `UtxoSetOracle` checks that the UTXO set of a target (`gettxoutsetinfo muhash`) matches the one
+/// recorded earlier at the same chain tip, i.e. that the UTXO set did not change without a
+/// corresponding chain change (e.g. across restarts or after reorging away and back). This catches
+/// chainstate corruption that doesn't crash the target.
+pub struct UtxoSetOracle<TX>(PhantomData<TX>);
+
+impl<TX> Default for UtxoSetOracle<TX> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'a, T, TX> Oracle<UtxoSetContext<'a, T>> for UtxoSetOracle<TX>
+where
+    TX: Transport,
+    T: Target<TX> + HasTxOutSetInfo,
+{
+    fn evaluate(&self, context: &mut UtxoSetContext<'a, T>) -> OracleResult {
+        let info = match context.target.tx_out_set_muhash() {
+            Ok(info) => info,
+            Err(e) => {
+                return OracleResult::Fail(format!("Failed to retrieve UTXO set muhash: {e}"));
+            }
+        };
+
+        match context.recorded.iter().find(|recorded| {
+            recorded.best_block == info.best_block && recorded.muhash != info.muhash
+        }) {
+            Some(recorded) => OracleResult::Fail(format!(
+                "UTXO set at block {} changed without a chain change: muhash {} (recorded {})",
+                info.best_block, info.muhash, recorded.muhash
+            )),
+            None => OracleResult::Pass,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "UtxoSetOracle"
+    }
+}
+
+/// `ChainTipContext` is the context for the `ChainTipOracle`
+pub struct ChainTipContext<'a, T> {
+    pub target: &'a T,
+    /// Valid blocks known to the scenario (e.g. the setup chain and valid blocks it delivered)
+    pub valid_blocks: &'a HashSet<BlockHash>,
+    /// Height of the best tip the target was on before
+    pub min_height: u64,
+}
+
+/// `ChainTipOracle` compares the chain tip of a target with the scenario's view of the chains it
+/// delivered: the tip has to be a block the scenario knows to be valid (i.e. the target did not
+/// accept an invalid chain) and must not be lower than a tip the target was on before (i.e. the
+/// target did not reorg to a chain with less work, assuming the difficulty of all chains is the
+/// same as on regtest).
+pub struct ChainTipOracle<TX>(PhantomData<TX>);
+
+impl<TX> Default for ChainTipOracle<TX> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'a, T, TX> Oracle<ChainTipContext<'a, T>> for ChainTipOracle<TX>
+where
+    TX: Transport,
+    T: Target<TX> + HasTipInfo,
+{
+    fn evaluate(&self, context: &mut ChainTipContext<'a, T>) -> OracleResult {
+        let Some((hash, height)) = context.target.get_tip_info() else {
+            return OracleResult::Fail("Failed to get tip info".to_string());
+        };
+
+        if !context.valid_blocks.contains(&hash) {
+            OracleResult::Fail(format!(
+                "Target is on tip {hash} (height {height}), which is not part of a valid chain"
+            ))
+        } else if height < context.min_height {
+            OracleResult::Fail(format!(
+                "Target tip regressed to {hash} (height {height}) from height {}",
+                context.min_height
+            ))
+        } else {
+            OracleResult::Pass
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ChainTipOracle"
+    }
+}
+
 pub struct BlockTemplateOracle<TX>(PhantomData<TX>);
 
 impl<TX> Default for BlockTemplateOracle<TX> {
@@ -269,6 +375,457 @@ where
     }
 }
 
+/// `ZmqOracle` checks that the ZMQ notifications published by the target are consistent, i.e.
+/// none are missing, duplicated or out of order (see `zmq::verify_notifications`).
+#[derive(Default)]
+pub struct ZmqOracle;
+
+impl<T: TargetNode> Oracle<T> for ZmqOracle {
+    fn evaluate(&self, target: &mut T) -> OracleResult {
+        match target
+            .zmq_notifications()
+            .and_then(zmq::verify_notifications)
+        {
+            Ok(()) => OracleResult::Pass,
+            Err(e) => OracleResult::Fail(format!("Inconsistent ZMQ notifications: {e}")),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ZmqOracle"
+    }
+}
+
+/// `ResourceOracle` checks that the resource usage of a target did not grow by more than
+/// `max_growth` compared to a `baseline` (e.g. sampled after the scenario setup), to detect memory
+/// or fd leaks and unbounded disk usage.
+pub struct ResourceOracle {
+    baseline: ResourceUsage,
+    max_growth: ResourceUsage,
+}
+
+impl ResourceOracle {
+    #[must_use]
+    pub fn new(baseline: ResourceUsage, max_growth: ResourceUsage) -> Self {
+        Self {
+            baseline,
+            max_growth,
+        }
+    }
+}
+
+impl<T: TargetNode> Oracle<T> for ResourceOracle {
+    fn evaluate(&self, target: &mut T) -> OracleResult {
+        let Some(usage) = target.resource_usage() else {
+            // Targets that can't be sampled are not checked
+            return OracleResult::Pass;
+        };
+
+        let checks = [
+            (
+                "rss bytes",
+                usage.rss_bytes,
+                self.baseline.rss_bytes,
+                self.max_growth.rss_bytes,
+            ),
+            (
+                "open fds",
+                usage.open_fds,
+                self.baseline.open_fds,
+                self.max_growth.open_fds,
+            ),
+            (
+                "threads",
+                usage.threads,
+                self.baseline.threads,
+                self.max_growth.threads,
+            ),
+            (
+                "disk bytes",
+                usage.disk_bytes,
+                self.baseline.disk_bytes,
+                self.max_growth.disk_bytes,
+            ),
+        ];
+        for (name, current, baseline, max_growth) in checks {
+            if current > baseline.saturating_add(max_growth) {
+                return OracleResult::Fail(format!(
+                    "Resource usage grew beyond limit: {current} {name} (baseline {baseline}, max growth {max_growth})"
+                ));
+            }
+        }
+
+        OracleResult::Pass
+    }
+
+    fn name(&self) -> &'static str {
+        "ResourceOracle"
+    }
+}
+
+/// `LatencyOracle` checks that the target processed every batch of messages sent on a
+/// `ConnectionPool` (from sending the first message until the following pong) within
+/// `max_factor` times the `baseline` latency (e.g. sampled during the scenario setup). Slower
+/// batches are potential algorithmic complexity DoS findings.
+pub struct LatencyOracle {
+    baseline: Duration,
+    max_factor: u32,
+}
+
+impl LatencyOracle {
+    #[must_use]
+    pub fn new(baseline: Duration, max_factor: u32) -> Self {
+        Self {
+            baseline,
+            max_factor,
+        }
+    }
+}
+
+impl<TX: Transport> Oracle<ConnectionPool<TX>> for LatencyOracle {
+    fn evaluate(&self, pool: &mut ConnectionPool<TX>) -> OracleResult {
+        let latency = pool.max_latency();
+        let limit = self.baseline.saturating_mul(self.max_factor);
+        if latency > limit {
+            OracleResult::Fail(format!(
+                "Processing a message batch took {latency:?} (baseline {:?}, limit {limit:?}), potential algorithmic complexity DoS",
+                self.baseline
+            ))
+        } else {
+            OracleResult::Pass
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LatencyOracle"
+    }
+}
+
+/// `LogOracle` checks that the target did not log an error matching one of the given patterns
+/// (see `TargetNode::log_events`).
+///
+/// By default, only errors that indicate internal bugs or database corruption are considered (e.g.
+/// failed `CHECK_NONFATAL`s or LevelDB errors), as errors about invalid blocks or messages are
+/// expected while fuzzing. Failures include the log lines leading up to the error.
+pub struct LogOracle {
+    patterns: Vec<String>,
+}
+
+impl LogOracle {
+    #[must_use]
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+}
+
+impl Default for LogOracle {
+    fn default() -> Self {
+        Self::new(
+            [
+                "Internal bug detected",
+                "A fatal internal error occurred",
+                "Corrupted block database detected",
+                "Fatal LevelDB error",
+                "Corruption: ",
+            ]
+            .map(String::from)
+            .to_vec(),
+        )
+    }
+}
+
+impl<T: TargetNode> Oracle<T> for LogOracle {
+    fn evaluate(&self, target: &mut T) -> OracleResult {
+        let events = match target.log_events() {
+            Ok(events) => events,
+            Err(e) => return OracleResult::Fail(format!("Failed to read target logs: {e}")),
+        };
+
+        match events.iter().find(|event| {
+            event.level == LogLevel::Error
+                && self
+                    .patterns
+                    .iter()
+                    .any(|pattern| event.line.contains(pattern.as_str()))
+        }) {
+            Some(event) => OracleResult::Fail(format!(
+                "Target logged an error: {}\n{}",
+                event.line,
+                event.excerpt()
+            )),
+            None => OracleResult::Pass,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "LogOracle"
+    }
+}
+
+/// `AssertionOracle` evaluates the registered `AssertionScope`s against the final state of the
+/// target (see `NodeState`), extracted through RPC once at the end of a test case rather than at
+/// individual log points.
+pub struct AssertionOracle {
+    scopes: Vec<AssertionScope>,
+}
+
+impl AssertionOracle {
+    #[must_use]
+    pub fn new(scopes: Vec<AssertionScope>) -> Self {
+        Self { scopes }
+    }
+
+    /// Oracle with the default scopes for a target started with `node_args` (see
+    /// `AssertionScope::defaults`).
+    #[must_use]
+    pub fn from_node_args(node_args: &[String]) -> Self {
+        Self::new(AssertionScope::defaults(node_args))
+    }
+
+    /// Register an additional scope to evaluate.
+    pub fn register(&mut self, scope: AssertionScope) {
+        self.scopes.push(scope);
+    }
+}
+
+impl<T> Oracle<T> for AssertionOracle
+where
+    T: TargetNode + HasTipInfo + HasGetRawMempoolEntries,
+{
+    fn evaluate(&self, target: &mut T) -> OracleResult {
+        let state = match NodeState::from_target(target) {
+            Ok(state) => state,
+            Err(e) => return OracleResult::Fail(format!("Failed to extract node state: {e}")),
+        };
+
+        let failures: Vec<_> = self
+            .scopes
+            .iter()
+            .filter_map(|scope| {
+                scope
+                    .evaluate(&state)
+                    .err()
+                    .map(|e| format!("{}: {e}", scope.name()))
+            })
+            .collect();
+        if failures.is_empty() {
+            OracleResult::Pass
+        } else {
+            OracleResult::Fail(format!("Assertions failed: {}", failures.join("; ")))
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "AssertionOracle"
+    }
+}
+
+/// `ElectrsSyncOracle` checks that electrs indexed the chain tip of the node it is attached to
+/// within `timeout` (e.g. after reorgs or unusual blocks).
+pub struct ElectrsSyncOracle {
+    timeout: Duration,
+}
+
+impl ElectrsSyncOracle {
+    #[must_use]
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Oracle<ElectrsTarget> for ElectrsSyncOracle {
+    fn evaluate(&self, target: &mut ElectrsTarget) -> OracleResult {
+        match target.wait_for_sync(self.timeout) {
+            Ok(()) => OracleResult::Pass,
+            Err(e) => OracleResult::Fail(e),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ElectrsSyncOracle"
+    }
+}
+
+/// `PropagationContext` is the context for the `PropagationOracle`
+pub struct PropagationContext<'a> {
+    pub network: &'a mut NodeNetworkTarget,
+    /// Current mocktime of the network, advanced by the oracle
+    pub time: &'a mut u64,
+    /// Transactions that were in a mempool before the test case, which are not checked (e.g.
+    /// transactions of the setup that were never relayed). The transactions of the test case are
+    /// added after the evaluation.
+    pub known_txids: &'a mut HashSet<Txid>,
+    /// Mocktime advance between checks, such that the nodes' relay timers fire
+    pub time_step: u64,
+    /// Number of checks before giving up
+    pub max_rounds: usize,
+    pub poll_interval: Duration,
+}
+
+/// `PropagationOracle` checks that blocks and transactions delivered to one node of a
+/// `NodeNetworkTarget` propagate to all nodes connected to it within a bounded number of mocktime
+/// advances, i.e. that connected nodes end up with tips of the same chain work and that no node
+/// lacks a mempool transaction of a connected node that it would accept (`testmempoolaccept`).
+#[derive(Default)]
+pub struct PropagationOracle;
+
+/// Transactions in the mempool of `node`
+fn mempool_txids(node: &BitcoinCoreTarget) -> Result<HashSet<Txid>, String> {
+    let mempool = node.call_rpc("getrawmempool", &[])?;
+    mempool
+        .as_array()
+        .ok_or_else(|| "Malformed mempool contents".to_string())?
+        .iter()
+        .map(|txid| {
+            Txid::from_str(txid.as_str().unwrap_or_default())
+                .map_err(|e| format!("Malformed txid {txid}: {e}"))
+        })
+        .collect()
+}
+
+/// Find a block or transaction that did not propagate between connected nodes of `network`.
+///
+/// Transactions in `known` are not checked and all mempool transactions are added to `seen`.
+/// Transactions a node won't accept are remembered in `rejected` (by node and txid), such that
+/// they are only tested once per evaluation.
+fn check_propagation(
+    network: &NodeNetworkTarget,
+    known: &HashSet<Txid>,
+    seen: &mut HashSet<Txid>,
+    rejected: &mut HashSet<(usize, Txid)>,
+) -> Result<(), String> {
+    for component in network.components() {
+        let nodes: Vec<&BitcoinCoreTarget> = component
+            .iter()
+            .filter_map(|index| network.node(*index))
+            .collect();
+
+        let chain_work = nodes
+            .iter()
+            .map(|node| {
+                let info = node.call_rpc("getblockchaininfo", &[])?;
+                Ok(info["chainwork"].as_str().unwrap_or_default().to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        // Chain work is formatted as fixed width hex, so strings compare like the numbers
+        if let Some(max) = chain_work.iter().max()
+            && let Some(position) = chain_work.iter().position(|work| work != max)
+        {
+            return Err(format!(
+                "Node {} has a tip with chain work {} instead of {max}",
+                component[position], chain_work[position]
+            ));
+        }
+
+        let mempools = nodes
+            .iter()
+            .map(|node| mempool_txids(node))
+            .collect::<Result<Vec<_>, String>>()?;
+        seen.extend(mempools.iter().flatten());
+        for (from, mempool) in mempools.iter().enumerate() {
+            for (to, other) in mempools.iter().enumerate() {
+                for txid in mempool.difference(other) {
+                    if known.contains(txid) || rejected.contains(&(component[to], *txid)) {
+                        continue;
+                    }
+                    let tx =
+                        nodes[from].call_rpc("getrawtransaction", &[txid.to_string().into()])?;
+                    let accept = nodes[to].call_rpc("testmempoolaccept", &[vec![tx].into()])?;
+                    if accept[0]["allowed"].as_bool() == Some(true) {
+                        return Err(format!(
+                            "Transaction {txid} of node {} is missing on node {}",
+                            component[from], component[to]
+                        ));
+                    }
+                    // Transactions missing a parent may become acceptable once it propagated
+                    if accept[0]["reject-reason"] != "missing-inputs" {
+                        rejected.insert((component[to], *txid));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Oracle<PropagationContext<'_>> for PropagationOracle {
+    fn evaluate(&self, context: &mut PropagationContext<'_>) -> OracleResult {
+        let mut seen = HashSet::new();
+        let mut rejected = HashSet::new();
+        let mut result = Ok(());
+        for round in 0..context.max_rounds {
+            if round > 0 {
+                *context.time += context.time_step;
+                if let Err(e) = context.network.set_mocktime(*context.time) {
+                    return OracleResult::Fail(format!("Failed to advance mocktime: {e}"));
+                }
+                std::thread::sleep(context.poll_interval);
+            }
+
+            result = check_propagation(
+                context.network,
+                context.known_txids,
+                &mut seen,
+                &mut rejected,
+            );
+            if result.is_ok() {
+                break;
+            }
+        }
+        context.known_txids.extend(seen);
+
+        match result {
+            Ok(()) => OracleResult::Pass,
+            Err(e) => OracleResult::Fail(format!(
+                "Nodes did not converge within {} rounds: {e}",
+                context.max_rounds
+            )),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "PropagationOracle"
+    }
+}
+
+/// `DisconnectOracle` checks that the target only disconnected connections of a `ConnectionPool`
+/// that were expected to be disconnected (see `ConnectionPool::expect_disconnect`), such that
+/// spurious evictions and stalls become detectable.
+///
+/// Protected connections (e.g. connections that only ever send valid messages) and stalled
+/// connections are never expected to be disconnected.
+#[derive(Default)]
+pub struct DisconnectOracle {
+    protected: Vec<usize>,
+}
+
+impl DisconnectOracle {
+    #[must_use]
+    pub fn new(protected: Vec<usize>) -> Self {
+        Self { protected }
+    }
+}
+
+impl<TX: Transport> Oracle<ConnectionPool<TX>> for DisconnectOracle {
+    fn evaluate(&self, pool: &mut ConnectionPool<TX>) -> OracleResult {
+        match pool.disconnects().iter().find(|event| {
+            !event.expected
+                || event.reason == DisconnectReason::Stalled
+                || self.protected.contains(&event.index)
+        }) {
+            Some(event) => OracleResult::Fail(format!(
+                "Connection {} was unexpectedly disconnected ({:?}): {}",
+                event.index, event.reason, event.error
+            )),
+            None => OracleResult::Pass,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "DisconnectOracle"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
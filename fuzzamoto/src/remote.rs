@@ -0,0 +1,382 @@
+//! Running test cases on a remote machine.
+//!
+//! A `RemoteDaemon` runs inside a separate VM, container or machine and executes the test cases it
+//! receives with a scenario built without nyx (see `ReplayRunner`). The fuzzer talks to it through
+//! a `RemoteClient` and receives the exit status, stdout, verdict and coverage of each test case.
+//!
+//! Messages are length prefixed (`u32`, little endian) frames. A request is the timeout of the test
+//! case in milliseconds (`u32`, little endian) followed by a frame holding the test case, which is
+//! answered with three frames: the JSON encoded status of the run, the raw
+//! stdout of the scenario and its coverage (5 bytes per non-zero map entry: `u32` little endian
+//! index and hit count). Connections are served one at a time.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::runners::Verdict;
+
+/// Maximum size of a single frame, to avoid allocating garbage lengths
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Size of a coverage entry in the coverage frame of a response
+const COVERAGE_ENTRY_SIZE: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteResponse {
+    /// Exit code of the scenario, `None` if it was killed by a signal or timed out
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: Vec<u8>,
+    /// Verdict reported by the scenario's runner, `None` if the scenario did not report one
+    pub verdict: Option<Verdict>,
+    /// Non-zero entries of the coverage map as `(index, hit count)`
+    pub coverage: Vec<(u32, u8)>,
+    /// Wall clock time of the execution
+    pub duration_micros: u64,
+}
+
+/// First frame of a response
+#[derive(Serialize, Deserialize)]
+struct RemoteStatus {
+    exit_code: Option<i32>,
+    timed_out: bool,
+    verdict: Option<Verdict>,
+    duration_micros: u64,
+}
+
+impl RemoteResponse {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        let status = serde_json::to_vec(&RemoteStatus {
+            exit_code: self.exit_code,
+            timed_out: self.timed_out,
+            verdict: self.verdict.clone(),
+            duration_micros: self.duration_micros,
+        })
+        .map_err(|e| format!("Failed to serialize status: {e}"))?;
+        let coverage: Vec<u8> = self
+            .coverage
+            .iter()
+            .flat_map(|(index, hits)| {
+                let mut entry = [0u8; COVERAGE_ENTRY_SIZE];
+                entry[..4].copy_from_slice(&index.to_le_bytes());
+                entry[4] = *hits;
+                entry
+            })
+            .collect();
+
+        write_frame(writer, &status)?;
+        write_frame(writer, &self.stdout)?;
+        write_frame(writer, &coverage)
+    }
+
+    /// Read a response, `None` if the connection was closed before it.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>, String> {
+        let Some(status) = read_frame(reader)? else {
+            return Ok(None);
+        };
+        let status: RemoteStatus = serde_json::from_slice(&status)
+            .map_err(|e| format!("Failed to deserialize status: {e}"))?;
+        let missing = || "Connection closed in the middle of a response".to_string();
+        let stdout = read_frame(reader)?.ok_or_else(missing)?;
+        let coverage = read_frame(reader)?.ok_or_else(missing)?;
+        if coverage.len() % COVERAGE_ENTRY_SIZE != 0 {
+            return Err(format!("Invalid coverage frame size: {}", coverage.len()));
+        }
+
+        Ok(Some(Self {
+            exit_code: status.exit_code,
+            timed_out: status.timed_out,
+            stdout,
+            verdict: status.verdict,
+            coverage: coverage
+                .chunks_exact(COVERAGE_ENTRY_SIZE)
+                .map(|entry| {
+                    (
+                        u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                        entry[4],
+                    )
+                })
+                .collect(),
+            duration_micros: status.duration_micros,
+        }))
+    }
+}
+
+/// Write `data` as a length prefixed frame.
+pub fn write_frame<W: Write>(writer: &mut W, data: &[u8]) -> Result<(), String> {
+    let len = u32::try_from(data.len()).map_err(|_| "Frame too large".to_string())?;
+    writer
+        .write_all(&len.to_le_bytes())
+        .and_then(|()| writer.write_all(data))
+        .and_then(|()| writer.flush())
+        .map_err(|e| format!("Failed to write frame: {e}"))
+}
+
+/// Read a length prefixed frame, `None` if the connection was closed before it.
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(format!("Failed to read frame length: {e}")),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(format!("Frame too large: {len} bytes"));
+    }
+    let mut data = vec![0u8; len];
+    reader
+        .read_exact(&mut data)
+        .map_err(|e| format!("Failed to read frame: {e}"))?;
+    Ok(Some(data))
+}
+
+/// `RemoteClient` is the fuzzer side of the protocol (see the module documentation).
+pub struct RemoteClient {
+    stream: TcpStream,
+}
+
+impl RemoteClient {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, String> {
+        let stream =
+            TcpStream::connect(addr).map_err(|e| format!("Failed to connect to daemon: {e}"))?;
+        stream
+            .set_nodelay(true)
+            .map_err(|e| format!("Failed to set TCP_NODELAY: {e}"))?;
+        Ok(Self { stream })
+    }
+
+    /// Run `input` on the daemon with `timeout` and wait for its result.
+    pub fn run(&mut self, input: &[u8], timeout: Duration) -> Result<RemoteResponse, String> {
+        let timeout = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+        self.stream
+            .write_all(&timeout.to_le_bytes())
+            .map_err(|e| format!("Failed to write timeout: {e}"))?;
+        write_frame(&mut self.stream, input)?;
+        RemoteResponse::read_from(&mut self.stream)?
+            .ok_or_else(|| "Daemon closed the connection".to_string())
+    }
+}
+
+/// AFL++ style coverage map in System V shared memory, which instrumented targets started with
+/// `__AFL_SHM_ID` set write their edge hit counts to.
+struct CoverageMap {
+    id: i32,
+    map: *mut u8,
+    size: usize,
+}
+
+impl CoverageMap {
+    fn new(size: usize) -> Result<Self, String> {
+        let id = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+        if id < 0 {
+            return Err(format!(
+                "Failed to create coverage map: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let map = unsafe { libc::shmat(id, std::ptr::null(), 0) };
+        if map as isize == -1 {
+            unsafe { libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut()) };
+            return Err(format!(
+                "Failed to attach coverage map: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(Self {
+            id,
+            map: map.cast(),
+            size,
+        })
+    }
+
+    fn as_slice_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.map, self.size) }
+    }
+
+    fn take(&mut self) -> Vec<(u32, u8)> {
+        let entries = (0u32..)
+            .zip(self.as_slice_mut().iter())
+            .filter(|(_, hits)| **hits != 0)
+            .map(|(index, hits)| (index, *hits))
+            .collect();
+        self.as_slice_mut().fill(0);
+        entries
+    }
+}
+
+impl Drop for CoverageMap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::shmdt(self.map.cast());
+            libc::shmctl(self.id, libc::IPC_RMID, std::ptr::null_mut());
+        }
+    }
+}
+
+/// `RemoteDaemon` is the runner side of the protocol (see the module documentation).
+///
+/// Every test case runs in a new scenario process (with `FUZZAMOTO_INPUT` and
+/// `FUZZAMOTO_VERDICT`), which inherits the daemon's environment, e.g.
+/// `FUZZAMOTO_DATADIR_SNAPSHOT` to skip most of the scenario setup.
+pub struct RemoteDaemon {
+    pub scenario: PathBuf,
+    pub bitcoind: PathBuf,
+    /// Size of the coverage map passed to the target as `AFL_MAP_SIZE`
+    pub map_size: usize,
+    /// Upper bound of the timeouts requested by clients
+    pub max_timeout: Duration,
+}
+
+impl RemoteDaemon {
+    /// Serve clients on `listener` until it fails.
+    pub fn serve(&self, listener: &TcpListener) -> Result<(), String> {
+        let mut coverage = CoverageMap::new(self.map_size)?;
+        for stream in listener.incoming() {
+            let mut stream = stream.map_err(|e| format!("Failed to accept client: {e}"))?;
+            log::info!("Serving client {:?}", stream.peer_addr());
+            if let Err(e) = self.serve_client(&mut stream, &mut coverage) {
+                log::warn!("Client failed: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn serve_client(
+        &self,
+        stream: &mut TcpStream,
+        coverage: &mut CoverageMap,
+    ) -> Result<(), String> {
+        let _ = stream.set_nodelay(true);
+        loop {
+            let mut timeout = [0u8; 4];
+            match stream.read_exact(&mut timeout) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(format!("Failed to read timeout: {e}")),
+            }
+            let timeout = Duration::from_millis(u64::from(u32::from_le_bytes(timeout)));
+            let input = read_frame(stream)?
+                .ok_or_else(|| "Connection closed in the middle of a request".to_string())?;
+            self.run(&input, timeout.min(self.max_timeout), coverage)?
+                .write_to(stream)?;
+        }
+    }
+
+    fn run(
+        &self,
+        input: &[u8],
+        timeout: Duration,
+        coverage: &mut CoverageMap,
+    ) -> Result<RemoteResponse, String> {
+        let tmp = std::env::temp_dir();
+        let input_path = tmp.join(format!("fuzzamoto-remote-input-{}", std::process::id()));
+        let verdict_path = tmp.join(format!("fuzzamoto-remote-verdict-{}", std::process::id()));
+        std::fs::write(&input_path, input).map_err(|e| format!("Failed to write input: {e}"))?;
+        let _ = std::fs::remove_file(&verdict_path);
+        coverage.take();
+
+        let start = Instant::now();
+        let mut child = Command::new(&self.scenario)
+            .arg(&self.bitcoind)
+            .env("FUZZAMOTO_INPUT", &input_path)
+            .env("FUZZAMOTO_VERDICT", &verdict_path)
+            .env("__AFL_SHM_ID", coverage.id.to_string())
+            .env("AFL_MAP_SIZE", self.map_size.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            // Own process group, such that the target(s) spawned by the scenario can be killed
+            // along with it
+            .process_group(0)
+            .spawn()
+            .map_err(|e| format!("Failed to spawn scenario: {e}"))?;
+
+        // Read stdout concurrently, the scenario blocks once the pipe is full
+        let mut stdout_pipe = child.stdout.take();
+        let stdout_reader = std::thread::spawn(move || {
+            let mut stdout = Vec::new();
+            if let Some(pipe) = &mut stdout_pipe {
+                let _ = pipe.read_to_end(&mut stdout);
+            }
+            stdout
+        });
+
+        let mut timed_out = false;
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| format!("Failed to wait for scenario: {e}"))?
+            {
+                break status;
+            }
+            if start.elapsed() > timeout {
+                timed_out = true;
+                kill_process_group(&child);
+                break child
+                    .wait()
+                    .map_err(|e| format!("Failed to wait for scenario: {e}"))?;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        // Don't leave targets behind that outlived a crashed scenario
+        kill_process_group(&child);
+        let duration_micros = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+
+        let verdict = std::fs::read_to_string(&verdict_path)
+            .ok()
+            .and_then(|json| Verdict::from_json(&json).ok());
+        let _ = std::fs::remove_file(&verdict_path);
+        let _ = std::fs::remove_file(&input_path);
+
+        Ok(RemoteResponse {
+            exit_code: if timed_out { None } else { status.code() },
+            timed_out,
+            stdout: stdout_reader.join().unwrap_or_default(),
+            verdict,
+            coverage: coverage.take(),
+            duration_micros,
+        })
+    }
+}
+
+/// Kill the process group led by `child` (see `Command::process_group`).
+fn kill_process_group(child: &Child) {
+    if let Ok(pid) = i32::try_from(child.id()) {
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_roundtrip() {
+        let response = RemoteResponse {
+            exit_code: Some(1),
+            stdout: b"CRASH: CRASH; target died".to_vec(),
+            coverage: vec![(1, 3), (4096, 1)],
+            ..RemoteResponse::default()
+        };
+
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &[1, 2]).unwrap();
+        response.write_to(&mut buffer).unwrap();
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), Some(vec![1, 2]));
+        assert_eq!(
+            RemoteResponse::read_from(&mut reader).unwrap(),
+            Some(response)
+        );
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+}
@@ -1,6 +1,15 @@
 #[cfg(feature = "nyx")]
 use fuzzamoto_nyx_sys::*;
 
+use std::{cell::RefCell, collections::VecDeque, io::Write, path::PathBuf, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    scenarios::{characterization::Characterization, crash_context::CrashContext},
+    trace,
+};
+
 /// `Runner` provides an abstraction for a fuzzamoto test case runner (e.g. run under nyx,
 /// libafl-qemu, local system, etc.)
 pub trait Runner {
@@ -12,21 +21,166 @@ pub trait Runner {
     fn fail(&self, message: &str);
     // Skip the last test case
     fn skip(&self);
+    // Report the characterizations of the last test case to the fuzzer
+    fn characterize(&self, characterizations: &[Characterization]);
+    // Attach context to the last test case, before failing it
+    fn attach_context(&self, context: &CrashContext);
+    // Report the execution metrics of the last test case to the fuzzer
+    fn report_metrics(&self, metrics: &ExecutionMetrics);
+    // Whether there are more inputs to run in this process (see `ResetStrategy::DatadirSnapshot`)
+    fn has_more_inputs(&self) -> bool;
+}
+
+/// `ResetStrategy` configures how the state of the target(s) is reset in between test cases, set
+/// through `FUZZAMOTO_RESET` (`vm` or `datadir`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStrategy {
+    /// Nyx restores a VM snapshot taken after the scenario setup (the default with nyx)
+    VmSnapshot,
+    /// The scenario is set up again for every test case (reconnecting to its targets), with
+    /// targets starting from a copy of the data directory snapshot in
+    /// `FUZZAMOTO_DATADIR_SNAPSHOT`. This works without nyx and allows running many inputs in
+    /// one process (e.g. `FUZZAMOTO_INPUT` pointing to a directory, see `LocalRunner`).
+    DatadirSnapshot,
+}
+
+impl ResetStrategy {
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("FUZZAMOTO_RESET").as_deref() {
+            Ok("vm") | Err(_) if cfg!(feature = "nyx") => Ok(Self::VmSnapshot),
+            Ok("datadir") | Err(_) => Ok(Self::DatadirSnapshot),
+            Ok("vm") => Err("VM snapshots require the nyx feature".to_string()),
+            Ok(other) => Err(format!(
+                "Unknown reset strategy in FUZZAMOTO_RESET: {other:?}"
+            )),
+        }
+    }
+}
+
+/// Snapshot point selected through `ScenarioArgs::snapshot` and the points declared by the
+/// scenario setup so far
+struct SnapshotPoints {
+    selected: Option<String>,
+    declared: Vec<String>,
+}
+
+static SNAPSHOT_POINTS: Mutex<SnapshotPoints> = Mutex::new(SnapshotPoints {
+    selected: None,
+    declared: Vec::new(),
+});
+
+/// Select the named snapshot point test cases run from (see `snapshot_point`), `None` for the
+/// end of the scenario setup. This forgets the points declared by earlier setups.
+pub fn select_snapshot_point(name: Option<String>) {
+    if let Ok(mut points) = SNAPSHOT_POINTS.lock() {
+        points.selected = name;
+        points.declared.clear();
+    }
+}
+
+/// Declare the named snapshot point `name` (e.g. "post-handshake"), which scenarios call after
+/// expensive stages of their setup.
+///
+/// Returns whether the point is the selected one, in which case the scenario skips the rest of its
+/// setup. The runner takes its (root) snapshot once the setup returns, so test cases run from the
+/// state at the selected point, with all stages after it skipped.
+#[must_use]
+pub fn snapshot_point(name: &str) -> bool {
+    let Ok(mut points) = SNAPSHOT_POINTS.lock() else {
+        return false;
+    };
+    points.declared.push(name.to_string());
+    let selected = points.selected.as_deref() == Some(name);
+    if selected {
+        log::info!("Reached snapshot point {name:?}, skipping the rest of the setup");
+    }
+    selected
+}
+
+/// Check that the selected snapshot point (if any) was declared by the scenario setup, as test
+/// cases would otherwise run from a state that was not asked for.
+pub fn check_snapshot_point() -> Result<(), String> {
+    let points = SNAPSHOT_POINTS
+        .lock()
+        .map_err(|e| format!("Failed to lock snapshot points: {e}"))?;
+    match &points.selected {
+        Some(name) if !points.declared.contains(name) => Err(format!(
+            "Snapshot point {name:?} was not reached, the scenario declares: {:?}",
+            points.declared
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Prefix of the output lines carrying execution metrics (see `ExecutionMetrics::to_line`)
+pub const METRICS_PREFIX: &str = "METRICS: ";
+
+/// `ExecutionMetrics` describes the cost of running a single test case, such that fuzzers can
+/// use it in feedbacks and benchmarks without profiling the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionMetrics {
+    /// Time spent running the test case (excluding the scenario setup)
+    pub duration_micros: u64,
+    pub bytes_sent: u64,
+    pub messages_sent: u64,
+    /// Messages processed by the target, i.e. received from it in response
+    pub messages_received: u64,
+}
+
+impl ExecutionMetrics {
+    /// Serialize the metrics into a single output line (without line break), prefixed with
+    /// `METRICS_PREFIX`.
+    #[must_use]
+    pub fn to_line(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{METRICS_PREFIX}{json}")
+    }
+
+    /// Parse the last metrics line in `output`, ignoring any other output.
+    #[must_use]
+    pub fn parse_output(output: &[u8]) -> Option<Self> {
+        output
+            .split(|b| *b == b'\n')
+            .filter_map(|line| line.strip_prefix(METRICS_PREFIX.as_bytes()))
+            .filter_map(|json| serde_json::from_slice(json).ok())
+            .last()
+    }
 }
 
 /// `LocalRunner` is a runner that reads the fuzz input from the environment variable `FUZZAMOTO_INPUT`
 /// or from stdin if the environment variable is not set.
 ///
+/// If `FUZZAMOTO_INPUT` points to a directory, all files in it are run one after the other (see
+/// `ResetStrategy::DatadirSnapshot`).
+///
 /// This runner is used for reproducing test cases locally without using nyx.
-pub struct LocalRunner;
+pub struct LocalRunner {
+    /// Inputs left to run, if `FUZZAMOTO_INPUT` is a directory
+    queue: RefCell<VecDeque<PathBuf>>,
+}
 impl Runner for LocalRunner {
     fn new() -> Self {
-        Self
+        let mut queue: Vec<PathBuf> = std::env::var("FUZZAMOTO_INPUT")
+            .ok()
+            .and_then(|path| std::fs::read_dir(path).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        queue.sort();
+        Self {
+            queue: RefCell::new(queue.into()),
+        }
     }
 
     fn get_fuzz_input(&self) -> Vec<u8> {
         use std::io::Read;
-        if let Ok(path) = std::env::var("FUZZAMOTO_INPUT") {
+        if let Some(path) = self.queue.borrow_mut().pop_front() {
+            log::info!("Reading input from {}", path.display());
+            std::fs::read(&path).unwrap_or_else(|_| vec![])
+        } else if let Ok(path) = std::env::var("FUZZAMOTO_INPUT") {
             log::info!("Reading input from {:?}", std::env::var("FUZZAMOTO_INPUT"));
             std::fs::read(&path).unwrap_or_else(|_| vec![])
         } else {
@@ -44,6 +198,211 @@ impl Runner for LocalRunner {
     fn skip(&self) {
         log::warn!("Skipping test case");
     }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        for characterization in characterizations {
+            log::info!("{}", characterization.to_line());
+        }
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        // Write the context next to the input if requested, otherwise just log it
+        let Ok(path) = std::env::var("FUZZAMOTO_CRASH_CONTEXT") else {
+            log::info!("{}", context.to_line());
+            return;
+        };
+        if let Err(e) = context
+            .to_json()
+            .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()))
+        {
+            log::error!("Failed to write crash context to {path}: {e}");
+        }
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        log::info!("{}", metrics.to_line());
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        !self.queue.borrow().is_empty()
+    }
+}
+
+/// Outcome of a test case (see `Verdict`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Pass,
+    Skip,
+    Fail,
+}
+
+/// `Verdict` summarizes a test case run by the `ReplayRunner`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub outcome: Outcome,
+    /// Failure message of a failing test case (including the oracle that failed)
+    pub message: Option<String>,
+    /// State of the target(s) after the test case
+    pub characterizations: Vec<Characterization>,
+    /// Context of a failing test case (see `Scenario::crash_context`)
+    pub crash_context: Option<CrashContext>,
+    #[serde(default)]
+    pub metrics: Option<ExecutionMetrics>,
+}
+
+impl Verdict {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize verdict: {e}"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Failed to deserialize verdict: {e}"))
+    }
+
+    /// Parse the verdicts written by a `ReplayRunner` (one per line, in the order the test cases
+    /// ran).
+    pub fn from_lines(lines: &str) -> Result<Vec<Self>, String> {
+        lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(Self::from_json)
+            .collect()
+    }
+}
+
+/// `ReplayRunner` runs test cases like the `LocalRunner` and, if `FUZZAMOTO_VERDICT` is set,
+/// appends the `Verdict` of each test case to that path (one line per test case, see
+/// `Verdict::from_lines`) once the scenario is done with it.
+///
+/// This allows tooling (e.g. `fuzzamoto-cli replay` or `run-corpus`) to reproduce test cases
+/// outside of Nyx and inspect their outcome without parsing logs.
+///
+/// If `FUZZAMOTO_RECORD_TRACE` is set, everything the scenario does to its target (including the
+/// setup) is recorded and written to that path as a `Trace` (see `crate::trace`), which
+/// `fuzzamoto-cli replay-trace` can replay against a node running under a debugger.
+pub struct ReplayRunner {
+    local: LocalRunner,
+    verdict_path: Option<String>,
+    trace_path: Option<String>,
+    /// Verdict of the test case, `None` until the input was read
+    verdict: RefCell<Option<Verdict>>,
+}
+
+impl ReplayRunner {
+    fn update<F: FnOnce(&mut Verdict)>(&self, f: F) {
+        if let Some(verdict) = self.verdict.borrow_mut().as_mut() {
+            f(verdict);
+        }
+    }
+
+    /// Append `verdict` to the verdict file, if requested.
+    fn write_verdict(&self, verdict: &Verdict) {
+        let Some(path) = &self.verdict_path else {
+            return;
+        };
+        let written = serde_json::to_string(verdict)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{json}"))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            log::error!("Failed to write verdict to {path}: {e}");
+        }
+    }
+}
+
+impl Runner for ReplayRunner {
+    fn new() -> Self {
+        // Runners are created before the scenario, so the trace includes the scenario setup
+        let trace_path = std::env::var("FUZZAMOTO_RECORD_TRACE").ok();
+        if trace_path.is_some() {
+            trace::start_recording();
+        }
+        let verdict_path = std::env::var("FUZZAMOTO_VERDICT").ok();
+        if let Some(path) = &verdict_path {
+            // Verdicts are appended, so start from an empty file
+            let _ = std::fs::remove_file(path);
+        }
+        Self {
+            local: LocalRunner::new(),
+            verdict_path,
+            trace_path,
+            verdict: RefCell::new(None),
+        }
+    }
+
+    fn get_fuzz_input(&self) -> Vec<u8> {
+        // The previous test case is done once the next one is requested
+        if let Some(verdict) = self.verdict.take() {
+            self.write_verdict(&verdict);
+        }
+        *self.verdict.borrow_mut() = Some(Verdict {
+            outcome: Outcome::Pass,
+            message: None,
+            characterizations: Vec::new(),
+            crash_context: None,
+            metrics: None,
+        });
+        self.local.get_fuzz_input()
+    }
+
+    fn fail(&self, message: &str) {
+        self.update(|verdict| {
+            verdict.outcome = Outcome::Fail;
+            verdict.message = Some(message.to_string());
+        });
+        self.local.fail(message);
+    }
+
+    fn skip(&self) {
+        self.update(|verdict| verdict.outcome = Outcome::Skip);
+        self.local.skip();
+    }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        self.update(|verdict| verdict.characterizations = characterizations.to_vec());
+        self.local.characterize(characterizations);
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        self.update(|verdict| verdict.crash_context = Some(context.clone()));
+        self.local.attach_context(context);
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        self.update(|verdict| verdict.metrics = Some(*metrics));
+        self.local.report_metrics(metrics);
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        self.local.has_more_inputs()
+    }
+}
+
+impl Drop for ReplayRunner {
+    fn drop(&mut self) {
+        if let Some(path) = &self.trace_path
+            && let Err(e) = trace::finish_recording()
+                .to_json()
+                .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()))
+        {
+            log::error!("Failed to write trace to {path}: {e}");
+        }
+
+        let Some(mut verdict) = self.verdict.take() else {
+            return;
+        };
+        if std::thread::panicking() {
+            verdict.outcome = Outcome::Fail;
+            verdict.message = Some("Scenario panicked".to_string());
+        }
+        self.write_verdict(&verdict);
+    }
 }
 
 #[cfg(feature = "nyx")]
@@ -81,6 +440,39 @@ impl Runner for NyxRunner {
             nyx_skip();
         }
     }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        // Characterizations are picked up from the `stdout` buffer of `NyxExecutor`, just like
+        // failure messages
+        for characterization in characterizations {
+            let c_line = std::ffi::CString::new(characterization.to_line()).unwrap_or_default();
+            unsafe {
+                nyx_println(c_line.as_ptr(), c_line.count_bytes());
+            }
+        }
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        // Picked up from the `stdout` buffer of `NyxExecutor` and stored alongside the crashing
+        // input (see `CrashCauseFeedback`)
+        let c_line = std::ffi::CString::new(context.to_line()).unwrap_or_default();
+        unsafe {
+            nyx_println(c_line.as_ptr(), c_line.count_bytes());
+        }
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        // Picked up from the `stdout` buffer of `NyxExecutor` (see `ExecutionMetricsFeedback`)
+        let c_line = std::ffi::CString::new(metrics.to_line()).unwrap_or_default();
+        unsafe {
+            nyx_println(c_line.as_ptr(), c_line.count_bytes());
+        }
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        // Every input runs from the VM snapshot
+        false
+    }
 }
 #[cfg(feature = "nyx")]
 impl Drop for NyxRunner {
@@ -91,10 +483,263 @@ impl Drop for NyxRunner {
     }
 }
 
+/// File descriptor afl-fuzz sends forkserver commands on, the status pipe is `FORKSRV_FD + 1`
+#[cfg(feature = "afl")]
+const FORKSRV_FD: i32 = 198;
+
+/// `AflRunner` runs scenarios under plain afl-fuzz (or libafl's forkserver executor) by
+/// implementing the AFL++ forkserver protocol, for machines without KVM/Nyx.
+///
+/// The forkserver runs before the scenario is initialized and forks a child for every
+/// execution, which sets up the scenario with a fresh target and runs the input read from stdin
+/// (see `LocalRunner`). This resets the target between executions without snapshots, at the cost
+/// of one scenario setup per execution. Setting `FUZZAMOTO_DATADIR_SNAPSHOT` makes targets start
+/// from a copy of a data directory created once beforehand (see `BitcoinCoreTarget`), which
+/// skips most of the block validation of the setup.
+///
+/// Failing test cases (exit code 1) are reported to afl-fuzz as aborts. Without afl-fuzz (i.e.
+/// the status pipe is not open) the runner behaves like the `LocalRunner`.
+#[cfg(feature = "afl")]
+pub struct AflRunner {
+    local: LocalRunner,
+}
+
+#[cfg(feature = "afl")]
+impl AflRunner {
+    fn write_status(value: i32) -> bool {
+        let bytes = value.to_ne_bytes();
+        unsafe { libc::write(FORKSRV_FD + 1, bytes.as_ptr().cast(), bytes.len()) == 4 }
+    }
+
+    /// Serve forkserver requests until afl-fuzz goes away, only returning in the forked children
+    /// (or right away if not running under afl-fuzz).
+    fn run_forkserver() {
+        if std::env::var("FUZZAMOTO_DATADIR_SNAPSHOT")
+            .is_ok_and(|path| !std::path::Path::new(&path).is_dir())
+        {
+            // Children would otherwise create the snapshot from the state after their test case
+            log::error!("FUZZAMOTO_DATADIR_SNAPSHOT does not exist, create it before fuzzing");
+            std::process::exit(1);
+        }
+
+        // Hello message, this fails if we are not running under afl-fuzz
+        if !Self::write_status(0) {
+            return;
+        }
+
+        loop {
+            let mut was_killed = [0u8; 4];
+            if unsafe { libc::read(FORKSRV_FD, was_killed.as_mut_ptr().cast(), 4) } != 4 {
+                // afl-fuzz is gone
+                std::process::exit(0);
+            }
+
+            let pid = unsafe { libc::fork() };
+            if pid < 0 {
+                log::error!("Failed to fork: {}", std::io::Error::last_os_error());
+                std::process::exit(1);
+            }
+            if pid == 0 {
+                // Close the forkserver pipes, such that instrumented targets (which inherit them)
+                // don't try to run a forkserver of their own
+                unsafe {
+                    libc::close(FORKSRV_FD);
+                    libc::close(FORKSRV_FD + 1);
+                }
+                return;
+            }
+
+            if !Self::write_status(pid) {
+                std::process::exit(1);
+            }
+            let mut status = 0;
+            if unsafe { libc::waitpid(pid, &raw mut status, 0) } < 0 {
+                std::process::exit(1);
+            }
+            // `fuzzamoto_main` exits with code 1 for failing test cases, which afl-fuzz would not
+            // consider a crash
+            if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 1 {
+                status = libc::SIGABRT;
+            }
+            if !Self::write_status(status) {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "afl")]
+impl Runner for AflRunner {
+    fn new() -> Self {
+        Self::run_forkserver();
+        Self {
+            local: LocalRunner::new(),
+        }
+    }
+
+    fn get_fuzz_input(&self) -> Vec<u8> {
+        self.local.get_fuzz_input()
+    }
+
+    fn fail(&self, message: &str) {
+        self.local.fail(message);
+    }
+
+    fn skip(&self) {
+        self.local.skip();
+    }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        self.local.characterize(characterizations);
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        self.local.attach_context(context);
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        self.local.report_metrics(metrics);
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        // Every input runs in a forked child
+        false
+    }
+}
+
+/// File descriptor of the file honggfuzz writes each input to (`_HF_INPUT_FD`)
+#[cfg(feature = "honggfuzz")]
+const HF_INPUT_FD: i32 = 1021;
+/// File descriptor of the socket honggfuzz drives persistent mode through (`_HF_PERSISTENT_FD`)
+#[cfg(feature = "honggfuzz")]
+const HF_PERSISTENT_FD: i32 = 1023;
+
+/// Signature honggfuzz looks for in target binaries to run them in persistent mode (without `-P`)
+#[cfg(feature = "honggfuzz")]
+#[used]
+static HF_PERSISTENT_SIG: [u8; 41] = *b"\x01_LIBHFUZZ_PERSISTENT_BINARY_SIGNATURE_\x02\xFF";
+
+/// `HonggfuzzRunner` runs scenarios under honggfuzz by implementing its persistent mode protocol,
+/// for machines without KVM/Nyx.
+///
+/// Once the scenario is set up, the runner tells honggfuzz it is ready (`R` on the persistent
+/// socket), receives the length of the next input and reads the input from honggfuzz's input
+/// file. Targets are reset in between inputs by setting the scenario up again (see
+/// `ResetStrategy::DatadirSnapshot`), such that one process runs inputs until one of them fails.
+///
+/// Failing test cases abort the scenario, which honggfuzz reports as a crash. Without honggfuzz
+/// (i.e. the persistent socket is not open) the runner behaves like the `LocalRunner`.
+#[cfg(feature = "honggfuzz")]
+pub struct HonggfuzzRunner {
+    local: LocalRunner,
+    persistent: bool,
+}
+
+#[cfg(feature = "honggfuzz")]
+impl HonggfuzzRunner {
+    fn next_input() -> Result<Vec<u8>, String> {
+        use std::{
+            fs::File,
+            io::{Read, Write},
+            mem::ManuallyDrop,
+            os::{fd::FromRawFd, unix::fs::FileExt},
+        };
+
+        // The descriptors are owned by honggfuzz and stay open across inputs
+        let mut socket = ManuallyDrop::new(unsafe { File::from_raw_fd(HF_PERSISTENT_FD) });
+        let input_file = ManuallyDrop::new(unsafe { File::from_raw_fd(HF_INPUT_FD) });
+
+        // Ready for the next input, which also tells honggfuzz that the last one passed
+        socket
+            .write_all(b"R")
+            .map_err(|e| format!("Failed to send ready tag: {e}"))?;
+        let mut len = [0u8; 8];
+        socket
+            .read_exact(&mut len)
+            .map_err(|e| format!("Failed to receive input length: {e}"))?;
+        let len = usize::try_from(u64::from_ne_bytes(len))
+            .map_err(|e| format!("Invalid input length: {e}"))?;
+
+        let mut input = vec![0u8; len];
+        input_file
+            .read_exact_at(&mut input, 0)
+            .map_err(|e| format!("Failed to read input: {e}"))?;
+        Ok(input)
+    }
+}
+
+#[cfg(feature = "honggfuzz")]
+impl Runner for HonggfuzzRunner {
+    fn new() -> Self {
+        // Keep the signature from being garbage collected by the linker
+        std::hint::black_box(&HF_PERSISTENT_SIG);
+
+        let persistent = unsafe { libc::fcntl(HF_PERSISTENT_FD, libc::F_GETFD) } != -1;
+        if persistent {
+            // Targets must not inherit the persistent socket and input file, the coverage bitmap
+            // is still passed on to instrumented targets
+            unsafe {
+                libc::fcntl(HF_PERSISTENT_FD, libc::F_SETFD, libc::FD_CLOEXEC);
+                libc::fcntl(HF_INPUT_FD, libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+        }
+        Self {
+            local: LocalRunner::new(),
+            persistent,
+        }
+    }
+
+    fn get_fuzz_input(&self) -> Vec<u8> {
+        if !self.persistent {
+            return self.local.get_fuzz_input();
+        }
+        match Self::next_input() {
+            Ok(input) => input,
+            Err(e) => {
+                // honggfuzz is gone
+                log::error!("{e}");
+                std::process::exit(0);
+            }
+        }
+    }
+
+    fn fail(&self, message: &str) {
+        self.local.fail(message);
+        if self.persistent {
+            // honggfuzz only considers signals to be crashes
+            std::process::abort();
+        }
+    }
+
+    fn skip(&self) {
+        self.local.skip();
+    }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        self.local.characterize(characterizations);
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        self.local.attach_context(context);
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        self.local.report_metrics(metrics);
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        self.persistent || self.local.has_more_inputs()
+    }
+}
+
 #[cfg(feature = "nyx")]
 type DefaultRunner = NyxRunner;
-#[cfg(not(feature = "nyx"))]
-type DefaultRunner = LocalRunner;
+#[cfg(all(feature = "afl", not(feature = "nyx")))]
+type DefaultRunner = AflRunner;
+#[cfg(all(feature = "honggfuzz", not(any(feature = "nyx", feature = "afl"))))]
+type DefaultRunner = HonggfuzzRunner;
+#[cfg(not(any(feature = "nyx", feature = "afl", feature = "honggfuzz")))]
+type DefaultRunner = ReplayRunner;
 
 pub struct StdRunner {
     runner: DefaultRunner,
@@ -118,4 +763,20 @@ impl Runner for StdRunner {
     fn skip(&self) {
         self.runner.skip();
     }
+
+    fn characterize(&self, characterizations: &[Characterization]) {
+        self.runner.characterize(characterizations);
+    }
+
+    fn attach_context(&self, context: &CrashContext) {
+        self.runner.attach_context(context);
+    }
+
+    fn report_metrics(&self, metrics: &ExecutionMetrics) {
+        self.runner.report_metrics(metrics);
+    }
+
+    fn has_more_inputs(&self) -> bool {
+        self.runner.has_more_inputs()
+    }
 }
@@ -0,0 +1,165 @@
+use bitcoin::p2p::ServiceFlags;
+use clap::{ArgAction, Args, Parser};
+
+use crate::{chain::ChainParams, connections::HandshakeOpts};
+
+/// Version message fields of the connections made by a scenario (see `HandshakeOpts`)
+#[derive(Args, Debug, Clone, Default)]
+pub struct HandshakeArgs {
+    /// Protocol version advertised in the version message
+    #[arg(long, env = "FUZZAMOTO_PROTOCOL_VERSION")]
+    pub protocol_version: Option<u32>,
+    /// Services advertised in the version message (e.g. 1032 for pruned peers)
+    #[arg(long, env = "FUZZAMOTO_SERVICES")]
+    pub services: Option<u64>,
+    /// User agent advertised in the version message
+    #[arg(long, env = "FUZZAMOTO_USER_AGENT")]
+    pub user_agent: Option<String>,
+    /// Nonce sent in the version message
+    #[arg(long, env = "FUZZAMOTO_NONCE")]
+    pub nonce: Option<u64>,
+}
+
+/// `ScenarioArgs` are the command line arguments shared by all scenarios.
+///
+/// The target executable (and any scenario specific files) are passed as positional arguments,
+/// everything else is optional and can also be set through the environment variables that
+/// configured scenarios before (`FUZZAMOTO_CHAIN`, `FUZZAMOTO_NODE_ARGS`, ...), such that
+/// scenario setups can be changed without recompiling.
+#[derive(Parser, Debug, Clone)]
+#[command(about = "Run a fuzzamoto scenario")]
+pub struct ScenarioArgs {
+    /// Path to the target executable
+    pub target: String,
+    /// Scenario specific positional arguments (e.g. the list of RPCs for `RpcScenario` or the
+    /// executable of the second target of the IR scenario)
+    pub extra: Vec<String>,
+
+    /// Chain the target runs on
+    #[arg(long, env = "FUZZAMOTO_CHAIN", default_value = "regtest")]
+    pub chain: String,
+    /// Number of connections created during the scenario setup
+    #[arg(long, env = "FUZZAMOTO_CONNECTIONS")]
+    pub connections: Option<usize>,
+    /// Number of blocks mined during the scenario setup
+    #[arg(long, env = "FUZZAMOTO_BLOCKS")]
+    pub blocks: Option<u32>,
+    /// Negotiate erlay on the setup connections that support it
+    #[arg(long, env = "FUZZAMOTO_ERLAY", default_value_t = true, action = ArgAction::Set)]
+    pub erlay: bool,
+    /// Negotiate addrv2 on the setup connections that support it
+    #[arg(long, env = "FUZZAMOTO_ADDRV2", default_value_t = true, action = ArgAction::Set)]
+    pub addrv2: bool,
+    /// Capture the ZMQ notifications published by the target (see
+    /// `TargetNode::zmq_notifications`)
+    #[arg(long, env = "FUZZAMOTO_ZMQ")]
+    pub zmq: bool,
+    /// Report the target's state after each test case (see `Scenario::characterizations`), for
+    /// `fuzzamoto-libafl --characterization-feedback`
+    #[arg(long, env = "FUZZAMOTO_CHARACTERIZE")]
+    pub characterize: bool,
+    /// Keep running the remaining inputs of an input directory after an input failed, instead of
+    /// stopping at the first failure (the process still exits with an error)
+    #[arg(long, env = "FUZZAMOTO_KEEP_GOING")]
+    pub keep_going: bool,
+    #[command(flatten)]
+    pub handshake: HandshakeArgs,
+    /// Named snapshot point to stop the scenario setup at and run test cases from (e.g.
+    /// `post-handshake`), instead of the end of the setup (see `runners::snapshot_point`)
+    #[arg(long, env = "FUZZAMOTO_SNAPSHOT")]
+    pub snapshot: Option<String>,
+    /// Extra arguments for the target node (e.g. `-acceptnonstdtxn=1`), taking precedence over
+    /// the default ones
+    #[arg(
+        long = "node-arg",
+        env = "FUZZAMOTO_NODE_ARGS",
+        value_delimiter = ' ',
+        allow_hyphen_values = true
+    )]
+    pub node_args: Vec<String>,
+}
+
+impl ScenarioArgs {
+    /// Parse the arguments of a scenario binary (including the binary name as first argument).
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut args = Self::try_parse_from(args).map_err(|e| e.to_string())?;
+        // Tolerate repeated whitespace in `FUZZAMOTO_NODE_ARGS`
+        args.node_args.retain(|arg| !arg.is_empty());
+
+        if let Some(arg) = args.node_args.iter().find(|arg| !arg.starts_with('-')) {
+            return Err(format!("Invalid node argument: {arg:?}"));
+        }
+        Ok(args)
+    }
+
+    pub fn chain_params(&self) -> Result<ChainParams, String> {
+        ChainParams::from_name(&self.chain)
+    }
+
+    /// Handshake options with the version message fields overridden by `handshake`.
+    #[must_use]
+    pub fn handshake_opts(&self) -> HandshakeOpts {
+        let mut opts = HandshakeOpts::default();
+        if let Some(version) = self.handshake.protocol_version {
+            opts.version = version;
+        }
+        if let Some(services) = self.handshake.services {
+            opts.services = ServiceFlags::from(services);
+        }
+        if let Some(user_agent) = &self.handshake.user_agent {
+            opts.user_agent.clone_from(user_agent);
+        }
+        if let Some(nonce) = self.handshake.nonce {
+            opts.nonce = nonce;
+        }
+        opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parse_scenario_args() {
+        let parsed = ScenarioArgs::from_args(&args(&[
+            "scenario-rpc-generic",
+            "/bin/bitcoind",
+            "rpcs.txt",
+            "--connections",
+            "6",
+            "--erlay",
+            "false",
+            "--services",
+            "1032",
+            "--node-arg=-acceptnonstdtxn=1",
+            "--node-arg",
+            "-mempoolfullrbf=0",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.target, "/bin/bitcoind");
+        assert_eq!(parsed.extra, ["rpcs.txt"]);
+        assert_eq!(parsed.connections, Some(6));
+        assert_eq!(parsed.blocks, None);
+        assert!(!parsed.erlay);
+        assert!(parsed.addrv2);
+        assert_eq!(
+            parsed.node_args,
+            ["-acceptnonstdtxn=1", "-mempoolfullrbf=0"]
+        );
+        assert_eq!(
+            parsed.handshake_opts().services,
+            ServiceFlags::from(1032u64)
+        );
+
+        assert!(ScenarioArgs::from_args(&args(&["scenario-generic"])).is_err());
+        assert!(
+            ScenarioArgs::from_args(&args(&["scenario-generic", "bitcoind", "--node-arg=foo"]))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    connections::TrafficMetrics,
+    targets::{HasTipInfo, TargetNode},
+};
+
+/// Prefix of the output lines carrying characterizations (see `Characterization::to_line`)
+pub const CHARACTERIZATION_PREFIX: &str = "CHARACTERIZATION: ";
+
+/// Mempool of the target after a test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MempoolCharacterization {
+    /// Number of transactions in the mempool
+    pub count: usize,
+    /// Total virtual size of the transactions in the mempool
+    pub bytes: u64,
+}
+
+impl MempoolCharacterization {
+    pub fn from_target<T: TargetNode>(target: &T) -> Result<Self, String> {
+        let contents = target.mempool_contents()?;
+        Ok(Self {
+            count: contents.len(),
+            bytes: contents.iter().map(|tx| tx.vsize).sum(),
+        })
+    }
+}
+
+/// Chain tip of the target after a test case.
+///
+/// Only the height is recorded: every mined block has a new hash (e.g. due to a different time or
+/// coinbase), which would make each execution that mines a block look novel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TipCharacterization {
+    pub height: u64,
+}
+
+impl TipCharacterization {
+    pub fn from_target<T: HasTipInfo>(target: &T) -> Result<Self, String> {
+        let (_, height) = target
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip info".to_string())?;
+        Ok(Self { height })
+    }
+}
+
+/// Shape of the traffic the target sent during a test case.
+///
+/// The number of messages is bucketed by its bit length, such that only responses of a new type
+/// or of a substantially different volume look novel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrafficCharacterization {
+    /// Number of distinct message types received from the target
+    pub message_types: usize,
+    /// Bit length of the number of messages received from the target
+    pub messages_bucket: u32,
+}
+
+impl TrafficCharacterization {
+    /// Characterize the traffic received in between two snapshots of a scenario's metrics.
+    #[must_use]
+    pub fn between(before: &TrafficMetrics, after: &TrafficMetrics) -> Self {
+        let received: Vec<u64> = after
+            .received
+            .iter()
+            .map(|(command, counters)| {
+                let previous = before.received.get(command).map_or(0, |c| c.messages);
+                counters.messages.saturating_sub(previous)
+            })
+            .filter(|messages| *messages > 0)
+            .collect();
+        let messages: u64 = received.iter().sum();
+        Self {
+            message_types: received.len(),
+            messages_bucket: u64::BITS - messages.leading_zeros(),
+        }
+    }
+}
+
+/// `Characterization` describes the state a test case left the target in, beyond the
+/// `ScenarioResult`.
+///
+/// Scenarios emit characterizations after each test case (see `Scenario::characterizations`) and
+/// runners pass them on to the fuzzer, such that feedbacks can treat state changes (e.g. a new tip
+/// or a mempool of a new size) as signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Characterization {
+    Mempool(MempoolCharacterization),
+    Tip(TipCharacterization),
+    Traffic(TrafficCharacterization),
+}
+
+impl Characterization {
+    /// Serialize the characterization into a single output line (without line break), prefixed
+    /// with `CHARACTERIZATION_PREFIX`.
+    #[must_use]
+    pub fn to_line(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{CHARACTERIZATION_PREFIX}{json}")
+    }
+
+    /// Parse all characterization lines in `output`, ignoring any other output.
+    #[must_use]
+    pub fn parse_output(output: &[u8]) -> Vec<Self> {
+        output
+            .split(|b| *b == b'\n')
+            .filter_map(|line| line.strip_prefix(CHARACTERIZATION_PREFIX.as_bytes()))
+            .filter_map(|json| serde_json::from_slice(json).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characterization_lines_roundtrip() {
+        let characterizations = [
+            Characterization::Mempool(MempoolCharacterization {
+                count: 3,
+                bytes: 423,
+            }),
+            Characterization::Tip(TipCharacterization { height: 201 }),
+            Characterization::Traffic(TrafficCharacterization {
+                message_types: 2,
+                messages_bucket: 3,
+            }),
+        ];
+
+        let mut output = b"some other output\n".to_vec();
+        for characterization in &characterizations {
+            output.extend_from_slice(characterization.to_line().as_bytes());
+            output.push(b'\n');
+        }
+        output.extend_from_slice(b"CHARACTERIZATION: not json\n");
+
+        assert_eq!(Characterization::parse_output(&output), characterizations);
+    }
+
+    #[test]
+    fn traffic_only_counts_new_messages() {
+        let mut before = TrafficMetrics::default();
+        before.record_received("inv", 37);
+        before.record_received("pong", 8);
+        let mut after = before.clone();
+        for _ in 0..5 {
+            after.record_received("inv", 37);
+        }
+        after.record_received("getdata", 37);
+
+        assert_eq!(
+            TrafficCharacterization::between(&before, &after),
+            TrafficCharacterization {
+                message_types: 2,
+                messages_bucket: 3,
+            }
+        );
+        assert_eq!(
+            TrafficCharacterization::between(&after, &after).messages_bucket,
+            0
+        );
+    }
+}
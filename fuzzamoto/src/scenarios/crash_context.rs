@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{scenarios::characterization::Characterization, transcript::TranscriptEntry};
+
+/// Prefix of the output lines carrying crash context (see `CrashContext::to_line`)
+pub const CRASH_CONTEXT_PREFIX: &str = "CRASH_CONTEXT: ";
+
+/// File the crash context is published to while a test case runs (see `CrashContext::publish`).
+///
+/// Crashes of the target are caught by the crash handler inside the target process, which never
+/// returns control to the scenario. The crash handler appends this file to its crash log instead
+/// (see `fuzzamoto-nyx-sys/src/nyx-crash-handler.c`).
+pub const CRASH_CONTEXT_PATH: &str = "/tmp/fuzzamoto_crash_context.json";
+
+/// `CrashContext` describes what a scenario was doing when a test case failed, such that a crash
+/// can be triaged without re-running its input.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashContext {
+    /// The last messages sent on each connection (see `Connection::recent_sent`), indexed like
+    /// the scenario's connections
+    pub recent_sent: Vec<Vec<TranscriptEntry>>,
+    /// Index of the IR instruction that was executed last (for IR based scenarios)
+    pub instruction_index: Option<usize>,
+    /// State of the target(s) at the time of the failure
+    pub characterizations: Vec<Characterization>,
+}
+
+impl CrashContext {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize crash context: {e}"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Failed to deserialize crash context: {e}"))
+    }
+
+    /// Serialize the crash context into a single output line (without line break), prefixed
+    /// with `CRASH_CONTEXT_PREFIX`.
+    #[must_use]
+    pub fn to_line(&self) -> String {
+        let json = self.to_json().unwrap_or_default();
+        format!("{CRASH_CONTEXT_PREFIX}{json}")
+    }
+
+    /// Parse the last crash context line in `output`, ignoring any other output.
+    #[must_use]
+    pub fn parse_output(output: &[u8]) -> Option<Self> {
+        output
+            .split(|b| *b == b'\n')
+            .filter_map(|line| line.strip_prefix(CRASH_CONTEXT_PREFIX.as_bytes()))
+            .filter_map(|json| serde_json::from_slice(json).ok())
+            .last()
+    }
+
+    /// Write the crash context to `CRASH_CONTEXT_PATH`, for the crash handler to pick up in case
+    /// the target crashes before the scenario gets to report a failure.
+    pub fn publish(&self) -> Result<(), String> {
+        std::fs::write(CRASH_CONTEXT_PATH, self.to_json()?)
+            .map_err(|e| format!("Failed to publish crash context: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        scenarios::characterization::MempoolCharacterization,
+        transcript::{Direction, TranscriptEntry},
+    };
+
+    #[test]
+    fn crash_context_lines_roundtrip() {
+        let context = CrashContext {
+            recent_sent: vec![
+                vec![TranscriptEntry::new(Direction::Sent, "tx", &[1, 2, 3])],
+                vec![],
+            ],
+            instruction_index: Some(42),
+            characterizations: vec![Characterization::Mempool(MempoolCharacterization {
+                count: 1,
+                bytes: 3,
+            })],
+        };
+
+        let mut output = b"some other output\n".to_vec();
+        output.extend_from_slice(CrashContext::default().to_line().as_bytes());
+        output.push(b'\n');
+        output.extend_from_slice(context.to_line().as_bytes());
+        output.extend_from_slice(b"\nCRASH_CONTEXT: not json\n");
+
+        assert_eq!(CrashContext::parse_output(&output), Some(context));
+        assert_eq!(CrashContext::parse_output(b"CRASH: CRASH; abort"), None);
+    }
+}
@@ -1,13 +1,19 @@
 use crate::{
-    connections::{Connection, ConnectionType, HandshakeOpts, Transport},
+    connections::{ConnectionPool, ConnectionType, HandshakeOpts, TrafficMetrics, Transport},
     dictionaries::{Dictionary, FileDictionary},
-    scenarios::{Scenario, ScenarioInput, ScenarioResult},
-    targets::Target,
+    runners::snapshot_point,
+    scenarios::{
+        Scenario, ScenarioInput, ScenarioResult,
+        args::ScenarioArgs,
+        characterization::{Characterization, MempoolCharacterization},
+        crash_context::CrashContext,
+    },
+    targets::{HasTipInfo, ResourceUsage, Target},
     test_utils,
 };
 
 use bitcoin::{
-    Block, BlockHash,
+    Amount, Block, BlockHash, OutPoint, Transaction,
     consensus::encode::{self, Decodable, Encodable, VarInt},
     hashes::Hash,
     p2p::{
@@ -18,7 +24,8 @@ use bitcoin::{
 };
 
 use io::{self, Read, Write};
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
 
 pub enum Action {
     Connect {
@@ -51,7 +58,14 @@ impl<'a> ScenarioInput<'a> for TestCase {
 /// node.
 ///
 /// The scenario setup creates a couple of connections to the target node and mines a chain of 200
-/// blocks. Testcases simulate the processing of a series of messages by the target node, i.e. each
+/// blocks (see `SetupOptions`). If `FUZZAMOTO_UTXO_SNAPSHOT` points to an assumeutxo snapshot of
+/// that chain (dumped with `FUZZAMOTO_DUMP_UTXO_SNAPSHOT`), only the headers are sent and the
+/// snapshot is loaded instead, which shortens the setup and puts the background sync under test
+/// (this requires a target that accepts the snapshot, see `Target::load_utxo_snapshot`).
+/// Selecting the `post-handshake` snapshot point (see `runners::snapshot_point`) skips the chain
+/// altogether.
+///
+/// Testcases simulate the processing of a series of messages by the target node, i.e. each
 /// testcase represents a series of three types of actions:
 ///
 /// 1. Send a message to the target node through one of the existing connections
@@ -62,80 +76,134 @@ impl<'a> ScenarioInput<'a> for TestCase {
 /// through a ping/pong roundtrip and checks that the target remains alive with `Target::is_alive`.
 pub struct GenericScenario<TX: Transport, T: Target<TX>> {
     pub target: T,
-    pub connections: Vec<Connection<TX>>,
+    pub connections: ConnectionPool<TX>,
     pub time: u64,
     pub block_tree: BTreeMap<BlockHash, (Block, u32)>,
+    /// Options the scenario was set up with
+    pub setup: SetupOptions,
 
     _phantom: std::marker::PhantomData<(TX, T)>,
 }
 
 const INTERVAL: u64 = 1;
 
+/// Options negotiated by the outbound setup connections: (relay, wtxidrelay, addrv2, erlay)
+const OUTBOUND_OPTIONS: [(bool, bool, bool, bool); 4] = [
+    (true, true, true, false),
+    (true, true, false, true),
+    (true, false, true, true),
+    (false, false, true, false),
+];
+/// Options negotiated by the inbound setup connections: (relay, wtxidrelay, addrv2, erlay)
+const INBOUND_OPTIONS: [(bool, bool, bool, bool); 4] = [
+    (true, true, true, true),
+    (true, true, false, true),
+    (true, false, true, true),
+    (false, false, true, false),
+];
+
+/// `SetupOptions` describe the state `GenericScenario` sets up before running testcases.
+///
+/// More connections and blocks make for a richer snapshot state at the cost of a slower setup.
+/// The defaults (8 connections, 200 blocks, erlay and addrv2 negotiated) can be changed through
+/// `ScenarioArgs` (see `SetupOptions::from_args`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SetupOptions {
+    /// Number of connections, half of them outbound (rounded up) and half inbound
+    pub connections: usize,
+    /// Number of blocks mined on top of the genesis block
+    pub blocks: u32,
+    /// Whether erlay (`sendtxrcncl`) is negotiated on the connections that support it
+    pub erlay: bool,
+    /// Whether addrv2 (`sendaddrv2`) is negotiated on the connections that support it
+    pub addrv2: bool,
+}
+
+impl Default for SetupOptions {
+    fn default() -> Self {
+        Self {
+            connections: 8,
+            blocks: 200,
+            erlay: true,
+            addrv2: true,
+        }
+    }
+}
+
+impl SetupOptions {
+    /// Setup options with the defaults overridden by `args`.
+    #[must_use]
+    pub fn from_args(args: &ScenarioArgs) -> Self {
+        let default = Self::default();
+        Self {
+            connections: args.connections.unwrap_or(default.connections),
+            blocks: args.blocks.unwrap_or(default.blocks),
+            erlay: args.erlay,
+            addrv2: args.addrv2,
+        }
+    }
+}
+
 impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
-    fn from_target(mut target: T) -> Result<Self, String> {
-        let genesis_block = bitcoin::blockdata::constants::genesis_block(bitcoin::Network::Regtest);
+    /// Set up the scenario on `target` with the default `SetupOptions`. The version message fields
+    /// (protocol version, services, user agent and nonce) of all connections are taken from
+    /// `handshake_opts`, the remaining options vary per connection.
+    pub fn from_target(target: T, handshake_opts: &HandshakeOpts) -> Result<Self, String> {
+        Self::from_target_with_setup(target, handshake_opts, SetupOptions::default())
+    }
+
+    /// Start the target and set up the scenario as described by the parsed `args`.
+    pub fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        let target = T::from_scenario_args(args)?;
+        Self::from_target_with_setup(
+            target,
+            &args.handshake_opts(),
+            SetupOptions::from_args(args),
+        )
+    }
+
+    /// Set up the scenario on `target` as described by `setup` (see `from_target`).
+    pub fn from_target_with_setup(
+        mut target: T,
+        handshake_opts: &HandshakeOpts,
+        setup: SetupOptions,
+    ) -> Result<Self, String> {
+        let chain_params = target.chain_params();
+        // The setup chain is mined at regtest difficulty
+        chain_params.require_regtest()?;
+        let genesis_block = chain_params.genesis;
 
         let mut time = u64::from(genesis_block.header.time);
         target.set_mocktime(time)?;
 
-        let mut connections = vec![
-            (
-                target.connect(ConnectionType::Outbound)?,
-                true,
-                true,
-                true,
-                false,
-            ),
-            (
-                target.connect(ConnectionType::Outbound)?,
-                true,
-                true,
-                false,
-                true,
-            ),
-            (
-                target.connect(ConnectionType::Outbound)?,
-                true,
-                false,
-                true,
-                true,
-            ),
-            (
-                target.connect(ConnectionType::Outbound)?,
-                false,
-                false,
-                true,
-                false,
-            ),
-            (
-                target.connect(ConnectionType::Inbound)?,
-                true,
-                true,
-                true,
-                true,
-            ),
-            (
-                target.connect(ConnectionType::Inbound)?,
-                true,
-                true,
-                false,
-                true,
-            ),
-            (
-                target.connect(ConnectionType::Inbound)?,
-                true,
-                false,
-                true,
-                true,
-            ),
-            (
-                target.connect(ConnectionType::Inbound)?,
-                false,
-                false,
-                true,
-                false,
-            ),
-        ];
+        if setup.connections == 0 {
+            return Err("The setup requires at least one connection".to_string());
+        }
+
+        // The first half of the connections are outbound, the second half inbound, each half
+        // cycling through the negotiation options in `OUTBOUND_OPTIONS`/`INBOUND_OPTIONS`
+        let num_outbound = setup.connections.div_ceil(2);
+        let mut connections = Vec::with_capacity(setup.connections);
+        for i in 0..setup.connections {
+            let (connection_type, (relay, wtxidrelay, addrv2, erlay)) = if i < num_outbound {
+                (
+                    ConnectionType::Outbound,
+                    OUTBOUND_OPTIONS[i % OUTBOUND_OPTIONS.len()],
+                )
+            } else {
+                (
+                    ConnectionType::Inbound,
+                    INBOUND_OPTIONS[(i - num_outbound) % INBOUND_OPTIONS.len()],
+                )
+            };
+            connections.push((
+                target.connect(connection_type)?,
+                relay,
+                wtxidrelay,
+                addrv2 && setup.addrv2,
+                erlay && setup.erlay,
+            ));
+        }
 
         let mut send_compact = false;
         #[expect(clippy::cast_possible_wrap)]
@@ -147,6 +215,7 @@ impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
                 wtxidrelay: *wtxidrelay,
                 addrv2: *addrv2,
                 erlay: *erlay,
+                ..handshake_opts.clone()
             })?;
             let sendcmpct = NetworkMessage::SendCmpct(SendCmpct {
                 version: 2,
@@ -160,8 +229,20 @@ impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
 
         let mut dictionary = FileDictionary::new();
 
+        // Test cases run from the fresh connections without a chain if the setup stops here
+        let blocks = if snapshot_point("post-handshake") {
+            0
+        } else {
+            setup.blocks
+        };
+
+        let utxo_snapshot = std::env::var("FUZZAMOTO_UTXO_SNAPSHOT")
+            .ok()
+            .filter(|_| blocks > 0);
+        let mut headers = Vec::new();
+
         let mut block_tree = BTreeMap::new();
-        for height in 1..=200 {
+        for height in 1..=blocks {
             time += INTERVAL;
 
             let block = test_utils::mining::mine_block(
@@ -170,12 +251,16 @@ impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
                 u32::try_from(time).map_err(|_| "Failed to convert time to u32".to_string())?,
             );
 
-            // Send block to the first connection
-            connections[0]
-                .0
-                .send(&("block".to_string(), encode::serialize(&block)))?;
+            if utxo_snapshot.is_some() {
+                headers.push(block.header);
+            } else {
+                // Send block to the first connection
+                connections[0]
+                    .0
+                    .send(&("block".to_string(), encode::serialize(&block)))?;
 
-            target.set_mocktime(time as u64)?;
+                target.set_mocktime(time as u64)?;
+            }
 
             // Update for next iteration
             prev_hash = block.block_hash();
@@ -193,6 +278,22 @@ impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
             block_tree.insert(prev_hash, (block, height));
         }
 
+        if let Some(path) = &utxo_snapshot {
+            // Only sync the headers and load the chain state from the snapshot, instead of
+            // having the target validate all blocks.
+            target.set_mocktime(time)?;
+            let headers = NetworkMessage::Headers(headers);
+            connections[0]
+                .0
+                .send(&("headers".to_string(), encode::serialize(&headers)))?;
+            connections[0].0.ping()?;
+            target.load_utxo_snapshot(Path::new(path))?;
+        }
+
+        if let Ok(path) = std::env::var("FUZZAMOTO_DUMP_UTXO_SNAPSHOT") {
+            target.dump_utxo_snapshot(Path::new(&path))?;
+        }
+
         let mut output = std::io::Cursor::new(Vec::new());
         dictionary.write(&mut output);
 
@@ -209,20 +310,120 @@ impl<TX: Transport, T: Target<TX>> GenericScenario<TX, T> {
             connection.send_and_recv(&("inv".to_string(), encode::serialize(&inv)), false)?;
         }
 
+        let mut pool = ConnectionPool::new();
+        for (connection, _, _, _, _) in connections {
+            pool.push(connection);
+        }
+
         Ok(Self {
             target,
             time,
-            connections: connections.drain(..).map(|(c, _, _, _, _)| c).collect(),
+            connections: pool,
             block_tree,
+            setup,
             _phantom: std::marker::PhantomData,
         })
     }
 }
 
+/// Number of mature coinbase outputs scenarios split up during setup (see `GenericScenario::fund`)
+pub const FUNDED_COINBASES: usize = 20;
+/// Number of outputs created per split coinbase output (see `GenericScenario::fund`)
+pub const OUTPUTS_PER_COINBASE: usize = 50;
+
+impl<TX: Transport, T: Target<TX> + HasTipInfo> GenericScenario<TX, T> {
+    /// Hash and height of the highest block in the block tree
+    pub fn tip(&self) -> Result<(BlockHash, u32), String> {
+        self.block_tree
+            .iter()
+            .max_by_key(|(_, (_, height))| *height)
+            .map(|(hash, (_, height))| (*hash, *height))
+            .ok_or_else(|| "Block tree is empty".to_string())
+    }
+
+    /// Mine a block on top of the tip of the block tree that confirms `txs` and make the target
+    /// accept it.
+    pub fn confirm(&mut self, txs: Vec<Transaction>) -> Result<(), String> {
+        let (tip_hash, tip_height) = self.tip()?;
+
+        self.time += 1;
+        let mut block = test_utils::mining::mine_block(
+            tip_hash,
+            tip_height + 1,
+            u32::try_from(self.time).map_err(|_| "Failed to convert time to u32".to_string())?,
+        );
+        block.txdata.extend(txs);
+        test_utils::mining::fixup_commitments(&mut block);
+        test_utils::mining::fixup_proof_of_work(&mut block);
+
+        self.target.set_mocktime(self.time)?;
+        self.connections
+            .send(0, &("block".to_string(), encode::serialize(&block)))?;
+        self.connections.ping_all();
+
+        let block_hash = block.block_hash();
+        match self.target.get_tip_info() {
+            Some((hash, _)) if hash == block_hash => {}
+            tip => return Err(format!("Target did not accept block: tip={tip:?}")),
+        }
+        self.block_tree.insert(block_hash, (block, tip_height + 1));
+        Ok(())
+    }
+
+    /// Split the `coinbases` oldest mature coinbase outputs of the block tree into
+    /// `outputs_per_coinbase` outputs each (see `test_utils::create_fanout_tx`) and confirm the
+    /// splitting transactions, returning the created outputs.
+    ///
+    /// This way scenarios can build many independent transactions without first having to create
+    /// their own funding transactions.
+    pub fn fund(
+        &mut self,
+        coinbases: usize,
+        outputs_per_coinbase: usize,
+    ) -> Result<Vec<(OutPoint, Amount)>, String> {
+        let (_, tip_height) = self.tip()?;
+        let mut mature: Vec<(u32, (OutPoint, Amount))> = self
+            .block_tree
+            .values()
+            .filter(|(_, height)| *height + test_utils::COINBASE_MATURITY <= tip_height)
+            .map(|(block, height)| {
+                let coinbase = &block.txdata[0];
+                (
+                    *height,
+                    (
+                        OutPoint::new(coinbase.compute_txid(), 0),
+                        coinbase.output[0].value,
+                    ),
+                )
+            })
+            .collect();
+        mature.sort_by_key(|(height, _)| *height);
+
+        let txs = mature
+            .iter()
+            .take(coinbases)
+            .map(|(_, coinbase)| test_utils::create_fanout_tx(*coinbase, outputs_per_coinbase))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut outputs = Vec::new();
+        for tx in &txs {
+            let txid = tx.compute_txid();
+            outputs.extend(
+                (0u32..)
+                    .zip(&tx.output)
+                    .map(|(vout, output)| (OutPoint::new(txid, vout), output.value)),
+            );
+        }
+
+        self.confirm(txs)?;
+        log::info!("Funded {} outputs", outputs.len());
+        Ok(outputs)
+    }
+}
+
 impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for GenericScenario<TX, T> {
-    fn new(args: &[String]) -> Result<Self, String> {
-        let target = T::from_path(&args[1])?;
-        Self::from_target(target)
+    fn new(args: &ScenarioArgs) -> Result<Self, String> {
+        Self::from_scenario_args(args)
     }
 
     fn run(&mut self, testcase: TestCase) -> ScenarioResult {
@@ -238,16 +439,9 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for GenericScenario<TX
                     command,
                     data,
                 } => {
-                    if self.connections.is_empty() {
-                        continue;
-                    }
-
-                    let num_connections = self.connections.len();
-                    if let Some(connection) =
-                        self.connections.get_mut(from as usize % num_connections)
-                    {
-                        let _ = connection.send(&(command.to_string(), data));
-                    }
+                    let _ = self
+                        .connections
+                        .send(usize::from(from), &(command.to_string(), data));
                 }
                 Action::SetMocktime { time } => {
                     let _ = self.target.set_mocktime(time);
@@ -259,9 +453,7 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for GenericScenario<TX
             }
         }
 
-        for connection in &mut self.connections {
-            let _ = connection.ping();
-        }
+        self.connections.ping_all();
 
         if let Err(e) = self.target.is_alive() {
             return ScenarioResult::Fail(format!("Target is not alive: {e}"));
@@ -269,6 +461,29 @@ impl<TX: Transport, T: Target<TX>> Scenario<'_, TestCase> for GenericScenario<TX
 
         ScenarioResult::Ok
     }
+
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        Some(self.connections.metrics())
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        self.target.resource_usage()
+    }
+
+    fn characterizations(&self) -> Vec<Characterization> {
+        MempoolCharacterization::from_target(&self.target)
+            .map(Characterization::Mempool)
+            .into_iter()
+            .collect()
+    }
+
+    fn crash_context(&self) -> Option<CrashContext> {
+        Some(CrashContext {
+            recent_sent: self.connections.recent_sent(),
+            instruction_index: None,
+            characterizations: self.characterizations(),
+        })
+    }
 }
 
 impl Encodable for Action {
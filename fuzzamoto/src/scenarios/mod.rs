@@ -1,5 +1,13 @@
+pub mod args;
+pub mod characterization;
+pub mod crash_context;
 pub mod generic;
 
+use crate::{connections::TrafficMetrics, targets::ResourceUsage};
+use args::ScenarioArgs;
+use characterization::Characterization;
+use crash_context::CrashContext;
+
 /// `ScenarioInput` is a trait for scenario input types
 pub trait ScenarioInput<'a>: Sized {
     /// Decode the input from a byte slice
@@ -21,10 +29,34 @@ pub trait Scenario<'a, I>: Sized
 where
     I: ScenarioInput<'a>,
 {
-    /// Create a new instance of the scenario, preparing the initial state of the test
-    fn new(args: &[String]) -> Result<Self, String>;
+    /// Create a new instance of the scenario from the parsed command line, preparing the initial
+    /// state of the test
+    fn new(args: &ScenarioArgs) -> Result<Self, String>;
     // Run the test
     fn run(&mut self, testcase: I) -> ScenarioResult;
+
+    /// Traffic exchanged with the target so far (including the scenario setup). The traffic of
+    /// each test case is reported as a `Characterization::Traffic` if characterizations are
+    /// requested.
+    fn traffic_metrics(&self) -> Option<TrafficMetrics> {
+        None
+    }
+
+    /// Resource usage of the target(s), logged (at debug level) when a test case fails.
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        None
+    }
+
+    /// State of the target(s) after each test case, passed on to the fuzzer by the runner if
+    /// `ScenarioArgs::characterize` is set.
+    fn characterizations(&self) -> Vec<Characterization> {
+        Vec::new()
+    }
+
+    /// Context of the last test case, attached by the runner to failing test cases.
+    fn crash_context(&self) -> Option<CrashContext> {
+        None
+    }
 }
 
 #[macro_export]
@@ -32,7 +64,7 @@ macro_rules! fuzzamoto_main {
     ($scenario_type:ty, $testcase_type:ty) => {
         fn main() -> std::process::ExitCode {
             use env_logger;
-            use fuzzamoto::runners::{Runner, StdRunner};
+            use fuzzamoto::runners::{ResetStrategy, Runner, StdRunner};
             use std::process::ExitCode;
 
             env_logger::init();
@@ -41,48 +73,294 @@ macro_rules! fuzzamoto_main {
             // in Nyx to ensure `nyx_init` is called before targets are spawned.
             let runner = StdRunner::new();
 
-            // Define the scenario type with the target as its generic parameter
+            let reset = match ResetStrategy::from_env() {
+                Ok(reset) => reset,
+                Err(e) => {
+                    log::error!("{}", e);
+                    return ExitCode::from(1);
+                }
+            };
+
             let args: Vec<String> = std::env::args().collect();
-            let mut scenario = match <$scenario_type>::new(&args) {
-                Ok(scenario) => scenario,
+            let args = match fuzzamoto::scenarios::args::ScenarioArgs::from_args(&args) {
+                Ok(args) => args,
                 Err(e) => {
                     log::error!("Failed to initialize scenario: {}", e);
-                    let exit_code = std::env::var("FUZZAMOTO_INIT_ERROR_EXIT_CODE")
-                        .map_or(0, |v| v.parse().unwrap_or(0));
-                    return ExitCode::from(exit_code);
+                    return init_error_exit_code();
                 }
             };
+            fuzzamoto::runners::select_snapshot_point(args.snapshot.clone());
+
+            // Define the scenario type with the target as its generic parameter
+            let mut scenario = match init_scenario(&args) {
+                Ok(scenario) => scenario,
+                Err(exit_code) => return exit_code,
+            };
 
             // Ensure the runner dropped prior to the scenario when returning from main.
             let runner = runner;
 
-            log::info!("Scenario initialized! Executing input...");
+            if reset == ResetStrategy::VmSnapshot {
+                log::info!("Scenario initialized! Executing input...");
+                // In nyx mode the snapshot is taken here and a new fuzz input is provided each
+                // reset.
+                let input = runner.get_fuzz_input();
+                return if run_test_case(&runner, &mut scenario, &input, args.characterize) {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::from(1)
+                };
+            }
+
+            if fuzzamoto::targets::bitcoin_core::datadir_snapshot_pending() {
+                // Dropping the target creates the snapshot, which has to happen before any test
+                // case changes the target's state
+                log::info!("Creating data directory snapshot...");
+                drop(scenario);
+                scenario = match init_scenario(&args) {
+                    Ok(scenario) => scenario,
+                    Err(exit_code) => return exit_code,
+                };
+            }
+
+            // Time spent running test cases and resetting in between, for comparison with the
+            // execution speed of VM snapshots
+            let (mut executions, mut execution_time) = (0u32, std::time::Duration::ZERO);
+            let (mut resets, mut reset_time) = (0u32, std::time::Duration::ZERO);
+            let mut failed = false;
+            loop {
+                log::info!("Scenario initialized! Executing input...");
+                let input = runner.get_fuzz_input();
+                let start = std::time::Instant::now();
+                let passed = run_test_case(&runner, &mut scenario, &input, args.characterize);
+                executions += 1;
+                execution_time += start.elapsed();
+                failed |= !passed;
+                // Stop at the first failing test case, unless asked to keep going
+                if (!passed && !args.keep_going) || !runner.has_more_inputs() {
+                    log::info!(
+                        "Ran {executions} inputs in {execution_time:?} (mean {:?}), {resets} resets \
+                         took {reset_time:?} (mean {:?})",
+                        execution_time / executions,
+                        reset_time.checked_div(resets).unwrap_or_default()
+                    );
+                    return if failed {
+                        ExitCode::from(1)
+                    } else {
+                        ExitCode::SUCCESS
+                    };
+                }
+
+                // Reset by setting the scenario up again, with its target(s) starting from a
+                // copy of the data directory snapshot
+                let start = std::time::Instant::now();
+                drop(scenario);
+                scenario = match init_scenario(&args) {
+                    Ok(scenario) => scenario,
+                    Err(exit_code) => return exit_code,
+                };
+                resets += 1;
+                reset_time += start.elapsed();
+                log::info!("Reset scenario in {:?}", start.elapsed());
+            }
+        }
+
+        fn init_scenario(
+            args: &fuzzamoto::scenarios::args::ScenarioArgs,
+        ) -> Result<$scenario_type, std::process::ExitCode> {
+            <$scenario_type>::new(args)
+                .and_then(|scenario| {
+                    fuzzamoto::runners::check_snapshot_point()?;
+                    Ok(scenario)
+                })
+                .map_err(|e| {
+                    log::error!("Failed to initialize scenario: {}", e);
+                    init_error_exit_code()
+                })
+        }
+
+        fn init_error_exit_code() -> std::process::ExitCode {
+            let exit_code = std::env::var("FUZZAMOTO_INIT_ERROR_EXIT_CODE")
+                .map_or(0, |v| v.parse().unwrap_or(0));
+            std::process::ExitCode::from(exit_code)
+        }
 
-            // In nyx mode the snapshot is taken here and a new fuzz input is provided each reset.
-            let input = runner.get_fuzz_input();
+        /// Run a single test case, returning whether it passed (or was skipped).
+        fn run_test_case(
+            runner: &fuzzamoto::runners::StdRunner,
+            scenario: &mut $scenario_type,
+            input: &[u8],
+            characterize: bool,
+        ) -> bool {
+            use fuzzamoto::runners::Runner;
 
-            let Ok(testcase) = <$testcase_type>::decode(&input) else {
+            let Ok(testcase) = <$testcase_type>::decode(input) else {
                 log::warn!("Failed to decode test case!");
                 // TODO drop(target);
                 runner.skip();
-                return ExitCode::SUCCESS;
+                return true;
             };
 
-            match scenario.run(testcase) {
+            // Traffic of the setup is not part of the test case
+            let setup_metrics = scenario.traffic_metrics().unwrap_or_default();
+
+            let start = std::time::Instant::now();
+            let result = scenario.run(testcase);
+            let duration = start.elapsed();
+
+            let metrics = scenario.traffic_metrics();
+            let (setup_sent, setup_received) =
+                (setup_metrics.total_sent(), setup_metrics.total_received());
+            let (sent, received) = metrics
+                .as_ref()
+                .map(|metrics| (metrics.total_sent(), metrics.total_received()))
+                .unwrap_or_default();
+            runner.report_metrics(&fuzzamoto::runners::ExecutionMetrics {
+                duration_micros: u64::try_from(duration.as_micros()).unwrap_or(u64::MAX),
+                bytes_sent: sent.bytes.saturating_sub(setup_sent.bytes),
+                messages_sent: sent.messages.saturating_sub(setup_sent.messages),
+                messages_received: received.messages.saturating_sub(setup_received.messages),
+            });
+
+            if let Some(metrics) = &metrics {
+                let sent = metrics.total_sent();
+                let received = metrics.total_received();
+                log::info!(
+                    "Traffic: sent {} messages ({} bytes), received {} messages ({} bytes)",
+                    sent.messages,
+                    sent.bytes,
+                    received.messages,
+                    received.bytes
+                );
+                log::debug!("Traffic per message type: {:?}", metrics);
+            }
+
+            // Characterizations cost RPCs to the target, so they are only collected if the
+            // fuzzer asked for them
+            if characterize {
+                let mut characterizations = scenario.characterizations();
+                if let Some(metrics) = &metrics {
+                    use fuzzamoto::scenarios::characterization::{
+                        Characterization, TrafficCharacterization,
+                    };
+                    characterizations.push(Characterization::Traffic(
+                        TrafficCharacterization::between(&setup_metrics, metrics),
+                    ));
+                }
+                if !characterizations.is_empty() {
+                    runner.characterize(&characterizations);
+                }
+            }
+
+            match result {
                 ScenarioResult::Ok => {}
                 ScenarioResult::Skip => {
                     // TODO drop(target);
                     runner.skip();
-                    return ExitCode::SUCCESS;
+                    return true;
                 }
                 ScenarioResult::Fail(err) => {
+                    // Sampling walks the data directory, so it's only done for failing test cases
+                    if log::log_enabled!(log::Level::Debug)
+                        && let Some(usage) = scenario.resource_usage()
+                    {
+                        log::debug!(
+                            "Resource usage: {} bytes rss, {} open fds, {} threads, {} bytes on disk",
+                            usage.rss_bytes,
+                            usage.open_fds,
+                            usage.threads,
+                            usage.disk_bytes
+                        );
+                    }
+                    if let Some(context) = scenario.crash_context() {
+                        runner.attach_context(&context);
+                    }
                     runner.fail(&format!("Test case failed: {}", err));
-                    return ExitCode::from(1);
+                    return false;
                 }
             }
 
             log::info!("Test case ran successfully!");
-            return ExitCode::SUCCESS;
+            true
+        }
+    };
+}
+
+/// Wrap a scenario into a libFuzzer style entry point, for smoke-fuzzing scenarios under
+/// cargo-fuzz or OSS-Fuzz without Nyx.
+///
+/// The scenario (and its target) is set up on the first input and reused for all following
+/// inputs, i.e. state carries over between inputs and crashes might not reproduce on their own
+/// (use `fuzzamoto-cli replay` for that). The target executable is taken from
+/// `FUZZAMOTO_TARGET`, all other scenario options from their environment variables (see
+/// `ScenarioArgs`), as libFuzzer owns the command line.
+///
+/// The target runs as a separate process, so libFuzzer's coverage feedback only covers the
+/// scenario process (decoding and message construction), not the target's code. Use Nyx for
+/// coverage guided fuzzing of the target.
+///
+/// Failing inputs abort the process, such that libFuzzer reports them as crashes, and inputs that
+/// fail to decode are rejected from the corpus.
+///
+/// `fuzzamoto_libfuzzer!(Scenario, TestCase)` defines `LLVMFuzzerTestOneInput` itself (e.g. for
+/// linking with `-fsanitize=fuzzer`), while `fuzzamoto_libfuzzer!(Scenario, TestCase, name)` only
+/// defines `fn name(data: &[u8]) -> i32`, to be called from cargo-fuzz's `fuzz_target!`.
+#[macro_export]
+macro_rules! fuzzamoto_libfuzzer {
+    ($scenario_type:ty, $testcase_type:ty) => {
+        $crate::fuzzamoto_libfuzzer!($scenario_type, $testcase_type, fuzzamoto_test_one_input);
+
+        /// # Safety
+        ///
+        /// `data` must point to `size` readable bytes (or be null), as guaranteed by libFuzzer.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn LLVMFuzzerTestOneInput(data: *const u8, size: usize) -> i32 {
+            let data = if data.is_null() || size == 0 {
+                &[][..]
+            } else {
+                unsafe { std::slice::from_raw_parts(data, size) }
+            };
+            fuzzamoto_test_one_input(data)
+        }
+    };
+    ($scenario_type:ty, $testcase_type:ty, $name:ident) => {
+        pub fn $name(data: &[u8]) -> i32 {
+            thread_local! {
+                static SCENARIO: std::cell::RefCell<Option<$scenario_type>> =
+                    const { std::cell::RefCell::new(None) };
+            }
+
+            SCENARIO.with_borrow_mut(|scenario| {
+                let scenario = scenario.get_or_insert_with(|| {
+                    let _ = env_logger::try_init();
+                    let target = std::env::var("FUZZAMOTO_TARGET")
+                        .expect("FUZZAMOTO_TARGET must point to the target executable");
+                    let args = vec!["fuzzamoto".to_string(), target];
+                    match $crate::scenarios::args::ScenarioArgs::from_args(&args)
+                        .and_then(|args| <$scenario_type>::new(&args))
+                    {
+                        Ok(scenario) => scenario,
+                        Err(e) => panic!("Failed to initialize scenario: {e}"),
+                    }
+                });
+
+                let Ok(testcase) = <$testcase_type>::decode(data) else {
+                    // Don't add undecodable inputs to the corpus
+                    return -1;
+                };
+
+                match scenario.run(testcase) {
+                    $crate::scenarios::ScenarioResult::Ok
+                    | $crate::scenarios::ScenarioResult::Skip => 0,
+                    $crate::scenarios::ScenarioResult::Fail(err) => {
+                        if let Some(context) = scenario.crash_context() {
+                            log::error!("{}", context.to_line());
+                        }
+                        log::error!("Test case failed: {err}");
+                        std::process::abort();
+                    }
+                }
+            })
         }
     };
 }
@@ -1,24 +1,111 @@
 use crate::{
-    connections::{Connection, ConnectionType, V1Transport, V2Transport},
+    chain::ChainParams,
+    connections::{
+        ChaosConfig, ChaosTransport, Connection, ConnectionType, I2pDestination, I2pSamTransport,
+        ProxiedTransport, SamBridge, V1Transport, V2Transport,
+    },
+    scenarios::args::ScenarioArgs,
     targets::{
         GenerateToAddress, HasBlockTemplate, HasGetBlock, HasGetRawMempoolEntries, HasTipInfo,
-        HasTxOutSetInfo, Target, TargetNode, Txid,
+        HasTxOutSetInfo, LogEvent, MempoolTx, PeerInfo, ResourceUsage, RpcTarget, Target,
+        TargetCapabilities, TargetNode, Txid, chain_tips_have_reorged, logs::LogTail,
     },
+    trace::{self, TraceEvent},
+    zmq::{ZmqNotification, ZmqSubscriber, ZmqTopic},
 };
 
-use bitcoin::{Amount, Block, BlockHash};
+use bitcoin::{Amount, Block, BlockHash, hashes::sha256};
 use corepc_node::{Conf, Node, P2P};
 use std::{
-    net::{SocketAddrV4, TcpListener, TcpStream},
+    io::{Read, Write},
+    net::{SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
 };
 
 use super::ConnectableTarget;
 
+/// Arguments every node is started with (before the node arguments of the scenario, see
+/// `ScenarioArgs::node_args`)
+pub(crate) const DEFAULT_NODE_ARGS: &[&str] = &[
+    "-txreconciliation",
+    "-peerbloomfilters",
+    "-peerblockfilters",
+    "-blockfilterindex",
+    "-rest",
+    "-par=4",
+    "-rpcthreads=4",
+    "-deprecatedrpc=create_bdb",
+    "-keypool=10",
+    "-listenonion=0",
+    "-i2pacceptincoming=0",
+    "-maxmempool=5", // 5MB
+    "-dbcache=4",    // 4MiB
+    "-datacarriersize=1000000",
+    "-peertimeout=31556952000",
+    "-noconnect",
+];
+
 pub struct BitcoinCoreTarget {
     pub node: Node,
+    /// Executable and arguments the node was started with (see `restart`)
+    exe_path: String,
+    args: Vec<String>,
     listeners: Vec<TcpListener>,
     time: u64,
+    chain_params: ChainParams,
+    /// Listener acting as the node's SOCKS5 proxy (see `from_path_with_proxy`)
+    proxy_listener: Option<TcpListener>,
+    /// SAM bridge acting as the node's I2P router (see `from_path_with_i2p_sam`)
+    sam_bridge: Option<SamBridge>,
+    /// Subscriber to the node's ZMQ notifications (see `from_path_with_zmq`)
+    zmq_subscriber: Option<ZmqSubscriber>,
+    /// ZMQ notifications received so far
+    zmq_notifications: Vec<ZmqNotification>,
+    /// Position in the node's `debug.log` (see `log_events`)
+    log_tail: LogTail,
+    /// Capabilities detected when the node was started
+    capabilities: TargetCapabilities,
+    /// Addresses of the node's `-whitebind` listeners (see `from_path_with_whitebinds`)
+    whitebinds: Vec<SocketAddrV4>,
+    /// Whitebind listener that inbound connections are made to, instead of the P2P port (see
+    /// `select_whitebind`)
+    inbound_whitebind: Option<usize>,
+}
+
+/// Environment variable with the path of the data directory snapshot nodes start from (see
+/// `BitcoinCoreTarget::start`)
+const DATADIR_SNAPSHOT_ENV: &str = "FUZZAMOTO_DATADIR_SNAPSHOT";
+
+/// Number of data directory snapshot copies made by this process (see `BitcoinCoreTarget::start`)
+static SNAPSHOT_COPIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of chaos connections made by this process, used as their seeds (see
+/// `Target<ChaosTransport<V1Transport>>`)
+static CHAOS_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Recursively copy the directory `from` to `to`.
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a data directory snapshot was requested with `FUZZAMOTO_DATADIR_SNAPSHOT` but does not
+/// exist yet, i.e. is created once the next target is dropped.
+#[must_use]
+pub fn datadir_snapshot_pending() -> bool {
+    std::env::var(DATADIR_SNAPSHOT_ENV).is_ok_and(|snapshot| !Path::new(&snapshot).exists())
 }
 
 // Gently stop the node when the target is dropped, if we are not using nyx.
@@ -26,6 +113,28 @@ pub struct BitcoinCoreTarget {
 impl Drop for BitcoinCoreTarget {
     fn drop(&mut self) {
         let _ = self.node.stop();
+
+        // Create the data directory snapshot requested with `FUZZAMOTO_DATADIR_SNAPSHOT`, if it
+        // does not exist yet
+        if let Ok(snapshot) = std::env::var(DATADIR_SNAPSHOT_ENV)
+            && !Path::new(&snapshot).exists()
+        {
+            // Copy to a temporary directory first, such that concurrent runs never start from a
+            // partial snapshot
+            let partial = PathBuf::from(format!("{snapshot}.{}", std::process::id()));
+            if let Err(e) = copy_dir(&self.node.workdir(), &partial)
+                .and_then(|()| std::fs::rename(&partial, &snapshot))
+            {
+                log::warn!("Failed to create data directory snapshot: {e}");
+                let _ = std::fs::remove_dir_all(&partial);
+            }
+        }
+
+        // Data directories of restarted nodes are not temporary (see `restart`)
+        let workdir = self.node.workdir();
+        if workdir.extension().is_some_and(|ext| ext == "restarted") {
+            let _ = std::fs::remove_dir_all(workdir);
+        }
     }
 }
 
@@ -43,8 +152,9 @@ impl BitcoinCoreTarget {
         Ok((listener, port))
     }
 
-    fn base_config() -> Conf<'static> {
+    fn base_config<'a>(chain_params: &ChainParams, node_args: &'a [String]) -> Conf<'a> {
         let mut config = Conf::default();
+        config.network = chain_params.chain;
         config.tmpdir = None;
         config.staticdir = None;
         config.p2p = P2P::Yes;
@@ -58,43 +168,378 @@ impl BitcoinCoreTarget {
             ]);
             config.view_stdout = true;
         }
-        config.args.extend_from_slice(&[
-            "-txreconciliation",
-            "-peerbloomfilters",
-            "-peerblockfilters",
-            "-blockfilterindex",
-            "-par=4",
-            "-rpcthreads=4",
-            "-deprecatedrpc=create_bdb",
-            "-keypool=10",
-            "-listenonion=0",
-            "-i2pacceptincoming=0",
-            "-maxmempool=5", // 5MB
-            "-dbcache=4",    // 4MiB
-            "-datacarriersize=1000000",
-            "-peertimeout=31556952000",
-            "-noconnect",
-        ]);
+        config.args.extend_from_slice(DEFAULT_NODE_ARGS);
+        config.args.extend(node_args.iter().map(String::as_str));
         config
     }
-}
 
-/// Transport-independent implementation for `BitcoinCoreTarget`
-impl TargetNode for BitcoinCoreTarget {
-    fn from_path(exe_path: &str) -> Result<Self, String> {
-        let config = Self::base_config();
+    /// Start the node described by `config`.
+    ///
+    /// If `FUZZAMOTO_DATADIR_SNAPSHOT` points to an existing directory, the node starts on a copy
+    /// of it instead of an empty data directory. Snapshots are created when a target is dropped
+    /// and the directory does not exist yet, e.g. by running a scenario once with an empty input,
+    /// such that later runs of the scenario setup find the blocks they mine already validated.
+    fn start(exe_path: &str, chain_params: &ChainParams, config: &Conf) -> Result<Self, String> {
+        let mut config = config.clone();
+        if let Ok(snapshot) = std::env::var(DATADIR_SNAPSHOT_ENV)
+            && Path::new(&snapshot).is_dir()
+        {
+            // The copy is removed on drop, like the data directories of restarted nodes
+            let datadir = std::env::temp_dir().join(format!(
+                "fuzzamoto-datadir-{}-{}.restarted",
+                std::process::id(),
+                SNAPSHOT_COPIES.fetch_add(1, Ordering::Relaxed)
+            ));
+            copy_dir(Path::new(&snapshot), &datadir)
+                .map_err(|e| format!("Failed to copy data directory snapshot: {e}"))?;
+            config.staticdir = Some(datadir);
+        }
 
         let node = Node::with_conf(exe_path, &config)
             .map_err(|e| format!("Failed to start node: {e:?}"))?;
 
+        let args: Vec<String> = config.args.iter().map(ToString::to_string).collect();
+        trace::record(TraceEvent::Start {
+            chain: chain_params.chain.to_string(),
+            args: args
+                .iter()
+                .filter(|arg| !DEFAULT_NODE_ARGS.contains(&arg.as_str()))
+                .cloned()
+                .collect(),
+        });
+        let network_info = node
+            .client
+            .call::<serde_json::Value>("getnetworkinfo", &[])
+            .map_err(|e| format!("Failed to get network info: {e:?}"))?;
+        let capabilities = TargetCapabilities::from_network_info(&network_info, &args)?;
+        log::info!("Detected target capabilities: {capabilities:?}");
+
         Ok(Self {
             node,
+            exe_path: exe_path.to_string(),
+            args,
             listeners: Vec::new(),
             time: u64::MAX,
+            chain_params: chain_params.clone(),
+            proxy_listener: None,
+            sam_bridge: None,
+            zmq_subscriber: None,
+            zmq_notifications: Vec::new(),
+            log_tail: LogTail::default(),
+            capabilities,
+            whitebinds: Vec::new(),
+            inbound_whitebind: None,
         })
     }
 
+    /// Path of `name` in the node's data directory, which is looked up in the chain specific
+    /// subdirectory (e.g. `regtest/`) as well.
+    fn datadir_file(&self, name: &str) -> Option<PathBuf> {
+        let workdir = self.node.workdir();
+        std::iter::once(workdir.clone())
+            .chain(
+                std::fs::read_dir(&workdir)
+                    .ok()?
+                    .flatten()
+                    .map(|entry| entry.path()),
+            )
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Create a target started with `node_args` in addition to the default arguments.
+    pub fn from_path_with_node_args(
+        exe_path: &str,
+        chain_params: &ChainParams,
+        node_args: &[String],
+    ) -> Result<Self, String> {
+        let config = Self::base_config(chain_params, node_args);
+        Self::start(exe_path, chain_params, &config)
+    }
+
+    /// Create a target for the scenario started with `extra_args` in addition to the default and
+    /// scenario node arguments (e.g. `-txreconciliation` to enable optional features for a
+    /// scenario).
+    pub fn from_path_with_args(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config.args.extend_from_slice(extra_args);
+
+        Self::start(&args.target, chain_params, &config)
+    }
+
+    /// Create a target that uses the fuzzer as its SOCKS5 proxy (`-proxy`), such that the node's
+    /// proxy handling can be exercised with `connect_proxied`.
+    ///
+    /// Note that all outbound connections of the node are routed through the proxy, so outbound
+    /// connections should be created with `connect_proxied` instead of `Target::connect`.
+    pub fn from_path_with_proxy(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let (proxy_listener, proxy_port) = Self::create_listener()?;
+        let proxy_arg = format!("-proxy=127.0.0.1:{proxy_port}");
+
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config.args.push(&proxy_arg);
+        config.args.extend_from_slice(extra_args);
+
+        let mut target = Self::start(&args.target, chain_params, &config)?;
+        target.proxy_listener = Some(proxy_listener);
+        Ok(target)
+    }
+
+    /// Make the node open an outbound connection to `destination` (e.g. an onion address) and
+    /// accept it on the node's proxy.
+    pub fn connect_proxied(
+        &mut self,
+        destination: &str,
+    ) -> Result<Connection<ProxiedTransport>, String> {
+        let Some(proxy_listener) = &self.proxy_listener else {
+            return Err("Target was not started with a proxy".to_string());
+        };
+
+        self.node
+            .client
+            .call::<serde_json::Value>(
+                "addconnection",
+                &[
+                    destination.into(),
+                    "outbound-full-relay".into(),
+                    false.into(), // no v2
+                ],
+            )
+            .map_err(|e| format!("Failed to initiate proxied connection: {e:?}"))?;
+
+        let (socket, _addr) = proxy_listener
+            .accept()
+            .map_err(|e| format!("Failed to accept proxied connection: {e}"))?;
+        socket
+            .set_nodelay(true)
+            .expect("Failed to set nodelay on proxied socket");
+
+        Ok(Connection::new(
+            ConnectionType::Outbound,
+            ProxiedTransport::accept(socket, &self.chain_params)?,
+        ))
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Create a target that uses the fuzzer as its I2P SAM bridge (`-i2psam`) and accepts
+    /// incoming I2P connections, such that the node's I2P code can be exercised with
+    /// `connect_i2p`.
+    pub fn from_path_with_i2p_sam(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let sam_bridge = SamBridge::bind(([127, 0, 0, 1], 0).into(), chain_params)?;
+        let sam_arg = format!("-i2psam={}", sam_bridge.local_addr()?);
+
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config.args.push(&sam_arg);
+        config.args.push("-i2pacceptincoming=1");
+        config.args.extend_from_slice(extra_args);
+
+        let mut target = Self::start(&args.target, chain_params, &config)?;
+        target.sam_bridge = Some(sam_bridge);
+        Ok(target)
+    }
+
+    /// Create a connection to the node over I2P, with `peer` as the remote destination.
+    ///
+    /// Inbound connections are handed to the node's pending `STREAM ACCEPT`, for outbound
+    /// connections the node is made to connect to `peer`'s b32 address.
+    pub fn connect_i2p(
+        &mut self,
+        connection_type: ConnectionType,
+        peer: &I2pDestination,
+    ) -> Result<Connection<I2pSamTransport>, String> {
+        let Some(sam_bridge) = &mut self.sam_bridge else {
+            return Err("Target was not started with a SAM bridge".to_string());
+        };
+
+        let transport = match connection_type {
+            ConnectionType::Inbound => sam_bridge.accept_inbound(peer)?,
+            ConnectionType::Outbound => {
+                sam_bridge.add_destination(peer.clone());
+                // I2P addresses always use port 0 with SAM 3.1
+                self.node
+                    .client
+                    .call::<serde_json::Value>(
+                        "addconnection",
+                        &[
+                            format!("{}:0", peer.b32_address()).into(),
+                            "outbound-full-relay".into(),
+                            false.into(), // no v2
+                        ],
+                    )
+                    .map_err(|e| format!("Failed to initiate i2p connection: {e:?}"))?;
+                sam_bridge.accept_outbound()?
+            }
+        };
+
+        Ok(Connection::new(connection_type, transport))
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Create a target publishing `rawblock`, `rawtx` and `sequence` ZMQ notifications, which
+    /// are captured by the fuzzer and exposed through `TargetNode::zmq_notifications`.
+    pub fn from_path_with_zmq(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let (zmq_addr, zmq_args) = Self::zmq_args()?;
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config.args.extend(zmq_args.iter().map(String::as_str));
+        config.args.extend_from_slice(extra_args);
+
+        let mut target = Self::start(&args.target, chain_params, &config)?;
+        target.zmq_subscriber = Some(ZmqSubscriber::connect(zmq_addr, &ZmqTopic::ALL)?);
+        Ok(target)
+    }
+
+    /// Reserve an address for the node's ZMQ publisher, returning it with the node arguments
+    /// publishing all topics to it.
+    fn zmq_args() -> Result<(SocketAddr, Vec<String>), String> {
+        // Reserve a port for the publisher, the node binds it once the listener is dropped
+        let (listener, port) = Self::create_listener()?;
+        drop(listener);
+        let zmq_addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let zmq_args = ZmqTopic::ALL
+            .iter()
+            .map(|topic| topic.node_arg(&zmq_addr))
+            .collect();
+        Ok((zmq_addr, zmq_args))
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Create a target with a `-whitebind` listener per entry of `permissions` (e.g.
+    /// `"noban,mempool"`), such that connections with different net permissions can be made to
+    /// the same node (see `select_whitebind`).
+    pub fn from_path_with_whitebinds(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        permissions: &[&str],
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let mut whitebinds = Vec::new();
+        let mut whitebind_args = Vec::new();
+        for permission in permissions {
+            // Reserve a port for the listener, the node binds it once the listener is dropped
+            let (listener, port) = Self::create_listener()?;
+            drop(listener);
+            let addr = SocketAddrV4::new([127, 0, 0, 1].into(), port);
+            whitebind_args.push(format!("-whitebind={permission}@{addr}"));
+            whitebinds.push(addr);
+        }
+
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config
+            .args
+            .extend(whitebind_args.iter().map(String::as_str));
+        config.args.extend_from_slice(extra_args);
+
+        let mut target = Self::start(&args.target, chain_params, &config)?;
+        target.whitebinds = whitebinds;
+        Ok(target)
+    }
+
+    /// Make subsequent inbound connections (`Target::connect`) to the whitebind listener at
+    /// `index` (in the order of the permissions passed to `from_path_with_whitebinds`), or to the
+    /// regular P2P port for `None`.
+    pub fn select_whitebind(&mut self, index: Option<usize>) -> Result<(), String> {
+        if let Some(index) = index
+            && index >= self.whitebinds.len()
+        {
+            return Err(format!("Target has no whitebind listener {index}"));
+        }
+        self.inbound_whitebind = index;
+        Ok(())
+    }
+
+    /// Address inbound connections are made to (see `select_whitebind`)
+    fn inbound_addr(&self) -> Result<SocketAddrV4, String> {
+        match self.inbound_whitebind {
+            Some(index) => Ok(self.whitebinds[index]),
+            None => self
+                .node
+                .params
+                .p2p_socket
+                .ok_or_else(|| "P2P socket address not available".to_string()),
+        }
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Create a target running as a pruned node (`-prune=1`, i.e. pruning is triggered manually
+    /// with `prune_blockchain`). Block files are kept small (`-fastprune`, regtest only), such
+    /// that a few hundred blocks are enough to prune some of them.
+    pub fn from_path_pruned(
+        args: &ScenarioArgs,
+        chain_params: &ChainParams,
+        extra_args: &[&str],
+    ) -> Result<Self, String> {
+        let mut config = Self::base_config(chain_params, &args.node_args);
+        config.args.extend_from_slice(&["-prune=1", "-fastprune"]);
+        config.args.extend_from_slice(extra_args);
+
+        Self::start(&args.target, chain_params, &config)
+    }
+
+    /// Prune the block files up to `height` (the node always keeps the most recent 288 blocks),
+    /// returning the height of the last pruned block (`None` if no block was pruned).
+    pub fn prune_blockchain(&self, height: u64) -> Result<Option<u64>, String> {
+        let pruned = self
+            .node
+            .client
+            .call::<i64>("pruneblockchain", &[height.into()])
+            .map_err(|e| format!("Failed to prune blockchain: {e:?}"))?;
+        Ok(u64::try_from(pruned).ok())
+    }
+}
+
+/// Transport-independent implementation for `BitcoinCoreTarget`
+impl TargetNode for BitcoinCoreTarget {
+    fn from_path(exe_path: &str) -> Result<Self, String> {
+        Self::from_path_with_chain(exe_path, &ChainParams::regtest())
+    }
+
+    fn from_path_with_chain(exe_path: &str, chain_params: &ChainParams) -> Result<Self, String> {
+        Self::from_path_with_node_args(exe_path, chain_params, &[])
+    }
+
+    fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        let chain_params = args.chain_params()?;
+        let zmq = args.zmq.then(Self::zmq_args).transpose()?;
+        let mut config = Self::base_config(&chain_params, &args.node_args);
+        if let Some((_, zmq_args)) = &zmq {
+            config.args.extend(zmq_args.iter().map(String::as_str));
+        }
+
+        let mut target = Self::start(&args.target, &chain_params, &config)?;
+        if let Some((zmq_addr, _)) = &zmq {
+            target.zmq_subscriber = Some(ZmqSubscriber::connect(*zmq_addr, &ZmqTopic::ALL)?);
+        }
+        Ok(target)
+    }
+
+    fn chain_params(&self) -> ChainParams {
+        self.chain_params.clone()
+    }
+
     fn set_mocktime(&mut self, time: u64) -> Result<(), String> {
+        trace::record(TraceEvent::SetMocktime(time));
         let client = &self.node.client;
 
         if self.time != u64::MAX && time > self.time {
@@ -130,30 +575,172 @@ Can you-"
 
         Ok(())
     }
+
+    fn load_utxo_snapshot(&mut self, path: &Path) -> Result<(), String> {
+        let result = self
+            .node
+            .client
+            .call::<serde_json::Value>("loadtxoutset", &[path.to_string_lossy().into()])
+            .map_err(|e| {
+                format!(
+                    "Failed to load utxo snapshot (it has to be listed in the target's regtest \
+                     m_assumeutxo_data, see dump_utxo_snapshot): {e:?}"
+                )
+            })?;
+        log::info!("Loaded utxo snapshot: {result}");
+        Ok(())
+    }
+
+    fn dump_utxo_snapshot(&self, path: &Path) -> Result<(), String> {
+        let result = self
+            .node
+            .client
+            .call::<serde_json::Value>(
+                "dumptxoutset",
+                &[path.to_string_lossy().into(), "latest".into()],
+            )
+            .map_err(|e| format!("Failed to dump utxo snapshot: {e:?}"))?;
+        log::info!("Dumped utxo snapshot: {result}");
+        // Bitcoin Core only loads snapshots listed in its chain parameters, which for regtest
+        // requires a patched target
+        log::info!(
+            "Add the snapshot to m_assumeutxo_data in CRegTestParams to load it: {{ .height = {}, \
+             .hash_serialized = AssumeutxoHash{{uint256{{\"{}\"}}}}, .m_chain_tx_count = {}, \
+             .blockhash = consteval_ctor(uint256{{\"{}\"}}) }}",
+            result["base_height"],
+            result["txoutset_hash"].as_str().unwrap_or_default(),
+            result["nchaintx"],
+            result["base_hash"].as_str().unwrap_or_default(),
+        );
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<(), String> {
+        trace::record(TraceEvent::Restart);
+        let workdir = self.node.workdir();
+        self.node
+            .stop()
+            .map_err(|e| format!("Failed to stop node: {e:?}"))?;
+
+        // The node's temporary data directory is removed once the old node is dropped, so move
+        // it out of the way first (nodes started on a static data directory keep it).
+        let datadir = if workdir.extension().is_some_and(|ext| ext == "restarted") {
+            workdir
+        } else {
+            let datadir = workdir.with_extension("restarted");
+            std::fs::rename(&workdir, &datadir)
+                .map_err(|e| format!("Failed to move data directory: {e}"))?;
+            datadir
+        };
+
+        let mut config = Self::base_config(&self.chain_params, &[]);
+        config.args = self.args.iter().map(String::as_str).collect();
+        config.staticdir = Some(datadir);
+        self.node = Node::with_conf(&self.exe_path, &config)
+            .map_err(|e| format!("Failed to restart node: {e:?}"))?;
+        self.listeners.clear();
+
+        if self.time != u64::MAX {
+            self.node
+                .client
+                .call::<()>("setmocktime", &[self.time.into()])
+                .map_err(|e| format!("Failed to set mocktime: {e:?}"))?;
+        }
+
+        if let Some(subscriber) = &self.zmq_subscriber {
+            // Sequence numbers start over after a restart
+            self.zmq_subscriber = Some(ZmqSubscriber::connect(subscriber.addr(), &ZmqTopic::ALL)?);
+            self.zmq_notifications.clear();
+        }
+
+        Ok(())
+    }
+
+    fn log_events(&mut self) -> Result<Vec<LogEvent>, String> {
+        let path = self
+            .datadir_file("debug.log")
+            .ok_or_else(|| "Failed to find debug.log of node".to_string())?;
+        self.log_tail.read_events(&path)
+    }
+
+    fn zmq_notifications(&mut self) -> Result<&[ZmqNotification], String> {
+        const ZMQ_RECEIVE_TIMEOUT: Duration = Duration::from_millis(50);
+
+        let Some(subscriber) = &mut self.zmq_subscriber else {
+            return Err("Target was not started with ZMQ notifications".to_string());
+        };
+
+        // Make sure all pending notifications have been published
+        self.node
+            .client
+            .call::<()>("syncwithvalidationinterfacequeue", &[])
+            .map_err(|e| format!("Failed to sync with validation interface queue: {e:?}"))?;
+
+        let notifications = subscriber.receive(ZMQ_RECEIVE_TIMEOUT)?;
+        self.zmq_notifications.extend(notifications);
+        Ok(&self.zmq_notifications)
+    }
+
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        let pid = std::fs::read_to_string(self.datadir_file("bitcoind.pid")?)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+
+        ResourceUsage::sample(pid, &self.node.workdir())
+            .inspect_err(|e| log::warn!("Failed to sample resource usage: {e}"))
+            .ok()
+    }
+
+    fn capabilities(&self) -> TargetCapabilities {
+        self.capabilities.clone()
+    }
+
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, String> {
+        let peers = self
+            .node
+            .client
+            .call::<serde_json::Value>("getpeerinfo", &[])
+            .map_err(|e| format!("Failed to get peer info: {e:?}"))?;
+        peers
+            .as_array()
+            .ok_or_else(|| "Malformed peer info".to_string())?
+            .iter()
+            .map(PeerInfo::from_rpc)
+            .collect()
+    }
+
+    fn mempool_contents(&self) -> Result<Vec<MempoolTx>, String> {
+        let mempool = self
+            .node
+            .client
+            .call::<serde_json::Value>("getrawmempool", &[true.into()])
+            .map_err(|e| format!("Failed to get mempool contents: {e:?}"))?;
+        mempool
+            .as_object()
+            .ok_or_else(|| "Malformed mempool contents".to_string())?
+            .iter()
+            .map(|(txid, entry)| MempoolTx::from_rpc(txid, entry))
+            .collect()
+    }
 }
 
-impl Target<V1Transport> for BitcoinCoreTarget {
-    fn connect(
-        &mut self,
-        connection_type: ConnectionType,
-    ) -> Result<Connection<V1Transport>, String> {
+impl BitcoinCoreTarget {
+    /// Open a v1 connection socket of the given type, i.e. connect to the node's P2P port for
+    /// inbound connections or have the node connect to a new listener for outbound connections.
+    fn connect_v1_socket(&mut self, connection_type: &ConnectionType) -> Result<TcpStream, String> {
         match connection_type {
             ConnectionType::Inbound => {
-                // For inbound, connect directly to the P2P port
-                let p2p_socket = self
-                    .node
-                    .params
-                    .p2p_socket
-                    .ok_or_else(|| "P2P socket address not available".to_string())?;
-                let socket = TcpStream::connect(p2p_socket)
+                // For inbound, connect directly to the P2P port (or the selected whitebind)
+                let socket = TcpStream::connect(self.inbound_addr()?)
                     .map_err(|e| format!("Failed to connect to P2P port: {e}"))?;
                 // Disable Nagle's algorithm, since most of the time we're sending small messages and
                 // we want to reduce latency when fuzzing.
                 socket
                     .set_nodelay(true)
                     .expect("Failed to set nodelay on inbound socket");
-
-                Ok(Connection::new(connection_type, V1Transport { socket }))
+                Ok(socket)
             }
             ConnectionType::Outbound => {
                 let (listener, port) = Self::create_listener()?;
@@ -180,11 +767,23 @@ impl Target<V1Transport> for BitcoinCoreTarget {
                 socket
                     .set_nodelay(true)
                     .expect("Failed to set nodelay on outbound socket");
-
-                Ok(Connection::new(connection_type, V1Transport { socket }))
+                Ok(socket)
             }
         }
     }
+}
+
+impl Target<V1Transport> for BitcoinCoreTarget {
+    fn connect(
+        &mut self,
+        connection_type: ConnectionType,
+    ) -> Result<Connection<V1Transport>, String> {
+        let socket = self.connect_v1_socket(&connection_type)?;
+        Ok(Connection::new(
+            connection_type,
+            V1Transport::with_chain_params(socket, &self.chain_params),
+        ))
+    }
 
     fn connect_to<O: ConnectableTarget>(&mut self, other: &O) -> Result<(), String> {
         if let Some(addr) = other.get_addr() {
@@ -207,20 +806,46 @@ impl Target<V1Transport> for BitcoinCoreTarget {
     }
 }
 
+/// Connections injecting transport faults (see `ChaosTransport`). Each connection gets its own
+/// seed, such that runs are reproducible.
+impl Target<ChaosTransport<V1Transport>> for BitcoinCoreTarget {
+    fn connect(
+        &mut self,
+        connection_type: ConnectionType,
+    ) -> Result<Connection<ChaosTransport<V1Transport>>, String> {
+        let socket = self.connect_v1_socket(&connection_type)?;
+        let config = ChaosConfig {
+            seed: CHAOS_CONNECTIONS.fetch_add(1, Ordering::Relaxed),
+            coalesce_probability: 0.25,
+            ..Default::default()
+        };
+        Ok(Connection::new(
+            connection_type,
+            ChaosTransport::new(
+                V1Transport::with_chain_params(socket, &self.chain_params),
+                &self.chain_params,
+                config,
+            ),
+        ))
+    }
+
+    fn connect_to<O: ConnectableTarget>(&mut self, other: &O) -> Result<(), String> {
+        <Self as Target<V1Transport>>::connect_to(self, other)
+    }
+}
+
 impl Target<V2Transport> for BitcoinCoreTarget {
     fn connect(
         &mut self,
         connection_type: ConnectionType,
     ) -> Result<Connection<V2Transport>, String> {
+        if !self.capabilities.v2_transport {
+            return Err("Target does not support the v2 transport (BIP324)".to_string());
+        }
         match connection_type {
             ConnectionType::Inbound => {
-                // For inbound, connect directly to the P2P port
-                let p2p_socket = self
-                    .node
-                    .params
-                    .p2p_socket
-                    .ok_or_else(|| "P2P socket address not available".to_string())?;
-                let socket = TcpStream::connect(p2p_socket)
+                // For inbound, connect directly to the P2P port (or the selected whitebind)
+                let socket = TcpStream::connect(self.inbound_addr()?)
                     .map_err(|e| format!("Failed to connect to P2P port: {e}"))?;
                 // Disable Nagle's algorithm, since most of the time we're sending small messages and
                 // we want to reduce latency when fuzzing.
@@ -230,7 +855,7 @@ impl Target<V2Transport> for BitcoinCoreTarget {
 
                 Ok(Connection::new(
                     connection_type,
-                    V2Transport::new(socket, bip324::Role::Initiator)?,
+                    V2Transport::new(socket, bip324::Role::Initiator, &self.chain_params)?,
                 ))
             }
             ConnectionType::Outbound => {
@@ -261,7 +886,7 @@ impl Target<V2Transport> for BitcoinCoreTarget {
 
                 Ok(Connection::new(
                     connection_type,
-                    V2Transport::new(socket, bip324::Role::Responder)?,
+                    V2Transport::new(socket, bip324::Role::Responder, &self.chain_params)?,
                 ))
             }
         }
@@ -318,6 +943,23 @@ impl ConnectableTarget for BitcoinCoreTarget {
     }
 }
 
+impl RpcTarget for BitcoinCoreTarget {
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        trace::record(TraceEvent::Rpc {
+            method: method.to_string(),
+            params: params.to_vec(),
+        });
+        self.node
+            .client
+            .call::<serde_json::Value>(method, params)
+            .map_err(|e| format!("RPC {method} failed: {e:?}"))
+    }
+}
+
 impl HasTipInfo for BitcoinCoreTarget {
     fn get_tip_info(&self) -> Option<(BlockHash, u64)> {
         let height = match self.node.client.get_block_count() {
@@ -331,6 +973,82 @@ impl HasTipInfo for BitcoinCoreTarget {
         };
         Some((hash, height))
     }
+
+    fn has_reorged(&self) -> Result<bool, String> {
+        chain_tips_have_reorged(&self.call_rpc("getchaintips", &[])?)
+    }
+}
+
+/// Response format requested from the REST interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestFormat {
+    Binary,
+    Hex,
+    Json,
+}
+
+impl RestFormat {
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            RestFormat::Binary => "bin",
+            RestFormat::Hex => "hex",
+            RestFormat::Json => "json",
+        }
+    }
+}
+
+/// Response of the REST interface
+#[derive(Debug, Clone)]
+pub struct RestResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl RestResponse {
+    pub(crate) fn parse(response: &[u8]) -> Result<Self, String> {
+        let header_len = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| "Malformed HTTP response".to_string())?;
+        let header = String::from_utf8_lossy(&response[..header_len]);
+        let status = header
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| format!("Malformed HTTP status line: {header}"))?;
+
+        Ok(Self {
+            status,
+            body: response[header_len + 4..].to_vec(),
+        })
+    }
+}
+
+impl BitcoinCoreTarget {
+    /// Issue a `GET` request for `path` (including the query string, e.g.
+    /// `/rest/headers/<hash>.json?count=5`) to the node's REST interface.
+    pub fn rest_request(&self, path: &str) -> Result<RestResponse, String> {
+        const REST_TIMEOUT: Duration = Duration::from_secs(10);
+
+        let mut stream = TcpStream::connect(self.node.params.rpc_socket)
+            .map_err(|e| format!("Failed to connect to REST interface: {e}"))?;
+        stream
+            .set_read_timeout(Some(REST_TIMEOUT))
+            .map_err(|e| format!("Failed to set REST read timeout: {e}"))?;
+
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .map_err(|e| format!("Failed to send REST request: {e}"))?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|e| format!("Failed to read REST response: {e}"))?;
+        RestResponse::parse(&response)
+    }
 }
 
 impl HasGetBlock for BitcoinCoreTarget {
@@ -365,17 +1083,26 @@ impl MempoolEntry {
 
 impl HasGetRawMempoolEntries for BitcoinCoreTarget {
     fn get_mempool_entries(&self) -> Result<Vec<MempoolEntry>, String> {
-        let mut ret_vec = vec![];
         let rawmempool = self
             .node
             .client
             .call::<serde_json::Value>("getrawmempool", &[serde_json::Value::Bool(true)])
             .map_err(|e| format!("Failed to request rawmempool {e:?}"))?;
+        MempoolEntry::parse_raw_mempool(&rawmempool)
+    }
+}
+
+impl MempoolEntry {
+    /// Parse the entries of a verbose `getrawmempool` result.
+    pub(crate) fn parse_raw_mempool(
+        rawmempool: &serde_json::Value,
+    ) -> Result<Vec<MempoolEntry>, String> {
+        let mut ret_vec = vec![];
         let serde_json::Value::Object(rawmempool) = rawmempool else {
             return Err("Failed to request txoutsetinfo".to_string());
         };
 
-        for (key, value) in &rawmempool {
+        for (key, value) in rawmempool {
             let txid = Txid::from_str(key).map_err(|e| format!("Failed to decode txid {e:?}"))?;
 
             let mut mempool = MempoolEntry {
@@ -438,6 +1165,36 @@ impl TxOutSetInfo {
     }
 }
 
+/// `MuHash3072` commitment to the UTXO set (`gettxoutsetinfo muhash`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TxOutSetMuHash {
+    /// Hash of the block the UTXO set was computed at
+    pub best_block: BlockHash,
+    pub muhash: sha256::Hash,
+}
+
+impl TxOutSetMuHash {
+    /// Parse a `gettxoutsetinfo muhash` result.
+    pub(crate) fn from_rpc(txoutsetinfo: &serde_json::Value) -> Result<Self, String> {
+        let Some(best_block) = txoutsetinfo
+            .get("bestblock")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|hash| BlockHash::from_str(hash).ok())
+        else {
+            return Err("txoutsetinfo returns invalid bestblock".to_string());
+        };
+        let Some(muhash) = txoutsetinfo
+            .get("muhash")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|hash| sha256::Hash::from_str(hash).ok())
+        else {
+            return Err("txoutsetinfo returns invalid muhash".to_string());
+        };
+
+        Ok(Self { best_block, muhash })
+    }
+}
+
 impl HasTxOutSetInfo for BitcoinCoreTarget {
     fn tx_out_set_info(&self) -> Result<TxOutSetInfo, String> {
         let txoutsetinfo = self
@@ -445,7 +1202,17 @@ impl HasTxOutSetInfo for BitcoinCoreTarget {
             .client
             .call::<serde_json::Value>("gettxoutsetinfo", &[])
             .map_err(|e| format!("Failed to request txoutsetinfo: {e:?}"))?;
+        TxOutSetInfo::from_rpc(&txoutsetinfo)
+    }
 
+    fn tx_out_set_muhash(&self) -> Result<TxOutSetMuHash, String> {
+        TxOutSetMuHash::from_rpc(&self.call_rpc("gettxoutsetinfo", &["muhash".into()])?)
+    }
+}
+
+impl TxOutSetInfo {
+    /// Parse a `gettxoutsetinfo` result.
+    pub(crate) fn from_rpc(txoutsetinfo: &serde_json::Value) -> Result<Self, String> {
         let serde_json::Value::Object(info) = txoutsetinfo else {
             return Err("Failed to request txoutsetinfo".to_string());
         };
@@ -492,6 +1259,7 @@ impl HasBlockTemplate for BitcoinCoreTarget {
 
 impl GenerateToAddress for BitcoinCoreTarget {
     fn generate_to_address(&self, address: &str) -> Result<(), String> {
+        trace::record(TraceEvent::GenerateToAddress(address.to_string()));
         let checked_addr = if let Ok(addr) = bitcoin::Address::from_str(address) {
             addr.require_network(bitcoin::Network::Regtest)
                 .map_err(|e| format!("Network mismatch: {e}"))?
@@ -0,0 +1,168 @@
+/// First Bitcoin Core version supporting BIP155 `addrv2` messages (0.21.0)
+const ADDRV2_VERSION: u64 = 210_000;
+/// First Bitcoin Core version implementing Erlay's `sendtxrcncl` negotiation (25.0), which is only
+/// enabled with `-txreconciliation`
+const ERLAY_VERSION: u64 = 250_000;
+/// First Bitcoin Core version with opportunistic one-parent-one-child package relay (28.0)
+const PACKAGE_RELAY_VERSION: u64 = 280_000;
+
+/// Version and optional P2P features supported by a target (see `TargetNode::capabilities`).
+///
+/// Scenarios and generators consult these to avoid emitting messages the target doesn't
+/// understand. The default describes a target of unknown version that supports everything, such
+/// that targets without capability detection are fuzzed with all features.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TargetCapabilities {
+    /// Numeric client version (e.g. `290000` for Bitcoin Core 29.0), if known
+    pub version: Option<u64>,
+    /// User agent of the target (e.g. `/Satoshi:29.0.0/`), if known
+    pub subversion: Option<String>,
+    /// BIP330 transaction reconciliation (`sendtxrcncl`, `reqtxrcncl`, `sketch`, ...)
+    pub erlay: bool,
+    /// BIP324 v2 transport
+    pub v2_transport: bool,
+    /// Relay of one-parent-one-child packages (e.g. a zero fee parent bumped by its child)
+    pub package_relay: bool,
+    /// BIP155 `sendaddrv2` and `addrv2` messages
+    pub addrv2: bool,
+}
+
+impl Default for TargetCapabilities {
+    fn default() -> Self {
+        Self {
+            version: None,
+            subversion: None,
+            erlay: true,
+            v2_transport: true,
+            package_relay: true,
+            addrv2: true,
+        }
+    }
+}
+
+impl TargetCapabilities {
+    /// Detect the capabilities of a Bitcoin Core node from its `getnetworkinfo` result and the
+    /// arguments it was started with.
+    pub fn from_network_info(info: &serde_json::Value, args: &[String]) -> Result<Self, String> {
+        let version = info["version"]
+            .as_u64()
+            .ok_or_else(|| "Missing version in network info".to_string())?;
+        let subversion = info["subversion"].as_str().map(ToString::to_string);
+        let v2_transport = info["localservicesnames"]
+            .as_array()
+            .is_some_and(|services| services.iter().any(|s| s.as_str() == Some("P2P_V2")));
+
+        Ok(Self {
+            version: Some(version),
+            subversion,
+            erlay: version >= ERLAY_VERSION && flag_enabled(args, "-txreconciliation"),
+            v2_transport,
+            package_relay: version >= PACKAGE_RELAY_VERSION,
+            addrv2: version >= ADDRV2_VERSION,
+        })
+    }
+
+    /// Restrict the capabilities to those also supported by `other`, e.g. to get the features
+    /// supported by all nodes of a network.
+    pub fn restrict(&mut self, other: &TargetCapabilities) {
+        self.version = match (self.version, other.version) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.subversion = self.subversion.take().or_else(|| other.subversion.clone());
+        self.erlay &= other.erlay;
+        self.v2_transport &= other.v2_transport;
+        self.package_relay &= other.package_relay;
+        self.addrv2 &= other.addrv2;
+    }
+}
+
+/// Whether the boolean argument `name` is enabled in `args` (the last occurrence wins, as for
+/// Bitcoin Core's argument parsing).
+fn flag_enabled(args: &[String], name: &str) -> bool {
+    args.iter()
+        .rev()
+        .find_map(|arg| {
+            let negated = format!("-no{}", name.trim_start_matches('-'));
+            if arg == name {
+                Some(true)
+            } else if arg == &negated {
+                Some(false)
+            } else {
+                arg.strip_prefix(name)
+                    .and_then(|value| value.strip_prefix('='))
+                    .map(|value| value != "0")
+            }
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn detect_from_network_info() {
+        let info = serde_json::json!({
+            "version": 290000,
+            "subversion": "/Satoshi:29.0.0/",
+            "localservicesnames": ["NETWORK", "WITNESS", "NETWORK_LIMITED", "P2P_V2"],
+        });
+        let capabilities =
+            TargetCapabilities::from_network_info(&info, &args(&["-txreconciliation"])).unwrap();
+        assert_eq!(
+            capabilities,
+            TargetCapabilities {
+                version: Some(290_000),
+                subversion: Some("/Satoshi:29.0.0/".to_string()),
+                erlay: true,
+                v2_transport: true,
+                package_relay: true,
+                addrv2: true,
+            }
+        );
+
+        let info = serde_json::json!({
+            "version": 240000,
+            "subversion": "/Satoshi:24.0.0/",
+            "localservicesnames": ["NETWORK", "WITNESS"],
+        });
+        let capabilities =
+            TargetCapabilities::from_network_info(&info, &args(&["-txreconciliation"])).unwrap();
+        assert!(!capabilities.erlay);
+        assert!(!capabilities.v2_transport);
+        assert!(!capabilities.package_relay);
+        assert!(capabilities.addrv2);
+
+        assert!(TargetCapabilities::from_network_info(&serde_json::json!({}), &[]).is_err());
+    }
+
+    #[test]
+    fn last_flag_wins() {
+        assert!(flag_enabled(
+            &args(&["-txreconciliation"]),
+            "-txreconciliation"
+        ));
+        assert!(!flag_enabled(&args(&["-rest"]), "-txreconciliation"));
+        assert!(!flag_enabled(
+            &args(&["-txreconciliation", "-txreconciliation=0"]),
+            "-txreconciliation"
+        ));
+        assert!(!flag_enabled(
+            &args(&["-txreconciliation=1", "-notxreconciliation"]),
+            "-txreconciliation"
+        ));
+        assert!(flag_enabled(
+            &args(&["-txreconciliation=0", "-txreconciliation=1"]),
+            "-txreconciliation"
+        ));
+        assert!(!flag_enabled(
+            &args(&["-txreconciliationfoo"]),
+            "-txreconciliation"
+        ));
+    }
+}
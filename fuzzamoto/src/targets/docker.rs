@@ -0,0 +1,462 @@
+use crate::{
+    chain::ChainParams,
+    connections::{Connection, ConnectionType, V1Transport, V2Transport},
+    scenarios::args::ScenarioArgs,
+    targets::{
+        ConnectableTarget, GenerateToAddress, HasBlockTemplate, HasGetBlock,
+        HasGetRawMempoolEntries, HasTipInfo, HasTxOutSetInfo, MempoolTx, PeerInfo, RestResponse,
+        RpcTarget, Target, TargetCapabilities, TargetNode,
+        bitcoin_core::{DEFAULT_NODE_ARGS, MempoolEntry, TxOutSetInfo, TxOutSetMuHash},
+        chain_tips_have_reorged,
+    },
+};
+
+use bitcoin::{Block, BlockHash, consensus::encode};
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream},
+    process::Command,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// Ports the node listens on inside the container, which are published to random ports on the
+/// host's loopback interface.
+const P2P_PORT: u16 = 18444;
+const RPC_PORT: u16 = 18443;
+
+/// RPC credentials of the node (`fuzzamoto:fuzzamoto`, base64 encoded for basic auth)
+const RPC_USER: &str = "fuzzamoto";
+const RPC_PASSWORD: &str = "fuzzamoto";
+const RPC_AUTH: &str = "ZnV6emFtb3RvOmZ1enphbW90bw==";
+
+/// `DockerTarget` runs Bitcoin Core inside a docker container (e.g. the image built for the Nyx
+/// sharedir), such that testcases can be reproduced locally against the exact same binary and
+/// runtime dependencies that were fuzzed.
+///
+/// The node's p2p and RPC ports are published to the host, so connections and RPCs work just
+/// like for `BitcoinCoreTarget`. Outbound connections are made to listeners on the host's address
+/// on the docker network.
+pub struct DockerTarget {
+    container_id: String,
+    p2p_socket: SocketAddrV4,
+    rpc_socket: SocketAddrV4,
+    /// Address of the host as seen from inside the container
+    host_ip: Ipv4Addr,
+    listeners: Vec<TcpListener>,
+    time: u64,
+    chain_params: ChainParams,
+    capabilities: TargetCapabilities,
+}
+
+impl Drop for DockerTarget {
+    fn drop(&mut self) {
+        let _ = Self::docker(&["rm", "-f", &self.container_id]);
+    }
+}
+
+impl DockerTarget {
+    /// Start `exe_path` (the path of `bitcoind` inside `image`) in a new container.
+    pub fn new(
+        image: &str,
+        exe_path: &str,
+        chain_params: &ChainParams,
+        node_args: &[String],
+    ) -> Result<Self, String> {
+        const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+        let mut node_config = vec![
+            format!("-chain={}", chain_params.chain),
+            "-datadir=/data".to_string(),
+            format!("-bind=0.0.0.0:{P2P_PORT}"),
+            format!("-rpcport={RPC_PORT}"),
+            "-rpcbind=0.0.0.0".to_string(),
+            "-rpcallowip=0.0.0.0/0".to_string(),
+            format!("-rpcuser={RPC_USER}"),
+            format!("-rpcpassword={RPC_PASSWORD}"),
+            "-fallbackfee=0.0001".to_string(),
+        ];
+        node_config.extend(DEFAULT_NODE_ARGS.iter().map(ToString::to_string));
+        node_config.extend_from_slice(node_args);
+
+        let p2p_publish = format!("127.0.0.1::{P2P_PORT}");
+        let rpc_publish = format!("127.0.0.1::{RPC_PORT}");
+        let mut args = vec![
+            "run",
+            "--detach",
+            "--tmpfs",
+            "/data",
+            "--publish",
+            &p2p_publish,
+            "--publish",
+            &rpc_publish,
+            image,
+            exe_path,
+        ];
+        args.extend(node_config.iter().map(String::as_str));
+
+        let container_id = Self::docker(&args)?;
+        log::info!("Started container {container_id} from {image}");
+
+        let published = Self::published_port(&container_id, P2P_PORT).and_then(|p2p_socket| {
+            let rpc_socket = Self::published_port(&container_id, RPC_PORT)?;
+            let host_ip = Self::docker(&[
+                "inspect",
+                "--format",
+                "{{range .NetworkSettings.Networks}}{{.Gateway}}{{end}}",
+                &container_id,
+            ])?
+            .parse()
+            .map_err(|e| format!("Failed to parse gateway of container {container_id}: {e}"))?;
+            Ok((p2p_socket, rpc_socket, host_ip))
+        });
+        let (p2p_socket, rpc_socket, host_ip) = match published {
+            Ok(published) => published,
+            Err(e) => {
+                let _ = Self::docker(&["rm", "-f", &container_id]);
+                return Err(e);
+            }
+        };
+
+        // From here on, the container is removed when the target is dropped
+        let mut target = Self {
+            container_id,
+            p2p_socket,
+            rpc_socket,
+            host_ip,
+            listeners: Vec::new(),
+            time: u64::MAX,
+            chain_params: chain_params.clone(),
+            capabilities: TargetCapabilities::default(),
+        };
+
+        target.wait_for_rpc(STARTUP_TIMEOUT)?;
+        let network_info = target.call_rpc("getnetworkinfo", &[])?;
+        target.capabilities = TargetCapabilities::from_network_info(&network_info, &node_config)?;
+
+        Ok(target)
+    }
+
+    /// Id of the container running the node, e.g. for inspecting its logs with `docker logs`.
+    #[must_use]
+    pub fn container_id(&self) -> &str {
+        &self.container_id
+    }
+
+    /// Run `docker` with `args`, returning its (trimmed) output.
+    fn docker(args: &[&str]) -> Result<String, String> {
+        let output = Command::new("docker")
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run docker: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "docker {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Host address the container's `port` is published to.
+    fn published_port(container_id: &str, port: u16) -> Result<SocketAddrV4, String> {
+        let addr = Self::docker(&["port", container_id, &format!("{port}/tcp")])?;
+        // Ports might be published on multiple addresses (one per line)
+        addr.lines()
+            .find_map(|line| SocketAddrV4::from_str(line.trim()).ok())
+            .ok_or_else(|| format!("Port {port} of container {container_id} is not published"))
+    }
+
+    fn wait_for_rpc(&self, timeout: Duration) -> Result<(), String> {
+        let start = Instant::now();
+        loop {
+            match self.call_rpc("getblockcount", &[]) {
+                Ok(_) => return Ok(()),
+                Err(e) if start.elapsed() > timeout => {
+                    return Err(format!("Node did not start within {timeout:?}: {e}"));
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(200)),
+            }
+        }
+    }
+
+    /// Send an HTTP `POST` with `body` to `path` on the node's RPC port.
+    fn http_post(&self, path: &str, body: &str) -> Result<RestResponse, String> {
+        const RPC_TIMEOUT: Duration = Duration::from_secs(60);
+
+        let mut stream = TcpStream::connect(self.rpc_socket)
+            .map_err(|e| format!("Failed to connect to RPC interface: {e}"))?;
+        stream
+            .set_read_timeout(Some(RPC_TIMEOUT))
+            .map_err(|e| format!("Failed to set RPC read timeout: {e}"))?;
+
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {RPC_AUTH}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .map_err(|e| format!("Failed to send RPC request: {e}"))?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|e| format!("Failed to read RPC response: {e}"))?;
+        RestResponse::parse(&response)
+    }
+
+    /// Create a listener on the host that the node can reach for outbound connections.
+    fn create_listener(&self) -> Result<(TcpListener, u16), String> {
+        let listener = TcpListener::bind((self.host_ip, 0))
+            .map_err(|e| format!("Failed to create TCP listener: {e}"))?;
+
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to get listener address: {e}"))?
+            .port();
+
+        Ok((listener, port))
+    }
+
+    /// Open a connection to or from the node, with outbound connections using v2 if `v2` is set.
+    fn connect_socket(
+        &mut self,
+        connection_type: &ConnectionType,
+        v2: bool,
+    ) -> Result<TcpStream, String> {
+        let socket = match connection_type {
+            ConnectionType::Inbound => TcpStream::connect(self.p2p_socket)
+                .map_err(|e| format!("Failed to connect to P2P port: {e}"))?,
+            ConnectionType::Outbound => {
+                let (listener, port) = self.create_listener()?;
+
+                // Tell Bitcoin Core to connect to our listener
+                self.call_rpc(
+                    "addconnection",
+                    &[
+                        format!("{}:{port}", self.host_ip).into(),
+                        "outbound-full-relay".into(),
+                        v2.into(),
+                    ],
+                )
+                .map_err(|e| format!("Failed to initiate outbound connection: {e}"))?;
+
+                let (socket, _addr) = listener
+                    .accept()
+                    .map_err(|e| format!("Failed to accept connection: {e}"))?;
+                self.listeners.push(listener);
+                socket
+            }
+        };
+        socket
+            .set_nodelay(true)
+            .map_err(|e| format!("Failed to set nodelay: {e}"))?;
+        Ok(socket)
+    }
+}
+
+impl TargetNode for DockerTarget {
+    /// Create the target from the path of `bitcoind` inside the image named by the
+    /// `FUZZAMOTO_DOCKER_IMAGE` environment variable.
+    fn from_path(exe_path: &str) -> Result<Self, String> {
+        Self::from_path_with_chain(exe_path, &ChainParams::regtest())
+    }
+
+    fn from_path_with_chain(exe_path: &str, chain_params: &ChainParams) -> Result<Self, String> {
+        let image = std::env::var("FUZZAMOTO_DOCKER_IMAGE")
+            .map_err(|_| "FUZZAMOTO_DOCKER_IMAGE is not set".to_string())?;
+        Self::new(&image, exe_path, chain_params, &[])
+    }
+
+    fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        let image = std::env::var("FUZZAMOTO_DOCKER_IMAGE")
+            .map_err(|_| "FUZZAMOTO_DOCKER_IMAGE is not set".to_string())?;
+        Self::new(&image, &args.target, &args.chain_params()?, &args.node_args)
+    }
+
+    fn chain_params(&self) -> ChainParams {
+        self.chain_params.clone()
+    }
+
+    fn set_mocktime(&mut self, time: u64) -> Result<(), String> {
+        if self.time != u64::MAX && time > self.time {
+            // Mock the scheduler forward if we're advancing in time
+            let delta = (time - self.time).min(3600);
+            let _ = self.call_rpc("mockscheduler", &[delta.into()]);
+        }
+        self.time = time;
+        self.call_rpc("setmocktime", &[time.into()])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to set mocktime: {e}"))
+    }
+
+    fn is_alive(&self) -> Result<(), String> {
+        self.call_rpc("echo", &[])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to check if node is alive: {e}"))
+    }
+
+    fn capabilities(&self) -> TargetCapabilities {
+        self.capabilities.clone()
+    }
+
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, String> {
+        self.call_rpc("getpeerinfo", &[])?
+            .as_array()
+            .ok_or_else(|| "Malformed peer info".to_string())?
+            .iter()
+            .map(PeerInfo::from_rpc)
+            .collect()
+    }
+
+    fn mempool_contents(&self) -> Result<Vec<MempoolTx>, String> {
+        self.call_rpc("getrawmempool", &[true.into()])?
+            .as_object()
+            .ok_or_else(|| "Malformed mempool contents".to_string())?
+            .iter()
+            .map(|(txid, entry)| MempoolTx::from_rpc(txid, entry))
+            .collect()
+    }
+}
+
+impl Target<V1Transport> for DockerTarget {
+    fn connect(
+        &mut self,
+        connection_type: ConnectionType,
+    ) -> Result<Connection<V1Transport>, String> {
+        let socket = self.connect_socket(&connection_type, false)?;
+        Ok(Connection::new(
+            connection_type,
+            V1Transport::with_chain_params(socket, &self.chain_params),
+        ))
+    }
+
+    fn connect_to<O: ConnectableTarget>(&mut self, _other: &O) -> Result<(), String> {
+        // Other targets only listen on the host's loopback interface
+        Err("DockerTarget does not support connecting to other targets".to_string())
+    }
+}
+
+impl Target<V2Transport> for DockerTarget {
+    fn connect(
+        &mut self,
+        connection_type: ConnectionType,
+    ) -> Result<Connection<V2Transport>, String> {
+        if !self.capabilities.v2_transport {
+            return Err("Target does not support the v2 transport (BIP324)".to_string());
+        }
+        let socket = self.connect_socket(&connection_type, true)?;
+        let role = match connection_type {
+            ConnectionType::Inbound => bip324::Role::Initiator,
+            ConnectionType::Outbound => bip324::Role::Responder,
+        };
+        Ok(Connection::new(
+            connection_type,
+            V2Transport::new(socket, role, &self.chain_params)?,
+        ))
+    }
+
+    fn connect_to<O: ConnectableTarget>(&mut self, _other: &O) -> Result<(), String> {
+        Err("DockerTarget does not support connecting to other targets".to_string())
+    }
+}
+
+impl ConnectableTarget for DockerTarget {
+    fn get_addr(&self) -> Option<SocketAddrV4> {
+        Some(self.p2p_socket)
+    }
+
+    fn is_connected_to<O: ConnectableTarget>(&self, _other: &O) -> bool {
+        // Connections from the host appear to come from the docker gateway, so peers can't be
+        // matched by address
+        false
+    }
+}
+
+impl RpcTarget for DockerTarget {
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        let request = serde_json::json!({
+            "jsonrpc": "1.0",
+            "id": "fuzzamoto",
+            "method": method,
+            "params": params,
+        });
+        let response = self.http_post("/", &request.to_string())?;
+
+        let mut response: serde_json::Value =
+            serde_json::from_slice(&response.body).map_err(|e| {
+                format!(
+                    "RPC {method} failed with malformed response (status {}): {e}",
+                    response.status
+                )
+            })?;
+        if !response["error"].is_null() {
+            return Err(format!("RPC {method} failed: {}", response["error"]));
+        }
+        Ok(response["result"].take())
+    }
+}
+
+impl HasTipInfo for DockerTarget {
+    fn get_tip_info(&self) -> Option<(BlockHash, u64)> {
+        let height = self.call_rpc("getblockcount", &[]).ok()?.as_u64()?;
+        let hash = self.call_rpc("getbestblockhash", &[]).ok()?;
+        Some((BlockHash::from_str(hash.as_str()?).ok()?, height))
+    }
+
+    fn has_reorged(&self) -> Result<bool, String> {
+        chain_tips_have_reorged(&self.call_rpc("getchaintips", &[])?)
+    }
+}
+
+impl HasGetBlock for DockerTarget {
+    fn get_block(&self, hash: BlockHash) -> Option<Block> {
+        let block = self
+            .call_rpc("getblock", &[hash.to_string().into(), 0.into()])
+            .ok()?;
+        encode::deserialize_hex(block.as_str()?).ok()
+    }
+}
+
+impl HasTxOutSetInfo for DockerTarget {
+    fn tx_out_set_info(&self) -> Result<TxOutSetInfo, String> {
+        TxOutSetInfo::from_rpc(&self.call_rpc("gettxoutsetinfo", &[])?)
+    }
+
+    fn tx_out_set_muhash(&self) -> Result<TxOutSetMuHash, String> {
+        TxOutSetMuHash::from_rpc(&self.call_rpc("gettxoutsetinfo", &["muhash".into()])?)
+    }
+}
+
+impl HasGetRawMempoolEntries for DockerTarget {
+    fn get_mempool_entries(&self) -> Result<Vec<MempoolEntry>, String> {
+        MempoolEntry::parse_raw_mempool(&self.call_rpc("getrawmempool", &[true.into()])?)
+    }
+}
+
+impl HasBlockTemplate for DockerTarget {
+    fn block_template(&self) -> Result<(), String> {
+        let request = serde_json::json!({
+            "mode": "template",
+            "capabilities": ["coinbasetxn", "workid", "coinbase/append"],
+            "rules": ["segwit"],
+        });
+        self.call_rpc("getblocktemplate", &[request])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to call getblocktemplate; reason: {e}"))
+    }
+}
+
+impl GenerateToAddress for DockerTarget {
+    fn generate_to_address(&self, address: &str) -> Result<(), String> {
+        self.call_rpc("generatetoaddress", &[1.into(), address.into()])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to call generatetoaddress {e}"))
+    }
+}
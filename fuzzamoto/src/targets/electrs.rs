@@ -0,0 +1,410 @@
+use crate::{
+    chain::ChainParams,
+    connections::{Connection, ConnectionType, Transport},
+    scenarios::args::ScenarioArgs,
+    targets::{
+        BitcoinCoreTarget, ConnectableTarget, GenerateToAddress, HasBlockTemplate, HasGetBlock,
+        HasGetRawMempoolEntries, HasTipInfo, HasTxOutSetInfo, LogEvent, MempoolTx, PeerInfo,
+        ResourceUsage, RpcTarget, Target, TargetCapabilities, TargetNode,
+        bitcoin_core::{MempoolEntry, TxOutSetInfo, TxOutSetMuHash},
+    },
+    zmq::ZmqNotification,
+};
+
+use bitcoin::{Block, BlockHash};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for electrs to accept Electrum connections
+const ELECTRS_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const ELECTRUM_READ_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `ElectrsTarget` is a Bitcoin Core node with an Electrum server (electrs) indexing it.
+///
+/// P2P connections, RPCs and the chain state queries are forwarded to the node, such that
+/// scenarios (e.g. the IR scenario) can feed it unusual blocks and transactions for electrs to
+/// index. The Electrum protocol is exposed through `connect_electrum`.
+///
+/// electrs only starts indexing once the node has left initial block download, i.e. a block with
+/// a recent timestamp has to be mined first.
+pub struct ElectrsTarget {
+    bitcoind: BitcoinCoreTarget,
+    electrs: Mutex<Child>,
+    electrum_addr: SocketAddr,
+    db_dir: PathBuf,
+}
+
+impl Drop for ElectrsTarget {
+    fn drop(&mut self) {
+        if let Ok(electrs) = self.electrs.get_mut() {
+            let _ = electrs.kill();
+            let _ = electrs.wait();
+        }
+        let _ = std::fs::remove_dir_all(&self.db_dir);
+    }
+}
+
+impl ElectrsTarget {
+    /// Start a node from `bitcoind_path` (with the extra `node_args`) and electrs from
+    /// `electrs_path` indexing it.
+    pub fn new(
+        bitcoind_path: &str,
+        electrs_path: &str,
+        chain_params: &ChainParams,
+        node_args: &[String],
+    ) -> Result<Self, String> {
+        let bitcoind =
+            BitcoinCoreTarget::from_path_with_node_args(bitcoind_path, chain_params, node_args)?;
+
+        let p2p_addr = bitcoind
+            .get_addr()
+            .ok_or_else(|| "P2P socket address not available".to_string())?;
+        let rpc_addr = bitcoind.node.params.rpc_socket;
+
+        // Reserve a port for the Electrum server, electrs binds it once the listener is dropped
+        let electrum_addr = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| format!("Failed to reserve Electrum port: {e}"))?;
+
+        let db_dir = std::env::temp_dir().join(format!(
+            "fuzzamoto-electrs-{}-{}",
+            std::process::id(),
+            electrum_addr.port()
+        ));
+        std::fs::create_dir_all(&db_dir)
+            .map_err(|e| format!("Failed to create electrs db directory: {e}"))?;
+
+        // The node's cookie file is found in `--daemon-dir`
+        let mut command = Command::new(electrs_path);
+        command
+            .arg("--network")
+            .arg(chain_params.chain.to_string())
+            .arg("--db-dir")
+            .arg(&db_dir)
+            .arg("--daemon-dir")
+            .arg(bitcoind.node.workdir())
+            .arg("--daemon-rpc-addr")
+            .arg(rpc_addr.to_string())
+            .arg("--daemon-p2p-addr")
+            .arg(p2p_addr.to_string())
+            .arg("--electrum-rpc-addr")
+            .arg(electrum_addr.to_string())
+            .stdin(Stdio::null());
+        if !cfg!(feature = "inherit_stdout") {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+        let electrs = command
+            .spawn()
+            .map_err(|e| format!("Failed to start electrs: {e}"))?;
+
+        log::info!("Started electrs (Electrum server on {electrum_addr})");
+
+        Ok(Self {
+            bitcoind,
+            electrs: Mutex::new(electrs),
+            electrum_addr,
+            db_dir,
+        })
+    }
+
+    #[must_use]
+    pub fn bitcoind(&self) -> &BitcoinCoreTarget {
+        &self.bitcoind
+    }
+
+    pub fn bitcoind_mut(&mut self) -> &mut BitcoinCoreTarget {
+        &mut self.bitcoind
+    }
+
+    /// Open a new connection to the Electrum server, waiting for electrs to start listening.
+    pub fn connect_electrum(&self) -> Result<ElectrumConnection, String> {
+        let start = Instant::now();
+        loop {
+            self.check_electrs()?;
+            match TcpStream::connect(self.electrum_addr) {
+                Ok(stream) => return ElectrumConnection::new(stream),
+                Err(e) if start.elapsed() > ELECTRS_STARTUP_TIMEOUT => {
+                    return Err(format!("Failed to connect to electrs: {e}"));
+                }
+                Err(_) => std::thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+
+    /// Wait for electrs to index the node's current chain tip.
+    pub fn wait_for_sync(&self, timeout: Duration) -> Result<(), String> {
+        let (_, height) = self
+            .bitcoind
+            .get_tip_info()
+            .ok_or_else(|| "Failed to get tip of node".to_string())?;
+
+        let mut connection = self.connect_electrum()?;
+        let start = Instant::now();
+        loop {
+            let header = connection.request("blockchain.headers.subscribe", &[])?;
+            let indexed = header["height"]
+                .as_u64()
+                .ok_or_else(|| format!("Malformed header notification: {header}"))?;
+            if indexed == height {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(format!(
+                    "electrs did not sync to height {height} (indexed height {indexed})"
+                ));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn check_electrs(&self) -> Result<(), String> {
+        let mut electrs = self
+            .electrs
+            .lock()
+            .map_err(|_| "electrs process lock is poisoned".to_string())?;
+        match electrs.try_wait() {
+            Ok(None) => Ok(()),
+            Ok(Some(status)) => Err(format!("electrs exited: {status}")),
+            Err(e) => Err(format!("Failed to check electrs status: {e}")),
+        }
+    }
+}
+
+/// Connection to an Electrum server, speaking newline delimited JSON-RPC.
+pub struct ElectrumConnection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_id: u64,
+}
+
+impl ElectrumConnection {
+    fn new(stream: TcpStream) -> Result<Self, String> {
+        stream
+            .set_read_timeout(Some(ELECTRUM_READ_TIMEOUT))
+            .map_err(|e| format!("Failed to set Electrum read timeout: {e}"))?;
+        stream
+            .set_nodelay(true)
+            .map_err(|e| format!("Failed to set nodelay on Electrum socket: {e}"))?;
+        let writer = stream
+            .try_clone()
+            .map_err(|e| format!("Failed to clone Electrum socket: {e}"))?;
+
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+            next_id: 0,
+        })
+    }
+
+    /// Send a request and wait for its response, returning the result (notifications received in
+    /// the meantime are skipped).
+    pub fn request(
+        &mut self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        let mut line = request.to_string();
+        line.push('\n');
+        self.send_raw(line.as_bytes())?;
+
+        loop {
+            let response = self.receive()?;
+            if response["id"].as_u64() != Some(id) {
+                continue;
+            }
+            if !response["error"].is_null() {
+                return Err(format!(
+                    "Electrum request {method} failed: {}",
+                    response["error"]
+                ));
+            }
+            return Ok(response["result"].clone());
+        }
+    }
+
+    /// Send raw bytes on the connection (e.g. malformed or batched requests).
+    pub fn send_raw(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to send to Electrum server: {e}"))
+    }
+
+    /// Receive the next response or notification.
+    pub fn receive(&mut self) -> Result<serde_json::Value, String> {
+        let mut line = String::new();
+        let n = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to receive from Electrum server: {e}"))?;
+        if n == 0 {
+            return Err("Electrum server closed the connection".to_string());
+        }
+        serde_json::from_str(&line).map_err(|e| format!("Malformed Electrum response: {e}"))
+    }
+}
+
+impl TargetNode for ElectrsTarget {
+    /// Create the target from the path to `bitcoind`, with the path to electrs taken from the
+    /// `FUZZAMOTO_ELECTRS_PATH` environment variable.
+    fn from_path(bitcoind_path: &str) -> Result<Self, String> {
+        Self::from_path_with_chain(bitcoind_path, &ChainParams::regtest())
+    }
+
+    fn from_path_with_chain(
+        bitcoind_path: &str,
+        chain_params: &ChainParams,
+    ) -> Result<Self, String> {
+        let electrs_path = std::env::var("FUZZAMOTO_ELECTRS_PATH")
+            .map_err(|_| "FUZZAMOTO_ELECTRS_PATH is not set".to_string())?;
+        Self::new(bitcoind_path, &electrs_path, chain_params, &[])
+    }
+
+    fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        let electrs_path = std::env::var("FUZZAMOTO_ELECTRS_PATH")
+            .map_err(|_| "FUZZAMOTO_ELECTRS_PATH is not set".to_string())?;
+        Self::new(
+            &args.target,
+            &electrs_path,
+            &args.chain_params()?,
+            &args.node_args,
+        )
+    }
+
+    fn chain_params(&self) -> ChainParams {
+        self.bitcoind.chain_params()
+    }
+
+    fn set_mocktime(&mut self, time: u64) -> Result<(), String> {
+        self.bitcoind.set_mocktime(time)
+    }
+
+    fn is_alive(&self) -> Result<(), String> {
+        self.bitcoind.is_alive()?;
+        self.check_electrs()
+    }
+
+    fn log_events(&mut self) -> Result<Vec<LogEvent>, String> {
+        self.bitcoind.log_events()
+    }
+
+    fn zmq_notifications(&mut self) -> Result<&[ZmqNotification], String> {
+        self.bitcoind.zmq_notifications()
+    }
+
+    /// Combined usage of the node and electrs
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        let mut usage = self.bitcoind.resource_usage()?;
+        let pid = self.electrs.lock().ok()?.id();
+        usage.add(
+            &ResourceUsage::sample(pid, &self.db_dir)
+                .inspect_err(|e| log::warn!("Failed to sample electrs resource usage: {e}"))
+                .ok()?,
+        );
+        Some(usage)
+    }
+
+    fn capabilities(&self) -> TargetCapabilities {
+        self.bitcoind.capabilities()
+    }
+
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, String> {
+        self.bitcoind.peer_info()
+    }
+
+    fn mempool_contents(&self) -> Result<Vec<MempoolTx>, String> {
+        self.bitcoind.mempool_contents()
+    }
+}
+
+impl<T: Transport> Target<T> for ElectrsTarget
+where
+    BitcoinCoreTarget: Target<T>,
+{
+    fn connect(&mut self, connection_type: ConnectionType) -> Result<Connection<T>, String> {
+        <BitcoinCoreTarget as Target<T>>::connect(&mut self.bitcoind, connection_type)
+    }
+
+    fn connect_to<O: ConnectableTarget>(&mut self, other: &O) -> Result<(), String> {
+        <BitcoinCoreTarget as Target<T>>::connect_to(&mut self.bitcoind, other)
+    }
+}
+
+impl ConnectableTarget for ElectrsTarget {
+    fn get_addr(&self) -> Option<SocketAddrV4> {
+        self.bitcoind.get_addr()
+    }
+
+    fn is_connected_to<O: ConnectableTarget>(&self, other: &O) -> bool {
+        self.bitcoind.is_connected_to(other)
+    }
+}
+
+impl RpcTarget for ElectrsTarget {
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        self.bitcoind.call_rpc(method, params)
+    }
+}
+
+impl HasTipInfo for ElectrsTarget {
+    fn get_tip_info(&self) -> Option<(BlockHash, u64)> {
+        self.bitcoind.get_tip_info()
+    }
+
+    fn has_reorged(&self) -> Result<bool, String> {
+        self.bitcoind.has_reorged()
+    }
+}
+
+impl HasGetBlock for ElectrsTarget {
+    fn get_block(&self, hash: BlockHash) -> Option<Block> {
+        self.bitcoind.get_block(hash)
+    }
+}
+
+impl HasTxOutSetInfo for ElectrsTarget {
+    fn tx_out_set_info(&self) -> Result<TxOutSetInfo, String> {
+        self.bitcoind.tx_out_set_info()
+    }
+
+    fn tx_out_set_muhash(&self) -> Result<TxOutSetMuHash, String> {
+        self.bitcoind.tx_out_set_muhash()
+    }
+}
+
+impl HasGetRawMempoolEntries for ElectrsTarget {
+    fn get_mempool_entries(&self) -> Result<Vec<MempoolEntry>, String> {
+        self.bitcoind.get_mempool_entries()
+    }
+}
+
+impl HasBlockTemplate for ElectrsTarget {
+    fn block_template(&self) -> Result<(), String> {
+        self.bitcoind.block_template()
+    }
+}
+
+impl GenerateToAddress for ElectrsTarget {
+    fn generate_to_address(&self, address: &str) -> Result<(), String> {
+        self.bitcoind.generate_to_address(address)
+    }
+}
@@ -0,0 +1,180 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use bitcoin::{Amount, Txid, Wtxid};
+
+/// A peer of the target, as seen by the target (see `TargetNode::peer_info`)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PeerInfo {
+    /// Target-local id of the peer
+    pub id: u64,
+    /// Address of the peer (e.g. `127.0.0.1:53122`)
+    pub addr: String,
+    pub inbound: bool,
+    /// Type of the connection (e.g. `inbound`, `outbound-full-relay` or `block-relay-only`)
+    pub connection_type: String,
+    /// Transport used for the connection (`v1` or `v2`, `detecting` while the handshake is in
+    /// progress)
+    pub transport: String,
+    /// Services announced by the peer
+    pub services: u64,
+    /// Protocol version announced by the peer (0 before the version handshake)
+    pub version: u32,
+    /// User agent announced by the peer
+    pub subver: String,
+    /// Whether the peer asked for transaction relay
+    pub relay_txes: bool,
+    pub bytes_sent: u64,
+    pub bytes_recv: u64,
+    /// Bytes received per message type, e.g. to compare against what a scenario sent
+    pub bytes_recv_per_msg: BTreeMap<String, u64>,
+}
+
+impl PeerInfo {
+    /// Parse an entry of Bitcoin Core's `getpeerinfo` result.
+    ///
+    /// Fields missing in older versions default to empty values.
+    pub fn from_rpc(peer: &serde_json::Value) -> Result<Self, String> {
+        let id = peer["id"]
+            .as_u64()
+            .ok_or_else(|| "Missing id in peer info".to_string())?;
+        let addr = peer["addr"]
+            .as_str()
+            .ok_or_else(|| format!("Missing addr in info of peer {id}"))?
+            .to_string();
+        let inbound = peer["inbound"]
+            .as_bool()
+            .ok_or_else(|| format!("Missing inbound in info of peer {id}"))?;
+        let services = match peer["services"].as_str() {
+            Some(services) => u64::from_str_radix(services, 16)
+                .map_err(|e| format!("Failed to parse services of peer {id}: {e}"))?,
+            None => 0,
+        };
+        let string = |name: &str| peer[name].as_str().unwrap_or_default().to_string();
+
+        Ok(Self {
+            id,
+            addr,
+            inbound,
+            connection_type: string("connection_type"),
+            transport: string("transport_protocol_type"),
+            services,
+            version: peer["version"]
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .unwrap_or_default(),
+            subver: string("subver"),
+            relay_txes: peer["relaytxes"].as_bool().unwrap_or_default(),
+            bytes_sent: peer["bytessent"].as_u64().unwrap_or_default(),
+            bytes_recv: peer["bytesrecv"].as_u64().unwrap_or_default(),
+            bytes_recv_per_msg: peer["bytesrecv_per_msg"]
+                .as_object()
+                .map(|per_msg| {
+                    per_msg
+                        .iter()
+                        .filter_map(|(msg, bytes)| Some((msg.clone(), bytes.as_u64()?)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A transaction in the target's mempool (see `TargetNode::mempool_contents`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MempoolTx {
+    pub txid: Txid,
+    pub wtxid: Wtxid,
+    pub vsize: u64,
+    /// Fee paid by the transaction itself (excluding ancestors and descendants)
+    pub fee: Amount,
+}
+
+impl MempoolTx {
+    /// Parse an entry of Bitcoin Core's verbose `getrawmempool` result.
+    pub fn from_rpc(txid: &str, entry: &serde_json::Value) -> Result<Self, String> {
+        let txid = Txid::from_str(txid).map_err(|e| format!("Failed to parse txid: {e}"))?;
+        let wtxid = entry["wtxid"]
+            .as_str()
+            .ok_or_else(|| format!("Missing wtxid of mempool tx {txid}"))
+            .and_then(|wtxid| {
+                Wtxid::from_str(wtxid)
+                    .map_err(|e| format!("Failed to parse wtxid of mempool tx {txid}: {e}"))
+            })?;
+        let vsize = entry["vsize"]
+            .as_u64()
+            .ok_or_else(|| format!("Missing vsize of mempool tx {txid}"))?;
+        let fee = entry["fees"]["base"]
+            .as_f64()
+            .ok_or_else(|| format!("Missing fee of mempool tx {txid}"))
+            .and_then(|fee| {
+                Amount::from_btc(fee)
+                    .map_err(|e| format!("Failed to parse fee of mempool tx {txid}: {e}"))
+            })?;
+
+        Ok(Self {
+            txid,
+            wtxid,
+            vsize,
+            fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_peer_info() {
+        let peer = serde_json::json!({
+            "id": 3,
+            "addr": "127.0.0.1:53122",
+            "services": "0000000000000409",
+            "relaytxes": true,
+            "bytessent": 1200,
+            "bytesrecv": 310,
+            "version": 70016,
+            "subver": "/fuzzamoto/",
+            "inbound": true,
+            "connection_type": "inbound",
+            "transport_protocol_type": "v1",
+            "bytesrecv_per_msg": { "verack": 24, "version": 126 },
+        });
+        let info = PeerInfo::from_rpc(&peer).unwrap();
+        assert_eq!(info.id, 3);
+        assert_eq!(info.services, 0x409);
+        assert_eq!(info.version, 70016);
+        assert!(info.inbound && info.relay_txes);
+        assert_eq!(info.bytes_recv_per_msg.get("version"), Some(&126));
+
+        // Peers that haven't completed the handshake yet
+        let info = PeerInfo::from_rpc(&serde_json::json!({
+            "id": 4,
+            "addr": "127.0.0.1:53123",
+            "inbound": false,
+        }))
+        .unwrap();
+        assert_eq!(info.version, 0);
+        assert!(info.bytes_recv_per_msg.is_empty());
+
+        assert!(PeerInfo::from_rpc(&serde_json::json!({ "addr": "127.0.0.1:1" })).is_err());
+    }
+
+    #[test]
+    fn parse_mempool_tx() {
+        let entry = serde_json::json!({
+            "vsize": 141,
+            "wtxid": "66f5c8e4a3e4dcee0ed5e0b28bb2e1a6a4dc5cae6d8ed1bd0a28a09c8e2d2b8c",
+            "fees": { "base": 1.41e-5, "modified": 1.41e-5 },
+        });
+        let tx = MempoolTx::from_rpc(
+            "c5d0a2a2a9d7b8e0df1c79ab2b2e8d3e4e9ff7b0a5a31b3c3e52f0d71f6c2a1d",
+            &entry,
+        )
+        .unwrap();
+        assert_eq!(tx.vsize, 141);
+        assert_eq!(tx.fee, Amount::from_sat(1410));
+
+        assert!(MempoolTx::from_rpc("00", &entry).is_err());
+    }
+}
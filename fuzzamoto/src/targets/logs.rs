@@ -0,0 +1,186 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Log line patterns (case sensitive) indicating an error, e.g. `[error]` for unconditional
+/// errors and `[net:error]` for errors of a specific log category.
+const ERROR_PATTERNS: &[&str] = &[
+    "[error]",
+    ":error]",
+    "ERROR: ",
+    "Internal bug detected",
+    "A fatal internal error occurred",
+    "Corrupted block database detected",
+    "Fatal LevelDB error",
+    "Corruption: ",
+];
+
+/// Number of log lines preceding an event that are kept as its context
+const CONTEXT_LINES: usize = 5;
+
+/// Log line patterns (case sensitive) indicating a warning.
+const WARNING_PATTERNS: &[&str] = &["[warning]", ":warning]", "Warning: ", "WARNING: "];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LogLevel {
+    Warning,
+    Error,
+}
+
+/// A notable line of a target's log (see `TargetNode::log_events`)
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LogEvent {
+    pub level: LogLevel,
+    /// The complete log line (including timestamps and categories, if logged)
+    pub line: String,
+    /// Up to `CONTEXT_LINES` lines logged right before the event (if read with `LogTail`)
+    pub context: Vec<String>,
+}
+
+impl LogEvent {
+    /// Parse a log line, returning `None` for lines that are neither errors nor warnings.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let level = if ERROR_PATTERNS.iter().any(|pattern| line.contains(pattern)) {
+            LogLevel::Error
+        } else if WARNING_PATTERNS
+            .iter()
+            .any(|pattern| line.contains(pattern))
+        {
+            LogLevel::Warning
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            level,
+            line: line.trim_end().to_string(),
+            context: Vec::new(),
+        })
+    }
+
+    /// The event's line preceded by its context, e.g. for attaching to failure reports
+    #[must_use]
+    pub fn excerpt(&self) -> String {
+        let mut excerpt = self.context.join("\n");
+        if !excerpt.is_empty() {
+            excerpt.push('\n');
+        }
+        excerpt.push_str(&self.line);
+        excerpt
+    }
+}
+
+/// Incremental reader for a log file that is appended to by the target, returning only the events
+/// logged since the previous read.
+#[derive(Debug, Default)]
+pub struct LogTail {
+    offset: u64,
+    /// Incomplete last line of the previous read
+    partial: Vec<u8>,
+    /// Last complete lines read, the context of the next event
+    recent: VecDeque<String>,
+}
+
+impl LogTail {
+    /// Read the lines appended to the log at `path` since the last call and parse them.
+    ///
+    /// Lines that are not terminated yet are held back until they are complete.
+    pub fn read_events(&mut self, path: &Path) -> Result<Vec<LogEvent>, String> {
+        let mut file =
+            File::open(path).map_err(|e| format!("Failed to open log {}: {e}", path.display()))?;
+        let len = file
+            .metadata()
+            .map_err(|e| format!("Failed to get size of log {}: {e}", path.display()))?
+            .len();
+        if len < self.offset {
+            // The log was truncated or replaced (e.g. by `-shrinkdebugfile`), start over
+            self.offset = 0;
+            self.partial.clear();
+        }
+
+        file.seek(SeekFrom::Start(self.offset))
+            .map_err(|e| format!("Failed to seek in log {}: {e}", path.display()))?;
+        let mut bytes = std::mem::take(&mut self.partial);
+        let read = file
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read log {}: {e}", path.display()))?;
+        self.offset += read as u64;
+
+        let complete = bytes.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        self.partial = bytes.split_off(complete);
+
+        let mut events = Vec::new();
+        for line in String::from_utf8_lossy(&bytes).lines() {
+            if let Some(mut event) = LogEvent::parse(line) {
+                event.context = self.recent.iter().cloned().collect();
+                events.push(event);
+            }
+
+            if self.recent.len() >= CONTEXT_LINES {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(line.trim_end().to_string());
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn parse_log_lines() {
+        let error = LogEvent::parse("2025-01-01T00:00:00Z [error] Failed to read block\n");
+        assert_eq!(
+            error,
+            Some(LogEvent {
+                level: LogLevel::Error,
+                line: "2025-01-01T00:00:00Z [error] Failed to read block".to_string(),
+                context: Vec::new(),
+            })
+        );
+        assert_eq!(
+            LogEvent::parse("[net:warning] Unexpected message").map(|event| event.level),
+            Some(LogLevel::Warning)
+        );
+        assert_eq!(
+            LogEvent::parse("Internal bug detected: ...").map(|event| event.level),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(
+            LogEvent::parse("[error] Fatal LevelDB error: Corruption: bad block")
+                .map(|event| event.level),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(LogEvent::parse("[net] received: ping (8 bytes)"), None);
+    }
+
+    #[test]
+    fn tail_holds_back_partial_lines() {
+        let path = std::env::temp_dir().join(format!("fuzzamoto-logs-{}.log", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        let mut tail = LogTail::default();
+
+        file.write_all(b"[error] first\n[warning] sec").unwrap();
+        let events = tail.read_events(&path).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].line, "[error] first");
+
+        file.write_all(b"ond\nnothing to see\n").unwrap();
+        let events = tail.read_events(&path).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, LogLevel::Warning);
+        assert_eq!(events[0].line, "[warning] second");
+        assert_eq!(events[0].context, ["[error] first"]);
+        assert_eq!(events[0].excerpt(), "[error] first\n[warning] second");
+
+        assert!(tail.read_events(&path).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
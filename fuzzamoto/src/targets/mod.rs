@@ -1,11 +1,28 @@
 pub mod bitcoin_core;
+pub mod capabilities;
+pub mod docker;
+pub mod electrs;
+pub mod introspection;
+pub mod logs;
+pub mod network;
+pub mod resources;
 use crate::{
+    chain::ChainParams,
     connections::{Connection, ConnectionType, Transport},
-    targets::bitcoin_core::{MempoolEntry, TxOutSetInfo},
+    scenarios::args::ScenarioArgs,
+    targets::bitcoin_core::{MempoolEntry, TxOutSetInfo, TxOutSetMuHash},
+    zmq::ZmqNotification,
 };
 use bitcoin::{Block, BlockHash, Txid};
-pub use bitcoin_core::BitcoinCoreTarget;
-use std::net::SocketAddrV4;
+pub use bitcoin_core::{BitcoinCoreTarget, RestFormat, RestResponse};
+pub use capabilities::TargetCapabilities;
+pub use docker::DockerTarget;
+pub use electrs::{ElectrsTarget, ElectrumConnection};
+pub use introspection::{MempoolTx, PeerInfo};
+pub use logs::{LogEvent, LogLevel};
+pub use network::{NodeNetworkTarget, Topology};
+pub use resources::ResourceUsage;
+use std::{net::SocketAddrV4, path::Path};
 
 /// Transport-independent operations for a target node.
 /// This trait is implemented once per target type, not per transport.
@@ -13,6 +30,32 @@ pub trait TargetNode: Sized {
     /// Create target from path to executable.
     fn from_path(path: &str) -> Result<Self, String>;
 
+    /// Create target from path to executable, running on the given chain.
+    ///
+    /// Targets only support regtest unless they override this.
+    fn from_path_with_chain(path: &str, chain_params: &ChainParams) -> Result<Self, String> {
+        if !chain_params.is_regtest() {
+            return Err(format!(
+                "Target does not support chain: {}",
+                chain_params.chain
+            ));
+        }
+        Self::from_path(path)
+    }
+
+    /// Create target from the arguments shared by all scenarios (target executable, chain and
+    /// extra node arguments).
+    ///
+    /// Targets that don't override this ignore the node arguments of `args`.
+    fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        Self::from_path_with_chain(&args.target, &args.chain_params()?)
+    }
+
+    /// Get the parameters of the chain the target is running on.
+    fn chain_params(&self) -> ChainParams {
+        ChainParams::regtest()
+    }
+
     /// Set the mocktime for the target.
     ///
     /// This is used to simulate time advancement in the target.
@@ -24,6 +67,73 @@ pub trait TargetNode: Sized {
 
     /// Check if the target is still alive.
     fn is_alive(&self) -> Result<(), String>;
+
+    /// Load an assumeutxo snapshot (as created by `dump_utxo_snapshot`) into the target.
+    ///
+    /// The headers up to the snapshot's base block have to be known to the target, and the
+    /// snapshot has to be listed in the target's chain parameters (for Bitcoin Core on regtest,
+    /// this requires a build with the snapshot added to `m_assumeutxo_data`). Targets without
+    /// assumeutxo support don't override this.
+    fn load_utxo_snapshot(&mut self, _path: &Path) -> Result<(), String> {
+        Err("Target does not support assumeutxo snapshots".to_string())
+    }
+
+    /// Dump an assumeutxo snapshot of the target's current chain tip to `path`.
+    fn dump_utxo_snapshot(&self, _path: &Path) -> Result<(), String> {
+        Err("Target does not support assumeutxo snapshots".to_string())
+    }
+
+    /// Gracefully shut the target down and restart it on the same data directory, e.g. to
+    /// exercise the code that persists and loads state across restarts (`mempool.dat`,
+    /// `peers.dat`, `anchors.dat`, startup re-validation).
+    ///
+    /// Existing connections to the target are closed and the mocktime is restored after the
+    /// restart.
+    fn restart(&mut self) -> Result<(), String> {
+        Err("Target does not support restarts".to_string())
+    }
+
+    /// Errors and warnings logged by the target since the previous call (see `logs::LogEvent`),
+    /// e.g. for detecting internal errors that don't crash the target.
+    ///
+    /// Targets without log capture don't override this.
+    fn log_events(&mut self) -> Result<Vec<LogEvent>, String> {
+        Ok(Vec::new())
+    }
+
+    /// ZMQ notifications published by the target so far, in the order they were received, e.g.
+    /// for detecting missing, duplicate or misordered notifications (see
+    /// `zmq::verify_notifications`).
+    ///
+    /// Targets without ZMQ capture don't override this.
+    fn zmq_notifications(&mut self) -> Result<&[ZmqNotification], String> {
+        Err("Target does not capture ZMQ notifications".to_string())
+    }
+
+    /// Current resource usage of the target (memory, open fds, threads and disk usage), e.g. for
+    /// detecting unbounded growth across test cases.
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        None
+    }
+
+    /// Version and optional P2P features of the target, detected when the target is created.
+    ///
+    /// Targets without capability detection don't override this, which assumes that all
+    /// features are supported.
+    fn capabilities(&self) -> TargetCapabilities {
+        TargetCapabilities::default()
+    }
+
+    /// Peers of the target as seen by the target, e.g. for oracles comparing what the target
+    /// believes against what the scenario knows it sent.
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, String> {
+        Err("Target does not support peer introspection".to_string())
+    }
+
+    /// Transactions in the target's mempool.
+    fn mempool_contents(&self) -> Result<Vec<MempoolTx>, String> {
+        Err("Target does not support mempool introspection".to_string())
+    }
 }
 
 /// `Target` is the interface that the test harness will use to interact with the target Bitcoin
@@ -52,8 +162,32 @@ pub trait ConnectableTarget {
     fn is_connected_to<O: ConnectableTarget>(&self, other: &O) -> bool;
 }
 
+/// Targets exposing a JSON-RPC interface, which lets scenarios and oracles query the target's
+/// state (e.g. `getmempoolinfo`, `getpeerinfo` or `gettxoutsetinfo`).
+pub trait RpcTarget {
+    /// Call `method` with `params`, returning the result.
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String>;
+}
+
 pub trait HasTipInfo {
     fn get_tip_info(&self) -> Option<(BlockHash, u64)>;
+
+    /// Whether blocks of the active chain were disconnected by a reorg since the node started,
+    /// i.e. whether `getchaintips` lists a fully validated fork.
+    fn has_reorged(&self) -> Result<bool, String>;
+}
+
+/// Whether the result of `getchaintips` contains a fully validated fork (see
+/// `HasTipInfo::has_reorged`)
+pub(crate) fn chain_tips_have_reorged(tips: &serde_json::Value) -> Result<bool, String> {
+    let tips = tips
+        .as_array()
+        .ok_or_else(|| format!("Unexpected getchaintips result: {tips}"))?;
+    Ok(tips.iter().any(|tip| tip["status"] == "valid-fork"))
 }
 
 pub trait GenerateToAddress {
@@ -66,6 +200,10 @@ pub trait HasGetBlock {
 
 pub trait HasTxOutSetInfo {
     fn tx_out_set_info(&self) -> Result<TxOutSetInfo, String>;
+
+    /// `MuHash3072` commitment to the UTXO set, which is slower to compute than the default hash
+    /// of `tx_out_set_info` but comparable across restarts and reorgs.
+    fn tx_out_set_muhash(&self) -> Result<TxOutSetMuHash, String>;
 }
 
 pub trait HasBlockTemplate {
@@ -0,0 +1,423 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    chain::ChainParams,
+    connections::{Connection, ConnectionType, Transport, V1Transport},
+    scenarios::args::ScenarioArgs,
+    targets::{
+        BitcoinCoreTarget, ConnectableTarget, LogEvent, MempoolTx, PeerInfo, ResourceUsage,
+        RpcTarget, Target, TargetCapabilities, TargetNode,
+    },
+};
+
+/// How long to wait for two nodes of a network to connect
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Topology of the connections between the nodes of a `NodeNetworkTarget`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    /// Node `i` connects to node `i + 1`
+    Line,
+    /// Like `Line`, with the last node connecting to the first
+    Ring,
+    /// The first node connects to all other nodes
+    Star,
+    /// Every node is connected to every other node
+    Full,
+    /// Explicit outbound connections `(from, to)`
+    Custom(Vec<(usize, usize)>),
+}
+
+impl Topology {
+    /// Select the topology by name (`line`, `ring`, `star` or `full`).
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "line" => Ok(Self::Line),
+            "ring" => Ok(Self::Ring),
+            "star" => Ok(Self::Star),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Unknown topology: {name}")),
+        }
+    }
+
+    /// The outbound connections `(from, to)` between `size` nodes
+    #[must_use]
+    pub fn edges(&self, size: usize) -> Vec<(usize, usize)> {
+        match self {
+            Self::Line => (1..size).map(|i| (i - 1, i)).collect(),
+            Self::Ring => {
+                let mut edges = Self::Line.edges(size);
+                if size > 2 {
+                    edges.push((size - 1, 0));
+                }
+                edges
+            }
+            Self::Star => (1..size).map(|i| (0, i)).collect(),
+            Self::Full => (0..size)
+                .flat_map(|from| (from + 1..size).map(move |to| (from, to)))
+                .collect(),
+            Self::Custom(edges) => edges.clone(),
+        }
+    }
+}
+
+/// Groups of nodes that are connected to each other (directly or through other nodes) by `edges`
+fn components(size: usize, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    // Label every node with the smallest node it is connected to
+    let mut labels: Vec<usize> = (0..size).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (from, to) in edges {
+            let label = labels[*from].min(labels[*to]);
+            if labels[*from] != label || labels[*to] != label {
+                labels[*from] = label;
+                labels[*to] = label;
+                changed = true;
+            }
+        }
+    }
+
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (node, label) in labels.into_iter().enumerate() {
+        components.entry(label).or_default().push(node);
+    }
+    components.into_values().collect()
+}
+
+/// `NodeNetworkTarget` is a network of Bitcoin Core nodes connected to each other, which allows
+/// fuzzing behaviour between real peers (e.g. relay, compact block reconstruction, partitions and
+/// reorgs).
+///
+/// As a `Target`, connections are spread over the nodes round-robin and the mocktime is set on
+/// all nodes. Individual nodes can be addressed with `connect_node` and `set_node_mocktime`.
+pub struct NodeNetworkTarget {
+    nodes: Vec<BitcoinCoreTarget>,
+    /// Current outbound connections `(from, to)` between the nodes
+    edges: Vec<(usize, usize)>,
+    /// Node the next `Target::connect` connects to
+    next_node: usize,
+}
+
+impl NodeNetworkTarget {
+    /// Spawn `size` nodes (started with the extra `node_args`) and connect them according to
+    /// `topology`.
+    pub fn new(
+        exe_path: &str,
+        chain_params: &ChainParams,
+        node_args: &[String],
+        size: usize,
+        topology: &Topology,
+    ) -> Result<Self, String> {
+        if size == 0 {
+            return Err("Network needs at least one node".to_string());
+        }
+
+        let mut network = Self {
+            nodes: Vec::with_capacity(size),
+            edges: Vec::new(),
+            next_node: 0,
+        };
+        for _ in 0..size {
+            let node =
+                BitcoinCoreTarget::from_path_with_node_args(exe_path, chain_params, node_args)?;
+            network.nodes.push(node);
+        }
+        for (from, to) in topology.edges(size) {
+            network.connect_nodes(from, to)?;
+        }
+
+        log::info!(
+            "Spawned network of {size} nodes ({topology:?}): {:?}",
+            network.edges
+        );
+
+        Ok(network)
+    }
+
+    /// Spawn a network configured through the `FUZZAMOTO_NETWORK_SIZE` (default: 3) and
+    /// `FUZZAMOTO_NETWORK_TOPOLOGY` (default: `ring`) environment variables.
+    pub fn from_env(
+        exe_path: &str,
+        chain_params: &ChainParams,
+        node_args: &[String],
+    ) -> Result<Self, String> {
+        let size = match std::env::var("FUZZAMOTO_NETWORK_SIZE") {
+            Ok(size) => size
+                .parse()
+                .map_err(|_| format!("Failed to parse FUZZAMOTO_NETWORK_SIZE: {size:?}"))?,
+            Err(_) => 3,
+        };
+        let topology = match std::env::var("FUZZAMOTO_NETWORK_TOPOLOGY") {
+            Ok(name) => Topology::from_name(&name)?,
+            Err(_) => Topology::Ring,
+        };
+
+        Self::new(exe_path, chain_params, node_args, size, &topology)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    #[must_use]
+    pub fn nodes(&self) -> &[BitcoinCoreTarget] {
+        &self.nodes
+    }
+
+    #[must_use]
+    pub fn node(&self, index: usize) -> Option<&BitcoinCoreTarget> {
+        self.nodes.get(index)
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> Option<&mut BitcoinCoreTarget> {
+        self.nodes.get_mut(index)
+    }
+
+    /// Current outbound connections `(from, to)` between the nodes
+    #[must_use]
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Groups of nodes that are connected to each other (directly or through other nodes), e.g.
+    /// the sides of a partition
+    #[must_use]
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        components(self.nodes.len(), &self.edges)
+    }
+
+    /// Create a new connection to the node at `index`.
+    pub fn connect_node<T: Transport>(
+        &mut self,
+        index: usize,
+        connection_type: ConnectionType,
+    ) -> Result<Connection<T>, String>
+    where
+        BitcoinCoreTarget: Target<T>,
+    {
+        let node = self
+            .nodes
+            .get_mut(index)
+            .ok_or_else(|| format!("Node {index} does not exist"))?;
+        <BitcoinCoreTarget as Target<T>>::connect(node, connection_type)
+    }
+
+    /// Set the mocktime of the node at `index` (e.g. to let the nodes' clocks drift apart).
+    pub fn set_node_mocktime(&mut self, index: usize, time: u64) -> Result<(), String> {
+        self.nodes
+            .get_mut(index)
+            .ok_or_else(|| format!("Node {index} does not exist"))?
+            .set_mocktime(time)
+    }
+
+    /// Make node `from` open an outbound connection to node `to` and wait for it to be
+    /// established.
+    pub fn connect_nodes(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from == to || from.max(to) >= self.nodes.len() {
+            return Err(format!("Invalid connection from node {from} to node {to}"));
+        }
+
+        let (from_node, to_node) = if from < to {
+            let (left, right) = self.nodes.split_at_mut(to);
+            (&mut left[from], &right[0])
+        } else {
+            let (left, right) = self.nodes.split_at_mut(from);
+            (&mut right[0], &left[to])
+        };
+
+        <BitcoinCoreTarget as Target<V1Transport>>::connect_to(from_node, to_node)?;
+
+        let start = Instant::now();
+        while !from_node.is_connected_to(to_node) {
+            if start.elapsed() > CONNECT_TIMEOUT {
+                return Err(format!("Node {from} failed to connect to node {to}"));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        self.edges.push((from, to));
+        Ok(())
+    }
+
+    /// Disconnect nodes `a` and `b` (in both directions), e.g. to partition the network.
+    pub fn disconnect_nodes(&mut self, a: usize, b: usize) -> Result<(), String> {
+        let edges: Vec<_> = self
+            .edges
+            .iter()
+            .copied()
+            .filter(|edge| *edge == (a, b) || *edge == (b, a))
+            .collect();
+
+        for (from, to) in &edges {
+            let addr = self.nodes[*to]
+                .get_addr()
+                .ok_or_else(|| format!("Node {to} does not have a valid address"))?;
+            self.nodes[*from]
+                .node
+                .client
+                .call::<serde_json::Value>("disconnectnode", &[addr.to_string().into()])
+                .map_err(|e| format!("Failed to disconnect node {from} from node {to}: {e:?}"))?;
+        }
+
+        self.edges.retain(|edge| !edges.contains(edge));
+        Ok(())
+    }
+}
+
+impl TargetNode for NodeNetworkTarget {
+    fn from_path(exe_path: &str) -> Result<Self, String> {
+        Self::from_path_with_chain(exe_path, &ChainParams::regtest())
+    }
+
+    fn from_path_with_chain(exe_path: &str, chain_params: &ChainParams) -> Result<Self, String> {
+        Self::from_env(exe_path, chain_params, &[])
+    }
+
+    fn from_scenario_args(args: &ScenarioArgs) -> Result<Self, String> {
+        Self::from_env(&args.target, &args.chain_params()?, &args.node_args)
+    }
+
+    fn chain_params(&self) -> ChainParams {
+        self.nodes[0].chain_params()
+    }
+
+    fn set_mocktime(&mut self, time: u64) -> Result<(), String> {
+        for node in &mut self.nodes {
+            node.set_mocktime(time)?;
+        }
+        Ok(())
+    }
+
+    fn is_alive(&self) -> Result<(), String> {
+        for (index, node) in self.nodes.iter().enumerate() {
+            node.is_alive()
+                .map_err(|e| format!("Node {index} is not alive: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Restart all nodes and re-establish the connections between them
+    fn restart(&mut self) -> Result<(), String> {
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            node.restart()
+                .map_err(|e| format!("Failed to restart node {index}: {e}"))?;
+        }
+        for (from, to) in std::mem::take(&mut self.edges) {
+            self.connect_nodes(from, to)?;
+        }
+        Ok(())
+    }
+
+    /// Events of all nodes, in node order
+    fn log_events(&mut self) -> Result<Vec<LogEvent>, String> {
+        let mut events = Vec::new();
+        for node in &mut self.nodes {
+            events.extend(node.log_events()?);
+        }
+        Ok(events)
+    }
+
+    /// Combined usage of all nodes
+    fn resource_usage(&self) -> Option<ResourceUsage> {
+        let mut total = ResourceUsage::default();
+        for node in &self.nodes {
+            total.add(&node.resource_usage()?);
+        }
+        Some(total)
+    }
+
+    /// Features supported by all nodes
+    fn capabilities(&self) -> TargetCapabilities {
+        let mut capabilities = self.nodes[0].capabilities();
+        for node in &self.nodes[1..] {
+            capabilities.restrict(&node.capabilities());
+        }
+        capabilities
+    }
+
+    /// Peers of all nodes, in node order
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, String> {
+        let mut peers = Vec::new();
+        for node in &self.nodes {
+            peers.extend(node.peer_info()?);
+        }
+        Ok(peers)
+    }
+
+    /// Mempool of the first node (mempools of connected nodes converge through relay)
+    fn mempool_contents(&self) -> Result<Vec<MempoolTx>, String> {
+        self.nodes[0].mempool_contents()
+    }
+}
+
+impl<T: Transport> Target<T> for NodeNetworkTarget
+where
+    BitcoinCoreTarget: Target<T>,
+{
+    fn connect(&mut self, connection_type: ConnectionType) -> Result<Connection<T>, String> {
+        let index = self.next_node;
+        self.next_node = (self.next_node + 1) % self.nodes.len();
+        self.connect_node(index, connection_type)
+    }
+
+    fn connect_to<O: ConnectableTarget>(&mut self, other: &O) -> Result<(), String> {
+        <BitcoinCoreTarget as Target<T>>::connect_to(&mut self.nodes[0], other)
+    }
+}
+
+impl ConnectableTarget for NodeNetworkTarget {
+    fn get_addr(&self) -> Option<std::net::SocketAddrV4> {
+        self.nodes[0].get_addr()
+    }
+
+    fn is_connected_to<O: ConnectableTarget>(&self, other: &O) -> bool {
+        self.nodes.iter().any(|node| node.is_connected_to(other))
+    }
+}
+
+/// RPCs are sent to the first node of the network, use `node` to query other nodes.
+impl RpcTarget for NodeNetworkTarget {
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        self.nodes[0].call_rpc(method, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topology_edges() {
+        assert_eq!(Topology::Line.edges(3), vec![(0, 1), (1, 2)]);
+        assert_eq!(Topology::Ring.edges(3), vec![(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(Topology::Ring.edges(2), vec![(0, 1)]);
+        assert_eq!(Topology::Star.edges(3), vec![(0, 1), (0, 2)]);
+        assert_eq!(Topology::Full.edges(3), vec![(0, 1), (0, 2), (1, 2)]);
+        assert!(Topology::Line.edges(1).is_empty());
+    }
+
+    #[test]
+    fn connected_components() {
+        assert_eq!(components(3, &Topology::Ring.edges(3)), vec![vec![0, 1, 2]]);
+        assert_eq!(
+            components(5, &[(3, 1), (0, 4), (4, 3)]),
+            vec![vec![0, 1, 3, 4], vec![2]]
+        );
+        assert_eq!(components(2, &[]), vec![vec![0], vec![1]]);
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fs, path::Path};
+
+/// Resource usage of a target (see `TargetNode::resource_usage`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResourceUsage {
+    /// Resident set size
+    pub rss_bytes: u64,
+    pub open_fds: u64,
+    pub threads: u64,
+    /// Size of all files in the target's data directory
+    pub disk_bytes: u64,
+}
+
+impl ResourceUsage {
+    /// Sample the resource usage of the process `pid` (from `/proc`) and its data directory.
+    pub fn sample(pid: u32, datadir: &Path) -> Result<Self, String> {
+        let proc_dir = Path::new("/proc").join(pid.to_string());
+
+        let status = fs::read_to_string(proc_dir.join("status"))
+            .map_err(|e| format!("Failed to read status of process {pid}: {e}"))?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|value| value.split_whitespace().next())
+                .and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| format!("Missing {name} in status of process {pid}"))
+        };
+        let rss_bytes = field("VmRSS:")? * 1024;
+        let threads = field("Threads:")?;
+
+        let open_fds = fs::read_dir(proc_dir.join("fd"))
+            .map_err(|e| format!("Failed to list fds of process {pid}: {e}"))?
+            .count() as u64;
+
+        Ok(Self {
+            rss_bytes,
+            open_fds,
+            threads,
+            disk_bytes: dir_size(datadir),
+        })
+    }
+
+    /// Add the usage of `other` (e.g. to sum up the usage of multiple nodes).
+    pub fn add(&mut self, other: &ResourceUsage) {
+        self.rss_bytes += other.rss_bytes;
+        self.open_fds += other.open_fds;
+        self.threads += other.threads;
+        self.disk_bytes += other.disk_bytes;
+    }
+}
+
+/// Total size of all files below `path` (files disappearing while walking are ignored).
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.path().symlink_metadata().ok()?;
+            if metadata.is_dir() {
+                Some(dir_size(&entry.path()))
+            } else {
+                Some(metadata.len())
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_own_process() {
+        let datadir =
+            std::env::temp_dir().join(format!("fuzzamoto-resources-{}", std::process::id()));
+        fs::create_dir_all(datadir.join("blocks")).unwrap();
+        fs::write(datadir.join("blocks").join("blk00000.dat"), [0u8; 100]).unwrap();
+        fs::write(datadir.join("debug.log"), [0u8; 23]).unwrap();
+
+        let usage = ResourceUsage::sample(std::process::id(), &datadir).unwrap();
+        fs::remove_dir_all(&datadir).unwrap();
+
+        assert!(usage.rss_bytes > 0);
+        assert!(usage.open_fds > 0);
+        assert!(usage.threads > 0);
+        assert_eq!(usage.disk_bytes, 123);
+    }
+}
@@ -52,20 +52,41 @@ pub fn fixup_commitments(block: &mut Block) {
 }
 
 pub fn fixup_proof_of_work(block: &mut Block) {
+    fixup_header_proof_of_work(&mut block.header);
+}
+
+pub fn fixup_header_proof_of_work(header: &mut block::Header) {
     if cfg!(feature = "reduced_pow") {
-        let mut block_hash = block.header.block_hash();
+        let mut block_hash = header.block_hash();
         while block_hash.as_raw_hash()[31] & 0x80 != 0 {
-            block.header.nonce += 1;
-            block_hash = block.header.block_hash();
+            header.nonce += 1;
+            block_hash = header.block_hash();
         }
     } else {
-        let target = block.header.target();
-        while block.header.validate_pow(target).is_err() {
-            block.header.nonce += 1;
+        let target = header.target();
+        while header.validate_pow(target).is_err() {
+            header.nonce += 1;
         }
     }
 }
 
+/// Mine a header without a corresponding block (e.g. for feeding long header chains to a target
+/// without ever providing the blocks). `merkle_root` distinguishes headers with the same parent
+/// and time.
+#[must_use]
+pub fn mine_header(prev_hash: BlockHash, time: u32, merkle_root: TxMerkleNode) -> block::Header {
+    let mut header = block::Header {
+        version: block::Version::from_consensus(5),
+        prev_blockhash: prev_hash,
+        merkle_root,
+        time,
+        bits: CompactTarget::from_consensus(0x207f_ffff),
+        nonce: 0,
+    };
+    fixup_header_proof_of_work(&mut header);
+    header
+}
+
 #[must_use]
 pub fn mine_block(prev_hash: BlockHash, height: u32, time: u32) -> Block {
     let mut p2wsh_optrue_spk = vec![OP_0.to_u8(), 32];
@@ -3,12 +3,24 @@ pub mod mining;
 use bitcoin::{
     Amount, OutPoint, Sequence, Transaction, TxIn, TxOut, Witness,
     blockdata::opcodes::{OP_0, OP_TRUE},
-    script::ScriptBuf,
+    script::{Builder, ScriptBuf},
     transaction,
 };
 
 use bitcoin_hashes::sha256;
 
+/// Number of confirmations required before coinbase outputs can be spent
+pub const COINBASE_MATURITY: u32 = 100;
+/// Maximum number of headers per `headers` message
+pub const MAX_HEADERS_RESULTS: usize = 2000;
+/// Maximum number of peers scenarios keep connected, including the ones opened by testcases
+pub const MAX_PEERS: usize = 32;
+
+/// Bare `OP_TRUE` script, anyone can spend outputs paying to it.
+pub fn op_true_script() -> ScriptBuf {
+    Builder::new().push_opcode(OP_TRUE).into_script()
+}
+
 /// Create a consolidation transaction at 1 sat/vb that consolidates all provided inputs into a
 /// single output.
 ///
@@ -61,6 +73,58 @@ pub fn create_consolidation_tx(
     Ok(tx)
 }
 
+/// Create a fan-out transaction at 1 sat/vb that splits `input` into `num_outputs` outputs of equal
+/// value.
+///
+/// Expects the input to be a P2WSH-OP_TRUE output and the created outputs will also be
+/// P2WSH-OP_TRUE outputs.
+pub fn create_fanout_tx(
+    input: (OutPoint, bitcoin::Amount),
+    num_outputs: usize,
+) -> Result<Transaction, String> {
+    let feerate = bitcoin::FeeRate::from_sat_per_vb(1).unwrap();
+
+    let mut p2wsh_optrue_spk = vec![OP_0.to_u8(), 32];
+    let op_true_hash = sha256::Hash::hash(&[OP_TRUE.to_u8()]);
+    p2wsh_optrue_spk.extend(op_true_hash.as_byte_array().as_slice());
+
+    let mut p2wsh_optrue_witness = Witness::new();
+    p2wsh_optrue_witness.push([OP_TRUE.to_u8()]);
+
+    let (outpoint, value) = input;
+    let mut tx = Transaction {
+        version: transaction::Version(2),
+        lock_time: bitcoin::absolute::LockTime::from_height(0).unwrap(),
+        input: vec![TxIn {
+            previous_output: outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0xFFFF_FFFF),
+            witness: p2wsh_optrue_witness,
+        }],
+        output: vec![
+            TxOut {
+                value: Amount::ZERO,
+                script_pubkey: p2wsh_optrue_spk.into(),
+            };
+            num_outputs
+        ],
+    };
+
+    // Calculate fee based on the virtual size
+    let fee = feerate.fee_wu(tx.weight()).unwrap();
+    let num_outputs = u64::try_from(num_outputs).map_err(|e| e.to_string())?;
+    let output_value = value
+        .checked_sub(fee)
+        .and_then(|value| value.checked_div(num_outputs))
+        .filter(|value| *value > Amount::ZERO)
+        .ok_or_else(|| "Insufficient funds to create fan-out transaction".to_string())?;
+
+    for output in &mut tx.output {
+        output.value = output_value;
+    }
+    Ok(tx)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,4 +144,22 @@ mod tests {
         assert!(block.check_merkle_root());
         assert!(block.check_witness_commitment());
     }
+
+    #[test]
+    fn test_fanout_tx() {
+        let tx = create_fanout_tx((OutPoint::null(), Amount::from_int_btc(25)), 10).unwrap();
+        assert_eq!(tx.output.len(), 10);
+        assert!(
+            tx.output
+                .iter()
+                .all(|output| output.value == tx.output[0].value)
+        );
+
+        let total: Amount = tx.output.iter().map(|output| output.value).sum();
+        assert!(total < Amount::from_int_btc(25));
+        assert!(total > Amount::from_int_btc(25) - Amount::from_sat(10_000));
+
+        assert!(create_fanout_tx((OutPoint::null(), Amount::from_sat(100)), 10).is_err());
+        assert!(create_fanout_tx((OutPoint::null(), Amount::from_int_btc(1)), 0).is_err());
+    }
 }
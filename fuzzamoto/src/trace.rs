@@ -0,0 +1,237 @@
+//! Recording and replaying of everything a scenario does to its target.
+//!
+//! While recording is enabled (see `start_recording`), node starts, connections, sent messages,
+//! ping/pong roundtrips, mocktime changes and RPC calls are appended to a process wide `Trace` in the order they
+//! happen. Replaying the trace against a fresh node (e.g. one running under a debugger) re-does
+//! all of it without the scenario, the fuzzer or Nyx being involved.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::ChainParams,
+    connections::{
+        Connection, ConnectionType, Transport, TransportVersion, V1Transport, V2Transport,
+    },
+    targets::{BitcoinCoreTarget, GenerateToAddress, RpcTarget, Target, TargetNode},
+    transcript::{Direction, TranscriptEntry},
+};
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// How long a replay waits for the pong of a recorded ping/pong roundtrip
+const SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceEvent {
+    /// A node was started on `chain` with `args`
+    Start {
+        chain: String,
+        args: Vec<String>,
+    },
+    /// The node was restarted on its data directory
+    Restart,
+    /// A connection to the node was opened, later events refer to it by `connection`
+    Connect {
+        connection: usize,
+        connection_type: ConnectionType,
+        /// Transport of the connection (traces recorded before it was tracked used v1)
+        #[serde(default)]
+        transport: TransportVersion,
+    },
+    /// A message (or raw frame) was sent on a connection
+    Send {
+        connection: usize,
+        entry: TranscriptEntry,
+    },
+    /// The pong to the ping with `nonce` was received on a connection, i.e. the node processed
+    /// everything sent on it before
+    Sync {
+        connection: usize,
+        nonce: u64,
+    },
+    SetMocktime(u64),
+    Rpc {
+        method: String,
+        params: Vec<serde_json::Value>,
+    },
+    GenerateToAddress(String),
+}
+
+/// Start recording events into the process wide trace.
+pub fn start_recording() {
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+/// Append `event` to the trace, if recording is enabled.
+pub fn record(event: TraceEvent) {
+    if is_recording()
+        && let Ok(mut events) = EVENTS.lock()
+    {
+        events.push(event);
+    }
+}
+
+/// Id of the next connection recorded with `TraceEvent::Connect`
+#[must_use]
+pub fn next_connection_id() -> usize {
+    NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Stop recording and return the trace recorded so far.
+#[must_use]
+pub fn finish_recording() -> Trace {
+    RECORDING.store(false, Ordering::Relaxed);
+    let events = EVENTS
+        .lock()
+        .map(|mut events| std::mem::take(&mut *events))
+        .unwrap_or_default();
+    Trace { events }
+}
+
+/// Trace of the events of a scenario run (see the module documentation)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    #[must_use]
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize trace: {e}"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Failed to deserialize trace: {e}"))
+    }
+
+    /// Replay the trace against a new node started from `exe_path`, returning the node once all
+    /// events were replayed.
+    ///
+    /// Messages are sent over connections of the recorded transport, with the same payloads (and
+    /// raw frames) as recorded, and the replay waits for the node at the recorded ping/pong
+    /// roundtrips. Errors of individual events are logged and ignored, as they might have failed
+    /// in the recorded run as well (e.g. sends on connections the node closed). Traces of
+    /// scenarios with more than one node can't be replayed.
+    pub fn replay(&self, exe_path: &str) -> Result<BitcoinCoreTarget, String> {
+        let mut target: Option<BitcoinCoreTarget> = None;
+        let mut connections: HashMap<usize, ReplayConnection> = HashMap::new();
+
+        for (index, event) in self.events.iter().enumerate() {
+            if let TraceEvent::Start { chain, args } = event {
+                if target.is_some() {
+                    return Err("Replaying traces of more than one node is not supported".into());
+                }
+                let chain_params = ChainParams::from_name(chain)?;
+                target = Some(BitcoinCoreTarget::from_path_with_node_args(
+                    exe_path,
+                    &chain_params,
+                    args,
+                )?);
+                continue;
+            }
+            let target = target
+                .as_mut()
+                .ok_or_else(|| format!("Event {index} precedes the node start"))?;
+
+            let result = match event {
+                TraceEvent::Start { .. } => unreachable!(),
+                TraceEvent::Connect {
+                    connection,
+                    connection_type,
+                    transport,
+                } => ReplayConnection::open(target, connection_type, *transport).map(
+                    |new_connection| {
+                        connections.insert(*connection, new_connection);
+                    },
+                ),
+                TraceEvent::Send { connection, .. } | TraceEvent::Sync { connection, .. } => {
+                    match connections.get_mut(connection) {
+                        Some(connection) => connection.replay(event),
+                        None => Err(format!("Unknown connection {connection}")),
+                    }
+                }
+                TraceEvent::Restart => target.restart(),
+                TraceEvent::SetMocktime(time) => target.set_mocktime(*time),
+                TraceEvent::Rpc { method, params } => target.call_rpc(method, params).map(|_| ()),
+                TraceEvent::GenerateToAddress(address) => target.generate_to_address(address),
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to replay event {index}: {e}");
+            }
+        }
+
+        target.ok_or_else(|| "Trace does not start a node".to_string())
+    }
+}
+
+/// Connection opened by a replay, with the transport of the recorded connection
+enum ReplayConnection {
+    V1(Connection<V1Transport>),
+    V2(Connection<V2Transport>),
+}
+
+impl ReplayConnection {
+    fn open(
+        target: &mut BitcoinCoreTarget,
+        connection_type: &ConnectionType,
+        transport: TransportVersion,
+    ) -> Result<Self, String> {
+        Ok(match transport {
+            TransportVersion::V1 => {
+                let mut connection =
+                    Target::<V1Transport>::connect(target, connection_type.clone())?;
+                connection.set_pong_timeout(Some(SYNC_TIMEOUT));
+                Self::V1(connection)
+            }
+            TransportVersion::V2 => {
+                let mut connection =
+                    Target::<V2Transport>::connect(target, connection_type.clone())?;
+                connection.set_pong_timeout(Some(SYNC_TIMEOUT));
+                Self::V2(connection)
+            }
+        })
+    }
+
+    /// Replay a `Send` or `Sync` event of this connection
+    fn replay(&mut self, event: &TraceEvent) -> Result<(), String> {
+        match self {
+            Self::V1(connection) => replay_on(connection, event),
+            Self::V2(connection) => replay_on(connection, event),
+        }
+    }
+}
+
+fn replay_on<T: Transport>(
+    connection: &mut Connection<T>,
+    event: &TraceEvent,
+) -> Result<(), String> {
+    match event {
+        TraceEvent::Send { entry, .. } => match entry.direction {
+            Direction::Sent => connection.send(&(entry.command.clone(), entry.payload.clone())),
+            Direction::SentRaw => connection.send_raw(&entry.payload),
+            Direction::Received => Ok(()),
+        },
+        TraceEvent::Sync { nonce, .. } => connection.wait_for_pong(*nonce, false).map(|_| ()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::p2p::Magic;
+
+use crate::connections::{Connection, Transport, encode_p2p_message};
+
+/// Direction of a recorded message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Direction {
+    /// Message sent to the target
+    Sent,
+    /// Raw frame sent to the target (see `Transport::send_raw`), `payload` holds the entire frame
+    SentRaw,
+    /// Message received from the target
+    Received,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TranscriptEntry {
+    pub direction: Direction,
+    pub command: String,
+    pub payload: Vec<u8>,
+    /// Time the message was sent or received (microseconds since the unix epoch)
+    pub timestamp_micros: u64,
+}
+
+impl TranscriptEntry {
+    /// Create an entry for a message sent or received now.
+    #[must_use]
+    pub fn new(direction: Direction, command: &str, payload: &[u8]) -> Self {
+        let timestamp_micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| u64::try_from(d.as_micros()).unwrap_or(u64::MAX))
+            .unwrap_or(0);
+
+        Self {
+            direction,
+            command: command.to_string(),
+            payload: payload.to_vec(),
+            timestamp_micros,
+        }
+    }
+}
+
+/// Transcript of all messages sent and received on a connection.
+///
+/// Transcripts can be serialized to JSON or written as a pcap file (e.g. for inspection with
+/// Wireshark), and replayed on a new connection.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Transcript {
+    local_addr: SocketAddr,
+    entries: Vec<TranscriptEntry>,
+}
+
+/// Port used for the target's side of the connection in pcap files
+const PCAP_TARGET_PORT: u16 = 8333;
+/// Max payload size of a single synthesized TCP segment in pcap files
+const PCAP_MAX_SEGMENT: usize = 65_000;
+/// `LINKTYPE_RAW`: packets start with an IPv4 header
+const PCAP_LINKTYPE_RAW: u32 = 101;
+
+impl Transcript {
+    #[must_use]
+    pub fn new(local_addr: SocketAddr) -> Self {
+        Self {
+            local_addr,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, direction: Direction, command: &str, payload: &[u8]) {
+        self.entries
+            .push(TranscriptEntry::new(direction, command, payload));
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize transcript: {e}"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Failed to deserialize transcript: {e}"))
+    }
+
+    /// Re-send all messages (and raw frames) that were sent in this transcript on `connection`.
+    pub fn replay<T: Transport>(&self, connection: &mut Connection<T>) -> Result<(), String> {
+        for entry in &self.entries {
+            match entry.direction {
+                Direction::Sent => {
+                    connection.send(&(entry.command.clone(), entry.payload.clone()))?;
+                }
+                Direction::SentRaw => connection.send_raw(&entry.payload)?,
+                Direction::Received => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the transcript as a pcap file.
+    ///
+    /// Messages are encoded as v1 frames using `magic` and wrapped in synthesized IPv4/TCP
+    /// packets between the connection's local address and port 8333, such that Wireshark's
+    /// bitcoin dissector can decode them.
+    #[expect(clippy::cast_possible_truncation)]
+    pub fn write_pcap<W: Write>(&self, magic: Magic, writer: &mut W) -> Result<(), String> {
+        let map_err = |e: std::io::Error| format!("Failed to write pcap: {e}");
+
+        // Global header
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes()); // thiszone
+        header.extend_from_slice(&0u32.to_le_bytes()); // sigfigs
+        header.extend_from_slice(&65_535u32.to_le_bytes()); // snaplen
+        header.extend_from_slice(&PCAP_LINKTYPE_RAW.to_le_bytes());
+        writer.write_all(&header).map_err(map_err)?;
+
+        let local_ip = match self.local_addr {
+            SocketAddr::V4(addr) => *addr.ip(),
+            SocketAddr::V6(_) => Ipv4Addr::LOCALHOST,
+        };
+        let local = (local_ip, self.local_addr.port());
+        let target = (Ipv4Addr::LOCALHOST, PCAP_TARGET_PORT);
+
+        // TCP sequence numbers for both directions, so that streams can be reassembled
+        let mut local_seq = 0u32;
+        let mut target_seq = 0u32;
+
+        for entry in &self.entries {
+            let frame = match entry.direction {
+                Direction::SentRaw => entry.payload.clone(),
+                Direction::Sent | Direction::Received => {
+                    encode_p2p_message(magic, &(entry.command.clone(), entry.payload.clone()))?
+                }
+            };
+
+            for segment in frame.chunks(PCAP_MAX_SEGMENT) {
+                let packet = if entry.direction == Direction::Received {
+                    let packet = tcp_packet(target, local, target_seq, local_seq, segment);
+                    target_seq = target_seq.wrapping_add(segment.len() as u32);
+                    packet
+                } else {
+                    let packet = tcp_packet(local, target, local_seq, target_seq, segment);
+                    local_seq = local_seq.wrapping_add(segment.len() as u32);
+                    packet
+                };
+
+                let mut record = Vec::with_capacity(16 + packet.len());
+                record.extend_from_slice(
+                    &u32::try_from(entry.timestamp_micros / 1_000_000)
+                        .unwrap_or(u32::MAX)
+                        .to_le_bytes(),
+                );
+                record.extend_from_slice(
+                    &u32::try_from(entry.timestamp_micros % 1_000_000)
+                        .unwrap()
+                        .to_le_bytes(),
+                );
+                record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+                record.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+                record.extend_from_slice(&packet);
+                writer.write_all(&record).map_err(map_err)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_pcap(&self, magic: Magic) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.write_pcap(magic, &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Build an IPv4 packet carrying a TCP segment (PSH|ACK) with the given payload
+#[expect(clippy::cast_possible_truncation)]
+fn tcp_packet(
+    src: (Ipv4Addr, u16),
+    dst: (Ipv4Addr, u16),
+    seq: u32,
+    ack: u32,
+    payload: &[u8],
+) -> Vec<u8> {
+    let total_len = (20 + 20 + payload.len()) as u16;
+
+    let mut packet = Vec::with_capacity(usize::from(total_len));
+    // IPv4 header
+    packet.extend_from_slice(&[0x45, 0x00]);
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    packet.extend_from_slice(&[0x00, 0x00, 0x40, 0x00]); // id, don't fragment
+    packet.extend_from_slice(&[64, 6, 0x00, 0x00]); // ttl, tcp, checksum placeholder
+    packet.extend_from_slice(&src.0.octets());
+    packet.extend_from_slice(&dst.0.octets());
+    let checksum = ipv4_checksum(&packet[..20]);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+    // TCP header (checksum left as zero)
+    packet.extend_from_slice(&src.1.to_be_bytes());
+    packet.extend_from_slice(&dst.1.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(&ack.to_be_bytes());
+    packet.extend_from_slice(&[0x50, 0x18, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]);
+
+    packet.extend_from_slice(payload);
+    packet
+}
+
+#[expect(clippy::cast_possible_truncation)]
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_and_pcap_encoding() {
+        let mut transcript = Transcript::new("127.0.0.1:1234".parse().unwrap());
+        transcript.record(Direction::Sent, "ping", &[0u8; 8]);
+        transcript.record(Direction::Received, "pong", &[0u8; 8]);
+
+        let decoded = Transcript::from_json(&transcript.to_json().unwrap()).unwrap();
+        assert_eq!(decoded.entries(), transcript.entries());
+
+        // Global header + 2 * (record header + ip header + tcp header + 32 byte frame)
+        let pcap = transcript
+            .to_pcap(bitcoin::Network::Regtest.magic())
+            .unwrap();
+        assert_eq!(pcap.len(), 24 + 2 * (16 + 20 + 20 + 32));
+    }
+}
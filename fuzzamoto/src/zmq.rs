@@ -0,0 +1,458 @@
+//! Minimal ZMQ subscriber for the node's `-zmqpub*` notifications.
+//!
+//! Only what is needed to receive notifications from a Bitcoin Core publisher is implemented:
+//! ZMTP 3.0 with the `NULL` security mechanism and a `SUB` socket.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use bitcoin::{
+    BlockHash,
+    block::Header,
+    consensus::encode,
+    hashes::{Hash, sha256d},
+};
+
+const GREETING_LEN: usize = 64;
+const ZMTP_MAJOR_VERSION: u8 = 3;
+
+const FLAG_MORE: u8 = 0x01;
+const FLAG_LONG: u8 = 0x02;
+const FLAG_COMMAND: u8 = 0x04;
+
+/// Notification topics published by Bitcoin Core
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZmqTopic {
+    RawBlock,
+    RawTx,
+    Sequence,
+}
+
+impl ZmqTopic {
+    pub const ALL: [ZmqTopic; 3] = [ZmqTopic::RawBlock, ZmqTopic::RawTx, ZmqTopic::Sequence];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ZmqTopic::RawBlock => "rawblock",
+            ZmqTopic::RawTx => "rawtx",
+            ZmqTopic::Sequence => "sequence",
+        }
+    }
+
+    /// Node argument enabling the topic, e.g. `-zmqpubrawblock=tcp://127.0.0.1:28332`
+    #[must_use]
+    pub fn node_arg(self, addr: &SocketAddr) -> String {
+        format!("-zmqpub{}=tcp://{addr}", self.as_str())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|topic| topic.as_str().as_bytes() == bytes)
+    }
+}
+
+/// Event of a `sequence` notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent {
+    BlockConnected(BlockHash),
+    BlockDisconnected(BlockHash),
+    /// Transaction added to the mempool, with the mempool sequence number
+    TxAdded(sha256d::Hash, u64),
+    /// Transaction removed from the mempool, with the mempool sequence number
+    TxRemoved(sha256d::Hash, u64),
+}
+
+/// A notification received from the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZmqNotification {
+    pub topic: ZmqTopic,
+    pub body: Vec<u8>,
+    /// Per-topic message sequence number
+    pub sequence: u32,
+}
+
+impl ZmqNotification {
+    /// Hash of the block of a `rawblock` notification
+    #[must_use]
+    pub fn block_hash(&self) -> Option<BlockHash> {
+        if self.topic != ZmqTopic::RawBlock {
+            return None;
+        }
+        let (header, _) = encode::deserialize_partial::<Header>(&self.body).ok()?;
+        Some(header.block_hash())
+    }
+
+    /// Event of a `sequence` notification
+    #[must_use]
+    pub fn sequence_event(&self) -> Option<SequenceEvent> {
+        if self.topic != ZmqTopic::Sequence || self.body.len() < 33 {
+            return None;
+        }
+
+        // Hashes are published in display (reversed) byte order
+        let mut hash: [u8; 32] = self.body[..32].try_into().ok()?;
+        hash.reverse();
+        let mempool_sequence = || {
+            self.body
+                .get(33..41)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u64::from_le_bytes)
+        };
+
+        match self.body[32] {
+            b'C' => Some(SequenceEvent::BlockConnected(BlockHash::from_byte_array(
+                hash,
+            ))),
+            b'D' => Some(SequenceEvent::BlockDisconnected(
+                BlockHash::from_byte_array(hash),
+            )),
+            b'A' => Some(SequenceEvent::TxAdded(
+                sha256d::Hash::from_byte_array(hash),
+                mempool_sequence()?,
+            )),
+            b'R' => Some(SequenceEvent::TxRemoved(
+                sha256d::Hash::from_byte_array(hash),
+                mempool_sequence()?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Check that `notifications` (in the order they were received) are consistent:
+///
+/// * the message sequence numbers of each topic increase by one (no missing or duplicate
+///   notifications),
+/// * blocks are disconnected in reverse order of being connected and not connected twice,
+/// * every `rawblock` notification was preceded by the block being connected,
+/// * mempool sequence numbers increase.
+pub fn verify_notifications(notifications: &[ZmqNotification]) -> Result<(), String> {
+    let mut last_sequence: [Option<u32>; 3] = [None; 3];
+    let mut connected: Vec<BlockHash> = Vec::new();
+    let mut last_mempool_sequence = None;
+
+    for notification in notifications {
+        let topic = notification.topic;
+        let index = topic as usize;
+        if let Some(last) = last_sequence[index] {
+            let expected = last.wrapping_add(1);
+            if notification.sequence != expected {
+                return Err(format!(
+                    "Unexpected {} notification sequence number: {} (expected {expected})",
+                    topic.as_str(),
+                    notification.sequence
+                ));
+            }
+        }
+        last_sequence[index] = Some(notification.sequence);
+
+        match topic {
+            ZmqTopic::RawBlock => {
+                let hash = notification
+                    .block_hash()
+                    .ok_or_else(|| "Malformed rawblock notification".to_string())?;
+                if !connected.contains(&hash) {
+                    return Err(format!(
+                        "rawblock notification for unconnected block {hash}"
+                    ));
+                }
+            }
+            ZmqTopic::Sequence => match notification.sequence_event() {
+                Some(SequenceEvent::BlockConnected(hash)) => {
+                    if connected.contains(&hash) {
+                        return Err(format!("Block {hash} connected twice"));
+                    }
+                    connected.push(hash);
+                }
+                Some(SequenceEvent::BlockDisconnected(hash)) => {
+                    // Blocks connected before the subscription can't be checked
+                    if let Some(tip) = connected.last() {
+                        if *tip != hash {
+                            return Err(format!("Block {hash} disconnected, but the tip is {tip}"));
+                        }
+                        connected.pop();
+                    }
+                }
+                Some(
+                    SequenceEvent::TxAdded(_, mempool_sequence)
+                    | SequenceEvent::TxRemoved(_, mempool_sequence),
+                ) => {
+                    if let Some(last) = last_mempool_sequence
+                        && mempool_sequence <= last
+                    {
+                        return Err(format!(
+                            "Mempool sequence number did not increase: {mempool_sequence} (last {last})"
+                        ));
+                    }
+                    last_mempool_sequence = Some(mempool_sequence);
+                }
+                None => return Err("Malformed sequence notification".to_string()),
+            },
+            ZmqTopic::RawTx => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a ZMTP frame from the start of `buffer`, returning its flags, body and total length.
+fn parse_frame(buffer: &[u8]) -> Option<(u8, &[u8], usize)> {
+    let flags = *buffer.first()?;
+    let (len, header_len) = if flags & FLAG_LONG == 0 {
+        (u64::from(*buffer.get(1)?), 2usize)
+    } else {
+        (u64::from_be_bytes(buffer.get(1..9)?.try_into().ok()?), 9)
+    };
+    let len = usize::try_from(len).ok()?;
+    let body = buffer.get(header_len..header_len.checked_add(len)?)?;
+    Some((flags, body, header_len + len))
+}
+
+fn encode_frame(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(body.len() + 9);
+    if let Ok(len) = u8::try_from(body.len()) {
+        frame.push(flags);
+        frame.push(len);
+    } else {
+        frame.push(flags | FLAG_LONG);
+        frame.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// Subscriber connected to the node's ZMQ publisher.
+///
+/// Notifications are buffered by the kernel until `receive` is called. Note that (as with any
+/// ZMQ subscriber) notifications published right after connecting might be missed.
+pub struct ZmqSubscriber {
+    addr: SocketAddr,
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl ZmqSubscriber {
+    /// Connect to the publisher at `addr` and subscribe to `topics`.
+    pub fn connect(addr: SocketAddr, topics: &[ZmqTopic]) -> Result<Self, String> {
+        let mut stream = TcpStream::connect(addr)
+            .map_err(|e| format!("Failed to connect to ZMQ publisher: {e}"))?;
+        stream
+            .set_nodelay(true)
+            .map_err(|e| format!("Failed to set nodelay on ZMQ socket: {e}"))?;
+
+        // Greeting: signature, version, mechanism and as-server flag
+        let mut greeting = [0u8; GREETING_LEN];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = ZMTP_MAJOR_VERSION;
+        greeting[12..16].copy_from_slice(b"NULL");
+        stream
+            .write_all(&greeting)
+            .map_err(|e| format!("Failed to send ZMQ greeting: {e}"))?;
+
+        let mut remote_greeting = [0u8; GREETING_LEN];
+        stream
+            .read_exact(&mut remote_greeting)
+            .map_err(|e| format!("Failed to receive ZMQ greeting: {e}"))?;
+        if remote_greeting[0] != 0xff
+            || remote_greeting[9] & 0x01 != 0x01
+            || remote_greeting[10] < ZMTP_MAJOR_VERSION
+        {
+            return Err(format!(
+                "Unsupported ZMQ greeting: {:?}",
+                &remote_greeting[..12]
+            ));
+        }
+
+        let mut ready = Vec::new();
+        ready.push(5);
+        ready.extend_from_slice(b"READY");
+        ready.push(11);
+        ready.extend_from_slice(b"Socket-Type");
+        ready.extend_from_slice(&3u32.to_be_bytes());
+        ready.extend_from_slice(b"SUB");
+        stream
+            .write_all(&encode_frame(FLAG_COMMAND, &ready))
+            .map_err(|e| format!("Failed to send ZMQ READY: {e}"))?;
+
+        let mut subscriber = Self {
+            addr,
+            stream,
+            buffer: Vec::new(),
+        };
+
+        let (flags, body) = subscriber.read_frame()?;
+        if flags & FLAG_COMMAND == 0 || !body.starts_with(b"\x05READY") {
+            return Err("Expected ZMQ READY command".to_string());
+        }
+
+        // ZMTP 3.0 subscriptions are messages starting with 0x01
+        for topic in topics {
+            let mut subscription = vec![0x01];
+            subscription.extend_from_slice(topic.as_str().as_bytes());
+            subscriber
+                .stream
+                .write_all(&encode_frame(0, &subscription))
+                .map_err(|e| format!("Failed to subscribe to {}: {e}", topic.as_str()))?;
+        }
+
+        Ok(subscriber)
+    }
+
+    /// Address of the publisher
+    #[must_use]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn read_frame(&mut self) -> Result<(u8, Vec<u8>), String> {
+        loop {
+            if let Some((flags, body, len)) = parse_frame(&self.buffer) {
+                let frame = (flags, body.to_vec());
+                self.buffer.drain(..len);
+                return Ok(frame);
+            }
+
+            let mut chunk = [0u8; 4096];
+            let n = self
+                .stream
+                .read(&mut chunk)
+                .map_err(|e| format!("Failed to read from ZMQ socket: {e}"))?;
+            if n == 0 {
+                return Err("ZMQ publisher closed the connection".to_string());
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Take the next complete message (all of its frames) out of the buffer.
+    fn next_message(&mut self) -> Option<Vec<Vec<u8>>> {
+        let mut offset = 0;
+        let mut frames = Vec::new();
+        loop {
+            let (flags, body, len) = parse_frame(&self.buffer[offset..])?;
+            offset += len;
+            if flags & FLAG_COMMAND != 0 {
+                continue;
+            }
+            frames.push(body.to_vec());
+            if flags & FLAG_MORE == 0 {
+                break;
+            }
+        }
+        self.buffer.drain(..offset);
+        Some(frames)
+    }
+
+    /// Receive notifications until none arrived for `timeout`.
+    pub fn receive(&mut self, timeout: Duration) -> Result<Vec<ZmqNotification>, String> {
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|e| format!("Failed to set ZMQ read timeout: {e}"))?;
+
+        let mut notifications = Vec::new();
+        loop {
+            while let Some(frames) = self.next_message() {
+                // Bitcoin Core notifications are [topic, body, sequence (LE)]
+                let [topic, body, sequence] = frames.as_slice() else {
+                    log::warn!("Ignoring ZMQ message with {} frames", frames.len());
+                    continue;
+                };
+                let (Some(topic), Ok(sequence)) = (
+                    ZmqTopic::from_bytes(topic),
+                    <[u8; 4]>::try_from(sequence.as_slice()),
+                ) else {
+                    log::warn!("Ignoring unexpected ZMQ message");
+                    continue;
+                };
+                notifications.push(ZmqNotification {
+                    topic,
+                    body: body.clone(),
+                    sequence: u32::from_le_bytes(sequence),
+                });
+            }
+
+            let mut chunk = [0u8; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err("ZMQ publisher closed the connection".to_string()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    break;
+                }
+                Err(e) => return Err(format!("Failed to read from ZMQ socket: {e}")),
+            }
+        }
+
+        Ok(notifications)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence_notification(sequence: u32, hash: [u8; 32], label: u8) -> ZmqNotification {
+        let mut body = hash.to_vec();
+        body.reverse();
+        body.push(label);
+        ZmqNotification {
+            topic: ZmqTopic::Sequence,
+            body,
+            sequence,
+        }
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        for len in [0, 1, 255, 256, 1000] {
+            let body = vec![0xab; len];
+            let frame = encode_frame(FLAG_MORE, &body);
+            let (flags, parsed, frame_len) = parse_frame(&frame).unwrap();
+            assert_eq!(flags & FLAG_MORE, FLAG_MORE);
+            assert_eq!(parsed, body.as_slice());
+            assert_eq!(frame_len, frame.len());
+            assert!(parse_frame(&frame[..frame.len() - 1]).is_none());
+        }
+    }
+
+    #[test]
+    fn detects_inconsistent_notifications() {
+        let a = [1u8; 32];
+        let b = [2u8; 32];
+
+        let valid = vec![
+            sequence_notification(7, a, b'C'),
+            sequence_notification(8, b, b'C'),
+            sequence_notification(9, b, b'D'),
+        ];
+        assert!(verify_notifications(&valid).is_ok());
+        assert_eq!(
+            valid[0].sequence_event(),
+            Some(SequenceEvent::BlockConnected(BlockHash::from_byte_array(a)))
+        );
+
+        let missing = vec![
+            sequence_notification(7, a, b'C'),
+            sequence_notification(9, b, b'C'),
+        ];
+        assert!(verify_notifications(&missing).is_err());
+
+        let duplicate = vec![
+            sequence_notification(7, a, b'C'),
+            sequence_notification(8, a, b'C'),
+        ];
+        assert!(verify_notifications(&duplicate).is_err());
+
+        let misordered = vec![
+            sequence_notification(7, a, b'C'),
+            sequence_notification(8, b, b'C'),
+            sequence_notification(9, a, b'D'),
+        ];
+        assert!(verify_notifications(&misordered).is_err());
+    }
+}